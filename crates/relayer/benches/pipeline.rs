@@ -0,0 +1,94 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use relayer::testkit::{self, LoadProfile};
+use relayer::{MockProofConfig, ProofBackendConfig, RelayerConfig, StoreBackend};
+use std::time::Duration;
+use tokio::runtime::Runtime;
+
+fn config(
+    state: &tempfile::TempDir,
+    proof_latency_ms: u64,
+    fetch_concurrency: usize,
+    store_backend: StoreBackend,
+) -> RelayerConfig {
+    let mut config = testkit::config(state.path())
+        .proof_backend(ProofBackendConfig::Mock(MockProofConfig {
+            latency_ms: proof_latency_ms,
+            ..Default::default()
+        }))
+        .store_backend(store_backend)
+        .build()
+        .unwrap();
+    config.proof_fetcher.max_concurrent_fetches = fetch_concurrency;
+    config
+}
+
+// Time `events` events through a fresh pipeline per iteration, leaving out
+// its startup and shutdown
+fn run(
+    runtime: &Runtime,
+    iterations: u64,
+    events: usize,
+    config: impl Fn(&tempfile::TempDir) -> RelayerConfig,
+) -> Duration {
+    let profile = LoadProfile {
+        events,
+        ..Default::default()
+    };
+    (0..iterations)
+        .map(|_| {
+            let state = tempfile::tempdir().unwrap();
+            let report = runtime
+                .block_on(testkit::run_load(config(&state), &profile))
+                .unwrap();
+            report.elapsed
+        })
+        .sum()
+}
+
+fn store_backends(c: &mut Criterion) {
+    let runtime = Runtime::new().unwrap();
+    let mut group = c.benchmark_group("pipeline/store");
+    group.sample_size(10);
+    let events = 500;
+    group.throughput(Throughput::Elements(events as u64));
+    for (name, backend) in [
+        ("file", StoreBackend::File),
+        ("sqlite", StoreBackend::Sqlite),
+    ] {
+        group.bench_function(name, |b| {
+            b.iter_custom(|iterations| {
+                run(&runtime, iterations, events, |state| {
+                    config(state, 0, 16, backend)
+                })
+            })
+        });
+    }
+    group.finish();
+}
+
+// Proofs that take a while, fetched with more or less concurrency, on the
+// store that keeps up with them
+fn fetch_concurrency(c: &mut Criterion) {
+    let runtime = Runtime::new().unwrap();
+    let mut group = c.benchmark_group("pipeline/fetch_concurrency");
+    group.sample_size(10);
+    let events = 200;
+    group.throughput(Throughput::Elements(events as u64));
+    for concurrency in [1, 8, 32] {
+        group.bench_with_input(
+            BenchmarkId::from_parameter(concurrency),
+            &concurrency,
+            |b, &concurrency| {
+                b.iter_custom(|iterations| {
+                    run(&runtime, iterations, events, |state| {
+                        config(state, 5, concurrency, StoreBackend::Sqlite)
+                    })
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, store_backends, fetch_concurrency);
+criterion_main!(benches);
@@ -0,0 +1,173 @@
+use ethers::core::types::{Address, TransactionReceipt, H256, U256};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tracing::{debug, warn};
+
+use crate::config::{ChainConfig, ChainKind};
+use crate::metrics::{self, GAS_SPENT};
+use crate::store::StateStore;
+use crate::types::ChainId;
+
+const SECS_PER_DAY: u64 = 86_400;
+
+/// Lossy conversion of a wei amount to ETH, for metrics and displays that
+/// only need to be roughly right
+pub fn wei_to_eth(wei: U256) -> f64 {
+    wei.to_string().parse::<f64>().unwrap_or(f64::MAX) / 1e18
+}
+
+/// What a transaction paid for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GasCostKind {
+    /// `requestRemoteExecution` on the source chain
+    Trigger,
+    /// Delivery, batch, or cancel transaction on the destination chain
+    Delivery,
+    /// `reportHeartbeat` on a heartbeat registry
+    Heartbeat,
+    /// Claim of earned relay fees from a resolver or executor contract
+    FeeClaim,
+}
+
+impl GasCostKind {
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            GasCostKind::Trigger => "trigger",
+            GasCostKind::Delivery => "delivery",
+            GasCostKind::Heartbeat => "heartbeat",
+            GasCostKind::FeeClaim => "fee_claim",
+        }
+    }
+}
+
+/// Gas spent by one relayer transaction
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GasCostRecord {
+    pub tx_hash: H256,
+    pub chain_id: ChainId,
+    /// Relay pair the transaction served, `batch` for multi-pair batches,
+    /// `heartbeat` for heartbeats or `fee_claim` for fee claims
+    pub pair: String,
+    pub kind: GasCostKind,
+    /// Wallet that paid, zero in records kept before senders were
+    #[serde(default)]
+    pub from: Address,
+    pub gas_used: U256,
+    pub effective_gas_price: U256,
+    /// L1 data fee charged on top of execution by OP-stack chains
+    #[serde(default)]
+    pub l1_fee_wei: U256,
+    /// `gas_used` × `effective_gas_price`, plus any L1 data fee
+    pub cost_wei: U256,
+    /// Unix time in seconds the cost was recorded
+    pub recorded_at: u64,
+}
+
+/// Spend summed per pair and per chain
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct GasCostTotals {
+    pub per_pair: HashMap<String, U256>,
+    pub per_chain: HashMap<ChainId, U256>,
+}
+
+impl GasCostTotals {
+    pub fn from_records(records: &[GasCostRecord]) -> Self {
+        let mut totals = Self::default();
+        for record in records {
+            *totals.per_pair.entry(record.pair.clone()).or_default() += record.cost_wei;
+            *totals.per_chain.entry(record.chain_id).or_default() += record.cost_wei;
+        }
+        totals
+    }
+}
+
+/// What the relayer spends on one chain, scaled to a day from a trailing
+/// window of recorded costs
+#[derive(Debug, Clone, Default)]
+pub(crate) struct SpendRate {
+    pub(crate) per_day: U256,
+    pub(crate) per_wallet: HashMap<Address, U256>,
+}
+
+impl SpendRate {
+    /// Spend on `chain_id` recorded in the `window_secs` before `now`, in
+    /// Unix seconds
+    pub(crate) fn over(
+        records: &[GasCostRecord],
+        chain_id: ChainId,
+        window_secs: u64,
+        now: u64,
+    ) -> Self {
+        let since = now.saturating_sub(window_secs);
+        let scale = |spent: U256| spent * U256::from(SECS_PER_DAY) / U256::from(window_secs.max(1));
+        let mut rate = Self::default();
+        for record in records {
+            if record.chain_id != chain_id || record.recorded_at < since {
+                continue;
+            }
+            rate.per_day += record.cost_wei;
+            *rate.per_wallet.entry(record.from).or_default() += record.cost_wei;
+        }
+        rate.per_day = scale(rate.per_day);
+        for spent in rate.per_wallet.values_mut() {
+            *spent = scale(*spent);
+        }
+        rate
+    }
+}
+
+/// Record what a mined transaction cost, in metrics and the store's history
+///
+/// Receipts from nodes that don't report an effective gas price are skipped.
+/// On OP-stack chains the receipt's `l1Fee` is counted as well, since it is
+/// often most of what a transaction costs there.
+pub fn record_gas_cost(
+    store: &dyn StateStore,
+    chain: &ChainConfig,
+    pair: &str,
+    kind: GasCostKind,
+    receipt: &TransactionReceipt,
+) {
+    let (Some(gas_used), Some(effective_gas_price)) =
+        (receipt.gas_used, receipt.effective_gas_price)
+    else {
+        debug!(tx_hash = ?receipt.transaction_hash, "Receipt has no gas cost details");
+        return;
+    };
+    let l1_fee_wei = match chain.kind {
+        ChainKind::OpStack => receipt
+            .other
+            .get_deserialized::<U256>("l1Fee")
+            .and_then(|fee| fee.ok())
+            .unwrap_or_default(),
+        _ => U256::zero(),
+    };
+    let cost_wei = gas_used * effective_gas_price + l1_fee_wei;
+
+    GAS_SPENT
+        .with_label_values(&[&chain.name, metrics::pair_id_label(pair), kind.as_str()])
+        .inc_by(wei_to_eth(cost_wei));
+
+    let record = GasCostRecord {
+        tx_hash: receipt.transaction_hash,
+        chain_id: chain.chain_id,
+        pair: pair.to_string(),
+        kind,
+        from: receipt.from,
+        gas_used,
+        effective_gas_price,
+        l1_fee_wei,
+        cost_wei,
+        recorded_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or_default(),
+    };
+    if let Err(e) = store.save_gas_cost(&record) {
+        warn!(error = %e, tx_hash = ?record.tx_hash, "Failed to record gas cost");
+    }
+}
@@ -0,0 +1,100 @@
+use anyhow::{anyhow, Context, Result};
+use serde::{de::DeserializeOwned, Deserialize};
+
+use crate::dead_letters::DeadLetterKey;
+use crate::status::{EventQuery, EventRecord};
+use crate::store::{DeadLetterQuery, FailedDelivery};
+use crate::types::EventId;
+
+/// Client for a running relayer's HTTP admin endpoints
+pub struct AdminClient {
+    http: reqwest::Client,
+    base_url: String,
+    token: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Replayed {
+    replayed: usize,
+}
+
+impl AdminClient {
+    pub fn new(base_url: impl Into<String>, token: Option<String>) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            token,
+        }
+    }
+
+    /// Dead letters matching `query`, oldest detection first
+    pub async fn dead_letters(&self, query: &DeadLetterQuery) -> Result<Vec<FailedDelivery>> {
+        let request = self.http.get(self.url("/dead-letters")).query(query);
+        self.send(request).await
+    }
+
+    pub async fn dead_letter(&self, key: &DeadLetterKey) -> Result<FailedDelivery> {
+        let request = self.http.get(self.url(&format!("/dead-letters/{}", key)));
+        self.send(request).await
+    }
+
+    /// Send one dead letter through the pipeline again
+    pub async fn replay(&self, key: &DeadLetterKey) -> Result<()> {
+        let request = self
+            .http
+            .post(self.url(&format!("/dead-letters/{}/replay", key)));
+        self.check(request).await?;
+        Ok(())
+    }
+
+    /// Send every dead letter matching `query` through the pipeline again,
+    /// returning how many were replayed
+    pub async fn replay_all(&self, query: &DeadLetterQuery) -> Result<usize> {
+        let request = self
+            .http
+            .post(self.url("/dead-letters/replay"))
+            .query(query);
+        let replayed: Replayed = self.send(request).await?;
+        Ok(replayed.replayed)
+    }
+
+    /// Where an event stands in the pipeline
+    pub async fn event(&self, id: &EventId) -> Result<EventRecord> {
+        let request = self.http.get(self.url(&format!("/events/{}", id)));
+        self.send(request).await
+    }
+
+    /// Event statuses matching `query`, least recently changed first
+    pub async fn events(&self, query: &EventQuery) -> Result<Vec<EventRecord>> {
+        let request = self.http.get(self.url("/events")).query(query);
+        self.send(request).await
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base_url, path)
+    }
+
+    async fn send<T: DeserializeOwned>(&self, request: reqwest::RequestBuilder) -> Result<T> {
+        let response = self.check(request).await?;
+        response
+            .json()
+            .await
+            .context("Failed to parse admin API response")
+    }
+
+    async fn check(&self, mut request: reqwest::RequestBuilder) -> Result<reqwest::Response> {
+        if let Some(token) = &self.token {
+            request = request.bearer_auth(token);
+        }
+        let response = request
+            .send()
+            .await
+            .context(format!("Failed to reach admin API at {}", self.base_url))?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(anyhow!("Admin API returned {}: {}", status, body));
+        }
+        Ok(response)
+    }
+}
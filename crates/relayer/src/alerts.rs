@@ -0,0 +1,257 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{Arc, Mutex, RwLock},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+use tracing::{debug, warn};
+
+use crate::config::{AlertConfig, WebhookConfig, WebhookKind};
+use crate::health::Health;
+use crate::metrics::ALERTS;
+
+// Alerter of the running relayer, unset while no webhooks are configured
+static ALERTER: RwLock<Option<Arc<Alerter>>> = RwLock::new(None);
+
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Condition an operator is alerted about
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum AlertKind {
+    ProofFailures,
+    DeliveryReverted,
+    DeadLetter,
+    LowBalance,
+    LowRunway,
+    PipelineStalled,
+    NonceGap,
+    SloBreach,
+}
+
+impl AlertKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::ProofFailures => "proof_failures",
+            Self::DeliveryReverted => "delivery_reverted",
+            Self::DeadLetter => "dead_letter",
+            Self::LowBalance => "low_balance",
+            Self::LowRunway => "low_runway",
+            Self::PipelineStalled => "pipeline_stalled",
+            Self::NonceGap => "nonce_gap",
+            Self::SloBreach => "slo_breach",
+        }
+    }
+}
+
+impl fmt::Display for AlertKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Body posted to generic webhooks
+#[derive(Debug, Serialize)]
+struct Alert {
+    kind: AlertKind,
+    /// What the alert is about, such as a chain or wallet; repeats are
+    /// throttled per kind and subject
+    subject: String,
+    message: String,
+    /// Repeats held back since this alert was last sent
+    suppressed: u64,
+    raised_at: u64,
+}
+
+impl Alert {
+    fn summary(&self) -> String {
+        let mut summary = format!(
+            "[relayer] {} ({}): {}",
+            self.kind, self.subject, self.message
+        );
+        if self.suppressed > 0 {
+            summary.push_str(&format!(" [{} similar alerts held back]", self.suppressed));
+        }
+        summary
+    }
+}
+
+struct Alerter {
+    config: AlertConfig,
+    http: reqwest::Client,
+    /// When each kind and subject was last sent, and repeats held back since
+    throttle: Mutex<HashMap<(AlertKind, String), (Instant, u64)>>,
+    /// Proof fetches failed in a row, per source chain
+    proof_failures: Mutex<HashMap<String, u32>>,
+}
+
+/// Start sending alerts to the configured webhooks, replacing any earlier
+/// configuration
+pub(crate) fn init(config: &AlertConfig) -> Result<()> {
+    let alerter = if config.webhooks.is_empty() {
+        None
+    } else {
+        let http = reqwest::Client::builder()
+            .timeout(WEBHOOK_TIMEOUT)
+            .build()
+            .context("Failed to create alert webhook client")?;
+        Some(Arc::new(Alerter {
+            config: config.clone(),
+            http,
+            throttle: Mutex::new(HashMap::new()),
+            proof_failures: Mutex::new(HashMap::new()),
+        }))
+    };
+    *ALERTER.write().expect("alerter lock poisoned") = alerter;
+    Ok(())
+}
+
+fn current() -> Option<Arc<Alerter>> {
+    ALERTER.read().expect("alerter lock poisoned").clone()
+}
+
+/// Send an alert to every webhook, unless the same kind and subject was sent
+/// within the throttle window
+pub(crate) fn notify(kind: AlertKind, subject: impl Into<String>, message: impl Into<String>) {
+    if let Some(alerter) = current() {
+        alerter.raise(kind, subject.into(), message.into());
+    }
+}
+
+/// Count a proof fetch for `source_chain`, alerting once failures in a row
+/// reach the threshold
+pub(crate) fn record_proof_result(source_chain: &str, succeeded: bool) {
+    let Some(alerter) = current() else {
+        return;
+    };
+    let failures = {
+        let mut streaks = alerter
+            .proof_failures
+            .lock()
+            .expect("proof failure lock poisoned");
+        if succeeded {
+            streaks.remove(source_chain);
+            return;
+        }
+        let streak = streaks.entry(source_chain.to_string()).or_default();
+        *streak += 1;
+        *streak
+    };
+    if failures >= alerter.config.proof_failure_threshold {
+        alerter.raise(
+            AlertKind::ProofFailures,
+            source_chain.to_string(),
+            format!("{} proof fetches in a row have failed", failures),
+        );
+    }
+}
+
+/// Alert about pipeline components that stopped making progress, checking on
+/// the configured interval
+pub(crate) async fn watch_stalls(health: Health) {
+    let Some(interval) = current().map(|alerter| alerter.config.stall_check_interval_ms) else {
+        return;
+    };
+    let mut ticker = tokio::time::interval(Duration::from_millis(interval));
+    loop {
+        ticker.tick().await;
+        let report = health.report();
+        for component in report.components.iter().filter(|component| !component.live) {
+            let idle = component
+                .idle_secs
+                .map_or("never started".to_string(), |secs| {
+                    format!("idle for {}s", secs)
+                });
+            notify(
+                AlertKind::PipelineStalled,
+                component.name,
+                format!(
+                    "{} is stalled ({}, {} items queued)",
+                    component.name,
+                    idle,
+                    component.queue_depth.unwrap_or_default()
+                ),
+            );
+        }
+    }
+}
+
+impl Alerter {
+    fn raise(&self, kind: AlertKind, subject: String, message: String) {
+        let window = Duration::from_millis(self.config.throttle_ms);
+        let suppressed = {
+            let mut throttle = self.throttle.lock().expect("alert throttle lock poisoned");
+            if let Some((sent_at, suppressed)) = throttle.get_mut(&(kind, subject.clone())) {
+                if sent_at.elapsed() < window {
+                    *suppressed += 1;
+                    ALERTS
+                        .with_label_values(&[kind.as_str(), "throttled"])
+                        .inc();
+                    debug!(%kind, %subject, "Alert throttled");
+                    return;
+                }
+            }
+            let suppressed = throttle
+                .remove(&(kind, subject.clone()))
+                .map_or(0, |(_, suppressed)| suppressed);
+            // Forget subjects that have gone quiet
+            throttle.retain(|_, (sent_at, _)| sent_at.elapsed() < window);
+            throttle.insert((kind, subject.clone()), (Instant::now(), 0));
+            suppressed
+        };
+
+        let alert = Alert {
+            kind,
+            subject,
+            message,
+            suppressed,
+            raised_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_secs())
+                .unwrap_or_default(),
+        };
+        // Alerts raised outside a runtime have nowhere to send from
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            return;
+        };
+        for webhook in &self.config.webhooks {
+            let request = self.http.post(&webhook.url).json(&payload(webhook, &alert));
+            runtime.spawn(async move {
+                let sent = request
+                    .send()
+                    .await
+                    .and_then(reqwest::Response::error_for_status);
+                match sent {
+                    Ok(_) => ALERTS.with_label_values(&[kind.as_str(), "sent"]).inc(),
+                    Err(e) => {
+                        ALERTS.with_label_values(&[kind.as_str(), "failed"]).inc();
+                        warn!(error = %e, %kind, "Failed to send alert webhook");
+                    }
+                }
+            });
+        }
+    }
+}
+
+// Body in the shape each webhook kind expects
+fn payload(webhook: &WebhookConfig, alert: &Alert) -> Value {
+    match &webhook.kind {
+        WebhookKind::Slack => json!({ "text": alert.summary() }),
+        WebhookKind::Discord => json!({ "content": alert.summary() }),
+        WebhookKind::PagerDuty { routing_key } => json!({
+            "routing_key": routing_key,
+            "event_action": "trigger",
+            "dedup_key": format!("relayer:{}:{}", alert.kind, alert.subject),
+            "payload": {
+                "summary": alert.summary(),
+                "source": "relayer",
+                "severity": "error",
+                "custom_details": alert,
+            },
+        }),
+        WebhookKind::Generic => json!(alert),
+    }
+}
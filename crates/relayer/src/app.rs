@@ -1,94 +1,879 @@
-use anyhow::Result;
-use std::{time::Duration};
-use tokio::sync::mpsc;
-use tracing::{error, info, instrument};
+use anyhow::{anyhow, Context, Result};
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio::{
+    sync::{broadcast, mpsc, watch},
+    task::{JoinError, JoinHandle, JoinSet},
+};
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info, instrument, warn};
 
-use crate::{EventDeliverer, EventGenerator, ProofFetcher, RelayerConfig};
+use crate::alerts;
+use crate::audit;
+use crate::builder::RelayerBuilder;
+use crate::clock::{self, Clock, SystemClock};
+use crate::config::{ProofBackendConfig, StoreBackend};
+use crate::control_socket::{self, ControlState};
+use crate::event_delivery::{SinkDeliverer, OUTCOME_CAPACITY};
+use crate::event_source::EventEmitter;
+use crate::fee_claims::{FeeClaimRecord, FeeClaimer};
+use crate::health::{Health, HealthReport, EVENT_DELIVERER, EVENT_GENERATOR, PROOF_FETCHER};
+use crate::heartbeat::Heartbeat;
+use crate::hooks::Hooks;
+use crate::journal::Journal;
+use crate::latency;
+use crate::lifecycle::Lifecycle;
+use crate::metrics::{self, COMPONENT_RESTARTS};
+use crate::proof_fetcher::TAP_CAPACITY;
+use crate::report;
+use crate::server;
+use crate::stream;
+use crate::{
+    DeadLetterQueue, DeliveryControl, DeliveryOutcome, DeliveryQuery, DeliveryRequest,
+    DeliverySink, EventDeliverer, EventGenerator, EventId, EventQuery, EventRecord, EventSource,
+    FailedDelivery, FileStateStore, GasCostRecord, GasCostTotals, IntentSource, LeaderElection,
+    MockProofProvider, PolymerProofProvider, ProofFetcher, ProofProvider, RelayEvent,
+    RelayerConfig, SqliteStateStore, StateStore, Topology,
+};
 
 pub struct RelayerApp {
+    config: RelayerConfig,
+    /// Only needed by the built-in generator and deliverer
+    private_key: Option<String>,
+    proof_provider: Arc<dyn ProofProvider>,
+    event_sources: Vec<Arc<dyn EventSource>>,
+    /// Whether the built-in generator watches the chains for events
+    chain_events: bool,
+    /// Takes proven events in place of the built-in deliverer
+    delivery_sink: Option<Arc<dyn DeliverySink>>,
+    hooks: Hooks,
+    journal: Journal,
+    lifecycle: Lifecycle,
+    clock: Arc<dyn Clock>,
+    pipeline: Option<Pipeline>,
+    detected: broadcast::Sender<RelayEvent>,
+    proven: broadcast::Sender<DeliveryRequest>,
+    outcomes: broadcast::Sender<DeliveryOutcome>,
+    store: Arc<dyn StateStore>,
+    control: DeliveryControl,
+    health: Health,
+    topology: Topology,
+    dead_letters: DeadLetterQueue,
+    heartbeat: Option<Heartbeat>,
+    fee_claimer: Option<FeeClaimer>,
+}
+
+// One set of components wired together by fresh channels
+struct Pipeline {
+    detection: Detection,
+    proof_fetcher: ProofFetcher,
+    deliverer: Deliverer,
+}
+
+// Everything feeding the event channel
+struct Detection {
     event_generator: Option<EventGenerator>,
-    proof_fetcher: Option<ProofFetcher>,
-    event_deliverer: Option<EventDeliverer>,
+    sources: Vec<(Arc<dyn EventSource>, EventEmitter)>,
+    /// Holds the event channel open until shutdown, even once every source
+    /// has returned
+    event_tx: mpsc::Sender<RelayEvent>,
+    health: Health,
+    heartbeat: Duration,
+}
+
+enum Deliverer {
+    Chain(EventDeliverer),
+    Sink(SinkDeliverer),
+}
+
+impl Detection {
+    // Run the generator and every source until `shutdown`, failing as soon as
+    // one of them does
+    async fn run(self, shutdown: CancellationToken) -> Result<()> {
+        let Self {
+            event_generator,
+            sources,
+            event_tx,
+            health,
+            heartbeat,
+        } = self;
+        let chain_events = event_generator.is_some();
+        let mut producers = JoinSet::new();
+        if let Some(event_generator) = event_generator {
+            let shutdown = shutdown.clone();
+            producers.spawn(async move { event_generator.start(shutdown).await });
+        }
+        for (source, emitter) in sources {
+            let shutdown = shutdown.clone();
+            producers.spawn(async move {
+                source
+                    .run(emitter, shutdown)
+                    .await
+                    .with_context(|| format!("Event source {} failed", source.name()))
+            });
+        }
+
+        // Sources only report progress by emitting, so without the generator
+        // beating for detection it is alive for as long as they run
+        let mut heartbeat = tokio::time::interval(heartbeat);
+        loop {
+            tokio::select! {
+                _ = shutdown.cancelled() => break,
+                Some(result) = producers.join_next() => result??,
+                _ = heartbeat.tick(), if !chain_events => health.beat(EVENT_GENERATOR),
+            }
+        }
+        while let Some(result) = producers.join_next().await {
+            result??;
+        }
+        // Only now can the fetcher see the channel close and start draining
+        drop(event_tx);
+        Ok(())
+    }
+}
+
+impl Deliverer {
+    async fn start(self) -> Result<()> {
+        match self {
+            Self::Chain(mut deliverer) => deliverer.start().await,
+            Self::Sink(mut deliverer) => deliverer.start().await,
+        }
+    }
 }
 
 impl RelayerApp {
-    #[instrument(skip_all, fields(config.chains_count = config.chains.len()))]
-    pub fn new(config: RelayerConfig, private_key: &str) -> Self {
+    /// Relayer built from `config` with every stage built in
+    ///
+    /// Use [`RelayerBuilder`] to supply stages of your own.
+    pub fn new(config: RelayerConfig, private_key: &str) -> Result<Self> {
+        RelayerBuilder::new(config).private_key(private_key).build()
+    }
+
+    #[instrument(skip_all, fields(config.chains_count = builder.config.chains.len()))]
+    pub(crate) fn build(builder: RelayerBuilder) -> Result<Self> {
         info!("Initializing relayer application");
+        let config = builder.config;
+        config.validate()?;
+
+        let store: Arc<dyn StateStore> = match builder.store {
+            Some(store) => store,
+            None => match config.store_backend {
+                StoreBackend::File => Arc::new(FileStateStore::open(&config.state_dir)?),
+                StoreBackend::Sqlite => Arc::new(SqliteStateStore::open(&config.state_dir)?),
+            },
+        };
 
-        // Create channels for communication between components
-        let (event_tx, event_rx) = mpsc::channel(100);
-        let (delivery_tx, delivery_rx) = mpsc::channel(100);
+        let plugins = &builder.plugins;
+        let proof_provider: Arc<dyn ProofProvider> =
+            match (builder.proof_provider, &config.plugins.proof_provider) {
+                (Some(provider), _) => provider,
+                (None, Some(spec)) => plugins.proof_provider(spec, &config, &store)?,
+                (None, None) => match &config.proof_backend {
+                    ProofBackendConfig::Polymer(api) => {
+                        Arc::new(PolymerProofProvider::new(api.clone(), store.clone())?)
+                    }
+                    ProofBackendConfig::Mock(mock) => {
+                        warn!("Using mock proof provider, proofs will not verify on-chain");
+                        Arc::new(MockProofProvider::new(mock.clone()))
+                    }
+                },
+            };
+
+        let mut event_sources = builder.event_sources;
+        for spec in &config.plugins.event_sources {
+            event_sources.push(plugins.event_source(spec, &config, &store)?);
+        }
+        let delivery_sink = match (builder.delivery_sink, &config.plugins.delivery_sink) {
+            (Some(sink), _) => Some(sink),
+            (None, Some(spec)) => Some(plugins.delivery_sink(spec, &config, &store)?),
+            (None, None) => None,
+        };
+
+        alerts::init(&config.alerts)?;
+        metrics::init(config.metrics.labels);
+        latency::init(&config.slo);
+        stream::init(config.event_stream.as_ref());
+        audit::init(config.audit.as_ref(), &config.state_dir)?;
 
         // Create components
-        let event_generator = EventGenerator::new(
-            config.chains,
-            config.relay_pairs,
-            private_key.to_string(),
-            Duration::from_millis(config.polling_interval_ms),
-            event_tx,
+        let health = Health::new(config.health.clone());
+        let topology = Topology::new(
+            config.chains.clone(),
+            config.relay_pairs.clone(),
+            config.sharding.clone().unwrap_or_default(),
         );
+        if config.sharding.is_some() {
+            let (shard_index, shard_count) = topology.shard();
+            info!(
+                shard_index,
+                shard_count,
+                pairs = topology.enabled_pairs().len(),
+                "Serving this instance's shard of relay pairs"
+            );
+        }
+        let dead_letters = DeadLetterQueue::new(store.clone());
+        let journal = Journal::open(&config.state_dir, &config.journal)?;
+        let lifecycle = Lifecycle::new(&config);
+        let heartbeat = match config.heartbeat.clone() {
+            Some(heartbeat) => Some(Heartbeat::new(
+                heartbeat,
+                builder.private_key.as_deref(),
+                topology.clone(),
+                store.clone(),
+            )?),
+            None => None,
+        };
+        let fee_claimer = match config.fee_claims.clone() {
+            Some(fee_claims) => Some(FeeClaimer::new(
+                fee_claims,
+                builder.private_key.as_deref(),
+                topology.clone(),
+                store.clone(),
+            )?),
+            None => None,
+        };
 
-        let proof_fetcher = ProofFetcher::new(
-            event_rx,
-            delivery_tx,
-            "https://api.polymer.zone/v1/proofs".to_string(),
-            "your-api-token".to_string(), // TODO: Get this from config/env
-        );
+        let mut app = Self {
+            config,
+            private_key: builder.private_key,
+            proof_provider,
+            event_sources,
+            chain_events: builder.chain_events,
+            delivery_sink,
+            hooks: Hooks::new(builder.hooks),
+            journal,
+            lifecycle,
+            clock: builder.clock.unwrap_or_else(|| Arc::new(SystemClock)),
+            pipeline: None,
+            detected: broadcast::channel(TAP_CAPACITY).0,
+            proven: broadcast::channel(TAP_CAPACITY).0,
+            outcomes: broadcast::channel(OUTCOME_CAPACITY).0,
+            store,
+            control: DeliveryControl::default(),
+            health,
+            topology,
+            dead_letters,
+            heartbeat,
+            fee_claimer,
+        };
+        app.pipeline = Some(app.build_pipeline()?);
+        Ok(app)
+    }
 
-        let event_deliverer = EventDeliverer::new(private_key.to_string(), delivery_rx);
+    /// Gas spent by the relayer so far, per transaction
+    pub fn gas_costs(&self) -> Result<Vec<GasCostRecord>> {
+        self.store.gas_costs()
+    }
+
+    /// Gas spent by the relayer so far, summed per pair and per chain
+    pub fn gas_cost_totals(&self) -> Result<GasCostTotals> {
+        Ok(GasCostTotals::from_records(&self.store.gas_costs()?))
+    }
 
-        Self {
-            event_generator: Some(event_generator),
-            proof_fetcher: Some(proof_fetcher),
-            event_deliverer: Some(event_deliverer),
+    /// Fee claims sent so far
+    pub fn fee_claims(&self) -> Result<Vec<FeeClaimRecord>> {
+        self.store.fee_claims()
+    }
+
+    /// Failed deliveries waiting for another attempt
+    pub fn pending_retries(&self) -> Result<Vec<FailedDelivery>> {
+        self.store.retries()
+    }
+
+    /// Events given up on after running out of attempts or failing for good
+    pub fn dead_letters(&self) -> Result<Vec<FailedDelivery>> {
+        self.store.dead_letters()
+    }
+
+    /// Handle for inspecting and replaying dead letters
+    pub fn dead_letter_queue(&self) -> DeadLetterQueue {
+        self.dead_letters.clone()
+    }
+
+    /// Recorded delivery attempts matching `query`, oldest first
+    pub fn delivery_history(&self, query: &DeliveryQuery) -> Result<Vec<DeliveryOutcome>> {
+        self.store.delivery_history(query)
+    }
+
+    /// Where the event `id` stands in the pipeline, if it was seen at all
+    pub fn event_status(&self, id: &EventId) -> Result<Option<EventRecord>> {
+        self.store.event_record(id)
+    }
+
+    /// Event statuses matching `query`, least recently changed first
+    pub fn events(&self, query: &EventQuery) -> Result<Vec<EventRecord>> {
+        query.validate()?;
+        self.store.query_events(query)
+    }
+
+    /// Handle for cancelling or re-broadcasting deliveries that are in flight
+    pub fn delivery_control(&self) -> DeliveryControl {
+        self.control.clone()
+    }
+
+    /// Chains and relay pairs being served, which can be changed while running
+    pub fn topology(&self) -> Topology {
+        self.topology.clone()
+    }
+
+    /// Receive every event taken in for proving from now on, whether detected
+    /// on chain or emitted by an [`EventSource`]
+    pub fn subscribe_events(&self) -> broadcast::Receiver<RelayEvent> {
+        self.detected.subscribe()
+    }
+
+    /// Receive every event once its proof is fetched, from now on
+    pub fn subscribe_proofs(&self) -> broadcast::Receiver<DeliveryRequest> {
+        self.proven.subscribe()
+    }
+
+    /// Receive the outcome of every delivery attempt from now on
+    pub fn subscribe_outcomes(&self) -> broadcast::Receiver<DeliveryOutcome> {
+        self.outcomes.subscribe()
+    }
+
+    /// Start all relayer components in the background and run until
+    /// [`RelayerHandle::shutdown`], then drain the pipeline within the grace
+    /// period
+    ///
+    /// A component that fails takes the pipeline down with it; the pipeline is
+    /// then rebuilt after a backoff, and events left unfinished are resumed
+    /// from the store. Too many failures in a row end the run with an error.
+    ///
+    /// With leader election configured, the pipeline only runs while this
+    /// instance holds the leader lease and is drained as soon as it loses it.
+    ///
+    /// Must be called from within a Tokio runtime.
+    pub fn run(self) -> RelayerHandle {
+        let lifecycle = self.lifecycle.clone();
+        let health = self.health.clone();
+        let task = tokio::spawn(self.supervise());
+        RelayerHandle {
+            lifecycle,
+            health,
+            task,
         }
     }
 
-    /// Start all relayer components and wait for completion
-    #[instrument(skip(self))]
-    pub async fn run(&mut self) -> Result<()> {
+    #[instrument(skip_all, name = "run")]
+    async fn supervise(mut self) -> Result<()> {
         info!("Starting all relayer components");
+        let _drained = self.lifecycle.drained_guard();
+        let shutdown = self.lifecycle.shutdown_token().clone();
+        // Stops the pipeline, but only shutdown stops the relayer
+        let drain = self.lifecycle.drain_token().clone();
+        let health = self.health.clone();
+        let draining = drain.clone();
+        tokio::spawn(async move {
+            draining.cancelled().await;
+            health.set_draining();
+        });
 
-        // Take ownership of components
-        let event_generator = self
-            .event_generator
-            .take()
-            .expect("event_generator should not be empty");
-        let mut proof_fetcher = self
-            .proof_fetcher
+        if let Some(addr) = self.config.http_addr {
+            let control = self.control.clone();
+            let store = self.store.clone();
+            let health = self.health.clone();
+            let topology = self.topology.clone();
+            let dead_letters = self.dead_letters.clone();
+            let admin_token = self.config.admin_token.clone();
+            tokio::spawn(async move {
+                let served = server::serve(
+                    addr,
+                    control,
+                    store,
+                    health,
+                    topology,
+                    dead_letters,
+                    admin_token,
+                );
+                if let Err(e) = served.await {
+                    error!(error = %e, "HTTP server error");
+                }
+            });
+        }
+
+        if let Some(path) = self.config.control_socket.clone() {
+            let state = ControlState {
+                store: self.store.clone(),
+                health: self.health.clone(),
+                topology: self.topology.clone(),
+                control: self.control.clone(),
+                lifecycle: self.lifecycle.clone(),
+            };
+            tokio::spawn(async move {
+                if let Err(e) = control_socket::serve(path, state).await {
+                    error!(error = %e, "Control socket error");
+                }
+            });
+        }
+
+        let health = self.health.clone();
+        let topology = self.topology.clone();
+        let chain_checks = tokio::spawn(async move { health.run_chain_checks(topology).await });
+        let stall_alerts = tokio::spawn(alerts::watch_stalls(self.health.clone()));
+        let stream_stop = CancellationToken::new();
+        let mut stream = tokio::spawn(stream::run(stream_stop.clone()));
+        let reports = self.config.health.report_interval_ms.map(|interval| {
+            let interval = Duration::from_millis(interval);
+            tokio::spawn(report::run(
+                self.health.clone(),
+                self.store.clone(),
+                interval,
+            ))
+        });
+
+        // Without an election this instance always leads
+        let (leader_tx, mut leader) = watch::channel(self.config.leader_election.is_none());
+        let election = match self.config.leader_election.clone() {
+            Some(config) => {
+                let election = LeaderElection::new(config)?;
+                let campaign = election.clone();
+                let task = tokio::spawn(async move { campaign.run(leader_tx).await });
+                Some((election, task))
+            }
+            // The unused sender lives to the end of the run, so leadership
+            // never reads as lost
+            None => None,
+        };
+        let heartbeats = self
+            .heartbeat
             .take()
-            .expect("proof_fetcher should not be empty");
-        let mut event_deliverer = self
-            .event_deliverer
+            .map(|heartbeat| tokio::spawn(heartbeat.run(leader.clone())));
+        let fee_claims = self
+            .fee_claimer
             .take()
-            .expect("event_deliverer should not be empty");
+            .map(|claimer| tokio::spawn(claimer.run(leader.clone())));
+
+        let supervisor = self.config.supervisor.clone();
+        let mut pipeline = self.pipeline.take().expect("pipeline should not be empty");
+        let mut restarts = 0;
+
+        loop {
+            if !*leader.borrow() {
+                info!("Standing by until this instance holds the leader lease");
+                self.health.set_standby(true);
+                tokio::select! {
+                    _ = leader.wait_for(|leading| *leading) => {}
+                    _ = drain.cancelled() => {
+                        info!("Shutdown requested while standing by");
+                        break;
+                    }
+                }
+                self.health.set_standby(false);
+                info!("Leading, starting pipeline");
+            }
+
+            let started = Instant::now();
+            let component = match self.run_pipeline(pipeline, &mut leader, &drain).await {
+                PipelineExit::Shutdown => break,
+                PipelineExit::Demoted => {
+                    pipeline = self.build_pipeline()?;
+                    continue;
+                }
+                PipelineExit::Failed(component) => component,
+            };
+
+            if started.elapsed() >= Duration::from_millis(supervisor.healthy_after_ms) {
+                restarts = 0;
+            }
+            restarts += 1;
+            if restarts > supervisor.max_restarts {
+                chain_checks.abort();
+                stall_alerts.abort();
+                stream.abort();
+                if let Some(reports) = &reports {
+                    reports.abort();
+                }
+                if let Some(heartbeats) = &heartbeats {
+                    heartbeats.abort();
+                }
+                if let Some(fee_claims) = &fee_claims {
+                    fee_claims.abort();
+                }
+                if let Some((election, task)) = election {
+                    task.abort();
+                    election.release().await;
+                }
+                self.store.flush()?;
+                return Err(anyhow!(
+                    "{} failed {} times in a row, giving up",
+                    component,
+                    restarts
+                ));
+            }
+
+            let delay = clock::backoff(
+                supervisor.initial_backoff_ms,
+                supervisor.max_backoff_ms,
+                restarts,
+            );
+            warn!(component, restarts, ?delay, "Restarting pipeline");
+            COMPONENT_RESTARTS.with_label_values(&[component]).inc();
+            tokio::select! {
+                _ = tokio::time::sleep(delay) => {}
+                _ = drain.cancelled() => {
+                    info!("Shutdown requested while waiting to restart");
+                    break;
+                }
+            }
+
+            pipeline = self.build_pipeline()?;
+        }
+
+        chain_checks.abort();
+        stall_alerts.abort();
+        if let Some(reports) = &reports {
+            reports.abort();
+        }
+        if let Some(heartbeats) = &heartbeats {
+            heartbeats.abort();
+        }
+        if let Some(fee_claims) = &fee_claims {
+            fee_claims.abort();
+        }
+        if let Some((election, task)) = election {
+            task.abort();
+            election.release().await;
+        }
+        self.store.flush()?;
+        // Let the drained pipeline's last status changes reach the broker
+        stream_stop.cancel();
+        let flush_period = self.lifecycle.flush_period();
+        if tokio::time::timeout(flush_period, &mut stream).await.is_err() {
+            warn!("Event stream not flushed in time, dropping unsent status changes");
+            stream.abort();
+        }
+        self.lifecycle.set_drained();
+        if !shutdown.is_cancelled() {
+            info!("Pipeline drained, waiting for shutdown");
+            shutdown.cancelled().await;
+        }
+        info!("Relayer stopped");
+        Ok(())
+    }
+
+    // A fresh pipeline wired to the long-lived subscription and control
+    // handles, so they keep working across restarts
+    fn build_pipeline(&self) -> Result<Pipeline> {
+        // Events lost in the channels of the last pipeline are resumed from
+        // the store by the new one
+        self.journal.recover(&*self.store)?;
+
+        let config = &self.config;
+        let backpressure = &config.backpressure;
+        let (event_tx, event_rx) = mpsc::channel(backpressure.event_queue_capacity.max(1));
+        let (delivery_tx, delivery_rx) = mpsc::channel(backpressure.delivery_queue_capacity.max(1));
+        self.health.watch_queue(PROOF_FETCHER, &event_tx);
+        self.health.watch_queue(EVENT_DELIVERER, &delivery_tx);
+        self.dead_letters.attach(&event_tx);
+
+        let event_generator = if self.chain_events {
+            Some(
+                EventGenerator::new(
+                    self.topology.clone(),
+                    self.private_key()?,
+                    Duration::from_millis(config.polling_interval_ms),
+                    event_tx.clone(),
+                    self.store.clone(),
+                    self.health.clone(),
+                    backpressure.slow_down_threshold,
+                )
+                .with_journal(self.journal.clone())
+                .with_clock(self.clock.clone())
+                .with_profitability(config.profitability.clone()),
+            )
+        } else {
+            None
+        };
+        let mut event_sources = self.event_sources.clone();
+        if self.chain_events {
+            let intents = IntentSource::new(
+                self.topology.clone(),
+                self.store.clone(),
+                config.intents.clone(),
+            )
+            .with_clock(self.clock.clone());
+            event_sources.push(Arc::new(intents));
+        }
+        let sources = event_sources
+            .iter()
+            .map(|source| {
+                let emitter = EventEmitter::new(
+                    event_tx.clone(),
+                    self.health.clone(),
+                    self.journal.clone(),
+                    self.store.clone(),
+                );
+                (source.clone(), emitter)
+            })
+            .collect();
+        let detection = Detection {
+            event_generator,
+            sources,
+            event_tx,
+            health: self.health.clone(),
+            heartbeat: Duration::from_millis(config.polling_interval_ms),
+        };
+
+        let proof_fetcher = ProofFetcher::new(
+            event_rx,
+            delivery_tx,
+            self.proof_provider.clone(),
+            self.store.clone(),
+            config.proof_fetcher.clone(),
+            backpressure.spill_to_store,
+            self.health.clone(),
+        )
+        .with_taps(self.detected.clone(), self.proven.clone())
+        .with_hooks(self.hooks.clone())
+        .with_journal(self.journal.clone())
+        .with_clock(self.clock.clone())
+        .with_topology(self.topology.clone());
+
+        let deliverer = match &self.delivery_sink {
+            Some(sink) => Deliverer::Sink(
+                SinkDeliverer::new(
+                    sink.clone(),
+                    delivery_rx,
+                    self.store.clone(),
+                    &config.delivery,
+                    self.topology.clone(),
+                    self.health.clone(),
+                    self.outcomes.clone(),
+                )
+                .with_hooks(self.hooks.clone())
+                .with_journal(self.journal.clone())
+                .with_clock(self.clock.clone()),
+            ),
+            None => Deliverer::Chain(
+                EventDeliverer::new(
+                    self.private_key()?,
+                    delivery_rx,
+                    self.store.clone(),
+                    config.delivery.clone(),
+                    self.topology.clone(),
+                    self.topology.chains(),
+                    self.health.clone(),
+                )?
+                .with_handles(self.outcomes.clone(), self.control.clone())
+                .with_hooks(self.hooks.clone())
+                .with_journal(self.journal.clone())
+                .with_clock(self.clock.clone()),
+            ),
+        };
+
+        Ok(Pipeline {
+            detection,
+            proof_fetcher,
+            deliverer,
+        })
+    }
+
+    fn private_key(&self) -> Result<String> {
+        self.private_key.clone().ok_or_else(|| {
+            anyhow!("A private key is needed to detect events or deliver them on chain")
+        })
+    }
+
+    // Run one pipeline until shutdown is requested, the leader lease is lost
+    // or a component exits, then drain it, or stop it outright once demoted
+    async fn run_pipeline(
+        &self,
+        pipeline: Pipeline,
+        leader: &mut watch::Receiver<bool>,
+        requested: &CancellationToken,
+    ) -> PipelineExit {
+        let Pipeline {
+            detection,
+            mut proof_fetcher,
+            deliverer,
+        } = pipeline;
 
         // Start components in separate tasks
-        let generator_handle = tokio::spawn(async move {
-            if let Err(e) = event_generator.start().await {
-                error!(error = %e, "Event generator error");
+        let shutdown = CancellationToken::new();
+        let generator_shutdown = shutdown.clone();
+        let mut generator_handle =
+            tokio::spawn(async move { detection.run(generator_shutdown).await });
+        let mut fetcher_handle = tokio::spawn(async move { proof_fetcher.start().await });
+        let mut deliverer_handle = tokio::spawn(async move { deliverer.start().await });
+
+        let exit = tokio::select! {
+            _ = requested.cancelled() => {
+                info!("Shutdown requested, draining pipeline");
+                PipelineExit::Shutdown
             }
-        });
+            _ = leader.wait_for(|leading| !*leading) => {
+                warn!("Lost the leader lease, stopping pipeline");
+                PipelineExit::Demoted
+            }
+            result = &mut generator_handle => {
+                PipelineExit::Failed(component_exited(EVENT_GENERATOR, result))
+            }
+            result = &mut fetcher_handle => {
+                PipelineExit::Failed(component_exited(PROOF_FETCHER, result))
+            }
+            result = &mut deliverer_handle => {
+                PipelineExit::Failed(component_exited(EVENT_DELIVERER, result))
+            }
+        };
+
+        // Another instance may take the lease as soon as it runs out, so a
+        // demoted leader stops at once rather than relaying while it drains.
+        // Unfinished events stay pending in the store
+        if matches!(exit, PipelineExit::Demoted) {
+            generator_handle.abort();
+            fetcher_handle.abort();
+            deliverer_handle.abort();
+            return exit;
+        }
 
-        let fetcher_handle = tokio::spawn(async move {
-            if let Err(e) = proof_fetcher.start().await {
-                error!(error = %e, "Proof fetcher error");
+        // Stopping detection closes the event channel, which lets the
+        // fetcher finish its in-flight proofs and then close the delivery
+        // channel in turn, so each stage drains into the next
+        shutdown.cancel();
+        let drain = async {
+            if !generator_handle.is_finished() {
+                log_result(EVENT_GENERATOR, (&mut generator_handle).await);
             }
-        });
+            if !fetcher_handle.is_finished() {
+                log_result(PROOF_FETCHER, (&mut fetcher_handle).await);
+            }
+            if !deliverer_handle.is_finished() {
+                log_result(EVENT_DELIVERER, (&mut deliverer_handle).await);
+            }
+        };
+        match tokio::time::timeout(self.lifecycle.drain_period(), drain).await {
+            Ok(()) => info!("Pipeline drained"),
+            Err(_) => {
+                // Unfinished events are still pending in the store and resume on restart
+                warn!("Shutdown grace period elapsed with work still in flight");
+                generator_handle.abort();
+                fetcher_handle.abort();
+                deliverer_handle.abort();
+            }
+        }
+
+        exit
+    }
+}
+
+/// Control over a relayer started with [`RelayerApp::run`]
+///
+/// Dropping the handle leaves the relayer running.
+pub struct RelayerHandle {
+    lifecycle: Lifecycle,
+    health: Health,
+    task: JoinHandle<Result<()>>,
+}
 
-        let deliverer_handle = tokio::spawn(async move {
-            if let Err(e) = event_deliverer.start().await {
-                error!(error = %e, "Event deliverer error");
+impl RelayerHandle {
+    /// Ask the relayer to drain its pipeline and stop
+    pub fn shutdown(&self) {
+        self.lifecycle.shutdown_token().cancel();
+    }
+
+    /// Token that shuts the relayer down once cancelled
+    pub fn shutdown_token(&self) -> CancellationToken {
+        self.lifecycle.shutdown_token().clone()
+    }
+
+    /// Drain the pipeline but keep serving health and admin endpoints until
+    /// shutdown, returning once drained
+    ///
+    /// This is what a Kubernetes preStop hook running `relayer drain` does,
+    /// so the SIGTERM that follows finds nothing left in flight.
+    pub async fn drain(&self) {
+        self.lifecycle.drain();
+        self.lifecycle.drained().await;
+    }
+
+    /// Shut the relayer down on Ctrl-C, or on SIGTERM where there is one
+    pub fn shutdown_on_signal(&self) {
+        let shutdown = self.lifecycle.shutdown_token().clone();
+        tokio::spawn(async move {
+            tokio::select! {
+                _ = shutdown_signal() => info!("Shutdown signal received"),
+                _ = shutdown.cancelled() => {}
             }
+            shutdown.cancel();
         });
+    }
+
+    /// Liveness of each pipeline component and chain
+    pub fn health(&self) -> HealthReport {
+        self.health.report()
+    }
 
-        tokio::select! {
-            _ = generator_handle => error!("Event generator task exited"),
-            _ = fetcher_handle => error!("Proof fetcher task exited"),
-            _ = deliverer_handle => error!("Event deliverer task exited"),
+    /// Whether the relayer has stopped
+    pub fn is_terminated(&self) -> bool {
+        self.task.is_finished()
+    }
+
+    /// Wait for the relayer to stop, with the error that stopped it if it
+    /// gave up rather than being shut down
+    pub async fn await_terminated(self) -> Result<()> {
+        self.task.await.context("Relayer task failed")?
+    }
+}
+
+// Why a pipeline stopped running
+enum PipelineExit {
+    Shutdown,
+    /// Another instance took over as leader
+    Demoted,
+    /// A component exited on its own
+    Failed(&'static str),
+}
+
+// Report a component that exited on its own, which is always a failure
+fn component_exited(
+    component: &'static str,
+    result: Result<Result<()>, JoinError>,
+) -> &'static str {
+    match result {
+        Ok(Ok(())) => error!(component, "Component exited unexpectedly"),
+        other => log_result(component, other),
+    }
+    component
+}
+
+fn log_result(component: &'static str, result: Result<Result<()>, JoinError>) {
+    match result {
+        Ok(Ok(())) => {}
+        Ok(Err(e)) => error!(component, error = %e, "Component failed"),
+        Err(e) if e.is_panic() => error!(component, error = %e, "Component panicked"),
+        Err(_) => {}
+    }
+}
+
+// Resolve on Ctrl-C, or on SIGTERM where there is one
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            error!(error = %e, "Failed to listen for Ctrl-C");
+            std::future::pending::<()>().await;
         }
+    };
 
-        Ok(())
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(e) => {
+                error!(error = %e, "Failed to listen for SIGTERM");
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
     }
 }
@@ -0,0 +1,334 @@
+use anyhow::{anyhow, Context, Result};
+use ethers::{types::H256, utils::keccak256};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, RwLock},
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tracing::{info, warn};
+
+use crate::config::AuditConfig;
+use crate::store::ProofKey;
+use crate::types::{ChainId, DeliveryOutcome, DeliveryStatus, EventId, RelayEvent, RelayerError};
+
+const AUDIT_FILE: &str = "audit.log";
+
+// Audit log of the running relayer, unset while none is configured
+static AUDIT: RwLock<Option<Arc<AuditLog>>> = RwLock::new(None);
+
+/// One line of the audit log
+///
+/// Each entry's `hash` covers the entry itself and, through `prev_hash`,
+/// every entry before it, so editing, removing or reordering any line
+/// breaks the chain from there on. See [`verify_audit_log`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    /// Position in the log, counting from 1
+    pub seq: u64,
+    /// Unix time in seconds the action was taken
+    pub recorded_at: u64,
+    #[serde(flatten)]
+    pub action: AuditAction,
+    /// `hash` of the entry before, zero for the first
+    pub prev_hash: H256,
+    pub hash: H256,
+}
+
+impl AuditEntry {
+    // Keccak-256 of the entry as JSON with `hash` zeroed
+    fn digest(&self) -> Result<H256> {
+        let unsealed = Self {
+            hash: H256::zero(),
+            ..self.clone()
+        };
+        Ok(H256(keccak256(serde_json::to_vec(&unsealed)?)))
+    }
+}
+
+/// What the relayer did
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum AuditAction {
+    /// An event was picked up on its source chain
+    Triggered {
+        event_id: EventId,
+        pair: String,
+        nonce: u64,
+        source_chain_id: ChainId,
+        /// Source transaction that emitted the event
+        tx_hash: Option<H256>,
+        block_number: u64,
+        log_index: u32,
+    },
+    /// Proving of an event began
+    ProofRequested {
+        event_id: EventId,
+        proof_key: ProofKey,
+    },
+    Proved {
+        event_id: EventId,
+        proof_key: ProofKey,
+        /// Polymer proof job the proof came from, if the backend keeps jobs
+        job_id: Option<i64>,
+    },
+    ProofFailed {
+        event_id: EventId,
+        proof_key: ProofKey,
+        error: String,
+    },
+    /// A delivery attempt ended, retrying or not
+    Delivery {
+        event_id: EventId,
+        dest_chain_id: ChainId,
+        attempt: u32,
+        status: DeliveryStatus,
+        /// Delivery transaction, when one was sent
+        tx_hash: Option<H256>,
+        block_number: Option<u64>,
+        error: Option<String>,
+    },
+}
+
+impl AuditAction {
+    pub(crate) fn triggered(event: &RelayEvent) -> Self {
+        Self::Triggered {
+            event_id: event.id(),
+            pair: event.pair_id(),
+            nonce: event.nonce,
+            source_chain_id: event.meta.chain_id,
+            tx_hash: event.meta.tx_hash,
+            block_number: event.meta.block_number,
+            log_index: event.meta.log_index,
+        }
+    }
+
+    pub(crate) fn proof_requested(event: &RelayEvent) -> Self {
+        Self::ProofRequested {
+            event_id: event.id(),
+            proof_key: ProofKey::from_meta(&event.meta),
+        }
+    }
+
+    pub(crate) fn proved(event: &RelayEvent, job_id: Option<i64>) -> Self {
+        Self::Proved {
+            event_id: event.id(),
+            proof_key: ProofKey::from_meta(&event.meta),
+            job_id,
+        }
+    }
+
+    pub(crate) fn proof_failed(event: &RelayEvent, error: &anyhow::Error) -> Self {
+        Self::ProofFailed {
+            event_id: event.id(),
+            proof_key: ProofKey::from_meta(&event.meta),
+            error: format!("{:#}", error),
+        }
+    }
+
+    pub(crate) fn delivery(outcome: &DeliveryOutcome) -> Self {
+        Self::Delivery {
+            event_id: outcome.event_id,
+            dest_chain_id: outcome.dest_chain_id,
+            attempt: outcome.attempt,
+            status: outcome.status,
+            tx_hash: outcome.tx_hash,
+            block_number: outcome.block_number,
+            error: outcome.error.clone(),
+        }
+    }
+}
+
+/// Length and last hash of a verified audit log
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct AuditHead {
+    pub entries: u64,
+    /// Zero for an empty log; kept somewhere the relayer cannot write, it
+    /// also makes cutting entries off the end evident
+    pub hash: H256,
+}
+
+struct AuditLog {
+    path: PathBuf,
+    fsync: bool,
+    // Appends are chained, so only one may be written at a time
+    writer: Mutex<Writer>,
+}
+
+struct Writer {
+    file: File,
+    head: AuditHead,
+}
+
+/// Start auditing to the configured log, continuing its chain if it exists,
+/// in place of any earlier configuration
+pub(crate) fn init(config: Option<&AuditConfig>, state_dir: &Path) -> Result<()> {
+    let log = match config {
+        Some(config) => {
+            let path = config
+                .path
+                .clone()
+                .unwrap_or_else(|| state_dir.join(AUDIT_FILE));
+            Some(Arc::new(AuditLog::open(path, config.fsync)?))
+        }
+        None => None,
+    };
+    *AUDIT.write().expect("audit log lock poisoned") = log;
+    Ok(())
+}
+
+/// Append `action` to the audit log, if one is configured
+///
+/// A failed write is logged; the pipeline carries on either way.
+pub(crate) fn record(action: AuditAction) {
+    let Some(log) = AUDIT.read().expect("audit log lock poisoned").clone() else {
+        return;
+    };
+    if let Err(e) = log.append(action) {
+        warn!(error = %e, path = %log.path.display(), "Failed to write audit entry");
+    }
+}
+
+impl AuditLog {
+    fn open(path: PathBuf, fsync: bool) -> Result<Self> {
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)
+                .context(format!("Failed to create audit log dir {}", dir.display()))?;
+        }
+        let head = match last_entry(&path)? {
+            Some(entry) => AuditHead {
+                entries: entry.seq,
+                hash: entry.hash,
+            },
+            None => AuditHead {
+                entries: 0,
+                hash: H256::zero(),
+            },
+        };
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .context(format!("Failed to open audit log {}", path.display()))?;
+        info!(path = %path.display(), entries = head.entries, head = ?head.hash, "Audit log open");
+        Ok(Self {
+            path,
+            fsync,
+            writer: Mutex::new(Writer { file, head }),
+        })
+    }
+
+    fn append(&self, action: AuditAction) -> Result<()> {
+        let mut writer = self
+            .writer
+            .lock()
+            .map_err(|_| anyhow!("Audit log lock poisoned"))?;
+        let mut entry = AuditEntry {
+            seq: writer.head.entries + 1,
+            recorded_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_secs())
+                .unwrap_or_default(),
+            action,
+            prev_hash: writer.head.hash,
+            hash: H256::zero(),
+        };
+        entry.hash = entry.digest()?;
+        let mut line = serde_json::to_vec(&entry)?;
+        line.push(b'\n');
+        writer.file.write_all(&line)?;
+        if self.fsync {
+            writer.file.sync_data()?;
+        }
+        writer.head = AuditHead {
+            entries: entry.seq,
+            hash: entry.hash,
+        };
+        Ok(())
+    }
+}
+
+// Last complete entry of an existing log, which the next one chains onto
+//
+// A crash mid-write leaves a last line without its newline, which is cut off
+// so the chain carries on from the entry before.
+fn last_entry(path: &Path) -> Result<Option<AuditEntry>> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e).context(format!("Failed to open audit log {}", path.display())),
+    };
+    let mut last = None;
+    let mut complete = 0;
+    let mut reader = BufReader::new(file);
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        let read = reader.read_until(b'\n', &mut line)?;
+        if read == 0 || line.last() != Some(&b'\n') {
+            break;
+        }
+        complete += read as u64;
+        last = Some(std::mem::take(&mut line));
+    }
+    if !line.is_empty() {
+        warn!(path = %path.display(), "Dropping partly written last audit entry");
+        OpenOptions::new()
+            .write(true)
+            .open(path)?
+            .set_len(complete)?;
+    }
+    last.map(|line| {
+        serde_json::from_slice(&line).context(format!(
+            "Unreadable last entry in audit log {}",
+            path.display()
+        ))
+    })
+    .transpose()
+}
+
+/// Check every entry of the audit log at `path` chains onto the one before
+/// and hashes to what it says, returning the log's head
+///
+/// Fails with [`RelayerError::AuditChainBroken`] at the first entry that
+/// does not.
+pub fn verify_audit_log(path: &Path) -> Result<AuditHead> {
+    let file = File::open(path).context(format!("Failed to open audit log {}", path.display()))?;
+    let mut head = AuditHead {
+        entries: 0,
+        hash: H256::zero(),
+    };
+    for (index, line) in BufReader::new(file).lines().enumerate() {
+        let broken = |reason: String| RelayerError::AuditChainBroken {
+            line: index as u64 + 1,
+            reason,
+        };
+        let entry: AuditEntry =
+            serde_json::from_str(&line?).map_err(|e| broken(format!("unreadable entry: {}", e)))?;
+        if entry.seq != head.entries + 1 {
+            return Err(broken(format!("seq {} follows {}", entry.seq, head.entries)).into());
+        }
+        if entry.prev_hash != head.hash {
+            return Err(broken(format!(
+                "prev_hash {:?} is not the hash {:?} before it",
+                entry.prev_hash, head.hash
+            ))
+            .into());
+        }
+        let digest = entry.digest()?;
+        if entry.hash != digest {
+            return Err(broken(format!(
+                "entry hashes to {:?}, not its hash {:?}",
+                digest, entry.hash
+            ))
+            .into());
+        }
+        head = AuditHead {
+            entries: entry.seq,
+            hash: entry.hash,
+        };
+    }
+    Ok(head)
+}
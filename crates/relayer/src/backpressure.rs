@@ -0,0 +1,31 @@
+use std::time::Instant;
+use tokio::sync::mpsc::{
+    self,
+    error::{SendError, TrySendError},
+};
+use tracing::debug;
+
+use crate::metrics::{QUEUE_FULL, QUEUE_SEND_WAIT};
+
+/// Send into the queue `component` consumes from, waiting for room if it is
+/// full and recording how long that took
+pub(crate) async fn send<T>(
+    component: &'static str,
+    tx: &mpsc::Sender<T>,
+    item: T,
+) -> Result<(), SendError<T>> {
+    let item = match tx.try_send(item) {
+        Ok(()) => return Ok(()),
+        Err(TrySendError::Closed(item)) => return Err(SendError(item)),
+        Err(TrySendError::Full(item)) => item,
+    };
+
+    QUEUE_FULL.with_label_values(&[component]).inc();
+    debug!(component, "Queue full, waiting for room");
+    let started = Instant::now();
+    let result = tx.send(item).await;
+    QUEUE_SEND_WAIT
+        .with_label_values(&[component])
+        .observe(started.elapsed().as_secs_f64());
+    result
+}
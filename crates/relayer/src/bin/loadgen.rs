@@ -0,0 +1,58 @@
+use anyhow::{anyhow, Result};
+use relayer::testkit::{self, LoadProfile};
+use relayer::{MockProofConfig, ProofBackendConfig, StoreBackend};
+use std::{fs, time::Duration};
+
+const USAGE: &str = "usage: relayer-loadgen [--events <n>] [--rate <events/s>] \
+    [--proof-latency-ms <ms>] [--fetch-concurrency <n>] [--store file|sqlite] \
+    [--timeout-secs <secs>]";
+
+// Pushes synthetic events through the pipeline with mock proofs and a
+// recording sink, then prints throughput and per-stage latency
+#[tokio::main]
+async fn main() -> Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let mut profile = LoadProfile::default();
+    let mut proof_latency_ms = 0;
+    let mut fetch_concurrency = None;
+    let mut store_backend = StoreBackend::default();
+    for pair in args.chunks(2) {
+        match pair {
+            [flag, value] if flag == "--events" => profile.events = value.parse()?,
+            [flag, value] if flag == "--rate" => profile.rate_per_sec = Some(value.parse()?),
+            [flag, value] if flag == "--proof-latency-ms" => proof_latency_ms = value.parse()?,
+            [flag, value] if flag == "--fetch-concurrency" => {
+                fetch_concurrency = Some(value.parse()?)
+            }
+            [flag, value] if flag == "--store" => {
+                store_backend = match value.as_str() {
+                    "file" => StoreBackend::File,
+                    "sqlite" => StoreBackend::Sqlite,
+                    _ => return Err(anyhow!(USAGE)),
+                }
+            }
+            [flag, value] if flag == "--timeout-secs" => {
+                profile.timeout = Duration::from_secs(value.parse()?)
+            }
+            _ => return Err(anyhow!(USAGE)),
+        }
+    }
+
+    // Every run starts from empty state, so its nonces are all new
+    let state_dir = std::env::temp_dir().join(format!("relayer-loadgen-{}", std::process::id()));
+    let mut config = testkit::config(&state_dir)
+        .proof_backend(ProofBackendConfig::Mock(MockProofConfig {
+            latency_ms: proof_latency_ms,
+            ..Default::default()
+        }))
+        .store_backend(store_backend)
+        .build()?;
+    if let Some(concurrency) = fetch_concurrency {
+        config.proof_fetcher.max_concurrent_fetches = concurrency;
+    }
+
+    let report = testkit::run_load(config, &profile).await;
+    let _ = fs::remove_dir_all(&state_dir);
+    println!("{}", report?);
+    Ok(())
+}
@@ -0,0 +1,107 @@
+use anyhow::Result;
+use std::sync::Arc;
+
+use crate::{
+    Clock, DeliverySink, EventSource, PipelineHook, PluginRegistry, ProofProvider, RelayerApp,
+    RelayerConfig, StateStore,
+};
+
+/// Assembles a [`RelayerApp`] for embedding, with any stage replaced by one
+/// the embedding application supplies
+///
+/// Stages left unset are built from the configuration as `relayer run` builds
+/// them. A private key is only needed while the built-in generator or
+/// deliverer is in use.
+pub struct RelayerBuilder {
+    pub(crate) config: RelayerConfig,
+    pub(crate) private_key: Option<String>,
+    pub(crate) store: Option<Arc<dyn StateStore>>,
+    pub(crate) proof_provider: Option<Arc<dyn ProofProvider>>,
+    pub(crate) event_sources: Vec<Arc<dyn EventSource>>,
+    pub(crate) chain_events: bool,
+    pub(crate) delivery_sink: Option<Arc<dyn DeliverySink>>,
+    pub(crate) hooks: Vec<Arc<dyn PipelineHook>>,
+    pub(crate) plugins: PluginRegistry,
+    pub(crate) clock: Option<Arc<dyn Clock>>,
+}
+
+impl RelayerBuilder {
+    pub fn new(config: RelayerConfig) -> Self {
+        Self {
+            config,
+            private_key: None,
+            store: None,
+            proof_provider: None,
+            event_sources: Vec::new(),
+            chain_events: true,
+            delivery_sink: None,
+            hooks: Vec::new(),
+            plugins: PluginRegistry::default(),
+            clock: None,
+        }
+    }
+
+    /// Key signing trigger and delivery transactions
+    pub fn private_key(mut self, private_key: impl Into<String>) -> Self {
+        self.private_key = Some(private_key.into());
+        self
+    }
+
+    /// Store to use in place of the configured backend
+    pub fn store(mut self, store: Arc<dyn StateStore>) -> Self {
+        self.store = Some(store);
+        self
+    }
+
+    /// Proof provider to use in place of the configured backend
+    pub fn proof_provider(mut self, provider: Arc<dyn ProofProvider>) -> Self {
+        self.proof_provider = Some(provider);
+        self
+    }
+
+    /// Add a source of events, run alongside the on-chain generator
+    pub fn event_source(mut self, source: Arc<dyn EventSource>) -> Self {
+        self.event_sources.push(source);
+        self
+    }
+
+    /// Don't watch the configured chains for events, relaying only those
+    /// emitted by added sources
+    pub fn without_chain_events(mut self) -> Self {
+        self.chain_events = false;
+        self
+    }
+
+    /// Hand proven events to `sink` instead of delivering them on chain
+    pub fn delivery_sink(mut self, sink: Arc<dyn DeliverySink>) -> Self {
+        self.delivery_sink = Some(sink);
+        self
+    }
+
+    /// Run `hook` on items passing through the pipeline, after any hooks
+    /// added before it
+    pub fn hook(mut self, hook: Arc<dyn PipelineHook>) -> Self {
+        self.hooks.push(hook);
+        self
+    }
+
+    /// Plugins the configuration can select stages from
+    ///
+    /// Stages set on the builder itself take precedence over those selected
+    /// in the configuration.
+    pub fn plugins(mut self, plugins: PluginRegistry) -> Self {
+        self.plugins = plugins;
+        self
+    }
+
+    /// Clock timing polls, retry backoffs, delivery deadlines and circuit
+    /// breaker cooldowns, in place of the system clock
+    pub fn clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = Some(clock);
+        self
+    }
+
+    pub fn build(self) -> Result<RelayerApp> {
+        RelayerApp::build(self)
+    }
+}
@@ -0,0 +1,113 @@
+use anyhow::Result;
+use ethers::{
+    abi::{self, ParamType, Token},
+    core::types::{Address, Bytes, U256},
+};
+use std::sync::Arc;
+use tracing::{debug, instrument};
+
+use crate::config::DeliveryMode;
+use crate::metrics::CCIP_LOOKUPS;
+use crate::status::EventStatus;
+use crate::store::StateStore;
+use crate::topology::Topology;
+use crate::types::{ChainId, RelayerError};
+
+/// ERC-3668 gateway for pairs in `ccip_read` delivery mode
+///
+/// The dapp reverts with `OffchainLookup(address(this), urls, callData, ...)`,
+/// where `callData` is [`CcipGateway::call_data`] for the message it wants.
+/// The caller fetches `{url}/{sender}/{callData}.json` or posts to `{url}`,
+/// and passes the proof in the response to the dapp's callback. Only events
+/// proven for a pair whose dapp is `sender` are served.
+#[derive(Clone)]
+pub struct CcipGateway {
+    store: Arc<dyn StateStore>,
+    topology: Topology,
+}
+
+impl CcipGateway {
+    pub fn new(store: Arc<dyn StateStore>, topology: Topology) -> Self {
+        Self { store, topology }
+    }
+
+    /// Lookup data a dapp puts in `OffchainLookup` for the message with
+    /// `nonce` from `source_chain_id`: `abi.encode(uint256, uint256)`
+    pub fn call_data(source_chain_id: ChainId, nonce: u64) -> Bytes {
+        abi::encode(&[
+            Token::Uint(source_chain_id.into()),
+            Token::Uint(nonce.into()),
+        ])
+        .into()
+    }
+
+    /// Proof of the message `data` asks `sender` about
+    #[instrument(skip(self, data), fields(?sender))]
+    pub fn lookup(&self, sender: Address, data: &[u8]) -> Result<Bytes> {
+        let result = self.find(sender, data);
+        let outcome = match &result {
+            Ok(_) => "served",
+            Err(e) => match e.downcast_ref::<RelayerError>() {
+                Some(RelayerError::ProofUnavailable { .. }) => "not_found",
+                _ => "invalid",
+            },
+        };
+        CCIP_LOOKUPS.with_label_values(&[outcome]).inc();
+        result
+    }
+
+    fn find(&self, sender: Address, data: &[u8]) -> Result<Bytes> {
+        let (source_chain_id, nonce) = decode_call_data(data)?;
+        let unavailable = || RelayerError::ProofUnavailable {
+            sender,
+            source_chain_id,
+            nonce,
+        };
+        // A dapp may sit at the same address on several destination chains
+        let pairs: Vec<String> = self
+            .topology
+            .pairs()
+            .into_iter()
+            .filter(|managed| {
+                let pair = &managed.pair;
+                pair.delivery_mode == DeliveryMode::CcipRead
+                    && pair.dest_dapp_address == sender
+                    && pair.source_chain_id == source_chain_id
+            })
+            .map(|managed| managed.id)
+            .collect();
+        if pairs.is_empty() {
+            return Err(unavailable().into());
+        }
+
+        let mut proven = Vec::new();
+        for pair in &pairs {
+            proven.extend(
+                self.store
+                    .events_at_nonce(pair, nonce)?
+                    .into_iter()
+                    .filter(|record| record.status == EventStatus::Proved),
+            );
+        }
+        for record in proven {
+            let proof = self.store.proof(&record.proof_key)?;
+            if let Some(proof) = proof.and_then(|record| record.proof) {
+                debug!(proof_key = %record.proof_key, "Serving proof");
+                return Ok(proof);
+            }
+        }
+        Err(unavailable().into())
+    }
+}
+
+fn decode_call_data(data: &[u8]) -> Result<(ChainId, u64), RelayerError> {
+    let invalid = |reason: &str| RelayerError::InvalidLookup(reason.to_string());
+    let tokens = abi::decode(&[ParamType::Uint(256), ParamType::Uint(256)], data)
+        .map_err(|_| invalid("call data is not abi.encode(uint256, uint256)"))?;
+    let word = |token: &Token| token.clone().into_uint().unwrap_or_default();
+    let (chain_id, nonce) = (word(&tokens[0]), word(&tokens[1]));
+    if chain_id > U256::from(u64::MAX) || nonce > U256::from(u64::MAX) {
+        return Err(invalid("chain id and nonce must fit in 64 bits"));
+    }
+    Ok((ChainId::new(chain_id.as_u64()), nonce.as_u64()))
+}
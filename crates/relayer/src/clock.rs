@@ -0,0 +1,89 @@
+use async_trait::async_trait;
+use std::{
+    sync::Arc,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+/// Exponential backoff after `failures` in a row: `initial_ms` after the
+/// first, doubling after each one since, capped at `max_ms`
+pub(crate) fn backoff(initial_ms: u64, max_ms: u64, failures: u32) -> Duration {
+    let doublings = failures.saturating_sub(1).min(32);
+    let delay = initial_ms.saturating_mul(1 << doublings);
+    Duration::from_millis(delay.min(max_ms))
+}
+
+/// Source of time for the pipeline's polling intervals, retry backoffs,
+/// delivery deadlines and circuit breaker cooldowns
+///
+/// The relayer runs on [`SystemClock`]; tests can hand
+/// [`RelayerBuilder::clock`] one they advance themselves, so time-based logic
+/// runs without real sleeps.
+///
+/// [`RelayerBuilder::clock`]: crate::RelayerBuilder::clock
+#[async_trait]
+pub trait Clock: Send + Sync {
+    /// Monotonic time, for measuring how long something took or waits
+    fn now(&self) -> Instant;
+
+    /// Wall-clock time, for timestamps that are stored or compared across
+    /// restarts
+    fn system_time(&self) -> SystemTime;
+
+    /// Wait until `duration` has passed on this clock
+    async fn sleep(&self, duration: Duration);
+
+    /// Seconds since the Unix epoch
+    fn unix_time(&self) -> u64 {
+        self.system_time()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or_default()
+    }
+}
+
+/// The real clock, sleeping on the Tokio timer
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+#[async_trait]
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn system_time(&self) -> SystemTime {
+        SystemTime::now()
+    }
+
+    async fn sleep(&self, duration: Duration) {
+        tokio::time::sleep(duration).await
+    }
+}
+
+/// Periodic ticks on a [`Clock`], like `tokio::time::interval`: the first
+/// tick completes at once and each later one a period after the one before
+pub(crate) struct Ticker {
+    clock: Arc<dyn Clock>,
+    period: Duration,
+    next: Option<Instant>,
+}
+
+impl Ticker {
+    pub(crate) fn new(clock: Arc<dyn Clock>, period: Duration) -> Self {
+        Self {
+            clock,
+            period,
+            next: None,
+        }
+    }
+
+    pub(crate) async fn tick(&mut self) {
+        if let Some(next) = self.next {
+            let wait = next.saturating_duration_since(self.clock.now());
+            if !wait.is_zero() {
+                self.clock.sleep(wait).await;
+            }
+        }
+        self.next = Some(self.clock.now() + self.period);
+    }
+}
@@ -1,28 +1,1912 @@
-use serde::Serialize;
-use std::collections::HashMap;
+use ethers::core::types::Address;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+
+use crate::types::{ChainId, RelayEvent, RelayerError, SolanaPubkey};
 
 // Chain configuration
-#[derive(Debug, Serialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
 pub struct ChainConfig {
     pub name: String,
-    pub chain_id: u64,
+    pub chain_id: ChainId,
     pub rpc_url: String,
+    /// Transaction type for deliveries; `auto` probes the chain at startup
+    pub transaction_type: TransactionType,
+    /// Same as `transaction_type: legacy`, kept for existing configs
+    pub legacy_transactions: bool,
+    /// EIP-1559 priority tip; the node's suggestion is used if unset
+    pub priority_fee_wei: Option<u64>,
+    /// MEV-protected endpoint (e.g. Flashbots Protect) deliveries are broadcast through
+    pub private_rpc_url: Option<String>,
+    /// Multicall3 deployment used for batched deliveries, if not at the canonical address
+    pub multicall_address: Option<Address>,
+    /// Deliveries in flight to this chain at once, unbounded if unset; 1 serializes them
+    pub max_concurrent_deliveries: Option<usize>,
+    /// Blocks a delivery must be buried under before it counts as final; 0 or 1 accepts inclusion
+    pub confirmations: u64,
+    /// Executor contract every delivery on this chain is routed through via
+    /// `executeWithProof(address target, bytes payload, bytes proof)`
+    pub executor_address: Option<Address>,
+    /// Hard ceiling on the gas limit of any delivery on this chain
+    pub max_gas_limit: Option<u64>,
+    /// Balance below which a delivery wallet stops being used on this chain
+    pub min_balance_wei: Option<u128>,
+    /// Attach an `eth_createAccessList` access list to deliveries when it lowers their gas
+    pub access_lists: bool,
+    /// Rollup family, for the gas and fee quirks deliveries need to account for
+    pub kind: ChainKind,
+    /// Symbol of the token the chain charges gas in, e.g. `POL`; `ETH` if unset
+    pub native_token: Option<String>,
+    /// Gas added to every estimated delivery gas limit after the multiplier,
+    /// for chains whose estimates run tight (e.g. zk rollups pricing pubdata)
+    pub extra_gas: u64,
+    /// Private keys of extra funded wallets deliveries on this chain are spread
+    /// across, alongside the main signer; never written out
+    #[serde(skip_serializing)]
+    pub wallet_keys: Vec<String>,
+    /// Deliver as ERC-4337 user operations from this smart account instead of plain transactions
+    pub smart_account: Option<SmartAccountConfig>,
+}
+
+impl ChainConfig {
+    /// Token the chain charges gas in
+    pub fn native_token(&self) -> &str {
+        self.native_token.as_deref().unwrap_or("ETH")
+    }
+}
+
+// Canonical EntryPoint v0.6 deployment
+const ENTRY_POINT_V06: &str = "0x5FF137D4b0FDCD49DcA30c7CF57E578a026d2789";
+
+// ERC-4337 account owned by the relayer signer
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct SmartAccountConfig {
+    /// Deployed account exposing `execute(address,uint256,bytes)`
+    pub address: Address,
+    pub bundler_url: String,
+    /// EntryPoint v0.6 contract the account and bundler use
+    pub entry_point: Address,
+    /// Paymaster service implementing `pm_sponsorUserOperation`, for sponsored pairs
+    pub paymaster_url: Option<String>,
+}
+
+impl Default for SmartAccountConfig {
+    fn default() -> Self {
+        Self {
+            address: Address::zero(),
+            bundler_url: String::new(),
+            entry_point: ENTRY_POINT_V06
+                .parse()
+                .expect("EntryPoint v0.6 address is valid"),
+            paymaster_url: None,
+        }
+    }
+}
+
+// Rollup family of a chain
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ChainKind {
+    #[default]
+    Standard,
+    /// Gas estimates include the L1 calldata cost in L2 gas units
+    Arbitrum,
+    /// Receipts report an L1 data fee on top of the L2 gas cost
+    OpStack,
+}
+
+// Fee market a chain's deliveries are priced for
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TransactionType {
+    /// EIP-1559 if the latest block carries a base fee, legacy otherwise
+    #[default]
+    Auto,
+    Legacy,
+    Eip1559,
 }
 
 // Source-destination pair for relaying
-#[derive(Debug, Serialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
 pub struct RelayPair {
-    pub source_chain_id: u64,
-    pub source_resolver_address: String,
-    pub dest_chain_id: u64,
-    pub dest_dapp_address: String,
+    pub source_chain_id: ChainId,
+    pub source_resolver_address: Address,
+    pub dest_chain_id: ChainId,
+    pub dest_dapp_address: Address,
+    /// Upper bound on the gas limit of a delivery for this pair
+    pub max_gas_limit: Option<u64>,
+    /// Fixed gas limit for deliveries, used instead of estimating
+    pub delivery_gas_limit: Option<u64>,
+    /// Deliver through an ERC-2771 trusted forwarder instead of calling the dapp directly
+    pub forwarder: Option<ForwarderConfig>,
+    /// How to ask the dapp whether a nonce was already executed before delivering it
+    pub executed_check: Option<ExecutedCheck>,
+    /// Time after detection by which a delivery must be confirmed, no deadline
+    /// if unset. Detection times are kept to the second, so it must be a
+    /// whole number of seconds.
+    pub delivery_deadline_ms: Option<u64>,
+    /// Dapp function taking the message nonce to call once a delivery
+    /// expires, e.g. `cancel(uint256)`
+    pub cancel_function: Option<String>,
+    /// How the exec payload and proof are laid out in the delivery calldata
+    pub call_encoding: CallEncoding,
+    /// Have the destination chain's paymaster pay for deliveries instead of the relayer
+    pub sponsored: bool,
+    /// Deliver one event at a time in ascending nonce order, for dapps that
+    /// only accept messages in sequence
+    pub ordered: bool,
+    /// Events of higher-priority pairs are proven and delivered first when
+    /// the stages are busy; pairs default to 0
+    pub priority: u8,
+    /// How messages reach the destination: with a Polymer proof, or natively
+    /// between chains of the same OP superchain
+    pub relay_mode: RelayMode,
+    /// Whether the relayer sends deliveries itself or leaves them to callers
+    /// fetching proofs from its CCIP-Read gateway
+    pub delivery_mode: DeliveryMode,
+    /// Deliver through the destination's Hyperlane Mailbox, for dapps that
+    /// receive messages from it and verify the proof in their ISM
+    pub hyperlane: Option<HyperlaneConfig>,
+    /// Resolver view function taking the destination chain ID and returning
+    /// the reward for relaying its pending request in wei, e.g.
+    /// `executionReward(uint32)`; requests of pairs with one are only relayed
+    /// if the reward covers what relaying them costs
+    pub reward_function: Option<String>,
+    /// Relay every request of the pair whatever it pays
+    pub always_relay: bool,
+}
+
+// Way a pair's messages are carried to the destination chain
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RelayMode {
+    /// Prove the resolver's request log with the proof backend and call the
+    /// dapp with the proof
+    #[default]
+    Polymer,
+    /// The resolver sends the message through the source chain's
+    /// L2ToL2CrossDomainMessenger, and deliveries relay it through the
+    /// destination chain's; no proof is fetched. Both chains must be OP stack
+    /// chains in the same interop set.
+    OpInterop,
+    /// Fill ERC-7683 orders: the resolver address is the origin settler,
+    /// whose `Open` events the intent source picks up, and deliveries call
+    /// `fill` on the destination settler at the dapp address with the proof
+    /// of the `Open` event as filler data
+    Erc7683,
+}
+
+// Who completes a pair's deliveries on the destination chain
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryMode {
+    /// The relayer sends a transaction for every proven event
+    #[default]
+    Push,
+    /// Proven events are kept in the store and served through the ERC-3668
+    /// gateway; the dapp reverts with `OffchainLookup` and its caller
+    /// completes the delivery with the proof
+    CcipRead,
+}
+
+// Layout of the calldata a delivery sends to the destination dapp
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CallEncoding {
+    /// The exec payload followed directly by the proof bytes
+    #[default]
+    Concat,
+    /// Call a function taking the payload and the proof as two `bytes`
+    /// arguments, e.g. `execute(bytes,bytes)`
+    Function { signature: String },
+    /// Decode the payload as a call to `signature` minus its last argument, then
+    /// re-encode it with the proof as that final `bytes` argument, e.g.
+    /// `execute(uint256,bytes,bytes)` for a payload calling `execute(uint256,bytes)`
+    AppendProof { signature: String },
+}
+
+// Destination-side lookup of whether a message nonce has been executed
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ExecutedCheck {
+    /// Call a view function taking the nonce and returning a bool, e.g. `isExecuted(uint256)`
+    ViewCall { signature: String },
+    /// Read a `mapping(uint256 => ...)` at this storage slot; non-zero means executed
+    StorageMapping { slot: u64 },
+}
+
+// ERC-2771 forwarder a pair's deliveries are routed through
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ForwarderConfig {
+    pub address: Address,
+    /// EIP-712 domain name the forwarder verifies signatures under
+    pub domain_name: String,
+    /// EIP-712 domain version the forwarder verifies signatures under
+    pub domain_version: String,
+}
+
+// Hyperlane Mailbox a pair's deliveries are processed by
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct HyperlaneConfig {
+    pub mailbox: Address,
+    /// Hyperlane domain of the source chain, its chain id if unset
+    pub origin_domain: Option<u32>,
+    /// Hyperlane domain of the destination chain, its chain id if unset
+    pub destination_domain: Option<u32>,
+}
+
+impl HyperlaneConfig {
+    pub fn new(mailbox: Address) -> Self {
+        Self {
+            mailbox,
+            ..Self::default()
+        }
+    }
+}
+
+impl ForwarderConfig {
+    pub fn new(address: Address) -> Self {
+        Self {
+            address,
+            domain_name: "MinimalForwarder".to_string(),
+            domain_version: "0.0.1".to_string(),
+        }
+    }
+}
+
+impl RelayPair {
+    /// Identifier for the pair in metrics and spend records
+    pub fn id(&self) -> String {
+        pair_id(
+            self.source_chain_id,
+            &self.source_resolver_address,
+            self.dest_chain_id,
+            &self.dest_dapp_address,
+        )
+    }
+
+    /// Whether an event was produced by this pair
+    pub fn matches(&self, event: &RelayEvent) -> bool {
+        self.source_chain_id == event.source_chain.chain_id
+            && self.dest_chain_id == event.destination_chain.chain_id
+            && self.source_resolver_address == event.source_resolver_address
+            && self.dest_dapp_address == event.dest_dapp_address
+    }
+
+    /// Pair from the resolver on the source chain to the dapp on the
+    /// destination chain, with the rest of its settings left to the builder
+    pub fn builder(
+        source_chain_id: ChainId,
+        source_resolver_address: impl Into<AddressInput>,
+        dest_chain_id: ChainId,
+        dest_dapp_address: impl Into<AddressInput>,
+    ) -> RelayPairBuilder {
+        RelayPairBuilder {
+            source_chain_id,
+            source_resolver_address: source_resolver_address.into(),
+            dest_chain_id,
+            dest_dapp_address: dest_dapp_address.into(),
+            pair: Self::default(),
+        }
+    }
+
+    // Problems a pair has on its own, whatever chains are configured
+    pub(crate) fn validate(&self) -> Result<(), RelayerError> {
+        let id = self.id();
+        if self.source_resolver_address.is_zero() {
+            return Err(invalid(format!("pair {} has a zero resolver address", id)));
+        }
+        if self.dest_dapp_address.is_zero() {
+            return Err(invalid(format!("pair {} has a zero dapp address", id)));
+        }
+        if self
+            .forwarder
+            .as_ref()
+            .is_some_and(|forwarder| forwarder.address.is_zero())
+        {
+            return Err(invalid(format!("pair {} has a zero forwarder address", id)));
+        }
+        if self.relay_mode == RelayMode::OpInterop && self.forwarder.is_some() {
+            return Err(invalid(format!(
+                "pair {} relays through the interop messenger and cannot use a forwarder",
+                id
+            )));
+        }
+        if self.delivery_mode == DeliveryMode::CcipRead && self.forwarder.is_some() {
+            return Err(invalid(format!(
+                "pair {} leaves deliveries to CCIP-Read callers and cannot use a forwarder",
+                id
+            )));
+        }
+        if self
+            .delivery_deadline_ms
+            .is_some_and(|deadline_ms| deadline_ms == 0 || deadline_ms % 1_000 != 0)
+        {
+            return Err(invalid(format!(
+                "pair {} delivery deadline must be a whole number of seconds",
+                id
+            )));
+        }
+        if self.reward_function.is_some() && self.relay_mode == RelayMode::Erc7683 {
+            return Err(invalid(format!(
+                "pair {} fills intents, which pay no resolver reward",
+                id
+            )));
+        }
+        if let Some(hyperlane) = &self.hyperlane {
+            if hyperlane.mailbox.is_zero() {
+                return Err(invalid(format!("pair {} has a zero mailbox address", id)));
+            }
+            if self.relay_mode != RelayMode::Polymer || self.delivery_mode != DeliveryMode::Push {
+                return Err(invalid(format!(
+                    "pair {} delivers through a Hyperlane mailbox and must push Polymer proofs",
+                    id
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    // Problems a pair has with the chains it runs between
+    pub(crate) fn validate_chains(
+        &self,
+        source: &ChainConfig,
+        dest: &ChainConfig,
+    ) -> Result<(), RelayerError> {
+        // The reward and both chains' costs are summed in wei, which only
+        // means something if they are all the same token
+        if self.reward_function.is_some()
+            && !self.always_relay
+            && !source
+                .native_token()
+                .eq_ignore_ascii_case(dest.native_token())
+        {
+            return Err(invalid(format!(
+                "pair {} is priced by its reward, but chain {} pays gas in {} and chain {} in {}",
+                self.id(),
+                source.chain_id,
+                source.native_token(),
+                dest.chain_id,
+                dest.native_token()
+            )));
+        }
+        if self.relay_mode != RelayMode::OpInterop {
+            return Ok(());
+        }
+        for chain in [source, dest] {
+            if chain.kind != ChainKind::OpStack {
+                return Err(invalid(format!(
+                    "pair {} relays natively over OP interop, but chain {} is not an OP stack chain",
+                    self.id(),
+                    chain.chain_id
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// An address given to a config builder, either parsed already or as hex
+/// parsed when the builder is built
+#[derive(Debug, Clone)]
+pub enum AddressInput {
+    Parsed(Address),
+    Hex(String),
+}
+
+impl AddressInput {
+    fn parse(self, what: &str) -> Result<Address, RelayerError> {
+        match self {
+            Self::Parsed(address) => Ok(address),
+            Self::Hex(hex) => hex
+                .parse()
+                .map_err(|_| invalid(format!("{} {:?} is not a valid address", what, hex))),
+        }
+    }
+}
+
+impl From<Address> for AddressInput {
+    fn from(address: Address) -> Self {
+        Self::Parsed(address)
+    }
+}
+
+impl From<&str> for AddressInput {
+    fn from(hex: &str) -> Self {
+        Self::Hex(hex.to_string())
+    }
+}
+
+impl From<String> for AddressInput {
+    fn from(hex: String) -> Self {
+        Self::Hex(hex)
+    }
+}
+
+/// Builds a [`RelayPair`], checking its addresses when built
+#[derive(Debug, Clone)]
+pub struct RelayPairBuilder {
+    source_chain_id: ChainId,
+    source_resolver_address: AddressInput,
+    dest_chain_id: ChainId,
+    dest_dapp_address: AddressInput,
+    pair: RelayPair,
+}
+
+impl RelayPairBuilder {
+    pub fn max_gas_limit(mut self, gas: u64) -> Self {
+        self.pair.max_gas_limit = Some(gas);
+        self
+    }
+
+    pub fn delivery_gas_limit(mut self, gas: u64) -> Self {
+        self.pair.delivery_gas_limit = Some(gas);
+        self
+    }
+
+    pub fn forwarder(mut self, forwarder: ForwarderConfig) -> Self {
+        self.pair.forwarder = Some(forwarder);
+        self
+    }
+
+    pub fn executed_check(mut self, check: ExecutedCheck) -> Self {
+        self.pair.executed_check = Some(check);
+        self
+    }
+
+    pub fn delivery_deadline_ms(mut self, deadline_ms: u64) -> Self {
+        self.pair.delivery_deadline_ms = Some(deadline_ms);
+        self
+    }
+
+    pub fn cancel_function(mut self, signature: impl Into<String>) -> Self {
+        self.pair.cancel_function = Some(signature.into());
+        self
+    }
+
+    pub fn call_encoding(mut self, encoding: CallEncoding) -> Self {
+        self.pair.call_encoding = encoding;
+        self
+    }
+
+    pub fn sponsored(mut self, sponsored: bool) -> Self {
+        self.pair.sponsored = sponsored;
+        self
+    }
+
+    pub fn ordered(mut self, ordered: bool) -> Self {
+        self.pair.ordered = ordered;
+        self
+    }
+
+    pub fn priority(mut self, priority: u8) -> Self {
+        self.pair.priority = priority;
+        self
+    }
+
+    pub fn relay_mode(mut self, mode: RelayMode) -> Self {
+        self.pair.relay_mode = mode;
+        self
+    }
+
+    pub fn delivery_mode(mut self, mode: DeliveryMode) -> Self {
+        self.pair.delivery_mode = mode;
+        self
+    }
+
+    pub fn hyperlane(mut self, hyperlane: HyperlaneConfig) -> Self {
+        self.pair.hyperlane = Some(hyperlane);
+        self
+    }
+
+    pub fn reward_function(mut self, signature: impl Into<String>) -> Self {
+        self.pair.reward_function = Some(signature.into());
+        self
+    }
+
+    pub fn always_relay(mut self, always_relay: bool) -> Self {
+        self.pair.always_relay = always_relay;
+        self
+    }
+
+    /// The pair, or an error if an address is malformed or zero
+    pub fn build(self) -> Result<RelayPair, RelayerError> {
+        let pair = RelayPair {
+            source_chain_id: self.source_chain_id,
+            source_resolver_address: self.source_resolver_address.parse("resolver address")?,
+            dest_chain_id: self.dest_chain_id,
+            dest_dapp_address: self.dest_dapp_address.parse("dapp address")?,
+            ..self.pair
+        };
+        pair.validate()?;
+        Ok(pair)
+    }
+}
+
+fn invalid(problem: String) -> RelayerError {
+    RelayerError::InvalidConfig(problem)
+}
+
+// Identifier of the pair between a resolver and a dapp, with both addresses
+// as full lowercase hex
+pub(crate) fn pair_id(
+    source_chain_id: ChainId,
+    source_resolver_address: &Address,
+    dest_chain_id: ChainId,
+    dest_dapp_address: &Address,
+) -> String {
+    format!(
+        "{}:{:?}->{}:{:?}",
+        source_chain_id, source_resolver_address, dest_chain_id, dest_dapp_address
+    )
+}
+
+// How to obtain a new Polymer API token once the current one expires
+#[derive(Debug, Serialize, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TokenRefreshConfig {
+    /// GET the URL; the body is either `{"token": "..."}` or the bare token
+    Endpoint { url: String },
+    /// Run a command and use its trimmed stdout as the token
+    Command { program: String, args: Vec<String> },
+}
+
+// Encoding of the proof string returned by the proof API
+#[derive(Debug, Serialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ProofEncoding {
+    /// Hex if `0x`-prefixed, base64 otherwise
+    #[default]
+    Auto,
+    Base64,
+    Hex,
+}
+
+// Polymer proof API connection settings
+#[derive(Debug, Serialize, Clone)]
+pub struct PolymerApiConfig {
+    pub endpoint: String,
+    /// Replicas to fail over to when the primary endpoint is unhealthy
+    pub fallback_endpoints: Vec<String>,
+    pub token: String,
+    /// Budget for proof API calls shared by all in-flight requests, unlimited if unset
+    pub requests_per_minute: Option<u32>,
+    /// Used to replace the token when the API answers 401
+    pub token_refresh: Option<TokenRefreshConfig>,
+    pub connect_timeout_ms: u64,
+    /// Deadline for a whole API call, including reading the response
+    pub request_timeout_ms: u64,
+    pub proof_encoding: ProofEncoding,
+    /// Combine concurrent proof requests and status queries into JSON-RPC
+    /// batch calls, one call each at a time if unset
+    pub batch: Option<ProofBatchConfig>,
+}
+
+impl Default for PolymerApiConfig {
+    fn default() -> Self {
+        Self {
+            endpoint: "https://api.polymer.zone/v1/proofs".to_string(),
+            fallback_endpoints: Vec::new(),
+            token: String::new(),
+            requests_per_minute: None,
+            token_refresh: None,
+            connect_timeout_ms: 5_000,
+            request_timeout_ms: 30_000,
+            proof_encoding: ProofEncoding::default(),
+            batch: None,
+        }
+    }
+}
+
+// Grouping of proof API calls into batch calls
+//
+// Only calls made while others are in flight can share a batch, so the
+// proof fetcher's `max_concurrent_fetches` bounds how large batches get.
+#[derive(Debug, Serialize, Clone)]
+pub struct ProofBatchConfig {
+    /// How long the first call waits for others to join it
+    pub window_ms: u64,
+    /// Batch size that triggers sending before the window ends
+    pub max_size: usize,
+}
+
+impl Default for ProofBatchConfig {
+    fn default() -> Self {
+        Self {
+            window_ms: 100,
+            max_size: 50,
+        }
+    }
+}
+
+// Settings for the local mock proof provider
+#[derive(Debug, Serialize, Clone)]
+pub struct MockProofConfig {
+    /// Simulated proof generation time
+    pub latency_ms: u64,
+    /// Fraction of proof requests that fail, between 0.0 and 1.0
+    pub failure_rate: f64,
+}
+
+impl Default for MockProofConfig {
+    fn default() -> Self {
+        Self {
+            latency_ms: 500,
+            failure_rate: 0.0,
+        }
+    }
+}
+
+// Which backend produces proofs
+#[derive(Debug, Serialize, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ProofBackendConfig {
+    Polymer(PolymerApiConfig),
+    Mock(MockProofConfig),
+}
+
+// Circuit breaker settings for the proof stage
+#[derive(Debug, Serialize, Clone)]
+pub struct CircuitBreakerConfig {
+    /// Consecutive proof failures before the breaker opens
+    pub failure_threshold: u32,
+    /// How long to stop issuing proof requests once open
+    pub cooldown_ms: u64,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 5,
+            cooldown_ms: 60_000,
+        }
+    }
+}
+
+// Proof stage settings
+#[derive(Debug, Serialize, Clone)]
+pub struct ProofFetcherConfig {
+    pub circuit_breaker: CircuitBreakerConfig,
+    /// Upper bound on proof fetches running at the same time
+    pub max_concurrent_fetches: usize,
+    /// Reject proofs whose encoded log location doesn't match the event
+    pub validate_proofs: bool,
+}
+
+impl Default for ProofFetcherConfig {
+    fn default() -> Self {
+        Self {
+            circuit_breaker: CircuitBreakerConfig::default(),
+            max_concurrent_fetches: 16,
+            validate_proofs: true,
+        }
+    }
+}
+
+// Delivery stage settings
+#[derive(Debug, Serialize, Clone)]
+pub struct DeliveryConfig {
+    /// Factor applied to the node's gas estimate for delivery transactions
+    pub gas_multiplier: f64,
+    /// How long a delivery may stay unmined before it is re-sent with higher
+    /// fees, and how long the destination head may stand still while a mined
+    /// delivery awaits its confirmations
+    pub stuck_timeout_ms: u64,
+    /// Percentage each replacement raises the fees by
+    pub fee_bump_percent: u64,
+    /// Fee cap (or gas price) past which a stuck delivery is no longer bumped
+    pub max_fee_per_gas_wei: u64,
+    /// Dry-run each delivery with `eth_call` and skip it if it would revert
+    pub simulate_deliveries: bool,
+    /// Batch deliveries to the same chain into Multicall3 transactions, off if unset
+    pub batch: Option<BatchConfig>,
+    /// How often the signer balance is checked on every chain
+    pub balance_check_interval_ms: u64,
+    /// How long another instance's unconfirmed claim on a delivery is
+    /// honoured before it is taken over
+    pub claim_timeout_ms: u64,
+    pub retry: RetryConfig,
+    pub gas_tank: GasTankConfig,
+}
+
+// Projection of how long each delivery wallet's balance lasts
+#[derive(Debug, Serialize, Clone)]
+pub struct GasTankConfig {
+    /// Trailing window whose gas spend sets the spend rate
+    pub spend_window_ms: u64,
+    /// Alert when a wallet is projected to run dry within this many days,
+    /// never alerted on if unset
+    pub min_runway_days: Option<f64>,
+}
+
+impl Default for GasTankConfig {
+    fn default() -> Self {
+        Self {
+            spend_window_ms: 86_400_000,
+            min_runway_days: None,
+        }
+    }
+}
+
+impl GasTankConfig {
+    fn validate(&self) -> Result<(), RelayerError> {
+        // Spend is recorded to the second
+        if self.spend_window_ms < 1_000 {
+            return Err(invalid(
+                "gas tank spend_window_ms must be at least 1000".to_string(),
+            ));
+        }
+        if self
+            .min_runway_days
+            .is_some_and(|days| days.is_nan() || days <= 0.0)
+        {
+            return Err(invalid(
+                "gas tank min_runway_days must be above 0".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+// Retry schedule for failed deliveries
+#[derive(Debug, Serialize, Clone)]
+pub struct RetryConfig {
+    /// Delivery attempts, the first included, before an event is dead-lettered
+    pub max_attempts: u32,
+    /// Wait before the first retry; doubled for each one after it
+    pub initial_backoff_ms: u64,
+    /// Upper bound on the wait between retries
+    pub max_backoff_ms: u64,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            initial_backoff_ms: 15_000,
+            max_backoff_ms: 3_600_000,
+        }
+    }
+}
+
+// Grouping of deliveries into Multicall3 batches
+#[derive(Debug, Serialize, Clone)]
+pub struct BatchConfig {
+    /// How long the first delivery to a chain waits for others to join it
+    pub window_ms: u64,
+    /// Batch size that triggers sending before the window ends
+    pub max_size: usize,
+}
+
+impl Default for BatchConfig {
+    fn default() -> Self {
+        Self {
+            window_ms: 2_000,
+            max_size: 20,
+        }
+    }
+}
+
+impl Default for DeliveryConfig {
+    fn default() -> Self {
+        Self {
+            gas_multiplier: 1.2,
+            stuck_timeout_ms: 120_000,
+            fee_bump_percent: 20,
+            max_fee_per_gas_wei: 500_000_000_000,
+            simulate_deliveries: true,
+            batch: None,
+            balance_check_interval_ms: 60_000,
+            claim_timeout_ms: 600_000,
+            retry: RetryConfig::default(),
+            gas_tank: GasTankConfig::default(),
+        }
+    }
+}
+
+// Restarting of pipeline components that fail
+#[derive(Debug, Serialize, Clone)]
+pub struct SupervisorConfig {
+    /// Restarts in a row, without a healthy run in between, before the relayer gives up
+    pub max_restarts: u32,
+    /// Wait before the first restart; doubled for each one after it
+    pub initial_backoff_ms: u64,
+    /// Upper bound on the wait between restarts
+    pub max_backoff_ms: u64,
+    /// How long the pipeline has to run before its earlier failures are forgotten
+    pub healthy_after_ms: u64,
+}
+
+impl Default for SupervisorConfig {
+    fn default() -> Self {
+        Self {
+            max_restarts: 5,
+            initial_backoff_ms: 1_000,
+            max_backoff_ms: 60_000,
+            healthy_after_ms: 300_000,
+        }
+    }
+}
+
+// Thresholds behind the health endpoints
+#[derive(Debug, Serialize, Clone)]
+pub struct HealthConfig {
+    /// How long a component may go without progress while work is queued for it
+    pub stall_timeout_ms: u64,
+    /// How often every chain's RPC endpoint is checked
+    pub rpc_check_interval_ms: u64,
+    /// How long an RPC check waits for an answer
+    pub rpc_timeout_ms: u64,
+    /// How often a summary of throughput and lag is logged, off if unset
+    pub report_interval_ms: Option<u64>,
+}
+
+impl Default for HealthConfig {
+    fn default() -> Self {
+        Self {
+            stall_timeout_ms: 300_000,
+            rpc_check_interval_ms: 30_000,
+            rpc_timeout_ms: 5_000,
+            report_interval_ms: Some(300_000),
+        }
+    }
+}
+
+// Queues between pipeline stages and what happens when they fill up
+#[derive(Debug, Serialize, Clone)]
+pub struct BackpressureConfig {
+    /// Detected events waiting for a proof fetch
+    pub event_queue_capacity: usize,
+    /// Proven events waiting for delivery
+    pub delivery_queue_capacity: usize,
+    /// Park proven events in the state store while the delivery queue is full,
+    /// rather than holding a fetch slot until there is room
+    pub spill_to_store: bool,
+    /// How full, from 0 to 1, a queue may get before the event generator
+    /// skips polls to let downstream stages catch up
+    pub slow_down_threshold: f64,
+}
+
+impl Default for BackpressureConfig {
+    fn default() -> Self {
+        Self {
+            event_queue_capacity: 100,
+            delivery_queue_capacity: 100,
+            spill_to_store: false,
+            slow_down_threshold: 0.8,
+        }
+    }
+}
+
+// Active/standby failover between instances running the same configuration
+#[derive(Debug, Serialize, Clone)]
+pub struct LeaderElectionConfig {
+    /// Redis instance holding the leader lease, e.g. `redis://redis:6379`
+    pub redis_url: String,
+    /// Key the lease is kept under; instances sharing it elect one leader
+    pub lock_key: String,
+    /// How long a lease lasts without renewal, and so how long a standby
+    /// waits to take over from a leader that stopped
+    pub lease_ms: u64,
+    /// How often the leader renews its lease and a standby tries to take it
+    pub renew_interval_ms: u64,
+}
+
+impl Default for LeaderElectionConfig {
+    fn default() -> Self {
+        Self {
+            redis_url: "redis://127.0.0.1:6379".to_string(),
+            lock_key: "relayer:leader".to_string(),
+            lease_ms: 15_000,
+            renew_interval_ms: 5_000,
+        }
+    }
+}
+
+impl LeaderElectionConfig {
+    // A leader steps down one renewal before its lease runs out, so the lease
+    // must outlast at least two renewals for it to ever renew in time
+    fn validate(&self) -> Result<(), RelayerError> {
+        if self.renew_interval_ms == 0 || self.lease_ms <= 2 * self.renew_interval_ms {
+            return Err(invalid(format!(
+                "leader election lease_ms ({}) must be above twice renew_interval_ms ({})",
+                self.lease_ms, self.renew_interval_ms
+            )));
+        }
+        Ok(())
+    }
+}
+
+// Splitting relay pairs between instances that share a configuration
+#[derive(Debug, Serialize, Clone)]
+pub struct ShardingConfig {
+    /// This instance's shard, from 0 to `shard_count - 1`
+    pub shard_index: u32,
+    pub shard_count: u32,
+    /// Shards pinned to pair IDs; other pairs go to the shard their ID hashes to
+    pub assignments: HashMap<String, u32>,
+}
+
+impl Default for ShardingConfig {
+    fn default() -> Self {
+        Self {
+            shard_index: 0,
+            shard_count: 1,
+            assignments: HashMap::new(),
+        }
+    }
+}
+
+// Every event status change, published as JSON for downstream consumers
+#[derive(Debug, Serialize, Clone)]
+pub struct EventStreamConfig {
+    pub broker: StreamBroker,
+    /// Status changes held while the broker is unreachable; the oldest are
+    /// dropped beyond this
+    pub buffer: usize,
+    /// Most status changes sent to the broker at once
+    pub max_batch: usize,
+    /// Deadline for connecting to the broker and for it to take a batch
+    pub timeout_ms: u64,
+    /// Wait before reconnecting after the broker failed a batch
+    pub reconnect_backoff_ms: u64,
+}
+
+impl Default for EventStreamConfig {
+    fn default() -> Self {
+        Self {
+            broker: StreamBroker::Nats {
+                url: "nats://127.0.0.1:4222".to_string(),
+                subject: "relayer.events".to_string(),
+                token: None,
+            },
+            buffer: 10_000,
+            max_batch: 500,
+            timeout_ms: 5_000,
+            reconnect_backoff_ms: 1_000,
+        }
+    }
+}
+
+impl EventStreamConfig {
+    fn validate(&self) -> Result<(), RelayerError> {
+        if self.buffer == 0 || self.max_batch == 0 {
+            return Err(invalid(
+                "event stream buffer and max_batch must be above 0".to_string(),
+            ));
+        }
+        match &self.broker {
+            StreamBroker::Nats { url, subject, .. } => {
+                if !url.starts_with("nats://") {
+                    return Err(invalid(format!(
+                        "event stream URL {:?} is not nats://",
+                        url
+                    )));
+                }
+                if subject.is_empty() || subject.contains(char::is_whitespace) {
+                    return Err(invalid(format!(
+                        "invalid event stream subject {:?}",
+                        subject
+                    )));
+                }
+            }
+            StreamBroker::Kafka { broker, topic, .. } => {
+                if broker.is_empty() || topic.is_empty() {
+                    return Err(invalid(
+                        "event stream needs a Kafka broker and topic".to_string(),
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+// Broker the event stream publishes to, over plain TCP
+#[derive(Debug, Serialize, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StreamBroker {
+    /// NATS server such as `nats://nats:4222`, publishing on `subject`
+    Nats {
+        url: String,
+        subject: String,
+        #[serde(skip_serializing)]
+        token: Option<String>,
+    },
+    /// Kafka broker such as `kafka:9092` leading `partition` of `topic`,
+    /// publishing with the event id as the key
+    Kafka {
+        broker: String,
+        topic: String,
+        partition: i32,
+    },
+}
+
+// Where an alert webhook posts, and the payload shape it expects
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum WebhookKind {
+    /// Slack incoming webhook, posting `{"text": ...}`
+    Slack,
+    /// Discord webhook, posting `{"content": ...}`
+    Discord,
+    /// PagerDuty Events API v2, deduplicated by alert kind and subject
+    PagerDuty {
+        #[serde(skip_serializing)]
+        routing_key: String,
+    },
+    /// Any endpoint accepting the alert itself as JSON
+    Generic,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct WebhookConfig {
+    /// Never written out, since webhook URLs carry their own credentials
+    #[serde(skip_serializing)]
+    pub url: String,
+    pub kind: WebhookKind,
+}
+
+// Notifications sent when the relayer needs an operator's attention
+#[derive(Debug, Serialize, Clone)]
+pub struct AlertConfig {
+    /// Every alert goes to each of these; alerts are only logged if empty
+    pub webhooks: Vec<WebhookConfig>,
+    /// Repeats of the same alert within this window are held back and counted
+    /// in the next one sent
+    pub throttle_ms: u64,
+    /// Proof fetches failing in a row for one source chain before alerting
+    pub proof_failure_threshold: u32,
+    /// How often pipeline components are checked for stalls
+    pub stall_check_interval_ms: u64,
+}
+
+impl Default for AlertConfig {
+    fn default() -> Self {
+        Self {
+            webhooks: Vec::new(),
+            throttle_ms: 900_000,
+            proof_failure_threshold: 5,
+            stall_check_interval_ms: 30_000,
+        }
+    }
+}
+
+// Append-only log of detected events, replayed after a crash
+#[derive(Debug, Serialize, Clone)]
+pub struct JournalConfig {
+    pub enabled: bool,
+    /// Flush each entry to disk before carrying on, so even a power loss
+    /// cannot drop a detected event
+    pub fsync: bool,
+}
+
+impl Default for JournalConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            fsync: true,
+        }
+    }
+}
+
+// Hash-chained record of everything the relayer did, for compliance and
+// dispute resolution
+#[derive(Debug, Serialize, Clone)]
+pub struct AuditConfig {
+    /// Defaults to `audit.log` in the state dir
+    pub path: Option<PathBuf>,
+    /// Flush each entry to disk before carrying on
+    pub fsync: bool,
+}
+
+impl Default for AuditConfig {
+    fn default() -> Self {
+        Self {
+            path: None,
+            fsync: true,
+        }
+    }
+}
+
+// Polling of ERC-7683 origin settlers for orders to fill
+#[derive(Debug, Serialize, Clone)]
+pub struct IntentSourceConfig {
+    pub poll_interval_ms: u64,
+    /// Blocks before the head the first scan of each settler starts at, so
+    /// orders opened while the relayer was down are still filled
+    pub lookback_blocks: u64,
+    /// Blocks an `Open` event must be buried under before it is picked up
+    pub confirmations: u64,
+    /// Most blocks asked for in a single `eth_getLogs` call
+    pub max_block_range: u64,
+}
+
+impl Default for IntentSourceConfig {
+    fn default() -> Self {
+        Self {
+            poll_interval_ms: 5_000,
+            lookback_blocks: 1_000,
+            confirmations: 0,
+            max_block_range: 2_000,
+        }
+    }
+}
+
+// Fitting shutdown into an orchestrator's pod lifecycle, such as Kubernetes
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct LifecycleConfig {
+    /// How long the orchestrator waits for the relayer to stop before killing
+    /// it, e.g. the pod's `terminationGracePeriodSeconds`; the shutdown drain
+    /// is cut short to end within it. Unbounded if unset
+    pub termination_grace_period_ms: Option<u64>,
+}
+
+// Stage implementations taken from the plugin registry, by the name they
+// were registered under
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct PluginConfig {
+    /// Produces proofs in place of the configured proof backend
+    pub proof_provider: Option<PluginSpec>,
+    /// Emit events alongside the on-chain generator
+    pub event_sources: Vec<PluginSpec>,
+    /// Takes proven events in place of on-chain delivery
+    pub delivery_sink: Option<PluginSpec>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct PluginSpec {
+    /// Name the plugin was registered under
+    pub name: String,
+    /// Handed to the plugin's factory as is
+    pub options: serde_json::Value,
+}
+
+impl PluginSpec {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            options: serde_json::Value::Null,
+        }
+    }
+}
+
+// Delivery to a Solana program, taken as the options of the `solana`
+// delivery sink plugin
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct SolanaSinkConfig {
+    pub rpc_url: String,
+    /// Program every delivery is submitted to
+    pub program_id: SolanaPubkey,
+    /// Instruction called with `(source_chain_id: u64, nonce: u64, payload:
+    /// Vec<u8>, proof: Vec<u8>)`, named for its Anchor discriminator
+    pub instruction: String,
+    /// Accounts the instruction takes after the fee payer, in order
+    pub accounts: Vec<SolanaAccountConfig>,
+    /// Keypair file, as written by `solana-keygen`, of the fee payer signing
+    /// every delivery
+    pub keypair_path: PathBuf,
+    /// Commitment a delivery must reach before it counts as delivered
+    pub commitment: SolanaCommitment,
+    /// How long a sent delivery may go unconfirmed before it is retried
+    pub confirm_timeout_ms: u64,
+    /// Deadline for a single RPC call
+    pub request_timeout_ms: u64,
+    pub fees: SolanaFeeConfig,
+    /// Where proofs too large to fit a transaction alongside the delivery
+    /// are staged first; without one such deliveries fail, and Polymer
+    /// receipt proofs rarely fit Solana's 1232 byte transaction limit
+    pub proof_buffer: Option<SolanaProofBufferConfig>,
+}
+
+impl Default for SolanaSinkConfig {
+    fn default() -> Self {
+        Self {
+            rpc_url: "https://api.mainnet-beta.solana.com".to_string(),
+            program_id: SolanaPubkey::default(),
+            instruction: "execute".to_string(),
+            accounts: Vec::new(),
+            keypair_path: PathBuf::new(),
+            commitment: SolanaCommitment::default(),
+            confirm_timeout_ms: 60_000,
+            request_timeout_ms: 30_000,
+            fees: SolanaFeeConfig::default(),
+            proof_buffer: None,
+        }
+    }
+}
+
+// Account of the delivery program's that a proof is written to a chunk at a
+// time, before the delivery that reads it. The delivery then gets an empty
+// proof and the buffer as its last account, and deliveries through the
+// buffer go one at a time
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct SolanaProofBufferConfig {
+    pub account: SolanaPubkey,
+    /// Instruction called with `(proof_len: u32, offset: u32, chunk: Vec<u8>)`
+    /// and the fee payer and buffer accounts, named for its Anchor
+    /// discriminator
+    pub instruction: String,
+}
+
+impl Default for SolanaProofBufferConfig {
+    fn default() -> Self {
+        Self {
+            account: SolanaPubkey::default(),
+            instruction: "write_proof".to_string(),
+        }
+    }
+}
+
+// Delivery by POSTing proven events to an endpoint that submits them itself,
+// taken as the options of the `webhook` delivery sink plugin
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct WebhookSinkConfig {
+    /// Endpoint every delivery is POSTed to; must be HTTPS unless
+    /// `allow_http` is set
+    #[serde(skip_serializing)]
+    pub url: String,
+    /// Key the `X-Relayer-Signature` HMAC of every body is computed with
+    #[serde(skip_serializing)]
+    pub secret: String,
+    /// Attempts at a delivery the endpoint fails transiently before it goes
+    /// back to the delivery retry queue
+    pub max_attempts: u32,
+    /// Wait before the second attempt, doubling for each one after
+    pub initial_backoff_ms: u64,
+    /// Deadline for a single POST
+    pub request_timeout_ms: u64,
+    /// Accept a plain HTTP url, for endpoints on a private network
+    pub allow_http: bool,
+}
+
+impl Default for WebhookSinkConfig {
+    fn default() -> Self {
+        Self {
+            url: String::new(),
+            secret: String::new(),
+            max_attempts: 3,
+            initial_backoff_ms: 500,
+            request_timeout_ms: 10_000,
+            allow_http: false,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub struct SolanaAccountConfig {
+    pub pubkey: SolanaPubkey,
+    #[serde(default)]
+    pub writable: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum SolanaCommitment {
+    Processed,
+    #[default]
+    Confirmed,
+    Finalized,
+}
+
+// Compute budget of Solana deliveries
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct SolanaFeeConfig {
+    pub compute_unit_limit: u32,
+    /// Percentile of the priority fees recently paid to write the same
+    /// accounts that deliveries bid
+    pub priority_fee_percentile: u8,
+    /// Floor of the priority fee bid, in micro-lamports per compute unit
+    pub min_compute_unit_price: u64,
+    /// Ceiling of the priority fee bid, in micro-lamports per compute unit
+    pub max_compute_unit_price: u64,
+}
+
+impl Default for SolanaFeeConfig {
+    fn default() -> Self {
+        Self {
+            compute_unit_limit: 400_000,
+            priority_fee_percentile: 75,
+            min_compute_unit_price: 0,
+            max_compute_unit_price: 1_000_000,
+        }
+    }
+}
+
+// How log lines are written to stdout
+#[derive(Debug, Serialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LogFormat {
+    /// Human-readable lines
+    #[default]
+    Text,
+    /// One JSON object per line, carrying the fields of the span it was
+    /// logged in (`stage`, `chain_id`, `pair`, `nonce`, `tx_hash` for events)
+    Json,
+}
+
+// Log output and trace export
+#[derive(Debug, Serialize, Clone)]
+pub struct TelemetryConfig {
+    pub log_format: LogFormat,
+    /// OTLP/HTTP traces endpoint, e.g. `http://localhost:4318/v1/traces`; spans
+    /// are only logged if unset
+    pub otlp_endpoint: Option<String>,
+    /// Service name traces are reported under
+    pub service_name: String,
+}
+
+impl Default for TelemetryConfig {
+    fn default() -> Self {
+        Self {
+            log_format: LogFormat::default(),
+            otlp_endpoint: None,
+            service_name: "relayer".to_string(),
+        }
+    }
+}
+
+// How finely metrics about relay pairs are labelled
+#[derive(Debug, Serialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MetricLabels {
+    /// Source chain, destination chain and pair ID
+    #[default]
+    Pair,
+    /// Source and destination chain, counting every pair between them together
+    Route,
+    /// No pair labels at all, counting every pair together
+    Aggregate,
+}
+
+// Prometheus metrics
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct MetricsConfig {
+    /// Coarser labels keep the number of series down in deployments with
+    /// many relay pairs
+    pub labels: MetricLabels,
+}
+
+// Objective for the time from detecting an event to its delivery confirming
+#[derive(Debug, Serialize, Clone)]
+pub struct SloConfig {
+    pub target_ms: u64,
+    /// Share of deliveries, from 0 to 1, that should make the target
+    pub objective: f64,
+    /// Compliance is measured over the deliveries confirmed this recently
+    pub window_ms: u64,
+    /// Deliveries the window must hold before a breach is alerted, so a few
+    /// slow ones after a quiet spell don't page anyone
+    pub min_deliveries: usize,
+}
+
+impl Default for SloConfig {
+    fn default() -> Self {
+        Self {
+            target_ms: 120_000,
+            objective: 0.95,
+            window_ms: 3_600_000,
+            min_deliveries: 20,
+        }
+    }
+}
+
+impl SloConfig {
+    fn validate(&self) -> Result<(), RelayerError> {
+        if !(self.objective > 0.0 && self.objective <= 1.0) {
+            return Err(invalid(format!(
+                "SLO objective {} is not above 0 and at most 1",
+                self.objective
+            )));
+        }
+        if self.window_ms == 0 {
+            return Err(invalid("SLO window_ms must be above 0".to_string()));
+        }
+        Ok(())
+    }
+}
+
+// What to do with a request whose reward does not cover relaying it
+#[derive(Debug, Serialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum UnprofitableRelays {
+    /// Leave it pending and price it again every poll, relaying it once the
+    /// reward covers the cost
+    #[default]
+    Queue,
+    /// Never relay it in this run, leaving it to the dapp's fallback
+    Skip,
+}
+
+// Pricing of requests of pairs whose resolver pays a reward for relaying
+// them. Costs on both chains and the reward are compared in wei, so such
+// pairs must run between chains with the same native token.
+#[derive(Debug, Serialize, Clone)]
+pub struct ProfitabilityConfig {
+    /// What the proof backend charges for a proof, in wei
+    pub proof_cost_wei: u64,
+    /// Delivery gas assumed for pairs with no delivery on record yet
+    pub default_delivery_gas: u64,
+    /// Recent deliveries of a pair whose gas use is averaged to price the next
+    pub delivery_samples: usize,
+    pub unprofitable: UnprofitableRelays,
+}
+
+impl Default for ProfitabilityConfig {
+    fn default() -> Self {
+        Self {
+            proof_cost_wei: 0,
+            default_delivery_gas: 300_000,
+            delivery_samples: 20,
+            unprofitable: UnprofitableRelays::default(),
+        }
+    }
+}
+
+// Registry contract a relayer reports its heartbeat to
+#[derive(Debug, Serialize, Clone)]
+pub struct HeartbeatRegistry {
+    pub chain_id: ChainId,
+    pub address: Address,
+}
+
+// Periodic `reportHeartbeat` calls letting dapps check on chain that their
+// relayer is alive and keeping up, and fall back to relaying themselves if not
+#[derive(Debug, Serialize, Clone)]
+pub struct HeartbeatConfig {
+    pub registries: Vec<HeartbeatRegistry>,
+    pub interval_ms: u64,
+    /// Key of the wallet heartbeats are sent from, the main signer if unset;
+    /// a wallet of its own keeps them clear of delivery nonces. Never written
+    /// out
+    #[serde(skip_serializing)]
+    pub wallet_key: Option<String>,
+}
+
+impl Default for HeartbeatConfig {
+    fn default() -> Self {
+        Self {
+            registries: Vec::new(),
+            interval_ms: 300_000,
+            wallet_key: None,
+        }
+    }
+}
+
+impl HeartbeatConfig {
+    fn validate(&self, chains: &HashMap<ChainId, ChainConfig>) -> Result<(), RelayerError> {
+        if self.interval_ms == 0 {
+            return Err(invalid("heartbeat interval_ms must be above 0".to_string()));
+        }
+        for registry in &self.registries {
+            if !chains.contains_key(&registry.chain_id) {
+                return Err(RelayerError::UnknownChain(registry.chain_id));
+            }
+            if registry.address.is_zero() {
+                return Err(invalid(format!(
+                    "heartbeat registry on chain {} has a zero address",
+                    registry.chain_id
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+// Resolver or executor contract holding relay fees the relayer has earned
+#[derive(Debug, Serialize, Clone)]
+pub struct FeeContract {
+    pub chain_id: ChainId,
+    pub address: Address,
+    /// Function paying out to its caller what it has earned, taking no
+    /// arguments, e.g. `claimFees()` or `withdraw()`
+    pub function: String,
+}
+
+// Periodic claims sweeping earned relay fees back to the relayer's wallet
+#[derive(Debug, Serialize, Clone)]
+pub struct FeeClaimConfig {
+    pub contracts: Vec<FeeContract>,
+    pub interval_ms: u64,
+    /// Claims a dry run shows would pay out less than this are not sent, as
+    /// they would cost more gas than they bring in. Claims whose function
+    /// returns no amount are always sent
+    pub min_claim_wei: u64,
+}
+
+impl Default for FeeClaimConfig {
+    fn default() -> Self {
+        Self {
+            contracts: Vec::new(),
+            interval_ms: 86_400_000,
+            min_claim_wei: 0,
+        }
+    }
+}
+
+impl FeeClaimConfig {
+    fn validate(&self, chains: &HashMap<ChainId, ChainConfig>) -> Result<(), RelayerError> {
+        if self.interval_ms == 0 {
+            return Err(invalid("fee claim interval_ms must be above 0".to_string()));
+        }
+        for contract in &self.contracts {
+            if !chains.contains_key(&contract.chain_id) {
+                return Err(RelayerError::UnknownChain(contract.chain_id));
+            }
+            if contract.address.is_zero() {
+                return Err(invalid(format!(
+                    "fee contract on chain {} has a zero address",
+                    contract.chain_id
+                )));
+            }
+            let name = contract.function.strip_suffix("()").unwrap_or_default();
+            if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+                return Err(invalid(format!(
+                    "fee contract {:?} on chain {} has function {:?}, expected a signature \
+                     without arguments like claimFees()",
+                    contract.address, contract.chain_id, contract.function
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+// Where relayer state is persisted
+#[derive(Debug, Serialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum StoreBackend {
+    /// JSON files in the state directory
+    #[default]
+    File,
+    /// A SQLite database in the state directory
+    Sqlite,
 }
 
 // Main configuration structure
 #[derive(Debug, Serialize, Clone)]
 pub struct RelayerConfig {
     pub polling_interval_ms: u64,
-    pub chains: HashMap<u64, ChainConfig>,
+    pub chains: HashMap<ChainId, ChainConfig>,
     pub relay_pairs: Vec<RelayPair>,
+    pub proof_backend: ProofBackendConfig,
+    pub proof_fetcher: ProofFetcherConfig,
+    pub delivery: DeliveryConfig,
+    /// Directory holding persisted relayer state (proof cache, etc.)
+    pub state_dir: PathBuf,
+    pub store_backend: StoreBackend,
+    /// Address to serve metrics, health and admin endpoints on, disabled if unset
+    pub http_addr: Option<SocketAddr>,
+    /// Bearer token for the endpoints that change relay pairs and chains, which
+    /// are not served if unset
+    pub admin_token: Option<String>,
+    /// Unix socket answering `relayer status` and other local control
+    /// commands, not opened if unset
+    pub control_socket: Option<PathBuf>,
+    /// How long in-flight proof fetches and deliveries get to finish on shutdown
+    pub shutdown_grace_period_ms: u64,
+    pub supervisor: SupervisorConfig,
+    pub health: HealthConfig,
+    pub backpressure: BackpressureConfig,
+    /// Only run the pipeline while holding a lease shared with standby
+    /// instances; the instance always runs it if unset
+    pub leader_election: Option<LeaderElectionConfig>,
+    /// Only pick up events for this instance's share of the relay pairs; the
+    /// instance serves every pair if unset
+    pub sharding: Option<ShardingConfig>,
+    pub alerts: AlertConfig,
+    pub telemetry: TelemetryConfig,
+    pub plugins: PluginConfig,
+    pub journal: JournalConfig,
+    pub lifecycle: LifecycleConfig,
+    pub intents: IntentSourceConfig,
+    /// Publish every event status change to a NATS subject or Kafka topic,
+    /// not published if unset
+    pub event_stream: Option<EventStreamConfig>,
+    pub metrics: MetricsConfig,
+    /// Append every source trigger, proof job and delivery to a tamper-evident
+    /// audit log, not written if unset
+    pub audit: Option<AuditConfig>,
+    pub slo: SloConfig,
+    /// Report liveness and the last delivered nonces to registry contracts,
+    /// not reported if unset
+    pub heartbeat: Option<HeartbeatConfig>,
+    pub profitability: ProfitabilityConfig,
+    /// Sweep earned relay fees from resolver and executor contracts, not
+    /// claimed if unset
+    pub fee_claims: Option<FeeClaimConfig>,
 }
 
+
+impl RelayerConfig {
+    /// Configuration built up one setting at a time, with every setting not
+    /// given at its default
+    pub fn builder() -> RelayerConfigBuilder {
+        RelayerConfigBuilder {
+            config: Self {
+                polling_interval_ms: 10_000,
+                chains: HashMap::new(),
+                relay_pairs: Vec::new(),
+                proof_backend: ProofBackendConfig::Polymer(PolymerApiConfig::default()),
+                proof_fetcher: Default::default(),
+                delivery: Default::default(),
+                state_dir: "./relayer-state".into(),
+                store_backend: Default::default(),
+                http_addr: None,
+                admin_token: None,
+                control_socket: None,
+                shutdown_grace_period_ms: 30_000,
+                supervisor: Default::default(),
+                health: Default::default(),
+                backpressure: Default::default(),
+                leader_election: None,
+                sharding: None,
+                alerts: Default::default(),
+                telemetry: Default::default(),
+                plugins: Default::default(),
+                journal: Default::default(),
+                lifecycle: Default::default(),
+                intents: Default::default(),
+                event_stream: None,
+                metrics: Default::default(),
+                audit: None,
+                slo: Default::default(),
+                heartbeat: None,
+                profitability: Default::default(),
+                fee_claims: None,
+            },
+        }
+    }
+
+    /// Check the configuration hangs together: every chain keyed by its own
+    /// ID, every relay pair between configured chains and configured once,
+    /// and no zero or unusable addresses and URLs
+    pub fn validate(&self) -> Result<(), RelayerError> {
+        if self.polling_interval_ms == 0 {
+            return Err(invalid("polling_interval_ms must be above 0".to_string()));
+        }
+        for (chain_id, chain) in &self.chains {
+            if *chain_id != chain.chain_id {
+                return Err(invalid(format!(
+                    "chain {} is configured under chain ID {}",
+                    chain.chain_id, chain_id
+                )));
+            }
+            if chain.rpc_url.parse::<reqwest::Url>().is_err() {
+                return Err(invalid(format!(
+                    "chain {} has an invalid RPC URL {:?}",
+                    chain_id, chain.rpc_url
+                )));
+            }
+            if chain
+                .smart_account
+                .as_ref()
+                .is_some_and(|account| account.address.is_zero())
+            {
+                return Err(invalid(format!(
+                    "chain {} has a zero smart account address",
+                    chain_id
+                )));
+            }
+        }
+
+        let mut ids = HashSet::new();
+        for pair in &self.relay_pairs {
+            pair.validate()?;
+            for chain_id in [pair.source_chain_id, pair.dest_chain_id] {
+                if !self.chains.contains_key(&chain_id) {
+                    return Err(RelayerError::UnknownChain(chain_id));
+                }
+            }
+            pair.validate_chains(
+                &self.chains[&pair.source_chain_id],
+                &self.chains[&pair.dest_chain_id],
+            )?;
+            let id = pair.id();
+            if !ids.insert(id.clone()) {
+                return Err(RelayerError::RelayPairExists(id));
+            }
+        }
+
+        if let Some(sharding) = &self.sharding {
+            let count = sharding.shard_count.max(1);
+            if sharding.shard_index >= count {
+                return Err(RelayerError::InvalidShard {
+                    shard: sharding.shard_index,
+                    count,
+                });
+            }
+        }
+        if let Some(leader_election) = &self.leader_election {
+            leader_election.validate()?;
+        }
+        if let Some(stream) = &self.event_stream {
+            stream.validate()?;
+        }
+        self.delivery.gas_tank.validate()?;
+        self.slo.validate()?;
+        if let Some(heartbeat) = &self.heartbeat {
+            heartbeat.validate(&self.chains)?;
+        }
+        if let Some(fee_claims) = &self.fee_claims {
+            fee_claims.validate(&self.chains)?;
+        }
+        Ok(())
+    }
+}
+
+/// Builds a [`RelayerConfig`], validating it when built
+#[derive(Debug, Clone)]
+pub struct RelayerConfigBuilder {
+    config: RelayerConfig,
+}
+
+impl RelayerConfigBuilder {
+    pub fn polling_interval_ms(mut self, interval_ms: u64) -> Self {
+        self.config.polling_interval_ms = interval_ms;
+        self
+    }
+
+    /// Add a chain, keyed by its chain ID
+    pub fn chain(mut self, chain: ChainConfig) -> Self {
+        self.config.chains.insert(chain.chain_id, chain);
+        self
+    }
+
+    pub fn relay_pair(mut self, pair: RelayPair) -> Self {
+        self.config.relay_pairs.push(pair);
+        self
+    }
+
+    pub fn proof_backend(mut self, backend: ProofBackendConfig) -> Self {
+        self.config.proof_backend = backend;
+        self
+    }
+
+    pub fn proof_fetcher(mut self, proof_fetcher: ProofFetcherConfig) -> Self {
+        self.config.proof_fetcher = proof_fetcher;
+        self
+    }
+
+    pub fn delivery(mut self, delivery: DeliveryConfig) -> Self {
+        self.config.delivery = delivery;
+        self
+    }
+
+    pub fn state_dir(mut self, state_dir: impl Into<PathBuf>) -> Self {
+        self.config.state_dir = state_dir.into();
+        self
+    }
+
+    pub fn store_backend(mut self, backend: StoreBackend) -> Self {
+        self.config.store_backend = backend;
+        self
+    }
+
+    pub fn http_addr(mut self, addr: SocketAddr) -> Self {
+        self.config.http_addr = Some(addr);
+        self
+    }
+
+    pub fn admin_token(mut self, token: impl Into<String>) -> Self {
+        self.config.admin_token = Some(token.into());
+        self
+    }
+
+    pub fn control_socket(mut self, path: impl Into<PathBuf>) -> Self {
+        self.config.control_socket = Some(path.into());
+        self
+    }
+
+    pub fn shutdown_grace_period_ms(mut self, grace_ms: u64) -> Self {
+        self.config.shutdown_grace_period_ms = grace_ms;
+        self
+    }
+
+    pub fn supervisor(mut self, supervisor: SupervisorConfig) -> Self {
+        self.config.supervisor = supervisor;
+        self
+    }
+
+    pub fn health(mut self, health: HealthConfig) -> Self {
+        self.config.health = health;
+        self
+    }
+
+    pub fn backpressure(mut self, backpressure: BackpressureConfig) -> Self {
+        self.config.backpressure = backpressure;
+        self
+    }
+
+    pub fn leader_election(mut self, leader_election: LeaderElectionConfig) -> Self {
+        self.config.leader_election = Some(leader_election);
+        self
+    }
+
+    pub fn sharding(mut self, sharding: ShardingConfig) -> Self {
+        self.config.sharding = Some(sharding);
+        self
+    }
+
+    pub fn alerts(mut self, alerts: AlertConfig) -> Self {
+        self.config.alerts = alerts;
+        self
+    }
+
+    pub fn telemetry(mut self, telemetry: TelemetryConfig) -> Self {
+        self.config.telemetry = telemetry;
+        self
+    }
+
+    pub fn plugins(mut self, plugins: PluginConfig) -> Self {
+        self.config.plugins = plugins;
+        self
+    }
+
+    pub fn journal(mut self, journal: JournalConfig) -> Self {
+        self.config.journal = journal;
+        self
+    }
+
+    pub fn lifecycle(mut self, lifecycle: LifecycleConfig) -> Self {
+        self.config.lifecycle = lifecycle;
+        self
+    }
+
+    pub fn intents(mut self, intents: IntentSourceConfig) -> Self {
+        self.config.intents = intents;
+        self
+    }
+
+    pub fn event_stream(mut self, stream: EventStreamConfig) -> Self {
+        self.config.event_stream = Some(stream);
+        self
+    }
+
+    pub fn metrics(mut self, metrics: MetricsConfig) -> Self {
+        self.config.metrics = metrics;
+        self
+    }
+
+    pub fn audit(mut self, audit: AuditConfig) -> Self {
+        self.config.audit = Some(audit);
+        self
+    }
+
+    pub fn slo(mut self, slo: SloConfig) -> Self {
+        self.config.slo = slo;
+        self
+    }
+
+    pub fn heartbeat(mut self, heartbeat: HeartbeatConfig) -> Self {
+        self.config.heartbeat = Some(heartbeat);
+        self
+    }
+
+    pub fn profitability(mut self, profitability: ProfitabilityConfig) -> Self {
+        self.config.profitability = profitability;
+        self
+    }
+
+    pub fn fee_claims(mut self, fee_claims: FeeClaimConfig) -> Self {
+        self.config.fee_claims = Some(fee_claims);
+        self
+    }
+
+    /// The configuration, or the first problem [`RelayerConfig::validate`]
+    /// finds with it
+    pub fn build(self) -> Result<RelayerConfig, RelayerError> {
+        self.config.validate()?;
+        Ok(self.config)
+    }
+}
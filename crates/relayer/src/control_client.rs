@@ -0,0 +1,66 @@
+use anyhow::{anyhow, Context, Result};
+use serde::de::DeserializeOwned;
+use std::path::PathBuf;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::UnixStream,
+};
+
+use crate::control_socket::{ControlRequest, ControlResponse, RelayerStatus};
+
+/// Client for a running relayer's local control socket
+pub struct ControlClient {
+    path: PathBuf,
+}
+
+impl ControlClient {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// Pipeline, queue and per-pair state of the relayer
+    pub async fn status(&self) -> Result<RelayerStatus> {
+        self.request(ControlRequest::Status).await
+    }
+
+    /// Hold new event detection and delivery submission for one relay pair,
+    /// or for every pair if `pair` is unset
+    pub async fn pause(&self, pair: Option<String>) -> Result<()> {
+        self.request(ControlRequest::Pause { pair }).await
+    }
+
+    /// Let a paused relay pair, or every pair if `pair` is unset, relay again
+    pub async fn resume(&self, pair: Option<String>) -> Result<()> {
+        self.request(ControlRequest::Resume { pair }).await
+    }
+
+    /// Stop the relayer's pipeline ahead of shutdown, returning once in-flight
+    /// work has drained; the relayer keeps serving its endpoints until it is
+    /// shut down
+    pub async fn drain(&self) -> Result<()> {
+        self.request(ControlRequest::Drain).await
+    }
+
+    async fn request<T: DeserializeOwned>(&self, request: ControlRequest) -> Result<T> {
+        let stream = UnixStream::connect(&self.path).await.context(format!(
+            "Failed to connect to control socket {}",
+            self.path.display()
+        ))?;
+        let (reader, mut writer) = stream.into_split();
+        let mut line = serde_json::to_vec(&request)?;
+        line.push(b'\n');
+        writer.write_all(&line).await?;
+
+        let response = BufReader::new(reader)
+            .lines()
+            .next_line()
+            .await?
+            .ok_or_else(|| anyhow!("Control socket closed without answering"))?;
+        match serde_json::from_str(&response).context("Failed to parse control response")? {
+            ControlResponse::Ok(value) => {
+                serde_json::from_value(value).context("Failed to parse control response")
+            }
+            ControlResponse::Error(error) => Err(anyhow!("Relayer refused command: {}", error)),
+        }
+    }
+}
@@ -0,0 +1,248 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{
+    fs::{self, Permissions},
+    io::ErrorKind,
+    os::unix::fs::PermissionsExt,
+    path::PathBuf,
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::{UnixListener, UnixStream},
+};
+use tracing::{debug, info, instrument};
+
+use crate::event_delivery::DeliveryControl;
+use crate::health::{GasTank, Health};
+use crate::lifecycle::Lifecycle;
+use crate::store::StateStore;
+use crate::topology::Topology;
+
+/// Command sent to the control socket, one JSON object per line
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub(crate) enum ControlRequest {
+    Status,
+    /// Hold detection and delivery for one pair, or every pair if unset
+    Pause {
+        pair: Option<String>,
+    },
+    Resume {
+        pair: Option<String>,
+    },
+    /// Stop the pipeline ahead of shutdown, answering once it has drained
+    Drain,
+}
+
+/// Answer to a control socket command, one JSON object per line
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum ControlResponse {
+    Ok(Value),
+    Error(String),
+}
+
+/// Snapshot of a running relayer, as printed by `relayer status`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RelayerStatus {
+    pub live: bool,
+    /// Relaying is paused for every pair
+    pub paused: bool,
+    /// Waiting to take over from the leading instance, with no pipeline running
+    pub standby: bool,
+    pub components: Vec<ComponentStatus>,
+    pub pairs: Vec<PairStatus>,
+    /// Detected events not yet delivered or given up on
+    pub pending_events: usize,
+    pub retrying: usize,
+    pub dead_letters: usize,
+    /// Deliveries broadcast and waiting to be mined
+    pub in_flight: usize,
+    /// Latest balance and runway of every delivery wallet, empty until the
+    /// first check or while standing by
+    #[serde(default)]
+    pub gas_tanks: Vec<GasTank>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ComponentStatus {
+    pub name: String,
+    pub live: bool,
+    /// Unix time in seconds the component last made progress
+    pub last_active_at: Option<u64>,
+    pub queue_depth: Option<usize>,
+    pub queue_capacity: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PairStatus {
+    pub id: String,
+    pub enabled: bool,
+    pub paused: bool,
+    pub shard: u32,
+    /// Enabled and in this instance's shard, so its events are picked up here
+    pub served: bool,
+    /// Unix time in seconds an event was last detected for the pair
+    pub last_detected_at: Option<u64>,
+    /// Unix time in seconds an event was last delivered for the pair
+    pub last_delivered_at: Option<u64>,
+    pub pending_events: usize,
+    pub retrying: usize,
+    pub dead_letters: usize,
+    pub in_flight: usize,
+}
+
+// Handles control commands act through
+#[derive(Clone)]
+pub(crate) struct ControlState {
+    pub(crate) store: Arc<dyn StateStore>,
+    pub(crate) health: Health,
+    pub(crate) topology: Topology,
+    pub(crate) control: DeliveryControl,
+    pub(crate) lifecycle: Lifecycle,
+}
+
+/// Answer control commands on a Unix socket at `path` until the listener fails
+///
+/// The socket is only accessible to the user the relayer runs as, which is
+/// what authorizes its commands.
+#[instrument(skip_all, fields(socket = %path.display()))]
+pub(crate) async fn serve(path: PathBuf, state: ControlState) -> Result<()> {
+    // A socket left behind by an earlier run would fail the bind
+    match fs::remove_file(&path) {
+        Ok(()) => {}
+        Err(e) if e.kind() == ErrorKind::NotFound => {}
+        Err(e) => {
+            return Err(e).context(format!("Failed to remove stale socket {}", path.display()))
+        }
+    }
+    let listener = UnixListener::bind(&path)
+        .context(format!("Failed to bind control socket {}", path.display()))?;
+    fs::set_permissions(&path, Permissions::from_mode(0o600))?;
+    info!("Serving control socket");
+
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let state = state.clone();
+        tokio::spawn(async move {
+            if let Err(e) = state.handle(stream).await {
+                debug!(error = %e, "Control connection failed");
+            }
+        });
+    }
+}
+
+impl ControlState {
+    async fn handle(&self, stream: UnixStream) -> Result<()> {
+        let (reader, mut writer) = stream.into_split();
+        let mut lines = BufReader::new(reader).lines();
+        while let Some(line) = lines.next_line().await? {
+            let response = match serde_json::from_str::<ControlRequest>(&line) {
+                Ok(request) => match self.execute(request).await {
+                    Ok(value) => ControlResponse::Ok(value),
+                    Err(e) => ControlResponse::Error(format!("{:#}", e)),
+                },
+                Err(e) => ControlResponse::Error(format!("Invalid request: {}", e)),
+            };
+            let mut body = serde_json::to_vec(&response)?;
+            body.push(b'\n');
+            writer.write_all(&body).await?;
+        }
+        Ok(())
+    }
+
+    async fn execute(&self, request: ControlRequest) -> Result<Value> {
+        match request {
+            ControlRequest::Status => Ok(serde_json::to_value(self.status()?)?),
+            ControlRequest::Pause { pair } => {
+                self.topology.set_paused(pair.as_deref(), true)?;
+                Ok(Value::Null)
+            }
+            ControlRequest::Resume { pair } => {
+                self.topology.set_paused(pair.as_deref(), false)?;
+                Ok(Value::Null)
+            }
+            ControlRequest::Drain => {
+                self.lifecycle.drain();
+                self.lifecycle.drained().await;
+                Ok(Value::Null)
+            }
+        }
+    }
+
+    fn status(&self) -> Result<RelayerStatus> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or_default();
+        let report = self.health.report();
+        let activity = self.health.pair_activity();
+        let pending = self.store.pending_events()?;
+        let retries = self.store.retries()?;
+        let dead_letters = self.store.dead_letters()?;
+        let in_flight = self.control.in_flight();
+        let (shard_index, _) = self.topology.shard();
+
+        let components = report
+            .components
+            .iter()
+            .map(|component| ComponentStatus {
+                name: component.name.to_string(),
+                live: component.live,
+                last_active_at: component.idle_secs.map(|idle| now.saturating_sub(idle)),
+                queue_depth: component.queue_depth,
+                queue_capacity: component.queue_capacity,
+            })
+            .collect();
+
+        let pairs = self
+            .topology
+            .pairs()
+            .into_iter()
+            .map(|managed| {
+                let activity = activity.get(&managed.id).copied().unwrap_or_default();
+                PairStatus {
+                    served: managed.enabled && managed.shard == shard_index,
+                    last_detected_at: activity.last_detected_at,
+                    last_delivered_at: activity.last_delivered_at,
+                    pending_events: pending
+                        .iter()
+                        .filter(|event| event.pair_id() == managed.id)
+                        .count(),
+                    retrying: retries
+                        .iter()
+                        .filter(|failed| failed.event.pair_id() == managed.id)
+                        .count(),
+                    dead_letters: dead_letters
+                        .iter()
+                        .filter(|failed| failed.event.pair_id() == managed.id)
+                        .count(),
+                    in_flight: in_flight
+                        .iter()
+                        .filter(|delivery| delivery.pair.as_deref() == Some(managed.id.as_str()))
+                        .count(),
+                    id: managed.id,
+                    enabled: managed.enabled,
+                    paused: managed.paused,
+                    shard: managed.shard,
+                }
+            })
+            .collect();
+
+        Ok(RelayerStatus {
+            live: report.live,
+            paused: self.topology.paused(),
+            standby: report.standby,
+            components,
+            pairs,
+            pending_events: pending.len(),
+            retrying: retries.len(),
+            dead_letters: dead_letters.len(),
+            in_flight: in_flight.len(),
+            gas_tanks: self.health.gas_tanks(),
+        })
+    }
+}
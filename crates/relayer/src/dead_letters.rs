@@ -0,0 +1,162 @@
+use anyhow::Result;
+use std::{
+    fmt,
+    str::FromStr,
+    sync::{Arc, Mutex},
+};
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+
+use crate::backpressure;
+use crate::health::PROOF_FETCHER;
+use crate::store::{DeadLetterQuery, FailedDelivery, FailureStage, ProofKey, StateStore};
+use crate::types::{EventId, RelayEvent, RelayerError};
+
+/// Dead letter named by the source log it was parked for, or by its event's ID
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeadLetterKey {
+    Proof(ProofKey),
+    Event(EventId),
+}
+
+impl From<ProofKey> for DeadLetterKey {
+    fn from(key: ProofKey) -> Self {
+        Self::Proof(key)
+    }
+}
+
+impl From<EventId> for DeadLetterKey {
+    fn from(id: EventId) -> Self {
+        Self::Event(id)
+    }
+}
+
+impl fmt::Display for DeadLetterKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Proof(key) => key.fmt(f),
+            Self::Event(id) => id.fmt(f),
+        }
+    }
+}
+
+impl FromStr for DeadLetterKey {
+    type Err = anyhow::Error;
+
+    /// Parse either a `0x`-prefixed event ID or a `chain:block:tx:log` proof key
+    fn from_str(s: &str) -> Result<Self> {
+        if s.starts_with("0x") {
+            s.parse().map(Self::Event)
+        } else {
+            s.parse().map(Self::Proof)
+        }
+    }
+}
+
+/// Handle for inspecting parked events and sending them through the pipeline
+/// again
+///
+/// Delivery failures are replayed through the retry queue with a fresh set of
+/// attempts; proof failures go back to the proof fetcher for a new proof job.
+#[derive(Clone)]
+pub struct DeadLetterQueue {
+    store: Arc<dyn StateStore>,
+    /// Input of the running proof fetcher, held weakly so shutdown can still
+    /// close the channel
+    events: Arc<Mutex<Option<mpsc::WeakSender<RelayEvent>>>>,
+}
+
+impl DeadLetterQueue {
+    pub fn new(store: Arc<dyn StateStore>) -> Self {
+        Self {
+            store,
+            events: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Route proof replays to a newly started pipeline
+    pub(crate) fn attach(&self, events: &mpsc::Sender<RelayEvent>) {
+        *self.events.lock().expect("dead letter lock poisoned") = Some(events.downgrade());
+    }
+
+    /// Parked events matching `query`, oldest detection first
+    pub fn list(&self, query: &DeadLetterQuery) -> Result<Vec<FailedDelivery>> {
+        let mut letters: Vec<FailedDelivery> = self
+            .store
+            .dead_letters()?
+            .into_iter()
+            .filter(|failed| query.matches(failed))
+            .collect();
+        letters.sort_by_key(|failed| (failed.event.detected_at, failed.key().to_string()));
+        Ok(letters)
+    }
+
+    pub fn get(&self, key: &ProofKey) -> Result<Option<FailedDelivery>> {
+        self.store.dead_letter(key)
+    }
+
+    /// Proof key of the dead letter `key` names, failing if no dead letter
+    /// has the event ID it gives
+    pub fn resolve(&self, key: &DeadLetterKey) -> Result<ProofKey> {
+        match key {
+            DeadLetterKey::Proof(key) => Ok(key.clone()),
+            DeadLetterKey::Event(id) => self
+                .store
+                .dead_letters()?
+                .into_iter()
+                .find(|failed| failed.event.id() == *id)
+                .map(|failed| failed.key())
+                .ok_or_else(|| RelayerError::UnknownDeadLetter(id.to_string()).into()),
+        }
+    }
+
+    /// Send one parked event through the pipeline again
+    pub async fn replay(&self, key: &ProofKey) -> Result<()> {
+        let failed = self
+            .store
+            .dead_letter(key)?
+            .ok_or_else(|| RelayerError::UnknownDeadLetter(key.to_string()))?;
+
+        match failed.stage {
+            FailureStage::Delivery => {
+                let retry = FailedDelivery {
+                    attempts: 0,
+                    next_attempt_at: 0,
+                    ..failed
+                };
+                self.store.save_retry(key, &retry)?;
+            }
+            FailureStage::Proof => {
+                // Pending events are also picked up by the next pipeline to
+                // start, should none be running now
+                self.store.save_pending_event(key, &failed.event)?;
+                let events = self
+                    .events
+                    .lock()
+                    .expect("dead letter lock poisoned")
+                    .as_ref()
+                    .and_then(mpsc::WeakSender::upgrade);
+                if let Some(events) = events {
+                    let sent = backpressure::send(PROOF_FETCHER, &events, failed.event.clone());
+                    if sent.await.is_err() {
+                        warn!(proof_key = %key, "Pipeline stopped, replay left pending");
+                    }
+                }
+            }
+        }
+
+        self.store.remove_dead_letter(key)?;
+        info!(proof_key = %key, "Replayed dead letter");
+        Ok(())
+    }
+
+    /// Send every parked event matching `query` through the pipeline again,
+    /// returning how many were replayed
+    pub async fn replay_all(&self, query: &DeadLetterQuery) -> Result<usize> {
+        let letters = self.list(query)?;
+        for failed in &letters {
+            self.replay(&failed.key()).await?;
+        }
+        Ok(letters.len())
+    }
+}
@@ -0,0 +1,230 @@
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose, Engine};
+use ethers::{
+    abi::{self, ParamType, Token},
+    core::types::{Address, Bytes, Log, TransactionReceipt, H160, H256, U256},
+    utils::{hex, keccak256},
+};
+
+use crate::config::ProofEncoding;
+use crate::types::{ChainId, EventMeta, RelayerError};
+
+/// Signature of the event a resolver emits when it requests a remote execution
+pub const CROSS_CHAIN_EXEC_REQUESTED: &str = "CrossChainExecRequested(uint32,bytes,uint256)";
+
+/// Predeploy sending and relaying native interop messages, at the same
+/// address on every OP superchain chain
+pub const L2_TO_L2_CROSS_DOMAIN_MESSENGER: Address = H160([
+    0x42, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x23,
+]);
+
+/// Signature of the event the messenger emits for every message sent
+pub const SENT_MESSAGE: &str = "SentMessage(uint256,address,uint256,address,bytes)";
+
+/// Signature of the event an ERC-7683 origin settler emits when an order is
+/// opened, carrying the order as a `ResolvedCrossChainOrder`
+pub const OPEN: &str = "Open(bytes32,(address,uint256,uint32,uint32,bytes32,\
+    (bytes32,uint256,bytes32,uint256)[],(bytes32,uint256,bytes32,uint256)[],\
+    (uint64,bytes32,bytes)[]))";
+
+/// An ERC-7683 order as opened on its origin chain, short of the outputs
+/// the relayer has no use for
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OpenOrder {
+    pub order_id: H256,
+    pub user: Address,
+    pub origin_chain_id: U256,
+    /// Unix time in seconds after which the order can no longer be filled
+    pub fill_deadline: u32,
+    pub fill_instructions: Vec<FillInstruction>,
+}
+
+/// One leg of an order, to be filled on `destination_chain_id`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FillInstruction {
+    pub destination_chain_id: u64,
+    /// Settler to call `fill` on, as a left-padded address
+    pub destination_settler: H256,
+    /// Passed to the destination settler's `fill` as it is
+    pub origin_data: Bytes,
+}
+
+/// Decode an origin settler's `Open` log
+pub fn decode_open(log: &Log) -> Result<OpenOrder> {
+    let invalid = |reason: &str| RelayerError::InvalidPayload(format!("Open event {}", reason));
+    if log.topics.first() != Some(&H256::from(keccak256(OPEN))) || log.topics.len() != 2 {
+        return Err(invalid("topics do not match").into());
+    }
+    let output = ParamType::Tuple(vec![
+        ParamType::FixedBytes(32),
+        ParamType::Uint(256),
+        ParamType::FixedBytes(32),
+        ParamType::Uint(256),
+    ]);
+    let order = ParamType::Tuple(vec![
+        ParamType::Address,
+        ParamType::Uint(256),
+        ParamType::Uint(32),
+        ParamType::Uint(32),
+        ParamType::FixedBytes(32),
+        ParamType::Array(Box::new(output.clone())),
+        ParamType::Array(Box::new(output)),
+        ParamType::Array(Box::new(ParamType::Tuple(vec![
+            ParamType::Uint(64),
+            ParamType::FixedBytes(32),
+            ParamType::Bytes,
+        ]))),
+    ]);
+    let mut tokens = abi::decode(&[order], &log.data)
+        .map_err(|e| invalid(&format!("data is malformed: {}", e)))?;
+    let Some(Token::Tuple(fields)) = tokens.pop() else {
+        return Err(invalid("data is not a tuple").into());
+    };
+    let malformed = || invalid("data is malformed");
+    // user, originChainId, openDeadline, fillDeadline, orderId, maxSpent,
+    // minReceived, fillInstructions
+    let field = |index: usize| fields.get(index).cloned().ok_or_else(malformed);
+    let fill_instructions = field(7)?
+        .into_array()
+        .ok_or_else(malformed)?
+        .into_iter()
+        .map(|instruction| {
+            let fields = instruction.into_tuple().ok_or_else(malformed)?;
+            let [chain_id, settler, origin_data] =
+                <[Token; 3]>::try_from(fields).map_err(|_| malformed())?;
+            Ok(FillInstruction {
+                destination_chain_id: chain_id.into_uint().ok_or_else(malformed)?.low_u64(),
+                destination_settler: H256::from_slice(
+                    &settler.into_fixed_bytes().ok_or_else(malformed)?,
+                ),
+                origin_data: origin_data.into_bytes().ok_or_else(malformed)?.into(),
+            })
+        })
+        .collect::<Result<_, RelayerError>>()?;
+    Ok(OpenOrder {
+        order_id: log.topics[1],
+        user: field(0)?.into_address().ok_or_else(malformed)?,
+        origin_chain_id: field(1)?.into_uint().ok_or_else(malformed)?,
+        fill_deadline: field(3)?.into_uint().ok_or_else(malformed)?.low_u32(),
+        fill_instructions,
+    })
+}
+
+/// The `CrossChainExecRequested` log `resolver` emitted, among a receipt's logs
+///
+/// Logs from other contracts, other events, and anonymous logs without topics
+/// are passed over.
+pub fn find_exec_request(logs: &[Log], resolver: Address) -> Option<&Log> {
+    let topic = H256::from(keccak256(CROSS_CHAIN_EXEC_REQUESTED));
+    logs.iter()
+        .find(|log| log.address == resolver && log.topics.first() == Some(&topic))
+}
+
+/// Where in `chain_id`'s history the `CrossChainExecRequested` log `resolver`
+/// emitted in `receipt` sits, for requesting its proof
+pub fn exec_request_meta(
+    chain_id: ChainId,
+    receipt: &TransactionReceipt,
+    resolver: Address,
+) -> Result<EventMeta> {
+    let log = find_exec_request(&receipt.logs, resolver)
+        .ok_or_else(|| anyhow!("CrossChainExecRequested event not found in transaction"))?;
+    log_meta(chain_id, receipt, log, "CrossChainExecRequested")
+}
+
+/// The `SentMessage` log the messenger emitted for a message from `sender`
+/// to `target` on chain `destination`, among a receipt's logs
+pub fn find_sent_message(
+    logs: &[Log],
+    sender: Address,
+    destination: ChainId,
+    target: Address,
+) -> Option<&Log> {
+    let topics = [
+        H256::from(keccak256(SENT_MESSAGE)),
+        H256::from_low_u64_be(destination.as_u64()),
+        H256::from(target),
+    ];
+    logs.iter().find(|log| {
+        log.address == L2_TO_L2_CROSS_DOMAIN_MESSENGER
+            && log.topics.len() == 4
+            && log.topics[..3] == topics
+            // The sender is the first word of the unindexed data
+            && log.data.get(..32).map(H256::from_slice) == Some(H256::from(sender))
+    })
+}
+
+/// Where in `chain_id`'s history the `SentMessage` log for a message from
+/// `sender` to `target` on `destination` sits, for relaying it natively
+pub fn sent_message_meta(
+    chain_id: ChainId,
+    receipt: &TransactionReceipt,
+    sender: Address,
+    destination: ChainId,
+    target: Address,
+) -> Result<EventMeta> {
+    let log = find_sent_message(&receipt.logs, sender, destination, target)
+        .ok_or_else(|| anyhow!("SentMessage event not found in transaction"))?;
+    log_meta(chain_id, receipt, log, "SentMessage")
+}
+
+fn log_meta(
+    chain_id: ChainId,
+    receipt: &TransactionReceipt,
+    log: &Log,
+    event: &str,
+) -> Result<EventMeta> {
+    let block_number = receipt
+        .block_number
+        .ok_or_else(|| anyhow!("block_number not found from receipt"))?;
+    let tx_index = u32::try_from(receipt.transaction_index.as_u64()).map_err(|_| {
+        anyhow!(
+            "Transaction index {} is out of range",
+            receipt.transaction_index
+        )
+    })?;
+    let log_index = log
+        .log_index
+        .ok_or_else(|| anyhow!("log_index not found from {} event", event))?;
+    let log_index =
+        u32::try_from(log_index).map_err(|_| anyhow!("Log index {} is out of range", log_index))?;
+    Ok(EventMeta {
+        chain_id,
+        tx_hash: Some(receipt.transaction_hash),
+        block_number: block_number.as_u64(),
+        tx_index,
+        log_index,
+    })
+}
+
+/// Selector of the function an exec payload calls
+///
+/// Payloads too short to hold one are an error rather than a panic, since
+/// they come from the resolver on chain.
+pub fn function_selector(payload: &[u8]) -> Result<[u8; 4], RelayerError> {
+    payload
+        .get(..4)
+        .and_then(|selector| selector.try_into().ok())
+        .ok_or_else(|| {
+            RelayerError::InvalidPayload(format!(
+                "{} bytes is too short for a function selector (0x{})",
+                payload.len(),
+                hex::encode(payload)
+            ))
+        })
+}
+
+/// Decode a proof as the proof API returned it
+pub fn decode_proof(proof: &str, encoding: ProofEncoding) -> Result<Bytes> {
+    let hex_digits = proof.strip_prefix("0x");
+    let bytes = match (encoding, hex_digits) {
+        (ProofEncoding::Hex, Some(digits)) | (ProofEncoding::Auto, Some(digits)) => {
+            hex::decode(digits)?
+        }
+        (ProofEncoding::Hex, None) => hex::decode(proof)?,
+        (ProofEncoding::Base64, _) | (ProofEncoding::Auto, None) => {
+            general_purpose::STANDARD.decode(proof)?
+        }
+    };
+    Ok(Bytes::from(bytes))
+}
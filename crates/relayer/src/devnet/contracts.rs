@@ -0,0 +1,24 @@
+// Sample contracts deployed on devnet chains, assembled by hand so they can
+// be embedded without a Solidity toolchain. Both are deployed through the
+// same 13-byte constructor, which copies the runtime code after it into
+// memory and returns it.
+
+/// Creation code of the sample resolver
+///
+/// `crossChainChecker(uint32)` always asks for an execution, with nonce
+/// `counter + 1` and an `execute(uint256 nonce)` payload.
+/// `requestRemoteExecution(uint32 destinationChainId)` bumps the counter in
+/// slot 0 and emits `CrossChainExecRequested` for the new nonce. Any other
+/// call reverts.
+pub(crate) const RESOLVER: &str = "\
+    61009b8061000d6000396000f3\
+    60003560e01c8063cd79188c146100205780635e0d4d561461004d57600080fd\
+    5b6001600054016001600052606060205280604052602460605263fe0d94c160e0\
+    1b60805260845260c06000f3\
+    5b600160005401806000556020600052602460205263fe0d94c160e01b6040528060\
+    44526004357f4161a03492df18917afe63f09dc8bea1ad102915aeb26edefcbd0722\
+    6a7c309160806000a300";
+
+/// Creation code of the sample dapp, which accepts any call and logs its
+/// calldata as an anonymous event
+pub(crate) const DAPP: &str = "61000b8061000d6000396000f3366000600037366000a000";
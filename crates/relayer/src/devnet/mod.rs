@@ -0,0 +1,233 @@
+use anyhow::{anyhow, Context, Result};
+use ethers::{
+    core::types::{Address, Bytes, TransactionRequest},
+    middleware::SignerMiddleware,
+    providers::{Http, Middleware, Provider},
+    signers::{LocalWallet, Signer},
+    utils::hex,
+};
+use std::{fs, path::PathBuf, process::Stdio, time::Duration};
+use tokio::process::{Child, Command};
+use tracing::{info, instrument};
+
+use crate::config::{ChainConfig, MockProofConfig, ProofBackendConfig, RelayPair, RelayerConfig};
+use crate::types::ChainId;
+
+mod contracts;
+
+/// Key of the first account anvil funds on every chain it starts; the devnet
+/// deploys with it and the relayer signs with it
+pub const DEVNET_PRIVATE_KEY: &str =
+    "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+
+// How long a freshly started node gets to answer its first request
+const NODE_STARTUP_TIMEOUT: Duration = Duration::from_secs(10);
+const NODE_STARTUP_POLL: Duration = Duration::from_millis(100);
+
+/// Local chain run by a devnet
+#[derive(Debug, Clone)]
+pub struct DevnetChain {
+    pub name: String,
+    pub chain_id: u64,
+    /// Port the node serves JSON-RPC on, on the loopback interface
+    pub port: u16,
+}
+
+/// What `relayer dev up` starts
+#[derive(Debug, Clone)]
+pub struct DevnetConfig {
+    /// anvil binary, looked up on `PATH` unless a path is given
+    pub anvil: PathBuf,
+    pub chains: Vec<DevnetChain>,
+    /// Seconds between blocks; blocks are mined per transaction if unset
+    pub block_time_secs: Option<u64>,
+    /// Relayer state and the written config live here
+    pub state_dir: PathBuf,
+}
+
+impl Default for DevnetConfig {
+    fn default() -> Self {
+        Self {
+            anvil: "anvil".into(),
+            chains: vec![
+                DevnetChain {
+                    name: "devnet-a".to_string(),
+                    chain_id: 31337,
+                    port: 8545,
+                },
+                DevnetChain {
+                    name: "devnet-b".to_string(),
+                    chain_id: 31338,
+                    port: 8546,
+                },
+            ],
+            block_time_secs: None,
+            state_dir: "./relayer-dev".into(),
+        }
+    }
+}
+
+/// Local chains with the sample resolver and dapp deployed on each, relayed
+/// between in both directions
+///
+/// The nodes are stopped when the devnet is dropped.
+pub struct Devnet {
+    chains: Vec<ChainConfig>,
+    relay_pairs: Vec<RelayPair>,
+    state_dir: PathBuf,
+    _nodes: Vec<Child>,
+}
+
+impl Devnet {
+    /// Start an anvil node per chain and deploy the sample contracts
+    #[instrument(skip_all, fields(chains = config.chains.len()))]
+    pub async fn up(config: DevnetConfig) -> Result<Self> {
+        let mut nodes = Vec::new();
+        let mut chains = Vec::new();
+        // Resolver and dapp on each chain, in chain order
+        let mut deployments = Vec::new();
+        for chain in &config.chains {
+            nodes.push(start_node(&config, chain)?);
+            let chain = ChainConfig {
+                name: chain.name.clone(),
+                chain_id: ChainId::new(chain.chain_id),
+                rpc_url: format!("http://127.0.0.1:{}", chain.port),
+                ..Default::default()
+            };
+            let provider = wait_for_node(&chain).await?;
+            let resolver = deploy(&provider, &chain, contracts::RESOLVER).await?;
+            let dapp = deploy(&provider, &chain, contracts::DAPP).await?;
+            info!(
+                chain = %chain.name,
+                rpc_url = %chain.rpc_url,
+                ?resolver,
+                ?dapp,
+                "Devnet chain ready"
+            );
+            deployments.push((resolver, dapp));
+            chains.push(chain);
+        }
+
+        let mut relay_pairs = Vec::new();
+        for (source, (resolver, _)) in chains.iter().zip(&deployments) {
+            for (dest, (_, dapp)) in chains.iter().zip(&deployments) {
+                if source.chain_id != dest.chain_id {
+                    relay_pairs.push(RelayPair {
+                        source_chain_id: source.chain_id,
+                        source_resolver_address: *resolver,
+                        dest_chain_id: dest.chain_id,
+                        dest_dapp_address: *dapp,
+                        ..Default::default()
+                    });
+                }
+            }
+        }
+
+        Ok(Self {
+            chains,
+            relay_pairs,
+            state_dir: config.state_dir,
+            _nodes: nodes,
+        })
+    }
+
+    pub fn chains(&self) -> &[ChainConfig] {
+        &self.chains
+    }
+
+    pub fn relay_pairs(&self) -> &[RelayPair] {
+        &self.relay_pairs
+    }
+
+    /// Relayer configuration for the devnet, with mock proofs and the admin
+    /// API on localhost
+    pub fn relayer_config(&self) -> Result<RelayerConfig> {
+        let mut builder = RelayerConfig::builder()
+            .polling_interval_ms(2_000)
+            .proof_backend(ProofBackendConfig::Mock(MockProofConfig::default()))
+            .state_dir(&self.state_dir)
+            .http_addr(([127, 0, 0, 1], 9090).into());
+        for chain in &self.chains {
+            builder = builder.chain(chain.clone());
+        }
+        for pair in &self.relay_pairs {
+            builder = builder.relay_pair(pair.clone());
+        }
+        Ok(builder.build()?)
+    }
+
+    /// Write `config` as JSON to `devnet.json` in the state directory,
+    /// returning where it went
+    pub fn write_config(&self, config: &RelayerConfig) -> Result<PathBuf> {
+        fs::create_dir_all(&self.state_dir)
+            .with_context(|| format!("Failed to create state dir {}", self.state_dir.display()))?;
+        let path = self.state_dir.join("devnet.json");
+        fs::write(&path, serde_json::to_string_pretty(config)?)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        Ok(path)
+    }
+}
+
+fn start_node(config: &DevnetConfig, chain: &DevnetChain) -> Result<Child> {
+    let mut command = Command::new(&config.anvil);
+    command
+        .arg("--chain-id")
+        .arg(chain.chain_id.to_string())
+        .arg("--port")
+        .arg(chain.port.to_string())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .kill_on_drop(true);
+    if let Some(block_time) = config.block_time_secs {
+        command.arg("--block-time").arg(block_time.to_string());
+    }
+    command.spawn().with_context(|| {
+        format!(
+            "Failed to start {} for chain {}; is Foundry installed?",
+            config.anvil.display(),
+            chain.name
+        )
+    })
+}
+
+async fn wait_for_node(chain: &ChainConfig) -> Result<Provider<Http>> {
+    let provider = Provider::<Http>::try_from(&chain.rpc_url)?;
+    let started = tokio::time::Instant::now();
+    loop {
+        match provider.get_chainid().await {
+            Ok(chain_id) if chain_id.as_u64() == chain.chain_id.as_u64() => return Ok(provider),
+            Ok(chain_id) => {
+                return Err(anyhow!(
+                    "{} serves chain {}, expected {}; is another node on the port?",
+                    chain.rpc_url,
+                    chain_id,
+                    chain.chain_id
+                ))
+            }
+            Err(e) if started.elapsed() >= NODE_STARTUP_TIMEOUT => {
+                return Err(anyhow!(e).context(format!(
+                    "Node for chain {} did not come up at {}",
+                    chain.name, chain.rpc_url
+                )))
+            }
+            Err(_) => tokio::time::sleep(NODE_STARTUP_POLL).await,
+        }
+    }
+}
+
+async fn deploy(provider: &Provider<Http>, chain: &ChainConfig, code: &str) -> Result<Address> {
+    let wallet = DEVNET_PRIVATE_KEY
+        .parse::<LocalWallet>()?
+        .with_chain_id(chain.chain_id);
+    let client = SignerMiddleware::new(provider.clone(), wallet);
+    let tx = TransactionRequest::new().data(Bytes::from(hex::decode(code)?));
+    let receipt = client
+        .send_transaction(tx, None)
+        .await
+        .with_context(|| format!("Failed to deploy sample contract on {}", chain.name))?
+        .await?
+        .ok_or_else(|| anyhow!("Deployment on {} was dropped", chain.name))?;
+    receipt
+        .contract_address
+        .ok_or_else(|| anyhow!("Deployment on {} created no contract", chain.name))
+}
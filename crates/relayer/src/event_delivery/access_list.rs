@@ -0,0 +1,47 @@
+use anyhow::Result;
+use ethers::{core::types::transaction::eip2718::TypedTransaction, providers::Middleware};
+use tracing::{debug, warn};
+
+use crate::config::ChainConfig;
+
+/// Attach an access list to a delivery on chains configured for it
+///
+/// Warming the prover's storage slots up front can make proof verification
+/// cheaper, but the list itself costs gas, so it is only kept when the node
+/// reports a lower total than a plain estimate. Legacy transactions cannot
+/// carry one and are left alone, as is any delivery the node fails to
+/// generate a list for.
+pub async fn apply_access_list<M: Middleware>(
+    client: &M,
+    tx: &mut TypedTransaction,
+    chain: &ChainConfig,
+) -> Result<()>
+where
+    M::Error: 'static,
+{
+    if !chain.access_lists || matches!(tx, TypedTransaction::Legacy(_)) {
+        return Ok(());
+    }
+
+    let generated = match client.create_access_list(tx, None).await {
+        Ok(generated) => generated,
+        Err(e) => {
+            warn!(error = %e, chain = %chain.name, "Failed to create access list");
+            return Ok(());
+        }
+    };
+    let without = client.estimate_gas(tx, None).await?;
+    if generated.gas_used >= without {
+        debug!(with = %generated.gas_used, %without, "Access list does not save gas, skipping");
+        return Ok(());
+    }
+
+    debug!(
+        with = %generated.gas_used,
+        %without,
+        entries = generated.access_list.0.len(),
+        "Using access list"
+    );
+    tx.set_access_list(generated.access_list);
+    Ok(())
+}
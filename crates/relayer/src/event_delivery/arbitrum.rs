@@ -0,0 +1,54 @@
+use anyhow::{anyhow, Result};
+use ethers::{
+    abi::{self, ParamType, Token},
+    core::types::{transaction::eip2718::TypedTransaction, Address, TransactionRequest, U256},
+    providers::Middleware,
+    utils::id,
+};
+
+// Arbitrum's NodeInterface precompile, only reachable through `eth_call`
+const NODE_INTERFACE: u64 = 0xc8;
+const L1_COMPONENT_SIGNATURE: &str = "gasEstimateL1Component(address,bool,bytes)";
+
+/// The part of a transaction's Arbitrum gas estimate that pays for posting
+/// its calldata to L1, in L2 gas units
+///
+/// This part scales with the L1 base fee rather than with execution, so
+/// padding it along with the execution estimate only overpays.
+pub async fn l1_gas_component<M: Middleware>(client: &M, tx: &TypedTransaction) -> Result<U256>
+where
+    M::Error: 'static,
+{
+    let to = tx
+        .to_addr()
+        .copied()
+        .ok_or_else(|| anyhow!("Delivery has no destination address"))?;
+    let data = tx.data().cloned().unwrap_or_default();
+
+    let mut calldata = id(L1_COMPONENT_SIGNATURE).to_vec();
+    calldata.extend(abi::encode(&[
+        Token::Address(to),
+        Token::Bool(false),
+        Token::Bytes(data.to_vec()),
+    ]));
+    let mut call = TransactionRequest::new()
+        .to(Address::from_low_u64_be(NODE_INTERFACE))
+        .data(calldata);
+    if let Some(from) = tx.from() {
+        call = call.from(*from);
+    }
+
+    let output = client.call(&call.into(), None).await?;
+    let decoded = abi::decode(
+        &[
+            ParamType::Uint(64),
+            ParamType::Uint(256),
+            ParamType::Uint(256),
+        ],
+        &output,
+    )?;
+    match decoded.first() {
+        Some(Token::Uint(l1_gas)) => Ok(*l1_gas),
+        _ => Err(anyhow!("Unexpected NodeInterface response")),
+    }
+}
@@ -0,0 +1,190 @@
+use anyhow::{Context, Result};
+use ethers::{
+    core::types::{Address, U256},
+    providers::{Http, Middleware, Provider},
+};
+use std::{
+    collections::HashSet,
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tracing::{error, info, warn};
+
+use crate::accounting::{wei_to_eth, GasCostRecord, SpendRate};
+use crate::alerts::{self, AlertKind};
+use crate::config::{ChainConfig, GasTankConfig};
+use crate::health::{GasTank, Health};
+use crate::metrics::{SIGNER_BALANCE, SIGNER_RUNWAY, SPEND_RATE};
+use crate::store::StateStore;
+use crate::types::ChainId;
+
+/// Tracks the balance of every delivery wallet, which are too low to deliver
+/// with, and how long each lasts at the relayer's spend rate
+pub struct BalanceMonitor {
+    low: Mutex<HashSet<(ChainId, Address)>>,
+    /// Wallets projected to run dry within the configured runway
+    short: Mutex<HashSet<(ChainId, Address)>>,
+    gas_tank: GasTankConfig,
+    store: Arc<dyn StateStore>,
+    health: Health,
+}
+
+impl BalanceMonitor {
+    pub fn new(gas_tank: GasTankConfig, store: Arc<dyn StateStore>, health: Health) -> Self {
+        Self {
+            low: Mutex::new(HashSet::new()),
+            short: Mutex::new(HashSet::new()),
+            gas_tank,
+            store,
+            health,
+        }
+    }
+
+    /// Whether `signer`'s balance on the chain was last seen below its threshold
+    pub fn is_low(&self, chain_id: ChainId, signer: Address) -> bool {
+        self.low
+            .lock()
+            .expect("balance lock poisoned")
+            .contains(&(chain_id, signer))
+    }
+
+    /// Check every chain's wallets every `interval`, forever
+    pub async fn run(&self, wallets: Vec<(ChainConfig, Address)>, interval: Duration) {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            // Read once a round, as the history only grows
+            let costs = match self.store.gas_costs() {
+                Ok(costs) => Some(costs),
+                Err(e) => {
+                    warn!(error = %e, "Failed to read gas costs, not projecting runways");
+                    None
+                }
+            };
+            for (chain, signer) in &wallets {
+                if let Err(e) = self.check(chain, *signer, costs.as_deref()).await {
+                    warn!(error = %e, chain = %chain.name, %signer, "Failed to check signer balance");
+                }
+            }
+        }
+    }
+
+    async fn check(
+        &self,
+        chain: &ChainConfig,
+        signer: Address,
+        costs: Option<&[GasCostRecord]>,
+    ) -> Result<()> {
+        let provider = Provider::<Http>::try_from(&chain.rpc_url)
+            .context(format!("Failed to create provider for {}", chain.name))?;
+        let balance = provider.get_balance(signer, None).await?;
+        SIGNER_BALANCE
+            .with_label_values(&[&chain.name, &format!("{:?}", signer)])
+            .set(wei_to_eth(balance));
+        if let Some(costs) = costs {
+            self.project(chain, signer, balance, costs);
+        }
+
+        let Some(threshold) = chain.min_balance_wei else {
+            return Ok(());
+        };
+        let is_low = balance < U256::from(threshold);
+        let mut low = self.low.lock().expect("balance lock poisoned");
+        if is_low && low.insert((chain.chain_id, signer)) {
+            error!(
+                chain = %chain.name,
+                %signer,
+                %balance,
+                threshold,
+                "ALERT: signer balance below threshold, halting its deliveries"
+            );
+            alerts::notify(
+                AlertKind::LowBalance,
+                format!("{} {:?}", chain.name, signer),
+                format!(
+                    "Signer {:?} on {} holds {} wei, below the {} wei threshold; \
+                     its deliveries are halted",
+                    signer, chain.name, balance, threshold
+                ),
+            );
+        } else if !is_low && low.remove(&(chain.chain_id, signer)) {
+            info!(
+                chain = %chain.name,
+                %signer,
+                %balance,
+                "Signer balance restored, resuming deliveries"
+            );
+        }
+        Ok(())
+    }
+
+    // Project how long the balance lasts at the wallet's spend over the
+    // trailing window, alerting once when that drops below the minimum runway
+    fn project(
+        &self,
+        chain: &ChainConfig,
+        signer: Address,
+        balance: U256,
+        costs: &[GasCostRecord],
+    ) {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or_default();
+        let window_secs = self.gas_tank.spend_window_ms / 1_000;
+        let rate = SpendRate::over(costs, chain.chain_id, window_secs, now);
+        let spend = rate.per_wallet.get(&signer).copied().unwrap_or_default();
+        let runway_days = (!spend.is_zero()).then(|| wei_to_eth(balance) / wei_to_eth(spend));
+
+        SPEND_RATE
+            .with_label_values(&[&chain.name])
+            .set(wei_to_eth(rate.per_day));
+        SIGNER_RUNWAY
+            .with_label_values(&[&chain.name, &format!("{:?}", signer)])
+            .set(runway_days.unwrap_or(f64::INFINITY));
+        self.health.gas_tank_checked(GasTank {
+            chain_id: chain.chain_id,
+            chain: chain.name.clone(),
+            wallet: signer,
+            balance_wei: balance,
+            chain_spend_per_day_wei: rate.per_day,
+            spend_per_day_wei: spend,
+            runway_days,
+            checked_at: now,
+        });
+
+        let Some(min_runway_days) = self.gas_tank.min_runway_days else {
+            return;
+        };
+        let is_short = runway_days.is_some_and(|days| days < min_runway_days);
+        let mut short = self.short.lock().expect("balance lock poisoned");
+        if is_short && short.insert((chain.chain_id, signer)) {
+            let days = runway_days.unwrap_or_default();
+            warn!(
+                chain = %chain.name,
+                %signer,
+                %balance,
+                spend_per_day = %spend,
+                runway_days = days,
+                "ALERT: signer projected to run dry soon"
+            );
+            alerts::notify(
+                AlertKind::LowRunway,
+                format!("{} {:?}", chain.name, signer),
+                format!(
+                    "Signer {:?} on {} holds {} wei and spends {} wei a day, \
+                     lasting {:.1} days, under the {} day minimum; top it up",
+                    signer, chain.name, balance, spend, days, min_runway_days
+                ),
+            );
+        } else if !is_short && short.remove(&(chain.chain_id, signer)) {
+            info!(
+                chain = %chain.name,
+                %signer,
+                %balance,
+                runway_days = ?runway_days,
+                "Signer runway restored"
+            );
+        }
+    }
+}
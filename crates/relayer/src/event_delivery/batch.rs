@@ -0,0 +1,143 @@
+use anyhow::{anyhow, Result};
+use ethers::{
+    abi::{self, ParamType, Token},
+    core::types::{
+        transaction::eip2718::TypedTransaction, Address, BlockId, Bytes, TransactionRequest,
+    },
+    providers::Middleware,
+    utils::id,
+};
+
+use crate::config::ChainConfig;
+
+// Multicall3 is deployed at the same address on nearly every EVM chain
+const MULTICALL3_ADDRESS: &str = "0xcA11bde05977b3631167028862bE2a173976CA11";
+const AGGREGATE3_SIGNATURE: &str = "aggregate3((address,bool,bytes)[])";
+
+/// Multicall3 contract to batch deliveries through on `chain`
+pub fn multicall_address(chain: &ChainConfig) -> Address {
+    chain.multicall_address.unwrap_or_else(|| {
+        MULTICALL3_ADDRESS
+            .parse()
+            .expect("Multicall3 address is valid")
+    })
+}
+
+/// Calldata for `aggregate3` with every call allowed to fail on its own
+pub fn encode_aggregate3(calls: &[(Address, Bytes)]) -> Bytes {
+    let calls = calls
+        .iter()
+        .map(|(target, data)| {
+            Token::Tuple(vec![
+                Token::Address(*target),
+                Token::Bool(true),
+                Token::Bytes(data.to_vec()),
+            ])
+        })
+        .collect();
+
+    let mut calldata = id(AGGREGATE3_SIGNATURE).to_vec();
+    calldata.extend(abi::encode(&[Token::Array(calls)]));
+    calldata.into()
+}
+
+// Per-call `(success, returnData)` pairs returned by `aggregate3`
+fn decode_aggregate3(data: &[u8]) -> Result<Vec<(bool, Bytes)>> {
+    let result_type = ParamType::Array(Box::new(ParamType::Tuple(vec![
+        ParamType::Bool,
+        ParamType::Bytes,
+    ])));
+    let tokens = abi::decode(&[result_type], data)?;
+    let Some(Token::Array(results)) = tokens.into_iter().next() else {
+        return Err(anyhow!("Unexpected aggregate3 result"));
+    };
+
+    results
+        .into_iter()
+        .map(|result| match result {
+            Token::Tuple(fields) => match fields.as_slice() {
+                [Token::Bool(success), Token::Bytes(return_data)] => {
+                    Ok((*success, Bytes::from(return_data.clone())))
+                }
+                _ => Err(anyhow!("Unexpected aggregate3 call result")),
+            },
+            _ => Err(anyhow!("Unexpected aggregate3 call result")),
+        })
+        .collect()
+}
+
+/// Run a batch through `eth_call`, returning each call's success and return data
+pub async fn simulate<M: Middleware>(
+    client: &M,
+    multicall: Address,
+    calls: &[(Address, Bytes)],
+    block: Option<BlockId>,
+) -> Result<Vec<(bool, Bytes)>>
+where
+    M::Error: 'static,
+{
+    let tx: TypedTransaction = TransactionRequest::new()
+        .from(client.default_sender().unwrap_or_default())
+        .to(multicall)
+        .data(encode_aggregate3(calls))
+        .into();
+    let output = client.call(&tx, block).await?;
+    decode_aggregate3(&output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_every_call_allowed_to_fail() {
+        let calls = vec![
+            (Address::repeat_byte(1), Bytes::from(vec![0xaa; 3])),
+            (Address::repeat_byte(2), Bytes::new()),
+        ];
+
+        let calldata = encode_aggregate3(&calls);
+
+        assert_eq!(calldata[..4], id(AGGREGATE3_SIGNATURE));
+        let call_type =
+            ParamType::Tuple(vec![ParamType::Address, ParamType::Bool, ParamType::Bytes]);
+        let decoded =
+            abi::decode(&[ParamType::Array(Box::new(call_type))], &calldata[4..]).unwrap();
+        let expected: Vec<Token> = calls
+            .iter()
+            .map(|(target, data)| {
+                Token::Tuple(vec![
+                    Token::Address(*target),
+                    Token::Bool(true),
+                    Token::Bytes(data.to_vec()),
+                ])
+            })
+            .collect();
+        assert_eq!(decoded, [Token::Array(expected)]);
+    }
+
+    #[test]
+    fn decodes_each_call_result() {
+        let results = abi::encode(&[Token::Array(vec![
+            Token::Tuple(vec![Token::Bool(true), Token::Bytes(vec![1, 2])]),
+            Token::Tuple(vec![Token::Bool(false), Token::Bytes(vec![0x08, 0xc3])]),
+        ])]);
+
+        let decoded = decode_aggregate3(&results).unwrap();
+
+        assert_eq!(
+            decoded,
+            [
+                (true, Bytes::from(vec![1, 2])),
+                (false, Bytes::from(vec![0x08, 0xc3]))
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_results_that_are_not_aggregate3_output() {
+        assert!(decode_aggregate3(&[0xff; 7]).is_err());
+        let wrong_shape = abi::encode(&[Token::Array(vec![Token::Uint(1.into())])]);
+        assert!(decode_aggregate3(&wrong_shape).is_err());
+    }
+}
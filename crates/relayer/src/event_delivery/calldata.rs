@@ -0,0 +1,174 @@
+use anyhow::{anyhow, Result};
+use ethers::{
+    abi::{self, Function, ParamType, Token},
+    core::types::{Address, Bytes},
+    utils::id,
+};
+
+use crate::config::CallEncoding;
+
+const EXECUTE_WITH_PROOF_SIGNATURE: &str = "executeWithProof(address,bytes,bytes)";
+const RELAY_MESSAGE_SIGNATURE: &str =
+    "relayMessage((address,uint256,uint256,uint256,uint256),bytes)";
+const FILL_SIGNATURE: &str = "fill(bytes32,bytes,bytes)";
+
+// Parse a single human-readable function signature such as `execute(bytes,bytes)`
+fn parse_function(signature: &str) -> Result<Function> {
+    let parsed = abi::parse_abi(&[&format!("function {} external", signature)])?;
+    parsed
+        .functions()
+        .next()
+        .cloned()
+        .ok_or_else(|| anyhow!("Invalid function signature {}", signature))
+}
+
+/// Build the destination calldata carrying the exec payload and its proof
+pub fn encode(encoding: &CallEncoding, payload: &[u8], proof: &[u8]) -> Result<Bytes> {
+    match encoding {
+        CallEncoding::Concat => Ok([payload, proof].concat().into()),
+        CallEncoding::Function { signature } => {
+            let function = parse_function(signature)?;
+            Ok(function
+                .encode_input(&[Token::Bytes(payload.to_vec()), Token::Bytes(proof.to_vec())])?
+                .into())
+        }
+        CallEncoding::AppendProof { signature } => append_proof(signature, payload, proof),
+    }
+}
+
+/// Calldata for `executeWithProof(address,bytes,bytes)` on a shared executor contract
+pub fn executor_call(target: Address, payload: &[u8], proof: &[u8]) -> Bytes {
+    let mut calldata = id(EXECUTE_WITH_PROOF_SIGNATURE).to_vec();
+    calldata.extend(abi::encode(&[
+        Token::Address(target),
+        Token::Bytes(payload.to_vec()),
+        Token::Bytes(proof.to_vec()),
+    ]));
+    calldata.into()
+}
+
+/// Calldata for the interop messenger's `relayMessage`, whose ABI-encoded
+/// arguments an interop proof already is
+pub fn relay_message_call(proof: &[u8]) -> Bytes {
+    [&id(RELAY_MESSAGE_SIGNATURE)[..], proof].concat().into()
+}
+
+/// Calldata for an ERC-7683 destination settler's `fill`, from a payload
+/// holding the ABI-encoded order ID and origin data, with the proof as the
+/// filler data
+pub fn fill_call(payload: &[u8], proof: &[u8]) -> Result<Bytes> {
+    let mut arguments = abi::decode(&[ParamType::FixedBytes(32), ParamType::Bytes], payload)
+        .map_err(|e| anyhow!("Exec payload is not an ERC-7683 order: {}", e))?;
+    arguments.push(Token::Bytes(proof.to_vec()));
+    let mut calldata = id(FILL_SIGNATURE).to_vec();
+    calldata.extend(abi::encode(&arguments));
+    Ok(calldata.into())
+}
+
+// Re-encode the payload's call with the proof added as its final `bytes`
+// argument, so offsets into the dynamic section stay correct
+fn append_proof(signature: &str, payload: &[u8], proof: &[u8]) -> Result<Bytes> {
+    let function = parse_function(signature)?;
+    let Some((last, payload_params)) = function.inputs.split_last() else {
+        return Err(anyhow!("{} takes no proof argument", signature));
+    };
+    if last.kind != ParamType::Bytes {
+        return Err(anyhow!("Last argument of {} must be bytes", signature));
+    }
+    if payload.len() < 4 {
+        return Err(anyhow!("Exec payload is too short to be a function call"));
+    }
+
+    let payload_types: Vec<ParamType> = payload_params
+        .iter()
+        .map(|param| param.kind.clone())
+        .collect();
+    let mut arguments = abi::decode(&payload_types, &payload[4..])?;
+    arguments.push(Token::Bytes(proof.to_vec()));
+    Ok(function.encode_input(&arguments)?.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn concatenates_payload_and_proof() {
+        let calldata = encode(&CallEncoding::Concat, &[1, 2], &[3]).unwrap();
+
+        assert_eq!(calldata.to_vec(), [1, 2, 3]);
+    }
+
+    #[test]
+    fn passes_payload_and_proof_as_bytes_arguments() {
+        let encoding = CallEncoding::Function {
+            signature: "execute(bytes,bytes)".to_string(),
+        };
+
+        let calldata = encode(&encoding, &[0xaa; 40], &[0xbb; 3]).unwrap();
+
+        assert_eq!(calldata[..4], id("execute(bytes,bytes)"));
+        let arguments = abi::decode(&[ParamType::Bytes, ParamType::Bytes], &calldata[4..]).unwrap();
+        assert_eq!(
+            arguments,
+            [Token::Bytes(vec![0xaa; 40]), Token::Bytes(vec![0xbb; 3])]
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_function_signatures() {
+        let encoding = CallEncoding::Function {
+            signature: "not a signature".to_string(),
+        };
+
+        assert!(encode(&encoding, &[], &[]).is_err());
+    }
+
+    #[test]
+    fn appends_the_proof_after_the_payload_arguments() {
+        let signature = "receive(uint256,bytes,bytes)";
+        let function = parse_function(signature).unwrap();
+        let message = vec![0xcc; 70];
+        // The dapp's call without its proof, as the source emitted it
+        let mut payload = id(signature).to_vec();
+        payload.extend(abi::encode(&[
+            Token::Uint(7.into()),
+            Token::Bytes(message.clone()),
+        ]));
+        let proof = vec![0xdd; 33];
+
+        let calldata = encode(
+            &CallEncoding::AppendProof {
+                signature: signature.to_string(),
+            },
+            &payload,
+            &proof,
+        )
+        .unwrap();
+
+        // Offsets are recomputed for the extra head word, which concatenating
+        // the proof's encoding onto the payload would get wrong
+        assert_eq!(
+            function.decode_input(&calldata[4..]).unwrap(),
+            [
+                Token::Uint(7.into()),
+                Token::Bytes(message),
+                Token::Bytes(proof)
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_append_proof_functions_not_ending_in_bytes() {
+        let append = |signature: &str, payload: &[u8]| {
+            let encoding = CallEncoding::AppendProof {
+                signature: signature.to_string(),
+            };
+            encode(&encoding, payload, &[1])
+        };
+
+        assert!(append("receive(uint256,uint256)", &[0; 36]).is_err());
+        assert!(append("receive()", &[0; 4]).is_err());
+        assert!(append("receive(bytes)", &[0; 3]).is_err());
+    }
+}
@@ -0,0 +1,116 @@
+use anyhow::{anyhow, Result};
+use ethers::{
+    core::types::{TransactionReceipt, H256},
+    providers::Middleware,
+};
+use std::time::Duration;
+use tokio::time::{self, Instant};
+use tracing::{debug, warn};
+
+use crate::clock::Clock;
+use crate::config::ChainConfig;
+use crate::types::RelayerError;
+
+const CONFIRMATION_POLL_INTERVAL: Duration = Duration::from_secs(3);
+const RECEIPT_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Poll for the receipt of a sent transaction until it is mined, giving up
+/// after `timeout`
+///
+/// Housekeeping transactions use this rather than `PendingTransaction`, which
+/// waits a whole provider interval before its first poll.
+pub(crate) async fn wait_for_receipt<M: Middleware>(
+    client: &M,
+    tx_hash: H256,
+    timeout: Duration,
+) -> Result<TransactionReceipt>
+where
+    M::Error: 'static,
+{
+    let deadline = Instant::now() + timeout;
+    loop {
+        time::sleep(RECEIPT_POLL_INTERVAL).await;
+        if let Some(receipt) = client.get_transaction_receipt(tx_hash).await? {
+            return Ok(receipt);
+        }
+        if Instant::now() >= deadline {
+            return Err(anyhow!(
+                "Transaction {:?} not mined within {}s",
+                tx_hash,
+                timeout.as_secs()
+            ));
+        }
+    }
+}
+
+/// Wait until a mined delivery is `dest_chain.confirmations` blocks deep and
+/// still canonical
+///
+/// The receipt is fetched again once deep enough; if the transaction moved to
+/// another block the wait restarts from there, and if it is gone entirely
+/// the delivery is reported as reorged out. A chain whose head stops
+/// advancing for `stall_timeout` fails the wait as retryable, rather than
+/// holding the delivery forever.
+pub async fn wait_for_confirmations<M: Middleware>(
+    client: &M,
+    mut receipt: TransactionReceipt,
+    dest_chain: &ChainConfig,
+    stall_timeout: Duration,
+    clock: &dyn Clock,
+) -> Result<TransactionReceipt>
+where
+    M::Error: 'static,
+{
+    let confirmations = dest_chain.confirmations;
+    if confirmations <= 1 {
+        return Ok(receipt);
+    }
+
+    loop {
+        let Some(mined_at) = receipt.block_number else {
+            return Ok(receipt);
+        };
+        let target = mined_at.as_u64() + confirmations - 1;
+        let mut head = client.get_block_number().await?.as_u64();
+        let mut advanced_at = clock.now();
+        while head < target {
+            if clock.now().saturating_duration_since(advanced_at) >= stall_timeout {
+                return Err(RelayerError::ConfirmationsStalled {
+                    chain_id: dest_chain.chain_id,
+                    tx_hash: receipt.transaction_hash,
+                    head,
+                }
+                .into());
+            }
+            clock.sleep(CONFIRMATION_POLL_INTERVAL).await;
+            let current = client.get_block_number().await?.as_u64();
+            if current > head {
+                head = current;
+                advanced_at = clock.now();
+            }
+        }
+
+        match client
+            .get_transaction_receipt(receipt.transaction_hash)
+            .await?
+        {
+            Some(current) if current.block_hash == receipt.block_hash => {
+                debug!(confirmations, "Delivery confirmed");
+                return Ok(current);
+            }
+            Some(current) => {
+                warn!(
+                    tx_hash = ?receipt.transaction_hash,
+                    "Delivery was reorged into another block, waiting again"
+                );
+                receipt = current;
+            }
+            None => {
+                return Err(RelayerError::DeliveryReorged {
+                    tx_hash: receipt.transaction_hash,
+                }
+                .into())
+            }
+        }
+    }
+}
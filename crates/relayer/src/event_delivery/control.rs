@@ -0,0 +1,173 @@
+use anyhow::{anyhow, Result};
+use ethers::core::types::{Address, H256, U256};
+use serde::Serialize;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+use tokio::sync::{mpsc, oneshot};
+
+use crate::types::ChainId;
+
+/// Operator action on a delivery that is waiting to be mined
+pub enum ControlCommand {
+    /// Replace the delivery with a zero-value self-transfer at the same nonce
+    Cancel(oneshot::Sender<Result<H256>>),
+    /// Re-broadcast now with bumped fees instead of waiting for the stuck timeout
+    Replace(oneshot::Sender<Result<H256>>),
+}
+
+/// A broadcast delivery that has not been mined yet
+#[derive(Debug, Clone, Serialize)]
+pub struct InFlightDelivery {
+    pub chain_id: ChainId,
+    /// Delivery wallet that sent it
+    pub sender: Address,
+    /// Transaction nonce shared by every broadcast of the delivery
+    pub nonce: U256,
+    /// Relay pair delivered to, unset for batches
+    pub pair: Option<String>,
+    /// Every broadcast so far, oldest first
+    pub tx_hashes: Vec<H256>,
+}
+
+struct Entry {
+    commands: mpsc::Sender<ControlCommand>,
+    pair: Option<String>,
+    tx_hashes: Vec<H256>,
+}
+
+type Key = (ChainId, Address, U256);
+type Registry = Arc<Mutex<HashMap<Key, Entry>>>;
+
+/// Handle for inspecting and intervening in deliveries that are in flight
+#[derive(Clone, Default)]
+pub struct DeliveryControl {
+    registry: Registry,
+}
+
+impl DeliveryControl {
+    /// Deliveries currently waiting to be mined
+    pub fn in_flight(&self) -> Vec<InFlightDelivery> {
+        let registry = self
+            .registry
+            .lock()
+            .expect("delivery control lock poisoned");
+        registry
+            .iter()
+            .map(|((chain_id, sender, nonce), entry)| InFlightDelivery {
+                chain_id: *chain_id,
+                sender: *sender,
+                nonce: *nonce,
+                pair: entry.pair.clone(),
+                tx_hashes: entry.tx_hashes.clone(),
+            })
+            .collect()
+    }
+
+    /// Cancel the in-flight delivery `sender` sent with `nonce` on `chain_id`,
+    /// returning the hash of the cancelling transaction
+    pub async fn cancel(&self, chain_id: ChainId, sender: Address, nonce: U256) -> Result<H256> {
+        self.send((chain_id, sender, nonce), ControlCommand::Cancel)
+            .await
+    }
+
+    /// Force a fee-bumped re-broadcast of the in-flight delivery `sender` sent
+    /// with `nonce` on `chain_id`, returning the new transaction hash
+    pub async fn replace(&self, chain_id: ChainId, sender: Address, nonce: U256) -> Result<H256> {
+        self.send((chain_id, sender, nonce), ControlCommand::Replace)
+            .await
+    }
+
+    async fn send(
+        &self,
+        key: Key,
+        command: fn(oneshot::Sender<Result<H256>>) -> ControlCommand,
+    ) -> Result<H256> {
+        let commands = self
+            .registry
+            .lock()
+            .expect("delivery control lock poisoned")
+            .get(&key)
+            .map(|entry| entry.commands.clone())
+            .ok_or_else(|| {
+                anyhow!(
+                    "No delivery in flight on chain {} from {:?} with nonce {}",
+                    key.0,
+                    key.1,
+                    key.2
+                )
+            })?;
+
+        let (reply_tx, reply_rx) = oneshot::channel();
+        commands
+            .send(command(reply_tx))
+            .await
+            .map_err(|_| anyhow!("Delivery finished before the command was handled"))?;
+        reply_rx
+            .await
+            .map_err(|_| anyhow!("Delivery finished before the command was handled"))?
+    }
+
+    /// Make a delivery about to be broadcast controllable until the returned
+    /// registration is dropped
+    pub(crate) fn register(
+        &self,
+        chain_id: ChainId,
+        sender: Address,
+        nonce: U256,
+        pair: Option<String>,
+    ) -> Registration {
+        let (commands_tx, commands_rx) = mpsc::channel(4);
+        self.registry
+            .lock()
+            .expect("delivery control lock poisoned")
+            .insert(
+                (chain_id, sender, nonce),
+                Entry {
+                    commands: commands_tx,
+                    pair,
+                    tx_hashes: Vec::new(),
+                },
+            );
+        Registration {
+            registry: self.registry.clone(),
+            key: (chain_id, sender, nonce),
+            commands: commands_rx,
+        }
+    }
+}
+
+/// An in-flight delivery's end of the control channel
+pub struct Registration {
+    registry: Registry,
+    key: Key,
+    commands: mpsc::Receiver<ControlCommand>,
+}
+
+impl Registration {
+    /// Wait for the next operator command
+    pub async fn next_command(&mut self) -> Option<ControlCommand> {
+        self.commands.recv().await
+    }
+
+    /// Note a new broadcast of the delivery
+    pub fn record_broadcast(&self, tx_hash: H256) {
+        if let Some(entry) = self
+            .registry
+            .lock()
+            .expect("delivery control lock poisoned")
+            .get_mut(&self.key)
+        {
+            entry.tx_hashes.push(tx_hash);
+        }
+    }
+}
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        if let Ok(mut registry) = self.registry.lock() {
+            registry.remove(&self.key);
+        }
+    }
+}
@@ -0,0 +1,270 @@
+use anyhow::{anyhow, Result};
+use ethers::{
+    core::types::{transaction::eip2718::TypedTransaction, Bytes, TransactionReceipt, H256, U256},
+    providers::Middleware,
+};
+use std::{collections::HashSet, time::Duration};
+use tokio::time::Instant;
+use tracing::{info, warn};
+
+use super::control::{ControlCommand, Registration};
+use crate::config::DeliveryConfig;
+use crate::types::RelayerError;
+
+const RECEIPT_POLL_INTERVAL: Duration = Duration::from_secs(3);
+// Gas for a plain value transfer, all a cancellation needs
+const TRANSFER_GAS: u64 = 21_000;
+
+// Scale a fee up by `percent`, rounding up so small fees still move
+fn bump(fee: U256, percent: u64) -> U256 {
+    let scaled = fee * U256::from(100 + percent);
+    (scaled + U256::from(99)) / U256::from(100)
+}
+
+// Raise every fee field of the transaction, returning the new fee cap
+fn bump_fees(tx: &mut TypedTransaction, percent: u64) -> U256 {
+    match tx {
+        TypedTransaction::Eip1559(inner) => {
+            let tip = bump(inner.max_priority_fee_per_gas.unwrap_or_default(), percent);
+            let max_fee = bump(inner.max_fee_per_gas.unwrap_or_default(), percent);
+            inner.max_priority_fee_per_gas = Some(tip);
+            inner.max_fee_per_gas = Some(max_fee);
+            max_fee
+        }
+        TypedTransaction::Legacy(inner) => {
+            let gas_price = bump(inner.gas_price.unwrap_or_default(), percent);
+            inner.gas_price = Some(gas_price);
+            gas_price
+        }
+        TypedTransaction::Eip2930(inner) => {
+            let gas_price = bump(inner.tx.gas_price.unwrap_or_default(), percent);
+            inner.tx.gas_price = Some(gas_price);
+            gas_price
+        }
+    }
+}
+
+// Turn a delivery into a zero-value self-transfer that reuses its nonce
+fn into_cancellation(tx: &TypedTransaction) -> TypedTransaction {
+    let mut cancel = tx.clone();
+    let sender = tx.from().copied().unwrap_or_default();
+    cancel.set_to(sender);
+    cancel.set_data(Bytes::new());
+    cancel.set_value(U256::zero());
+    cancel.set_gas(TRANSFER_GAS);
+    if let TypedTransaction::Eip1559(inner) = &mut cancel {
+        inner.access_list = Default::default();
+    }
+    cancel
+}
+
+/// Broadcast a delivery and re-broadcast it with higher fees while it is stuck
+///
+/// Transactions go out through `broadcaster`, which may be a private relay,
+/// while receipts are polled through `client`.
+///
+/// Every replacement reuses the nonce of the original, so whichever broadcast
+/// is mined first wins and all of them are watched for a receipt. Once the
+/// next bump would go past `max_fee_per_gas_wei` the delivery is given up as
+/// stuck, leaving the last broadcast in the mempool.
+///
+/// Operators can force a bump or cancel the delivery through `control`; once
+/// cancelled, later bumps re-send the cancellation rather than the delivery.
+pub async fn send_with_escalation<M: Middleware, B: Middleware>(
+    client: &M,
+    broadcaster: &B,
+    mut tx: TypedTransaction,
+    config: &DeliveryConfig,
+    control: &mut Registration,
+) -> Result<TransactionReceipt>
+where
+    M::Error: 'static,
+    B::Error: 'static,
+{
+    let stuck_timeout = Duration::from_millis(config.stuck_timeout_ms);
+    let max_fee = U256::from(config.max_fee_per_gas_wei);
+
+    let first = broadcaster
+        .send_transaction(tx.clone(), None)
+        .await?
+        .tx_hash();
+    info!(tx_hash = ?first, "Proof submission transaction sent");
+    control.record_broadcast(first);
+    let mut hashes: Vec<H256> = vec![first];
+    let mut cancellations: HashSet<H256> = HashSet::new();
+
+    loop {
+        let deadline = Instant::now() + stuck_timeout;
+        let mut forced = None;
+        while forced.is_none() && Instant::now() < deadline {
+            tokio::select! {
+                _ = tokio::time::sleep(RECEIPT_POLL_INTERVAL) => {
+                    for hash in &hashes {
+                        if let Some(receipt) = client.get_transaction_receipt(*hash).await? {
+                            if cancellations.contains(hash) {
+                                return Err(RelayerError::DeliveryCancelled { tx_hash: *hash }.into());
+                            }
+                            return Ok(receipt);
+                        }
+                    }
+                }
+                Some(command) = control.next_command() => match command {
+                    ControlCommand::Replace(reply) => forced = Some(reply),
+                    ControlCommand::Cancel(reply) => {
+                        warn!("Operator cancelled delivery");
+                        let mut cancel = into_cancellation(&tx);
+                        bump_fees(&mut cancel, config.fee_bump_percent);
+                        let sent = broadcaster.send_transaction(cancel.clone(), None).await;
+                        let _ = reply.send(match sent {
+                            Ok(pending) => {
+                                let hash = pending.tx_hash();
+                                info!(tx_hash = ?hash, "Cancellation transaction sent");
+                                control.record_broadcast(hash);
+                                hashes.push(hash);
+                                cancellations.insert(hash);
+                                tx = cancel;
+                                Ok(hash)
+                            }
+                            Err(e) => Err(anyhow!("Cancellation broadcast rejected: {}", e)),
+                        });
+                    }
+                },
+            }
+        }
+
+        let last_hash = *hashes.last().expect("at least one broadcast");
+        let mut replacement = tx.clone();
+        let fee = bump_fees(&mut replacement, config.fee_bump_percent);
+        if fee > max_fee {
+            let stuck = RelayerError::DeliveryStuck {
+                tx_hash: last_hash,
+                attempts: hashes.len() as u32,
+            };
+            match forced {
+                // Refuse the operator's bump but keep waiting on what was sent
+                Some(reply) => {
+                    let _ = reply.send(Err(anyhow!("{}", stuck)));
+                    continue;
+                }
+                None => return Err(stuck.into()),
+            }
+        }
+
+        warn!(tx_hash = ?last_hash, %fee, "Delivery not mined in time, bumping fees");
+        let sent = broadcaster
+            .send_transaction(replacement.clone(), None)
+            .await;
+        let result = match sent {
+            Ok(pending) => {
+                let hash = pending.tx_hash();
+                info!(tx_hash = ?hash, "Replacement transaction sent");
+                control.record_broadcast(hash);
+                hashes.push(hash);
+                if cancellations.contains(&last_hash) {
+                    cancellations.insert(hash);
+                }
+                Ok(hash)
+            }
+            // Either an earlier broadcast was mined in the meantime, which the
+            // next round of receipt polls will pick up, or the bump was too
+            // small for the node to accept as a replacement
+            Err(e) => {
+                warn!(error = %e, "Replacement broadcast rejected");
+                Err(anyhow!("Replacement broadcast rejected: {}", e))
+            }
+        };
+        // Bump from here next time even if rejected, so an underpriced
+        // replacement still climbs towards the ceiling instead of repeating
+        tx = replacement;
+        if let Some(reply) = forced {
+            let _ = reply.send(result);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use ethers::{
+        core::types::{Address, Eip1559TransactionRequest},
+        providers::{JsonRpcClient, JsonRpcError, MockError, Provider},
+    };
+    use serde::{de::DeserializeOwned, Serialize};
+    use serde_json::Value;
+    use std::sync::Mutex;
+
+    use super::super::control::DeliveryControl;
+    use crate::types::ChainId;
+
+    // Node that never mines anything and rejects the first replacement as
+    // underpriced, recording the fee cap of every broadcast it is sent
+    #[derive(Debug, Default)]
+    struct UnderpricedNode {
+        fee_caps: Mutex<Vec<U256>>,
+    }
+
+    #[async_trait]
+    impl JsonRpcClient for UnderpricedNode {
+        type Error = MockError;
+
+        async fn request<T, R>(&self, method: &str, params: T) -> Result<R, MockError>
+        where
+            T: std::fmt::Debug + Serialize + Send + Sync,
+            R: DeserializeOwned + Send,
+        {
+            let result = match method {
+                "eth_sendTransaction" => {
+                    let params = serde_json::to_value(params)?;
+                    let fee_cap: U256 = serde_json::from_value(params[0]["maxFeePerGas"].clone())?;
+                    let mut fee_caps = self.fee_caps.lock().unwrap();
+                    fee_caps.push(fee_cap);
+                    if fee_caps.len() == 2 {
+                        return Err(MockError::JsonRpcError(JsonRpcError {
+                            code: -32000,
+                            message: "replacement transaction underpriced".to_string(),
+                            data: None,
+                        }));
+                    }
+                    serde_json::to_value(H256::from_low_u64_be(fee_caps.len() as u64))?
+                }
+                _ => Value::Null,
+            };
+            Ok(serde_json::from_value(result)?)
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn rejected_replacements_still_climb_to_the_fee_ceiling() {
+        let node = Provider::new(UnderpricedNode::default());
+        let tx: TypedTransaction = Eip1559TransactionRequest::new()
+            .from(Address::repeat_byte(1))
+            .to(Address::repeat_byte(2))
+            .nonce(7)
+            .gas(21_000)
+            .max_fee_per_gas(100)
+            .max_priority_fee_per_gas(10)
+            .into();
+        let config = DeliveryConfig {
+            stuck_timeout_ms: 10_000,
+            fee_bump_percent: 10,
+            max_fee_per_gas_wei: 125,
+            ..Default::default()
+        };
+        let control = DeliveryControl::default();
+        let mut registration =
+            control.register(ChainId::new(1), Address::repeat_byte(1), 7.into(), None);
+
+        let error = send_with_escalation(&node, &node, tx, &config, &mut registration)
+            .await
+            .unwrap_err();
+
+        // The rejected bump to 110 is built on rather than retried
+        let fee_caps = node.as_ref().fee_caps.lock().unwrap().clone();
+        assert_eq!(fee_caps, vec![100.into(), 110.into(), 121.into()]);
+        assert!(matches!(
+            error.downcast_ref::<RelayerError>(),
+            Some(RelayerError::DeliveryStuck { attempts: 2, .. })
+        ));
+    }
+}
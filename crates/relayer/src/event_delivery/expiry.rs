@@ -0,0 +1,32 @@
+use anyhow::{anyhow, Result};
+use ethers::{
+    abi::{self, Token},
+    core::types::Bytes,
+};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::types::RelayEvent;
+
+/// Whether an event's delivery deadline had passed at `now`
+///
+/// Detection times are whole seconds, which is why pair validation only
+/// accepts deadlines of whole seconds. Events persisted before detection
+/// times were recorded carry no time and never expire.
+pub fn is_expired(event: &RelayEvent, deadline_ms: u64, now: SystemTime) -> bool {
+    if event.detected_at == 0 {
+        return false;
+    }
+    let deadline =
+        UNIX_EPOCH + Duration::from_secs(event.detected_at) + Duration::from_millis(deadline_ms);
+    now > deadline
+}
+
+/// Calldata for the dapp's cancel/refund function, called with the message nonce
+pub fn cancel_calldata(signature: &str, nonce: u64) -> Result<Bytes> {
+    let cancel_abi = abi::parse_abi(&[&format!("function {} external", signature)])?;
+    let function = cancel_abi
+        .functions()
+        .next()
+        .ok_or_else(|| anyhow!("Invalid cancel function signature {}", signature))?;
+    Ok(function.encode_input(&[Token::Uint(nonce.into())])?.into())
+}
@@ -0,0 +1,132 @@
+use anyhow::{anyhow, Result};
+use ethers::{
+    core::types::{
+        transaction::eip2718::TypedTransaction, Address, BlockNumber, Bytes,
+        Eip1559TransactionRequest, TransactionRequest, U256,
+    },
+    providers::Middleware,
+};
+use std::{collections::HashMap, sync::Mutex};
+use tracing::{debug, info, warn};
+
+use crate::config::{ChainConfig, TransactionType};
+use crate::types::ChainId;
+
+/// Which chains take EIP-1559 transactions, probed once per chain
+#[derive(Default)]
+pub struct FeeMarkets {
+    eip1559: Mutex<HashMap<ChainId, bool>>,
+}
+
+impl FeeMarkets {
+    /// Whether deliveries on `chain` should be EIP-1559 transactions
+    ///
+    /// A configured transaction type wins; otherwise the chain counts as
+    /// EIP-1559 if its latest block carries a base fee.
+    pub async fn supports_eip1559<M: Middleware>(
+        &self,
+        client: &M,
+        chain: &ChainConfig,
+    ) -> Result<bool>
+    where
+        M::Error: 'static,
+    {
+        if chain.legacy_transactions {
+            return Ok(false);
+        }
+        match chain.transaction_type {
+            TransactionType::Legacy => return Ok(false),
+            TransactionType::Eip1559 => return Ok(true),
+            TransactionType::Auto => {}
+        }
+        if let Some(eip1559) = self.cached(chain.chain_id) {
+            return Ok(eip1559);
+        }
+
+        let eip1559 = latest_base_fee(client, chain).await?.is_some();
+        info!(
+            chain = %chain.name,
+            transaction_type = if eip1559 { "eip1559" } else { "legacy" },
+            "Detected fee market"
+        );
+        self.eip1559
+            .lock()
+            .expect("fee market lock poisoned")
+            .insert(chain.chain_id, eip1559);
+        Ok(eip1559)
+    }
+
+    fn cached(&self, chain_id: ChainId) -> Option<bool> {
+        self.eip1559
+            .lock()
+            .expect("fee market lock poisoned")
+            .get(&chain_id)
+            .copied()
+    }
+}
+
+async fn latest_base_fee<M: Middleware>(client: &M, chain: &ChainConfig) -> Result<Option<U256>>
+where
+    M::Error: 'static,
+{
+    Ok(client
+        .get_block(BlockNumber::Latest)
+        .await?
+        .ok_or_else(|| anyhow!("Latest block not found on {}", chain.name))?
+        .base_fee_per_gas)
+}
+
+/// Build the delivery transaction with fees suited to the destination chain
+///
+/// EIP-1559 transactions are capped at twice the latest base fee plus the
+/// priority tip; everything else gets a legacy gas-price transaction. A chain
+/// forced to EIP-1559 whose latest block has no base fee falls back to legacy.
+pub async fn build_transaction<M: Middleware>(
+    client: &M,
+    chain: &ChainConfig,
+    eip1559: bool,
+    to: Address,
+    data: Bytes,
+) -> Result<TypedTransaction>
+where
+    M::Error: 'static,
+{
+    let base_fee = if eip1559 {
+        let base_fee = latest_base_fee(client, chain).await?;
+        if base_fee.is_none() {
+            warn!(chain = %chain.name, "Latest block has no base fee, sending legacy transaction");
+        }
+        base_fee
+    } else {
+        None
+    };
+
+    let Some(base_fee) = base_fee else {
+        let gas_price = client.get_gas_price().await?;
+        debug!(%gas_price, "Using legacy transaction");
+        return Ok(TransactionRequest::new()
+            .to(to)
+            .data(data)
+            .gas_price(gas_price)
+            .into());
+    };
+
+    let priority_fee = match chain.priority_fee_wei {
+        Some(tip) => U256::from(tip),
+        None => {
+            client
+                .provider()
+                .request::<_, U256>("eth_maxPriorityFeePerGas", ())
+                .await?
+        }
+    };
+    let max_fee = base_fee * 2 + priority_fee;
+    debug!(%base_fee, %priority_fee, %max_fee, "Using EIP-1559 transaction");
+
+    Ok(Eip1559TransactionRequest::new()
+        .to(to)
+        .data(data)
+        .max_priority_fee_per_gas(priority_fee)
+        .max_fee_per_gas(max_fee)
+        .into())
+}
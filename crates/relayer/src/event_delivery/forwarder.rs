@@ -0,0 +1,162 @@
+use anyhow::Result;
+use ethers::{
+    abi::{self, Token},
+    contract::Contract,
+    core::types::{
+        transaction::eip2718::TypedTransaction, Address, Bytes, TransactionRequest, H256, U256,
+    },
+    providers::Middleware,
+    signers::{LocalWallet, Signer},
+    utils::{id, keccak256},
+};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+use tokio::sync::OwnedMutexGuard;
+use tracing::debug;
+
+use super::nonce::NonceManager;
+use crate::config::ForwarderConfig;
+use crate::types::{ChainId, RelayerError};
+
+const DOMAIN_TYPE: &str =
+    "EIP712Domain(string name,string version,uint256 chainId,address verifyingContract)";
+const FORWARD_REQUEST_TYPE: &str =
+    "ForwardRequest(address from,address to,uint256 value,uint256 gas,uint256 nonce,bytes data)";
+const EXECUTE_SIGNATURE: &str = "execute((address,address,uint256,uint256,uint256,bytes),bytes)";
+
+// Held by the forwarded delivery of one signer on one chain that is in flight
+type SignerLock = Arc<tokio::sync::Mutex<()>>;
+
+/// Serializes forwarded deliveries per (chain, signer)
+///
+/// The forwarder only accepts a signer's requests in nonce order, but the
+/// forward request is signed before the outer transaction gets its nonce.
+/// Two forwarded deliveries in flight from one signer could be mined in the
+/// opposite order to their forwarder nonces, reverting the second, so each
+/// holds the signer's lock until its transaction has settled.
+#[derive(Default)]
+pub struct ForwardLocks {
+    locks: Mutex<HashMap<(ChainId, Address), SignerLock>>,
+}
+
+impl ForwardLocks {
+    /// Wait for the signer's other forwarded deliveries on `chain_id` to settle
+    pub async fn lock(&self, chain_id: ChainId, signer: Address) -> OwnedMutexGuard<()> {
+        let lock = self
+            .locks
+            .lock()
+            .expect("forward locks poisoned")
+            .entry((chain_id, signer))
+            .or_default()
+            .clone();
+        lock.lock_owned().await
+    }
+}
+
+/// Forwarder-wrapped delivery ready to be sent to the forwarder contract
+pub struct ForwardedCall {
+    pub forwarder: Address,
+    /// Signer whose forwarder nonce the request consumed
+    pub signer: Address,
+    pub calldata: Bytes,
+}
+
+// EIP-712 digest of a forward request under the forwarder's domain
+fn forward_request_digest(
+    config: &ForwarderConfig,
+    chain_id: ChainId,
+    forwarder: Address,
+    request: &[Token],
+    data: &[u8],
+) -> H256 {
+    let domain_separator = keccak256(abi::encode(&[
+        Token::FixedBytes(keccak256(DOMAIN_TYPE).to_vec()),
+        Token::FixedBytes(keccak256(&config.domain_name).to_vec()),
+        Token::FixedBytes(keccak256(&config.domain_version).to_vec()),
+        Token::Uint(chain_id.into()),
+        Token::Address(forwarder),
+    ]));
+
+    // `bytes data` is hashed in place; the other fields are encoded as-is
+    let mut fields = vec![Token::FixedBytes(keccak256(FORWARD_REQUEST_TYPE).to_vec())];
+    fields.extend_from_slice(&request[..5]);
+    fields.push(Token::FixedBytes(keccak256(data).to_vec()));
+    let struct_hash = keccak256(abi::encode(&fields));
+
+    let mut message = Vec::with_capacity(66);
+    message.extend_from_slice(&[0x19, 0x01]);
+    message.extend_from_slice(&domain_separator);
+    message.extend_from_slice(&struct_hash);
+    H256(keccak256(message))
+}
+
+/// Wrap a delivery in an ERC-2771 forward request signed by `signer`
+///
+/// Targets forwarders with the `MinimalForwarder` request layout. The dapp
+/// sees the signer as `_msgSender()` whichever key submits the outer
+/// transaction. The inner call's gas is estimated as if sent by the
+/// forwarder, with the signer address appended the way ERC-2771 does.
+pub async fn wrap<M: Middleware + 'static>(
+    client: Arc<M>,
+    nonces: &NonceManager,
+    config: &ForwarderConfig,
+    signer: &LocalWallet,
+    to: Address,
+    data: Bytes,
+    gas_multiplier: f64,
+) -> Result<ForwardedCall>
+where
+    M::Error: 'static,
+{
+    let forwarder = config.address;
+    let chain_id = ChainId::from(signer.chain_id());
+    let signer_address = signer.address();
+
+    let forwarder_abi =
+        abi::parse_abi(&["function getNonce(address from) external view returns (uint256)"])?;
+    let forwarder_contract = Contract::new(forwarder, forwarder_abi, client.clone());
+    let nonce = nonces
+        .next_with(chain_id, forwarder, signer_address, || async {
+            Ok(forwarder_contract
+                .method::<_, U256>("getNonce", signer_address)?
+                .call()
+                .await?)
+        })
+        .await?;
+
+    let inner: TypedTransaction = TransactionRequest::new()
+        .from(forwarder)
+        .to(to)
+        .data([data.as_ref(), signer_address.as_bytes()].concat())
+        .into();
+    let estimate = client.estimate_gas(&inner, None).await?;
+    let gas = U256::from((estimate.as_u128() as f64 * gas_multiplier.max(1.0)).ceil() as u128);
+
+    let request = vec![
+        Token::Address(signer_address),
+        Token::Address(to),
+        Token::Uint(U256::zero()),
+        Token::Uint(gas),
+        Token::Uint(nonce),
+        Token::Bytes(data.to_vec()),
+    ];
+    let digest = forward_request_digest(config, chain_id, forwarder, &request, &data);
+    let signature = signer
+        .sign_hash(digest)
+        .map_err(|e| RelayerError::Signing { source: e.into() })?;
+    debug!(%forwarder, %nonce, %gas, "Signed forward request");
+
+    let mut calldata = id(EXECUTE_SIGNATURE).to_vec();
+    calldata.extend(abi::encode(&[
+        Token::Tuple(request),
+        Token::Bytes(signature.to_vec()),
+    ]));
+
+    Ok(ForwardedCall {
+        forwarder,
+        signer: signer_address,
+        calldata: calldata.into(),
+    })
+}
@@ -0,0 +1,87 @@
+use anyhow::{anyhow, Result};
+use ethers::{
+    core::types::{transaction::eip2718::TypedTransaction, U256},
+    providers::Middleware,
+};
+use tracing::{debug, warn};
+
+use super::arbitrum;
+use crate::config::{ChainConfig, ChainKind, RelayPair};
+
+// Fixed-point scale for applying the fractional gas multiplier
+const MULTIPLIER_SCALE: u64 = 1_000;
+
+// Lower a padded gas limit to `cap`, refusing if even the unpadded need exceeds it
+fn clamp(gas_limit: U256, required: U256, cap: U256, what: &str) -> Result<U256> {
+    if required > cap {
+        return Err(anyhow!(
+            "Delivery needs {} gas, above the {} of {}",
+            required,
+            what,
+            cap
+        ));
+    }
+    if gas_limit > cap {
+        warn!(%gas_limit, %cap, what, "Padded gas limit exceeds cap, clamping");
+        return Ok(cap);
+    }
+    Ok(gas_limit)
+}
+
+/// Set the gas limit of a delivery
+///
+/// A pair's `delivery_gas_limit` is used as-is. Otherwise the node's estimate
+/// is scaled up by the multiplier, since estimates for proof verification
+/// are sometimes tight, and topped up with the chain's `extra_gas`. On
+/// Arbitrum only the L2 execution part of the estimate is scaled; the L1
+/// part is added back unchanged.
+///
+/// The pair's `max_gas_limit` caps the padded estimate, and the chain's
+/// `max_gas_limit` caps the final limit, whether fixed or estimated and
+/// including the L1 part and extra gas. Padding is trimmed to fit a cap, but
+/// a delivery whose unpadded need exceeds one is refused rather than sent
+/// with a limit that would run out of gas.
+pub async fn apply_gas_limit<M: Middleware>(
+    client: &M,
+    tx: &mut TypedTransaction,
+    multiplier: f64,
+    pair: Option<&RelayPair>,
+    chain: &ChainConfig,
+) -> Result<()>
+where
+    M::Error: 'static,
+{
+    let (required, mut gas_limit) = match pair.and_then(|pair| pair.delivery_gas_limit) {
+        Some(gas_limit) => (U256::from(gas_limit), U256::from(gas_limit)),
+        None => {
+            let estimate = client.estimate_gas(tx, None).await?;
+            let l1_gas = match chain.kind {
+                ChainKind::Arbitrum => match arbitrum::l1_gas_component(client, tx).await {
+                    Ok(l1_gas) => l1_gas.min(estimate),
+                    Err(e) => {
+                        warn!(error = %e, "Failed to split Arbitrum gas estimate, padding all of it");
+                        U256::zero()
+                    }
+                },
+                _ => U256::zero(),
+            };
+            let scaled = (multiplier.max(1.0) * MULTIPLIER_SCALE as f64).round() as u64;
+            let mut gas_limit = (estimate - l1_gas) * U256::from(scaled)
+                / U256::from(MULTIPLIER_SCALE)
+                + l1_gas
+                + U256::from(chain.extra_gas);
+            if let Some(cap) = pair.and_then(|pair| pair.max_gas_limit) {
+                gas_limit = clamp(gas_limit, estimate, cap.into(), "pair cap")?;
+            }
+            (estimate, gas_limit)
+        }
+    };
+
+    if let Some(max) = chain.max_gas_limit {
+        gas_limit = clamp(gas_limit, required, max.into(), "chain maximum")?;
+    }
+
+    debug!(%required, %gas_limit, "Set delivery gas limit");
+    tx.set_gas(gas_limit);
+    Ok(())
+}
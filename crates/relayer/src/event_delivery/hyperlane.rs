@@ -0,0 +1,74 @@
+use anyhow::Result;
+use ethers::{
+    abi::{self, Token},
+    contract::Contract,
+    core::types::{Address, Bytes, H256},
+    providers::Middleware,
+    utils::{id, keccak256},
+};
+use std::sync::Arc;
+
+use crate::config::HyperlaneConfig;
+use crate::types::{RelayEvent, RelayerError};
+
+const VERSION: u8 = 3;
+const PROCESS_SIGNATURE: &str = "process(bytes,bytes)";
+
+/// Hyperlane v3 message carrying an event's exec payload from the resolver
+/// to the dapp:
+/// `version . nonce . origin . sender . destination . recipient . body`,
+/// packed, with the addresses as `bytes32`
+pub fn message(config: &HyperlaneConfig, event: &RelayEvent) -> Result<Bytes, RelayerError> {
+    let nonce = u32::try_from(event.nonce).map_err(|_| {
+        RelayerError::InvalidPayload(format!(
+            "nonce {} does not fit a Hyperlane message",
+            event.nonce
+        ))
+    })?;
+    let origin = match config.origin_domain {
+        Some(domain) => domain,
+        None => event.source_chain.chain_id.as_u32()?,
+    };
+    let destination = match config.destination_domain {
+        Some(domain) => domain,
+        None => event.destination_chain.chain_id.as_u32()?,
+    };
+
+    let mut message = vec![VERSION];
+    message.extend(nonce.to_be_bytes());
+    message.extend(origin.to_be_bytes());
+    message.extend(H256::from(event.source_resolver_address).as_bytes());
+    message.extend(destination.to_be_bytes());
+    message.extend(H256::from(event.dest_dapp_address).as_bytes());
+    message.extend_from_slice(&event.exec_payload);
+    Ok(message.into())
+}
+
+/// Calldata for the Mailbox's `process`, with the proof as the metadata the
+/// recipient's ISM verifies
+pub fn process_call(proof: &[u8], message: &[u8]) -> Bytes {
+    let mut calldata = id(PROCESS_SIGNATURE).to_vec();
+    calldata.extend(abi::encode(&[
+        Token::Bytes(proof.to_vec()),
+        Token::Bytes(message.to_vec()),
+    ]));
+    calldata.into()
+}
+
+/// Ask the Mailbox whether `message` was already processed
+pub async fn is_delivered<M: Middleware + 'static>(
+    client: Arc<M>,
+    mailbox: Address,
+    message: &[u8],
+) -> Result<bool>
+where
+    M::Error: 'static,
+{
+    let mailbox_abi =
+        abi::parse_abi(&["function delivered(bytes32) external view returns (bool)"])?;
+    let mailbox = Contract::new(mailbox, mailbox_abi, client);
+    Ok(mailbox
+        .method::<_, bool>("delivered", H256(keccak256(message)))?
+        .call()
+        .await?)
+}
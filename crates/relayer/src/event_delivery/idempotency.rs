@@ -0,0 +1,49 @@
+use anyhow::{anyhow, Result};
+use ethers::{
+    abi::{self, Token},
+    contract::Contract,
+    core::types::{Address, H256, U256},
+    providers::Middleware,
+    utils::keccak256,
+};
+use std::sync::Arc;
+
+use crate::config::ExecutedCheck;
+
+/// Ask the destination dapp whether the message with `nonce` was already executed
+pub async fn is_executed<M: Middleware + 'static>(
+    client: Arc<M>,
+    check: &ExecutedCheck,
+    dapp: Address,
+    nonce: u64,
+) -> Result<bool>
+where
+    M::Error: 'static,
+{
+    match check {
+        ExecutedCheck::ViewCall { signature } => {
+            let name = signature
+                .split_once('(')
+                .map(|(name, _)| name)
+                .ok_or_else(|| anyhow!("Invalid executed check signature {}", signature))?;
+            let dapp_abi = abi::parse_abi(&[&format!(
+                "function {} external view returns (bool)",
+                signature
+            )])?;
+            let dapp_contract = Contract::new(dapp, dapp_abi, client);
+            Ok(dapp_contract
+                .method::<_, bool>(name, U256::from(nonce))?
+                .call()
+                .await?)
+        }
+        ExecutedCheck::StorageMapping { slot } => {
+            // Solidity places `mapping(uint256 => ...)[key]` at keccak256(key . slot)
+            let location = H256(keccak256(abi::encode(&[
+                Token::Uint(nonce.into()),
+                Token::Uint((*slot).into()),
+            ])));
+            let value = client.get_storage_at(dapp, location, None).await?;
+            Ok(!value.is_zero())
+        }
+    }
+}
@@ -0,0 +1,71 @@
+use anyhow::Result;
+use ethers::core::types::H256;
+use std::sync::Arc;
+use tracing::warn;
+
+use crate::leader::INSTANCE_ID;
+use crate::store::{DeliveryClaim, StateStore};
+use crate::types::{RelayEvent, RelayerError};
+
+/// Claims on nonces in the store's delivery ledger, taken before a delivery
+/// is submitted and settled once it finishes
+///
+/// Instances sharing a store never submit the same nonce of a pair at once,
+/// and never again once it is confirmed. A claim left by an instance that
+/// died is taken over once it is older than the claim timeout.
+pub struct DeliveryLedger {
+    store: Arc<dyn StateStore>,
+    stale_after_secs: u64,
+}
+
+impl DeliveryLedger {
+    pub fn new(store: Arc<dyn StateStore>, claim_timeout_ms: u64) -> Self {
+        Self {
+            store,
+            stale_after_secs: claim_timeout_ms / 1000,
+        }
+    }
+
+    /// Claim the event's nonce for this instance, failing if it was delivered
+    /// already or another instance is delivering it
+    pub fn claim(&self, event: &RelayEvent) -> Result<()> {
+        let pair = event.pair_id();
+        let claim =
+            self.store
+                .claim_delivery(&pair, event.nonce, &INSTANCE_ID, self.stale_after_secs)?;
+        match claim {
+            DeliveryClaim::Claimed => Ok(()),
+            DeliveryClaim::Delivered(_) => Err(RelayerError::AlreadyExecuted {
+                chain_id: event.destination_chain.chain_id,
+                nonce: event.nonce,
+            }
+            .into()),
+            DeliveryClaim::Held => Err(RelayerError::DeliveryClaimed {
+                pair,
+                nonce: event.nonce,
+            }
+            .into()),
+        }
+    }
+
+    /// Record the event's nonce as delivered for good
+    pub fn confirm(&self, event: &RelayEvent, tx_hash: Option<H256>) {
+        if let Err(e) = self
+            .store
+            .confirm_delivery(&event.pair_id(), event.nonce, tx_hash)
+        {
+            warn!(error = %e, nonce = event.nonce, "Failed to confirm delivery in ledger");
+        }
+    }
+
+    /// Give up this instance's claim after a failed attempt, so any instance
+    /// may retry it
+    pub fn release(&self, event: &RelayEvent) {
+        if let Err(e) = self
+            .store
+            .release_delivery(&event.pair_id(), event.nonce, &INSTANCE_ID)
+        {
+            warn!(error = %e, nonce = event.nonce, "Failed to release delivery claim");
+        }
+    }
+}
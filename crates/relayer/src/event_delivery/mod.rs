@@ -0,0 +1,1300 @@
+mod access_list;
+mod arbitrum;
+mod balance;
+mod batch;
+mod calldata;
+mod confirm;
+mod control;
+mod escalator;
+mod expiry;
+mod fees;
+mod forwarder;
+mod gas;
+mod hyperlane;
+mod idempotency;
+mod ledger;
+mod nonce;
+mod retry;
+mod revert;
+mod sequencer;
+mod sink;
+mod solana;
+mod user_op;
+mod wallets;
+mod webhook;
+
+use crate::accounting::{record_gas_cost, GasCostKind};
+use crate::alerts::{self, AlertKind};
+use crate::audit::{self, AuditAction};
+use crate::clock::{Clock, SystemClock, Ticker};
+use crate::config::{
+    BatchConfig, ChainConfig, DeliveryConfig, RelayMode, RelayPair, SmartAccountConfig,
+};
+use crate::decode::{self, L2_TO_L2_CROSS_DOMAIN_MESSENGER};
+use crate::failure::{self, FailureClass};
+use crate::health::{Health, EVENT_DELIVERER};
+use crate::hooks::Hooks;
+use crate::journal::Journal;
+use crate::latency;
+use crate::metrics::{PairLabels, DELIVERIES_EXPIRED};
+use crate::priority::{PrioritySlot, PrioritySlots};
+use crate::status::{self, EventStatus};
+use crate::store::{ProofKey, StateStore};
+use crate::telemetry;
+use crate::topology::Topology;
+use crate::types::{
+    ChainId, DeliveryOutcome, DeliveryRequest, DeliveryStatus, RelayEvent, RelayerError,
+};
+use anyhow::{anyhow, Context, Result};
+use ethers::utils::hex;
+use ethers::{
+    core::types::{transaction::eip2718::TypedTransaction, Address},
+    prelude::*,
+    providers::{Http, Provider},
+    signers::LocalWallet,
+};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::{
+    sync::{broadcast, mpsc, OwnedMutexGuard},
+    time::Instant,
+};
+use tokio_util::task::TaskTracker;
+use tracing::{error, info, info_span, instrument, warn, Instrument, Span};
+
+pub(crate) use confirm::wait_for_receipt;
+pub use control::{DeliveryControl, InFlightDelivery};
+pub(crate) use fees::{build_transaction, FeeMarkets};
+pub use hyperlane::message as hyperlane_message;
+pub(crate) use revert::decode_revert;
+pub use sink::DeliverySink;
+pub(crate) use sink::SinkDeliverer;
+#[cfg(feature = "testkit")]
+pub(crate) use solana::{
+    decode, DecodedTransaction, COMPUTE_BUDGET_PROGRAM, SET_COMPUTE_UNIT_LIMIT,
+    SET_COMPUTE_UNIT_PRICE,
+};
+pub use solana::{SolanaKeypair, SolanaSink};
+pub use webhook::WebhookSink;
+
+use balance::BalanceMonitor;
+use forwarder::ForwardLocks;
+use ledger::DeliveryLedger;
+use nonce::NonceManager;
+use retry::RetryQueue;
+use sequencer::Sequencer;
+use wallets::{WalletLease, WalletPool};
+
+// Outcomes buffered for each subscriber before it starts lagging
+pub(crate) const OUTCOME_CAPACITY: usize = 256;
+// How often the retry queue is checked for deliveries that are due
+const RETRY_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+type Client = SignerMiddleware<Arc<Provider<Http>>, LocalWallet>;
+
+pub struct EventDeliverer {
+    delivery_rx: mpsc::Receiver<DeliveryRequest>,
+    context: Arc<DeliveryContext>,
+    health: Health,
+}
+
+// State shared by every delivery task
+struct DeliveryContext {
+    wallets: WalletPool,
+    store: Arc<dyn StateStore>,
+    config: DeliveryConfig,
+    /// Relay pairs as currently configured, consulted for each delivery
+    topology: Topology,
+    /// Every configured chain, watched for the signer's balance
+    chains: Vec<ChainConfig>,
+    nonces: NonceManager,
+    forwarder_nonces: NonceManager,
+    forward_locks: ForwardLocks,
+    /// Delivery slots for destination chains with a concurrency limit, given
+    /// to waiting deliveries by priority
+    chain_slots: Mutex<HashMap<ChainId, PrioritySlots>>,
+    outcomes: broadcast::Sender<DeliveryOutcome>,
+    /// Where deliveries per relay pair are recorded
+    health: Health,
+    balances: BalanceMonitor,
+    control: DeliveryControl,
+    hooks: Hooks,
+    journal: Journal,
+    ledger: DeliveryLedger,
+    retries: RetryQueue,
+    /// Paces retries and decides when deliveries have passed their deadline
+    clock: Arc<dyn Clock>,
+    fee_markets: FeeMarkets,
+    sequencer: Sequencer,
+    /// Every delivery task, so shutdown can wait for them to finish
+    tasks: TaskTracker,
+}
+
+// Destination call for one delivery, ready to be sent on its own or in a batch
+struct PreparedCall {
+    to: Address,
+    data: Bytes,
+    /// Forwarder and signer whose forwarder nonce the call consumed, if it was
+    /// wrapped in a forward request
+    forwarder: Option<(Address, Address)>,
+    pair: Option<RelayPair>,
+}
+
+// The same failure for every delivery of a batch that could not be attempted
+fn fail_all(count: usize, error: &anyhow::Error) -> Vec<Result<TransactionReceipt>> {
+    (0..count)
+        .map(|_| Err(anyhow!("Delivery batch failed: {:#}", error)))
+        .collect()
+}
+
+// Span of the work done delivering one event, alone or in a batch
+fn delivery_span(delivery: &DeliveryRequest) -> Span {
+    info_span!(
+        "delivery",
+        stage = "delivery",
+        event_id = %delivery.event.id(),
+        chain_id = delivery.event.destination_chain.chain_id.as_u64(),
+        pair = %delivery.event.pair_id(),
+        nonce = delivery.event.nonce,
+        tx_hash = delivery.event.meta.tx_hash.map(tracing::field::debug),
+        dest_chain = %delivery.event.destination_chain.name
+    )
+}
+
+impl EventDeliverer {
+    pub fn new(
+        private_key: String,
+        delivery_rx: mpsc::Receiver<DeliveryRequest>,
+        store: Arc<dyn StateStore>,
+        config: DeliveryConfig,
+        topology: Topology,
+        chains: Vec<ChainConfig>,
+        health: Health,
+    ) -> Result<Self> {
+        let wallets = WalletPool::new(&private_key, &chains)?;
+        let clock: Arc<dyn Clock> = Arc::new(SystemClock);
+        let retries = RetryQueue::new(store.clone(), config.retry.clone(), clock.clone());
+        let ledger = DeliveryLedger::new(store.clone(), config.claim_timeout_ms);
+        let balances = BalanceMonitor::new(config.gas_tank.clone(), store.clone(), health.clone());
+        Ok(Self {
+            delivery_rx,
+            health: health.clone(),
+            context: Arc::new(DeliveryContext {
+                wallets,
+                store,
+                config,
+                topology,
+                chains,
+                nonces: NonceManager::default(),
+                forwarder_nonces: NonceManager::default(),
+                forward_locks: ForwardLocks::default(),
+                chain_slots: Mutex::new(HashMap::new()),
+                outcomes: broadcast::channel(OUTCOME_CAPACITY).0,
+                health,
+                balances,
+                control: DeliveryControl::default(),
+                hooks: Hooks::default(),
+                journal: Journal::default(),
+                ledger,
+                retries,
+                clock,
+                fee_markets: FeeMarkets::default(),
+                sequencer: Sequencer::default(),
+                tasks: TaskTracker::new(),
+            }),
+        })
+    }
+
+    /// Receive the outcome of every delivery attempt from now on
+    ///
+    /// Outcomes are dropped for receivers that fall more than a few hundred
+    /// behind; they see a lag error instead.
+    pub fn subscribe(&self) -> broadcast::Receiver<DeliveryOutcome> {
+        self.context.outcomes.subscribe()
+    }
+
+    /// Handle for cancelling or re-broadcasting deliveries that are in flight
+    pub fn control(&self) -> DeliveryControl {
+        self.context.control.clone()
+    }
+
+    // Publish outcomes and take operator commands through the handles of an
+    // earlier deliverer, so subscribers keep working across a restart
+    pub(crate) fn with_handles(
+        mut self,
+        outcomes: broadcast::Sender<DeliveryOutcome>,
+        control: DeliveryControl,
+    ) -> Self {
+        let context = Arc::get_mut(&mut self.context).expect("deliverer has not started");
+        context.outcomes = outcomes;
+        context.control = control;
+        self
+    }
+
+    pub(crate) fn with_hooks(mut self, hooks: Hooks) -> Self {
+        Arc::get_mut(&mut self.context)
+            .expect("deliverer has not started")
+            .hooks = hooks;
+        self
+    }
+
+    pub(crate) fn with_journal(mut self, journal: Journal) -> Self {
+        Arc::get_mut(&mut self.context)
+            .expect("deliverer has not started")
+            .journal = journal;
+        self
+    }
+
+    pub(crate) fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        let context = Arc::get_mut(&mut self.context).expect("deliverer has not started");
+        context.retries = context.retries.on_clock(clock.clone());
+        context.clock = clock;
+        self
+    }
+
+    #[instrument(skip(self), name = "event_deliverer_start")]
+    pub async fn start(&mut self) -> Result<()> {
+        info!("Starting event deliverer");
+        self.health.beat(EVENT_DELIVERER);
+
+        let wallets = self
+            .context
+            .chains
+            .iter()
+            .flat_map(|chain| {
+                let addresses = self.context.wallets.addresses(chain.chain_id);
+                addresses.into_iter().map(|address| (chain.clone(), address))
+            })
+            .collect();
+        let context = self.context.clone();
+        let interval = Duration::from_millis(self.context.config.balance_check_interval_ms);
+        let balances = tokio::spawn(async move { context.balances.run(wallets, interval).await });
+        let retries = tokio::spawn(self.context.clone().run_retries());
+        self.context.probe_fee_markets().await;
+
+        let result = match self.context.config.batch.clone() {
+            Some(batch) => self.run_batched(batch).await,
+            None => {
+                self.run_unbatched().await;
+                Ok(())
+            }
+        };
+
+        // The delivery channel closed: let what was already handed out finish.
+        // Queued retries stay in the store for the next run.
+        balances.abort();
+        retries.abort();
+        self.context.tasks.close();
+        info!(
+            in_flight = self.context.tasks.len(),
+            "Delivery channel closed, waiting for in-flight deliveries"
+        );
+        self.context.tasks.wait().await;
+        result
+    }
+
+    async fn run_unbatched(&mut self) {
+        while let Some(delivery) = self.delivery_rx.recv().await {
+            self.health.beat(EVENT_DELIVERER);
+            let Some(delivery) = self.context.admit(delivery) else {
+                continue;
+            };
+            // Process delivery in a separate task to allow concurrent deliveries
+            let context = self.context.clone();
+            self.context
+                .tasks
+                .spawn(async move { context.deliver_one(delivery).await });
+        }
+    }
+
+    // Collect deliveries per destination chain and send each group once it is
+    // full or its window has elapsed
+    async fn run_batched(&mut self, config: BatchConfig) -> Result<()> {
+        let window = Duration::from_millis(config.window_ms);
+        let mut batches: HashMap<ChainId, (Instant, Vec<DeliveryRequest>)> = HashMap::new();
+
+        loop {
+            let next_flush = batches.values().map(|(deadline, _)| *deadline).min();
+            let flush_at = next_flush.unwrap_or_else(Instant::now);
+            tokio::select! {
+                delivery = self.delivery_rx.recv() => {
+                    let Some(delivery) = delivery else {
+                        for (_, (_, deliveries)) in batches.drain() {
+                            self.spawn_batch(deliveries);
+                        }
+                        return Ok(());
+                    };
+                    self.health.beat(EVENT_DELIVERER);
+                    let Some(delivery) = self.context.admit(delivery) else {
+                        continue;
+                    };
+                    let chain_id = delivery.event.destination_chain.chain_id;
+                    let (_, deliveries) = batches
+                        .entry(chain_id)
+                        .or_insert_with(|| (Instant::now() + window, Vec::new()));
+                    deliveries.push(delivery);
+                    if deliveries.len() >= config.max_size.max(1) {
+                        if let Some((_, deliveries)) = batches.remove(&chain_id) {
+                            self.spawn_batch(deliveries);
+                        }
+                    }
+                }
+                _ = tokio::time::sleep_until(flush_at), if next_flush.is_some() => {
+                    let now = Instant::now();
+                    let due: Vec<ChainId> = batches
+                        .iter()
+                        .filter(|(_, (deadline, _))| *deadline <= now)
+                        .map(|(chain_id, _)| *chain_id)
+                        .collect();
+                    for chain_id in due {
+                        if let Some((_, deliveries)) = batches.remove(&chain_id) {
+                            self.spawn_batch(deliveries);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn spawn_batch(&self, deliveries: Vec<DeliveryRequest>) {
+        let context = self.context.clone();
+        let event_ids: Vec<String> = deliveries
+            .iter()
+            .map(|delivery| delivery.event.id().to_string())
+            .collect();
+        let span = info_span!(
+            "delivery_batch",
+            stage = "delivery",
+            event_ids = %event_ids.join(","),
+            chain_id = deliveries[0].event.destination_chain.chain_id.as_u64(),
+            dest_chain = %deliveries[0].event.destination_chain.name,
+            size = deliveries.len()
+        );
+        match deliveries.as_slice() {
+            [delivery] => telemetry::attach(&span, &delivery.event.trace_context),
+            deliveries => {
+                for delivery in deliveries {
+                    telemetry::link(&span, &delivery.event.trace_context);
+                }
+            }
+        }
+        let batch = async move {
+            let events: Vec<&RelayEvent> =
+                deliveries.iter().map(|delivery| &delivery.event).collect();
+            context.hold_while_paused(&events).await;
+            let mut allowed = Vec::with_capacity(deliveries.len());
+            for delivery in deliveries {
+                let span = delivery_span(&delivery);
+                let cleared = context.clear_to_deliver(&delivery);
+                match cleared.instrument(span.clone()).await {
+                    Ok(()) => allowed.push(delivery),
+                    Err(e) => {
+                        context
+                            .record_outcome(&delivery, Err(e))
+                            .instrument(span)
+                            .await;
+                        context.release_next(&delivery.event);
+                    }
+                }
+            }
+            let deliveries = allowed;
+            if deliveries.is_empty() {
+                return;
+            }
+            let requests = deliveries.clone();
+            // A batch waits with the priority of its most urgent event
+            let priority = deliveries
+                .iter()
+                .map(|delivery| delivery.event.priority)
+                .max()
+                .unwrap_or_default();
+            let _slot = context
+                .chain_slot(&deliveries[0].event.destination_chain, priority)
+                .await;
+            let results = match <[DeliveryRequest; 1]>::try_from(deliveries) {
+                Ok([delivery]) => {
+                    let span = delivery_span(&delivery);
+                    vec![context.deliver_event(delivery).instrument(span).await]
+                }
+                Err(deliveries) => {
+                    let dest_chain = deliveries[0].event.destination_chain.clone();
+                    context.deliver_batch(&dest_chain, &deliveries).await
+                }
+            };
+            for (delivery, result) in requests.iter().zip(results) {
+                context
+                    .record_outcome(delivery, result)
+                    .instrument(delivery_span(delivery))
+                    .await;
+                context.release_next(&delivery.event);
+            }
+        };
+        self.context.tasks.spawn(batch.instrument(span));
+    }
+}
+
+impl DeliveryContext {
+    // Wait for a free delivery slot on a chain with a concurrency limit;
+    // higher-priority deliveries get the next free slot first
+    async fn chain_slot(&self, chain: &ChainConfig, priority: u8) -> Option<PrioritySlot> {
+        let limit = chain.max_concurrent_deliveries?;
+        let slots = self
+            .chain_slots
+            .lock()
+            .expect("chain slots lock poisoned")
+            .entry(chain.chain_id)
+            .or_insert_with(|| PrioritySlots::new(limit.max(1)))
+            .clone();
+        Some(slots.acquire(priority).await)
+    }
+
+    // Detect every chain's fee market up front; chains that cannot be reached
+    // now are probed on their first delivery instead
+    async fn probe_fee_markets(&self) {
+        for chain in &self.chains {
+            let provider = match Provider::<Http>::try_from(&chain.rpc_url) {
+                Ok(provider) => provider,
+                Err(e) => {
+                    warn!(error = %e, chain = %chain.name, "Failed to create provider");
+                    continue;
+                }
+            };
+            if let Err(e) = self.fee_markets.supports_eip1559(&provider, chain).await {
+                warn!(error = %e, chain = %chain.name, "Failed to detect fee market");
+            }
+        }
+    }
+
+    // Deliver a single event once its chain has a free slot
+    async fn deliver_one(self: Arc<Self>, delivery: DeliveryRequest) {
+        let span = delivery_span(&delivery);
+        telemetry::attach(&span, &delivery.event.trace_context);
+        async move {
+            self.hold_while_paused(&[&delivery.event]).await;
+            let result = match self.clear_to_deliver(&delivery).await {
+                Ok(()) => {
+                    let _slot = self
+                        .chain_slot(&delivery.event.destination_chain, delivery.event.priority)
+                        .await;
+                    self.deliver_event(delivery.clone()).await
+                }
+                Err(e) => Err(e),
+            };
+            self.record_outcome(&delivery, result).await;
+            self.release_next(&delivery.event);
+        }
+        .instrument(span)
+        .await
+    }
+
+    // Run the hooks on a delivery about to be submitted, then claim its nonce
+    // in the ledger so no other attempt submits it meanwhile
+    async fn clear_to_deliver(&self, delivery: &DeliveryRequest) -> Result<()> {
+        self.hooks.before_delivery(delivery).await?;
+        self.ledger.claim(&delivery.event)?;
+        status::advance(&*self.store, &delivery.event, EventStatus::Delivering);
+        Ok(())
+    }
+
+    // Keep deliveries from being submitted while relaying is paused for them
+    async fn hold_while_paused(&self, events: &[&RelayEvent]) {
+        if events.iter().any(|event| self.topology.is_paused(event)) {
+            info!("Delivery held while relaying is paused");
+            self.topology.wait_while_paused(events).await;
+            info!("Relaying resumed, submitting delivery");
+        }
+    }
+
+    // The pair an event belongs to, if that pair delivers strictly in nonce order
+    fn ordered_pair(&self, event: &RelayEvent) -> Option<RelayPair> {
+        self.topology.pair_for(event).filter(|pair| pair.ordered)
+    }
+
+    // Let a new delivery through, unless its pair is still waiting on lower nonces
+    fn admit(&self, delivery: DeliveryRequest) -> Option<DeliveryRequest> {
+        let Some(pair) = self.ordered_pair(&delivery.event) else {
+            return Some(delivery);
+        };
+        let event_id = delivery.event.id();
+        self.sequencer
+            .admit(&pair, delivery, &*self.store)
+            .unwrap_or_else(|e| {
+                // Stays held until the next event of the pair settles
+                warn!(
+                    error = %e,
+                    %event_id,
+                    pair = %pair.id(),
+                    "Failed to check delivery order"
+                );
+                None
+            })
+    }
+
+    // Start the next held delivery of an ordered pair once one of its events settles
+    fn release_next(self: &Arc<Self>, event: &RelayEvent) {
+        let Some(pair) = self.ordered_pair(event) else {
+            return;
+        };
+        match self.sequencer.release(&pair, &*self.store) {
+            Ok(Some(next)) => {
+                let context = self.clone();
+                self.tasks
+                    .spawn(async move { context.deliver_one(next).await });
+            }
+            Ok(None) => {}
+            Err(e) => warn!(error = %e, pair = %pair.id(), "Failed to check delivery order"),
+        }
+    }
+
+    // Re-send queued deliveries as they come due, forever
+    async fn run_retries(self: Arc<Self>) {
+        let mut ticker = Ticker::new(self.clock.clone(), RETRY_POLL_INTERVAL);
+        loop {
+            ticker.tick().await;
+            let due = match self.retries.take_due() {
+                Ok(due) => due,
+                Err(e) => {
+                    warn!(error = %e, "Failed to read retry queue");
+                    continue;
+                }
+            };
+            for delivery in due {
+                info!(
+                    event_id = %delivery.event.id(),
+                    proof_key = %ProofKey::from_meta(&delivery.event.meta),
+                    "Retrying delivery"
+                );
+                let context = self.clone();
+                self.tasks
+                    .spawn(async move { context.deliver_one(delivery).await });
+            }
+        }
+    }
+
+    // Settle the stored state of a finished delivery and publish its outcome
+    async fn record_outcome(&self, delivery: &DeliveryRequest, result: Result<TransactionReceipt>) {
+        let event = &delivery.event;
+        let result = result.map_err(|e| failure::attribute(event.destination_chain.chain_id, e));
+        let proof_key = &ProofKey::from_meta(&event.meta);
+        let mut outcome = DeliveryOutcome {
+            proof_key: proof_key.clone(),
+            event_id: event.id(),
+            pair: self.topology.pair_for(event).map(|pair| pair.id()),
+            source_chain_id: event.source_chain.chain_id,
+            dest_chain_id: event.destination_chain.chain_id,
+            nonce: event.nonce,
+            attempt: self.retries.attempts_made(proof_key) + 1,
+            attempted_at: self.clock.unix_time(),
+            tx_hash: None,
+            block_number: None,
+            gas_used: None,
+            status: DeliveryStatus::Delivered,
+            error: None,
+        };
+
+        match &result {
+            Ok(receipt) => {
+                info!(proof_key = %proof_key, "Event delivered successfully");
+                outcome.tx_hash = Some(receipt.transaction_hash);
+                outcome.block_number = receipt.block_number.map(|block| block.as_u64());
+                outcome.gas_used = receipt.gas_used;
+                self.health.event_delivered(outcome.pair.as_deref());
+                self.ledger.confirm(event, outcome.tx_hash);
+                self.finish(proof_key);
+            }
+            Err(e)
+                if e.downcast_ref::<RelayerError>()
+                    .is_some_and(RelayerError::is_already_delivered) =>
+            {
+                info!(
+                    error = %e,
+                    proof_key = %proof_key,
+                    "Event was already executed on destination"
+                );
+                outcome.status = DeliveryStatus::AlreadyExecuted;
+                self.ledger.confirm(event, None);
+                self.finish(proof_key);
+            }
+            Err(e)
+                if matches!(
+                    e.downcast_ref::<RelayerError>(),
+                    Some(RelayerError::DeliveryExpired { .. })
+                ) =>
+            {
+                // Stale payloads are never delivered, so the event is done with
+                // unless an operator replays it
+                error!(error = %e, proof_key = %proof_key, "ALERT: delivery expired");
+                outcome.status = DeliveryStatus::Expired;
+                self.ledger.release(event);
+                self.retries.record_failure(delivery, e, false);
+                self.finish(proof_key);
+            }
+            Err(e) => {
+                let retryable = FailureClass::of(e) == FailureClass::Retryable;
+                error!(error = %e, proof_key = %proof_key, retryable, "Failed to deliver event");
+                self.ledger.release(event);
+                outcome.status = self.retries.record_failure(delivery, e, retryable);
+                if outcome.status == DeliveryStatus::DeadLettered {
+                    self.finish(proof_key);
+                }
+            }
+        }
+
+        if let Err(e) = &result {
+            outcome.error = Some(format!("{:#}", e));
+            if let Some(
+                RelayerError::DeliveryReverted { tx_hash, .. }
+                | RelayerError::DeliveryStuck { tx_hash, .. }
+                | RelayerError::DeliveryReorged { tx_hash }
+                | RelayerError::ConfirmationsStalled { tx_hash, .. }
+                | RelayerError::DeliveryCancelled { tx_hash },
+            ) = e.downcast_ref::<RelayerError>()
+            {
+                outcome.tx_hash = Some(*tx_hash);
+            }
+            if let Some(RelayerError::DeliveryReverted {
+                tx_hash, reason, ..
+            }) = e.downcast_ref::<RelayerError>()
+            {
+                alerts::notify(
+                    AlertKind::DeliveryReverted,
+                    event.destination_chain.name.as_str(),
+                    format!(
+                        "Delivery of {} reverted in {:?}: {}",
+                        proof_key, tx_hash, reason
+                    ),
+                );
+            }
+        }
+        if let Err(e) = self.store.save_delivery_attempt(&outcome) {
+            warn!(error = %e, proof_key = %proof_key, "Failed to record delivery attempt");
+        }
+        audit::record(AuditAction::delivery(&outcome));
+        self.journal.settled(proof_key, &outcome.status);
+        if let Some(settled) = EventStatus::settled(&outcome.status, outcome.error.as_deref()) {
+            status::advance(&*self.store, event, settled);
+        }
+        self.hooks.after_delivery(&outcome).await;
+        // Nobody listening is fine
+        let _ = self.outcomes.send(outcome);
+    }
+
+    // The cached proof and pending event have served their purpose
+    fn finish(&self, proof_key: &ProofKey) {
+        if let Err(e) = self.retries.remove(proof_key) {
+            warn!(error = %e, proof_key = %proof_key, "Failed to clear delivery retry");
+        }
+        if let Err(e) = self.store.remove_pending_event(proof_key) {
+            warn!(error = %e, proof_key = %proof_key, "Failed to clear pending event");
+        }
+        if let Err(e) = self.store.remove_proof(proof_key) {
+            warn!(error = %e, proof_key = %proof_key, "Failed to prune cached proof");
+        }
+    }
+
+    // Connect to the destination chain with the least busy funded delivery wallet
+    fn connect(
+        &self,
+        dest_chain: &ChainConfig,
+    ) -> Result<(Arc<Provider<Http>>, Client, WalletLease)> {
+        // Connect to provider
+        let provider = Provider::<Http>::try_from(&dest_chain.rpc_url)
+            .context(format!("Failed to create provider for {}", dest_chain.name))?;
+        let provider = Arc::new(provider);
+
+        let (wallet, lease) = self.wallets.acquire(dest_chain, &self.balances)?;
+        let client = SignerMiddleware::new(provider.clone(), wallet);
+        Ok((provider, client, lease))
+    }
+
+    // Build the call that delivers an event, checking first that it is still needed
+    async fn prepare(
+        &self,
+        provider: Arc<Provider<Http>>,
+        client: &Client,
+        delivery: &DeliveryRequest,
+    ) -> Result<PreparedCall> {
+        // Decode the execution payload to determine which function to call
+        let function_selector = decode::function_selector(&delivery.event.exec_payload)?;
+        info!(
+            "Using function selector: 0x{}",
+            hex::encode(function_selector)
+        );
+
+        let pair = self.topology.pair_for(&delivery.event);
+
+        let dapp = delivery.event.dest_dapp_address;
+        let dest_chain = &delivery.event.destination_chain;
+
+        // Native interop messages are relayed by the destination's messenger
+        // and intents filled on the destination settler; Hyperlane pairs have
+        // the Mailbox process the message; chains with a shared executor get
+        // every other delivery routed through it; otherwise combine the exec
+        // payload and proof the way the dapp expects them
+        let relay_mode = pair
+            .as_ref()
+            .map(|pair| pair.relay_mode)
+            .unwrap_or_default();
+        let mailbox = match pair.as_ref().and_then(|pair| pair.hyperlane.as_ref()) {
+            Some(config) => Some((config.mailbox, hyperlane::message(config, &delivery.event)?)),
+            None => None,
+        };
+        let (target, tx_data) = match (&mailbox, dest_chain.executor_address) {
+            _ if relay_mode == RelayMode::OpInterop => (
+                L2_TO_L2_CROSS_DOMAIN_MESSENGER,
+                calldata::relay_message_call(&delivery.proof.data),
+            ),
+            _ if relay_mode == RelayMode::Erc7683 => (
+                dapp,
+                calldata::fill_call(&delivery.event.exec_payload, &delivery.proof.data)?,
+            ),
+            (Some((mailbox, message)), _) => (
+                *mailbox,
+                hyperlane::process_call(&delivery.proof.data, message),
+            ),
+            (None, Some(executor)) => (
+                executor,
+                calldata::executor_call(dapp, &delivery.event.exec_payload, &delivery.proof.data),
+            ),
+            (None, None) => {
+                let encoding = pair
+                    .as_ref()
+                    .map(|pair| pair.call_encoding.clone())
+                    .unwrap_or_default();
+                let tx_data = calldata::encode(
+                    &encoding,
+                    &delivery.event.exec_payload,
+                    &delivery.proof.data,
+                )?;
+                (dapp, tx_data)
+            }
+        };
+
+        // Skip messages another relayer has already executed
+        if let Some(check) = pair.as_ref().and_then(|pair| pair.executed_check.as_ref()) {
+            let nonce = delivery.event.nonce;
+            if idempotency::is_executed(provider.clone(), check, dapp, nonce).await? {
+                return Err(RelayerError::AlreadyExecuted {
+                    chain_id: delivery.event.destination_chain.chain_id,
+                    nonce,
+                }
+                .into());
+            }
+        }
+        if let Some((mailbox, message)) = &mailbox {
+            if hyperlane::is_delivered(provider.clone(), *mailbox, message).await? {
+                return Err(RelayerError::AlreadyExecuted {
+                    chain_id: delivery.event.destination_chain.chain_id,
+                    nonce: delivery.event.nonce,
+                }
+                .into());
+            }
+        }
+
+        let forwarder = pair.as_ref().and_then(|pair| pair.forwarder.as_ref());
+
+        let (to, data, forwarder_address) = match forwarder {
+            Some(forwarder) => {
+                let call = forwarder::wrap(
+                    provider,
+                    &self.forwarder_nonces,
+                    forwarder,
+                    client.signer(),
+                    target,
+                    tx_data,
+                    self.config.gas_multiplier,
+                )
+                .await?;
+                (
+                    call.forwarder,
+                    call.calldata,
+                    Some((call.forwarder, call.signer)),
+                )
+            }
+            None => (target, tx_data, None),
+        };
+
+        Ok(PreparedCall {
+            to,
+            data,
+            forwarder: forwarder_address,
+            pair,
+        })
+    }
+
+    // The pair of a delivery whose deadline has passed
+    fn expired_pair(&self, delivery: &DeliveryRequest) -> Option<RelayPair> {
+        self.topology.pair_for(&delivery.event).filter(|pair| {
+            pair.delivery_deadline_ms.is_some_and(|deadline| {
+                expiry::is_expired(&delivery.event, deadline, self.clock.system_time())
+            })
+        })
+    }
+
+    // Give up on a stale delivery, calling the dapp's cancel function if it has one
+    async fn expire(
+        &self,
+        client: &Client,
+        pair: &RelayPair,
+        delivery: &DeliveryRequest,
+    ) -> Result<TransactionReceipt> {
+        let dest_chain = &delivery.event.destination_chain;
+        DELIVERIES_EXPIRED
+            .with_label_values(&PairLabels::of(&delivery.event).values())
+            .inc();
+
+        if let Some(cancel_function) = &pair.cancel_function {
+            let dapp = delivery.event.dest_dapp_address;
+            let calldata = expiry::cancel_calldata(cancel_function, delivery.event.nonce)?;
+            let receipt = self
+                .submit(client, dest_chain, dapp, calldata, Some(pair))
+                .await?;
+            info!(tx_hash = ?receipt.transaction_hash, "Cancelled expired delivery");
+        }
+
+        Err(RelayerError::DeliveryExpired {
+            chain_id: dest_chain.chain_id,
+            nonce: delivery.event.nonce,
+        }
+        .into())
+    }
+
+    // Hold the signer's forwarding lock if any of these deliveries go through a forwarder
+    async fn lock_forwarding(
+        &self,
+        client: &Client,
+        deliveries: &[DeliveryRequest],
+    ) -> Option<OwnedMutexGuard<()>> {
+        let forwarded = deliveries.iter().any(|delivery| {
+            self.topology
+                .pair_for(&delivery.event)
+                .is_some_and(|pair| pair.forwarder.is_some())
+        });
+        match deliveries.first() {
+            Some(delivery) if forwarded => {
+                let chain_id = delivery.event.destination_chain.chain_id;
+                Some(self.forward_locks.lock(chain_id, client.address()).await)
+            }
+            _ => None,
+        }
+    }
+
+    // The signed forward request was never executed, so its nonce is free again
+    async fn release(&self, chain_id: ChainId, call: &PreparedCall) {
+        if let Some((forwarder, signer)) = call.forwarder {
+            self.forwarder_nonces
+                .reset_with(chain_id, forwarder, signer)
+                .await;
+        }
+    }
+
+    #[instrument(skip(self), fields(
+        event_id = %delivery.event.id(),
+        source_chain = %delivery.event.source_chain.name,
+        dest_chain = %delivery.event.destination_chain.name,
+        nonce = delivery.event.nonce,
+        proven_height = ?delivery.proof.metadata.proven_height,
+        receipt_root = ?delivery.proof.metadata.receipt_root,
+        proof_version = ?delivery.proof.metadata.format_version
+    ))]
+    async fn deliver_event(&self, delivery: DeliveryRequest) -> Result<TransactionReceipt> {
+        let dest_chain = delivery.event.destination_chain.clone();
+
+        info!("Delivering event to destination chain");
+        let (provider, client, _lease) = self.connect(&dest_chain)?;
+        if let Some(pair) = self.expired_pair(&delivery) {
+            return self.expire(&client, &pair, &delivery).await;
+        }
+        let _forwarding = self
+            .lock_forwarding(&client, std::slice::from_ref(&delivery))
+            .await;
+        let call = self.prepare(provider, &client, &delivery).await?;
+
+        info!("Submitting transaction to destination chain");
+        latency::broadcast(&delivery.event);
+        let result = self
+            .submit(&client, &dest_chain, call.to, call.data.clone(), call.pair.as_ref())
+            .await;
+        if result.is_err() {
+            self.release(dest_chain.chain_id, &call).await;
+        }
+        let receipt = result?;
+
+        info!("Proof submission confirmed: {:?}", receipt);
+
+        Ok(receipt)
+    }
+
+    /// Deliver several events to one chain in a single Multicall3 transaction
+    ///
+    /// Each call may fail on its own without reverting the batch. Calls that
+    /// fail simulation are left out, and per-call outcomes of the mined batch
+    /// are read back by replaying it against the parent block.
+    #[instrument(skip_all, fields(dest_chain = %dest_chain.name, size = deliveries.len()))]
+    async fn deliver_batch(
+        &self,
+        dest_chain: &ChainConfig,
+        deliveries: &[DeliveryRequest],
+    ) -> Vec<Result<TransactionReceipt>> {
+        let mut outcomes: Vec<Option<Result<TransactionReceipt>>> =
+            deliveries.iter().map(|_| None).collect();
+        let (provider, client, _lease) = match self.connect(dest_chain) {
+            Ok(connection) => connection,
+            Err(e) => {
+                if let Some(&RelayerError::InsufficientBalance { chain_id }) = e.downcast_ref() {
+                    // Kept typed so every delivery stays retryable
+                    return deliveries
+                        .iter()
+                        .map(|_| Err(RelayerError::InsufficientBalance { chain_id }.into()))
+                        .collect();
+                }
+                return fail_all(deliveries.len(), &e);
+            }
+        };
+        let multicall = batch::multicall_address(dest_chain);
+        // Calls in one batch run in the order their forwarder nonces were taken
+        let _forwarding = self.lock_forwarding(&client, deliveries).await;
+
+        // Prepare every call, keeping the ones still worth sending
+        let mut calls = Vec::new();
+        for (index, delivery) in deliveries.iter().enumerate() {
+            if let Some(pair) = self.expired_pair(delivery) {
+                outcomes[index] = Some(self.expire(&client, &pair, delivery).await);
+                continue;
+            }
+            match self.prepare(provider.clone(), &client, delivery).await {
+                Ok(call) => calls.push((index, call)),
+                Err(e) => outcomes[index] = Some(Err(e)),
+            }
+        }
+
+        // Drop calls that would revert so they don't cost gas
+        if self.config.simulate_deliveries && !calls.is_empty() {
+            let targets: Vec<_> = calls
+                .iter()
+                .map(|(_, call)| (call.to, call.data.clone()))
+                .collect();
+            match batch::simulate(&client, multicall, &targets, None).await {
+                Ok(results) => {
+                    let mut kept = Vec::new();
+                    for ((index, call), (success, return_data)) in calls.into_iter().zip(results) {
+                        if success {
+                            kept.push((index, call));
+                        } else {
+                            let reason = revert::decode_revert(&return_data);
+                            self.release(dest_chain.chain_id, &call).await;
+                            outcomes[index] = Some(Err(RelayerError::DeliverySimulationFailed {
+                                chain_id: dest_chain.chain_id,
+                                kind: revert::classify(&reason),
+                                reason,
+                            }
+                            .into()));
+                        }
+                    }
+                    calls = kept;
+                }
+                Err(e) => warn!(error = %e, "Batch simulation failed, sending unfiltered"),
+            }
+        }
+
+        if !calls.is_empty() {
+            let targets: Vec<_> = calls
+                .iter()
+                .map(|(_, call)| (call.to, call.data.clone()))
+                .collect();
+            info!(calls = targets.len(), "Submitting delivery batch");
+            for (index, _) in &calls {
+                latency::broadcast(&deliveries[*index].event);
+            }
+            let result = self
+                .submit(
+                    &client,
+                    dest_chain,
+                    multicall,
+                    batch::encode_aggregate3(&targets),
+                    None,
+                )
+                .await;
+
+            let results = match result {
+                Ok(receipt) => {
+                    info!(tx_hash = ?receipt.transaction_hash, "Delivery batch confirmed");
+                    // State as of the parent block is what the batch executed against
+                    let parent = receipt
+                        .block_number
+                        .map(|number| BlockId::from(number.saturating_sub(U64::one())));
+                    batch::simulate(&client, multicall, &targets, parent)
+                        .await
+                        .map(|results| (receipt, results))
+                }
+                Err(e) => Err(e),
+            };
+
+            match results {
+                Ok((receipt, results)) => {
+                    let tx_hash = receipt.transaction_hash;
+                    for ((index, call), (success, return_data)) in calls.iter().zip(results) {
+                        outcomes[*index] = Some(if success {
+                            Ok(receipt.clone())
+                        } else {
+                            self.release(dest_chain.chain_id, call).await;
+                            let reason = revert::decode_revert(&return_data);
+                            Err(RelayerError::DeliveryReverted {
+                                chain_id: dest_chain.chain_id,
+                                tx_hash,
+                                kind: revert::classify(&reason),
+                                reason,
+                            }
+                            .into())
+                        });
+                    }
+                }
+                Err(e) => {
+                    for (index, call) in &calls {
+                        self.release(dest_chain.chain_id, call).await;
+                        outcomes[*index] = Some(Err(anyhow!("Delivery batch failed: {:#}", e)));
+                    }
+                }
+            }
+        }
+
+        outcomes
+            .into_iter()
+            .map(|outcome| outcome.expect("every delivery in the batch has an outcome"))
+            .collect()
+    }
+
+    // Simulate, price, and broadcast a delivery, returning its successful receipt
+    async fn submit<M: Middleware + 'static>(
+        &self,
+        client: &SignerMiddleware<M, LocalWallet>,
+        dest_chain: &ChainConfig,
+        to: Address,
+        tx_data: Bytes,
+        pair: Option<&RelayPair>,
+    ) -> Result<TransactionReceipt> {
+        let sender = client.address();
+
+        // Create transaction request
+        let eip1559 = self.fee_markets.supports_eip1559(client, dest_chain).await?;
+        let mut tx_request =
+            fees::build_transaction(client, dest_chain, eip1559, to, tx_data).await?;
+        // Sponsorship goes through a paymaster, which only smart accounts can use
+        if pair.is_some_and(|pair| pair.sponsored) && dest_chain.smart_account.is_none() {
+            return Err(anyhow!(
+                "Sponsored delivery to {} needs a smart account configured",
+                dest_chain.name
+            ));
+        }
+        if let Some(account) = &dest_chain.smart_account {
+            return self
+                .submit_user_operation(client, dest_chain, account, tx_request, pair)
+                .await;
+        }
+        tx_request.set_from(sender);
+
+        // Catch reverts before they cost gas
+        if self.config.simulate_deliveries {
+            if let Some(reason) = revert::simulate(client, &tx_request, None).await? {
+                return Err(RelayerError::DeliverySimulationFailed {
+                    chain_id: dest_chain.chain_id,
+                    kind: revert::classify(&reason),
+                    reason,
+                }
+                .into());
+            }
+        }
+
+        access_list::apply_access_list(client, &mut tx_request, dest_chain).await?;
+        gas::apply_gas_limit(
+            client,
+            &mut tx_request,
+            self.config.gas_multiplier,
+            pair,
+            dest_chain,
+        )
+        .await?;
+
+        let nonce = self
+            .nonces
+            .next(client, dest_chain.chain_id, sender)
+            .await?;
+        tx_request.set_nonce(nonce);
+        let pair_id = pair.map(RelayPair::id);
+        let mut registration =
+            self.control
+                .register(dest_chain.chain_id, sender, nonce, pair_id.clone());
+
+        // Send the transaction and wait for it to be mined, bumping fees if it stalls.
+        // A private relay keeps the payload out of the public mempool until it is mined.
+        let sent = match &dest_chain.private_rpc_url {
+            Some(url) => {
+                let private = Provider::<Http>::try_from(url.as_str()).context(format!(
+                    "Failed to create private provider for {}",
+                    dest_chain.name
+                ))?;
+                let broadcaster = SignerMiddleware::new(private, client.signer().clone());
+                escalator::send_with_escalation(
+                    client,
+                    &broadcaster,
+                    tx_request.clone(),
+                    &self.config,
+                    &mut registration,
+                )
+                .await
+            }
+            None => {
+                escalator::send_with_escalation(
+                    client,
+                    client,
+                    tx_request.clone(),
+                    &self.config,
+                    &mut registration,
+                )
+                .await
+            }
+        };
+        drop(registration);
+        let sent = match sent {
+            Ok(receipt) => {
+                confirm::wait_for_confirmations(
+                    client,
+                    receipt,
+                    dest_chain,
+                    Duration::from_millis(self.config.stuck_timeout_ms),
+                    &*self.clock,
+                )
+                .await
+            }
+            Err(e) => Err(e),
+        };
+        let receipt = match sent {
+            Ok(receipt) => receipt,
+            Err(e) => {
+                // Resync from the node's pending count, which covers both a
+                // nonce that was never used and one still sitting in the mempool
+                let next = self.nonces.reset(dest_chain.chain_id, sender).await;
+                if let Some(next) = next.filter(|next| *next > nonce + 1) {
+                    warn!(
+                        chain = %dest_chain.name,
+                        %sender,
+                        %nonce,
+                        "Later nonces are queued behind a failed broadcast"
+                    );
+                    alerts::notify(
+                        AlertKind::NonceGap,
+                        format!("{} {:?}", dest_chain.name, sender),
+                        format!(
+                            "Broadcast of nonce {} from {:?} on {} failed with nonces up to {} \
+                             already handed out: {:#}",
+                            nonce,
+                            sender,
+                            dest_chain.name,
+                            next - 1,
+                            e
+                        ),
+                    );
+                }
+                return Err(e);
+            }
+        };
+
+        // Reverted transactions cost gas too
+        record_gas_cost(
+            &*self.store,
+            dest_chain,
+            pair_id.as_deref().unwrap_or("batch"),
+            GasCostKind::Delivery,
+            &receipt,
+        );
+
+        if receipt.status == Some(U64::zero()) {
+            // Replay the call against the block it was mined in to recover the reason
+            let reason =
+                revert::simulate(client, &tx_request, receipt.block_number.map(Into::into))
+                    .await?
+                    .unwrap_or_else(|| "no revert reason".to_string());
+            return Err(RelayerError::DeliveryReverted {
+                chain_id: dest_chain.chain_id,
+                tx_hash: receipt.transaction_hash,
+                kind: revert::classify(&reason),
+                reason,
+            }
+            .into());
+        }
+
+        Ok(receipt)
+    }
+
+    // Send a delivery as a user operation from the relayer's smart account
+    async fn submit_user_operation<M: Middleware + 'static>(
+        &self,
+        client: &SignerMiddleware<M, LocalWallet>,
+        dest_chain: &ChainConfig,
+        account: &SmartAccountConfig,
+        mut tx_request: TypedTransaction,
+        pair: Option<&RelayPair>,
+    ) -> Result<TransactionReceipt> {
+        // The destination sees the account as the caller, so simulate as it
+        tx_request.set_from(account.address);
+        if self.config.simulate_deliveries {
+            if let Some(reason) = revert::simulate(client, &tx_request, None).await? {
+                return Err(RelayerError::DeliverySimulationFailed {
+                    chain_id: dest_chain.chain_id,
+                    kind: revert::classify(&reason),
+                    reason,
+                }
+                .into());
+            }
+        }
+
+        // Batches mix pairs, so only single deliveries can be sponsored
+        let sponsored = pair.is_some_and(|pair| pair.sponsored);
+        let sent = user_op::send(
+            client,
+            client.signer(),
+            dest_chain.chain_id,
+            account,
+            &tx_request,
+            &self.config,
+            sponsored,
+        )
+        .await?;
+
+        // The bundler fronts the bundle's gas and the account reimburses it,
+        // unless a paymaster picked up the bill
+        if !sponsored {
+            let pair_id = pair.map(RelayPair::id);
+            record_gas_cost(
+                &*self.store,
+                dest_chain,
+                pair_id.as_deref().unwrap_or("batch"),
+                GasCostKind::Delivery,
+                &sent.receipt,
+            );
+        }
+
+        let receipt = confirm::wait_for_confirmations(
+            client,
+            sent.receipt,
+            dest_chain,
+            Duration::from_millis(self.config.stuck_timeout_ms),
+            &*self.clock,
+        )
+        .await?;
+        if let Some(reason) = sent.revert_reason {
+            return Err(RelayerError::DeliveryReverted {
+                chain_id: dest_chain.chain_id,
+                tx_hash: receipt.transaction_hash,
+                kind: revert::classify(&reason),
+                reason,
+            }
+            .into());
+        }
+
+        Ok(receipt)
+    }
+}
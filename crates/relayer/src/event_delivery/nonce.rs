@@ -0,0 +1,110 @@
+use anyhow::Result;
+use ethers::{
+    core::types::{Address, BlockNumber, U256},
+    providers::Middleware,
+};
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::{Arc, Mutex},
+};
+use tracing::debug;
+
+use crate::types::ChainId;
+
+// Next nonce to hand out for one counter on one chain, unknown until first use
+type NonceSlot = Arc<tokio::sync::Mutex<Option<U256>>>;
+
+/// Hands out destination nonces to concurrent deliveries
+///
+/// Assignment is serialized per (chain, counter, owner) so no two deliveries
+/// sign with the same nonce, but the lock is released before broadcast so
+/// the transactions themselves are still sent in parallel. An account's own
+/// nonce is the counter it keeps for itself; a contract such as a forwarder
+/// keeps a separate counter per owner. The first assignment reads the
+/// counter from the node; after that nonces are counted locally until
+/// `reset` forces a resync.
+#[derive(Default)]
+pub struct NonceManager {
+    slots: Mutex<HashMap<(ChainId, Address, Address), NonceSlot>>,
+}
+
+impl NonceManager {
+    fn slot(&self, chain_id: ChainId, counter: Address, owner: Address) -> NonceSlot {
+        self.slots
+            .lock()
+            .expect("nonce slots lock poisoned")
+            .entry((chain_id, counter, owner))
+            .or_default()
+            .clone()
+    }
+
+    /// Reserve the next nonce for `address` on `chain_id`
+    pub async fn next<M: Middleware>(
+        &self,
+        client: &M,
+        chain_id: ChainId,
+        address: Address,
+    ) -> Result<U256>
+    where
+        M::Error: 'static,
+    {
+        self.next_with(chain_id, address, address, || async {
+            Ok(client
+                .get_transaction_count(address, Some(BlockNumber::Pending.into()))
+                .await?)
+        })
+        .await
+    }
+
+    /// Reserve the next nonce `counter` keeps for `owner`, syncing from
+    /// `fetch` on first use
+    ///
+    /// Lets nonces other than account nonces, such as a forwarder's
+    /// per-signer counter, share the same serialization.
+    pub async fn next_with<F, Fut>(
+        &self,
+        chain_id: ChainId,
+        counter: Address,
+        owner: Address,
+        fetch: F,
+    ) -> Result<U256>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<U256>>,
+    {
+        let slot = self.slot(chain_id, counter, owner);
+        let mut next = slot.lock().await;
+        let nonce = match *next {
+            Some(nonce) => nonce,
+            None => {
+                let nonce = fetch().await?;
+                debug!(%chain_id, %counter, %owner, %nonce, "Synced nonce from node");
+                nonce
+            }
+        };
+        *next = Some(nonce + 1);
+        Ok(nonce)
+    }
+
+    /// Forget the locally tracked nonce so the next assignment re-reads it,
+    /// returning the nonce that would have been handed out next
+    ///
+    /// Called when a broadcast fails, since the reserved nonce may never be
+    /// used and later ones would otherwise be stuck behind the gap.
+    pub async fn reset(&self, chain_id: ChainId, address: Address) -> Option<U256> {
+        self.reset_with(chain_id, address, address).await
+    }
+
+    /// Forget the nonce `counter` keeps for `owner`, like `reset`
+    pub async fn reset_with(
+        &self,
+        chain_id: ChainId,
+        counter: Address,
+        owner: Address,
+    ) -> Option<U256> {
+        let slot = self.slot(chain_id, counter, owner);
+        let next = slot.lock().await.take();
+        next
+    }
+}
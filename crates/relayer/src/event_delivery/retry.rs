@@ -0,0 +1,125 @@
+use anyhow::Result;
+use std::{
+    collections::HashSet,
+    sync::{Arc, Mutex},
+};
+use tracing::{error, warn};
+
+use crate::alerts::{self, AlertKind};
+use crate::clock::{backoff, Clock};
+use crate::config::RetryConfig;
+use crate::metrics::{PairLabels, DEAD_LETTERS, DELIVERY_RETRIES};
+use crate::store::{FailedDelivery, ProofKey, StateStore};
+use crate::types::{DeliveryRequest, DeliveryStatus};
+
+/// Failed deliveries waiting in the store for their next attempt
+pub struct RetryQueue {
+    store: Arc<dyn StateStore>,
+    config: RetryConfig,
+    /// Decides when a retry is due
+    clock: Arc<dyn Clock>,
+    /// Retries currently being attempted, so they are not handed out twice
+    in_flight: Mutex<HashSet<ProofKey>>,
+}
+
+impl RetryQueue {
+    pub fn new(store: Arc<dyn StateStore>, config: RetryConfig, clock: Arc<dyn Clock>) -> Self {
+        Self {
+            store,
+            config,
+            clock,
+            in_flight: Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// An empty queue over the same store and settings, timed by `clock`
+    pub fn on_clock(&self, clock: Arc<dyn Clock>) -> Self {
+        Self::new(self.store.clone(), self.config.clone(), clock)
+    }
+
+    /// Queue a failed delivery for another attempt, or dead-letter it once it
+    /// is out of attempts or the failure is permanent
+    pub fn record_failure(
+        &self,
+        delivery: &DeliveryRequest,
+        error: &anyhow::Error,
+        retryable: bool,
+    ) -> DeliveryStatus {
+        let key = ProofKey::from_meta(&delivery.event.meta);
+        let attempts = self.attempts_made(&key) + 1;
+        let mut failed = FailedDelivery::new(delivery, attempts, format!("{:#}", error));
+        let dest_chain = delivery.event.destination_chain.name.as_str();
+        let labels = PairLabels::of(&delivery.event);
+        self.settle(&key);
+
+        if retryable && attempts < self.config.max_attempts {
+            let delay = backoff(
+                self.config.initial_backoff_ms,
+                self.config.max_backoff_ms,
+                attempts,
+            );
+            failed.next_attempt_at = self.clock.unix_time() + delay.as_secs();
+            // Without the queue entry the event is still pending, and is
+            // picked up again on the next restart
+            if let Err(e) = self.store.save_retry(&key, &failed) {
+                warn!(error = %e, proof_key = %key, "Failed to persist delivery retry");
+            }
+            DELIVERY_RETRIES.with_label_values(&labels.values()).inc();
+            warn!(proof_key = %key, attempts, ?delay, "Delivery queued for retry");
+            return DeliveryStatus::Retrying { attempts };
+        }
+
+        if let Err(e) = self.store.save_dead_letter(&key, &failed) {
+            warn!(error = %e, proof_key = %key, "Failed to persist dead letter");
+        }
+        DEAD_LETTERS.with_label_values(&labels.values()).inc();
+        error!(proof_key = %key, attempts, retryable, "ALERT: delivery moved to dead letter queue");
+        alerts::notify(
+            AlertKind::DeadLetter,
+            dest_chain,
+            format!(
+                "Delivery of {} moved to dead letter queue after {} attempts: {}",
+                key, attempts, failed.last_error
+            ),
+        );
+        DeliveryStatus::DeadLettered
+    }
+
+    /// Failed attempts already made at delivering an event
+    pub fn attempts_made(&self, key: &ProofKey) -> u32 {
+        match self.store.retry(key) {
+            Ok(queued) => queued.map_or(0, |failed| failed.attempts),
+            Err(e) => {
+                warn!(error = %e, proof_key = %key, "Failed to look up delivery retry");
+                0
+            }
+        }
+    }
+
+    /// Take every retry that is due, marking it in flight until it is settled
+    pub fn take_due(&self) -> Result<Vec<DeliveryRequest>> {
+        let now = self.clock.unix_time();
+        let mut in_flight = self.in_flight.lock().expect("retry lock poisoned");
+        Ok(self
+            .store
+            .retries()?
+            .into_iter()
+            .filter(|failed| failed.next_attempt_at <= now)
+            .filter(|failed| in_flight.insert(ProofKey::from_meta(&failed.event.meta)))
+            .map(|failed| failed.to_request())
+            .collect())
+    }
+
+    /// Forget a delivery that has finished for good
+    pub fn remove(&self, key: &ProofKey) -> Result<()> {
+        self.settle(key);
+        self.store.remove_retry(key)
+    }
+
+    fn settle(&self, key: &ProofKey) {
+        self.in_flight
+            .lock()
+            .expect("retry lock poisoned")
+            .remove(key);
+    }
+}
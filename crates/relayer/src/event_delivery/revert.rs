@@ -0,0 +1,97 @@
+use anyhow::Result;
+use ethers::{
+    abi::{self, ParamType, Token},
+    core::types::{transaction::eip2718::TypedTransaction, BlockId},
+    providers::{Middleware, MiddlewareError},
+    utils::{hex, id},
+};
+
+use crate::types::RevertKind;
+
+// Selector of the `Error(string)` revert emitted by `require` and `revert("...")`
+const ERROR_STRING_SELECTOR: [u8; 4] = [0x08, 0xc3, 0x79, 0xa0];
+// Selector of the `Panic(uint256)` revert emitted on assertion and arithmetic failures
+const PANIC_SELECTOR: [u8; 4] = [0x4e, 0x48, 0x7b, 0x71];
+// Custom errors of the L2ToL2CrossDomainMessenger, which `op_interop`
+// deliveries call directly
+const MESSENGER_ERRORS: [&str; 6] = [
+    "MessageAlreadyRelayed()",
+    "MessageDestinationNotRelayChain()",
+    "MessageTargetL2ToL2CrossDomainMessenger()",
+    "IdOriginNotL2ToL2CrossDomainMessenger()",
+    "EventPayloadNotSentMessage()",
+    "TargetCallFailed()",
+];
+
+/// Render revert data as a human-readable reason
+///
+/// Standard `Error(string)` and `Panic(uint256)` payloads are decoded, and the
+/// interop messenger's custom errors named; other custom errors are reported
+/// by selector since their ABI is not known here.
+pub fn decode_revert(data: &[u8]) -> String {
+    if data.len() < 4 {
+        return format!("reverted without reason (0x{})", hex::encode(data));
+    }
+    let (selector, args) = data.split_at(4);
+
+    if selector == ERROR_STRING_SELECTOR {
+        if let Ok(tokens) = abi::decode(&[ParamType::String], args) {
+            if let Some(Token::String(reason)) = tokens.into_iter().next() {
+                return reason;
+            }
+        }
+    } else if selector == PANIC_SELECTOR {
+        if let Ok(tokens) = abi::decode(&[ParamType::Uint(256)], args) {
+            if let Some(Token::Uint(code)) = tokens.into_iter().next() {
+                return format!("panic 0x{:x}", code);
+            }
+        }
+    } else if let Some(error) = MESSENGER_ERRORS
+        .iter()
+        .find(|signature| id(signature) == selector)
+    {
+        return error.trim_end_matches("()").to_string();
+    }
+
+    format!("custom error 0x{}", hex::encode(selector))
+}
+
+/// Sort a revert reason into the failure classes the relayer acts on
+///
+/// Matching is on the reason text, so it relies on dapps and the prover
+/// using recognizable wording; anything unrecognized is the dapp's own rejection.
+pub fn classify(reason: &str) -> RevertKind {
+    let reason = reason.to_lowercase();
+    if reason.contains("already") {
+        RevertKind::AlreadyExecuted
+    } else if reason.contains("proof") || reason.contains("verif") {
+        RevertKind::InvalidProof
+    } else {
+        RevertKind::DappRejected
+    }
+}
+
+/// Run the transaction through `eth_call`, returning the revert reason if it fails
+///
+/// Errors that are not reverts, such as a dropped connection, are passed through.
+pub async fn simulate<M: Middleware>(
+    client: &M,
+    tx: &TypedTransaction,
+    block: Option<BlockId>,
+) -> Result<Option<String>>
+where
+    M::Error: 'static,
+{
+    match client.call(tx, block).await {
+        Ok(_) => Ok(None),
+        Err(e) => match e.as_error_response() {
+            Some(response) if response.is_revert() => Ok(Some(
+                response
+                    .as_revert_data()
+                    .map(|data| decode_revert(&data))
+                    .unwrap_or_else(|| response.message.clone()),
+            )),
+            _ => Err(e.into()),
+        },
+    }
+}
@@ -0,0 +1,181 @@
+use anyhow::Result;
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Mutex,
+};
+use tracing::debug;
+
+use crate::config::RelayPair;
+use crate::store::StateStore;
+use crate::types::DeliveryRequest;
+
+/// Holds deliveries of ordered pairs until every lower nonce of the pair is done
+///
+/// An event counts as done once it leaves the store's pending events, so a
+/// lower nonce still waiting on its proof or queued for retry holds back
+/// everything after it.
+#[derive(Default)]
+pub struct Sequencer {
+    held: Mutex<HashMap<String, BTreeMap<u64, DeliveryRequest>>>,
+}
+
+impl Sequencer {
+    /// Hold `delivery`, returning the pair's next delivery if it is clear to go
+    pub fn admit(
+        &self,
+        pair: &RelayPair,
+        delivery: DeliveryRequest,
+        store: &dyn StateStore,
+    ) -> Result<Option<DeliveryRequest>> {
+        debug!(pair = %pair.id(), nonce = delivery.event.nonce, "Holding ordered delivery");
+        self.held
+            .lock()
+            .expect("sequencer lock poisoned")
+            .entry(pair.id())
+            .or_default()
+            .insert(delivery.event.nonce, delivery);
+        self.release(pair, store)
+    }
+
+    /// The pair's lowest held delivery, once no lower nonce is still pending
+    pub fn release(
+        &self,
+        pair: &RelayPair,
+        store: &dyn StateStore,
+    ) -> Result<Option<DeliveryRequest>> {
+        let lowest_pending = store
+            .pending_events()?
+            .into_iter()
+            .filter(|event| pair.matches(event))
+            .map(|event| event.nonce)
+            .min();
+
+        let mut held = self.held.lock().expect("sequencer lock poisoned");
+        let Some(queue) = held.get_mut(&pair.id()) else {
+            return Ok(None);
+        };
+        let Some((&next, _)) = queue.first_key_value() else {
+            return Ok(None);
+        };
+        if lowest_pending.is_some_and(|lowest| lowest < next) {
+            return Ok(None);
+        }
+        Ok(queue.remove(&next))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::core::types::Address;
+    use std::path::Path;
+
+    use crate::store::{FileStateStore, ProofKey};
+    use crate::testkit::{self, EventBuilder};
+
+    // Delivery of `nonce`, its source log in a block of its own
+    fn delivery(nonce: u64) -> DeliveryRequest {
+        let event = EventBuilder::new(&testkit::relay_pair())
+            .nonce(nonce)
+            .log(100 + nonce, 0, 0)
+            .build();
+        testkit::delivery_request(event)
+    }
+
+    // A store with `nonces` still pending
+    fn pending(dir: &Path, nonces: &[u64]) -> FileStateStore {
+        let store = FileStateStore::open(dir).unwrap();
+        for &nonce in nonces {
+            let event = delivery(nonce).event;
+            store
+                .save_pending_event(&ProofKey::from_meta(&event.meta), &event)
+                .unwrap();
+        }
+        store
+    }
+
+    fn finish(store: &FileStateStore, nonce: u64) {
+        let event = delivery(nonce).event;
+        store
+            .remove_pending_event(&ProofKey::from_meta(&event.meta))
+            .unwrap();
+    }
+
+    fn nonce(delivery: Option<DeliveryRequest>) -> Option<u64> {
+        delivery.map(|delivery| delivery.event.nonce)
+    }
+
+    #[test]
+    fn holds_deliveries_until_lower_nonces_are_done() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = pending(dir.path(), &[1, 2, 3]);
+        let pair = testkit::relay_pair();
+        let sequencer = Sequencer::default();
+
+        assert_eq!(
+            nonce(sequencer.admit(&pair, delivery(3), &store).unwrap()),
+            None
+        );
+        assert_eq!(
+            nonce(sequencer.admit(&pair, delivery(2), &store).unwrap()),
+            None
+        );
+        assert_eq!(
+            nonce(sequencer.admit(&pair, delivery(1), &store).unwrap()),
+            Some(1)
+        );
+
+        // Released deliveries stay pending until they are delivered
+        assert_eq!(nonce(sequencer.release(&pair, &store).unwrap()), None);
+        finish(&store, 1);
+        assert_eq!(nonce(sequencer.release(&pair, &store).unwrap()), Some(2));
+        finish(&store, 2);
+        assert_eq!(nonce(sequencer.release(&pair, &store).unwrap()), Some(3));
+        assert_eq!(nonce(sequencer.release(&pair, &store).unwrap()), None);
+    }
+
+    #[test]
+    fn releases_in_nonce_order_once_nothing_lower_is_pending() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = pending(dir.path(), &[5, 6]);
+        let pair = testkit::relay_pair();
+        let sequencer = Sequencer::default();
+
+        assert_eq!(
+            nonce(sequencer.admit(&pair, delivery(6), &store).unwrap()),
+            None
+        );
+        assert_eq!(
+            nonce(sequencer.admit(&pair, delivery(5), &store).unwrap()),
+            Some(5)
+        );
+        finish(&store, 5);
+        finish(&store, 6);
+        assert_eq!(nonce(sequencer.release(&pair, &store).unwrap()), Some(6));
+    }
+
+    #[test]
+    fn keeps_pairs_apart() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = pending(dir.path(), &[1]);
+        let pair = testkit::relay_pair();
+        let other = RelayPair {
+            dest_dapp_address: Address::from_low_u64_be(0xbeef),
+            ..testkit::relay_pair()
+        };
+        let sequencer = Sequencer::default();
+
+        assert_eq!(
+            nonce(sequencer.admit(&pair, delivery(2), &store).unwrap()),
+            None
+        );
+        // Nothing is held for the other pair, and its pending events are its own
+        assert_eq!(nonce(sequencer.release(&other, &store).unwrap()), None);
+        let other_delivery =
+            testkit::delivery_request(EventBuilder::new(&other).nonce(9).log(900, 0, 0).build());
+        assert_eq!(
+            nonce(sequencer.admit(&other, other_delivery, &store).unwrap()),
+            Some(9)
+        );
+    }
+}
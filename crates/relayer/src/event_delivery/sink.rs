@@ -0,0 +1,262 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use ethers::core::types::H256;
+use std::{sync::Arc, time::Duration};
+use tokio::sync::{broadcast, mpsc};
+use tokio_util::task::TaskTracker;
+use tracing::{error, info, info_span, instrument, warn, Instrument};
+
+use super::ledger::DeliveryLedger;
+use super::retry::RetryQueue;
+use crate::audit::{self, AuditAction};
+use crate::clock::{Clock, SystemClock, Ticker};
+use crate::config::DeliveryConfig;
+use crate::failure::{self, FailureClass};
+use crate::health::{Health, EVENT_DELIVERER};
+use crate::hooks::Hooks;
+use crate::journal::Journal;
+use crate::latency;
+use crate::status::{self, EventStatus};
+use crate::store::{ProofKey, StateStore};
+use crate::topology::Topology;
+use crate::types::{DeliveryOutcome, DeliveryRequest, DeliveryStatus, RelayerError};
+
+// How often the retry queue is checked for deliveries that are due
+const RETRY_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Destination for proven events, used in place of on-chain delivery
+///
+/// Without one, a relayer delivers on chain through [`EventDeliverer`], which
+/// batches, orders and prices transactions across a whole queue and so is
+/// not a sink itself.
+///
+/// A failed delivery is retried with backoff like an on-chain one, unless the
+/// error is a [`RelayerError`] that is not retryable, which dead-letters it.
+///
+/// [`EventDeliverer`]: crate::EventDeliverer
+#[async_trait]
+pub trait DeliverySink: Send + Sync {
+    /// Deliver one proven event, returning the transaction that carried it,
+    /// if there was one
+    async fn deliver(&self, request: &DeliveryRequest) -> Result<Option<H256>>;
+}
+
+/// Feeds the delivery queue into a [`DeliverySink`], keeping the pending
+/// event, retry and outcome bookkeeping of the built-in deliverer
+pub(crate) struct SinkDeliverer {
+    delivery_rx: mpsc::Receiver<DeliveryRequest>,
+    context: Arc<SinkContext>,
+}
+
+struct SinkContext {
+    sink: Arc<dyn DeliverySink>,
+    store: Arc<dyn StateStore>,
+    retries: RetryQueue,
+    topology: Topology,
+    health: Health,
+    outcomes: broadcast::Sender<DeliveryOutcome>,
+    hooks: Hooks,
+    journal: Journal,
+    ledger: DeliveryLedger,
+    clock: Arc<dyn Clock>,
+    tasks: TaskTracker,
+}
+
+impl SinkDeliverer {
+    pub(crate) fn new(
+        sink: Arc<dyn DeliverySink>,
+        delivery_rx: mpsc::Receiver<DeliveryRequest>,
+        store: Arc<dyn StateStore>,
+        config: &DeliveryConfig,
+        topology: Topology,
+        health: Health,
+        outcomes: broadcast::Sender<DeliveryOutcome>,
+    ) -> Self {
+        let clock: Arc<dyn Clock> = Arc::new(SystemClock);
+        Self {
+            delivery_rx,
+            context: Arc::new(SinkContext {
+                sink,
+                retries: RetryQueue::new(store.clone(), config.retry.clone(), clock.clone()),
+                ledger: DeliveryLedger::new(store.clone(), config.claim_timeout_ms),
+                store,
+                topology,
+                health,
+                outcomes,
+                hooks: Hooks::default(),
+                journal: Journal::default(),
+                clock,
+                tasks: TaskTracker::new(),
+            }),
+        }
+    }
+
+    pub(crate) fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        let context = Arc::get_mut(&mut self.context).expect("sink deliverer has not started");
+        context.retries = context.retries.on_clock(clock.clone());
+        context.clock = clock;
+        self
+    }
+
+    pub(crate) fn with_hooks(mut self, hooks: Hooks) -> Self {
+        Arc::get_mut(&mut self.context)
+            .expect("sink deliverer has not started")
+            .hooks = hooks;
+        self
+    }
+
+    pub(crate) fn with_journal(mut self, journal: Journal) -> Self {
+        Arc::get_mut(&mut self.context)
+            .expect("sink deliverer has not started")
+            .journal = journal;
+        self
+    }
+
+    #[instrument(skip(self), name = "sink_deliverer_start")]
+    pub(crate) async fn start(&mut self) -> Result<()> {
+        info!("Starting delivery sink");
+        self.context.health.beat(EVENT_DELIVERER);
+        let retries = tokio::spawn(self.context.clone().run_retries());
+
+        while let Some(delivery) = self.delivery_rx.recv().await {
+            self.context.health.beat(EVENT_DELIVERER);
+            let context = self.context.clone();
+            self.context
+                .tasks
+                .spawn(async move { context.deliver(delivery).await });
+        }
+
+        retries.abort();
+        self.context.tasks.close();
+        info!(
+            in_flight = self.context.tasks.len(),
+            "Delivery channel closed, waiting for in-flight deliveries"
+        );
+        self.context.tasks.wait().await;
+        Ok(())
+    }
+}
+
+impl SinkContext {
+    async fn deliver(&self, delivery: DeliveryRequest) {
+        let event = &delivery.event;
+        let proof_key = ProofKey::from_meta(&event.meta);
+        let span = info_span!(
+            "delivery",
+            stage = "delivery",
+            event_id = %event.id(),
+            chain_id = event.destination_chain.chain_id.as_u64(),
+            pair = %event.pair_id(),
+            nonce = event.nonce
+        );
+        async {
+            self.topology.wait_while_paused(&[event]).await;
+            let cleared = match self.hooks.before_delivery(&delivery).await {
+                Ok(()) => self.ledger.claim(event),
+                Err(e) => Err(e),
+            };
+            let result = match cleared {
+                Ok(()) => {
+                    status::advance(&*self.store, event, EventStatus::Delivering);
+                    latency::broadcast(event);
+                    self.sink.deliver(&delivery).await
+                }
+                Err(e) => Err(e),
+            }
+            .map_err(|e| failure::attribute(event.destination_chain.chain_id, e));
+            let mut outcome = DeliveryOutcome {
+                proof_key: proof_key.clone(),
+                event_id: event.id(),
+                pair: self.topology.pair_for(event).map(|pair| pair.id()),
+                source_chain_id: event.source_chain.chain_id,
+                dest_chain_id: event.destination_chain.chain_id,
+                nonce: event.nonce,
+                attempt: self.retries.attempts_made(&proof_key) + 1,
+                attempted_at: self.clock.unix_time(),
+                tx_hash: None,
+                block_number: None,
+                gas_used: None,
+                status: DeliveryStatus::Delivered,
+                error: None,
+            };
+
+            match result {
+                Ok(tx_hash) => {
+                    info!(proof_key = %proof_key, "Event delivered to sink");
+                    outcome.tx_hash = tx_hash;
+                    self.health.event_delivered(outcome.pair.as_deref());
+                    self.ledger.confirm(event, tx_hash);
+                    self.finish(&proof_key);
+                }
+                Err(e)
+                    if e.downcast_ref::<RelayerError>()
+                        .is_some_and(RelayerError::is_already_delivered) =>
+                {
+                    info!(error = %e, proof_key = %proof_key, "Event was already delivered");
+                    outcome.error = Some(format!("{:#}", e));
+                    outcome.status = DeliveryStatus::AlreadyExecuted;
+                    self.ledger.confirm(event, None);
+                    self.finish(&proof_key);
+                }
+                Err(e) => {
+                    // Sinks report failures of their own kinds, so only
+                    // those known to be fatal are given up on
+                    let retryable = FailureClass::of(&e) != FailureClass::Fatal;
+                    error!(error = %e, proof_key = %proof_key, retryable, "Failed to deliver event");
+                    self.ledger.release(event);
+                    outcome.error = Some(format!("{:#}", e));
+                    outcome.status = self.retries.record_failure(&delivery, &e, retryable);
+                    if outcome.status == DeliveryStatus::DeadLettered {
+                        self.finish(&proof_key);
+                    }
+                }
+            }
+
+            if let Err(e) = self.store.save_delivery_attempt(&outcome) {
+                warn!(error = %e, proof_key = %proof_key, "Failed to record delivery attempt");
+            }
+            audit::record(AuditAction::delivery(&outcome));
+            self.journal.settled(&proof_key, &outcome.status);
+            if let Some(settled) = EventStatus::settled(&outcome.status, outcome.error.as_deref()) {
+                status::advance(&*self.store, event, settled);
+            }
+            self.hooks.after_delivery(&outcome).await;
+            // Nobody listening is fine
+            let _ = self.outcomes.send(outcome);
+        }
+        .instrument(span)
+        .await
+    }
+
+    fn finish(&self, proof_key: &ProofKey) {
+        if let Err(e) = self.retries.remove(proof_key) {
+            warn!(error = %e, proof_key = %proof_key, "Failed to clear delivery retry");
+        }
+        if let Err(e) = self.store.remove_pending_event(proof_key) {
+            warn!(error = %e, proof_key = %proof_key, "Failed to clear pending event");
+        }
+        if let Err(e) = self.store.remove_proof(proof_key) {
+            warn!(error = %e, proof_key = %proof_key, "Failed to prune cached proof");
+        }
+    }
+
+    // Re-send queued deliveries as they come due, forever
+    async fn run_retries(self: Arc<Self>) {
+        let mut ticker = Ticker::new(self.clock.clone(), RETRY_POLL_INTERVAL);
+        loop {
+            ticker.tick().await;
+            let due = match self.retries.take_due() {
+                Ok(due) => due,
+                Err(e) => {
+                    warn!(error = %e, "Failed to read retry queue");
+                    continue;
+                }
+            };
+            for delivery in due {
+                let context = self.clone();
+                self.tasks
+                    .spawn(async move { context.deliver(delivery).await });
+            }
+        }
+    }
+}
@@ -0,0 +1,66 @@
+use anyhow::{anyhow, Context, Result};
+use ed25519_dalek::{Signer, SigningKey};
+use std::{fmt, fs, path::Path};
+
+use crate::types::{RelayerError, SolanaPubkey};
+
+/// Ed25519 keypair paying for and signing Solana deliveries
+#[derive(Clone)]
+pub struct SolanaKeypair {
+    signing_key: SigningKey,
+}
+
+impl SolanaKeypair {
+    /// Keypair of the 32-byte secret `seed`
+    pub fn from_seed(seed: [u8; 32]) -> Self {
+        Self {
+            signing_key: SigningKey::from_bytes(&seed),
+        }
+    }
+
+    /// Keypair of 64 bytes holding the secret followed by the public key, as
+    /// `solana-keygen` writes them
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let bytes: &[u8; 64] = bytes
+            .try_into()
+            .map_err(|_| anyhow!("Solana keypair is {} bytes, expected 64", bytes.len()))?;
+        let signing_key =
+            SigningKey::from_keypair_bytes(bytes).map_err(|_| RelayerError::Signing {
+                source: anyhow!("Solana keypair's public key does not match its secret"),
+            })?;
+        Ok(Self { signing_key })
+    }
+
+    /// Read a keypair file written by `solana-keygen`, a JSON array of its
+    /// 64 bytes
+    pub fn read(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let data = fs::read(path)
+            .with_context(|| format!("Failed to read Solana keypair {}", path.display()))?;
+        let bytes: Vec<u8> = serde_json::from_slice(&data)
+            .with_context(|| format!("Failed to parse Solana keypair {}", path.display()))?;
+        Self::from_bytes(&bytes)
+    }
+
+    /// The secret followed by the public key, as a keypair file holds them
+    pub fn to_bytes(&self) -> [u8; 64] {
+        self.signing_key.to_keypair_bytes()
+    }
+
+    pub fn pubkey(&self) -> SolanaPubkey {
+        SolanaPubkey::new(self.signing_key.verifying_key().to_bytes())
+    }
+
+    pub(crate) fn sign(&self, message: &[u8]) -> [u8; 64] {
+        self.signing_key.sign(message).to_bytes()
+    }
+}
+
+// Only the public half is ever printed
+impl fmt::Debug for SolanaKeypair {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SolanaKeypair")
+            .field("pubkey", &self.pubkey())
+            .finish_non_exhaustive()
+    }
+}
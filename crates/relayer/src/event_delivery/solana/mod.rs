@@ -0,0 +1,442 @@
+mod keypair;
+mod rpc;
+mod transaction;
+
+use anyhow::{anyhow, bail, Result};
+use async_trait::async_trait;
+use ethers::core::types::H256;
+use std::time::Duration;
+use tokio::{
+    sync::Mutex,
+    time::{self, Instant},
+};
+use tracing::{info, instrument, warn};
+
+use super::revert;
+use super::sink::DeliverySink;
+use crate::config::{SolanaProofBufferConfig, SolanaSinkConfig};
+use crate::types::{ChainId, DeliveryRequest, RelayerError, SolanaPubkey};
+use rpc::{RpcError, SolanaRpcClient};
+use transaction::{AccountMeta, Instruction, MAX_TRANSACTION_SIZE};
+
+pub use keypair::SolanaKeypair;
+#[cfg(feature = "testkit")]
+pub(crate) use transaction::{
+    decode, DecodedTransaction, COMPUTE_BUDGET_PROGRAM, SET_COMPUTE_UNIT_LIMIT,
+    SET_COMPUTE_UNIT_PRICE,
+};
+
+// How often a sent delivery is checked on
+const CONFIRM_POLL_INTERVAL: Duration = Duration::from_millis(500);
+// Base fee of every signature, in lamports
+const LAMPORTS_PER_SIGNATURE: u64 = 5_000;
+// Most compute units a transaction may ask for
+const MAX_COMPUTE_UNIT_LIMIT: u32 = 1_400_000;
+
+/// Delivers proven events to a program on Solana
+///
+/// Each delivery is a transaction signed by the configured fee payer, calling
+/// the program's instruction with the event's source chain id, nonce, payload
+/// and proof. It bids a priority fee from what recent transactions writing
+/// the same accounts paid, and counts as delivered once it reaches the
+/// configured commitment.
+///
+/// A delivery too large for one transaction has its proof written to the
+/// configured proof buffer first, in as many transactions as it takes.
+///
+/// Solana signatures do not fit in a transaction hash, so deliveries are
+/// reported without one and their signatures are logged instead.
+pub struct SolanaSink {
+    rpc: SolanaRpcClient,
+    payer: SolanaKeypair,
+    config: SolanaSinkConfig,
+    /// Held from the first proof chunk written to the buffer until the
+    /// delivery reading it is confirmed
+    proof_buffer: Mutex<()>,
+}
+
+impl SolanaSink {
+    /// Sink signing with the keypair file the configuration names
+    pub fn new(config: SolanaSinkConfig) -> Result<Self> {
+        let payer = SolanaKeypair::read(&config.keypair_path)?;
+        Self::with_keypair(config, payer)
+    }
+
+    /// Sink signing with `payer`, ignoring the configured keypair file
+    pub fn with_keypair(config: SolanaSinkConfig, payer: SolanaKeypair) -> Result<Self> {
+        validate(&config)?;
+        let rpc = SolanaRpcClient::new(
+            config.rpc_url.clone(),
+            config.commitment,
+            Duration::from_millis(config.request_timeout_ms),
+        )?;
+        Ok(Self {
+            rpc,
+            payer,
+            config,
+            proof_buffer: Mutex::new(()),
+        })
+    }
+
+    /// Fee payer of every delivery
+    pub fn pubkey(&self) -> SolanaPubkey {
+        self.payer.pubkey()
+    }
+
+    // The delivery call, carrying the proof itself or, once it is staged,
+    // an empty proof and the buffer holding it
+    fn instructions(
+        &self,
+        request: &DeliveryRequest,
+        compute_unit_price: u64,
+        buffer: Option<&SolanaProofBufferConfig>,
+    ) -> Vec<Instruction> {
+        let event = &request.event;
+        let mut data = transaction::anchor_discriminator(&self.config.instruction).to_vec();
+        data.extend_from_slice(&event.source_chain.chain_id.as_u64().to_le_bytes());
+        data.extend_from_slice(&event.nonce.to_le_bytes());
+        push_bytes(&mut data, &event.exec_payload);
+        match buffer {
+            Some(_) => push_bytes(&mut data, &[]),
+            None => push_bytes(&mut data, &request.proof.data),
+        }
+
+        let accounts = std::iter::once(self.payer_meta())
+            .chain(self.config.accounts.iter().map(|account| AccountMeta {
+                pubkey: account.pubkey,
+                signer: false,
+                writable: account.writable,
+            }))
+            .chain(buffer.map(|buffer| AccountMeta {
+                pubkey: buffer.account,
+                signer: false,
+                writable: true,
+            }))
+            .collect();
+        self.with_compute_budget(
+            compute_unit_price,
+            Instruction {
+                program_id: self.config.program_id,
+                accounts,
+                data,
+            },
+        )
+    }
+
+    // Write of `chunk` at `offset` into the buffer of a `proof_len` byte proof
+    fn buffer_write(
+        &self,
+        buffer: &SolanaProofBufferConfig,
+        compute_unit_price: u64,
+        proof_len: usize,
+        offset: usize,
+        chunk: &[u8],
+    ) -> Vec<Instruction> {
+        let mut data = transaction::anchor_discriminator(&buffer.instruction).to_vec();
+        data.extend_from_slice(&(proof_len as u32).to_le_bytes());
+        data.extend_from_slice(&(offset as u32).to_le_bytes());
+        push_bytes(&mut data, chunk);
+        let buffer_meta = AccountMeta {
+            pubkey: buffer.account,
+            signer: false,
+            writable: true,
+        };
+        self.with_compute_budget(
+            compute_unit_price,
+            Instruction {
+                program_id: self.config.program_id,
+                accounts: vec![self.payer_meta(), buffer_meta],
+                data,
+            },
+        )
+    }
+
+    fn payer_meta(&self) -> AccountMeta {
+        AccountMeta {
+            pubkey: self.payer.pubkey(),
+            signer: true,
+            writable: true,
+        }
+    }
+
+    fn with_compute_budget(&self, compute_unit_price: u64, call: Instruction) -> Vec<Instruction> {
+        vec![
+            Instruction::set_compute_unit_limit(self.config.fees.compute_unit_limit),
+            Instruction::set_compute_unit_price(compute_unit_price),
+            call,
+        ]
+    }
+
+    // Size of the signed transaction running `instructions`, which does not
+    // depend on the blockhash
+    fn transaction_size(&self, instructions: &[Instruction]) -> usize {
+        transaction::sign(&self.payer, instructions, [0; 32])
+            .1
+            .len()
+    }
+
+    // Write the proof of `request` to the buffer, as many bytes a
+    // transaction as fit
+    async fn stage_proof(
+        &self,
+        request: &DeliveryRequest,
+        buffer: &SolanaProofBufferConfig,
+        compute_unit_price: u64,
+    ) -> Result<()> {
+        let proof = &request.proof.data;
+        let overhead =
+            self.transaction_size(&self.buffer_write(buffer, compute_unit_price, 0, 0, &[]));
+        // The instruction's compact data length may take one more byte once
+        // the chunk is in
+        let chunk_size = MAX_TRANSACTION_SIZE.saturating_sub(overhead + 1);
+        if chunk_size == 0 {
+            bail!("Solana proof buffer writes leave no room for proof bytes");
+        }
+        for (index, chunk) in proof.chunks(chunk_size).enumerate() {
+            let instructions = self.buffer_write(
+                buffer,
+                compute_unit_price,
+                proof.len(),
+                index * chunk_size,
+                chunk,
+            );
+            self.submit(request, &instructions, compute_unit_price)
+                .await?;
+        }
+        info!(
+            proof_len = proof.len(),
+            chunks = proof.len().div_ceil(chunk_size),
+            "Staged proof in Solana buffer"
+        );
+        Ok(())
+    }
+
+    // Sign `instructions` with a fresh blockhash, send them and wait for the
+    // transaction to reach the configured commitment
+    async fn submit(
+        &self,
+        request: &DeliveryRequest,
+        instructions: &[Instruction],
+        compute_unit_price: u64,
+    ) -> Result<()> {
+        let chain_id = request.destination_chain_id;
+        let latest = self.rpc.latest_blockhash().await?;
+        let mut blockhash = [0u8; 32];
+        if bs58::decode(&latest.blockhash).onto(&mut blockhash)? != blockhash.len() {
+            bail!(
+                "Solana node returned malformed blockhash {}",
+                latest.blockhash
+            );
+        }
+
+        let (signature, transaction) = transaction::sign(&self.payer, instructions, blockhash);
+        if transaction.len() > MAX_TRANSACTION_SIZE {
+            return Err(RelayerError::InvalidPayload(format!(
+                "delivery transaction is {} bytes, over Solana's {} byte limit",
+                transaction.len(),
+                MAX_TRANSACTION_SIZE
+            ))
+            .into());
+        }
+        let signature = bs58::encode(signature).into_string();
+        self.rpc
+            .send_transaction(&transaction)
+            .await
+            .map_err(|e| rejection(chain_id, request.event.nonce, e))?;
+        let priority_fee = (u128::from(compute_unit_price)
+            * u128::from(self.config.fees.compute_unit_limit))
+        .div_ceil(1_000_000);
+        info!(
+            signature,
+            compute_unit_price,
+            max_fee_lamports = LAMPORTS_PER_SIGNATURE as u128 + priority_fee,
+            "Sent Solana transaction"
+        );
+
+        self.confirm(chain_id, &signature, latest.last_valid_block_height)
+            .await?;
+        info!(signature, "Solana transaction confirmed");
+        Ok(())
+    }
+
+    // The configured percentile of what recent transactions writing the same
+    // accounts paid, within the configured bounds; the floor if the node
+    // cannot say
+    async fn compute_unit_price(&self) -> u64 {
+        let fees = &self.config.fees;
+        let writable: Vec<SolanaPubkey> = self
+            .config
+            .accounts
+            .iter()
+            .filter(|account| account.writable)
+            .map(|account| account.pubkey)
+            .collect();
+        match self.rpc.recent_prioritization_fees(&writable).await {
+            Ok(mut recent) => {
+                recent.sort_unstable();
+                let index = recent.len().saturating_sub(1)
+                    * usize::from(fees.priority_fee_percentile)
+                    / 100;
+                let bid = recent.get(index).copied().unwrap_or_default();
+                bid.clamp(fees.min_compute_unit_price, fees.max_compute_unit_price)
+            }
+            Err(e) => {
+                warn!(error = %e, "Failed to read recent Solana priority fees, bidding the floor");
+                fees.min_compute_unit_price
+            }
+        }
+    }
+
+    // Wait for a sent delivery to reach the configured commitment
+    //
+    // One that has not landed once its blockhash expires never will, so it
+    // fails to be signed again with a fresh one.
+    async fn confirm(
+        &self,
+        chain_id: ChainId,
+        signature: &str,
+        last_valid_block_height: u64,
+    ) -> Result<()> {
+        let timeout = Duration::from_millis(self.config.confirm_timeout_ms);
+        let deadline = Instant::now() + timeout;
+        loop {
+            time::sleep(CONFIRM_POLL_INTERVAL).await;
+            match self.rpc.signature_status(signature).await {
+                Ok(Some(status)) => {
+                    if let Some(err) = status.err.filter(|err| !err.is_null()) {
+                        return Err(RelayerError::TransactionFailed {
+                            chain_id,
+                            source: anyhow!("Solana transaction {} failed: {}", signature, err),
+                        }
+                        .into());
+                    }
+                    if status
+                        .confirmation_status
+                        .is_some_and(|reached| reached >= self.config.commitment)
+                    {
+                        return Ok(());
+                    }
+                }
+                Ok(None) => {
+                    let expired = self
+                        .rpc
+                        .block_height()
+                        .await
+                        .is_ok_and(|height| height > last_valid_block_height);
+                    if expired {
+                        bail!("Solana transaction {} expired before it landed", signature);
+                    }
+                }
+                Err(e) => warn!(error = %e, signature, "Failed to check Solana delivery"),
+            }
+            if Instant::now() >= deadline {
+                bail!(
+                    "Solana transaction {} not confirmed within {:?}",
+                    signature,
+                    timeout
+                );
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl DeliverySink for SolanaSink {
+    #[instrument(skip_all, fields(program_id = %self.config.program_id))]
+    async fn deliver(&self, request: &DeliveryRequest) -> Result<Option<H256>> {
+        let compute_unit_price = self.compute_unit_price().await;
+        let inline = self.instructions(request, compute_unit_price, None);
+        let buffer = match &self.config.proof_buffer {
+            Some(buffer) if self.transaction_size(&inline) > MAX_TRANSACTION_SIZE => buffer,
+            _ => {
+                self.submit(request, &inline, compute_unit_price).await?;
+                return Ok(None);
+            }
+        };
+
+        // Another delivery must not overwrite the buffer before this one has
+        // read it
+        let _buffer = self.proof_buffer.lock().await;
+        let buffered = self.instructions(request, compute_unit_price, Some(buffer));
+        if self.transaction_size(&buffered) > MAX_TRANSACTION_SIZE {
+            return Err(RelayerError::InvalidPayload(format!(
+                "delivery payload does not fit Solana's {} byte transaction limit \
+                 even with its proof buffered",
+                MAX_TRANSACTION_SIZE
+            ))
+            .into());
+        }
+        self.stage_proof(request, buffer, compute_unit_price)
+            .await?;
+        self.submit(request, &buffered, compute_unit_price).await?;
+        Ok(None)
+    }
+}
+
+// Sort a send the node refused into the failures the sink deliverer acts on:
+// a program failing the preflight simulation is classified by its logs like
+// an EVM revert, and a payer too poor for the fee like a low balance.
+// Anything else, such as an expired blockhash, is left to be retried.
+fn rejection(chain_id: ChainId, nonce: u64, error: anyhow::Error) -> anyhow::Error {
+    let Some(rpc_error) = error.downcast_ref::<RpcError>() else {
+        return error;
+    };
+    let Some(err) = rpc_error.transaction_error() else {
+        return error;
+    };
+    match err.as_str() {
+        Some("AlreadyProcessed") => {
+            return RelayerError::AlreadyExecuted { chain_id, nonce }.into()
+        }
+        Some("InsufficientFundsForFee") | Some("AccountNotFound") => {
+            return RelayerError::InsufficientBalance { chain_id }.into()
+        }
+        _ => {}
+    }
+    if err.get("InstructionError").is_none() {
+        return error;
+    }
+    let logs = rpc_error.logs();
+    let reason = if logs.is_empty() {
+        err.to_string()
+    } else {
+        logs.join("; ")
+    };
+    RelayerError::DeliverySimulationFailed {
+        chain_id,
+        kind: revert::classify(&reason),
+        reason,
+    }
+    .into()
+}
+
+// Borsh encoding of a `Vec<u8>`: its length as a little-endian u32, then its bytes
+fn push_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+    out.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+    out.extend_from_slice(bytes);
+}
+
+fn validate(config: &SolanaSinkConfig) -> Result<(), RelayerError> {
+    let fees = &config.fees;
+    let problem = if config.program_id == SolanaPubkey::default() {
+        "a program_id is required"
+    } else if config.instruction.is_empty() {
+        "instruction must not be empty"
+    } else if fees.compute_unit_limit == 0 || fees.compute_unit_limit > MAX_COMPUTE_UNIT_LIMIT {
+        "compute_unit_limit must be between 1 and 1400000"
+    } else if fees.priority_fee_percentile > 100 {
+        "priority_fee_percentile must be at most 100"
+    } else if fees.min_compute_unit_price > fees.max_compute_unit_price {
+        "min_compute_unit_price must not exceed max_compute_unit_price"
+    } else if config.proof_buffer.as_ref().is_some_and(|buffer| {
+        buffer.account == SolanaPubkey::default() || buffer.instruction.is_empty()
+    }) {
+        "proof_buffer needs an account and an instruction"
+    } else {
+        return Ok(());
+    };
+    Err(RelayerError::InvalidConfig(format!(
+        "Solana sink: {}",
+        problem
+    )))
+}
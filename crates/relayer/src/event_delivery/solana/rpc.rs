@@ -0,0 +1,186 @@
+use anyhow::{anyhow, Context, Result};
+use base64::{engine::general_purpose, Engine};
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::{json, Value};
+use std::time::Duration;
+
+use crate::config::SolanaCommitment;
+use crate::types::SolanaPubkey;
+
+// JSON-RPC response envelope, carrying either a result or an error object
+#[derive(Deserialize)]
+struct JsonRpcResponse<T> {
+    result: Option<T>,
+    error: Option<RpcError>,
+}
+
+/// Error object a Solana node answered a call with
+///
+/// A failed preflight simulation carries the transaction error and program
+/// logs in `data`.
+#[derive(Debug, Clone, Deserialize, thiserror::Error)]
+#[error("{message} (code {code})")]
+pub(crate) struct RpcError {
+    pub(crate) code: i64,
+    pub(crate) message: String,
+    #[serde(default)]
+    pub(crate) data: Option<Value>,
+}
+
+impl RpcError {
+    /// Program logs of a failed preflight simulation
+    pub(crate) fn logs(&self) -> Vec<String> {
+        self.data
+            .as_ref()
+            .and_then(|data| data.get("logs"))
+            .and_then(|logs| serde_json::from_value(logs.clone()).ok())
+            .unwrap_or_default()
+    }
+
+    /// Transaction error of a failed preflight simulation, such as
+    /// `"BlockhashNotFound"` or `{"InstructionError": [1, {"Custom": 6000}]}`
+    pub(crate) fn transaction_error(&self) -> Option<&Value> {
+        self.data
+            .as_ref()
+            .and_then(|data| data.get("err"))
+            .filter(|err| !err.is_null())
+    }
+}
+
+#[derive(Deserialize)]
+struct WithContext<T> {
+    value: T,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct LatestBlockhash {
+    pub(crate) blockhash: String,
+    pub(crate) last_valid_block_height: u64,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PrioritizationFee {
+    prioritization_fee: u64,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct SignatureStatus {
+    #[serde(default)]
+    pub(crate) err: Option<Value>,
+    #[serde(default)]
+    pub(crate) confirmation_status: Option<SolanaCommitment>,
+}
+
+/// Client of the Solana JSON-RPC API, for the calls deliveries make
+pub(crate) struct SolanaRpcClient {
+    http: reqwest::Client,
+    url: String,
+    commitment: SolanaCommitment,
+}
+
+impl SolanaRpcClient {
+    pub(crate) fn new(
+        url: String,
+        commitment: SolanaCommitment,
+        timeout: Duration,
+    ) -> Result<Self> {
+        let http = reqwest::Client::builder().timeout(timeout).build()?;
+        Ok(Self {
+            http,
+            url,
+            commitment,
+        })
+    }
+
+    pub(crate) async fn latest_blockhash(&self) -> Result<LatestBlockhash> {
+        let latest: WithContext<LatestBlockhash> = self
+            .call(
+                "getLatestBlockhash",
+                json!([{ "commitment": self.commitment }]),
+            )
+            .await?;
+        Ok(latest.value)
+    }
+
+    pub(crate) async fn block_height(&self) -> Result<u64> {
+        self.call("getBlockHeight", json!([{ "commitment": self.commitment }]))
+            .await
+    }
+
+    /// Priority fees paid in recent slots by transactions writing any of
+    /// `accounts`, in micro-lamports per compute unit
+    pub(crate) async fn recent_prioritization_fees(
+        &self,
+        accounts: &[SolanaPubkey],
+    ) -> Result<Vec<u64>> {
+        let fees: Vec<PrioritizationFee> = self
+            .call("getRecentPrioritizationFees", json!([accounts]))
+            .await?;
+        Ok(fees.into_iter().map(|fee| fee.prioritization_fee).collect())
+    }
+
+    /// Send a signed transaction after simulating it at the client's
+    /// commitment, returning its signature
+    ///
+    /// The node is not asked to rebroadcast it; a delivery that is not
+    /// confirmed in time is signed again with a fresh blockhash instead.
+    pub(crate) async fn send_transaction(&self, transaction: &[u8]) -> Result<String> {
+        let encoded = general_purpose::STANDARD.encode(transaction);
+        self.call(
+            "sendTransaction",
+            json!([encoded, {
+                "encoding": "base64",
+                "preflightCommitment": self.commitment,
+                "maxRetries": 0,
+            }]),
+        )
+        .await
+    }
+
+    pub(crate) async fn signature_status(
+        &self,
+        signature: &str,
+    ) -> Result<Option<SignatureStatus>> {
+        let statuses: WithContext<Vec<Option<SignatureStatus>>> = self
+            .call("getSignatureStatuses", json!([[signature]]))
+            .await?;
+        Ok(statuses.value.into_iter().next().flatten())
+    }
+
+    async fn call<T: DeserializeOwned>(&self, method: &str, params: Value) -> Result<T> {
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": method,
+            "params": params,
+        });
+        let response = self
+            .http
+            .post(&self.url)
+            .json(&body)
+            .send()
+            .await
+            .with_context(|| format!("Solana RPC call {} failed", method))?;
+        let status = response.status();
+        let text = response.text().await?;
+        if !status.is_success() {
+            return Err(anyhow!(
+                "Solana RPC call {} answered {}: {}",
+                method,
+                status,
+                text
+            ));
+        }
+        let response: JsonRpcResponse<T> = serde_json::from_str(&text)
+            .with_context(|| format!("Failed to parse Solana RPC {} response", method))?;
+        match (response.result, response.error) {
+            (_, Some(error)) => Err(anyhow::Error::new(error)
+                .context(format!("Solana RPC call {} was rejected", method))),
+            (Some(result), None) => Ok(result),
+            (None, None) => Err(anyhow!("{} response has neither result nor error", method)),
+        }
+    }
+}
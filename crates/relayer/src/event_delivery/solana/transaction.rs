@@ -0,0 +1,266 @@
+use sha2::{Digest, Sha256};
+
+use super::keypair::SolanaKeypair;
+use crate::types::SolanaPubkey;
+
+/// Largest serialized transaction a Solana node accepts
+pub(crate) const MAX_TRANSACTION_SIZE: usize = 1232;
+
+// `ComputeBudget111111111111111111111111111111`, the native program setting a
+// transaction's compute unit limit and price
+pub(crate) const COMPUTE_BUDGET_PROGRAM: SolanaPubkey = SolanaPubkey::new([
+    0x03, 0x06, 0x46, 0x6f, 0xe5, 0x21, 0x17, 0x32, 0xff, 0xec, 0xad, 0xba, 0x72, 0xc3, 0x9b, 0xe7,
+    0xbc, 0x8c, 0xe5, 0xbb, 0xc5, 0xf7, 0x12, 0x6b, 0x2c, 0x43, 0x9b, 0x3a, 0x40, 0x00, 0x00, 0x00,
+]);
+// Compute budget instruction tags
+pub(crate) const SET_COMPUTE_UNIT_LIMIT: u8 = 2;
+pub(crate) const SET_COMPUTE_UNIT_PRICE: u8 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct AccountMeta {
+    pub(crate) pubkey: SolanaPubkey,
+    pub(crate) signer: bool,
+    pub(crate) writable: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Instruction {
+    pub(crate) program_id: SolanaPubkey,
+    pub(crate) accounts: Vec<AccountMeta>,
+    pub(crate) data: Vec<u8>,
+}
+
+impl Instruction {
+    pub(crate) fn set_compute_unit_limit(units: u32) -> Self {
+        let mut data = vec![SET_COMPUTE_UNIT_LIMIT];
+        data.extend_from_slice(&units.to_le_bytes());
+        Self {
+            program_id: COMPUTE_BUDGET_PROGRAM,
+            accounts: Vec::new(),
+            data,
+        }
+    }
+
+    /// Priority fee, in micro-lamports per compute unit
+    pub(crate) fn set_compute_unit_price(micro_lamports: u64) -> Self {
+        let mut data = vec![SET_COMPUTE_UNIT_PRICE];
+        data.extend_from_slice(&micro_lamports.to_le_bytes());
+        Self {
+            program_id: COMPUTE_BUDGET_PROGRAM,
+            accounts: Vec::new(),
+            data,
+        }
+    }
+}
+
+/// First 8 bytes of the data of a call to the Anchor instruction `name`
+pub(crate) fn anchor_discriminator(name: &str) -> [u8; 8] {
+    let hash = Sha256::digest(format!("global:{}", name));
+    let mut discriminator = [0u8; 8];
+    discriminator.copy_from_slice(&hash[..8]);
+    discriminator
+}
+
+/// Legacy transaction running `instructions` with `payer` as its only signer
+///
+/// Returns the transaction's signature, which is also its id, and its wire
+/// encoding.
+pub(crate) fn sign(
+    payer: &SolanaKeypair,
+    instructions: &[Instruction],
+    recent_blockhash: [u8; 32],
+) -> ([u8; 64], Vec<u8>) {
+    let message = compile(payer.pubkey(), instructions, recent_blockhash);
+    let signature = payer.sign(&message);
+    let mut transaction = Vec::with_capacity(1 + signature.len() + message.len());
+    push_length(&mut transaction, 1);
+    transaction.extend_from_slice(&signature);
+    transaction.extend_from_slice(&message);
+    (signature, transaction)
+}
+
+// Serialize the legacy message: a header counting signers and read-only
+// accounts, every account once ordered writable signers, read-only signers,
+// writable others, read-only others, then the blockhash and the instructions
+// indexing into the accounts
+fn compile(
+    payer: SolanaPubkey,
+    instructions: &[Instruction],
+    recent_blockhash: [u8; 32],
+) -> Vec<u8> {
+    let mut accounts = vec![AccountMeta {
+        pubkey: payer,
+        signer: true,
+        writable: true,
+    }];
+    let mut add = |meta: AccountMeta| match accounts.iter_mut().find(|a| a.pubkey == meta.pubkey) {
+        Some(existing) => {
+            existing.signer |= meta.signer;
+            existing.writable |= meta.writable;
+        }
+        None => accounts.push(meta),
+    };
+    for instruction in instructions {
+        instruction.accounts.iter().copied().for_each(&mut add);
+        add(AccountMeta {
+            pubkey: instruction.program_id,
+            signer: false,
+            writable: false,
+        });
+    }
+    // Stable, so the payer stays first and the rest keep their order
+    accounts.sort_by_key(|meta| (!meta.signer, !meta.writable));
+
+    let signers = accounts.iter().filter(|meta| meta.signer).count();
+    let readonly_signers = accounts
+        .iter()
+        .filter(|meta| meta.signer && !meta.writable)
+        .count();
+    let readonly_others = accounts
+        .iter()
+        .filter(|meta| !meta.signer && !meta.writable)
+        .count();
+    let index = |pubkey: &SolanaPubkey| {
+        accounts
+            .iter()
+            .position(|meta| meta.pubkey == *pubkey)
+            .expect("every instruction account was added") as u8
+    };
+
+    let mut message = vec![signers as u8, readonly_signers as u8, readonly_others as u8];
+    push_length(&mut message, accounts.len());
+    for meta in &accounts {
+        message.extend_from_slice(&meta.pubkey.to_bytes());
+    }
+    message.extend_from_slice(&recent_blockhash);
+    push_length(&mut message, instructions.len());
+    for instruction in instructions {
+        message.push(index(&instruction.program_id));
+        push_length(&mut message, instruction.accounts.len());
+        message.extend(instruction.accounts.iter().map(|meta| index(&meta.pubkey)));
+        push_length(&mut message, instruction.data.len());
+        message.extend_from_slice(&instruction.data);
+    }
+    message
+}
+
+// Lengths are written as compact-u16: 7 bits a byte, low bits first, with the
+// top bit set on every byte but the last
+fn push_length(out: &mut Vec<u8>, mut len: usize) {
+    loop {
+        let byte = (len & 0x7f) as u8;
+        len >>= 7;
+        if len == 0 {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+/// Wire transaction taken apart again, for checking what was sent
+#[cfg(feature = "testkit")]
+#[derive(Debug, Clone)]
+pub(crate) struct DecodedTransaction {
+    pub(crate) signatures: Vec<[u8; 64]>,
+    /// The signed bytes
+    pub(crate) message: Vec<u8>,
+    pub(crate) account_keys: Vec<SolanaPubkey>,
+    pub(crate) recent_blockhash: [u8; 32],
+    pub(crate) instructions: Vec<Instruction>,
+}
+
+#[cfg(feature = "testkit")]
+pub(crate) fn decode(bytes: &[u8]) -> anyhow::Result<DecodedTransaction> {
+    use anyhow::{anyhow, bail};
+
+    let mut reader = Reader { bytes, at: 0 };
+    let signatures = (0..reader.length()?)
+        .map(|_| Ok(reader.take(64)?.try_into()?))
+        .collect::<anyhow::Result<Vec<[u8; 64]>>>()?;
+    let message = reader.bytes[reader.at..].to_vec();
+    let [signers, readonly_signers, readonly_others] = reader.array()?;
+    let account_keys = (0..reader.length()?)
+        .map(|_| Ok(SolanaPubkey::new(reader.array()?)))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let recent_blockhash = reader.array()?;
+    let key = |index: u8| {
+        account_keys
+            .get(index as usize)
+            .copied()
+            .ok_or_else(|| anyhow!("account index {} out of range", index))
+    };
+    let writable_signers = usize::from(signers.saturating_sub(readonly_signers));
+    let writable_others = account_keys
+        .len()
+        .saturating_sub(usize::from(readonly_others));
+    let mut instructions = Vec::new();
+    for _ in 0..reader.length()? {
+        let program_id = key(reader.take(1)?[0])?;
+        let mut accounts = Vec::new();
+        for _ in 0..reader.length()? {
+            let index = reader.take(1)?[0];
+            let position = usize::from(index);
+            accounts.push(AccountMeta {
+                pubkey: key(index)?,
+                signer: position < usize::from(signers),
+                writable: position < writable_signers
+                    || (position >= usize::from(signers) && position < writable_others),
+            });
+        }
+        let len = reader.length()?;
+        let data = reader.take(len)?.to_vec();
+        instructions.push(Instruction {
+            program_id,
+            accounts,
+            data,
+        });
+    }
+    if reader.at != bytes.len() {
+        bail!(
+            "{} trailing bytes after the transaction",
+            bytes.len() - reader.at
+        );
+    }
+    Ok(DecodedTransaction {
+        signatures,
+        message,
+        account_keys,
+        recent_blockhash,
+        instructions,
+    })
+}
+
+#[cfg(feature = "testkit")]
+struct Reader<'a> {
+    bytes: &'a [u8],
+    at: usize,
+}
+
+#[cfg(feature = "testkit")]
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> anyhow::Result<&'a [u8]> {
+        let bytes = self
+            .bytes
+            .get(self.at..self.at + len)
+            .ok_or_else(|| anyhow::anyhow!("transaction ends early"))?;
+        self.at += len;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> anyhow::Result<[u8; N]> {
+        Ok(self.take(N)?.try_into()?)
+    }
+
+    fn length(&mut self) -> anyhow::Result<usize> {
+        let mut len = 0;
+        for shift in [0, 7, 14] {
+            let byte = self.take(1)?[0];
+            len |= usize::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(len);
+            }
+        }
+        anyhow::bail!("compact length longer than 3 bytes")
+    }
+}
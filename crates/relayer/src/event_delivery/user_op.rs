@@ -0,0 +1,266 @@
+use anyhow::{anyhow, Context, Result};
+use ethers::{
+    abi::{self, Token},
+    core::types::{
+        transaction::eip2718::TypedTransaction, Address, Bytes, TransactionReceipt,
+        TransactionRequest, H256, U256,
+    },
+    providers::{Http, Middleware, Provider},
+    signers::{LocalWallet, Signer},
+    utils::{hex, id, keccak256},
+};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tokio::time::Instant;
+use tracing::{debug, info};
+
+use super::revert;
+use crate::config::{DeliveryConfig, SmartAccountConfig};
+use crate::types::{ChainId, RelayerError};
+
+const GET_NONCE_SIGNATURE: &str = "getNonce(address,uint192)";
+const EXECUTE_SIGNATURE: &str = "execute(address,uint256,bytes)";
+const RECEIPT_POLL_INTERVAL: Duration = Duration::from_secs(3);
+// Placeholder the bundler estimates verification gas against; the shape of a
+// real ECDSA signature so the account's checks cost the same
+const DUMMY_SIGNATURE: [u8; 65] = {
+    let mut signature = [0xff; 65];
+    signature[64] = 0x1c;
+    signature
+};
+
+/// ERC-4337 (EntryPoint v0.6) user operation as sent to the bundler
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct UserOperation {
+    sender: Address,
+    nonce: U256,
+    init_code: Bytes,
+    call_data: Bytes,
+    call_gas_limit: U256,
+    verification_gas_limit: U256,
+    pre_verification_gas: U256,
+    max_fee_per_gas: U256,
+    max_priority_fee_per_gas: U256,
+    paymaster_and_data: Bytes,
+    signature: Bytes,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GasEstimate {
+    call_gas_limit: U256,
+    verification_gas_limit: U256,
+    pre_verification_gas: U256,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Sponsorship {
+    paymaster_and_data: Bytes,
+    call_gas_limit: U256,
+    verification_gas_limit: U256,
+    pre_verification_gas: U256,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct UserOperationReceipt {
+    success: bool,
+    #[serde(default)]
+    reason: Option<String>,
+    receipt: TransactionReceipt,
+}
+
+/// A user operation that made it on-chain, successfully or not
+pub struct SentUserOperation {
+    /// Receipt of the bundle transaction that included the operation
+    pub receipt: TransactionReceipt,
+    /// Revert reason of the account's call, if it failed
+    pub revert_reason: Option<String>,
+}
+
+impl UserOperation {
+    // Hash the owner signs, per the v0.6 EntryPoint's `getUserOpHash`
+    fn hash(&self, entry_point: Address, chain_id: ChainId) -> H256 {
+        let packed = abi::encode(&[
+            Token::Address(self.sender),
+            Token::Uint(self.nonce),
+            Token::FixedBytes(keccak256(&self.init_code).to_vec()),
+            Token::FixedBytes(keccak256(&self.call_data).to_vec()),
+            Token::Uint(self.call_gas_limit),
+            Token::Uint(self.verification_gas_limit),
+            Token::Uint(self.pre_verification_gas),
+            Token::Uint(self.max_fee_per_gas),
+            Token::Uint(self.max_priority_fee_per_gas),
+            Token::FixedBytes(keccak256(&self.paymaster_and_data).to_vec()),
+        ]);
+        H256(keccak256(abi::encode(&[
+            Token::FixedBytes(keccak256(packed).to_vec()),
+            Token::Address(entry_point),
+            Token::Uint(chain_id.into()),
+        ])))
+    }
+}
+
+// Fee fields of a built delivery transaction, as a user operation prices them
+fn fees(tx: &TypedTransaction) -> (U256, U256) {
+    match tx {
+        TypedTransaction::Eip1559(inner) => (
+            inner.max_fee_per_gas.unwrap_or_default(),
+            inner.max_priority_fee_per_gas.unwrap_or_default(),
+        ),
+        _ => {
+            let gas_price = tx.gas_price().unwrap_or_default();
+            (gas_price, gas_price)
+        }
+    }
+}
+
+// EntryPoint nonce key for a call, the low 192 bits of its hash. Resending
+// the same call reuses the key and so picks up where its last attempt left off
+fn nonce_key(call_data: &[u8]) -> U256 {
+    U256::from_big_endian(&keccak256(call_data)[8..])
+}
+
+fn scale(gas: U256, multiplier: f64) -> U256 {
+    gas * U256::from((multiplier * 1_000.0) as u64) / 1_000
+}
+
+/// Deliver through the relayer's smart account as a user operation
+///
+/// The signer owns the account and signs the operation; the bundler pays for
+/// and submits the bundle transaction. `tx` supplies the destination call and
+/// the fees to offer. With `sponsored` set, the account's paymaster service
+/// covers the gas and sets the gas limits; otherwise the account pays.
+/// Returns once the bundle is mined or `stuck_timeout_ms` has passed without
+/// it.
+///
+/// Deliveries through the same account run concurrently, so each takes its
+/// nonce from its own key, derived from the destination call, rather than
+/// racing for the next nonce of a shared sequence.
+pub async fn send<M: Middleware>(
+    client: &M,
+    signer: &LocalWallet,
+    chain_id: ChainId,
+    account: &SmartAccountConfig,
+    tx: &TypedTransaction,
+    config: &DeliveryConfig,
+    sponsored: bool,
+) -> Result<SentUserOperation>
+where
+    M::Error: 'static,
+{
+    let sender = account.address;
+    let entry_point = account.entry_point;
+    let bundler = Provider::<Http>::try_from(account.bundler_url.as_str())
+        .context("Failed to create bundler provider")?;
+
+    let to = tx.to_addr().copied().unwrap_or_default();
+    let data = tx.data().cloned().unwrap_or_default();
+    let call_data = abi::encode(&[
+        Token::Address(to),
+        Token::Uint(U256::zero()),
+        Token::Bytes(data.to_vec()),
+    ]);
+    let mut call_data_with_selector = id(EXECUTE_SIGNATURE).to_vec();
+    call_data_with_selector.extend(call_data);
+
+    let mut nonce_call = id(GET_NONCE_SIGNATURE).to_vec();
+    nonce_call.extend(abi::encode(&[
+        Token::Address(sender),
+        Token::Uint(nonce_key(&call_data_with_selector)),
+    ]));
+    let nonce_call: TypedTransaction = TransactionRequest::new()
+        .to(entry_point)
+        .data(nonce_call)
+        .into();
+    let nonce = client
+        .call(&nonce_call, None)
+        .await
+        .context("Failed to read smart account nonce")?;
+    let nonce = U256::from_big_endian(&nonce);
+
+    let (max_fee_per_gas, max_priority_fee_per_gas) = fees(tx);
+    let mut op = UserOperation {
+        sender,
+        nonce,
+        init_code: Bytes::new(),
+        call_data: call_data_with_selector.into(),
+        call_gas_limit: U256::zero(),
+        verification_gas_limit: U256::zero(),
+        pre_verification_gas: U256::zero(),
+        max_fee_per_gas,
+        max_priority_fee_per_gas,
+        paymaster_and_data: Bytes::new(),
+        signature: Bytes::from(DUMMY_SIGNATURE.to_vec()),
+    };
+
+    if sponsored {
+        let url = account
+            .paymaster_url
+            .as_deref()
+            .ok_or_else(|| anyhow!("Sponsored delivery but no paymaster is configured"))?;
+        let paymaster =
+            Provider::<Http>::try_from(url).context("Failed to create paymaster provider")?;
+        let sponsorship: Sponsorship = paymaster
+            .request("pm_sponsorUserOperation", (&op, entry_point))
+            .await
+            .context("Paymaster declined to sponsor user operation")?;
+        // The paymaster signed over these limits, so they are used unpadded
+        op.paymaster_and_data = sponsorship.paymaster_and_data;
+        op.call_gas_limit = sponsorship.call_gas_limit;
+        op.verification_gas_limit = sponsorship.verification_gas_limit;
+        op.pre_verification_gas = sponsorship.pre_verification_gas;
+    } else {
+        let estimate: GasEstimate = bundler
+            .request("eth_estimateUserOperationGas", (&op, entry_point))
+            .await
+            .context("Bundler failed to estimate user operation gas")?;
+        op.call_gas_limit = scale(estimate.call_gas_limit, config.gas_multiplier);
+        op.verification_gas_limit = estimate.verification_gas_limit;
+        op.pre_verification_gas = estimate.pre_verification_gas;
+    }
+
+    let hash = op.hash(entry_point, chain_id);
+    op.signature = signer
+        .sign_message(hash.as_bytes())
+        .await
+        .map_err(|e| RelayerError::Signing { source: e.into() })?
+        .to_vec()
+        .into();
+
+    let op_hash: H256 = bundler
+        .request("eth_sendUserOperation", (&op, entry_point))
+        .await
+        .context("Bundler rejected user operation")?;
+    info!(user_op_hash = ?op_hash, nonce = %op.nonce, sponsored, "User operation sent");
+
+    let deadline = Instant::now() + Duration::from_millis(config.stuck_timeout_ms);
+    loop {
+        let receipt: Option<UserOperationReceipt> = bundler
+            .request("eth_getUserOperationReceipt", [op_hash])
+            .await?;
+        if let Some(receipt) = receipt {
+            debug!(tx_hash = ?receipt.receipt.transaction_hash, "User operation included");
+            let revert_reason = (!receipt.success).then(|| {
+                let data = receipt
+                    .reason
+                    .and_then(|reason| hex::decode(reason.trim_start_matches("0x")).ok())
+                    .unwrap_or_default();
+                revert::decode_revert(&data)
+            });
+            return Ok(SentUserOperation {
+                receipt: receipt.receipt,
+                revert_reason,
+            });
+        }
+        if Instant::now() >= deadline {
+            return Err(anyhow!(
+                "User operation {:?} not included within the stuck timeout",
+                op_hash
+            ));
+        }
+        tokio::time::sleep(RECEIPT_POLL_INTERVAL).await;
+    }
+}
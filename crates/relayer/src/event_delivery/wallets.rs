@@ -0,0 +1,110 @@
+use anyhow::{Context, Result};
+use ethers::{
+    core::types::Address,
+    signers::{LocalWallet, Signer},
+};
+use std::{
+    collections::HashMap,
+    str::FromStr,
+    sync::{Arc, Mutex},
+};
+
+use super::balance::BalanceMonitor;
+use crate::config::ChainConfig;
+use crate::types::{ChainId, RelayerError};
+
+type InFlight = Arc<Mutex<HashMap<(ChainId, Address), usize>>>;
+
+/// Delivery wallets for every chain: the main signer plus the chain's extra
+/// `wallet_keys`, each with its own nonce sequence
+pub struct WalletPool {
+    main: LocalWallet,
+    wallets: HashMap<ChainId, Vec<LocalWallet>>,
+    in_flight: InFlight,
+}
+
+/// A wallet's claim on one delivery, released when dropped
+pub struct WalletLease {
+    in_flight: InFlight,
+    key: (ChainId, Address),
+}
+
+impl WalletPool {
+    pub fn new(private_key: &str, chains: &[ChainConfig]) -> Result<Self> {
+        let main = LocalWallet::from_str(private_key).context("Failed to create wallet")?;
+        let mut wallets = HashMap::new();
+        for chain in chains {
+            let mut pool = vec![main.clone().with_chain_id(chain.chain_id)];
+            for key in &chain.wallet_keys {
+                let wallet = LocalWallet::from_str(key)
+                    .context(format!("Invalid delivery wallet key for {}", chain.name))?;
+                pool.push(wallet.with_chain_id(chain.chain_id));
+            }
+            wallets.insert(chain.chain_id, pool);
+        }
+        Ok(Self {
+            main,
+            wallets,
+            in_flight: InFlight::default(),
+        })
+    }
+
+    /// Every wallet address that may deliver on `chain_id`
+    pub fn addresses(&self, chain_id: ChainId) -> Vec<Address> {
+        self.wallets
+            .get(&chain_id)
+            .map(|pool| pool.iter().map(Signer::address).collect())
+            .unwrap_or_default()
+    }
+
+    /// Take the funded wallet with the fewest deliveries in flight on `chain`
+    pub fn acquire(
+        &self,
+        chain: &ChainConfig,
+        balances: &BalanceMonitor,
+    ) -> Result<(LocalWallet, WalletLease)> {
+        // Chains added at runtime deliver from the main signer alone
+        let fallback;
+        let pool = match self.wallets.get(&chain.chain_id) {
+            Some(pool) => pool.as_slice(),
+            None => {
+                fallback = [self.main.clone().with_chain_id(chain.chain_id)];
+                &fallback[..]
+            }
+        };
+
+        let mut in_flight = self.in_flight.lock().expect("wallet pool lock poisoned");
+        let wallet = pool
+            .iter()
+            .filter(|wallet| !balances.is_low(chain.chain_id, wallet.address()))
+            .min_by_key(|wallet| {
+                in_flight
+                    .get(&(chain.chain_id, wallet.address()))
+                    .copied()
+                    .unwrap_or(0)
+            })
+            .ok_or(RelayerError::InsufficientBalance {
+                chain_id: chain.chain_id,
+            })?;
+
+        let key = (chain.chain_id, wallet.address());
+        *in_flight.entry(key).or_default() += 1;
+        Ok((
+            wallet.clone(),
+            WalletLease {
+                in_flight: self.in_flight.clone(),
+                key,
+            },
+        ))
+    }
+}
+
+impl Drop for WalletLease {
+    fn drop(&mut self) {
+        if let Ok(mut in_flight) = self.in_flight.lock() {
+            if let Some(count) = in_flight.get_mut(&self.key) {
+                *count = count.saturating_sub(1);
+            }
+        }
+    }
+}
@@ -0,0 +1,168 @@
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use ethers::{core::types::H256, utils::hex};
+use hmac::{Hmac, Mac};
+use reqwest::StatusCode;
+use serde::Deserialize;
+use sha2::Sha256;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::{info, instrument, warn};
+
+use super::sink::DeliverySink;
+use crate::config::WebhookSinkConfig;
+use crate::types::{DeliveryRequest, RelayerError};
+
+// Header carrying the id of the delivered event, stable across retries
+const EVENT_ID_HEADER: &str = "x-relayer-event-id";
+// Header carrying the unix time the body was signed at
+const TIMESTAMP_HEADER: &str = "x-relayer-timestamp";
+// Header carrying `sha256=` and the hex HMAC of `{timestamp}.{body}`
+const SIGNATURE_HEADER: &str = "x-relayer-signature";
+
+// What an endpoint may answer a delivery with
+#[derive(Deserialize)]
+struct Receipt {
+    tx_hash: Option<H256>,
+}
+
+/// Delivers proven events by POSTing them to an endpoint that submits them
+/// itself
+///
+/// The body is the schema-tagged [`DeliveryRequest`] as JSON, signed with the
+/// configured secret so the endpoint can check it came from the relayer. A
+/// 2xx answer delivers the event, with the transaction the endpoint sent if
+/// its body names one as `tx_hash`; 409 means the event was delivered
+/// already. Timeouts, 408, 429 and 5xx are retried with backoff, and any other
+/// answer rejects the delivery for good.
+pub struct WebhookSink {
+    http: reqwest::Client,
+    config: WebhookSinkConfig,
+}
+
+impl WebhookSink {
+    pub fn new(config: WebhookSinkConfig) -> Result<Self> {
+        validate(&config)?;
+        let http = reqwest::Client::builder()
+            .timeout(Duration::from_millis(config.request_timeout_ms))
+            .build()?;
+        Ok(Self { http, config })
+    }
+
+    /// Signature of `body` signed at `timestamp` with `secret`, as sent in
+    /// the signature header
+    pub fn signature(secret: &str, timestamp: u64, body: &[u8]) -> String {
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC takes any key length");
+        mac.update(format!("{}.", timestamp).as_bytes());
+        mac.update(body);
+        format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+    }
+
+    // One POST of the delivery, signed anew so the timestamp stays fresh
+    async fn post(&self, request: &DeliveryRequest, body: &[u8]) -> Result<Option<H256>, Failure> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let response = self
+            .http
+            .post(&self.config.url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(EVENT_ID_HEADER, request.event.id().to_string())
+            .header(TIMESTAMP_HEADER, timestamp)
+            .header(
+                SIGNATURE_HEADER,
+                Self::signature(&self.config.secret, timestamp, body),
+            )
+            .body(body.to_vec())
+            .send()
+            .await
+            .map_err(|e| Failure::Transient(e.into()))?;
+
+        let status = response.status();
+        if status.is_success() {
+            let receipt = response.json::<Receipt>().await.ok();
+            return Ok(receipt.and_then(|receipt| receipt.tx_hash));
+        }
+        let message = response.text().await.unwrap_or_default();
+        match status {
+            StatusCode::CONFLICT => Err(Failure::Fatal(
+                RelayerError::AlreadyExecuted {
+                    chain_id: request.destination_chain_id,
+                    nonce: request.event.nonce,
+                }
+                .into(),
+            )),
+            StatusCode::REQUEST_TIMEOUT | StatusCode::TOO_MANY_REQUESTS => {
+                Err(Failure::Transient(anyhow!("{}: {}", status, message)))
+            }
+            _ if status.is_server_error() => {
+                Err(Failure::Transient(anyhow!("{}: {}", status, message)))
+            }
+            _ => Err(Failure::Fatal(
+                RelayerError::WebhookRejected {
+                    status: status.as_u16(),
+                    message,
+                }
+                .into(),
+            )),
+        }
+    }
+}
+
+// Whether another POST of a failed delivery may succeed
+enum Failure {
+    Transient(anyhow::Error),
+    Fatal(anyhow::Error),
+}
+
+#[async_trait]
+impl DeliverySink for WebhookSink {
+    #[instrument(skip_all, fields(nonce = request.event.nonce))]
+    async fn deliver(&self, request: &DeliveryRequest) -> Result<Option<H256>> {
+        let body = serde_json::to_vec(request)?;
+        let mut backoff = Duration::from_millis(self.config.initial_backoff_ms);
+        let mut attempt = 1;
+        loop {
+            match self.post(request, &body).await {
+                Ok(tx_hash) => {
+                    info!(attempt, ?tx_hash, "Webhook accepted delivery");
+                    return Ok(tx_hash);
+                }
+                Err(Failure::Fatal(e)) => return Err(e),
+                Err(Failure::Transient(e)) if attempt >= self.config.max_attempts => {
+                    return Err(RelayerError::WebhookUnavailable {
+                        attempts: attempt,
+                        source: e,
+                    }
+                    .into());
+                }
+                Err(Failure::Transient(e)) => {
+                    warn!(attempt, error = %e, "Webhook delivery failed, retrying");
+                    tokio::time::sleep(backoff).await;
+                    backoff *= 2;
+                    attempt += 1;
+                }
+            }
+        }
+    }
+}
+
+fn validate(config: &WebhookSinkConfig) -> Result<(), RelayerError> {
+    let scheme = config.url.split_once("://").map(|(scheme, _)| scheme);
+    let problem = if config.url.is_empty() {
+        "a url is required"
+    } else if !(scheme == Some("https") || config.allow_http && scheme == Some("http")) {
+        "url must be https, or http with allow_http set"
+    } else if config.secret.is_empty() {
+        "a secret is required"
+    } else if config.max_attempts == 0 {
+        "max_attempts must be at least 1"
+    } else {
+        return Ok(());
+    };
+    Err(RelayerError::InvalidConfig(format!(
+        "Webhook sink: {}",
+        problem
+    )))
+}
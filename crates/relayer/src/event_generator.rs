@@ -1,65 +1,144 @@
-use crate::config::RelayPair;
-use crate::types::{ChainConfig, EventMeta, RelayEvent};
-use anyhow::anyhow;
+use crate::accounting::{record_gas_cost, GasCostKind};
+use crate::clock::{Clock, SystemClock, Ticker};
+use crate::config::{ProfitabilityConfig, RelayMode, RelayPair, UnprofitableRelays};
+use crate::decode;
+use crate::event_source::{EventEmitter, EventSource};
+use crate::failure::{self, FailureClass};
+use crate::health::{Health, EVENT_GENERATOR};
+use crate::journal::Journal;
+use crate::metrics::{PairLabels, GENERATOR_POLLS_SKIPPED, RELAY_DECISIONS};
+use crate::profitability;
+use crate::store::StateStore;
+use crate::telemetry;
+use crate::topology::Topology;
+use crate::types::{ChainConfig, RelayEvent};
 use anyhow::{Context, Result};
+use async_trait::async_trait;
 use ethers::{
     abi::{self},
-    core::types::{Address, Bytes, H256, U256},
+    core::types::{Bytes, H256, U256},
     prelude::*,
     providers::{Http, Provider},
     signers::{LocalWallet, Signer},
-    utils::keccak256,
 };
-use std::collections::HashMap;
-use std::{str::FromStr, sync::Arc, time::Duration};
-use tokio::{sync::mpsc, time};
-use tracing::{debug, error, info, instrument};
+use std::{
+    collections::HashSet,
+    str::FromStr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error, info, info_span, instrument, warn, Instrument};
 
 pub struct EventGenerator {
-    chains: HashMap<u64, ChainConfig>,
-    relay_pairs: Vec<RelayPair>,
+    topology: Topology,
     private_key: String,
     polling_interval: Duration,
     event_tx: mpsc::Sender<RelayEvent>,
+    store: Arc<dyn StateStore>,
+    health: Health,
+    /// Queue fill level above which polls are skipped
+    slow_down_threshold: f64,
+    journal: Journal,
+    clock: Arc<dyn Clock>,
+    profitability: ProfitabilityConfig,
+    /// Pair IDs and nonces of requests skipped as unprofitable
+    skipped: Mutex<HashSet<(String, u64)>>,
 }
 
 impl EventGenerator {
     pub fn new(
-        chains: HashMap<u64, ChainConfig>,
-        relay_pairs: Vec<RelayPair>,
+        topology: Topology,
         private_key: String,
         polling_interval: Duration,
         event_tx: mpsc::Sender<RelayEvent>,
+        store: Arc<dyn StateStore>,
+        health: Health,
+        slow_down_threshold: f64,
     ) -> Self {
         Self {
-            chains,
+            store,
+            health,
+            slow_down_threshold,
+            topology,
             private_key,
             polling_interval,
             event_tx,
-            relay_pairs,
+            journal: Journal::default(),
+            clock: Arc::new(SystemClock),
+            profitability: ProfitabilityConfig::default(),
+            skipped: Mutex::default(),
         }
     }
 
-    #[instrument(skip(self), name = "event_generator_start")]
-    pub async fn start(&self) -> Result<()> {
+    pub(crate) fn with_journal(mut self, journal: Journal) -> Self {
+        self.journal = journal;
+        self
+    }
+
+    pub(crate) fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub(crate) fn with_profitability(mut self, profitability: ProfitabilityConfig) -> Self {
+        self.profitability = profitability;
+        self
+    }
+
+    /// Poll for new events until `shutdown` is cancelled
+    ///
+    /// A poll already underway is finished first, so no trigger transaction is
+    /// left sent but unrecorded. Polls are skipped while a downstream queue is
+    /// filled past the slow-down threshold.
+    pub async fn start(&self, shutdown: CancellationToken) -> Result<()> {
+        let emitter = EventEmitter::new(
+            self.event_tx.clone(),
+            self.health.clone(),
+            self.journal.clone(),
+            self.store.clone(),
+        );
+        self.poll(&emitter, shutdown).await
+    }
+
+    #[instrument(skip_all, name = "event_generator_start")]
+    async fn poll(&self, emitter: &EventEmitter, shutdown: CancellationToken) -> Result<()> {
         info!("Starting event generator");
 
-        let mut interval_timer = time::interval(self.polling_interval);
+        let mut interval_timer = Ticker::new(self.clock.clone(), self.polling_interval);
 
         loop {
-            interval_timer.tick().await;
-            if let Err(e) = self.check_all_chains().await {
+            tokio::select! {
+                _ = interval_timer.tick() => self.health.beat(EVENT_GENERATOR),
+                _ = shutdown.cancelled() => {
+                    info!("Event generator stopped");
+                    return Ok(());
+                }
+            }
+            let saturation = self.health.saturation();
+            if saturation >= self.slow_down_threshold {
+                warn!(saturation, "Downstream queues saturated, skipping poll");
+                GENERATOR_POLLS_SKIPPED.inc();
+                continue;
+            }
+            if let Err(e) = self.check_all_chains(emitter).await {
                 error!(error = %e, "Error checking chains");
             }
         }
     }
 
-    #[instrument(skip(self))]
-    async fn check_all_chains(&self) -> Result<()> {
-        for relay_pair in &self.relay_pairs {
-            let source_chain = self
-                .chains
-                .get(&relay_pair.source_chain_id)
+    #[instrument(skip_all)]
+    async fn check_all_chains(&self, emitter: &EventEmitter) -> Result<()> {
+        // Read the pairs afresh each poll to pick up changes made at runtime;
+        // intent pairs have no resolver to poll, the intent source finds their
+        // orders instead
+        let mut relay_pairs = self.topology.enabled_pairs();
+        relay_pairs.retain(|pair| pair.relay_mode != RelayMode::Erc7683);
+        for relay_pair in &relay_pairs {
+            let source_chain = &self
+                .topology
+                .chain(relay_pair.source_chain_id)
                 .ok_or_else(|| {
                     anyhow::anyhow!(
                         "Source chain {} not found in config",
@@ -67,7 +146,7 @@ impl EventGenerator {
                     )
                 })?;
 
-            let dest_chain = self.chains.get(&relay_pair.dest_chain_id).ok_or_else(|| {
+            let dest_chain = &self.topology.chain(relay_pair.dest_chain_id).ok_or_else(|| {
                 anyhow::anyhow!(
                     "Destination chain {} not found in config",
                     relay_pair.dest_chain_id
@@ -75,24 +154,29 @@ impl EventGenerator {
             })?;
 
             match self
-                .check_cross_chain_events(source_chain, dest_chain, relay_pair)
+                .check_cross_chain_events(emitter, source_chain, dest_chain, relay_pair)
                 .await
             {
                 Ok(_) => {}
-                Err(e) => error!(
-                    source_chain = %source_chain.name,
-                    dest_chain = %dest_chain.name,
-                    error = %e,
-                    "Error checking cross-chain events"
-                ),
+                Err(e) => {
+                    let e = failure::attribute(source_chain.chain_id, e);
+                    error!(
+                        source_chain = %source_chain.name,
+                        dest_chain = %dest_chain.name,
+                        error = %e,
+                        retryable = FailureClass::of(&e) == FailureClass::Retryable,
+                        "Error checking cross-chain events"
+                    )
+                }
             }
         }
         Ok(())
     }
 
-    #[instrument(skip(self), fields(source_chain = %source_chain.name, dest_chain = %dest_chain.name))]
+    #[instrument(skip(self, emitter), fields(source_chain = %source_chain.name, dest_chain = %dest_chain.name))]
     async fn check_cross_chain_events(
         &self,
+        emitter: &EventEmitter,
         source_chain: &ChainConfig,
         dest_chain: &ChainConfig,
         relay_pair: &RelayPair,
@@ -113,8 +197,7 @@ impl EventGenerator {
         let client = SignerMiddleware::new(client, wallet);
 
         // Create resolver contract interface
-        let resolver_address = Address::from_str(&relay_pair.source_resolver_address)
-            .context("Invalid resolver address")?;
+        let resolver_address = relay_pair.source_resolver_address;
 
         // Create ABI for the cross-chain resolver interface
         let resolver_abi = abi::parse_abi(&[
@@ -126,15 +209,25 @@ impl EventGenerator {
         debug!("Calling crossChainChecker() on resolver");
 
         // Call the crossChainChecker function
-        let dest_chain_id_u32 = dest_chain.chain_id as u32;
         let result: (bool, Bytes, U256) = resolver_contract
-            .method("crossChainChecker", dest_chain_id_u32)?
+            .method("crossChainChecker", dest_chain.chain_id.as_u32()?)?
             .call()
             .await?;
 
         let (can_exec, exec_payload, nonce) = result;
 
-        if can_exec {
+        if can_exec && self.already_delivered(relay_pair, nonce.as_u64()) {
+            info!(
+                nonce = nonce.as_u64(),
+                "Nonce already delivered according to the ledger, not triggering"
+            );
+        } else if can_exec
+            && !self
+                .worth_relaying(&client, dest_chain, relay_pair, nonce.as_u64())
+                .await
+        {
+            // Left pending, or skipped for good
+        } else if can_exec {
             info!(
                 nonce = nonce.as_u64(),
                 source_chain = source_chain.name,
@@ -142,14 +235,27 @@ impl EventGenerator {
                 "✅ Cross-chain execution needed"
             );
 
-            // Process the cross-chain event
-            let tx_hash = self
-                .request_remote_execution(&source_chain, relay_pair)
-                .await?;
-
-            // Extract event details and create the RelayEvent
-            let event = self
-                .extract_event_details(
+            // Every stage this event goes through joins the trace started here
+            let span = info_span!(
+                parent: None,
+                "relay_event",
+                stage = "detect",
+                event_id = tracing::field::Empty,
+                chain_id = source_chain.chain_id.as_u64(),
+                pair = %relay_pair.id(),
+                nonce = nonce.as_u64(),
+                tx_hash = tracing::field::Empty,
+                source_chain = %source_chain.name,
+                dest_chain = %dest_chain.name
+            );
+            let mut event = async {
+                // Process the cross-chain event
+                let tx_hash = self
+                    .request_remote_execution(source_chain, relay_pair)
+                    .await?;
+
+                // Extract event details and create the RelayEvent
+                self.extract_event_details(
                     tx_hash,
                     source_chain,
                     dest_chain,
@@ -157,11 +263,20 @@ impl EventGenerator {
                     nonce.as_u64(),
                     relay_pair,
                 )
-                .await?;
+                .await
+            }
+            .instrument(span.clone())
+            .await?;
+            event.trace_context = telemetry::inject(&span);
+            span.record("event_id", tracing::field::display(event.id()));
+            if let Some(tx_hash) = event.meta.tx_hash {
+                span.record("tx_hash", tracing::field::debug(tx_hash));
+            }
 
             // Send the event to the proof fetcher
-            if let Err(e) = self.event_tx.send(event).await {
-                error!(error = %e, "Failed to send event to proof fetcher");
+            let event_id = event.id();
+            if let Err(e) = emitter.emit(event).await {
+                error!(error = %e, %event_id, "Failed to send event to proof fetcher");
             }
         } else {
             debug!("⏳ No cross-chain execution needed");
@@ -170,6 +285,85 @@ impl EventGenerator {
         Ok(())
     }
 
+    // Whether the delivery ledger has this nonce of the pair delivered already
+    fn already_delivered(&self, relay_pair: &RelayPair, nonce: u64) -> bool {
+        match self.store.ledger_entry(&relay_pair.id(), nonce) {
+            Ok(entry) => entry.is_some_and(|entry| entry.delivered),
+            Err(e) => {
+                warn!(error = %e, "Failed to check delivery ledger");
+                false
+            }
+        }
+    }
+
+    // Whether the reward for the pending request of a rewarding pair covers
+    // relaying it; requests of other pairs are always worth it
+    async fn worth_relaying<M: Middleware>(
+        &self,
+        client: &M,
+        dest_chain: &ChainConfig,
+        relay_pair: &RelayPair,
+        nonce: u64,
+    ) -> bool
+    where
+        M::Error: 'static,
+    {
+        let Some(reward_function) = &relay_pair.reward_function else {
+            return true;
+        };
+        if relay_pair.always_relay {
+            return true;
+        }
+        let key = (relay_pair.id(), nonce);
+        if self.skipped().contains(&key) {
+            debug!(nonce, "Request was skipped as unprofitable, not triggering");
+            return false;
+        }
+
+        let estimate = match profitability::estimate(
+            client,
+            dest_chain,
+            relay_pair,
+            reward_function,
+            &self.profitability,
+            &*self.store,
+        )
+        .await
+        {
+            Ok(estimate) => estimate,
+            Err(e) => {
+                warn!(error = %e, nonce, "Failed to price request, trying again next poll");
+                return false;
+            }
+        };
+        let decision = match (estimate.profitable(), self.profitability.unprofitable) {
+            (true, _) => "relayed",
+            (false, UnprofitableRelays::Queue) => "queued",
+            (false, UnprofitableRelays::Skip) => {
+                self.skipped().insert(key);
+                "skipped"
+            }
+        };
+        info!(
+            nonce,
+            reward = %estimate.reward,
+            cost = %estimate.cost(),
+            trigger_cost = %estimate.trigger,
+            proof_cost = %estimate.proof,
+            delivery_cost = %estimate.delivery,
+            decision,
+            "Priced request"
+        );
+        RELAY_DECISIONS
+            .with_label_values(&PairLabels::of_pair(relay_pair).and(&[decision]))
+            .inc();
+        estimate.profitable()
+    }
+
+    fn skipped(&self) -> std::sync::MutexGuard<'_, HashSet<(String, u64)>> {
+        self.skipped.lock().expect("skipped requests lock poisoned")
+    }
+
     #[instrument(skip(self), fields(source_chain = %source_chain.name, dest_chain = %destination_chain.name))]
     async fn extract_event_details(
         &self,
@@ -190,53 +384,38 @@ impl EventGenerator {
             .await?
             .ok_or_else(|| anyhow::anyhow!("Transaction receipt not found"))?;
 
-        // Find the CrossChainExecRequested event in the logs
-        let cross_chain_event = tx_receipt
-            .logs
-            .iter()
-            .find(|log| {
-                // Check if this log is from our source resolver address
-                let from_resolver = log.address
-                    == Address::from_str(&relay_pair.source_resolver_address).unwrap_or_default();
-
-                // Check if the log has the CrossChainExecRequested event signature
-                // Event: CrossChainExecRequested(uint32 indexed destinationChainId, bytes execPayload, uint256 indexed nonce)
-                // Keccak256 hash of the event signature
-                let event_signature = "CrossChainExecRequested(uint32,bytes,uint256)";
-                let event_signature_hash = keccak256(event_signature.as_bytes());
-
-                from_resolver
-                    && log
-                        .topics
-                        .get(0)
-                        .map_or(false, |t| t.as_bytes() == &event_signature_hash[..])
-            })
-            .ok_or_else(|| {
-                anyhow::anyhow!("CrossChainExecRequested event not found in transaction")
-            })?;
+        // Natively relayed pairs are located by the message the resolver sent
+        // through the interop messenger rather than by its request log
+        let meta = match relay_pair.relay_mode {
+            RelayMode::Polymer => decode::exec_request_meta(
+                source_chain.chain_id,
+                &tx_receipt,
+                relay_pair.source_resolver_address,
+            )?,
+            RelayMode::OpInterop => decode::sent_message_meta(
+                source_chain.chain_id,
+                &tx_receipt,
+                relay_pair.source_resolver_address,
+                relay_pair.dest_chain_id,
+                relay_pair.dest_dapp_address,
+            )?,
+            RelayMode::Erc7683 => {
+                anyhow::bail!("Intent pairs are picked up by the intent source")
+            }
+        };
 
         // Create a relay event with actual transaction details
         let event = RelayEvent {
             source_chain: source_chain.clone(),
-            source_resolver_address: relay_pair.source_resolver_address.clone(),
+            source_resolver_address: relay_pair.source_resolver_address,
             destination_chain: destination_chain.clone(),
-            dest_dapp_address: relay_pair.dest_dapp_address.clone(),
+            dest_dapp_address: relay_pair.dest_dapp_address,
             exec_payload,
             nonce,
-            meta: EventMeta {
-                tx_hash: Some(tx_hash),
-                block_number: tx_receipt
-                    .block_number
-                    .map(|n| n.as_u64())
-                    .ok_or(anyhow!("block_number not found from receipt"))?,
-                tx_index: tx_receipt.transaction_index.as_u32(),
-                log_index: cross_chain_event
-                    .log_index
-                    .map(|n| n.as_u32())
-                    .ok_or(anyhow!(
-                        "log_index not found from CrossChainExecRequested event"
-                    ))?,
-            },
+            detected_at: self.clock.unix_time(),
+            trace_context: Default::default(),
+            priority: relay_pair.priority,
+            meta,
         };
 
         Ok(event)
@@ -263,8 +442,7 @@ impl EventGenerator {
         let client = SignerMiddleware::new(client, wallet);
 
         // Create resolver contract interface
-        let resolver_address = Address::from_str(&relay_pair.source_resolver_address)
-            .context("Invalid resolver address")?;
+        let resolver_address = relay_pair.source_resolver_address;
 
         // Create ABI for the cross-chain resolver interface
         let resolver_abi = abi::parse_abi(&[
@@ -276,7 +454,7 @@ impl EventGenerator {
         // Call requestRemoteExecution
         info!("Calling requestRemoteExecution on resolver");
         let tx_req = resolver_contract
-            .method::<_, ()>("requestRemoteExecution", relay_pair.dest_chain_id)?;
+            .method::<_, ()>("requestRemoteExecution", relay_pair.dest_chain_id.as_u32()?)?;
         let tx = tx_req.send().await?;
 
         let tx_hash = tx.tx_hash();
@@ -288,7 +466,30 @@ impl EventGenerator {
             .ok_or_else(|| anyhow::anyhow!("Transaction receipt not found"))?;
 
         info!(?receipt, "Transaction confirmed");
+        record_gas_cost(
+            &*self.store,
+            source_chain,
+            &relay_pair.id(),
+            GasCostKind::Trigger,
+            &receipt,
+        );
 
         Ok(tx_hash)
     }
 }
+
+/// The generator as an [`EventSource`], for pipelines that run it next to
+/// sources of their own or on a different event channel
+///
+/// Events go to the emitter passed to `run` rather than the channel given to
+/// [`EventGenerator::new`].
+#[async_trait]
+impl EventSource for EventGenerator {
+    fn name(&self) -> &str {
+        "chain"
+    }
+
+    async fn run(&self, emitter: EventEmitter, shutdown: CancellationToken) -> Result<()> {
+        self.poll(&emitter, shutdown).await
+    }
+}
@@ -0,0 +1,69 @@
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+
+use crate::audit::{self, AuditAction};
+use crate::backpressure;
+use crate::health::{Health, PROOF_FETCHER};
+use crate::journal::Journal;
+use crate::status::{self, EventStatus};
+use crate::store::StateStore;
+use crate::types::RelayEvent;
+
+/// Producer of relay events, run alongside or in place of the built-in
+/// on-chain event generator
+///
+/// Events are proven and delivered like detected ones, and persisted as
+/// pending once the proof fetcher takes them in. [`EventGenerator`] is the
+/// source a relayer runs unless told otherwise.
+///
+/// [`EventGenerator`]: crate::EventGenerator
+#[async_trait]
+pub trait EventSource: Send + Sync {
+    /// Name the source is reported under when it fails
+    fn name(&self) -> &str;
+
+    /// Emit events until `shutdown` is cancelled
+    ///
+    /// Returning early is fine; the pipeline keeps running for other sources.
+    /// Returning an error restarts the pipeline.
+    async fn run(&self, emitter: EventEmitter, shutdown: CancellationToken) -> Result<()>;
+}
+
+/// Hands events from an [`EventSource`] to the proof fetcher
+#[derive(Clone)]
+pub struct EventEmitter {
+    events: mpsc::Sender<RelayEvent>,
+    health: Health,
+    journal: Journal,
+    store: Arc<dyn StateStore>,
+}
+
+impl EventEmitter {
+    pub(crate) fn new(
+        events: mpsc::Sender<RelayEvent>,
+        health: Health,
+        journal: Journal,
+        store: Arc<dyn StateStore>,
+    ) -> Self {
+        Self {
+            events,
+            health,
+            journal,
+            store,
+        }
+    }
+
+    /// Queue an event for proving, waiting while the queue is full
+    pub async fn emit(&self, event: RelayEvent) -> Result<()> {
+        self.health.event_detected(&event.pair_id());
+        self.journal.detected(&event);
+        audit::record(AuditAction::triggered(&event));
+        status::advance(&*self.store, &event, EventStatus::Detected);
+        backpressure::send(PROOF_FETCHER, &self.events, event)
+            .await
+            .map_err(|_| anyhow!("Pipeline stopped, event was not queued"))
+    }
+}
@@ -0,0 +1,154 @@
+use ethers::{
+    contract::ContractError,
+    middleware::{signer::SignerMiddlewareError, SignerMiddleware},
+    providers::{Http, JsonRpcError, MiddlewareError, Provider, ProviderError},
+    signers::{LocalWallet, WalletError},
+};
+use std::{error::Error as StdError, sync::Arc};
+
+use crate::types::{ChainId, RelayerError};
+
+// JSON-RPC error codes a node answers with while it is overloaded or behind:
+// internal error, resource unavailable and limit exceeded
+const TRANSIENT_RPC_CODES: [i64; 3] = [-32603, -32002, -32005];
+
+// Messages of node-side errors that go away on their own; nonce races clear
+// once the deliverer resyncs its nonces
+const TRANSIENT_RPC_MESSAGES: [&str; 9] = [
+    "header not found",
+    "rate limit",
+    "too many requests",
+    "timeout",
+    "timed out",
+    "busy",
+    "nonce too low",
+    "replacement transaction underpriced",
+    "already known",
+];
+
+/// How a stage should treat a failure
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FailureClass {
+    /// Transient; the same operation may succeed if tried again
+    Retryable,
+    /// Trying again cannot help
+    Fatal,
+    /// Nothing in the error says either way, so the stage's own default applies
+    Unknown,
+}
+
+impl FailureClass {
+    /// Classify a failure by the first [`RelayerError`] in its chain
+    ///
+    /// Ethers failures only carry one once the stage that hit them has
+    /// attributed them; any other error comes out [`FailureClass::Unknown`].
+    pub fn of(error: &anyhow::Error) -> Self {
+        match error
+            .chain()
+            .find_map(|cause| cause.downcast_ref::<RelayerError>())
+        {
+            Some(error) if error.is_retryable() => Self::Retryable,
+            Some(_) => Self::Fatal,
+            None => Self::Unknown,
+        }
+    }
+}
+
+/// Wrap an ethers failure on `chain_id` in the [`RelayerError`] it amounts to,
+/// so stages can tell transient trouble from fatal
+///
+/// Transport trouble and an overloaded node become
+/// [`RelayerError::RpcConnection`]; the node or a contract rejecting the call
+/// becomes [`RelayerError::TransactionFailed`] and a wallet failing to sign
+/// [`RelayerError::Signing`]. Errors already carrying a `RelayerError`, and
+/// ones not from ethers, are returned as they are.
+pub(crate) fn attribute(chain_id: ChainId, error: anyhow::Error) -> anyhow::Error {
+    let mut retryable = None;
+    for cause in error.chain() {
+        if cause.is::<RelayerError>() {
+            return error;
+        }
+        if cause.is::<WalletError>() {
+            return RelayerError::Signing { source: error }.into();
+        }
+        retryable = ethers_retryable(cause);
+        if retryable.is_some() {
+            break;
+        }
+    }
+    match retryable {
+        Some(true) => RelayerError::RpcConnection {
+            chain_id,
+            source: error,
+        }
+        .into(),
+        Some(false) => RelayerError::TransactionFailed {
+            chain_id,
+            source: error,
+        }
+        .into(),
+        None => error,
+    }
+}
+
+// Whether an ethers error is worth retrying, or None if `cause` is not one of
+// the client stacks the relayer builds
+fn ethers_retryable(cause: &(dyn StdError + 'static)) -> Option<bool> {
+    type Signer<M> = SignerMiddleware<M, LocalWallet>;
+    type SignerError<M> = SignerMiddlewareError<M, LocalWallet>;
+
+    if let Some(e) = cause.downcast_ref::<ProviderError>() {
+        return Some(provider_retryable(e));
+    }
+    if let Some(e) = cause.downcast_ref::<SignerError<Provider<Http>>>() {
+        return Some(middleware_retryable(e));
+    }
+    if let Some(e) = cause.downcast_ref::<SignerError<Arc<Provider<Http>>>>() {
+        return Some(middleware_retryable(e));
+    }
+    if let Some(e) = cause.downcast_ref::<ContractError<Signer<Provider<Http>>>>() {
+        return Some(contract_retryable(e));
+    }
+    if let Some(e) = cause.downcast_ref::<ContractError<Provider<Http>>>() {
+        return Some(contract_retryable(e));
+    }
+    if let Some(e) = cause.downcast_ref::<reqwest::Error>() {
+        return Some(e.is_timeout() || e.is_connect() || e.is_request());
+    }
+    None
+}
+
+fn provider_retryable(error: &ProviderError) -> bool {
+    if let Some(response) = error.as_error_response() {
+        return response_retryable(response);
+    }
+    // Anything else from the transport means the node was not reached or
+    // answered with something other than JSON-RPC, e.g. a gateway error page
+    matches!(
+        error,
+        ProviderError::HTTPError(_) | ProviderError::JsonRpcClientError(_)
+    )
+}
+
+// Errors raised by a middleware layer itself, like a missing signer, are fatal
+fn middleware_retryable<E: MiddlewareError>(error: &E) -> bool {
+    error.as_provider_error().is_some_and(provider_retryable)
+}
+
+// A revert or an undecodable result is the contract's answer; only failing
+// to reach the node is worth retrying
+fn contract_retryable<M: ethers::providers::Middleware>(error: &ContractError<M>) -> bool {
+    match error {
+        ContractError::MiddlewareError { e } => middleware_retryable(e),
+        ContractError::ProviderError { e } => provider_retryable(e),
+        _ => false,
+    }
+}
+
+fn response_retryable(response: &JsonRpcError) -> bool {
+    let message = response.message.to_lowercase();
+    TRANSIENT_RPC_CODES.contains(&response.code)
+        || TRANSIENT_RPC_MESSAGES
+            .iter()
+            .any(|transient| message.contains(transient))
+}
@@ -0,0 +1,222 @@
+use anyhow::{anyhow, Context, Result};
+use ethers::{
+    core::types::{Address, Bytes, TransactionRequest, H256, U256},
+    middleware::SignerMiddleware,
+    providers::{Http, Middleware, MiddlewareError, Provider},
+    signers::{LocalWallet, Signer},
+    utils::id,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    str::FromStr,
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tokio::{sync::watch, time};
+use tracing::{debug, info, warn};
+
+use crate::accounting::{record_gas_cost, wei_to_eth, GasCostKind};
+use crate::config::{FeeClaimConfig, FeeContract};
+use crate::event_delivery::{build_transaction, decode_revert, wait_for_receipt, FeeMarkets};
+use crate::metrics::{FEES_CLAIMED, FEE_CLAIMS};
+use crate::store::StateStore;
+use crate::topology::Topology;
+use crate::types::ChainId;
+
+// How long a sent claim may take to be mined
+const RECEIPT_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// A fee claim the relayer sent, as kept in the claim history
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FeeClaimRecord {
+    pub tx_hash: H256,
+    pub chain_id: ChainId,
+    pub contract: Address,
+    pub function: String,
+    /// What the claim's dry run said it would pay out, if its function
+    /// returns an amount
+    pub amount_wei: Option<U256>,
+    /// Whether the claim was mined without reverting
+    pub succeeded: bool,
+    /// Unix time in seconds the claim was mined
+    pub claimed_at: u64,
+}
+
+// What came of one round's claim on a fee contract
+enum Claim {
+    Mined,
+    /// Not sent, as the dry run showed it would revert or pay too little
+    Skipped,
+}
+
+/// Sweeps earned relay fees from every configured fee contract to the
+/// relayer's wallet on a timer, while it leads
+pub(crate) struct FeeClaimer {
+    config: FeeClaimConfig,
+    wallet: LocalWallet,
+    topology: Topology,
+    store: Arc<dyn StateStore>,
+    fee_markets: FeeMarkets,
+}
+
+impl FeeClaimer {
+    pub(crate) fn new(
+        config: FeeClaimConfig,
+        private_key: Option<&str>,
+        topology: Topology,
+        store: Arc<dyn StateStore>,
+    ) -> Result<Self> {
+        // Fees accrue to the address that relayed, so claims come from it too
+        let key = private_key.ok_or_else(|| anyhow!("A private key is needed to claim fees"))?;
+        let wallet = LocalWallet::from_str(key).context("Invalid private key")?;
+        Ok(Self {
+            config,
+            wallet,
+            topology,
+            store,
+            fee_markets: FeeMarkets::default(),
+        })
+    }
+
+    pub(crate) async fn run(self, leader: watch::Receiver<bool>) {
+        let interval = Duration::from_millis(self.config.interval_ms);
+        let mut ticker = time::interval(interval);
+        ticker.set_missed_tick_behavior(time::MissedTickBehavior::Delay);
+        info!(
+            contracts = self.config.contracts.len(),
+            interval_secs = interval.as_secs(),
+            to = ?self.wallet.address(),
+            "Claiming relay fees"
+        );
+        loop {
+            ticker.tick().await;
+            // Only the leader claims, so instances don't race for the same fees
+            if !*leader.borrow() {
+                continue;
+            }
+            for contract in &self.config.contracts {
+                let result = match self.claim(contract).await {
+                    Ok(Claim::Mined) => "mined",
+                    Ok(Claim::Skipped) => "skipped",
+                    Err(e) => {
+                        warn!(
+                            error = %e,
+                            chain_id = %contract.chain_id,
+                            contract = ?contract.address,
+                            function = %contract.function,
+                            "Failed to claim fees"
+                        );
+                        "failed"
+                    }
+                };
+                FEE_CLAIMS
+                    .with_label_values(&[&self.chain_name(contract.chain_id), result])
+                    .inc();
+            }
+        }
+    }
+
+    // Dry-run the contract's claim function and send it if it would pay out
+    // enough, waiting for it to be mined
+    async fn claim(&self, contract: &FeeContract) -> Result<Claim> {
+        let chain = self
+            .topology
+            .chain(contract.chain_id)
+            .ok_or_else(|| anyhow!("Chain {} is not configured", contract.chain_id))?;
+        let provider = Provider::<Http>::try_from(&chain.rpc_url)
+            .context(format!("Failed to create provider for {}", chain.name))?;
+        let wallet = self.wallet.clone().with_chain_id(chain.chain_id);
+        let client = SignerMiddleware::new(provider, wallet);
+        let calldata = Bytes::from(id(&contract.function).to_vec());
+
+        let dry_run = TransactionRequest::new()
+            .from(self.wallet.address())
+            .to(contract.address)
+            .data(calldata.clone());
+        let amount_wei = match client.call(&dry_run.into(), None).await {
+            Ok(returned) => returned.get(..32).map(U256::from_big_endian),
+            Err(e) => match e.as_error_response() {
+                Some(response) if response.is_revert() => {
+                    let reason = response
+                        .as_revert_data()
+                        .map(|data| decode_revert(&data))
+                        .unwrap_or_else(|| response.message.clone());
+                    debug!(
+                        contract = ?contract.address,
+                        chain = %chain.name,
+                        %reason,
+                        "Fee claim would revert, nothing to claim"
+                    );
+                    return Ok(Claim::Skipped);
+                }
+                _ => return Err(e.into()),
+            },
+        };
+        if let Some(amount) = amount_wei {
+            if amount.is_zero() || amount < U256::from(self.config.min_claim_wei) {
+                debug!(
+                    contract = ?contract.address,
+                    chain = %chain.name,
+                    %amount,
+                    min_claim_wei = self.config.min_claim_wei,
+                    "Too little to claim"
+                );
+                return Ok(Claim::Skipped);
+            }
+        }
+
+        let eip1559 = self.fee_markets.supports_eip1559(&client, &chain).await?;
+        let tx = build_transaction(&client, &chain, eip1559, contract.address, calldata).await?;
+        let tx_hash = client.send_transaction(tx, None).await?.tx_hash();
+        debug!(?tx_hash, contract = ?contract.address, chain = %chain.name, "Fee claim sent");
+
+        let receipt = wait_for_receipt(&client, tx_hash, RECEIPT_TIMEOUT).await?;
+        record_gas_cost(
+            &*self.store,
+            &chain,
+            "fee_claim",
+            GasCostKind::FeeClaim,
+            &receipt,
+        );
+        let succeeded = receipt.status == Some(1.into());
+        let record = FeeClaimRecord {
+            tx_hash,
+            chain_id: chain.chain_id,
+            contract: contract.address,
+            function: contract.function.clone(),
+            amount_wei,
+            succeeded,
+            claimed_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_secs())
+                .unwrap_or_default(),
+        };
+        if let Err(e) = self.store.save_fee_claim(&record) {
+            warn!(error = %e, ?tx_hash, "Failed to record fee claim");
+        }
+        if !succeeded {
+            return Err(anyhow!("Fee claim {:?} reverted", tx_hash));
+        }
+
+        if let Some(amount) = amount_wei {
+            FEES_CLAIMED
+                .with_label_values(&[&chain.name])
+                .inc_by(wei_to_eth(amount));
+        }
+        info!(
+            ?tx_hash,
+            contract = ?contract.address,
+            chain = %chain.name,
+            amount_wei = ?amount_wei,
+            "Fees claimed"
+        );
+        Ok(Claim::Mined)
+    }
+
+    fn chain_name(&self, chain_id: ChainId) -> String {
+        self.topology
+            .chain(chain_id)
+            .map(|chain| chain.name)
+            .unwrap_or_else(|| chain_id.to_string())
+    }
+}
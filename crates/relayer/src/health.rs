@@ -0,0 +1,425 @@
+use ethers::{
+    core::types::{Address, U256},
+    providers::{Http, Middleware, Provider, ProviderError},
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+
+use crate::config::{ChainConfig, HealthConfig};
+use crate::metrics::{QUEUE_CAPACITY, QUEUE_DEPTH};
+use crate::topology::Topology;
+use crate::types::ChainId;
+
+pub(crate) const EVENT_GENERATOR: &str = "event_generator";
+pub(crate) const PROOF_FETCHER: &str = "proof_fetcher";
+pub(crate) const EVENT_DELIVERER: &str = "event_deliverer";
+
+const COMPONENTS: [&str; 3] = [EVENT_GENERATOR, PROOF_FETCHER, EVENT_DELIVERER];
+
+// Items queued and the queue's capacity, unset once the queue has closed
+type QueueFill = Box<dyn Fn() -> Option<(usize, usize)> + Send + Sync>;
+
+/// Shared view of component liveness, chain reachability and queue depths
+#[derive(Clone)]
+pub struct Health {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    config: HealthConfig,
+    /// Last time each component made progress
+    activity: Mutex<HashMap<&'static str, Instant>>,
+    /// Depth of the channel each component consumes from
+    queues: Mutex<HashMap<&'static str, QueueFill>>,
+    chains: Mutex<BTreeMap<ChainId, ChainHealth>>,
+    /// Latest events seen per relay pair ID
+    pairs: Mutex<HashMap<String, PairActivity>>,
+    gas_tanks: Mutex<BTreeMap<(ChainId, Address), GasTank>>,
+    detected: AtomicU64,
+    proven: AtomicU64,
+    delivered: AtomicU64,
+    /// The pipeline is stopped on purpose while another instance leads
+    standby: AtomicBool,
+    /// Every chain's RPC has been verified since the relayer started
+    started: AtomicBool,
+    /// The pipeline is being stopped ahead of shutdown
+    draining: AtomicBool,
+}
+
+/// Liveness of one pipeline component
+#[derive(Debug, Clone, Serialize)]
+pub struct ComponentHealth {
+    pub name: &'static str,
+    pub live: bool,
+    /// Seconds since the component last made progress, unset if it never started
+    pub idle_secs: Option<u64>,
+    /// Items waiting in the channel the component consumes from
+    pub queue_depth: Option<usize>,
+    pub queue_capacity: Option<usize>,
+}
+
+/// When a relay pair last had an event detected and delivered, in Unix
+/// seconds
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct PairActivity {
+    pub last_detected_at: Option<u64>,
+    pub last_delivered_at: Option<u64>,
+}
+
+/// Events that passed each stage since the relayer started
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub(crate) struct StageTotals {
+    pub(crate) detected: u64,
+    pub(crate) proven: u64,
+    pub(crate) delivered: u64,
+}
+
+/// Result of the latest RPC check against a chain
+#[derive(Debug, Clone, Serialize)]
+pub struct ChainHealth {
+    pub chain_id: ChainId,
+    pub name: String,
+    pub reachable: bool,
+    pub block_number: Option<u64>,
+    pub error: Option<String>,
+}
+
+/// Balance of one delivery wallet on one chain and how long it lasts at the
+/// relayer's current spend
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GasTank {
+    pub chain_id: ChainId,
+    pub chain: String,
+    pub wallet: Address,
+    pub balance_wei: U256,
+    /// Spent on the chain from every wallet over the spend window, scaled to
+    /// a day
+    pub chain_spend_per_day_wei: U256,
+    /// This wallet's part of it
+    pub spend_per_day_wei: U256,
+    /// Days until the wallet runs dry at its spend rate, unset while it
+    /// spends nothing
+    pub runway_days: Option<f64>,
+    /// Unix time in seconds the balance was read
+    pub checked_at: u64,
+}
+
+/// Snapshot served by `/healthz`, `/readyz` and `/startupz`
+#[derive(Debug, Clone, Serialize)]
+pub struct HealthReport {
+    /// Every component is making progress or has nothing to do
+    pub live: bool,
+    /// Started and live, not draining, and every chain's RPC answered its
+    /// latest check
+    pub ready: bool,
+    /// State was loaded and every chain's RPC answered with the configured
+    /// chain ID at least once
+    pub started: bool,
+    /// The pipeline is being stopped ahead of shutdown
+    pub draining: bool,
+    /// Waiting to take over from the leading instance, with no pipeline running
+    pub standby: bool,
+    pub components: Vec<ComponentHealth>,
+    pub chains: Vec<ChainHealth>,
+}
+
+impl Health {
+    pub fn new(config: HealthConfig) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                config,
+                activity: Mutex::new(HashMap::new()),
+                queues: Mutex::new(HashMap::new()),
+                chains: Mutex::new(BTreeMap::new()),
+                pairs: Mutex::new(HashMap::new()),
+                gas_tanks: Mutex::new(BTreeMap::new()),
+                detected: AtomicU64::new(0),
+                proven: AtomicU64::new(0),
+                delivered: AtomicU64::new(0),
+                standby: AtomicBool::new(false),
+                started: AtomicBool::new(false),
+                draining: AtomicBool::new(false),
+            }),
+        }
+    }
+
+    /// Record that a component made progress
+    pub(crate) fn beat(&self, component: &'static str) {
+        self.inner
+            .activity
+            .lock()
+            .expect("health lock poisoned")
+            .insert(component, Instant::now());
+    }
+
+    /// Record that an event was detected for relay pair `pair`
+    pub(crate) fn event_detected(&self, pair: &str) {
+        self.inner.detected.fetch_add(1, Ordering::Relaxed);
+        self.pair_activity_mut(pair, |activity| {
+            activity.last_detected_at = Some(unix_now())
+        });
+    }
+
+    /// Record that an event's proof was fetched
+    pub(crate) fn event_proven(&self) {
+        self.inner.proven.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Record that an event was delivered, for relay pair `pair` if it is
+    /// still configured
+    pub(crate) fn event_delivered(&self, pair: Option<&str>) {
+        self.inner.delivered.fetch_add(1, Ordering::Relaxed);
+        if let Some(pair) = pair {
+            self.pair_activity_mut(pair, |activity| {
+                activity.last_delivered_at = Some(unix_now())
+            });
+        }
+    }
+
+    fn pair_activity_mut(&self, pair: &str, update: impl FnOnce(&mut PairActivity)) {
+        let mut pairs = self.inner.pairs.lock().expect("health lock poisoned");
+        update(pairs.entry(pair.to_string()).or_default());
+    }
+
+    /// Latest events seen per relay pair ID, for pairs that have had any
+    pub fn pair_activity(&self) -> HashMap<String, PairActivity> {
+        self.inner
+            .pairs
+            .lock()
+            .expect("health lock poisoned")
+            .clone()
+    }
+
+    /// Record the latest balance reading and projection of a wallet
+    pub(crate) fn gas_tank_checked(&self, tank: GasTank) {
+        self.inner
+            .gas_tanks
+            .lock()
+            .expect("health lock poisoned")
+            .insert((tank.chain_id, tank.wallet), tank);
+    }
+
+    /// Latest reading of every delivery wallet checked so far, by chain
+    pub fn gas_tanks(&self) -> Vec<GasTank> {
+        self.inner
+            .gas_tanks
+            .lock()
+            .expect("health lock poisoned")
+            .values()
+            .cloned()
+            .collect()
+    }
+
+    pub(crate) fn stage_totals(&self) -> StageTotals {
+        StageTotals {
+            detected: self.inner.detected.load(Ordering::Relaxed),
+            proven: self.inner.proven.load(Ordering::Relaxed),
+            delivered: self.inner.delivered.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Record whether the pipeline is stopped while another instance leads,
+    /// which idle components are not to be blamed for
+    pub(crate) fn set_standby(&self, standby: bool) {
+        self.inner.standby.store(standby, Ordering::Relaxed);
+    }
+
+    /// Record that the pipeline is being stopped, which takes the relayer out
+    /// of readiness for good
+    pub(crate) fn set_draining(&self) {
+        self.inner.draining.store(true, Ordering::Relaxed);
+    }
+
+    /// Report the depth of the channel `component` consumes from
+    ///
+    /// Only a weak handle is kept, so the channel still closes when its
+    /// producer goes away.
+    pub(crate) fn watch_queue<T: Send + 'static>(
+        &self,
+        component: &'static str,
+        sender: &mpsc::Sender<T>,
+    ) {
+        QUEUE_CAPACITY
+            .with_label_values(&[component])
+            .set(sender.max_capacity() as i64);
+        let sender = sender.downgrade();
+        let fill: QueueFill = Box::new(move || {
+            let sender = sender.upgrade()?;
+            Some((
+                sender.max_capacity() - sender.capacity(),
+                sender.max_capacity(),
+            ))
+        });
+        self.inner
+            .queues
+            .lock()
+            .expect("health lock poisoned")
+            .insert(component, fill);
+    }
+
+    /// How full the fullest watched queue is, from 0 to 1
+    ///
+    /// Also brings the queue depth gauges up to date.
+    pub(crate) fn saturation(&self) -> f64 {
+        let queues = self.inner.queues.lock().expect("health lock poisoned");
+        queues
+            .iter()
+            .filter_map(|(component, fill)| {
+                let (depth, capacity) = fill()?;
+                QUEUE_DEPTH
+                    .with_label_values(&[component])
+                    .set(depth as i64);
+                Some(depth as f64 / capacity as f64)
+            })
+            .fold(0.0, f64::max)
+    }
+
+    /// Check every chain's RPC endpoint on the configured interval
+    ///
+    /// The relayer counts as started once a round of checks finds every
+    /// chain reachable; its state is loaded before it runs.
+    pub async fn run_chain_checks(&self, topology: Topology) {
+        let interval = Duration::from_millis(self.inner.config.rpc_check_interval_ms);
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            let chains = topology.chains();
+            self.inner
+                .chains
+                .lock()
+                .expect("health lock poisoned")
+                .retain(|chain_id, _| chains.iter().any(|chain| chain.chain_id == *chain_id));
+            for chain in &chains {
+                let health = self.check_chain(chain).await;
+                if let Some(error) = &health.error {
+                    warn!(chain = %chain.name, %error, "Chain RPC unreachable");
+                }
+                self.inner
+                    .chains
+                    .lock()
+                    .expect("health lock poisoned")
+                    .insert(chain.chain_id, health);
+            }
+            let verified = self
+                .inner
+                .chains
+                .lock()
+                .expect("health lock poisoned")
+                .values()
+                .all(|chain| chain.reachable);
+            if verified && !self.inner.started.swap(true, Ordering::Relaxed) {
+                info!(
+                    chains = chains.len(),
+                    "Chain RPCs verified, startup complete"
+                );
+            }
+        }
+    }
+
+    async fn check_chain(&self, chain: &ChainConfig) -> ChainHealth {
+        let timeout = Duration::from_millis(self.inner.config.rpc_timeout_ms);
+        let result = match Provider::<Http>::try_from(&chain.rpc_url) {
+            Ok(provider) => {
+                let check = async {
+                    let (chain_id, block) =
+                        tokio::try_join!(provider.get_chainid(), provider.get_block_number())?;
+                    Ok::<_, ProviderError>((ChainId::from(chain_id.as_u64()), block.as_u64()))
+                };
+                match tokio::time::timeout(timeout, check).await {
+                    Ok(Ok((chain_id, _))) if chain_id != chain.chain_id => Err(format!(
+                        "RPC serves chain {}, expected {}",
+                        chain_id, chain.chain_id
+                    )),
+                    Ok(Ok((_, block))) => Ok(block),
+                    Ok(Err(e)) => Err(e.to_string()),
+                    Err(_) => Err(format!("no response within {:?}", timeout)),
+                }
+            }
+            Err(e) => Err(e.to_string()),
+        };
+        ChainHealth {
+            chain_id: chain.chain_id,
+            name: chain.name.clone(),
+            reachable: result.is_ok(),
+            block_number: result.as_ref().ok().copied(),
+            error: result.err(),
+        }
+    }
+
+    /// Current liveness and readiness
+    ///
+    /// A component counts as live while it has made progress within the stall
+    /// timeout or has nothing queued for it; one that stops pulling from a
+    /// backed-up channel (for example behind an open circuit breaker) is not.
+    /// Every component counts as live on a standby.
+    pub fn report(&self) -> HealthReport {
+        let stall_timeout = Duration::from_millis(self.inner.config.stall_timeout_ms);
+        let standby = self.inner.standby.load(Ordering::Relaxed);
+        let activity = self.inner.activity.lock().expect("health lock poisoned");
+        let queues = self.inner.queues.lock().expect("health lock poisoned");
+
+        let components: Vec<ComponentHealth> = COMPONENTS
+            .iter()
+            .map(|&name| {
+                let idle = activity.get(name).map(|at| at.elapsed());
+                let fill = queues.get(name).and_then(|fill| fill());
+                let queue_depth = fill.map(|(depth, _)| depth);
+                let live = standby
+                    || match idle {
+                        Some(idle) => idle < stall_timeout || queue_depth == Some(0),
+                        None => false,
+                    };
+                ComponentHealth {
+                    name,
+                    live,
+                    idle_secs: idle.map(|idle| idle.as_secs()),
+                    queue_depth,
+                    queue_capacity: fill.map(|(_, capacity)| capacity),
+                }
+            })
+            .collect();
+
+        let chains: Vec<ChainHealth> = self
+            .inner
+            .chains
+            .lock()
+            .expect("health lock poisoned")
+            .values()
+            .cloned()
+            .collect();
+
+        let live = components.iter().all(|component| component.live);
+        let started = self.inner.started.load(Ordering::Relaxed);
+        let draining = self.inner.draining.load(Ordering::Relaxed);
+        let ready = started
+            && !draining
+            && live
+            && !chains.is_empty()
+            && chains.iter().all(|chain| chain.reachable);
+        HealthReport {
+            live,
+            ready,
+            started,
+            draining,
+            standby,
+            components,
+            chains,
+        }
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default()
+}
@@ -0,0 +1,157 @@
+use anyhow::{anyhow, Context, Result};
+use ethers::{
+    abi::{self, Token},
+    core::types::{Bytes, U256},
+    middleware::SignerMiddleware,
+    providers::{Http, Middleware, Provider},
+    signers::{LocalWallet, Signer},
+    utils::id,
+};
+use std::{collections::HashMap, str::FromStr, sync::Arc, time::Duration};
+use tokio::{sync::watch, time};
+use tracing::{debug, info, warn};
+
+use crate::accounting::{record_gas_cost, GasCostKind};
+use crate::config::{HeartbeatConfig, HeartbeatRegistry, RelayPair};
+use crate::event_delivery::{build_transaction, wait_for_receipt, FeeMarkets};
+use crate::metrics::HEARTBEATS;
+use crate::store::StateStore;
+use crate::topology::Topology;
+
+const REPORT_HEARTBEAT_SIGNATURE: &str = "reportHeartbeat((uint256,address,address,uint256)[])";
+// How long a sent heartbeat may take to be mined
+const RECEIPT_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Calldata for `reportHeartbeat`, giving for each of `pairs` its source
+/// chain ID, source resolver, destination dapp and the highest nonce in
+/// `last_nonces` delivered for it, or 0 if none was
+pub fn heartbeat_call(pairs: &[RelayPair], last_nonces: &HashMap<String, u64>) -> Bytes {
+    let reports = pairs
+        .iter()
+        .map(|pair| {
+            let last_nonce = last_nonces.get(&pair.id()).copied().unwrap_or_default();
+            Token::Tuple(vec![
+                Token::Uint(U256::from(pair.source_chain_id.as_u64())),
+                Token::Address(pair.source_resolver_address),
+                Token::Address(pair.dest_dapp_address),
+                Token::Uint(U256::from(last_nonce)),
+            ])
+        })
+        .collect();
+    let mut calldata = id(REPORT_HEARTBEAT_SIGNATURE).to_vec();
+    calldata.extend(abi::encode(&[Token::Array(reports)]));
+    calldata.into()
+}
+
+/// Reports this relayer's heartbeat to every configured registry on a timer,
+/// while it leads
+pub(crate) struct Heartbeat {
+    config: HeartbeatConfig,
+    wallet: LocalWallet,
+    topology: Topology,
+    store: Arc<dyn StateStore>,
+    fee_markets: FeeMarkets,
+}
+
+impl Heartbeat {
+    pub(crate) fn new(
+        config: HeartbeatConfig,
+        private_key: Option<&str>,
+        topology: Topology,
+        store: Arc<dyn StateStore>,
+    ) -> Result<Self> {
+        let key = config
+            .wallet_key
+            .as_deref()
+            .or(private_key)
+            .ok_or_else(|| anyhow!("A private key is needed to send heartbeats"))?;
+        let wallet = LocalWallet::from_str(key).context("Invalid heartbeat wallet key")?;
+        Ok(Self {
+            config,
+            wallet,
+            topology,
+            store,
+            fee_markets: FeeMarkets::default(),
+        })
+    }
+
+    pub(crate) async fn run(self, leader: watch::Receiver<bool>) {
+        let interval = Duration::from_millis(self.config.interval_ms);
+        let mut ticker = time::interval(interval);
+        // A heartbeat still being mined when the next is due delays it; dapps
+        // only care how recent the last one is
+        ticker.set_missed_tick_behavior(time::MissedTickBehavior::Delay);
+        info!(
+            registries = self.config.registries.len(),
+            interval_secs = interval.as_secs(),
+            from = ?self.wallet.address(),
+            "Reporting heartbeats"
+        );
+        loop {
+            ticker.tick().await;
+            // Standby instances stay quiet, so a registry sees the leader only
+            if !*leader.borrow() {
+                continue;
+            }
+            for registry in &self.config.registries {
+                let result = match self.report(registry).await {
+                    Ok(()) => "mined",
+                    Err(e) => {
+                        warn!(
+                            error = %e,
+                            chain_id = %registry.chain_id,
+                            registry = ?registry.address,
+                            "Failed to report heartbeat"
+                        );
+                        "failed"
+                    }
+                };
+                let chain = self
+                    .topology
+                    .chain(registry.chain_id)
+                    .map(|chain| chain.name)
+                    .unwrap_or_else(|| registry.chain_id.to_string());
+                HEARTBEATS.with_label_values(&[&chain, result]).inc();
+            }
+        }
+    }
+
+    // Send one heartbeat covering the pairs delivering to the registry's
+    // chain, waiting for it to be mined
+    async fn report(&self, registry: &HeartbeatRegistry) -> Result<()> {
+        let chain = self
+            .topology
+            .chain(registry.chain_id)
+            .ok_or_else(|| anyhow!("Chain {} is not configured", registry.chain_id))?;
+        let pairs: Vec<RelayPair> = self
+            .topology
+            .enabled_pairs()
+            .into_iter()
+            .filter(|pair| pair.dest_chain_id == registry.chain_id)
+            .collect();
+        let calldata = heartbeat_call(&pairs, &self.store.last_delivered_nonces()?);
+
+        let provider = Provider::<Http>::try_from(&chain.rpc_url)
+            .context(format!("Failed to create provider for {}", chain.name))?;
+        let wallet = self.wallet.clone().with_chain_id(chain.chain_id);
+        let client = SignerMiddleware::new(provider, wallet);
+        let eip1559 = self.fee_markets.supports_eip1559(&client, &chain).await?;
+        let tx = build_transaction(&client, &chain, eip1559, registry.address, calldata).await?;
+        let tx_hash = client.send_transaction(tx, None).await?.tx_hash();
+        debug!(?tx_hash, pairs = pairs.len(), chain = %chain.name, "Heartbeat sent");
+
+        let receipt = wait_for_receipt(&client, tx_hash, RECEIPT_TIMEOUT).await?;
+        record_gas_cost(
+            &*self.store,
+            &chain,
+            "heartbeat",
+            GasCostKind::Heartbeat,
+            &receipt,
+        );
+        if receipt.status != Some(1.into()) {
+            return Err(anyhow!("Heartbeat {:?} reverted", tx_hash));
+        }
+        info!(?tx_hash, pairs = pairs.len(), chain = %chain.name, "Heartbeat reported");
+        Ok(())
+    }
+}
@@ -0,0 +1,109 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use std::sync::Arc;
+use tracing::warn;
+
+use crate::metrics::HOOK_VETOES;
+use crate::types::{DeliveryOutcome, DeliveryRequest, RelayEvent, RelayerError};
+
+/// What a hook wants done with the item it was shown
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HookDecision {
+    Continue,
+    /// Stop relaying the item, for the given reason
+    Veto(String),
+}
+
+/// Callbacks run as items pass through the pipeline, which can observe them
+/// or veto them before they go further
+///
+/// A vetoed event is moved to the dead letter queue, where an operator can
+/// inspect or replay it. A hook that fails vetoes the item too, so a broken
+/// policy never lets items through unchecked. Hooks run in the order they
+/// were added and the first veto wins.
+#[async_trait]
+pub trait PipelineHook: Send + Sync {
+    /// Name the hook's vetoes are reported under
+    fn name(&self) -> &str;
+
+    /// An event taken in for proving, before its proof is requested
+    async fn on_event_detected(&self, _event: &RelayEvent) -> Result<HookDecision> {
+        Ok(HookDecision::Continue)
+    }
+
+    /// An event whose proof was fetched, before it is queued for delivery
+    async fn on_proof_fetched(&self, _request: &DeliveryRequest) -> Result<HookDecision> {
+        Ok(HookDecision::Continue)
+    }
+
+    /// A proven event about to be submitted, checked again on every attempt
+    async fn before_delivery(&self, _request: &DeliveryRequest) -> Result<HookDecision> {
+        Ok(HookDecision::Continue)
+    }
+
+    /// The outcome of a delivery attempt, including attempts that were vetoed
+    async fn after_delivery(&self, _outcome: &DeliveryOutcome) {}
+}
+
+/// Registered hooks, shared by every stage
+#[derive(Clone, Default)]
+pub(crate) struct Hooks(Arc<Vec<Arc<dyn PipelineHook>>>);
+
+impl Hooks {
+    pub(crate) fn new(hooks: Vec<Arc<dyn PipelineHook>>) -> Self {
+        Self(Arc::new(hooks))
+    }
+
+    pub(crate) async fn event_detected(&self, event: &RelayEvent) -> Result<()> {
+        for hook in self.0.iter() {
+            let decision = hook.on_event_detected(event).await;
+            verdict(hook.as_ref(), "on_event_detected", decision)?;
+        }
+        Ok(())
+    }
+
+    pub(crate) async fn proof_fetched(&self, request: &DeliveryRequest) -> Result<()> {
+        for hook in self.0.iter() {
+            let decision = hook.on_proof_fetched(request).await;
+            verdict(hook.as_ref(), "on_proof_fetched", decision)?;
+        }
+        Ok(())
+    }
+
+    pub(crate) async fn before_delivery(&self, request: &DeliveryRequest) -> Result<()> {
+        for hook in self.0.iter() {
+            let decision = hook.before_delivery(request).await;
+            verdict(hook.as_ref(), "before_delivery", decision)?;
+        }
+        Ok(())
+    }
+
+    pub(crate) async fn after_delivery(&self, outcome: &DeliveryOutcome) {
+        for hook in self.0.iter() {
+            hook.after_delivery(outcome).await;
+        }
+    }
+}
+
+// Turn a hook's answer into the error that stops the item, if it was stopped
+fn verdict(
+    hook: &dyn PipelineHook,
+    stage: &'static str,
+    decision: Result<HookDecision>,
+) -> Result<()> {
+    let reason = match decision {
+        Ok(HookDecision::Continue) => return Ok(()),
+        Ok(HookDecision::Veto(reason)) => reason,
+        Err(e) => {
+            warn!(hook = hook.name(), stage, error = %e, "Pipeline hook failed");
+            format!("hook failed: {:#}", e)
+        }
+    };
+    HOOK_VETOES.with_label_values(&[hook.name(), stage]).inc();
+    Err(RelayerError::Vetoed {
+        hook: hook.name().to_string(),
+        stage,
+        reason,
+    }
+    .into())
+}
@@ -0,0 +1,223 @@
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use ethers::{
+    abi::{self, Token},
+    core::types::{Filter, Log, H256},
+    providers::{Http, Middleware, Provider},
+    utils::keccak256,
+};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error, info, info_span, instrument};
+
+use crate::clock::{Clock, SystemClock, Ticker};
+use crate::config::{IntentSourceConfig, RelayMode, RelayPair};
+use crate::decode::{self, OPEN};
+use crate::event_source::{EventEmitter, EventSource};
+use crate::store::StateStore;
+use crate::telemetry;
+use crate::topology::Topology;
+use crate::types::{ChainConfig, EventMeta, RelayEvent};
+
+/// Event source turning open ERC-7683 orders into relay events
+///
+/// Each poll scans the origin settler of every enabled `erc7683` pair for
+/// `Open` events since its last scan, the first scan after a start reaching
+/// `lookback_blocks` back. An order is relayed if one of its fill
+/// instructions names the pair's destination chain and settler and its fill
+/// deadline has not passed. The event's payload is the ABI-encoded order ID
+/// and that instruction's origin data, and its nonce the first eight bytes
+/// of the order ID. Orders an earlier run picked up are known by their
+/// stored status and skipped.
+///
+/// A relayer detecting chain events runs one of these next to the event
+/// generator.
+pub struct IntentSource {
+    topology: Topology,
+    store: Arc<dyn StateStore>,
+    config: IntentSourceConfig,
+    clock: Arc<dyn Clock>,
+    /// Next block to scan, per pair
+    cursors: Mutex<HashMap<String, u64>>,
+}
+
+impl IntentSource {
+    pub fn new(topology: Topology, store: Arc<dyn StateStore>, config: IntentSourceConfig) -> Self {
+        Self {
+            topology,
+            store,
+            config,
+            clock: Arc::new(SystemClock),
+            cursors: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub(crate) fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    async fn poll(&self, emitter: &EventEmitter) {
+        let pairs = self.topology.enabled_pairs();
+        for pair in pairs
+            .iter()
+            .filter(|pair| pair.relay_mode == RelayMode::Erc7683)
+        {
+            if let Err(e) = self.scan(emitter, pair).await {
+                error!(pair = %pair.id(), error = %e, "Error scanning origin settler for orders");
+            }
+        }
+    }
+
+    // Emit the orders opened since the pair's last scan
+    #[instrument(skip_all, fields(pair = %pair.id()))]
+    async fn scan(&self, emitter: &EventEmitter, pair: &RelayPair) -> Result<()> {
+        let chain = |chain_id| {
+            self.topology
+                .chain(chain_id)
+                .ok_or_else(|| anyhow!("Chain {} not found in config", chain_id))
+        };
+        let (source, dest) = (chain(pair.source_chain_id)?, chain(pair.dest_chain_id)?);
+        let provider = Provider::<Http>::try_from(&source.rpc_url)
+            .context(format!("Failed to create provider for {}", source.name))?;
+        let head = provider.get_block_number().await?.as_u64();
+        let Some(to) = head.checked_sub(self.config.confirmations) else {
+            return Ok(());
+        };
+        let id = pair.id();
+        let from = self
+            .lock()
+            .get(&id)
+            .copied()
+            .unwrap_or_else(|| to.saturating_sub(self.config.lookback_blocks));
+        if from > to {
+            return Ok(());
+        }
+        let to = to.min(from + self.config.max_block_range.max(1) - 1);
+
+        debug!(from, to, "Scanning origin settler for opened orders");
+        let filter = Filter::new()
+            .address(pair.source_resolver_address)
+            .topic0(H256::from(keccak256(OPEN)))
+            .from_block(from)
+            .to_block(to);
+        for log in provider.get_logs(&filter).await? {
+            match self.order_event(&source, &dest, pair, &log) {
+                Ok(Some(event)) => emitter.emit(event).await?,
+                Ok(None) => {}
+                Err(e) => info!(tx_hash = ?log.transaction_hash, error = %e, "Skipping order"),
+            }
+        }
+        self.lock().insert(id, to + 1);
+        Ok(())
+    }
+
+    // The event relaying an order, if it has a leg for the pair that still
+    // needs filling
+    fn order_event(
+        &self,
+        source: &ChainConfig,
+        dest: &ChainConfig,
+        pair: &RelayPair,
+        log: &Log,
+    ) -> Result<Option<RelayEvent>> {
+        let order = decode::decode_open(log)?;
+        let settler = H256::from(pair.dest_dapp_address);
+        let Some(instruction) = order.fill_instructions.iter().find(|instruction| {
+            instruction.destination_chain_id == pair.dest_chain_id.as_u64()
+                && instruction.destination_settler == settler
+        }) else {
+            return Ok(None);
+        };
+        if u64::from(order.fill_deadline) < self.clock.unix_time() {
+            info!(order_id = ?order.order_id, "Order fill deadline passed, not filling");
+            return Ok(None);
+        }
+
+        let missing = |field: &str| anyhow!("Open log has no {}", field);
+        let tx_index = log
+            .transaction_index
+            .ok_or_else(|| missing("transaction index"))?;
+        let log_index = log.log_index.ok_or_else(|| missing("log index"))?;
+        let meta = EventMeta {
+            chain_id: source.chain_id,
+            tx_hash: Some(
+                log.transaction_hash
+                    .ok_or_else(|| missing("transaction hash"))?,
+            ),
+            block_number: log
+                .block_number
+                .ok_or_else(|| missing("block number"))?
+                .as_u64(),
+            tx_index: u32::try_from(tx_index.as_u64())
+                .map_err(|_| anyhow!("Transaction index {} is out of range", tx_index))?,
+            log_index: u32::try_from(log_index)
+                .map_err(|_| anyhow!("Log index {} is out of range", log_index))?,
+        };
+        let mut nonce = [0; 8];
+        nonce.copy_from_slice(&order.order_id[..8]);
+        let mut event = RelayEvent {
+            source_chain: source.clone(),
+            source_resolver_address: pair.source_resolver_address,
+            destination_chain: dest.clone(),
+            dest_dapp_address: pair.dest_dapp_address,
+            exec_payload: abi::encode(&[
+                Token::FixedBytes(order.order_id.as_bytes().to_vec()),
+                Token::Bytes(instruction.origin_data.to_vec()),
+            ])
+            .into(),
+            nonce: u64::from_be_bytes(nonce),
+            meta,
+            detected_at: self.clock.unix_time(),
+            trace_context: Default::default(),
+            priority: pair.priority,
+        };
+        if self.store.event_record(&event.id())?.is_some() {
+            debug!(order_id = ?order.order_id, "Order already picked up");
+            return Ok(None);
+        }
+
+        // Every stage this event goes through joins the trace started here
+        let span = info_span!(
+            parent: None,
+            "relay_event",
+            stage = "detect",
+            event_id = %event.id(),
+            chain_id = source.chain_id.as_u64(),
+            pair = %pair.id(),
+            nonce = event.nonce,
+            tx_hash = ?event.meta.tx_hash,
+            order_id = ?order.order_id
+        );
+        event.trace_context = telemetry::inject(&span);
+        info!(parent: &span, "✅ Order to fill");
+        Ok(Some(event))
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, u64>> {
+        self.cursors.lock().expect("intent cursor lock poisoned")
+    }
+}
+
+#[async_trait]
+impl EventSource for IntentSource {
+    fn name(&self) -> &str {
+        "erc7683"
+    }
+
+    async fn run(&self, emitter: EventEmitter, shutdown: CancellationToken) -> Result<()> {
+        let period = Duration::from_millis(self.config.poll_interval_ms);
+        let mut ticker = Ticker::new(self.clock.clone(), period);
+        loop {
+            tokio::select! {
+                _ = ticker.tick() => {}
+                _ = shutdown.cancelled() => return Ok(()),
+            }
+            self.poll(&emitter).await;
+        }
+    }
+}
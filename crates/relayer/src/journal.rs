@@ -0,0 +1,255 @@
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    fs::{self, File, OpenOptions},
+    io::{BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tracing::{info, warn};
+
+use crate::config::JournalConfig;
+use crate::store::{ProofKey, StateStore};
+use crate::types::{DeliveryStatus, RelayEvent};
+
+const JOURNAL_FILE: &str = "journal.log";
+
+/// How far an event has come through the pipeline
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum JournalStage {
+    Detected,
+    Proven,
+    /// Delivered, or found executed already
+    Delivered,
+    /// Given up on; the dead letter queue holds it from here
+    DeadLettered,
+    /// Proven for a `ccip_read` pair, its proof kept for the gateway
+    Published,
+}
+
+impl JournalStage {
+    fn is_final(self) -> bool {
+        matches!(self, Self::Delivered | Self::DeadLettered | Self::Published)
+    }
+}
+
+// One line of the journal
+#[derive(Debug, Serialize, Deserialize)]
+struct JournalEntry {
+    key: String,
+    stage: JournalStage,
+    /// Only carried by the detection entry
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    event: Option<RelayEvent>,
+    recorded_at: u64,
+}
+
+/// Append-only log of every detected event and the stages it reaches
+///
+/// Events are journaled before they enter the pipeline, so one lost in a
+/// queue by a crash or a restarted pipeline is found unfinished in the
+/// journal and resumed. A disabled journal records nothing.
+#[derive(Clone, Default)]
+pub(crate) struct Journal {
+    inner: Option<Arc<Inner>>,
+}
+
+struct Inner {
+    path: PathBuf,
+    file: Mutex<File>,
+    fsync: bool,
+}
+
+impl Journal {
+    pub(crate) fn open(state_dir: &Path, config: &JournalConfig) -> Result<Self> {
+        if !config.enabled {
+            return Ok(Self::default());
+        }
+        fs::create_dir_all(state_dir).context(format!(
+            "Failed to create state dir {}",
+            state_dir.display()
+        ))?;
+        let path = state_dir.join(JOURNAL_FILE);
+        let file = open_append(&path)?;
+        Ok(Self {
+            inner: Some(Arc::new(Inner {
+                path,
+                file: Mutex::new(file),
+                fsync: config.fsync,
+            })),
+        })
+    }
+
+    /// Record an event about to be sent into the pipeline
+    pub(crate) fn detected(&self, event: &RelayEvent) {
+        self.record(
+            &ProofKey::from_meta(&event.meta),
+            JournalStage::Detected,
+            Some(event),
+        );
+    }
+
+    pub(crate) fn advanced(&self, key: &ProofKey, stage: JournalStage) {
+        self.record(key, stage, None);
+    }
+
+    /// Record how a delivery attempt ended, unless the event is still retrying
+    pub(crate) fn settled(&self, key: &ProofKey, status: &DeliveryStatus) {
+        match status {
+            DeliveryStatus::Delivered | DeliveryStatus::AlreadyExecuted => {
+                self.advanced(key, JournalStage::Delivered)
+            }
+            DeliveryStatus::Expired | DeliveryStatus::DeadLettered => {
+                self.advanced(key, JournalStage::DeadLettered)
+            }
+            DeliveryStatus::Retrying { .. } => {}
+        }
+    }
+
+    fn record(&self, key: &ProofKey, stage: JournalStage, event: Option<&RelayEvent>) {
+        let Some(inner) = &self.inner else {
+            return;
+        };
+        let entry = JournalEntry {
+            key: key.to_string(),
+            stage,
+            event: event.cloned(),
+            recorded_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_secs())
+                .unwrap_or_default(),
+        };
+        if let Err(e) = inner.append(&entry) {
+            warn!(error = %e, proof_key = %key, ?stage, "Failed to write journal entry");
+        }
+    }
+
+    /// Make every unfinished journaled event pending in the store, so the
+    /// next pipeline resumes it, then drop finished events from the journal
+    ///
+    /// Events the store already tracks as pending, retrying, parked or dead
+    /// are left to it. Returns how many events were resumed.
+    pub(crate) fn recover(&self, store: &dyn StateStore) -> Result<usize> {
+        let Some(inner) = &self.inner else {
+            return Ok(0);
+        };
+        let mut file = inner
+            .file
+            .lock()
+            .map_err(|_| anyhow!("Journal lock poisoned"))?;
+        file.flush()?;
+
+        // Latest stage and detected event per key, in journal order
+        let mut order = Vec::new();
+        let mut events: HashMap<String, JournalEntry> = HashMap::new();
+        let reader = BufReader::new(File::open(&inner.path)?);
+        for (index, line) in reader.lines().enumerate() {
+            let line = line?;
+            let entry: JournalEntry = match serde_json::from_str(&line) {
+                Ok(entry) => entry,
+                // A write cut short by a crash leaves a partial last line
+                Err(e) => {
+                    warn!(error = %e, line = index + 1, "Skipping unreadable journal entry");
+                    continue;
+                }
+            };
+            match events.get_mut(&entry.key) {
+                Some(latest) => {
+                    latest.stage = entry.stage;
+                    latest.event = entry.event.or(latest.event.take());
+                }
+                None => {
+                    order.push(entry.key.clone());
+                    events.insert(entry.key.clone(), entry);
+                }
+            }
+        }
+
+        let tracked: HashSet<String> = store
+            .pending_events()?
+            .iter()
+            .map(|event| ProofKey::from_meta(&event.meta).to_string())
+            .chain(
+                store
+                    .retries()?
+                    .iter()
+                    .map(|failed| failed.key().to_string()),
+            )
+            .chain(
+                store
+                    .dead_letters()?
+                    .iter()
+                    .map(|failed| failed.key().to_string()),
+            )
+            .chain(
+                store
+                    .spilled_deliveries()?
+                    .iter()
+                    .map(|request| ProofKey::from_meta(&request.event.meta).to_string()),
+            )
+            .collect();
+
+        let mut unfinished = Vec::new();
+        let mut resumed = 0;
+        for key in order {
+            let Some(entry) = events.remove(&key) else {
+                continue;
+            };
+            // Events resumed from the store were never journaled as detected
+            let Some(event) = &entry.event else {
+                continue;
+            };
+            if entry.stage.is_final() {
+                continue;
+            }
+            if !tracked.contains(&key) {
+                store.save_pending_event(&ProofKey::from_meta(&event.meta), event)?;
+                resumed += 1;
+            }
+            unfinished.push(entry);
+        }
+
+        // Keep only what is still unfinished, swapping the file in atomically
+        let tmp = inner.path.with_extension("log.tmp");
+        let mut compacted = File::create(&tmp)?;
+        for entry in &unfinished {
+            serde_json::to_writer(&mut compacted, entry)?;
+            compacted.write_all(b"\n")?;
+        }
+        compacted.sync_all()?;
+        fs::rename(&tmp, &inner.path)?;
+        *file = open_append(&inner.path)?;
+
+        if resumed > 0 {
+            info!(resumed, "Resuming events left unfinished in the journal");
+        }
+        Ok(resumed)
+    }
+}
+
+impl Inner {
+    fn append(&self, entry: &JournalEntry) -> Result<()> {
+        let mut line = serde_json::to_vec(entry)?;
+        line.push(b'\n');
+        let mut file = self
+            .file
+            .lock()
+            .map_err(|_| anyhow!("Journal lock poisoned"))?;
+        file.write_all(&line)?;
+        if self.fsync {
+            file.sync_data()?;
+        }
+        Ok(())
+    }
+}
+
+fn open_append(path: &Path) -> Result<File> {
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .context(format!("Failed to open journal {}", path.display()))
+}
@@ -0,0 +1,167 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{LazyLock, Mutex, MutexGuard},
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tracing::warn;
+
+use crate::alerts::{self, AlertKind};
+use crate::config::SloConfig;
+use crate::metrics::{
+    PairLabels, END_TO_END_LATENCY, SLO_COMPLIANCE, SLO_DELIVERIES, STAGE_LATENCY,
+};
+use crate::status::EventStatus;
+use crate::types::{EventId, RelayEvent};
+
+// Milestones of events in flight and the SLO of the running relayer
+static LATENCY: LazyLock<Mutex<Latency>> = LazyLock::new(|| Mutex::new(Latency::default()));
+
+#[derive(Default)]
+struct Latency {
+    slo: SloConfig,
+    timelines: HashMap<EventId, Timeline>,
+    /// When each delivery in the SLO window confirmed, and whether it made
+    /// the target, oldest first
+    window: VecDeque<(u64, bool)>,
+}
+
+// Unix times in milliseconds an event reached each milestone
+struct Timeline {
+    detected: u64,
+    proved: Option<u64>,
+    /// Latest submission of a delivery transaction, or hand-off to a sink
+    broadcast: Option<u64>,
+}
+
+/// Measure against `slo` from now on, forgetting events seen so far
+pub(crate) fn init(slo: &SloConfig) {
+    *lock() = Latency {
+        slo: slo.clone(),
+        ..Default::default()
+    };
+}
+
+fn lock() -> MutexGuard<'static, Latency> {
+    LATENCY.lock().expect("latency lock poisoned")
+}
+
+fn unix_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+/// Note `event` entering `status`, observing its latencies once delivered
+pub(crate) fn record(event: &RelayEvent, status: &EventStatus) {
+    let now = unix_millis();
+    let id = event.id();
+    let mut latency = lock();
+    match status {
+        EventStatus::Detected => {
+            latency.timelines.entry(id).or_insert(Timeline {
+                detected: now,
+                proved: None,
+                broadcast: None,
+            });
+        }
+        EventStatus::Proved => latency.timeline(event).proved = Some(now),
+        EventStatus::Delivered => {
+            let timeline = latency.timeline(event);
+            let labels = PairLabels::of(event);
+            let stages = [
+                (
+                    "detection_to_proof",
+                    Some(timeline.detected),
+                    timeline.proved,
+                ),
+                ("proof_to_broadcast", timeline.proved, timeline.broadcast),
+                ("broadcast_to_confirmation", timeline.broadcast, Some(now)),
+            ];
+            for (stage, from, to) in stages {
+                if let (Some(from), Some(to)) = (from, to) {
+                    STAGE_LATENCY
+                        .with_label_values(&labels.and(&[stage]))
+                        .observe(seconds(to.saturating_sub(from)));
+                }
+            }
+            let end_to_end = now.saturating_sub(timeline.detected);
+            END_TO_END_LATENCY
+                .with_label_values(&labels.values())
+                .observe(seconds(end_to_end));
+            latency.timelines.remove(&id);
+            let breach = latency.confirmed(now, end_to_end);
+            drop(latency);
+            if let Some(message) = breach {
+                warn!("{}", message);
+                alerts::notify(AlertKind::SloBreach, "end_to_end_latency", message);
+            }
+        }
+        // Not delivered by the pipeline, so there is nothing more to measure
+        EventStatus::Failed { .. } | EventStatus::Expired => {
+            latency.timelines.remove(&id);
+        }
+        EventStatus::Proving | EventStatus::Delivering => {}
+    }
+}
+
+/// Note a delivery of `event` being submitted to its destination
+pub(crate) fn broadcast(event: &RelayEvent) {
+    lock().timeline(event).broadcast = Some(unix_millis());
+}
+
+/// Stop tracking `event`, which the pipeline is done with short of delivery
+pub(crate) fn forget(event: &RelayEvent) {
+    lock().timelines.remove(&event.id());
+}
+
+impl Latency {
+    // Timeline of `event`, started from its detection time for events this
+    // relayer did not see detected, such as those resumed after a restart
+    fn timeline(&mut self, event: &RelayEvent) -> &mut Timeline {
+        self.timelines
+            .entry(event.id())
+            .or_insert_with(|| Timeline {
+                detected: match event.detected_at {
+                    0 => unix_millis(),
+                    at => at * 1_000,
+                },
+                proved: None,
+                broadcast: None,
+            })
+    }
+
+    // Count a delivery confirmed at `now` against the SLO, returning what is
+    // wrong if the window no longer meets the objective
+    fn confirmed(&mut self, now: u64, end_to_end: u64) -> Option<String> {
+        let met = end_to_end <= self.slo.target_ms;
+        SLO_DELIVERIES
+            .with_label_values(&[if met { "met" } else { "missed" }])
+            .inc();
+        self.window.push_back((now, met));
+        let since = now.saturating_sub(self.slo.window_ms);
+        while self.window.front().is_some_and(|(at, _)| *at < since) {
+            self.window.pop_front();
+        }
+
+        let total = self.window.len();
+        let made = self.window.iter().filter(|(_, met)| *met).count();
+        let compliance = made as f64 / total as f64;
+        SLO_COMPLIANCE.set(compliance);
+        (total >= self.slo.min_deliveries.max(1) && compliance < self.slo.objective).then(|| {
+            format!(
+                "{:.1}% of {} deliveries in the last {}s confirmed within {}ms of \
+                 detection, below the {:.1}% objective",
+                compliance * 100.0,
+                total,
+                self.slo.window_ms / 1_000,
+                self.slo.target_ms,
+                self.slo.objective * 100.0
+            )
+        })
+    }
+}
+
+fn seconds(millis: u64) -> f64 {
+    millis as f64 / 1_000.0
+}
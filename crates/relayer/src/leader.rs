@@ -0,0 +1,186 @@
+use anyhow::{Context, Result};
+use redis::{aio::MultiplexedConnection, Script};
+use std::{
+    future::Future,
+    sync::LazyLock,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+use tokio::sync::watch;
+use tracing::{info, warn};
+
+use crate::config::LeaderElectionConfig;
+use crate::metrics::LEADER;
+
+// Extend the lease only while this instance still holds it
+const RENEW_SCRIPT: &str = r#"
+if redis.call("GET", KEYS[1]) == ARGV[1] then
+    return redis.call("PEXPIRE", KEYS[1], ARGV[2])
+end
+return 0
+"#;
+
+// Drop the lease only while this instance still holds it
+const RELEASE_SCRIPT: &str = r#"
+if redis.call("GET", KEYS[1]) == ARGV[1] then
+    return redis.call("DEL", KEYS[1])
+end
+return 0
+"#;
+
+/// Identifies this process to other instances, in leases and delivery claims
+pub(crate) static INSTANCE_ID: LazyLock<String> = LazyLock::new(|| {
+    let started = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_nanos())
+        .unwrap_or_default();
+    let host = std::env::var("HOSTNAME").unwrap_or_else(|_| "relayer".to_string());
+    format!("{}:{}:{}", host, std::process::id(), started)
+});
+
+/// Lease in Redis deciding which of several instances runs the pipeline
+///
+/// The leader renews its lease well before it runs out; a standby takes the
+/// lease once it has expired. A leader that cannot renew steps down before
+/// its lease expires, so two instances never both believe they lead.
+#[derive(Clone)]
+pub struct LeaderElection {
+    client: redis::Client,
+    config: LeaderElectionConfig,
+    /// Value stored under the lock key while this instance leads
+    instance_id: String,
+}
+
+impl LeaderElection {
+    pub fn new(config: LeaderElectionConfig) -> Result<Self> {
+        let client = redis::Client::open(config.redis_url.as_str())
+            .context("Invalid leader election Redis URL")?;
+        Ok(Self {
+            client,
+            config,
+            instance_id: INSTANCE_ID.clone(),
+        })
+    }
+
+    /// Take and keep the lease whenever possible, publishing whether this
+    /// instance currently leads
+    pub async fn run(&self, leader: watch::Sender<bool>) {
+        let renew_interval = Duration::from_millis(self.config.renew_interval_ms);
+        let lease = Duration::from_millis(self.config.lease_ms);
+        let mut conn = None;
+        let mut renewed_at: Option<Instant> = None;
+        let mut ticker = tokio::time::interval(renew_interval);
+
+        loop {
+            ticker.tick().await;
+            let leading = renewed_at.is_some();
+            let result = if leading {
+                self.call(&mut conn, |mut conn, key, id, lease_ms| async move {
+                    let renewed: i64 = Script::new(RENEW_SCRIPT)
+                        .key(key)
+                        .arg(id)
+                        .arg(lease_ms)
+                        .invoke_async(&mut conn)
+                        .await?;
+                    Ok(renewed == 1)
+                })
+                .await
+            } else {
+                self.call(&mut conn, |mut conn, key, id, lease_ms| async move {
+                    let set: Option<String> = redis::cmd("SET")
+                        .arg(key)
+                        .arg(id)
+                        .arg("NX")
+                        .arg("PX")
+                        .arg(lease_ms)
+                        .query_async(&mut conn)
+                        .await?;
+                    Ok(set.is_some())
+                })
+                .await
+            };
+
+            match result {
+                Ok(true) => {
+                    if !leading {
+                        info!(instance = %self.instance_id, "Acquired leader lease");
+                    }
+                    renewed_at = Some(Instant::now());
+                }
+                Ok(false) => {
+                    if leading {
+                        warn!("Leader lease was taken over, stepping down");
+                    }
+                    renewed_at = None;
+                }
+                Err(e) => {
+                    warn!(error = %e, "Failed to reach leader election Redis");
+                    conn = None;
+                    // Step down while the lease is still ours, leaving time to stop
+                    if renewed_at.is_some_and(|at| at.elapsed() + renew_interval >= lease) {
+                        warn!("Leader lease could not be renewed in time, stepping down");
+                        renewed_at = None;
+                    }
+                }
+            }
+
+            let leading = renewed_at.is_some();
+            LEADER.set(leading as i64);
+            leader.send_if_modified(|current| {
+                let changed = *current != leading;
+                *current = leading;
+                changed
+            });
+        }
+    }
+
+    /// Give up the lease, if held, so a standby can take over right away
+    pub async fn release(&self) {
+        let mut conn = None;
+        let released = self
+            .call(&mut conn, |mut conn, key, id, _| async move {
+                let released: i64 = Script::new(RELEASE_SCRIPT)
+                    .key(key)
+                    .arg(id)
+                    .invoke_async(&mut conn)
+                    .await?;
+                Ok(released == 1)
+            })
+            .await;
+        match released {
+            Ok(true) => info!("Released leader lease"),
+            Ok(false) => {}
+            Err(e) => warn!(error = %e, "Failed to release leader lease"),
+        }
+        LEADER.set(0);
+    }
+
+    // Run one Redis operation on a shared connection, opening it if needed,
+    // and give up once a renewal interval has passed
+    async fn call<F, Fut>(&self, conn: &mut Option<MultiplexedConnection>, op: F) -> Result<bool>
+    where
+        F: FnOnce(MultiplexedConnection, String, String, u64) -> Fut,
+        Fut: Future<Output = Result<bool>>,
+    {
+        let timeout = Duration::from_millis(self.config.renew_interval_ms);
+        let attempt = async {
+            let connection = match conn {
+                Some(connection) => connection.clone(),
+                None => {
+                    let connection = self.client.get_multiplexed_async_connection().await?;
+                    *conn = Some(connection.clone());
+                    connection
+                }
+            };
+            op(
+                connection,
+                self.config.lock_key.clone(),
+                self.instance_id.clone(),
+                self.config.lease_ms,
+            )
+            .await
+        };
+        tokio::time::timeout(timeout, attempt)
+            .await
+            .context("Leader election Redis did not answer in time")?
+    }
+}
@@ -4,10 +4,98 @@ mod event_generator;
 mod proof_fetcher;
 mod event_delivery;
 mod app;
+mod store;
+mod metrics;
+mod server;
+mod accounting;
+mod health;
+mod telemetry;
+mod topology;
+mod backpressure;
+mod priority;
+mod leader;
+mod dead_letters;
+mod admin_client;
+mod alerts;
+mod control_socket;
+mod control_client;
+mod event_source;
+mod builder;
+mod hooks;
+mod plugins;
+mod journal;
+mod lifecycle;
+mod report;
+mod schema;
+mod ccip_gateway;
+mod intent_source;
+mod failure;
+mod status;
+mod stream;
+mod audit;
+mod latency;
+mod heartbeat;
+mod profitability;
+mod fee_claims;
+mod clock;
+mod decode;
+mod devnet;
+#[cfg(feature = "testkit")]
+pub mod testkit;
 
-pub use config::{ChainConfig, RelayerConfig, RelayPair};
-pub use types::{RelayEvent, ProofRequest, DeliveryRequest, RelayerError};
+pub use config::{
+    AddressInput, AlertConfig, AuditConfig, BackpressureConfig, BatchConfig, CallEncoding, ChainConfig,
+    ChainKind, CircuitBreakerConfig, DeliveryConfig, DeliveryMode, EventStreamConfig, ExecutedCheck, FeeClaimConfig, FeeContract, ForwarderConfig, GasTankConfig, HealthConfig, HeartbeatConfig, HeartbeatRegistry, HyperlaneConfig,
+    IntentSourceConfig, JournalConfig, LeaderElectionConfig, LifecycleConfig, LogFormat, MetricLabels, MetricsConfig, MockProofConfig, PluginConfig,
+    PluginSpec, PolymerApiConfig, ProfitabilityConfig, ProofBackendConfig, ProofBatchConfig, ProofEncoding,
+    ProofFetcherConfig, RelayMode, RelayPair,
+    RelayPairBuilder, RelayerConfig, RelayerConfigBuilder, RetryConfig, ShardingConfig,
+    SloConfig, SmartAccountConfig, SolanaAccountConfig, SolanaCommitment, SolanaFeeConfig, SolanaProofBufferConfig, SolanaSinkConfig,
+    StoreBackend, StreamBroker, SupervisorConfig, TelemetryConfig, TokenRefreshConfig, TransactionType, UnprofitableRelays,
+    WebhookConfig, WebhookKind, WebhookSinkConfig,
+};
+pub use types::{
+    ChainId, DeliveryOutcome, DeliveryRequest, DeliveryStatus, EventId, EventMeta, Proof,
+    ProofMetadata, ProofRequest, RelayEvent, RelayerError, RevertKind, SolanaPubkey,
+};
 pub use event_generator::EventGenerator;
-pub use proof_fetcher::ProofFetcher;
-pub use event_delivery::EventDeliverer;
-pub use app::RelayerApp;
+pub use proof_fetcher::{
+    InteropProofProvider, LogIdentifier, MockProofProvider, PolymerProofProvider, ProofFetcher, ProofProvider,
+};
+pub use event_delivery::{
+    DeliveryControl, DeliverySink, EventDeliverer, InFlightDelivery, hyperlane_message,
+    SolanaKeypair, SolanaSink, WebhookSink,
+};
+pub use app::{RelayerApp, RelayerHandle};
+pub use ccip_gateway::CcipGateway;
+pub use intent_source::IntentSource;
+pub use builder::RelayerBuilder;
+pub use clock::{Clock, SystemClock};
+pub use event_source::{EventEmitter, EventSource};
+pub use failure::FailureClass;
+pub use audit::{verify_audit_log, AuditAction, AuditEntry, AuditHead};
+pub use heartbeat::heartbeat_call;
+pub use fee_claims::FeeClaimRecord;
+pub use hooks::{HookDecision, PipelineHook};
+pub use plugins::{PluginContext, PluginRegistry};
+pub use status::{EventQuery, EventRecord, EventStatus, MAX_EVENT_PAGE};
+pub use schema::{DELIVERY_REQUEST_SCHEMA, EVENT_META_SCHEMA, RELAY_EVENT_SCHEMA};
+pub use telemetry::{init_tracing, TraceContext};
+pub use topology::{ManagedPair, Topology};
+pub use dead_letters::{DeadLetterKey, DeadLetterQueue};
+pub use leader::LeaderElection;
+pub use admin_client::AdminClient;
+pub use devnet::{Devnet, DevnetChain, DevnetConfig, DEVNET_PRIVATE_KEY};
+pub use control_client::ControlClient;
+pub use control_socket::{ComponentStatus, PairStatus, RelayerStatus};
+pub use health::{ChainHealth, ComponentHealth, GasTank, Health, HealthReport, PairActivity};
+pub use accounting::{wei_to_eth, GasCostKind, GasCostRecord, GasCostTotals};
+pub use store::{
+    DeadLetterQuery, DeliveryClaim, DeliveryQuery, FailedDelivery, FailureStage, FileStateStore,
+    LedgerEntry, ProofKey, ProofRecord, SqliteStateStore, StateStore,
+};
+pub use decode::{
+    decode_open, decode_proof, exec_request_meta, find_exec_request, find_sent_message,
+    function_selector, sent_message_meta, FillInstruction, OpenOrder, CROSS_CHAIN_EXEC_REQUESTED,
+    L2_TO_L2_CROSS_DOMAIN_MESSENGER, OPEN, SENT_MESSAGE,
+};
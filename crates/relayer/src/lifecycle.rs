@@ -0,0 +1,85 @@
+use std::time::Duration;
+use tokio_util::sync::{CancellationToken, DropGuard};
+use tracing::warn;
+
+use crate::config::RelayerConfig;
+
+// Left of the termination grace period after the drain, for flushing the
+// store and event stream and exiting
+const STOP_MARGIN: Duration = Duration::from_secs(2);
+
+/// How a running relayer is brought down
+///
+/// Draining stops the pipeline once in-flight work has finished but keeps
+/// health and admin endpoints up until shutdown, as a Kubernetes preStop hook
+/// wants; shutdown drains too if that hasn't happened yet, then stops.
+#[derive(Clone)]
+pub(crate) struct Lifecycle {
+    shutdown: CancellationToken,
+    /// Cancelled by shutdown as well
+    drain: CancellationToken,
+    drained: CancellationToken,
+    drain_period: Duration,
+}
+
+impl Lifecycle {
+    pub(crate) fn new(config: &RelayerConfig) -> Self {
+        let shutdown = CancellationToken::new();
+        let mut drain_period = Duration::from_millis(config.shutdown_grace_period_ms);
+        if let Some(grace) = config.lifecycle.termination_grace_period_ms {
+            let limit = Duration::from_millis(grace).saturating_sub(STOP_MARGIN);
+            if drain_period > limit {
+                warn!(
+                    shutdown_grace_period_ms = config.shutdown_grace_period_ms,
+                    termination_grace_period_ms = grace,
+                    "Shutdown grace period exceeds the termination grace period, shortening it"
+                );
+                drain_period = limit;
+            }
+        }
+        Self {
+            drain: shutdown.child_token(),
+            shutdown,
+            drained: CancellationToken::new(),
+            drain_period,
+        }
+    }
+
+    pub(crate) fn shutdown_token(&self) -> &CancellationToken {
+        &self.shutdown
+    }
+
+    pub(crate) fn drain_token(&self) -> &CancellationToken {
+        &self.drain
+    }
+
+    /// Stop the pipeline, leaving the relayer up until it is shut down
+    pub(crate) fn drain(&self) {
+        self.drain.cancel();
+    }
+
+    /// Wait until the pipeline has drained, or the relayer has stopped
+    pub(crate) async fn drained(&self) {
+        self.drained.cancelled().await
+    }
+
+    pub(crate) fn set_drained(&self) {
+        self.drained.cancel();
+    }
+
+    /// Marks the pipeline drained when dropped, however the run ends
+    pub(crate) fn drained_guard(&self) -> DropGuard {
+        self.drained.clone().drop_guard()
+    }
+
+    /// How long in-flight work gets to finish once the pipeline is stopped
+    pub(crate) fn drain_period(&self) -> Duration {
+        self.drain_period
+    }
+
+    /// How long the event stream gets to flush once the pipeline has
+    /// drained, which fits in the margin kept for stopping
+    pub(crate) fn flush_period(&self) -> Duration {
+        STOP_MARGIN / 2
+    }
+}
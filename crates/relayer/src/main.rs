@@ -1,59 +1,390 @@
-use anyhow::Result;
-use tracing::info;
+use anyhow::{anyhow, Result};
+use tracing::{info, warn};
 use std::collections::HashMap;
-use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+use std::path::Path;
 
-use relayer::{ChainConfig, RelayerApp, RelayerConfig, RelayPair};
+use relayer::{
+    init_tracing, verify_audit_log, wei_to_eth, AdminClient, AlertConfig, AuditConfig, ChainConfig,
+    ChainId, ControlClient, DeadLetterKey, DeadLetterQuery, Devnet, DevnetConfig,
+    EventStreamConfig, FailureStage, LeaderElectionConfig, LogFormat, PolymerApiConfig,
+    ProofBackendConfig, RelayPair, RelayerApp, RelayerConfig, StoreBackend, StreamBroker,
+    TelemetryConfig, WebhookConfig, WebhookKind, DEVNET_PRIVATE_KEY,
+};
+
+const DLQ_USAGE: &str = "usage: relayer dlq list|show <key>|replay <key>|replay-all \
+                         [--source-chain <id>] [--dest-chain <id>] [--stage proof|delivery] \
+                         [--event-id <id>]; <key> is a proof key or an event id";
+const DEV_USAGE: &str =
+    "usage: relayer dev up [--anvil <path>] [--block-time <secs>] [--state-dir <dir>]";
+const AUDIT_USAGE: &str = "usage: relayer audit verify [<path>]";
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    // Initialize tracing
-    tracing_subscriber::registry()
-        .with(tracing_subscriber::EnvFilter::new(
-            std::env::var("RUST_LOG").unwrap_or_else(|_| "info".into()),
-        ))
-        .with(tracing_subscriber::fmt::layer())
-        .init();
-
-    info!("Starting cross-chain relayer");
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("dlq") {
+        return dlq(&args[1..]).await;
+    }
+    match args.first().map(String::as_str) {
+        Some("status") => return status().await,
+        Some(command @ ("pause" | "resume")) => return pause(command, &args[1..]).await,
+        Some("drain") => return drain().await,
+        Some("dev") => return dev(&args[1..]).await,
+        Some("audit") => return audit(&args[1..]),
+        _ => {}
+    }
+    let log_format = match args.iter().position(|arg| arg == "--log-format") {
+        Some(index) => match args.get(index + 1).map(String::as_str) {
+            Some("text") => LogFormat::Text,
+            Some("json") => LogFormat::Json,
+            _ => return Err(anyhow!("usage: relayer [--log-format text|json]")),
+        },
+        None => LogFormat::Text,
+    };
 
     // Load configuration
     let config = RelayerConfig {
         polling_interval_ms: 10000,
         chains: {
             let mut chains = HashMap::new();
-            chains.insert(11155420, ChainConfig {
+            chains.insert(ChainId::new(11155420), ChainConfig {
                 name: "Optimism Sepolia".to_string(),
-                chain_id: 11155420,
+                chain_id: ChainId::new(11155420),
                 rpc_url: "https://optimism-sepolia.example.com".to_string(),
+                ..Default::default()
             });
-            chains.insert(84532, ChainConfig {
+            chains.insert(ChainId::new(84532), ChainConfig {
                 name: "Base Sepolia".to_string(),
-                chain_id: 84532,
+                chain_id: ChainId::new(84532),
                 rpc_url: "https://base-sepolia.example.com".to_string(),
+                ..Default::default()
             });
             chains
         },
         relay_pairs: vec![
             RelayPair {
-                source_chain_id: 11155420,
-                source_resolver_address: "0x1234567890123456789012345678901234567890".to_string(),
-                dest_chain_id: 84532,
-                dest_dapp_address: "0x0987654321098765432109876543210987654321".to_string(),
+                source_chain_id: ChainId::new(11155420),
+                source_resolver_address: "0x1234567890123456789012345678901234567890".parse()?,
+                dest_chain_id: ChainId::new(84532),
+                dest_dapp_address: "0x0987654321098765432109876543210987654321".parse()?,
+                ..Default::default()
             },
             RelayPair {
-                source_chain_id: 84532,
-                source_resolver_address: "0x2345678901234567890123456789012345678901".to_string(),
-                dest_chain_id: 11155420,
-                dest_dapp_address: "0x9876543210987654321098765432109876543210".to_string(),
+                source_chain_id: ChainId::new(84532),
+                source_resolver_address: "0x2345678901234567890123456789012345678901".parse()?,
+                dest_chain_id: ChainId::new(11155420),
+                dest_dapp_address: "0x9876543210987654321098765432109876543210".parse()?,
+                ..Default::default()
             },
         ],
+        proof_backend: ProofBackendConfig::Polymer(PolymerApiConfig {
+            token: std::env::var("POLYMER_API_TOKEN").unwrap_or_default(),
+            ..Default::default()
+        }),
+        proof_fetcher: Default::default(),
+        delivery: Default::default(),
+        state_dir: "./relayer-state".into(),
+        store_backend: StoreBackend::Sqlite,
+        http_addr: Some(([0, 0, 0, 0], 9090).into()),
+        admin_token: std::env::var("RELAYER_ADMIN_TOKEN").ok(),
+        control_socket: Some(control_socket_path().into()),
+        shutdown_grace_period_ms: 30_000,
+        supervisor: Default::default(),
+        health: Default::default(),
+        backpressure: Default::default(),
+        leader_election: std::env::var("RELAYER_REDIS_URL")
+            .ok()
+            .map(|redis_url| LeaderElectionConfig {
+                redis_url,
+                ..Default::default()
+            }),
+        sharding: None,
+        alerts: AlertConfig {
+            webhooks: std::env::var("RELAYER_SLACK_WEBHOOK_URL")
+                .ok()
+                .map(|url| WebhookConfig {
+                    url,
+                    kind: WebhookKind::Slack,
+                })
+                .into_iter()
+                .collect(),
+            ..Default::default()
+        },
+        telemetry: TelemetryConfig {
+            log_format,
+            otlp_endpoint: std::env::var("OTEL_EXPORTER_OTLP_TRACES_ENDPOINT").ok(),
+            ..Default::default()
+        },
+        plugins: Default::default(),
+        journal: Default::default(),
+        lifecycle: Default::default(),
+        intents: Default::default(),
+        event_stream: std::env::var("RELAYER_NATS_URL")
+            .ok()
+            .map(|url| EventStreamConfig {
+                broker: StreamBroker::Nats {
+                    url,
+                    subject: "relayer.events".to_string(),
+                    token: std::env::var("RELAYER_NATS_TOKEN").ok(),
+                },
+                ..Default::default()
+            }),
+        metrics: Default::default(),
+        audit: std::env::var("RELAYER_AUDIT_LOG")
+            .ok()
+            .map(|path| AuditConfig {
+                path: Some(path.into()),
+                ..Default::default()
+            }),
+        slo: Default::default(),
+        heartbeat: None,
+        profitability: Default::default(),
+        fee_claims: None,
     };
 
+    // Initialize tracing
+    let tracer_provider = init_tracing(&config.telemetry)?;
+
+    info!("Starting cross-chain relayer");
+
     // Private key (would come from env or secure storage)
     let private_key = "0x1234567890abcdef1234567890abcdef1234567890abcdef1234567890abcdef";
 
     // Create and run the application
-    let mut app = RelayerApp::new(config, private_key);
-    app.run().await
+    let relayer = RelayerApp::new(config, private_key)?.run();
+    relayer.shutdown_on_signal();
+    let result = relayer.await_terminated().await;
+
+    // Send any spans still buffered
+    if let Some(provider) = tracer_provider {
+        if let Err(e) = provider.shutdown() {
+            warn!(error = %e, "Failed to flush traces");
+        }
+    }
+    result
+}
+
+// Inspect and replay dead letters through a running relayer's admin API
+async fn dlq(args: &[String]) -> Result<()> {
+    let client = AdminClient::new(
+        std::env::var("RELAYER_ADMIN_URL").unwrap_or_else(|_| "http://127.0.0.1:9090".into()),
+        std::env::var("RELAYER_ADMIN_TOKEN").ok(),
+    );
+
+    match args {
+        [command, filters @ ..] if command == "list" => {
+            for failed in client.dead_letters(&dlq_query(filters)?).await? {
+                println!(
+                    "{}\t{}\t{:?}\tattempts={}\t{}",
+                    failed.key(),
+                    failed.event.id(),
+                    failed.stage,
+                    failed.attempts,
+                    failed.last_error
+                );
+            }
+        }
+        [command, key] if command == "show" => {
+            let failed = client.dead_letter(&key.parse::<DeadLetterKey>()?).await?;
+            println!("{}", serde_json::to_string_pretty(&failed)?);
+        }
+        [command, key] if command == "replay" => {
+            client.replay(&key.parse::<DeadLetterKey>()?).await?;
+            println!("Replayed {}", key);
+        }
+        [command, filters @ ..] if command == "replay-all" => {
+            let replayed = client.replay_all(&dlq_query(filters)?).await?;
+            println!("Replayed {} dead letters", replayed);
+        }
+        _ => return Err(anyhow!(DLQ_USAGE)),
+    }
+    Ok(())
+}
+
+fn dlq_query(filters: &[String]) -> Result<DeadLetterQuery> {
+    let mut query = DeadLetterQuery::default();
+    for pair in filters.chunks(2) {
+        match pair {
+            [flag, value] if flag == "--source-chain" => {
+                query.source_chain_id = Some(value.parse()?)
+            }
+            [flag, value] if flag == "--dest-chain" => {
+                query.dest_chain_id = Some(value.parse()?)
+            }
+            [flag, value] if flag == "--event-id" => query.event_id = Some(value.parse()?),
+            [flag, value] if flag == "--stage" => {
+                query.stage = Some(match value.as_str() {
+                    "proof" => FailureStage::Proof,
+                    "delivery" => FailureStage::Delivery,
+                    _ => return Err(anyhow!(DLQ_USAGE)),
+                })
+            }
+            _ => return Err(anyhow!(DLQ_USAGE)),
+        }
+    }
+    Ok(query)
+}
+
+// Start local chains with sample contracts and relay between them with mock
+// proofs until interrupted
+async fn dev(args: &[String]) -> Result<()> {
+    let flags = match args {
+        [command, flags @ ..] if command == "up" => flags,
+        _ => return Err(anyhow!(DEV_USAGE)),
+    };
+    let mut devnet = DevnetConfig::default();
+    for pair in flags.chunks(2) {
+        match pair {
+            [flag, value] if flag == "--anvil" => devnet.anvil = value.into(),
+            [flag, value] if flag == "--block-time" => {
+                devnet.block_time_secs = Some(value.parse()?)
+            }
+            [flag, value] if flag == "--state-dir" => devnet.state_dir = value.into(),
+            _ => return Err(anyhow!(DEV_USAGE)),
+        }
+    }
+    init_tracing(&TelemetryConfig::default())?;
+
+    let devnet = Devnet::up(devnet).await?;
+    let config = devnet.relayer_config()?;
+    let path = devnet.write_config(&config)?;
+    info!(
+        config = %path.display(),
+        pairs = config.relay_pairs.len(),
+        "Devnet up, relaying with mock proofs; admin API on http://127.0.0.1:9090"
+    );
+
+    let relayer = RelayerApp::new(config, DEVNET_PRIVATE_KEY)?.run();
+    relayer.shutdown_on_signal();
+    // The devnet's nodes stop once it is dropped, after the relayer
+    relayer.await_terminated().await
+}
+
+// Check an audit log's hash chain, printing its head to keep elsewhere
+fn audit(args: &[String]) -> Result<()> {
+    let path = match args {
+        [command] if command == "verify" => "./relayer-state/audit.log",
+        [command, path] if command == "verify" => path.as_str(),
+        _ => return Err(anyhow!(AUDIT_USAGE)),
+    };
+    let head = verify_audit_log(Path::new(path))?;
+    println!("{} entries intact, head {:?}", head.entries, head.hash);
+    Ok(())
+}
+
+fn control_socket_path() -> String {
+    std::env::var("RELAYER_CONTROL_SOCKET")
+        .unwrap_or_else(|_| "./relayer-state/relayer.sock".to_string())
+}
+
+async fn status() -> Result<()> {
+    let status = ControlClient::new(control_socket_path()).status().await?;
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)?
+        .as_secs();
+    let ago = |at: Option<u64>| match at {
+        Some(at) => format!("{}s ago", now.saturating_sub(at)),
+        None => "never".to_string(),
+    };
+
+    let state = if status.standby {
+        "standby"
+    } else if status.paused {
+        "paused"
+    } else if status.live {
+        "live"
+    } else {
+        "stalled"
+    };
+    println!("Relayer: {}", state);
+    println!(
+        "Events: {} pending, {} retrying, {} dead-lettered, {} in flight",
+        status.pending_events, status.retrying, status.dead_letters, status.in_flight
+    );
+
+    println!("\nComponents:");
+    for component in &status.components {
+        let queue = match (component.queue_depth, component.queue_capacity) {
+            (Some(depth), Some(capacity)) => format!("{}/{}", depth, capacity),
+            _ => "-".to_string(),
+        };
+        println!(
+            "  {:<16} {:<8} queue={:<9} active {}",
+            component.name,
+            if component.live { "live" } else { "stalled" },
+            queue,
+            ago(component.last_active_at)
+        );
+    }
+
+    println!("\nPairs:");
+    for pair in &status.pairs {
+        let state = match (pair.enabled, pair.paused, pair.served) {
+            (false, _, _) => "disabled",
+            (true, true, _) => "paused",
+            (true, false, true) => "serving",
+            (true, false, false) => "other shard",
+        };
+        println!(
+            "  {} [{}, shard {}] pending={} retrying={} dead={} in_flight={} \
+             detected {}, delivered {}",
+            pair.id,
+            state,
+            pair.shard,
+            pair.pending_events,
+            pair.retrying,
+            pair.dead_letters,
+            pair.in_flight,
+            ago(pair.last_detected_at),
+            ago(pair.last_delivered_at)
+        );
+    }
+
+    if status.gas_tanks.is_empty() {
+        return Ok(());
+    }
+    println!("\nGas tanks:");
+    for tank in &status.gas_tanks {
+        let runway = match tank.runway_days {
+            Some(days) => format!("{:.1} days", days),
+            None => "no spend".to_string(),
+        };
+        println!(
+            "  {:<16} {:?} balance={:.6} spend={:.6}/day (chain {:.6}/day) runway {}",
+            tank.chain,
+            tank.wallet,
+            wei_to_eth(tank.balance_wei),
+            wei_to_eth(tank.spend_per_day_wei),
+            wei_to_eth(tank.chain_spend_per_day_wei),
+            runway
+        );
+    }
+    Ok(())
+}
+
+// Hold or resume relaying for one pair, or for every pair without one
+async fn pause(command: &str, args: &[String]) -> Result<()> {
+    let pair = match args {
+        [] => None,
+        [pair] => Some(pair.clone()),
+        _ => return Err(anyhow!("usage: relayer pause|resume [<pair id>]")),
+    };
+    let client = ControlClient::new(control_socket_path());
+    if command == "pause" {
+        client.pause(pair.clone()).await?;
+    } else {
+        client.resume(pair.clone()).await?;
+    }
+    println!(
+        "{} {}",
+        if command == "pause" { "Paused" } else { "Resumed" },
+        pair.as_deref().unwrap_or("every relay pair")
+    );
+    Ok(())
+}
+
+// Stop the pipeline ahead of shutdown, e.g. as a Kubernetes preStop hook
+async fn drain() -> Result<()> {
+    ControlClient::new(control_socket_path()).drain().await?;
+    println!("Pipeline drained");
+    Ok(())
 }
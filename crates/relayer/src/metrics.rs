@@ -0,0 +1,409 @@
+use prometheus::{
+    register_counter_vec, register_gauge, register_gauge_vec, register_histogram_vec,
+    register_int_counter, register_int_counter_vec, register_int_gauge, register_int_gauge_vec,
+    CounterVec, Encoder, Gauge, GaugeVec, HistogramVec, IntCounter, IntCounterVec, IntGauge,
+    IntGaugeVec, TextEncoder,
+};
+use std::sync::{LazyLock, RwLock};
+
+use crate::config::{MetricLabels, RelayPair};
+use crate::types::{ChainId, RelayEvent};
+
+// How finely series of relay pairs are labelled, set from the configuration
+static PAIR_LABELS: RwLock<MetricLabels> = RwLock::new(MetricLabels::Pair);
+
+// Value of a label collapsed away
+const COLLAPSED: &str = "*";
+
+pub static PROOF_CIRCUIT_OPEN: LazyLock<IntGauge> = LazyLock::new(|| {
+    register_int_gauge!(
+        "relayer_proof_circuit_open",
+        "Whether the proof API circuit breaker is currently open"
+    )
+    .expect("metric can be registered")
+});
+
+pub static PROOF_CIRCUIT_TRIPS: LazyLock<IntCounter> = LazyLock::new(|| {
+    register_int_counter!(
+        "relayer_proof_circuit_trips_total",
+        "Number of times the proof API circuit breaker has opened"
+    )
+    .expect("metric can be registered")
+});
+
+pub static PROOF_DURATION: LazyLock<HistogramVec> = LazyLock::new(|| {
+    register_histogram_vec!(
+        "relayer_proof_duration_seconds",
+        "Time from proof request to a usable proof",
+        &["source_chain", "dest_chain", "pair_id"],
+        vec![1.0, 2.5, 5.0, 10.0, 20.0, 30.0, 60.0, 120.0, 300.0]
+    )
+    .expect("metric can be registered")
+});
+
+pub static PROOF_POLLS: LazyLock<HistogramVec> = LazyLock::new(|| {
+    register_histogram_vec!(
+        "relayer_proof_polls",
+        "Proof status queries needed before a proof was ready",
+        &["source_chain"],
+        vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 8.0, 10.0]
+    )
+    .expect("metric can be registered")
+});
+
+pub static PROOF_BATCH_SIZE: LazyLock<HistogramVec> = LazyLock::new(|| {
+    register_histogram_vec!(
+        "relayer_proof_batch_size",
+        "Proof API calls combined into each batch call",
+        &["method"],
+        vec![1.0, 2.0, 5.0, 10.0, 20.0, 50.0, 100.0]
+    )
+    .expect("metric can be registered")
+});
+
+pub static PROOF_RESULTS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "relayer_proof_results_total",
+        "Proof fetch outcomes by relay pair and failure reason",
+        &["source_chain", "dest_chain", "pair_id", "result"]
+    )
+    .expect("metric can be registered")
+});
+
+pub static PROOF_ENDPOINT_HEALTH: LazyLock<GaugeVec> = LazyLock::new(|| {
+    register_gauge_vec!(
+        "relayer_proof_endpoint_health",
+        "Health score of each proof API endpoint, from 0 to 1",
+        &["endpoint"]
+    )
+    .expect("metric can be registered")
+});
+
+pub static DELIVERIES_EXPIRED: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "relayer_deliveries_expired_total",
+        "Deliveries abandoned because their deadline passed",
+        &["source_chain", "dest_chain", "pair_id"]
+    )
+    .expect("metric can be registered")
+});
+
+pub static DELIVERY_RETRIES: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "relayer_delivery_retries_total",
+        "Failed deliveries queued for another attempt",
+        &["source_chain", "dest_chain", "pair_id"]
+    )
+    .expect("metric can be registered")
+});
+
+pub static DEAD_LETTERS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "relayer_dead_letters_total",
+        "Deliveries given up on and moved to the dead letter queue",
+        &["source_chain", "dest_chain", "pair_id"]
+    )
+    .expect("metric can be registered")
+});
+
+pub static SIGNER_BALANCE: LazyLock<GaugeVec> = LazyLock::new(|| {
+    register_gauge_vec!(
+        "relayer_signer_balance_eth",
+        "Native token balance of each relayer delivery wallet on each chain",
+        &["chain", "signer"]
+    )
+    .expect("metric can be registered")
+});
+
+pub static SPEND_RATE: LazyLock<GaugeVec> = LazyLock::new(|| {
+    register_gauge_vec!(
+        "relayer_spend_rate_eth_per_day",
+        "Native token spent on gas per day over the spend window, by chain",
+        &["chain"]
+    )
+    .expect("metric can be registered")
+});
+
+pub static SIGNER_RUNWAY: LazyLock<GaugeVec> = LazyLock::new(|| {
+    register_gauge_vec!(
+        "relayer_signer_runway_days",
+        "Days until each delivery wallet runs dry at its spend rate, +Inf while it spends nothing",
+        &["chain", "signer"]
+    )
+    .expect("metric can be registered")
+});
+
+pub static GAS_SPENT: LazyLock<CounterVec> = LazyLock::new(|| {
+    register_counter_vec!(
+        "relayer_gas_spent_eth_total",
+        "Native token spent on gas by chain, relay pair, and transaction kind",
+        &["chain", "pair_id", "kind"]
+    )
+    .expect("metric can be registered")
+});
+
+pub static COMPONENT_RESTARTS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "relayer_component_restarts_total",
+        "Pipeline restarts by the component whose failure caused them",
+        &["component"]
+    )
+    .expect("metric can be registered")
+});
+
+pub static QUEUE_DEPTH: LazyLock<IntGaugeVec> = LazyLock::new(|| {
+    register_int_gauge_vec!(
+        "relayer_queue_depth",
+        "Items waiting in the queue each pipeline component consumes from",
+        &["component"]
+    )
+    .expect("metric can be registered")
+});
+
+pub static QUEUE_CAPACITY: LazyLock<IntGaugeVec> = LazyLock::new(|| {
+    register_int_gauge_vec!(
+        "relayer_queue_capacity",
+        "Capacity of the queue each pipeline component consumes from",
+        &["component"]
+    )
+    .expect("metric can be registered")
+});
+
+pub static QUEUE_FULL: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "relayer_queue_full_total",
+        "Sends that found a component's queue full",
+        &["component"]
+    )
+    .expect("metric can be registered")
+});
+
+pub static QUEUE_SEND_WAIT: LazyLock<HistogramVec> = LazyLock::new(|| {
+    register_histogram_vec!(
+        "relayer_queue_send_wait_seconds",
+        "Time senders spent waiting for room in a full queue",
+        &["component"],
+        vec![0.01, 0.1, 0.5, 1.0, 5.0, 10.0, 30.0, 60.0, 300.0]
+    )
+    .expect("metric can be registered")
+});
+
+pub static QUEUE_SPILLED: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "relayer_queue_spilled_total",
+        "Items parked in the state store because a component's queue was full",
+        &["component"]
+    )
+    .expect("metric can be registered")
+});
+
+pub static GENERATOR_POLLS_SKIPPED: LazyLock<IntCounter> = LazyLock::new(|| {
+    register_int_counter!(
+        "relayer_generator_polls_skipped_total",
+        "Event generator polls skipped because downstream queues were saturated"
+    )
+    .expect("metric can be registered")
+});
+
+pub static LEADER: LazyLock<IntGauge> = LazyLock::new(|| {
+    register_int_gauge!(
+        "relayer_leader",
+        "Whether this instance holds the leader lease and runs the pipeline"
+    )
+    .expect("metric can be registered")
+});
+
+pub static ALERTS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "relayer_alerts_total",
+        "Alerts raised by kind and whether they were sent, throttled or failed to send",
+        &["kind", "result"]
+    )
+    .expect("metric can be registered")
+});
+
+pub static HOOK_VETOES: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "relayer_hook_vetoes_total",
+        "Items held back by pipeline hooks, per hook and stage",
+        &["hook", "stage"]
+    )
+    .expect("metric can be registered")
+});
+
+pub static PAUSED: LazyLock<IntGaugeVec> = LazyLock::new(|| {
+    register_int_gauge_vec!(
+        "relayer_paused",
+        "Whether relaying is paused, per relay pair or for every pair as `*`",
+        &["pair"]
+    )
+    .expect("metric can be registered")
+});
+
+pub static CCIP_LOOKUPS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "relayer_ccip_lookups_total",
+        "CCIP-Read gateway lookups by whether a proof was served, not found or invalid",
+        &["result"]
+    )
+    .expect("metric can be registered")
+});
+
+pub static STREAM_MESSAGES: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "relayer_stream_messages_total",
+        "Event status changes sent to the event stream, by whether published or dropped",
+        &["result"]
+    )
+    .expect("metric can be registered")
+});
+
+pub static EVENTS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "relayer_events_total",
+        "Events entering each status, by relay pair",
+        &["source_chain", "dest_chain", "pair_id", "status"]
+    )
+    .expect("metric can be registered")
+});
+
+pub static STAGE_LATENCY: LazyLock<HistogramVec> = LazyLock::new(|| {
+    register_histogram_vec!(
+        "relayer_stage_latency_seconds",
+        "Time events spent between pipeline milestones: detection to proof, proof to \
+         broadcast and broadcast to confirmation",
+        &["source_chain", "dest_chain", "pair_id", "stage"],
+        vec![0.5, 1.0, 2.5, 5.0, 10.0, 20.0, 30.0, 60.0, 120.0, 300.0, 600.0]
+    )
+    .expect("metric can be registered")
+});
+
+pub static END_TO_END_LATENCY: LazyLock<HistogramVec> = LazyLock::new(|| {
+    register_histogram_vec!(
+        "relayer_end_to_end_latency_seconds",
+        "Time from detecting an event to its delivery confirming",
+        &["source_chain", "dest_chain", "pair_id"],
+        vec![1.0, 5.0, 10.0, 30.0, 60.0, 120.0, 300.0, 600.0, 1800.0]
+    )
+    .expect("metric can be registered")
+});
+
+pub static SLO_DELIVERIES: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "relayer_slo_deliveries_total",
+        "Confirmed deliveries by whether they made the end-to-end latency target",
+        &["result"]
+    )
+    .expect("metric can be registered")
+});
+
+pub static SLO_COMPLIANCE: LazyLock<Gauge> = LazyLock::new(|| {
+    register_gauge!(
+        "relayer_slo_compliance_ratio",
+        "Share of deliveries in the SLO window that made the end-to-end latency target"
+    )
+    .expect("metric can be registered")
+});
+
+pub static HEARTBEATS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "relayer_heartbeats_total",
+        "Heartbeats reported to registry contracts, by chain and whether they were mined",
+        &["chain", "result"]
+    )
+    .expect("metric can be registered")
+});
+
+pub static RELAY_DECISIONS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "relayer_relay_decisions_total",
+        "Priced requests of rewarding pairs by whether they were relayed, queued or skipped",
+        &["source_chain", "dest_chain", "pair_id", "decision"]
+    )
+    .expect("metric can be registered")
+});
+
+pub static FEE_CLAIMS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "relayer_fee_claims_total",
+        "Fee claims by chain and whether they were mined, skipped as not worth it or failed",
+        &["chain", "result"]
+    )
+    .expect("metric can be registered")
+});
+
+pub static FEES_CLAIMED: LazyLock<CounterVec> = LazyLock::new(|| {
+    register_counter_vec!(
+        "relayer_fees_claimed_eth_total",
+        "Native token swept from fee contracts by chain, as their dry runs priced the claims",
+        &["chain"]
+    )
+    .expect("metric can be registered")
+});
+
+/// Label series of relay pairs as finely as `labels` allows from now on
+pub(crate) fn init(labels: MetricLabels) {
+    *PAIR_LABELS.write().expect("metric labels lock poisoned") = labels;
+}
+
+/// `source_chain`, `dest_chain` and `pair_id` values for a series of one
+/// relay pair, with those the configuration collapses set to `*`
+pub(crate) struct PairLabels([String; 3]);
+
+impl PairLabels {
+    /// Labels of the pair that produced `event`
+    pub(crate) fn of(event: &RelayEvent) -> Self {
+        Self::new(
+            event.source_chain.chain_id,
+            event.destination_chain.chain_id,
+            event.pair_id(),
+        )
+    }
+
+    /// Labels of `pair`
+    pub(crate) fn of_pair(pair: &RelayPair) -> Self {
+        Self::new(pair.source_chain_id, pair.dest_chain_id, pair.id())
+    }
+
+    fn new(source: ChainId, dest: ChainId, pair_id: String) -> Self {
+        let source = source.to_string();
+        let dest = dest.to_string();
+        let collapsed = || COLLAPSED.to_string();
+        let labels = match *PAIR_LABELS.read().expect("metric labels lock poisoned") {
+            MetricLabels::Pair => [source, dest, pair_id],
+            MetricLabels::Route => [source, dest, collapsed()],
+            MetricLabels::Aggregate => [collapsed(), collapsed(), collapsed()],
+        };
+        Self(labels)
+    }
+
+    pub(crate) fn values(&self) -> [&str; 3] {
+        let [source, dest, pair] = &self.0;
+        [source, dest, pair]
+    }
+
+    /// The pair's values followed by those of the metric's other labels
+    pub(crate) fn and<'a>(&'a self, rest: &[&'a str]) -> Vec<&'a str> {
+        self.values()
+            .into_iter()
+            .chain(rest.iter().copied())
+            .collect()
+    }
+}
+
+/// A pair ID as the configuration lets it be a label value
+pub(crate) fn pair_id_label(pair_id: &str) -> &str {
+    match *PAIR_LABELS.read().expect("metric labels lock poisoned") {
+        MetricLabels::Pair => pair_id,
+        MetricLabels::Route | MetricLabels::Aggregate => COLLAPSED,
+    }
+}
+
+/// Render every registered metric in the Prometheus text format
+pub fn gather() -> String {
+    let mut buffer = Vec::new();
+    TextEncoder::new()
+        .encode(&prometheus::gather(), &mut buffer)
+        .expect("text encoding cannot fail");
+    String::from_utf8(buffer).expect("text encoding is valid utf-8")
+}
@@ -0,0 +1,131 @@
+use anyhow::{Context, Result};
+use serde_json::Value;
+use std::{collections::HashMap, sync::Arc};
+
+use crate::config::{PluginSpec, RelayerConfig};
+use crate::types::RelayerError;
+use crate::{DeliverySink, EventSource, ProofProvider, SolanaSink, StateStore, WebhookSink};
+
+/// What a plugin factory gets to build its stage from
+pub struct PluginContext<'a> {
+    pub config: &'a RelayerConfig,
+    pub store: &'a Arc<dyn StateStore>,
+    /// Options given to the plugin in its [`PluginSpec`]
+    pub options: &'a Value,
+}
+
+type Factory<T> = Arc<dyn Fn(&PluginContext<'_>) -> Result<Arc<T>> + Send + Sync>;
+
+/// Stage implementations that [`PluginConfig`](crate::PluginConfig) can
+/// select by name
+///
+/// Downstream crates register their plugins at startup and hand the registry
+/// to [`RelayerBuilder::plugins`](crate::RelayerBuilder::plugins). Registering
+/// a name again replaces the earlier plugin.
+///
+/// A new registry holds the built-in `solana` delivery sink, a [`SolanaSink`]
+/// taking a [`SolanaSinkConfig`](crate::SolanaSinkConfig) as its options, and
+/// the `webhook` one, a [`WebhookSink`] taking a
+/// [`WebhookSinkConfig`](crate::WebhookSinkConfig).
+#[derive(Clone)]
+pub struct PluginRegistry {
+    proof_providers: HashMap<String, Factory<dyn ProofProvider>>,
+    event_sources: HashMap<String, Factory<dyn EventSource>>,
+    delivery_sinks: HashMap<String, Factory<dyn DeliverySink>>,
+}
+
+impl PluginRegistry {
+    pub fn new() -> Self {
+        let mut registry = Self {
+            proof_providers: HashMap::new(),
+            event_sources: HashMap::new(),
+            delivery_sinks: HashMap::new(),
+        };
+        registry.register_delivery_sink("solana", |context| {
+            let config = serde_json::from_value(context.options.clone())
+                .context("Invalid Solana sink options")?;
+            Ok(Arc::new(SolanaSink::new(config)?))
+        });
+        registry.register_delivery_sink("webhook", |context| {
+            let config = serde_json::from_value(context.options.clone())
+                .context("Invalid webhook sink options")?;
+            Ok(Arc::new(WebhookSink::new(config)?))
+        });
+        registry
+    }
+
+    pub fn register_proof_provider<F>(&mut self, name: impl Into<String>, factory: F)
+    where
+        F: Fn(&PluginContext<'_>) -> Result<Arc<dyn ProofProvider>> + Send + Sync + 'static,
+    {
+        self.proof_providers.insert(name.into(), Arc::new(factory));
+    }
+
+    pub fn register_event_source<F>(&mut self, name: impl Into<String>, factory: F)
+    where
+        F: Fn(&PluginContext<'_>) -> Result<Arc<dyn EventSource>> + Send + Sync + 'static,
+    {
+        self.event_sources.insert(name.into(), Arc::new(factory));
+    }
+
+    pub fn register_delivery_sink<F>(&mut self, name: impl Into<String>, factory: F)
+    where
+        F: Fn(&PluginContext<'_>) -> Result<Arc<dyn DeliverySink>> + Send + Sync + 'static,
+    {
+        self.delivery_sinks.insert(name.into(), Arc::new(factory));
+    }
+
+    pub(crate) fn proof_provider(
+        &self,
+        spec: &PluginSpec,
+        config: &RelayerConfig,
+        store: &Arc<dyn StateStore>,
+    ) -> Result<Arc<dyn ProofProvider>> {
+        create(&self.proof_providers, "proof_provider", spec, config, store)
+    }
+
+    pub(crate) fn event_source(
+        &self,
+        spec: &PluginSpec,
+        config: &RelayerConfig,
+        store: &Arc<dyn StateStore>,
+    ) -> Result<Arc<dyn EventSource>> {
+        create(&self.event_sources, "event_source", spec, config, store)
+    }
+
+    pub(crate) fn delivery_sink(
+        &self,
+        spec: &PluginSpec,
+        config: &RelayerConfig,
+        store: &Arc<dyn StateStore>,
+    ) -> Result<Arc<dyn DeliverySink>> {
+        create(&self.delivery_sinks, "delivery_sink", spec, config, store)
+    }
+}
+
+impl Default for PluginRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn create<T: ?Sized>(
+    factories: &HashMap<String, Factory<T>>,
+    kind: &'static str,
+    spec: &PluginSpec,
+    config: &RelayerConfig,
+    store: &Arc<dyn StateStore>,
+) -> Result<Arc<T>> {
+    let factory = factories
+        .get(&spec.name)
+        .ok_or_else(|| RelayerError::UnknownPlugin {
+            kind,
+            name: spec.name.clone(),
+        })?;
+    let context = PluginContext {
+        config,
+        store,
+        options: &spec.options,
+    };
+    factory(&context).with_context(|| format!("Failed to create {} plugin {}", kind, spec.name))
+}
@@ -0,0 +1,157 @@
+use std::{
+    cmp::{Ordering, Reverse},
+    collections::BinaryHeap,
+    sync::{Arc, Mutex},
+};
+use tokio::sync::oneshot;
+
+/// Items served highest priority first, and in arrival order within a priority
+pub(crate) struct PriorityQueue<T> {
+    heap: BinaryHeap<Entry<T>>,
+    next_seq: u64,
+}
+
+struct Entry<T> {
+    priority: u8,
+    seq: Reverse<u64>,
+    item: T,
+}
+
+impl<T> PartialEq for Entry<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<T> Eq for Entry<T> {}
+
+impl<T> PartialOrd for Entry<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T> Ord for Entry<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.priority, self.seq).cmp(&(other.priority, other.seq))
+    }
+}
+
+impl<T> PriorityQueue<T> {
+    pub(crate) fn new() -> Self {
+        Self {
+            heap: BinaryHeap::new(),
+            next_seq: 0,
+        }
+    }
+
+    pub(crate) fn push(&mut self, priority: u8, item: T) {
+        self.heap.push(Entry {
+            priority,
+            seq: Reverse(self.next_seq),
+            item,
+        });
+        self.next_seq += 1;
+    }
+
+    pub(crate) fn pop(&mut self) -> Option<T> {
+        self.heap.pop().map(|entry| entry.item)
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.heap.len()
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.heap.is_empty()
+    }
+}
+
+/// A fixed number of slots handed to waiters by priority rather than in the
+/// order they started waiting
+#[derive(Clone)]
+pub(crate) struct PrioritySlots {
+    inner: Arc<Mutex<SlotsInner>>,
+}
+
+struct SlotsInner {
+    available: usize,
+    waiters: PriorityQueue<oneshot::Sender<()>>,
+}
+
+/// A slot taken from [`PrioritySlots`], given to the next waiter when dropped
+pub(crate) struct PrioritySlot {
+    inner: Arc<Mutex<SlotsInner>>,
+}
+
+// Gives back a slot granted to a waiter that stopped waiting before it noticed
+struct Waiting {
+    rx: Option<oneshot::Receiver<()>>,
+    inner: Arc<Mutex<SlotsInner>>,
+}
+
+impl PrioritySlots {
+    pub(crate) fn new(slots: usize) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(SlotsInner {
+                available: slots,
+                waiters: PriorityQueue::new(),
+            })),
+        }
+    }
+
+    /// Wait for a free slot
+    pub(crate) async fn acquire(&self, priority: u8) -> PrioritySlot {
+        let rx = {
+            let mut inner = self.inner.lock().expect("priority slots lock poisoned");
+            if inner.available > 0 {
+                inner.available -= 1;
+                return PrioritySlot {
+                    inner: self.inner.clone(),
+                };
+            }
+            let (tx, rx) = oneshot::channel();
+            inner.waiters.push(priority, tx);
+            rx
+        };
+
+        let mut waiting = Waiting {
+            rx: Some(rx),
+            inner: self.inner.clone(),
+        };
+        // Senders are only dropped after sending, so this always succeeds
+        let _ = waiting.rx.as_mut().expect("receiver is set").await;
+        waiting.rx = None;
+        PrioritySlot {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+// Hand a slot to the most urgent waiter still waiting, or put it back
+fn release(inner: &Mutex<SlotsInner>) {
+    let mut inner = inner.lock().expect("priority slots lock poisoned");
+    while let Some(waiter) = inner.waiters.pop() {
+        if waiter.send(()).is_ok() {
+            return;
+        }
+    }
+    inner.available += 1;
+}
+
+impl Drop for PrioritySlot {
+    fn drop(&mut self) {
+        release(&self.inner);
+    }
+}
+
+impl Drop for Waiting {
+    fn drop(&mut self) {
+        if let Some(mut rx) = self.rx.take() {
+            rx.close();
+            if rx.try_recv().is_ok() {
+                release(&self.inner);
+            }
+        }
+    }
+}
@@ -0,0 +1,120 @@
+use anyhow::{anyhow, Context, Result};
+use ethers::{
+    abi::{self, Token},
+    core::types::{transaction::eip2718::TypedTransaction, TransactionRequest, U256},
+    providers::{Http, Middleware, Provider},
+    utils::id,
+};
+
+use crate::accounting::GasCostKind;
+use crate::config::{ChainConfig, DeliveryMode, ProfitabilityConfig, RelayMode, RelayPair};
+use crate::store::StateStore;
+
+const REQUEST_REMOTE_EXECUTION_SIGNATURE: &str = "requestRemoteExecution(uint32)";
+
+/// What relaying one request of a pair is expected to earn and cost, in wei
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct RelayEstimate {
+    pub(crate) reward: U256,
+    /// `requestRemoteExecution` on the source chain
+    pub(crate) trigger: U256,
+    pub(crate) proof: U256,
+    /// Nothing for pairs whose deliveries the relayer does not pay for
+    pub(crate) delivery: U256,
+}
+
+impl RelayEstimate {
+    pub(crate) fn cost(&self) -> U256 {
+        self.trigger + self.proof + self.delivery
+    }
+
+    pub(crate) fn profitable(&self) -> bool {
+        self.reward >= self.cost()
+    }
+}
+
+/// Price relaying the pending request of `pair`, whose resolver pays what
+/// `reward_function` returns, with `source` the client the trigger would be
+/// sent from
+pub(crate) async fn estimate<M: Middleware>(
+    source: &M,
+    dest_chain: &ChainConfig,
+    pair: &RelayPair,
+    reward_function: &str,
+    config: &ProfitabilityConfig,
+    store: &dyn StateStore,
+) -> Result<RelayEstimate>
+where
+    M::Error: 'static,
+{
+    let dest_chain_id = [Token::Uint(pair.dest_chain_id.as_u32()?.into())];
+    let resolver_call = |signature: &str| -> TypedTransaction {
+        let mut data = id(signature).to_vec();
+        data.extend(abi::encode(&dest_chain_id));
+        let tx = TransactionRequest::new()
+            .to(pair.source_resolver_address)
+            .data(data);
+        match source.default_sender() {
+            Some(from) => tx.from(from).into(),
+            None => tx.into(),
+        }
+    };
+
+    let returned = source
+        .call(&resolver_call(reward_function), None)
+        .await
+        .context(format!(
+            "Failed to call {} on the resolver",
+            reward_function
+        ))?;
+    let reward = returned
+        .get(..32)
+        .map(U256::from_big_endian)
+        .ok_or_else(|| {
+            anyhow!(
+                "{} returned {} bytes, not a reward",
+                reward_function,
+                returned.len()
+            )
+        })?;
+
+    let trigger_call = resolver_call(REQUEST_REMOTE_EXECUTION_SIGNATURE);
+    let trigger_gas = source.estimate_gas(&trigger_call, None).await?;
+    let trigger = trigger_gas * source.get_gas_price().await?;
+    // Natively relayed messages need no proof
+    let proof = match pair.relay_mode {
+        RelayMode::OpInterop => U256::zero(),
+        _ => U256::from(config.proof_cost_wei),
+    };
+    let delivery = if pair.delivery_mode == DeliveryMode::CcipRead || pair.sponsored {
+        U256::zero()
+    } else {
+        let dest = Provider::<Http>::try_from(&dest_chain.rpc_url)
+            .context(format!("Failed to create provider for {}", dest_chain.name))?;
+        delivery_gas(store, pair, config)? * dest.get_gas_price().await?
+    };
+    Ok(RelayEstimate {
+        reward,
+        trigger,
+        proof,
+        delivery,
+    })
+}
+
+// Gas the next delivery of `pair` is expected to use: the average of its
+// latest deliveries, or the configured default before it has any
+fn delivery_gas(
+    store: &dyn StateStore,
+    pair: &RelayPair,
+    config: &ProfitabilityConfig,
+) -> Result<U256> {
+    let latest =
+        store.recent_gas_costs(&pair.id(), GasCostKind::Delivery, config.delivery_samples)?;
+    if latest.is_empty() {
+        return Ok(U256::from(config.default_delivery_gas));
+    }
+    let total = latest
+        .iter()
+        .fold(U256::zero(), |total, record| total + record.gas_used);
+    Ok(total / U256::from(latest.len()))
+}
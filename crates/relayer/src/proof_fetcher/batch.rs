@@ -0,0 +1,95 @@
+use anyhow::Result;
+use std::{future::Future, sync::Mutex, time::Duration};
+use tokio::sync::oneshot;
+use tracing::warn;
+
+use crate::config::ProofBatchConfig;
+
+/// Answer to one call of a batch; `None` if the batch call failed as a whole
+/// or left the call unanswered, so the caller should make it on its own
+pub(crate) type Answer<R> = Option<Result<R>>;
+
+struct Pending<T, R> {
+    // Bumped each time a batch is taken, so callers can tell whether theirs
+    // is still waiting
+    generation: u64,
+    calls: Vec<(T, oneshot::Sender<Answer<R>>)>,
+}
+
+/// Gathers calls made around the same time so they go out as one batch
+///
+/// Every caller waits out the window from its own arrival; the first whose
+/// window ends, or whose call fills the batch, sends it for all of them. A
+/// caller dropped while waiting leaves the others to send the batch.
+pub(crate) struct Coalescer<T, R> {
+    window: Duration,
+    max_size: usize,
+    pending: Mutex<Pending<T, R>>,
+}
+
+impl<T, R> Coalescer<T, R> {
+    pub(crate) fn new(config: &ProofBatchConfig) -> Self {
+        Self {
+            window: Duration::from_millis(config.window_ms),
+            max_size: config.max_size.max(1),
+            pending: Mutex::new(Pending {
+                generation: 0,
+                calls: Vec::new(),
+            }),
+        }
+    }
+
+    /// Add `call` to the next batch and wait for its answer
+    ///
+    /// `send` is used if this caller ends up sending the batch; it answers
+    /// the calls in the order given.
+    pub(crate) async fn call<F, Fut>(&self, call: T, send: F) -> Answer<R>
+    where
+        F: FnOnce(Vec<T>) -> Fut,
+        Fut: Future<Output = Result<Vec<Answer<R>>>>,
+    {
+        let (tx, mut rx) = oneshot::channel();
+        let (generation, full) = {
+            let mut pending = self.lock();
+            pending.calls.push((call, tx));
+            (pending.generation, pending.calls.len() >= self.max_size)
+        };
+        if !full {
+            tokio::select! {
+                answer = &mut rx => return answer.ok().flatten(),
+                _ = tokio::time::sleep(self.window) => {}
+            }
+        }
+
+        let batch = self.take(generation);
+        if !batch.is_empty() {
+            let (calls, senders): (Vec<_>, Vec<_>) = batch.into_iter().unzip();
+            let size = calls.len();
+            let mut answers = match send(calls).await {
+                Ok(answers) => answers.into_iter(),
+                Err(e) => {
+                    warn!(error = %e, size, "Batch call failed, sending its calls one by one");
+                    Vec::new().into_iter()
+                }
+            };
+            for sender in senders {
+                let _ = sender.send(answers.next().flatten());
+            }
+        }
+        rx.await.ok().flatten()
+    }
+
+    // The calls waiting in `generation`, unless another caller already sent them
+    fn take(&self, generation: u64) -> Vec<(T, oneshot::Sender<Answer<R>>)> {
+        let mut pending = self.lock();
+        if pending.generation != generation {
+            return Vec::new();
+        }
+        pending.generation += 1;
+        std::mem::take(&mut pending.calls)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Pending<T, R>> {
+        self.pending.lock().expect("batch lock poisoned")
+    }
+}
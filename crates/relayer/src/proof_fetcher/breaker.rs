@@ -0,0 +1,121 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tracing::{error, info};
+
+use crate::clock::Clock;
+use crate::config::CircuitBreakerConfig;
+use crate::metrics::{PROOF_CIRCUIT_OPEN, PROOF_CIRCUIT_TRIPS};
+
+// How often to re-check while a half-open probe is in flight
+const PROBE_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Debug)]
+enum BreakerState {
+    Closed { consecutive_failures: u32 },
+    Open { until: Instant },
+    HalfOpen,
+}
+
+/// Stops new proof requests while the proving backend is failing
+///
+/// After `failure_threshold` consecutive failures the breaker opens for
+/// `cooldown`. Once the cooldown elapses a single probe request is let
+/// through; its outcome decides whether the breaker closes or re-opens.
+pub struct CircuitBreaker {
+    failure_threshold: u32,
+    cooldown: Duration,
+    state: Mutex<BreakerState>,
+    clock: Arc<dyn Clock>,
+}
+
+impl CircuitBreaker {
+    pub fn new(config: &CircuitBreakerConfig, clock: Arc<dyn Clock>) -> Self {
+        Self {
+            failure_threshold: config.failure_threshold.max(1),
+            cooldown: Duration::from_millis(config.cooldown_ms),
+            state: Mutex::new(BreakerState::Closed {
+                consecutive_failures: 0,
+            }),
+            clock,
+        }
+    }
+
+    /// A closed breaker with the same settings, timed by `clock`
+    pub fn on_clock(&self, clock: Arc<dyn Clock>) -> Self {
+        Self {
+            failure_threshold: self.failure_threshold,
+            cooldown: self.cooldown,
+            state: Mutex::new(BreakerState::Closed {
+                consecutive_failures: 0,
+            }),
+            clock,
+        }
+    }
+
+    /// Wait until a new request is allowed through
+    pub async fn ready(&self) {
+        loop {
+            let wait = {
+                let mut state = self.state.lock().expect("breaker lock poisoned");
+                match *state {
+                    BreakerState::Closed { .. } => return,
+                    BreakerState::Open { until } => {
+                        let now = self.clock.now();
+                        if now >= until {
+                            info!("Proof circuit breaker half-open, sending probe request");
+                            *state = BreakerState::HalfOpen;
+                            return;
+                        }
+                        until - now
+                    }
+                    BreakerState::HalfOpen => PROBE_POLL_INTERVAL,
+                }
+            };
+            self.clock.sleep(wait).await;
+        }
+    }
+
+    pub fn record_success(&self) {
+        let mut state = self.state.lock().expect("breaker lock poisoned");
+        if !matches!(*state, BreakerState::Closed { .. }) {
+            info!("Proof circuit breaker closed");
+            PROOF_CIRCUIT_OPEN.set(0);
+        }
+        *state = BreakerState::Closed {
+            consecutive_failures: 0,
+        };
+    }
+
+    pub fn record_failure(&self) {
+        let mut state = self.state.lock().expect("breaker lock poisoned");
+        let trip = match *state {
+            BreakerState::Closed {
+                consecutive_failures,
+            } => {
+                let consecutive_failures = consecutive_failures + 1;
+                *state = BreakerState::Closed {
+                    consecutive_failures,
+                };
+                consecutive_failures >= self.failure_threshold
+            }
+            BreakerState::HalfOpen => true,
+            // Late failures from requests issued before the breaker opened
+            BreakerState::Open { .. } => false,
+        };
+
+        if trip {
+            error!(
+                cooldown_ms = self.cooldown.as_millis() as u64,
+                failure_threshold = self.failure_threshold,
+                "🚨 Proof circuit breaker opened, pausing proof requests"
+            );
+            PROOF_CIRCUIT_OPEN.set(1);
+            PROOF_CIRCUIT_TRIPS.inc();
+            *state = BreakerState::Open {
+                until: self.clock.now() + self.cooldown,
+            };
+        }
+    }
+}
@@ -1,9 +1,26 @@
 use anyhow::Result;
-use base64::{engine::general_purpose, Engine};
-use ethers::types::Bytes;
-use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION};
-use serde::{Deserialize, Serialize};
-use tracing::instrument;
+use ethers::types::H256;
+use reqwest::StatusCode;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::time::Duration;
+use tracing::{instrument, warn};
+
+use super::batch::{Answer, Coalescer};
+use super::endpoints::EndpointPool;
+use super::rate_limit::RateLimiter;
+use super::token::TokenSource;
+use crate::config::{PolymerApiConfig, ProofEncoding};
+use crate::decode::decode_proof;
+use crate::failure::FailureClass;
+use crate::metrics::PROOF_BATCH_SIZE;
+use crate::types::{ChainId, Proof, ProofMetadata, RelayerError};
+
+const MAX_REQUEST_ATTEMPTS: u32 = 3;
+const REQUEST_RETRY_DELAY: Duration = Duration::from_secs(2);
+const MAX_POLL_ATTEMPTS: u32 = 5;
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+const REQUEST_PROOF: &str = "log_requestProof";
+const QUERY_PROOF: &str = "log_queryProof";
 
 #[derive(Serialize)]
 struct RequestProofParams {
@@ -13,11 +30,6 @@ struct RequestProofParams {
     params: Vec<u64>,
 }
 
-#[derive(Deserialize)]
-struct RequestProofResponse {
-    result: i64,
-}
-
 #[derive(Serialize)]
 struct QueryProofParams {
     jsonrpc: String,
@@ -26,9 +38,22 @@ struct QueryProofParams {
     params: Vec<i64>,
 }
 
+// One call of a JSON-RPC batch, identified by its position in the batch
+#[derive(Serialize)]
+struct BatchCall<'a, P> {
+    jsonrpc: &'static str,
+    id: usize,
+    method: &'a str,
+    params: P,
+}
+
+// Batch responses may come in any order and may leave calls out
 #[derive(Deserialize)]
-struct QueryProofResponse {
-    result: QueryProofResult,
+struct BatchResponse<T> {
+    #[serde(default)]
+    id: Option<usize>,
+    result: Option<T>,
+    error: Option<JsonRpcError>,
 }
 
 #[derive(Deserialize)]
@@ -36,103 +61,386 @@ struct QueryProofResult {
     #[serde(default)]
     proof: String,
     status: String,
+    // Optional details reported by newer API versions
+    #[serde(default)]
+    proven_height: Option<u64>,
+    #[serde(default)]
+    receipt_root: Option<H256>,
+    #[serde(default)]
+    version: Option<String>,
+}
+
+// JSON-RPC response envelope, carrying either a result or an error object
+#[derive(Deserialize)]
+struct JsonRpcResponse<T> {
+    result: Option<T>,
+    error: Option<JsonRpcError>,
+}
+
+#[derive(Deserialize)]
+struct JsonRpcError {
+    code: i64,
+    message: String,
+}
+
+impl<T> JsonRpcResponse<T> {
+    fn into_result(self, method: &str) -> Result<T> {
+        if let Some(error) = self.error {
+            return Err(error.into_relayer_error(method).into());
+        }
+        self.result
+            .ok_or_else(|| anyhow::anyhow!("{} response has neither result nor error", method))
+    }
+}
+
+impl JsonRpcError {
+    fn into_relayer_error(self, method: &str) -> RelayerError {
+        let method = method.to_string();
+        let JsonRpcError { code, message } = self;
+        match code {
+            // Parse error, invalid request, method not found, invalid params:
+            // sending the same request again cannot succeed
+            -32700 | -32600 | -32601 | -32602 => RelayerError::ProofRequestRejected {
+                method,
+                code,
+                message,
+            },
+            // Internal and implementation-defined server errors
+            -32603 | -32099..=-32000 => RelayerError::ProofApiUnavailable {
+                method,
+                code,
+                message,
+            },
+            _ => RelayerError::ProofRequestRejected {
+                method,
+                code,
+                message,
+            },
+        }
+    }
+}
+
+fn parse_response<T: DeserializeOwned>(text: &str, method: &str) -> Result<T> {
+    let response: JsonRpcResponse<T> = serde_json::from_str(text)?;
+    response.into_result(method)
+}
+
+// Timeouts and refused connections are worth retrying; other transport errors are not
+fn transport_error(error: reqwest::Error, method: &str) -> anyhow::Error {
+    if error.is_timeout() || error.is_connect() {
+        RelayerError::ProofApiTimeout {
+            method: method.to_string(),
+            source: error.into(),
+        }
+        .into()
+    } else {
+        error.into()
+    }
+}
+
+fn is_retryable(error: &anyhow::Error) -> bool {
+    FailureClass::of(error) == FailureClass::Retryable
+}
+
+// Source log a proof is requested for: chain ID, block, transaction index
+// and log index, as `log_requestProof` takes them
+type LogLocation = [u64; 4];
+
+struct Batches {
+    requests: Coalescer<LogLocation, i64>,
+    queries: Coalescer<i64, QueryProofResult>,
 }
 
 pub struct ProofApiClient {
-    token: String,
-    endpoint: String,
+    http: reqwest::Client,
+    token: TokenSource,
+    endpoints: EndpointPool,
+    rate_limiter: Option<RateLimiter>,
+    proof_encoding: ProofEncoding,
+    batches: Option<Batches>,
 }
 
 impl ProofApiClient {
-    pub fn new(token: String, endpoint: String) -> Self {
-        Self { token, endpoint }
+    pub fn new(config: PolymerApiConfig) -> Result<Self> {
+        let http = reqwest::Client::builder()
+            .connect_timeout(Duration::from_millis(config.connect_timeout_ms))
+            .timeout(Duration::from_millis(config.request_timeout_ms))
+            .build()?;
+
+        Ok(Self {
+            http,
+            token: TokenSource::new(config.token, config.token_refresh),
+            endpoints: EndpointPool::new(config.endpoint, config.fallback_endpoints),
+            rate_limiter: config.requests_per_minute.map(RateLimiter::per_minute),
+            proof_encoding: config.proof_encoding,
+            batches: config.batch.map(|batch| Batches {
+                requests: Coalescer::new(&batch),
+                queries: Coalescer::new(&batch),
+            }),
+        })
     }
 
-    pub async fn fetch_proof(
-        &self,
-        chain_id: u64,
-        block_number: u64,
-        tx_index: u32,
-        log_index: u32,
-    ) -> Result<Bytes> {
-        let job_id = self
-            .request_proof(chain_id, block_number, tx_index, log_index)
-            .await?;
+    async fn throttle(&self) {
+        if let Some(rate_limiter) = &self.rate_limiter {
+            rate_limiter.acquire().await;
+        }
+    }
 
+    /// Poll a proof job until the proof is ready, returning it with the
+    /// number of polls it took
+    #[instrument(skip(self), fields(job_id = job_id))]
+    pub async fn wait_for_proof(&self, job_id: i64) -> Result<(Proof, u32)> {
         let mut attempts = 0;
         loop {
-            let result = self.query_proof(job_id).await?;
+            let result = match self.query_proof(job_id).await {
+                Ok(result) => result,
+                Err(e) if is_retryable(&e) && attempts < MAX_POLL_ATTEMPTS => {
+                    warn!(error = %e, attempts, "Proof query failed, will poll again");
+                    attempts += 1;
+                    tokio::time::sleep(POLL_INTERVAL).await;
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
             if result.status == "ready" || result.status == "complete" {
-                let proof_bytes = general_purpose::STANDARD.decode(&result.proof)?;
-                return Ok(Bytes::from(proof_bytes));
+                let proof = Proof {
+                    data: decode_proof(&result.proof, self.proof_encoding)?,
+                    metadata: ProofMetadata {
+                        proven_height: result.proven_height,
+                        receipt_root: result.receipt_root,
+                        format_version: result.version,
+                    },
+                };
+                return Ok((proof, attempts + 1));
             }
 
             attempts += 1;
-            if attempts > 5 {
-                return Err(anyhow::anyhow!("Timeout waiting for proof"));
+            if attempts > MAX_POLL_ATTEMPTS {
+                return Err(RelayerError::ProofTimeout { job_id, attempts }.into());
             }
 
-            tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
+            tokio::time::sleep(POLL_INTERVAL).await;
         }
     }
 
-    #[instrument(skip(self), fields(chain_id = chain_id, block_number = block_number, tx_index = tx_index, log_index = log_index))]
-    async fn request_proof(
+    /// Start a proof job for a source log, returning its job ID
+    ///
+    /// Errors the API reports as transient are retried a few times before
+    /// giving up; rejected requests fail immediately. With batching on, the
+    /// first attempt joins a batch call and retries go out on their own.
+    #[instrument(skip(self), fields(chain_id = chain_id.as_u64(), block_number = block_number, tx_index = tx_index, log_index = log_index))]
+    pub async fn request_proof(
         &self,
-        chain_id: u64,
+        chain_id: ChainId,
         block_number: u64,
         tx_index: u32,
         log_index: u32,
     ) -> Result<i64> {
-        let client = reqwest::Client::new();
-
-        let mut headers = HeaderMap::new();
-        headers.insert(
-            AUTHORIZATION,
-            HeaderValue::from_str(&format!("Bearer {}", self.token))?,
-        );
+        let log = [
+            chain_id.as_u64(),
+            block_number,
+            u64::from(tx_index),
+            u64::from(log_index),
+        ];
+        let mut attempt = 1;
+        loop {
+            let batched = match &self.batches {
+                Some(batches) if attempt == 1 => {
+                    batches
+                        .requests
+                        .call(log, |logs| self.send_batch(REQUEST_PROOF, logs, true))
+                        .await
+                }
+                _ => None,
+            };
+            let result = match batched {
+                Some(result) => result,
+                None => self.send_request_proof(log).await,
+            };
+            match result {
+                Err(e) if is_retryable(&e) && attempt < MAX_REQUEST_ATTEMPTS => {
+                    warn!(error = %e, attempt, "Proof request failed, retrying");
+                    attempt += 1;
+                    tokio::time::sleep(REQUEST_RETRY_DELAY).await;
+                }
+                result => return result,
+            }
+        }
+    }
 
+    async fn send_request_proof(&self, log: LogLocation) -> Result<i64> {
         let params = RequestProofParams {
             jsonrpc: "2.0".to_string(),
             id: 1,
-            method: "log_requestProof".to_string(),
-            params: vec![
-                chain_id as u64,
-                block_number,
-                tx_index as u64,
-                log_index as u64,
-            ],
+            method: REQUEST_PROOF.to_string(),
+            params: log.to_vec(),
+        };
+
+        let response = self.post_authorized(&params.method, &params).await?;
+
+        let text = response
+            .text()
+            .await
+            .map_err(|e| transport_error(e, REQUEST_PROOF))?;
+        tracing::info!(response = %text, method = REQUEST_PROOF, "Raw proof response");
+        parse_response(&text, REQUEST_PROOF)
+    }
+
+    /// Call `method` once for each of `params` in a single JSON-RPC batch,
+    /// answering the calls in order
+    async fn send_batch<P: Serialize, T: DeserializeOwned>(
+        &self,
+        method: &str,
+        params: Vec<P>,
+        authorized: bool,
+    ) -> Result<Vec<Answer<T>>> {
+        let size = params.len();
+        PROOF_BATCH_SIZE
+            .with_label_values(&[method])
+            .observe(size as f64);
+        let calls: Vec<_> = params
+            .into_iter()
+            .enumerate()
+            .map(|(id, params)| BatchCall {
+                jsonrpc: "2.0",
+                id,
+                method,
+                params,
+            })
+            .collect();
+
+        let response = if authorized {
+            self.post_authorized(method, &calls).await?
+        } else {
+            self.post(method, &calls, None).await?
         };
 
-        let response = client
-            .post(&self.endpoint)
-            .headers(headers)
-            .json(&params)
-            .send()
-            .await?;
-
-        let text = response.text().await?;
-        tracing::info!(response = %text, method = "log_requestProof", "Raw proof response");
-        let proof_response: RequestProofResponse = serde_json::from_str(&text)?;
-        Ok(proof_response.result)
+        let text = response
+            .text()
+            .await
+            .map_err(|e| transport_error(e, method))?;
+        tracing::info!(response = %text, method, size, "Raw batch response");
+        let responses: Vec<BatchResponse<T>> = serde_json::from_str(&text)?;
+        let mut answers: Vec<Answer<T>> = (0..size).map(|_| None).collect();
+        for response in responses {
+            let Some(answer) = response.id.and_then(|id| answers.get_mut(id)) else {
+                continue;
+            };
+            let response = JsonRpcResponse {
+                result: response.result,
+                error: response.error,
+            };
+            *answer = Some(response.into_result(method));
+        }
+        Ok(answers)
+    }
+
+    /// Send a call that needs the API token, refreshing an expired token once
+    /// and replaying the call with the new one
+    async fn post_authorized<P: Serialize>(
+        &self,
+        method: &str,
+        params: &P,
+    ) -> Result<reqwest::Response> {
+        let token = self.token.current();
+        let mut response = self.post(method, params, Some(&token)).await?;
+
+        if response.status() == StatusCode::UNAUTHORIZED && self.token.can_refresh() {
+            warn!("Proof API rejected token, refreshing");
+            self.token.refresh(&token).await?;
+            response = self
+                .post(method, params, Some(&self.token.current()))
+                .await?;
+        }
+        if response.status() == StatusCode::UNAUTHORIZED {
+            return Err(anyhow::anyhow!("Proof API rejected the configured token"));
+        }
+        Ok(response)
+    }
+
+    /// Send a JSON-RPC call, failing over to the next healthiest endpoint on
+    /// connection errors, timeouts, and 5xx responses
+    async fn post<P: Serialize>(
+        &self,
+        method: &str,
+        params: &P,
+        token: Option<&str>,
+    ) -> Result<reqwest::Response> {
+        let mut last_error = None;
+
+        for endpoint in self.endpoints.ordered() {
+            self.throttle().await;
+
+            let mut request = self.http.post(endpoint.url()).json(params);
+            if let Some(token) = token {
+                request = request.bearer_auth(token);
+            }
+
+            let error = match request.send().await {
+                Ok(response) if response.status().is_server_error() => {
+                    let status = response.status();
+                    RelayerError::ProofApiUnavailable {
+                        method: method.to_string(),
+                        code: i64::from(status.as_u16()),
+                        message: format!("HTTP {}", status),
+                    }
+                    .into()
+                }
+                Ok(response) => {
+                    endpoint.record_success();
+                    return Ok(response);
+                }
+                Err(e) => {
+                    let error = transport_error(e, method);
+                    if !is_retryable(&error) {
+                        return Err(error);
+                    }
+                    error
+                }
+            };
+
+            endpoint.record_failure();
+            warn!(
+                endpoint = endpoint.url(),
+                method,
+                error = %error,
+                "Proof API endpoint failed, trying next"
+            );
+            last_error = Some(error);
+        }
+
+        Err(last_error.expect("endpoint pool always holds the primary endpoint"))
     }
 
     #[instrument(skip(self), fields(job_id = job_id))]
     async fn query_proof(&self, job_id: i64) -> Result<QueryProofResult> {
-        let client = reqwest::Client::new();
+        if let Some(batches) = &self.batches {
+            let batched = batches
+                .queries
+                .call(job_id, |jobs| {
+                    let params = jobs.into_iter().map(|job_id| [job_id]).collect();
+                    self.send_batch(QUERY_PROOF, params, false)
+                })
+                .await;
+            if let Some(result) = batched {
+                return result;
+            }
+        }
 
         let params = QueryProofParams {
             jsonrpc: "2.0".to_string(),
             id: 1,
-            method: "log_queryProof".to_string(),
+            method: QUERY_PROOF.to_string(),
             params: vec![job_id],
         };
 
-        let response = client.post(&self.endpoint).json(&params).send().await?;
+        let response = self.post(&params.method, &params, None).await?;
 
-        let text = response.text().await?;
-        tracing::info!(response = %text, method = "log_queryProof", "Raw query response");
-        let proof_response: QueryProofResponse = serde_json::from_str(&text)?;
-        Ok(proof_response.result)
+        let text = response
+            .text()
+            .await
+            .map_err(|e| transport_error(e, QUERY_PROOF))?;
+        tracing::info!(response = %text, method = QUERY_PROOF, "Raw query response");
+        parse_response(&text, QUERY_PROOF)
     }
 }
@@ -0,0 +1,71 @@
+use std::sync::Mutex;
+
+use crate::metrics::PROOF_ENDPOINT_HEALTH;
+
+// Weight kept from the previous score on every update
+const SCORE_DECAY: f64 = 0.8;
+
+/// A proof API endpoint with a health score between 0.0 and 1.0
+pub struct Endpoint {
+    url: String,
+    score: Mutex<f64>,
+}
+
+impl Endpoint {
+    fn new(url: String) -> Self {
+        PROOF_ENDPOINT_HEALTH.with_label_values(&[&url]).set(1.0);
+        Self {
+            url,
+            score: Mutex::new(1.0),
+        }
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    fn score(&self) -> f64 {
+        *self.score.lock().expect("endpoint score lock poisoned")
+    }
+
+    fn update(&self, outcome: f64) {
+        let mut score = self.score.lock().expect("endpoint score lock poisoned");
+        *score = *score * SCORE_DECAY + outcome * (1.0 - SCORE_DECAY);
+        PROOF_ENDPOINT_HEALTH
+            .with_label_values(&[&self.url])
+            .set(*score);
+    }
+
+    pub fn record_success(&self) {
+        self.update(1.0);
+    }
+
+    pub fn record_failure(&self) {
+        self.update(0.0);
+    }
+}
+
+/// Primary and fallback proof API endpoints, tried healthiest first
+///
+/// Endpoints are expected to be replicas sharing job state, so a job
+/// requested through one can be queried through another.
+pub struct EndpointPool {
+    endpoints: Vec<Endpoint>,
+}
+
+impl EndpointPool {
+    pub fn new(primary: String, fallbacks: Vec<String>) -> Self {
+        let endpoints = std::iter::once(primary)
+            .chain(fallbacks)
+            .map(Endpoint::new)
+            .collect();
+        Self { endpoints }
+    }
+
+    /// Endpoints in the order they should be tried; ties keep config order
+    pub fn ordered(&self) -> Vec<&Endpoint> {
+        let mut endpoints: Vec<&Endpoint> = self.endpoints.iter().collect();
+        endpoints.sort_by(|a, b| b.score().total_cmp(&a.score()));
+        endpoints
+    }
+}
@@ -0,0 +1,102 @@
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use ethers::{
+    abi::{self, Token},
+    core::types::H256,
+    providers::{Http, Middleware, Provider},
+    utils::keccak256,
+};
+use tracing::{info, instrument};
+
+use super::provider::ProofProvider;
+use crate::decode::{L2_TO_L2_CROSS_DOMAIN_MESSENGER, SENT_MESSAGE};
+use crate::topology::Topology;
+use crate::types::{EventMeta, Proof, ProofMetadata};
+
+/// Stand-in proof provider for pairs relayed over OP interop
+///
+/// A native message needs no proof: the destination's messenger checks it
+/// against the source chain itself. What it needs instead is the message's
+/// identifier, read here off the `SentMessage` log and its block, which
+/// becomes the "proof" as the ABI-encoded arguments of `relayMessage`.
+pub struct InteropProofProvider {
+    topology: Topology,
+}
+
+impl InteropProofProvider {
+    pub fn new(topology: Topology) -> Self {
+        Self { topology }
+    }
+}
+
+#[async_trait]
+impl ProofProvider for InteropProofProvider {
+    #[instrument(skip(self), fields(
+        chain_id = meta.chain_id.as_u64(),
+        block_number = meta.block_number,
+        tx_hash = ?meta.tx_hash
+    ))]
+    async fn prove(&self, meta: &EventMeta) -> Result<Proof> {
+        let chain = self
+            .topology
+            .chain(meta.chain_id)
+            .ok_or_else(|| anyhow!("Source chain {} not found in config", meta.chain_id))?;
+        let tx_hash = meta
+            .tx_hash
+            .ok_or_else(|| anyhow!("Interop messages are located by their transaction hash"))?;
+        let provider = Provider::<Http>::try_from(&chain.rpc_url)
+            .context(format!("Failed to create provider for {}", chain.name))?;
+
+        let receipt = provider
+            .get_transaction_receipt(tx_hash)
+            .await?
+            .ok_or_else(|| anyhow!("Transaction receipt not found"))?;
+        let log = receipt
+            .logs
+            .iter()
+            .find(|log| log.log_index == Some(meta.log_index.into()))
+            .ok_or_else(|| anyhow!("Log {} not found in transaction", meta.log_index))?;
+        let sent_message = H256::from(keccak256(SENT_MESSAGE));
+        if log.address != L2_TO_L2_CROSS_DOMAIN_MESSENGER
+            || log.topics.first() != Some(&sent_message)
+        {
+            return Err(anyhow!(
+                "Log {} is not a SentMessage from the L2-to-L2 messenger",
+                meta.log_index
+            ));
+        }
+        let block = provider
+            .get_block(meta.block_number)
+            .await?
+            .ok_or_else(|| anyhow!("Block {} not found", meta.block_number))?;
+
+        // The messenger takes the log as its topics followed by its data
+        let mut message: Vec<u8> = log
+            .topics
+            .iter()
+            .flat_map(|topic| topic.as_bytes().to_vec())
+            .collect();
+        message.extend_from_slice(&log.data);
+        let data = abi::encode(&[
+            Token::Tuple(vec![
+                Token::Address(log.address),
+                Token::Uint(meta.block_number.into()),
+                Token::Uint(meta.log_index.into()),
+                Token::Uint(block.timestamp),
+                Token::Uint(meta.chain_id.into()),
+            ]),
+            Token::Bytes(message),
+        ]);
+
+        info!("Built interop message identifier");
+
+        Ok(Proof {
+            data: data.into(),
+            metadata: ProofMetadata {
+                proven_height: Some(meta.block_number),
+                receipt_root: None,
+                format_version: Some("op-interop".to_string()),
+            },
+        })
+    }
+}
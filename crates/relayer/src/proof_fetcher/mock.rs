@@ -0,0 +1,114 @@
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use ethers::{
+    abi::{self, ParamType, Token},
+    core::types::{Bytes, H256, U256},
+    utils::keccak256,
+};
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+use tracing::{info, instrument};
+
+use super::provider::{LogIdentifier, ProofProvider};
+use crate::config::MockProofConfig;
+use crate::types::{ChainId, EventMeta, Proof, ProofMetadata};
+
+/// Proof provider for local development that never talks to the Polymer API
+///
+/// Proofs are deterministic for a given source log. Failures are drawn from a
+/// deterministic sequence seeded by the log and attempt number, so a run can be
+/// reproduced exactly.
+pub struct MockProofProvider {
+    config: MockProofConfig,
+    attempts: AtomicU64,
+}
+
+impl MockProofProvider {
+    pub fn new(config: MockProofConfig) -> Self {
+        Self {
+            config,
+            attempts: AtomicU64::new(0),
+        }
+    }
+
+    fn encode_location(meta: &EventMeta) -> Vec<u8> {
+        abi::encode(&[
+            Token::Uint(U256::from(meta.chain_id)),
+            Token::Uint(U256::from(meta.block_number)),
+            Token::Uint(U256::from(meta.tx_index)),
+            Token::Uint(U256::from(meta.log_index)),
+        ])
+    }
+
+    fn should_fail(&self, meta: &EventMeta) -> bool {
+        if self.config.failure_rate <= 0.0 {
+            return false;
+        }
+        let attempt = self.attempts.fetch_add(1, Ordering::Relaxed);
+        let mut seed = Self::encode_location(meta);
+        seed.extend_from_slice(&attempt.to_be_bytes());
+        let hash = keccak256(seed);
+        let sample = u64::from_be_bytes(hash[..8].try_into().expect("hash is 32 bytes"));
+        (sample as f64 / u64::MAX as f64) < self.config.failure_rate
+    }
+}
+
+#[async_trait]
+impl ProofProvider for MockProofProvider {
+    #[instrument(skip(self), fields(
+        chain_id = meta.chain_id.as_u64(),
+        block_number = meta.block_number,
+        tx_hash = ?meta.tx_hash
+    ))]
+    async fn prove(&self, meta: &EventMeta) -> Result<Proof> {
+        tokio::time::sleep(Duration::from_millis(self.config.latency_ms)).await;
+
+        if self.should_fail(meta) {
+            return Err(anyhow!("Mock proof provider injected failure"));
+        }
+
+        // The dummy proof is the log location followed by its hash
+        let location = Self::encode_location(meta);
+        let digest = keccak256(&location);
+        let data = Bytes::from([location.as_slice(), digest.as_slice()].concat());
+
+        info!("Generated mock proof");
+
+        Ok(Proof {
+            data,
+            metadata: ProofMetadata {
+                proven_height: Some(meta.block_number),
+                receipt_root: Some(H256::from(digest)),
+                format_version: Some("mock-v1".to_string()),
+            },
+        })
+    }
+
+    fn inspect(&self, proof: &Proof) -> Result<Option<LogIdentifier>> {
+        let location = proof
+            .data
+            .get(..128)
+            .ok_or_else(|| anyhow!("Mock proof is too short"))?;
+        let tokens = abi::decode(
+            &[ParamType::Uint(64), ParamType::Uint(64), ParamType::Uint(32), ParamType::Uint(32)],
+            location,
+        )?;
+        // Words are decoded unchecked, so out of range values are possible
+        let word = |i: usize| -> Result<u64> {
+            tokens[i]
+                .clone()
+                .into_uint()
+                .and_then(|word| u64::try_from(word).ok())
+                .ok_or_else(|| anyhow!("Malformed mock proof"))
+        };
+
+        Ok(Some(LogIdentifier {
+            chain_id: ChainId::new(word(0)?),
+            block_number: word(1)?,
+            receipt_index: u32::try_from(word(2)?)?,
+            log_index: u32::try_from(word(3)?)?,
+        }))
+    }
+}
@@ -1,108 +1,620 @@
+mod batch;
+mod breaker;
 mod client;
+mod endpoints;
+mod interop;
+mod mock;
+mod polymer;
+mod provider;
+mod rate_limit;
+mod token;
 
-use self::client::ProofApiClient;
-use crate::types::{DeliveryRequest, ProofRequest, RelayEvent};
+pub use self::interop::InteropProofProvider;
+pub use self::mock::MockProofProvider;
+pub use self::polymer::PolymerProofProvider;
+pub use self::provider::{LogIdentifier, ProofProvider};
+
+use self::breaker::CircuitBreaker;
+use crate::alerts::{self, AlertKind};
+use crate::audit::{self, AuditAction};
+use crate::backpressure;
+use crate::clock::{Clock, SystemClock};
+use crate::config::{DeliveryMode, ProofFetcherConfig, RelayMode};
+use crate::failure::FailureClass;
+use crate::health::{Health, EVENT_DELIVERER, PROOF_FETCHER};
+use crate::hooks::Hooks;
+use crate::journal::{Journal, JournalStage};
+use crate::latency;
+use crate::metrics::{PairLabels, DEAD_LETTERS, PROOF_DURATION, PROOF_RESULTS, QUEUE_SPILLED};
+use crate::priority::PriorityQueue;
+use crate::status::{self, EventStatus};
+use crate::store::{FailedDelivery, ProofKey, StateStore};
+use crate::telemetry;
+use crate::topology::Topology;
+use crate::types::{DeliveryRequest, Proof, ProofRequest, RelayEvent, RelayerError};
 use anyhow::Result;
-use ethers::core::types::Bytes;
-use tokio::{sync::mpsc};
-use tracing::{error, info, instrument};
+use std::{
+    collections::HashSet,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+use tokio::{
+    sync::{broadcast, mpsc, mpsc::error::TrySendError, OwnedSemaphorePermit, Semaphore},
+    task::JoinSet,
+    time,
+};
+use tracing::{error, info, info_span, instrument, warn, Instrument};
 
 pub struct ProofFetcher {
     event_rx: mpsc::Receiver<RelayEvent>,
     delivery_tx: mpsc::Sender<DeliveryRequest>,
-    polymer_api_url: String,
-    api_token: String,
+    provider: Arc<dyn ProofProvider>,
+    store: Arc<dyn StateStore>,
+    breaker: Arc<CircuitBreaker>,
+    fetch_permits: Arc<Semaphore>,
+    validate_proofs: bool,
+    /// Park proven events in the store while the delivery queue is full
+    spill_to_store: bool,
+    /// Proven events currently parked in the store
+    spilled: Arc<AtomicUsize>,
+    health: Health,
+    /// Copies of every event taken in, for subscribers
+    detected: broadcast::Sender<RelayEvent>,
+    /// Copies of every proven event, for subscribers
+    proven: broadcast::Sender<DeliveryRequest>,
+    hooks: Hooks,
+    journal: Journal,
+    routes: Option<PairRoutes>,
+}
+
+// Events of `op_interop` pairs are handed to `interop` rather than the proof
+// backend, and those of `ccip_read` pairs are kept for the gateway once
+// proven rather than delivered
+struct PairRoutes {
+    topology: Topology,
+    interop: Arc<dyn ProofProvider>,
 }
 
+// Stage outputs buffered for each subscriber before it starts lagging
+pub(crate) const TAP_CAPACITY: usize = 256;
+
+// How often parked events are moved back into the delivery queue
+const REFILL_INTERVAL: Duration = Duration::from_secs(1);
+// Events taken off the queue to wait for a fetch slot, ordered by priority
+const PRIORITY_LOOKAHEAD: usize = 100;
+
 impl ProofFetcher {
     pub fn new(
         event_rx: mpsc::Receiver<RelayEvent>,
         delivery_tx: mpsc::Sender<DeliveryRequest>,
-        polymer_api_url: String,
-        api_token: String,
+        provider: Arc<dyn ProofProvider>,
+        store: Arc<dyn StateStore>,
+        config: ProofFetcherConfig,
+        spill_to_store: bool,
+        health: Health,
     ) -> Self {
+        let breaker = CircuitBreaker::new(&config.circuit_breaker, Arc::new(SystemClock));
         Self {
             event_rx,
             delivery_tx,
-            polymer_api_url,
-            api_token,
+            provider,
+            store,
+            breaker: Arc::new(breaker),
+            fetch_permits: Arc::new(Semaphore::new(config.max_concurrent_fetches.max(1))),
+            validate_proofs: config.validate_proofs,
+            spill_to_store,
+            spilled: Arc::new(AtomicUsize::new(0)),
+            health,
+            detected: broadcast::channel(TAP_CAPACITY).0,
+            proven: broadcast::channel(TAP_CAPACITY).0,
+            hooks: Hooks::default(),
+            journal: Journal::default(),
+            routes: None,
         }
     }
 
+    // Publish events taken in and proven to the subscribers of an earlier
+    // fetcher, so they keep receiving across a restart
+    pub(crate) fn with_taps(
+        mut self,
+        detected: broadcast::Sender<RelayEvent>,
+        proven: broadcast::Sender<DeliveryRequest>,
+    ) -> Self {
+        self.detected = detected;
+        self.proven = proven;
+        self
+    }
+
+    pub(crate) fn with_hooks(mut self, hooks: Hooks) -> Self {
+        self.hooks = hooks;
+        self
+    }
+
+    pub(crate) fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.breaker = Arc::new(self.breaker.on_clock(clock));
+        self
+    }
+
+    pub(crate) fn with_journal(mut self, journal: Journal) -> Self {
+        self.journal = journal;
+        self
+    }
+
+    pub(crate) fn with_topology(mut self, topology: Topology) -> Self {
+        self.routes = Some(PairRoutes {
+            interop: Arc::new(InteropProofProvider::new(topology.clone())),
+            topology,
+        });
+        self
+    }
+
     #[instrument(skip(self), name = "proof_fetcher_start")]
     pub async fn start(&mut self) -> Result<()> {
         info!("Starting proof fetcher");
+        self.health.beat(PROOF_FETCHER);
+
+        let mut tasks = JoinSet::new();
+
+        // Pick up events a previous run accepted but never delivered; their
+        // proof jobs are resumed from the store rather than requested again.
+        // Failed deliveries are left to the deliverer's retry queue, and
+        // events already proven wait in the spill for room to be delivered.
+        let spilled: HashSet<String> = self
+            .store
+            .spilled_deliveries()?
+            .iter()
+            .map(|request| ProofKey::from_meta(&request.event.meta).to_string())
+            .collect();
+        self.spilled.store(spilled.len(), Ordering::Relaxed);
+        let mut resumed = self.store.pending_events()?;
+        resumed.retain(|event| {
+            let key = ProofKey::from_meta(&event.meta);
+            !spilled.contains(&key.to_string()) && !matches!(self.store.retry(&key), Ok(Some(_)))
+        });
+        if !resumed.is_empty() {
+            info!(count = resumed.len(), "Resuming pending events from previous run");
+        }
+
+        // Events wait here for a fetch slot, so the most urgent goes first
+        let mut waiting = PriorityQueue::new();
+        for event in resumed {
+            waiting.push(event.priority, event);
+        }
+
+        let fetch_permits = self.fetch_permits.clone();
+        let breaker = self.breaker.clone();
+        let mut refill = time::interval(REFILL_INTERVAL);
+        let mut open = true;
+        while open || !waiting.is_empty() {
+            tokio::select! {
+                event = self.event_rx.recv(), if open && waiting.len() < PRIORITY_LOOKAHEAD => {
+                    let Some(event) = event else {
+                        open = false;
+                        continue;
+                    };
+                    self.health.beat(PROOF_FETCHER);
+                    Self::reap_finished(&mut tasks);
 
-        while let Some(event) = self.event_rx.recv().await {
-            let tx_hash = match event.meta.tx_hash {
-                Some(hash) => hash,
-                None => {
-                    error!("Event missing transaction hash");
-                    continue;
+                    if let Err(e) = self
+                        .store
+                        .save_pending_event(&ProofKey::from_meta(&event.meta), &event)
+                    {
+                        warn!(error = %e, event_id = %event.id(), "Failed to persist pending event");
+                    }
+                    if self.detected.receiver_count() > 0 {
+                        let _ = self.detected.send(event.clone());
+                    }
+                    waiting.push(event.priority, event);
+                }
+                permit = Self::fetch_slot(&fetch_permits, &breaker), if !waiting.is_empty() => {
+                    let event = waiting.pop().expect("waiting events are not empty");
+                    self.dispatch(event, permit?, &mut tasks);
                 }
-            };
-
-            let proof_request = ProofRequest {
-                event: event.clone(),
-                tx_hash,
-                destination_chain_id: event.destination_chain.chain_id,
-                dest_contract_address: event.dest_dapp_address.clone(),
-            };
-
-            // Process proof request in a separate task
-            let delivery_tx = self.delivery_tx.clone();
-            let polymer_api_url = self.polymer_api_url.clone();
-            let api_token = self.api_token.clone();
-
-            tokio::spawn(async move {
-                match Self::fetch_proof(proof_request.clone(), polymer_api_url, api_token).await {
-                    Ok(proof) => {
-                        let delivery_request = DeliveryRequest {
-                            event,
-                            proof,
-                            destination_chain_id: proof_request.destination_chain_id,
-                            destination_contract_address: proof_request.dest_contract_address,
-                        };
-
-                        if let Err(e) = delivery_tx.send(delivery_request).await {
-                            error!(error = %e, "Failed to send delivery request");
+                _ = refill.tick() => self.refill(),
+            }
+        }
+
+        info!(in_flight = tasks.len(), "Event channel closed, waiting for in-flight proof fetches");
+        while let Some(result) = tasks.join_next().await {
+            if let Err(e) = result {
+                error!(error = %e, "Proof fetch task failed");
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Wait until the concurrency limit and breaker allow another fetch
+    ///
+    /// The breaker is checked last, as it may let a single probe through and
+    /// that must not be lost to a cancelled wait for a slot.
+    async fn fetch_slot(
+        permits: &Arc<Semaphore>,
+        breaker: &CircuitBreaker,
+    ) -> Result<OwnedSemaphorePermit> {
+        let permit = permits.clone().acquire_owned().await?;
+        // Hold pending events while the proving backend is down
+        breaker.ready().await;
+        Ok(permit)
+    }
+
+    /// Start fetching the proof for an event in a slot of its own
+    // Provider for an event's pair: the interop one for `op_interop` pairs,
+    // the proof backend for the rest
+    fn provider_for(&self, event: &RelayEvent) -> Arc<dyn ProofProvider> {
+        match &self.routes {
+            Some(routes)
+                if routes
+                    .topology
+                    .pair_for(event)
+                    .is_some_and(|pair| pair.relay_mode == RelayMode::OpInterop) =>
+            {
+                routes.interop.clone()
+            }
+            _ => self.provider.clone(),
+        }
+    }
+
+    // Whether an event's pair leaves delivery to CCIP-Read callers
+    fn published(&self, event: &RelayEvent) -> bool {
+        self.routes.as_ref().is_some_and(|routes| {
+            routes
+                .topology
+                .pair_for(event)
+                .is_some_and(|pair| pair.delivery_mode == DeliveryMode::CcipRead)
+        })
+    }
+
+    fn dispatch(&self, event: RelayEvent, permit: OwnedSemaphorePermit, tasks: &mut JoinSet<()>) {
+        let tx_hash = match event.meta.tx_hash {
+            Some(hash) => hash,
+            None => {
+                error!(event_id = %event.id(), "Event missing transaction hash");
+                return;
+            }
+        };
+
+        let proof_request = ProofRequest {
+            event: event.clone(),
+            tx_hash,
+            destination_chain_id: event.destination_chain.chain_id,
+            dest_contract_address: event.dest_dapp_address,
+        };
+
+        // Process proof request in a separate task
+        let delivery_tx = self.delivery_tx.clone();
+        let provider = self.provider_for(&event);
+        let published = self.published(&event);
+        let breaker = self.breaker.clone();
+        let store = self.store.clone();
+        let validate_proofs = self.validate_proofs;
+        let spill_to_store = self.spill_to_store;
+        let spilled = self.spilled.clone();
+        let proven = self.proven.clone();
+        let hooks = self.hooks.clone();
+        let journal = self.journal.clone();
+        let health = self.health.clone();
+        let span = info_span!(
+            "proof_fetch",
+            stage = "proof",
+            event_id = %event.id(),
+            chain_id = event.source_chain.chain_id.as_u64(),
+            pair = %event.pair_id(),
+            nonce = event.nonce,
+            tx_hash = ?tx_hash,
+            source_chain = %event.source_chain.name
+        );
+        telemetry::attach(&span, &event.trace_context);
+
+        let fetch = async move {
+            let _permit = permit;
+            if Self::already_delivered(&*store, &journal, &event) {
+                return;
+            }
+            if let Err(e) = hooks.event_detected(&event).await {
+                warn!(error = %e, "Event vetoed before proving");
+                Self::dead_letter(&*store, &journal, &event, &e);
+                return;
+            }
+            status::advance(&*store, &event, EventStatus::Proving);
+            audit::record(AuditAction::proof_requested(&event));
+            match Self::fetch_proof(proof_request.clone(), provider, validate_proofs).await {
+                Ok(proof) => {
+                    breaker.record_success();
+                    alerts::record_proof_result(&event.source_chain.name, true);
+                    let delivery_request = DeliveryRequest {
+                        event,
+                        proof,
+                        destination_chain_id: proof_request.destination_chain_id,
+                        destination_contract_address: proof_request.dest_contract_address,
+                    };
+                    if let Err(e) = hooks.proof_fetched(&delivery_request).await {
+                        warn!(error = %e, "Event vetoed before delivery");
+                        Self::dead_letter(&*store, &journal, &delivery_request.event, &e);
+                        return;
+                    }
+                    let key = ProofKey::from_meta(&delivery_request.event.meta);
+                    journal.advanced(&key, JournalStage::Proven);
+                    status::advance(&*store, &delivery_request.event, EventStatus::Proved);
+                    audit::record(AuditAction::proved(
+                        &delivery_request.event,
+                        Self::proof_job(&*store, &key),
+                    ));
+                    health.event_proven();
+                    if proven.receiver_count() > 0 {
+                        let _ = proven.send(delivery_request.clone());
+                    }
+                    if published {
+                        Self::publish(&*store, &journal, &delivery_request);
+                        return;
+                    }
+
+                    if spill_to_store {
+                        match delivery_tx.try_send(delivery_request) {
+                            Ok(()) => {}
+                            Err(TrySendError::Full(request)) => {
+                                Self::spill(&*store, &request, &spilled)
+                            }
+                            Err(TrySendError::Closed(_)) => {
+                                error!("Failed to send delivery request, channel closed")
+                            }
                         }
+                    } else if let Err(e) =
+                        backpressure::send(EVENT_DELIVERER, &delivery_tx, delivery_request).await
+                    {
+                        error!(error = %e, "Failed to send delivery request");
                     }
-                    Err(e) => {
-                        error!(error = %e, "Failed to fetch proof");
+                }
+                Err(e) => {
+                    // A bad proof says nothing about the backend's availability
+                    if !matches!(
+                        e.downcast_ref::<RelayerError>(),
+                        Some(RelayerError::ProofVerification(_))
+                    ) {
+                        breaker.record_failure();
+                    }
+                    error!(error = %e, "Failed to fetch proof");
+                    alerts::record_proof_result(&event.source_chain.name, false);
+                    audit::record(AuditAction::proof_failed(&event, &e));
+                    // Transient failures leave the event pending for the next run
+                    if FailureClass::of(&e) == FailureClass::Fatal {
+                        Self::dead_letter(&*store, &journal, &event, &e);
                     }
                 }
-            });
+            }
+        };
+        tasks.spawn(fetch.instrument(span));
+    }
+
+    // Park a proven event until the delivery queue has room; it stays pending,
+    // so the next run picks it up should this one stop first
+    fn spill(store: &dyn StateStore, request: &DeliveryRequest, spilled: &AtomicUsize) {
+        let key = ProofKey::from_meta(&request.event.meta);
+        if let Err(e) = store.spill_delivery(&key, request) {
+            warn!(error = %e, proof_key = %key, "Failed to spill delivery request");
+            return;
         }
+        spilled.fetch_add(1, Ordering::Relaxed);
+        QUEUE_SPILLED.with_label_values(&[EVENT_DELIVERER]).inc();
+        info!(proof_key = %key, "Delivery queue full, spilled request to the store");
+    }
 
-        Ok(())
+    // Move parked events back into the delivery queue while it has room
+    fn refill(&self) {
+        if self.spilled.load(Ordering::Relaxed) == 0 {
+            return;
+        }
+        let requests = match self.store.spilled_deliveries() {
+            Ok(requests) => requests,
+            Err(e) => {
+                warn!(error = %e, "Failed to read spilled delivery requests");
+                return;
+            }
+        };
+        for request in requests {
+            let key = ProofKey::from_meta(&request.event.meta);
+            let event_id = request.event.id();
+            if let Err(e) = self.delivery_tx.try_send(request) {
+                if matches!(e, TrySendError::Closed(_)) {
+                    error!(%event_id, "Failed to send delivery request, channel closed");
+                }
+                return;
+            }
+            if let Err(e) = self.store.remove_spilled_delivery(&key) {
+                warn!(
+                    error = %e,
+                    %event_id,
+                    proof_key = %key,
+                    "Failed to clear spilled delivery request"
+                );
+            }
+            self.spilled.fetch_sub(1, Ordering::Relaxed);
+        }
+    }
+
+    // Keep a proven event's proof for the CCIP-Read gateway to serve; its
+    // callers deliver it, so the pipeline is done with the event. Should the
+    // proof not be stored, the event stays pending for the next run.
+    fn publish(store: &dyn StateStore, journal: &Journal, request: &DeliveryRequest) {
+        let key = ProofKey::from_meta(&request.event.meta);
+        // Providers other than Polymer's keep no proof job to attach it to
+        let stored = match store.proof(&key) {
+            Ok(Some(_)) => Ok(()),
+            Ok(None) => store.save_proof_job(&key, 0),
+            Err(e) => Err(e),
+        }
+        .and_then(|()| store.save_proof(&key, &request.proof));
+        if let Err(e) = stored {
+            warn!(error = %e, proof_key = %key, "Failed to store proof for the CCIP-Read gateway");
+            return;
+        }
+        if let Err(e) = store.remove_pending_event(&key) {
+            warn!(error = %e, proof_key = %key, "Failed to clear pending event");
+        }
+        journal.advanced(&key, JournalStage::Published);
+        latency::forget(&request.event);
+        info!(proof_key = %key, "Proof published for CCIP-Read callers");
     }
 
-    #[instrument(skip(polymer_api_url, api_token), fields(
+    // Polymer proof job a proof was fetched with, if the provider kept one
+    fn proof_job(store: &dyn StateStore, key: &ProofKey) -> Option<i64> {
+        match store.proof(key) {
+            Ok(record) => record
+                .map(|record| record.job_id)
+                .filter(|job_id| *job_id != 0),
+            Err(e) => {
+                warn!(error = %e, proof_key = %key, "Failed to read proof job");
+                None
+            }
+        }
+    }
+
+    // Drop an event whose nonce the delivery ledger shows delivered, say by
+    // another instance or before a replay
+    fn already_delivered(store: &dyn StateStore, journal: &Journal, event: &RelayEvent) -> bool {
+        let delivered = match store.ledger_entry(&event.pair_id(), event.nonce) {
+            Ok(entry) => entry.is_some_and(|entry| entry.delivered),
+            Err(e) => {
+                warn!(error = %e, "Failed to check delivery ledger");
+                false
+            }
+        };
+        if delivered {
+            let key = ProofKey::from_meta(&event.meta);
+            info!(proof_key = %key, "Nonce already delivered, skipping proof");
+            if let Err(e) = store.remove_pending_event(&key) {
+                warn!(error = %e, proof_key = %key, "Failed to clear pending event");
+            }
+            journal.advanced(&key, JournalStage::Delivered);
+            status::advance(store, event, EventStatus::Delivered);
+        }
+        delivered
+    }
+
+    // Park an event the proving backend will never produce a proof for
+    fn dead_letter(
+        store: &dyn StateStore,
+        journal: &Journal,
+        event: &RelayEvent,
+        error: &anyhow::Error,
+    ) {
+        let key = ProofKey::from_meta(&event.meta);
+        let failed = FailedDelivery::unproven(event, format!("{:#}", error));
+        if let Err(e) = store.save_dead_letter(&key, &failed) {
+            warn!(error = %e, proof_key = %key, "Failed to persist dead letter");
+            return;
+        }
+        if let Err(e) = store.remove_pending_event(&key) {
+            warn!(error = %e, proof_key = %key, "Failed to clear pending event");
+        }
+        journal.advanced(&key, JournalStage::DeadLettered);
+        let reason = failed.last_error;
+        status::advance(store, event, EventStatus::Failed { reason });
+        // A replay starts a fresh proof job rather than polling the failed one
+        if let Err(e) = store.remove_proof(&key) {
+            warn!(error = %e, proof_key = %key, "Failed to clear proof job");
+        }
+        DEAD_LETTERS
+            .with_label_values(&PairLabels::of(event).values())
+            .inc();
+        error!(proof_key = %key, "ALERT: event moved to dead letter queue");
+        alerts::notify(
+            AlertKind::DeadLetter,
+            event.destination_chain.name.as_str(),
+            format!(
+                "Event {} moved to dead letter queue, no proof: {:#}",
+                key, error
+            ),
+        );
+    }
+
+    fn reap_finished(tasks: &mut JoinSet<()>) {
+        while let Some(result) = tasks.try_join_next() {
+            if let Err(e) = result {
+                error!(error = %e, "Proof fetch task failed");
+            }
+        }
+    }
+
+    #[instrument(skip(provider), fields(
+        event_id = %request.event.id(),
         source_chain_id = ?request.event.source_chain.chain_id,
         dest_chain_id = ?request.event.destination_chain.chain_id,
         tx_hash = ?request.tx_hash
     ))]
     async fn fetch_proof(
-        request: ProofRequest, 
-        polymer_api_url: String, 
-        api_token: String
-    ) -> Result<Bytes> {
-        info!("Fetching proof from Polymer API");
-
-        // Create the proof API client
-        let client = ProofApiClient::new(api_token, polymer_api_url);
-        
-        // Request the proof from the Polymer API
-        let proof = client.fetch_proof(
-            request.event.source_chain.chain_id,
-            request.event.meta.block_number,
-            request.event.meta.tx_index,
-            request.event.meta.log_index,
-        ).await?;
-        
-        info!("Proof fetched successfully");
-        
-        Ok(proof)
+        request: ProofRequest,
+        provider: Arc<dyn ProofProvider>,
+        validate: bool,
+    ) -> Result<Proof> {
+        let labels = PairLabels::of(&request.event);
+        let started = Instant::now();
+
+        let result = provider.prove(&request.event.meta).await.and_then(|proof| {
+            if validate {
+                Self::validate_proof(provider.as_ref(), &proof, &request)?;
+            }
+            Ok(proof)
+        });
+
+        let outcome = match &result {
+            Ok(_) => {
+                PROOF_DURATION
+                    .with_label_values(&labels.values())
+                    .observe(started.elapsed().as_secs_f64());
+                "success"
+            }
+            Err(e) => Self::failure_reason(e),
+        };
+        PROOF_RESULTS
+            .with_label_values(&labels.and(&[outcome]))
+            .inc();
+
+        result
+    }
+
+    fn failure_reason(error: &anyhow::Error) -> &'static str {
+        match error.downcast_ref::<RelayerError>() {
+            Some(RelayerError::ProofRequestRejected { .. }) => "rejected",
+            Some(RelayerError::ProofApiUnavailable { .. }) => "unavailable",
+            Some(RelayerError::ProofApiTimeout { .. }) => "api_timeout",
+            Some(RelayerError::ProofTimeout { .. }) => "timeout",
+            Some(RelayerError::ProofVerification(_)) => "invalid_proof",
+            _ => "error",
+        }
+    }
+
+    /// Check the proof attests to the log we asked for before paying to deliver it
+    fn validate_proof(
+        provider: &dyn ProofProvider,
+        proof: &Proof,
+        request: &ProofRequest,
+    ) -> Result<()> {
+        let meta = &request.event.meta;
+        let Some(identifier) = provider
+            .inspect(proof)
+            .map_err(|e| RelayerError::ProofVerification(e.to_string()))?
+        else {
+            return Ok(());
+        };
+
+        let expected = LogIdentifier {
+            chain_id: meta.chain_id,
+            block_number: meta.block_number,
+            receipt_index: meta.tx_index,
+            log_index: meta.log_index,
+        };
+        if identifier != expected {
+            return Err(RelayerError::ProofVerification(format!(
+                "proof references {:?}, expected {:?}",
+                identifier, expected
+            ))
+            .into());
+        }
+
+        Ok(())
     }
 }
@@ -0,0 +1,132 @@
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use std::sync::Arc;
+use tracing::{info, instrument, warn};
+
+use super::client::ProofApiClient;
+use super::provider::{LogIdentifier, ProofProvider};
+use crate::config::PolymerApiConfig;
+use crate::metrics::PROOF_POLLS;
+use crate::store::{ProofKey, StateStore};
+use crate::types::{ChainId, EventMeta, Proof};
+
+// Polymer v2 proof header layout:
+//   [0..32)    app state root
+//   [32..97)   sequencer signature
+//   [97..101)  source chain ID (u32, big endian)
+//   [101..109) peptide height (u64)
+//   [109..117) source block number (u64)
+//   [117..119) receipt index (u16)
+//   [119..120) log index (u8)
+const CHAIN_ID_OFFSET: usize = 97;
+const BLOCK_NUMBER_OFFSET: usize = 109;
+const RECEIPT_INDEX_OFFSET: usize = 117;
+const LOG_INDEX_OFFSET: usize = 119;
+const HEADER_LEN: usize = 120;
+
+// Assumed when the API doesn't report a version
+const PROOF_FORMAT_VERSION: &str = "polymer-v2";
+
+/// Proof provider backed by the Polymer proof API
+pub struct PolymerProofProvider {
+    client: ProofApiClient,
+    store: Arc<dyn StateStore>,
+}
+
+impl PolymerProofProvider {
+    pub fn new(config: PolymerApiConfig, store: Arc<dyn StateStore>) -> Result<Self> {
+        Ok(Self {
+            client: ProofApiClient::new(config)?,
+            store,
+        })
+    }
+}
+
+#[async_trait]
+impl ProofProvider for PolymerProofProvider {
+    #[instrument(skip(self), fields(
+        chain_id = meta.chain_id.as_u64(),
+        block_number = meta.block_number,
+        tx_hash = ?meta.tx_hash
+    ))]
+    async fn prove(&self, meta: &EventMeta) -> Result<Proof> {
+        let key = ProofKey::from_meta(meta);
+
+        // Reuse a proof (or an in-progress job) left behind by an earlier attempt
+        let cached = self.store.proof(&key)?;
+        if let Some(proof) = cached.as_ref().and_then(|record| record.to_proof()) {
+            info!(proof_key = %key, "Reusing cached proof");
+            return Ok(proof);
+        }
+
+        info!("Fetching proof from Polymer API");
+
+        let job_id = match cached {
+            Some(record) => {
+                info!(proof_key = %key, job_id = record.job_id, "Resuming cached proof job");
+                record.job_id
+            }
+            None => {
+                let job_id = self
+                    .client
+                    .request_proof(meta.chain_id, meta.block_number, meta.tx_index, meta.log_index)
+                    .await?;
+                self.store.save_proof_job(&key, job_id)?;
+                job_id
+            }
+        };
+
+        let mut proof = match self.client.wait_for_proof(job_id).await {
+            Ok((proof, polls)) => {
+                PROOF_POLLS
+                    .with_label_values(&[&meta.chain_id.to_string()])
+                    .observe(f64::from(polls));
+                proof
+            }
+            Err(e) => {
+                // Forget the job so the next attempt starts a fresh one
+                if let Err(store_err) = self.store.remove_proof(&key) {
+                    warn!(error = %store_err, proof_key = %key, "Failed to drop stale proof job");
+                }
+                return Err(e);
+            }
+        };
+        proof
+            .metadata
+            .format_version
+            .get_or_insert_with(|| PROOF_FORMAT_VERSION.to_string());
+        self.store.save_proof(&key, &proof)?;
+
+        info!(
+            proven_height = ?proof.metadata.proven_height,
+            receipt_root = ?proof.metadata.receipt_root,
+            "Proof fetched successfully"
+        );
+
+        Ok(proof)
+    }
+
+    fn inspect(&self, proof: &Proof) -> Result<Option<LogIdentifier>> {
+        let data = proof.data.as_ref();
+        if data.len() < HEADER_LEN {
+            return Err(anyhow!(
+                "Proof is {} bytes, shorter than the {} byte header",
+                data.len(),
+                HEADER_LEN
+            ));
+        }
+
+        let be_bytes = |offset: usize, len: usize| -> u64 {
+            data[offset..offset + len]
+                .iter()
+                .fold(0u64, |acc, byte| (acc << 8) | u64::from(*byte))
+        };
+
+        Ok(Some(LogIdentifier {
+            chain_id: ChainId::new(be_bytes(CHAIN_ID_OFFSET, 4)),
+            block_number: be_bytes(BLOCK_NUMBER_OFFSET, 8),
+            receipt_index: be_bytes(RECEIPT_INDEX_OFFSET, 2) as u32,
+            log_index: be_bytes(LOG_INDEX_OFFSET, 1) as u32,
+        }))
+    }
+}
@@ -0,0 +1,32 @@
+use anyhow::Result;
+use async_trait::async_trait;
+
+use crate::types::{ChainId, EventMeta, Proof};
+
+/// Source log a proof claims to attest to, as encoded inside the proof
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogIdentifier {
+    pub chain_id: ChainId,
+    pub block_number: u64,
+    pub receipt_index: u32,
+    pub log_index: u32,
+}
+
+/// Backend that turns a source log into a proof the destination chain can verify
+///
+/// `ProofFetcher` only handles the channel plumbing between stages; everything
+/// specific to a proving service lives behind this trait.
+/// [`PolymerProofProvider`] is the backend a relayer uses unless told
+/// otherwise, and [`MockProofProvider`] stands in for it in tests.
+///
+/// [`PolymerProofProvider`]: crate::PolymerProofProvider
+/// [`MockProofProvider`]: crate::MockProofProvider
+#[async_trait]
+pub trait ProofProvider: Send + Sync {
+    async fn prove(&self, meta: &EventMeta) -> Result<Proof>;
+
+    /// Decode which log a proof refers to, if this backend's format allows it
+    fn inspect(&self, _proof: &Proof) -> Result<Option<LogIdentifier>> {
+        Ok(None)
+    }
+}
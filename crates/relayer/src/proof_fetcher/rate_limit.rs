@@ -0,0 +1,55 @@
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+use tracing::debug;
+
+struct Bucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+/// Token bucket shared by every task that calls the proof API
+///
+/// The bucket holds up to one minute's worth of requests, so short bursts are
+/// served immediately while sustained load is held to the configured rate.
+pub struct RateLimiter {
+    capacity: f64,
+    refill_per_sec: f64,
+    bucket: Mutex<Bucket>,
+}
+
+impl RateLimiter {
+    pub fn per_minute(requests_per_minute: u32) -> Self {
+        let capacity = f64::from(requests_per_minute.max(1));
+        Self {
+            capacity,
+            refill_per_sec: capacity / 60.0,
+            bucket: Mutex::new(Bucket {
+                tokens: capacity,
+                last_refill: Instant::now(),
+            }),
+        }
+    }
+
+    /// Wait until a request token is available and consume it
+    pub async fn acquire(&self) {
+        loop {
+            let wait = {
+                let mut bucket = self.bucket.lock().expect("rate limiter lock poisoned");
+                let now = Instant::now();
+                let elapsed = now.duration_since(bucket.last_refill).as_secs_f64();
+                bucket.tokens = (bucket.tokens + elapsed * self.refill_per_sec).min(self.capacity);
+                bucket.last_refill = now;
+
+                if bucket.tokens >= 1.0 {
+                    bucket.tokens -= 1.0;
+                    return;
+                }
+                Duration::from_secs_f64((1.0 - bucket.tokens) / self.refill_per_sec)
+            };
+            debug!(wait_ms = wait.as_millis() as u64, "Proof API rate limit reached, waiting");
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
@@ -0,0 +1,97 @@
+use anyhow::{anyhow, Context, Result};
+use serde::Deserialize;
+use std::sync::RwLock;
+use tokio::{process::Command, sync::Mutex};
+use tracing::{info, instrument};
+
+use crate::config::TokenRefreshConfig;
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    token: String,
+}
+
+/// Holds the current Polymer API token and replaces it when it expires
+pub struct TokenSource {
+    current: RwLock<String>,
+    refresh: Option<TokenRefreshConfig>,
+    // Serializes refreshes so a burst of 401s only fetches one new token
+    refresh_lock: Mutex<()>,
+}
+
+impl TokenSource {
+    pub fn new(token: String, refresh: Option<TokenRefreshConfig>) -> Self {
+        Self {
+            current: RwLock::new(token),
+            refresh,
+            refresh_lock: Mutex::new(()),
+        }
+    }
+
+    pub fn current(&self) -> String {
+        self.current.read().expect("token lock poisoned").clone()
+    }
+
+    pub fn can_refresh(&self) -> bool {
+        self.refresh.is_some()
+    }
+
+    /// Replace `stale` with a fresh token, unless another task already did
+    #[instrument(skip_all)]
+    pub async fn refresh(&self, stale: &str) -> Result<()> {
+        let refresh = self
+            .refresh
+            .as_ref()
+            .ok_or_else(|| anyhow!("No token refresh method configured"))?;
+
+        let _guard = self.refresh_lock.lock().await;
+        if self.current() != stale {
+            return Ok(());
+        }
+
+        let token = match refresh {
+            TokenRefreshConfig::Endpoint { url } => Self::fetch_from_endpoint(url).await?,
+            TokenRefreshConfig::Command { program, args } => {
+                Self::run_command(program, args).await?
+            }
+        };
+        if token.is_empty() {
+            return Err(anyhow!("Token refresh returned an empty token"));
+        }
+
+        *self.current.write().expect("token lock poisoned") = token;
+        info!("Polymer API token refreshed");
+        Ok(())
+    }
+
+    async fn fetch_from_endpoint(url: &str) -> Result<String> {
+        let text = reqwest::get(url)
+            .await
+            .context("Failed to reach token refresh endpoint")?
+            .error_for_status()?
+            .text()
+            .await?;
+
+        // Accept either `{"token": "..."}` or the bare token
+        match serde_json::from_str::<TokenResponse>(&text) {
+            Ok(response) => Ok(response.token),
+            Err(_) => Ok(text.trim().to_string()),
+        }
+    }
+
+    async fn run_command(program: &str, args: &[String]) -> Result<String> {
+        let output = Command::new(program)
+            .args(args)
+            .output()
+            .await
+            .context(format!("Failed to run token refresh command {}", program))?;
+        if !output.status.success() {
+            return Err(anyhow!(
+                "Token refresh command {} exited with {}",
+                program,
+                output.status
+            ));
+        }
+        Ok(String::from_utf8(output.stdout)?.trim().to_string())
+    }
+}
@@ -0,0 +1,71 @@
+use anyhow::Result;
+use std::{
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tokio::time::{self, Instant};
+use tracing::{info, warn};
+
+use crate::health::{Health, StageTotals, EVENT_DELIVERER, PROOF_FETCHER};
+use crate::store::StateStore;
+
+/// Log a summary of the pipeline every `interval`: events through each stage
+/// since the last one, what is waiting at each stage and how long the oldest
+/// unfinished event has been waiting
+///
+/// Slow degradation shows up here even where no metrics are scraped.
+pub(crate) async fn run(health: Health, store: Arc<dyn StateStore>, interval: Duration) {
+    let mut ticker = time::interval_at(Instant::now() + interval, interval);
+    let mut last = health.stage_totals();
+    loop {
+        ticker.tick().await;
+        let totals = health.stage_totals();
+        if let Err(e) = summarize(&health, &*store, interval, &last, &totals) {
+            warn!(error = %e, "Failed to report pipeline throughput");
+        }
+        last = totals;
+    }
+}
+
+fn summarize(
+    health: &Health,
+    store: &dyn StateStore,
+    interval: Duration,
+    last: &StageTotals,
+    totals: &StageTotals,
+) -> Result<()> {
+    let report = health.report();
+    let queue_depth = |name: &str| {
+        report
+            .components
+            .iter()
+            .find(|component| component.name == name)
+            .and_then(|component| component.queue_depth)
+            .unwrap_or_default()
+    };
+    let pending = store.pending_events()?;
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default();
+    let oldest_pending_secs = pending
+        .iter()
+        .filter(|event| event.detected_at > 0)
+        .map(|event| now.saturating_sub(event.detected_at))
+        .max();
+
+    info!(
+        interval_secs = interval.as_secs(),
+        detected = totals.detected - last.detected,
+        proven = totals.proven - last.proven,
+        delivered = totals.delivered - last.delivered,
+        proof_queue = queue_depth(PROOF_FETCHER),
+        delivery_queue = queue_depth(EVENT_DELIVERER),
+        spilled = store.spilled_deliveries()?.len(),
+        retrying = store.retries()?.len(),
+        pending = pending.len(),
+        oldest_pending_secs,
+        "Pipeline report"
+    );
+    Ok(())
+}
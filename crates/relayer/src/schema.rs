@@ -0,0 +1,161 @@
+use ethers::core::types::{Address, Bytes, H256};
+use serde::{Deserialize, Serialize};
+
+use crate::config::ChainConfig;
+use crate::telemetry::TraceContext;
+use crate::types::{ChainId, DeliveryRequest, EventMeta, Proof, RelayEvent, RelayerError};
+
+// Versioned representations of the types that are persisted, journaled,
+// replayed and served over the admin API. Each is written with a `schema` tag
+// naming its version ahead of its fields, and hashes and byte strings as
+// `0x`-prefixed hex. A change old readers could not follow gets a new version
+// here; records written before tags were added read as the first version.
+
+/// Schema tag written with every [`RelayEvent`]
+pub const RELAY_EVENT_SCHEMA: &str = "relay_event/v1";
+/// Schema tag written with every [`EventMeta`]
+pub const EVENT_META_SCHEMA: &str = "event_meta/v1";
+/// Schema tag written with every [`DeliveryRequest`]
+pub const DELIVERY_REQUEST_SCHEMA: &str = "delivery_request/v1";
+
+// Accept the current tag, or none from a record written before tagging
+fn check(schema: Option<String>, expected: &'static str) -> Result<(), RelayerError> {
+    match schema {
+        Some(schema) if schema != expected => {
+            Err(RelayerError::UnsupportedSchema { schema, expected })
+        }
+        _ => Ok(()),
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct RelayEventV1 {
+    #[serde(default)]
+    schema: Option<String>,
+    source_chain: ChainConfig,
+    source_resolver_address: Address,
+    destination_chain: ChainConfig,
+    dest_dapp_address: Address,
+    exec_payload: Bytes,
+    nonce: u64,
+    meta: EventMeta,
+    #[serde(default)]
+    detected_at: u64,
+    #[serde(default)]
+    trace_context: TraceContext,
+    #[serde(default)]
+    priority: u8,
+}
+
+impl From<RelayEvent> for RelayEventV1 {
+    fn from(event: RelayEvent) -> Self {
+        Self {
+            schema: Some(RELAY_EVENT_SCHEMA.to_string()),
+            source_chain: event.source_chain,
+            source_resolver_address: event.source_resolver_address,
+            destination_chain: event.destination_chain,
+            dest_dapp_address: event.dest_dapp_address,
+            exec_payload: event.exec_payload,
+            nonce: event.nonce,
+            meta: event.meta,
+            detected_at: event.detected_at,
+            trace_context: event.trace_context,
+            priority: event.priority,
+        }
+    }
+}
+
+impl TryFrom<RelayEventV1> for RelayEvent {
+    type Error = RelayerError;
+
+    fn try_from(event: RelayEventV1) -> Result<Self, Self::Error> {
+        check(event.schema, RELAY_EVENT_SCHEMA)?;
+        Ok(Self {
+            source_chain: event.source_chain,
+            source_resolver_address: event.source_resolver_address,
+            destination_chain: event.destination_chain,
+            dest_dapp_address: event.dest_dapp_address,
+            exec_payload: event.exec_payload,
+            nonce: event.nonce,
+            meta: event.meta,
+            detected_at: event.detected_at,
+            trace_context: event.trace_context,
+            priority: event.priority,
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct EventMetaV1 {
+    #[serde(default)]
+    schema: Option<String>,
+    chain_id: ChainId,
+    tx_hash: Option<H256>,
+    block_number: u64,
+    tx_index: u32,
+    log_index: u32,
+}
+
+impl From<EventMeta> for EventMetaV1 {
+    fn from(meta: EventMeta) -> Self {
+        Self {
+            schema: Some(EVENT_META_SCHEMA.to_string()),
+            chain_id: meta.chain_id,
+            tx_hash: meta.tx_hash,
+            block_number: meta.block_number,
+            tx_index: meta.tx_index,
+            log_index: meta.log_index,
+        }
+    }
+}
+
+impl TryFrom<EventMetaV1> for EventMeta {
+    type Error = RelayerError;
+
+    fn try_from(meta: EventMetaV1) -> Result<Self, Self::Error> {
+        check(meta.schema, EVENT_META_SCHEMA)?;
+        Ok(Self {
+            chain_id: meta.chain_id,
+            tx_hash: meta.tx_hash,
+            block_number: meta.block_number,
+            tx_index: meta.tx_index,
+            log_index: meta.log_index,
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct DeliveryRequestV1 {
+    #[serde(default)]
+    schema: Option<String>,
+    destination_chain_id: ChainId,
+    destination_contract_address: Address,
+    event: RelayEvent,
+    proof: Proof,
+}
+
+impl From<DeliveryRequest> for DeliveryRequestV1 {
+    fn from(request: DeliveryRequest) -> Self {
+        Self {
+            schema: Some(DELIVERY_REQUEST_SCHEMA.to_string()),
+            destination_chain_id: request.destination_chain_id,
+            destination_contract_address: request.destination_contract_address,
+            event: request.event,
+            proof: request.proof,
+        }
+    }
+}
+
+impl TryFrom<DeliveryRequestV1> for DeliveryRequest {
+    type Error = RelayerError;
+
+    fn try_from(request: DeliveryRequestV1) -> Result<Self, Self::Error> {
+        check(request.schema, DELIVERY_REQUEST_SCHEMA)?;
+        Ok(Self {
+            destination_chain_id: request.destination_chain_id,
+            destination_contract_address: request.destination_contract_address,
+            event: request.event,
+            proof: request.proof,
+        })
+    }
+}
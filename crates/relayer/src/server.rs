@@ -0,0 +1,543 @@
+use anyhow::{Context, Result};
+use axum::{
+    extract::{Path, Query, Request, State},
+    http::{header, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::{delete, get, post, put},
+    Json, Router,
+};
+use ethers::core::types::{Address, Bytes, H256, U256};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::{net::SocketAddr, sync::Arc};
+use tracing::{info, instrument, warn};
+
+use crate::ccip_gateway::CcipGateway;
+use crate::config::{ChainConfig, RelayPair};
+use crate::dead_letters::{DeadLetterKey, DeadLetterQueue};
+use crate::event_delivery::{DeliveryControl, InFlightDelivery};
+use crate::health::{GasTank, Health, HealthReport};
+use crate::metrics;
+use crate::status::{EventQuery, EventRecord};
+use crate::store::{DeadLetterQuery, DeliveryQuery, FailedDelivery, ProofKey, StateStore};
+use crate::topology::{ManagedPair, Topology};
+use crate::types::{ChainId, DeliveryOutcome, EventId, RelayerError};
+
+// Handles the admin endpoints act through
+#[derive(Clone)]
+struct AdminState {
+    control: DeliveryControl,
+    store: Arc<dyn StateStore>,
+    health: Health,
+    topology: Topology,
+    dead_letters: DeadLetterQueue,
+    gateway: CcipGateway,
+}
+
+/// Serve operational endpoints until the listener fails
+///
+/// Endpoints that change relay pairs and chains, pause relaying, cancel or
+/// replace in-flight deliveries or replay dead letters are only served when
+/// an admin token is set, and require it as a bearer token.
+///
+/// The CCIP-Read gateway is served under `/ccip` without a token, over plain
+/// HTTP; callers expecting HTTPS reach it through a TLS-terminating proxy.
+#[instrument(skip_all, fields(%addr))]
+pub async fn serve(
+    addr: SocketAddr,
+    control: DeliveryControl,
+    store: Arc<dyn StateStore>,
+    health: Health,
+    topology: Topology,
+    dead_letters: DeadLetterQueue,
+    admin_token: Option<String>,
+) -> Result<()> {
+    let mut app = Router::new()
+        .route("/metrics", get(|| async { metrics::gather() }))
+        .route("/healthz", get(healthz))
+        .route("/readyz", get(readyz))
+        .route("/startupz", get(startupz))
+        .route("/deliveries", get(history))
+        .route("/deliveries/inflight", get(in_flight))
+        .route("/gas-tanks", get(gas_tanks))
+        .route("/events", get(list_events))
+        .route("/events/:id", get(event_status))
+        .route("/dead-letters", get(list_dead_letters))
+        .route("/dead-letters/:key", get(dead_letter))
+        .route("/ccip", post(ccip_lookup))
+        .route("/ccip/:sender/:data", get(ccip_get));
+
+    match admin_token {
+        Some(token) => {
+            let admin_routes = Router::new()
+                .route("/deliveries/:chain_id/:sender/:nonce/cancel", post(cancel))
+                .route(
+                    "/deliveries/:chain_id/:sender/:nonce/replace",
+                    post(replace),
+                )
+                .route("/pairs", get(list_pairs).post(add_pair))
+                .route("/pairs/:id", delete(remove_pair))
+                .route("/pairs/:id/enable", post(enable_pair))
+                .route("/pairs/:id/disable", post(disable_pair))
+                .route("/pairs/:id/shard", put(assign_shard))
+                .route("/pairs/:id/pause", post(pause_pair))
+                .route("/pairs/:id/resume", post(resume_pair))
+                .route("/pause", post(pause))
+                .route("/resume", post(resume))
+                .route("/chains", get(list_chains).post(add_chain))
+                .route("/chains/:chain_id", delete(remove_chain))
+                .route("/dead-letters/replay", post(replay_dead_letters))
+                .route("/dead-letters/:key/replay", post(replay_dead_letter))
+                .route_layer(middleware::from_fn_with_state(
+                    Arc::<str>::from(token),
+                    require_token,
+                ));
+            app = app.merge(admin_routes);
+        }
+        None => info!(
+            "No admin token set, topology, pause, delivery control and replay endpoints are disabled"
+        ),
+    }
+
+    let gateway = CcipGateway::new(store.clone(), topology.clone());
+    let app = app.with_state(AdminState {
+        control,
+        store,
+        health,
+        topology,
+        dead_letters,
+        gateway,
+    });
+
+    let listener = tokio::net::TcpListener::bind(addr)
+        .await
+        .context(format!("Failed to bind HTTP server on {}", addr))?;
+    info!(%addr, "HTTP server listening");
+
+    axum::serve(listener, app).await?;
+    Ok(())
+}
+
+// Liveness probe: every component is making progress or has nothing to do
+async fn healthz(State(state): State<AdminState>) -> (StatusCode, Json<HealthReport>) {
+    let report = state.health.report();
+    (probe_status(report.live), Json(report))
+}
+
+// Readiness probe: started, live and not draining, and every chain's RPC is reachable
+async fn readyz(State(state): State<AdminState>) -> (StatusCode, Json<HealthReport>) {
+    let report = state.health.report();
+    (probe_status(report.ready), Json(report))
+}
+
+// Startup probe: state loaded and every chain's RPC verified once, after
+// which liveness and readiness take over
+async fn startupz(State(state): State<AdminState>) -> (StatusCode, Json<HealthReport>) {
+    let report = state.health.report();
+    (probe_status(report.started), Json(report))
+}
+
+fn probe_status(ok: bool) -> StatusCode {
+    if ok {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    }
+}
+
+// Delivery attempts filtered by the query string, e.g. `?pair=...&nonce=42`
+async fn history(
+    State(state): State<AdminState>,
+    Query(query): Query<DeliveryQuery>,
+) -> Result<Json<Vec<DeliveryOutcome>>, (StatusCode, String)> {
+    state
+        .store
+        .delivery_history(&query)
+        .map(Json)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("{:#}", e)))
+}
+
+// Event statuses filtered by the query string, e.g.
+// `?pair=...&status=failed&from_nonce=10&to_nonce=20&since=1700000000&limit=100`
+async fn list_events(
+    State(state): State<AdminState>,
+    Query(query): Query<EventQuery>,
+) -> Result<Json<Vec<EventRecord>>, (StatusCode, String)> {
+    query
+        .validate()
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+    state
+        .store
+        .query_events(&query)
+        .map(Json)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("{:#}", e)))
+}
+
+async fn event_status(
+    State(state): State<AdminState>,
+    Path(id): Path<String>,
+) -> Result<Json<EventRecord>, (StatusCode, String)> {
+    let id: EventId = id
+        .parse()
+        .map_err(|e| (StatusCode::BAD_REQUEST, format!("{:#}", e)))?;
+    match state.store.event_record(&id) {
+        Ok(Some(record)) => Ok(Json(record)),
+        Ok(None) => Err((
+            StatusCode::NOT_FOUND,
+            RelayerError::UnknownEvent(id).to_string(),
+        )),
+        Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, format!("{:#}", e))),
+    }
+}
+
+async fn in_flight(State(state): State<AdminState>) -> Json<Vec<InFlightDelivery>> {
+    Json(state.control.in_flight())
+}
+
+async fn gas_tanks(State(state): State<AdminState>) -> Json<Vec<GasTank>> {
+    Json(state.health.gas_tanks())
+}
+
+async fn cancel(
+    State(state): State<AdminState>,
+    Path((chain_id, sender, nonce)): Path<(ChainId, Address, u64)>,
+) -> Result<Json<H256>, (StatusCode, String)> {
+    warn!(
+        %chain_id,
+        ?sender,
+        nonce,
+        "Operator requested delivery cancellation"
+    );
+    state
+        .control
+        .cancel(chain_id, sender, U256::from(nonce))
+        .await
+        .map(Json)
+        .map_err(|e| (StatusCode::CONFLICT, format!("{:#}", e)))
+}
+
+async fn replace(
+    State(state): State<AdminState>,
+    Path((chain_id, sender, nonce)): Path<(ChainId, Address, u64)>,
+) -> Result<Json<H256>, (StatusCode, String)> {
+    warn!(
+        %chain_id,
+        ?sender,
+        nonce,
+        "Operator requested delivery replacement"
+    );
+    state
+        .control
+        .replace(chain_id, sender, U256::from(nonce))
+        .await
+        .map(Json)
+        .map_err(|e| (StatusCode::CONFLICT, format!("{:#}", e)))
+}
+
+// Reject requests that don't carry the admin token as a bearer token
+async fn require_token(State(token): State<Arc<str>>, request: Request, next: Next) -> Response {
+    let presented = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    match presented {
+        Some(presented) if tokens_match(presented, &token) => next.run(request).await,
+        _ => (StatusCode::UNAUTHORIZED, "Missing or invalid admin token").into_response(),
+    }
+}
+
+// Compare every byte, so response timing doesn't reveal how much of the token matched
+fn tokens_match(presented: &str, expected: &str) -> bool {
+    presented.len() == expected.len()
+        && presented
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+fn topology_error(e: anyhow::Error) -> (StatusCode, String) {
+    let status = match e.downcast_ref::<RelayerError>() {
+        Some(RelayerError::UnknownChain(_) | RelayerError::UnknownRelayPair(_)) => {
+            StatusCode::NOT_FOUND
+        }
+        Some(
+            RelayerError::ChainExists(_)
+            | RelayerError::ChainInUse { .. }
+            | RelayerError::RelayPairExists(_),
+        ) => StatusCode::CONFLICT,
+        Some(RelayerError::InvalidShard { .. } | RelayerError::InvalidConfig(_)) => {
+            StatusCode::BAD_REQUEST
+        }
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    (status, format!("{:#}", e))
+}
+
+async fn list_pairs(State(state): State<AdminState>) -> Json<Vec<ManagedPair>> {
+    Json(state.topology.pairs())
+}
+
+async fn add_pair(
+    State(state): State<AdminState>,
+    Json(pair): Json<RelayPair>,
+) -> Result<Json<ManagedPair>, (StatusCode, String)> {
+    warn!(pair = %pair.id(), "Operator added relay pair");
+    state
+        .topology
+        .add_pair(pair)
+        .map(Json)
+        .map_err(topology_error)
+}
+
+async fn enable_pair(
+    State(state): State<AdminState>,
+    Path(id): Path<String>,
+) -> Result<Json<ManagedPair>, (StatusCode, String)> {
+    warn!(pair = %id, "Operator enabled relay pair");
+    state
+        .topology
+        .set_enabled(&id, true)
+        .map(Json)
+        .map_err(topology_error)
+}
+
+async fn disable_pair(
+    State(state): State<AdminState>,
+    Path(id): Path<String>,
+) -> Result<Json<ManagedPair>, (StatusCode, String)> {
+    warn!(pair = %id, "Operator disabled relay pair");
+    state
+        .topology
+        .set_enabled(&id, false)
+        .map(Json)
+        .map_err(topology_error)
+}
+
+async fn pause_pair(
+    State(state): State<AdminState>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    warn!(pair = %id, "Operator paused relay pair");
+    set_paused(&state, Some(&id), true)
+}
+
+async fn resume_pair(
+    State(state): State<AdminState>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    warn!(pair = %id, "Operator resumed relay pair");
+    set_paused(&state, Some(&id), false)
+}
+
+async fn pause(State(state): State<AdminState>) -> Result<StatusCode, (StatusCode, String)> {
+    warn!("Operator paused relaying");
+    set_paused(&state, None, true)
+}
+
+async fn resume(State(state): State<AdminState>) -> Result<StatusCode, (StatusCode, String)> {
+    warn!("Operator resumed relaying");
+    set_paused(&state, None, false)
+}
+
+fn set_paused(
+    state: &AdminState,
+    id: Option<&str>,
+    paused: bool,
+) -> Result<StatusCode, (StatusCode, String)> {
+    state
+        .topology
+        .set_paused(id, paused)
+        .map(|()| StatusCode::NO_CONTENT)
+        .map_err(topology_error)
+}
+
+#[derive(Deserialize)]
+struct ShardAssignment {
+    /// Shard to pin the pair to, or null to go back to the hashed shard
+    shard: Option<u32>,
+}
+
+async fn assign_shard(
+    State(state): State<AdminState>,
+    Path(id): Path<String>,
+    Json(assignment): Json<ShardAssignment>,
+) -> Result<Json<ManagedPair>, (StatusCode, String)> {
+    warn!(pair = %id, shard = ?assignment.shard, "Operator assigned relay pair shard");
+    state
+        .topology
+        .assign_shard(&id, assignment.shard)
+        .map(Json)
+        .map_err(topology_error)
+}
+
+async fn remove_pair(
+    State(state): State<AdminState>,
+    Path(id): Path<String>,
+) -> Result<Json<ManagedPair>, (StatusCode, String)> {
+    warn!(pair = %id, "Operator removed relay pair");
+    state
+        .topology
+        .remove_pair(&id)
+        .map(Json)
+        .map_err(topology_error)
+}
+
+async fn list_chains(State(state): State<AdminState>) -> Json<Vec<ChainConfig>> {
+    Json(state.topology.chains())
+}
+
+async fn add_chain(
+    State(state): State<AdminState>,
+    Json(chain): Json<ChainConfig>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    warn!(chain_id = chain.chain_id.as_u64(), chain = %chain.name, "Operator added chain");
+    state
+        .topology
+        .add_chain(chain)
+        .map(|()| StatusCode::CREATED)
+        .map_err(topology_error)
+}
+
+async fn remove_chain(
+    State(state): State<AdminState>,
+    Path(chain_id): Path<ChainId>,
+) -> Result<Json<ChainConfig>, (StatusCode, String)> {
+    warn!(%chain_id, "Operator removed chain");
+    state
+        .topology
+        .remove_chain(chain_id)
+        .map(Json)
+        .map_err(topology_error)
+}
+
+fn dead_letter_error(e: anyhow::Error) -> (StatusCode, String) {
+    let status = match e.downcast_ref::<RelayerError>() {
+        Some(RelayerError::UnknownDeadLetter(_)) => StatusCode::NOT_FOUND,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    (status, format!("{:#}", e))
+}
+
+// Proof key of the dead letter a path names, by proof key or event ID
+fn parse_key(dead_letters: &DeadLetterQueue, key: &str) -> Result<ProofKey, (StatusCode, String)> {
+    let key: DeadLetterKey = key
+        .parse()
+        .map_err(|e| (StatusCode::BAD_REQUEST, format!("{:#}", e)))?;
+    dead_letters.resolve(&key).map_err(dead_letter_error)
+}
+
+// Dead letters filtered by the query string, e.g. `?dest_chain_id=84532&stage=proof`
+async fn list_dead_letters(
+    State(state): State<AdminState>,
+    Query(query): Query<DeadLetterQuery>,
+) -> Result<Json<Vec<FailedDelivery>>, (StatusCode, String)> {
+    state
+        .dead_letters
+        .list(&query)
+        .map(Json)
+        .map_err(dead_letter_error)
+}
+
+async fn dead_letter(
+    State(state): State<AdminState>,
+    Path(key): Path<String>,
+) -> Result<Json<FailedDelivery>, (StatusCode, String)> {
+    let key = parse_key(&state.dead_letters, &key)?;
+    match state.dead_letters.get(&key) {
+        Ok(Some(failed)) => Ok(Json(failed)),
+        Ok(None) => Err(dead_letter_error(
+            RelayerError::UnknownDeadLetter(key.to_string()).into(),
+        )),
+        Err(e) => Err(dead_letter_error(e)),
+    }
+}
+
+async fn replay_dead_letter(
+    State(state): State<AdminState>,
+    Path(key): Path<String>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let key = parse_key(&state.dead_letters, &key)?;
+    warn!(proof_key = %key, "Operator replayed dead letter");
+    state
+        .dead_letters
+        .replay(&key)
+        .await
+        .map(|()| StatusCode::ACCEPTED)
+        .map_err(dead_letter_error)
+}
+
+#[derive(Serialize)]
+struct Replayed {
+    replayed: usize,
+}
+
+// Replay every dead letter matching the query string
+async fn replay_dead_letters(
+    State(state): State<AdminState>,
+    Query(query): Query<DeadLetterQuery>,
+) -> Result<Json<Replayed>, (StatusCode, String)> {
+    warn!(?query, "Operator replayed dead letters");
+    state
+        .dead_letters
+        .replay_all(&query)
+        .await
+        .map(|replayed| Json(Replayed { replayed }))
+        .map_err(dead_letter_error)
+}
+
+#[derive(Deserialize)]
+struct CcipRequest {
+    sender: String,
+    data: String,
+}
+
+// ERC-3668 lookup in the POST form, with `{sender, data}` as the body
+async fn ccip_lookup(
+    State(state): State<AdminState>,
+    Json(request): Json<CcipRequest>,
+) -> Response {
+    ccip_response(&state.gateway, &request.sender, &request.data)
+}
+
+// ERC-3668 lookup in the GET form, `/ccip/{sender}/{data}.json`
+async fn ccip_get(
+    State(state): State<AdminState>,
+    Path((sender, data)): Path<(String, String)>,
+) -> Response {
+    let data = data.strip_suffix(".json").unwrap_or(&data);
+    ccip_response(&state.gateway, &sender, data)
+}
+
+// Gateway answer as ERC-3668 expects it: `{"data": ...}` on success and
+// `{"message": ...}` otherwise, readable from any origin
+fn ccip_response(gateway: &CcipGateway, sender: &str, data: &str) -> Response {
+    let lookup = || -> anyhow::Result<Bytes> {
+        let sender: Address = sender
+            .parse()
+            .map_err(|_| RelayerError::InvalidLookup(format!("invalid sender {}", sender)))?;
+        let data: Bytes = data
+            .parse()
+            .map_err(|_| RelayerError::InvalidLookup("call data is not hex".to_string()))?;
+        gateway.lookup(sender, &data)
+    };
+    let (status, body) = match lookup() {
+        Ok(proof) => (StatusCode::OK, json!({ "data": proof })),
+        Err(e) => {
+            let status = match e.downcast_ref::<RelayerError>() {
+                Some(RelayerError::InvalidLookup(_)) => StatusCode::BAD_REQUEST,
+                Some(RelayerError::ProofUnavailable { .. }) => StatusCode::NOT_FOUND,
+                _ => StatusCode::INTERNAL_SERVER_ERROR,
+            };
+            (status, json!({ "message": format!("{:#}", e) }))
+        }
+    };
+    (
+        status,
+        [(header::ACCESS_CONTROL_ALLOW_ORIGIN, "*")],
+        Json(body),
+    )
+        .into_response()
+}
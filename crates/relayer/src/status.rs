@@ -0,0 +1,251 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tracing::warn;
+
+use crate::latency;
+use crate::metrics::{PairLabels, EVENTS};
+use crate::store::{ProofKey, StateStore};
+use crate::stream;
+use crate::types::{DeliveryStatus, EventId, RelayEvent, RelayerError};
+
+/// Where an event stands in the pipeline
+///
+/// Events move forward from `Detected` until they are `Delivered`, which is
+/// final. `Failed` and `Expired` events stay put until an operator replays
+/// them, which sends them back to proving or delivery. Any unfinished event
+/// may go back to `Proving` when a restarted pipeline resumes it, and every
+/// unfinished one may fail, expire or turn out delivered already.
+///
+/// Events of `ccip_read` pairs end at `Proved`, their callers delivering
+/// them with the proof the gateway serves.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum EventStatus {
+    Detected,
+    Proving,
+    Proved,
+    /// Claimed for delivery and being submitted, possibly not for the first time
+    Delivering,
+    Delivered,
+    /// Given up on; the dead letter queue holds the event
+    Failed {
+        reason: String,
+    },
+    /// Abandoned after the pair's delivery deadline
+    Expired,
+}
+
+impl EventStatus {
+    /// Every status's name, in pipeline order
+    pub const NAMES: [&'static str; 7] = [
+        "detected",
+        "proving",
+        "proved",
+        "delivering",
+        "delivered",
+        "failed",
+        "expired",
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Detected => "detected",
+            Self::Proving => "proving",
+            Self::Proved => "proved",
+            Self::Delivering => "delivering",
+            Self::Delivered => "delivered",
+            Self::Failed { .. } => "failed",
+            Self::Expired => "expired",
+        }
+    }
+
+    /// Whether an event in this status may move to `next`
+    pub fn can_become(&self, next: &EventStatus) -> bool {
+        use EventStatus::*;
+        match (self, next) {
+            (Delivered, _) => false,
+            // Replayed by an operator
+            (Failed { .. } | Expired, Proving | Delivering) => true,
+            (Failed { .. } | Expired, _) => false,
+            (Detected, Detected) => true,
+            (_, Detected) => false,
+            (_, Proving | Delivered | Failed { .. } | Expired) => true,
+            (Proving, Proved) => true,
+            (Proved | Delivering, Delivering) => true,
+            _ => false,
+        }
+    }
+
+    /// The status a settled delivery attempt leaves its event in, or `None`
+    /// while it is still retrying
+    pub(crate) fn settled(status: &DeliveryStatus, error: Option<&str>) -> Option<Self> {
+        match status {
+            DeliveryStatus::Delivered | DeliveryStatus::AlreadyExecuted => Some(Self::Delivered),
+            DeliveryStatus::Expired => Some(Self::Expired),
+            DeliveryStatus::DeadLettered => Some(Self::Failed {
+                reason: error.unwrap_or_default().to_string(),
+            }),
+            DeliveryStatus::Retrying { .. } => None,
+        }
+    }
+}
+
+impl fmt::Display for EventStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Stored status of one event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventRecord {
+    pub event_id: EventId,
+    pub proof_key: ProofKey,
+    pub pair: String,
+    pub nonce: u64,
+    pub status: EventStatus,
+    /// Unix time in seconds the event entered its status
+    pub updated_at: u64,
+}
+
+impl EventRecord {
+    pub fn new(event: &RelayEvent, status: EventStatus) -> Self {
+        Self {
+            event_id: event.id(),
+            proof_key: ProofKey::from_meta(&event.meta),
+            pair: event.pair_id(),
+            nonce: event.nonce,
+            status,
+            updated_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_secs())
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Check that an event stored as `current` may move to this record's
+    /// status; an event with no stored status may enter any
+    pub(crate) fn follows(&self, current: Option<&EventRecord>) -> Result<(), RelayerError> {
+        match current {
+            Some(current) if !current.status.can_become(&self.status) => {
+                Err(RelayerError::InvalidTransition {
+                    event_id: self.event_id,
+                    from: current.status.name(),
+                    to: self.status.name(),
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Most event statuses one query returns
+pub const MAX_EVENT_PAGE: usize = 1_000;
+
+/// Which event statuses to return; unset fields match anything
+///
+/// Matching statuses come least recently changed first, at most `limit` of
+/// them after skipping `offset`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EventQuery {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pair: Option<String>,
+    /// Status name, e.g. `failed`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
+    /// Lowest nonce to return
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from_nonce: Option<u64>,
+    /// Highest nonce to return
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub to_nonce: Option<u64>,
+    /// Earliest Unix time in seconds the event may have entered its status
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub since: Option<u64>,
+    /// Latest Unix time in seconds the event may have entered its status
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub until: Option<u64>,
+    /// Defaults to, and is capped at, [`MAX_EVENT_PAGE`]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offset: Option<usize>,
+}
+
+impl EventQuery {
+    pub fn matches(&self, record: &EventRecord) -> bool {
+        self.pair
+            .as_ref()
+            .is_none_or(|pair| pair.eq_ignore_ascii_case(&record.pair))
+            && self
+                .status
+                .as_ref()
+                .is_none_or(|status| status.eq_ignore_ascii_case(record.status.name()))
+            && self.from_nonce.is_none_or(|from| record.nonce >= from)
+            && self.to_nonce.is_none_or(|to| record.nonce <= to)
+            && self.since.is_none_or(|since| record.updated_at >= since)
+            && self.until.is_none_or(|until| record.updated_at <= until)
+    }
+
+    /// How many matching statuses to return at most
+    pub fn page_size(&self) -> usize {
+        self.limit.unwrap_or(MAX_EVENT_PAGE).min(MAX_EVENT_PAGE)
+    }
+
+    /// Reject queries that can match nothing by construction, so a typo
+    /// doesn't pass for an empty history
+    pub fn validate(&self) -> Result<(), RelayerError> {
+        if let Some(status) = &self.status {
+            if !EventStatus::NAMES
+                .iter()
+                .any(|name| name.eq_ignore_ascii_case(status))
+            {
+                return Err(RelayerError::InvalidQuery(format!(
+                    "unknown status {}, expected one of {}",
+                    status,
+                    EventStatus::NAMES.join(", ")
+                )));
+            }
+        }
+        if let (Some(from), Some(to)) = (self.from_nonce, self.to_nonce) {
+            if from > to {
+                return Err(RelayerError::InvalidQuery(format!(
+                    "from_nonce {} is above to_nonce {}",
+                    from, to
+                )));
+            }
+        }
+        if let (Some(since), Some(until)) = (self.since, self.until) {
+            if since > until {
+                return Err(RelayerError::InvalidQuery(format!(
+                    "since {} is after until {}",
+                    since, until
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Move `event` to `status` in the store, publishing the change to the event
+/// stream if one is configured
+///
+/// A refused transition is logged and leaves the stored status as it was;
+/// the pipeline carries on either way.
+pub(crate) fn advance(store: &dyn StateStore, event: &RelayEvent, status: EventStatus) {
+    let record = EventRecord::new(event, status);
+    match store.transition_event(&record) {
+        Ok(()) => {
+            EVENTS
+                .with_label_values(&PairLabels::of(event).and(&[record.status.name()]))
+                .inc();
+            stream::publish(&record);
+            latency::record(event, &record.status);
+        }
+        Err(e) => warn!(error = %e, event_id = %record.event_id, "Failed to record event status"),
+    }
+}
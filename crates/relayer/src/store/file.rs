@@ -0,0 +1,386 @@
+use anyhow::{anyhow, Context, Result};
+use ethers::core::types::H256;
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+use tracing::info;
+
+use super::{
+    DeliveryClaim, DeliveryQuery, FailedDelivery, LedgerEntry, ProofKey, ProofRecord, StateStore,
+};
+use crate::accounting::GasCostRecord;
+use crate::fee_claims::FeeClaimRecord;
+use crate::status::{EventQuery, EventRecord};
+use crate::types::{DeliveryOutcome, DeliveryRequest, EventId, Proof, RelayEvent, RelayerError};
+
+const PROOFS_FILE: &str = "proofs.json";
+const PENDING_EVENTS_FILE: &str = "pending_events.json";
+const GAS_COSTS_FILE: &str = "gas_costs.json";
+const FEE_CLAIMS_FILE: &str = "fee_claims.json";
+const RETRIES_FILE: &str = "retries.json";
+const DEAD_LETTERS_FILE: &str = "dead_letters.json";
+const DELIVERIES_FILE: &str = "deliveries.json";
+const SPILLED_FILE: &str = "spilled_deliveries.json";
+const LEDGER_FILE: &str = "delivery_ledger.json";
+const EVENTS_FILE: &str = "event_statuses.json";
+
+// A keyed collection mirrored to a single JSON file
+struct Collection<T> {
+    path: PathBuf,
+    entries: Mutex<HashMap<String, T>>,
+}
+
+impl<T: Serialize + DeserializeOwned + Clone> Collection<T> {
+    fn open(path: PathBuf) -> Result<Self> {
+        let entries = load(&path)?;
+        Ok(Self {
+            path,
+            entries: Mutex::new(entries),
+        })
+    }
+
+    fn len(&self) -> usize {
+        self.entries.lock().map(|entries| entries.len()).unwrap_or(0)
+    }
+
+    fn get(&self, key: &str) -> Result<Option<T>> {
+        let entries = self
+            .entries
+            .lock()
+            .map_err(|_| anyhow!("State store lock poisoned"))?;
+        Ok(entries.get(key).cloned())
+    }
+
+    fn values(&self) -> Result<Vec<T>> {
+        let entries = self
+            .entries
+            .lock()
+            .map_err(|_| anyhow!("State store lock poisoned"))?;
+        Ok(entries.values().cloned().collect())
+    }
+
+    fn update<F, R>(&self, f: F) -> Result<R>
+    where
+        F: FnOnce(&mut HashMap<String, T>) -> R,
+    {
+        let mut entries = self
+            .entries
+            .lock()
+            .map_err(|_| anyhow!("State store lock poisoned"))?;
+        let result = f(&mut entries);
+        persist(&self.path, &*entries)?;
+        Ok(result)
+    }
+}
+
+/// State store that keeps everything in memory and mirrors it to JSON files
+/// in a local directory
+pub struct FileStateStore {
+    proofs: Collection<ProofRecord>,
+    pending_events: Collection<RelayEvent>,
+    gas_costs: Collection<GasCostRecord>,
+    fee_claims: Collection<FeeClaimRecord>,
+    retries: Collection<FailedDelivery>,
+    dead_letters: Collection<FailedDelivery>,
+    deliveries: Collection<DeliveryOutcome>,
+    spilled: Collection<DeliveryRequest>,
+    ledger: Collection<LedgerEntry>,
+    events: Collection<EventRecord>,
+}
+
+impl FileStateStore {
+    pub fn open(dir: impl Into<PathBuf>) -> Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)
+            .context(format!("Failed to create state directory {}", dir.display()))?;
+
+        let store = Self {
+            proofs: Collection::open(dir.join(PROOFS_FILE))?,
+            pending_events: Collection::open(dir.join(PENDING_EVENTS_FILE))?,
+            gas_costs: Collection::open(dir.join(GAS_COSTS_FILE))?,
+            fee_claims: Collection::open(dir.join(FEE_CLAIMS_FILE))?,
+            retries: Collection::open(dir.join(RETRIES_FILE))?,
+            dead_letters: Collection::open(dir.join(DEAD_LETTERS_FILE))?,
+            deliveries: Collection::open(dir.join(DELIVERIES_FILE))?,
+            spilled: Collection::open(dir.join(SPILLED_FILE))?,
+            ledger: Collection::open(dir.join(LEDGER_FILE))?,
+            events: Collection::open(dir.join(EVENTS_FILE))?,
+        };
+        info!(
+            state_dir = %dir.display(),
+            proofs = store.proofs.len(),
+            pending_events = store.pending_events.len(),
+            retries = store.retries.len(),
+            dead_letters = store.dead_letters.len(),
+            spilled = store.spilled.len(),
+            "Opened state store"
+        );
+
+        Ok(store)
+    }
+}
+
+impl StateStore for FileStateStore {
+    fn proof(&self, key: &ProofKey) -> Result<Option<ProofRecord>> {
+        self.proofs.get(&key.to_string())
+    }
+
+    fn save_proof_job(&self, key: &ProofKey, job_id: i64) -> Result<()> {
+        self.proofs.update(|proofs| {
+            proofs.insert(
+                key.to_string(),
+                ProofRecord {
+                    job_id,
+                    proof: None,
+                    metadata: Default::default(),
+                },
+            );
+        })
+    }
+
+    fn save_proof(&self, key: &ProofKey, proof: &Proof) -> Result<()> {
+        self.proofs.update(|proofs| {
+            if let Some(record) = proofs.get_mut(&key.to_string()) {
+                record.proof = Some(proof.data.clone());
+                record.metadata = proof.metadata.clone();
+            }
+        })
+    }
+
+    fn remove_proof(&self, key: &ProofKey) -> Result<()> {
+        self.proofs.update(|proofs| {
+            proofs.remove(&key.to_string());
+        })
+    }
+
+    fn save_pending_event(&self, key: &ProofKey, event: &RelayEvent) -> Result<()> {
+        self.pending_events.update(|events| {
+            events.insert(key.to_string(), event.clone());
+        })
+    }
+
+    fn pending_events(&self) -> Result<Vec<RelayEvent>> {
+        self.pending_events.values()
+    }
+
+    fn remove_pending_event(&self, key: &ProofKey) -> Result<()> {
+        self.pending_events.update(|events| {
+            events.remove(&key.to_string());
+        })
+    }
+
+    fn save_gas_cost(&self, record: &GasCostRecord) -> Result<()> {
+        self.gas_costs.update(|costs| {
+            costs.insert(format!("{:?}", record.tx_hash), record.clone());
+        })
+    }
+
+    fn gas_costs(&self) -> Result<Vec<GasCostRecord>> {
+        self.gas_costs.values()
+    }
+
+    fn save_fee_claim(&self, record: &FeeClaimRecord) -> Result<()> {
+        self.fee_claims.update(|claims| {
+            claims.insert(format!("{:?}", record.tx_hash), record.clone());
+        })
+    }
+
+    fn fee_claims(&self) -> Result<Vec<FeeClaimRecord>> {
+        self.fee_claims.values()
+    }
+
+    fn save_retry(&self, key: &ProofKey, delivery: &FailedDelivery) -> Result<()> {
+        self.retries.update(|retries| {
+            retries.insert(key.to_string(), delivery.clone());
+        })
+    }
+
+    fn retry(&self, key: &ProofKey) -> Result<Option<FailedDelivery>> {
+        self.retries.get(&key.to_string())
+    }
+
+    fn retries(&self) -> Result<Vec<FailedDelivery>> {
+        self.retries.values()
+    }
+
+    fn remove_retry(&self, key: &ProofKey) -> Result<()> {
+        // Most finished deliveries were never retried; skip rewriting the file for them
+        if self.retry(key)?.is_none() {
+            return Ok(());
+        }
+        self.retries.update(|retries| {
+            retries.remove(&key.to_string());
+        })
+    }
+
+    fn save_dead_letter(&self, key: &ProofKey, delivery: &FailedDelivery) -> Result<()> {
+        self.dead_letters.update(|letters| {
+            letters.insert(key.to_string(), delivery.clone());
+        })
+    }
+
+    fn dead_letter(&self, key: &ProofKey) -> Result<Option<FailedDelivery>> {
+        self.dead_letters.get(&key.to_string())
+    }
+
+    fn dead_letters(&self) -> Result<Vec<FailedDelivery>> {
+        self.dead_letters.values()
+    }
+
+    fn remove_dead_letter(&self, key: &ProofKey) -> Result<()> {
+        self.dead_letters.update(|letters| {
+            letters.remove(&key.to_string());
+        })
+    }
+
+    fn spill_delivery(&self, key: &ProofKey, request: &DeliveryRequest) -> Result<()> {
+        self.spilled.update(|spilled| {
+            spilled.insert(key.to_string(), request.clone());
+        })
+    }
+
+    fn spilled_deliveries(&self) -> Result<Vec<DeliveryRequest>> {
+        self.spilled.values()
+    }
+
+    fn remove_spilled_delivery(&self, key: &ProofKey) -> Result<()> {
+        self.spilled.update(|spilled| {
+            spilled.remove(&key.to_string());
+        })
+    }
+
+    fn save_delivery_attempt(&self, outcome: &DeliveryOutcome) -> Result<()> {
+        let key = format!(
+            "{}#{}@{}",
+            outcome.proof_key, outcome.attempt, outcome.attempted_at
+        );
+        self.deliveries.update(|deliveries| {
+            deliveries.insert(key, outcome.clone());
+        })
+    }
+
+    fn delivery_history(&self, query: &DeliveryQuery) -> Result<Vec<DeliveryOutcome>> {
+        let mut history: Vec<DeliveryOutcome> = self
+            .deliveries
+            .values()?
+            .into_iter()
+            .filter(|outcome| query.matches(outcome))
+            .collect();
+        history.sort_by_key(|outcome| (outcome.attempted_at, outcome.attempt));
+        Ok(history)
+    }
+
+    // Only this process can reach the files, so the collection lock is
+    // enough to make claims atomic
+    fn claim_delivery(
+        &self,
+        pair: &str,
+        nonce: u64,
+        owner: &str,
+        stale_after_secs: u64,
+    ) -> Result<DeliveryClaim> {
+        self.ledger.update(|ledger| {
+            let key = LedgerEntry::key(pair, nonce);
+            if let Some(refusal) = ledger
+                .get(&key)
+                .and_then(|entry| entry.refuses(owner, stale_after_secs))
+            {
+                return refusal;
+            }
+            ledger.insert(key, LedgerEntry::claimed(pair, nonce, owner));
+            DeliveryClaim::Claimed
+        })
+    }
+
+    fn confirm_delivery(&self, pair: &str, nonce: u64, tx_hash: Option<H256>) -> Result<()> {
+        self.ledger.update(|ledger| {
+            let entry = ledger
+                .entry(LedgerEntry::key(pair, nonce))
+                .or_insert_with(|| LedgerEntry::claimed(pair, nonce, ""));
+            entry.delivered = true;
+            // Finding it executed later says nothing of the transaction
+            entry.tx_hash = tx_hash.or(entry.tx_hash);
+        })
+    }
+
+    fn release_delivery(&self, pair: &str, nonce: u64, owner: &str) -> Result<()> {
+        let key = LedgerEntry::key(pair, nonce);
+        self.ledger.update(|ledger| {
+            if ledger
+                .get(&key)
+                .is_some_and(|entry| !entry.delivered && entry.owner == owner)
+            {
+                ledger.remove(&key);
+            }
+        })
+    }
+
+    fn ledger_entry(&self, pair: &str, nonce: u64) -> Result<Option<LedgerEntry>> {
+        self.ledger.get(&LedgerEntry::key(pair, nonce))
+    }
+
+    fn last_delivered_nonces(&self) -> Result<HashMap<String, u64>> {
+        let mut nonces = HashMap::new();
+        for entry in self.ledger.values()? {
+            if entry.delivered {
+                let last = nonces.entry(entry.pair).or_default();
+                *last = entry.nonce.max(*last);
+            }
+        }
+        Ok(nonces)
+    }
+
+    fn transition_event(&self, record: &EventRecord) -> Result<()> {
+        let key = record.event_id.to_string();
+        self.events.update(|events| {
+            record.follows(events.get(&key))?;
+            events.insert(key, record.clone());
+            Ok::<_, RelayerError>(())
+        })??;
+        Ok(())
+    }
+
+    fn event_record(&self, id: &EventId) -> Result<Option<EventRecord>> {
+        self.events.get(&id.to_string())
+    }
+
+    fn event_records(&self) -> Result<Vec<EventRecord>> {
+        self.events.values()
+    }
+
+    fn query_events(&self, query: &EventQuery) -> Result<Vec<EventRecord>> {
+        let mut records: Vec<EventRecord> = self
+            .events
+            .values()?
+            .into_iter()
+            .filter(|record| query.matches(record))
+            .collect();
+        records.sort_by_cached_key(|record| (record.updated_at, record.event_id.to_string()));
+        Ok(records
+            .into_iter()
+            .skip(query.offset.unwrap_or_default())
+            .take(query.page_size())
+            .collect())
+    }
+}
+
+fn load<T: DeserializeOwned + Default>(path: &Path) -> Result<T> {
+    if !path.exists() {
+        return Ok(T::default());
+    }
+    let data = fs::read(path).context(format!("Failed to read {}", path.display()))?;
+    serde_json::from_slice(&data).context(format!("Failed to parse {}", path.display()))
+}
+
+// Write to a temporary file first so a crash never leaves a truncated file behind
+fn persist<T: Serialize>(path: &Path, value: &T) -> Result<()> {
+    let tmp = path.with_extension("json.tmp");
+    let data = serde_json::to_vec_pretty(value)?;
+    fs::write(&tmp, data).context(format!("Failed to write {}", tmp.display()))?;
+    fs::rename(&tmp, path).context(format!("Failed to replace {}", path.display()))?;
+    Ok(())
+}
@@ -0,0 +1,435 @@
+mod file;
+mod sqlite;
+
+pub use self::file::FileStateStore;
+pub use self::sqlite::SqliteStateStore;
+
+use anyhow::{anyhow, Result};
+use ethers::core::types::{Address, Bytes, H256};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fmt,
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::accounting::{GasCostKind, GasCostRecord};
+use crate::fee_claims::FeeClaimRecord;
+use crate::status::{EventQuery, EventRecord};
+use crate::types::{
+    ChainId, DeliveryOutcome, DeliveryRequest, EventId, EventMeta, Proof, ProofMetadata, RelayEvent,
+};
+
+// Identifies the source log a proof was generated for
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ProofKey {
+    pub chain_id: ChainId,
+    pub block_number: u64,
+    pub tx_index: u32,
+    pub log_index: u32,
+}
+
+impl ProofKey {
+    pub fn from_meta(meta: &EventMeta) -> Self {
+        Self {
+            chain_id: meta.chain_id,
+            block_number: meta.block_number,
+            tx_index: meta.tx_index,
+            log_index: meta.log_index,
+        }
+    }
+}
+
+impl fmt::Display for ProofKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}:{}",
+            self.chain_id, self.block_number, self.tx_index, self.log_index
+        )
+    }
+}
+
+impl FromStr for ProofKey {
+    type Err = anyhow::Error;
+
+    /// Parse the `chain:block:tx:log` form the key is displayed in
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || anyhow!("Invalid proof key {:?}, expected chain:block:tx:log", s);
+        let mut parts = s.split(':');
+        let mut next = || parts.next().ok_or_else(invalid);
+        let key = Self {
+            chain_id: next()?.parse().map_err(|_| invalid())?,
+            block_number: next()?.parse().map_err(|_| invalid())?,
+            tx_index: next()?.parse().map_err(|_| invalid())?,
+            log_index: next()?.parse().map_err(|_| invalid())?,
+        };
+        if parts.next().is_some() {
+            return Err(invalid());
+        }
+        Ok(key)
+    }
+}
+
+// Proof job state persisted across restarts
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProofRecord {
+    pub job_id: i64,
+    pub proof: Option<Bytes>,
+    #[serde(default)]
+    pub metadata: ProofMetadata,
+}
+
+impl ProofRecord {
+    /// The stored proof, if generation has finished
+    pub fn to_proof(&self) -> Option<Proof> {
+        self.proof.clone().map(|data| Proof {
+            data,
+            metadata: self.metadata.clone(),
+        })
+    }
+}
+
+/// Pipeline stage an event failed in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FailureStage {
+    /// No proof could be produced; the proof fields are empty
+    Proof,
+    #[default]
+    Delivery,
+}
+
+/// A failed delivery, with everything needed to send it again
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FailedDelivery {
+    pub event: RelayEvent,
+    pub destination_contract_address: Address,
+    pub proof: Bytes,
+    #[serde(default)]
+    pub proof_metadata: ProofMetadata,
+    /// Delivery attempts made so far
+    pub attempts: u32,
+    /// Unix time in seconds the next attempt is due; unused once dead-lettered
+    pub next_attempt_at: u64,
+    pub last_error: String,
+    #[serde(default)]
+    pub stage: FailureStage,
+}
+
+impl FailedDelivery {
+    pub fn new(delivery: &DeliveryRequest, attempts: u32, last_error: String) -> Self {
+        Self {
+            event: delivery.event.clone(),
+            destination_contract_address: delivery.destination_contract_address,
+            proof: delivery.proof.data.clone(),
+            proof_metadata: delivery.proof.metadata.clone(),
+            attempts,
+            next_attempt_at: 0,
+            last_error,
+            stage: FailureStage::Delivery,
+        }
+    }
+
+    /// An event whose proof could not be produced
+    pub fn unproven(event: &RelayEvent, last_error: String) -> Self {
+        Self {
+            event: event.clone(),
+            destination_contract_address: event.dest_dapp_address,
+            proof: Bytes::new(),
+            proof_metadata: ProofMetadata::default(),
+            attempts: 1,
+            next_attempt_at: 0,
+            last_error,
+            stage: FailureStage::Proof,
+        }
+    }
+
+    pub fn key(&self) -> ProofKey {
+        ProofKey::from_meta(&self.event.meta)
+    }
+
+    /// Rebuild the delivery request for another attempt
+    pub fn to_request(&self) -> DeliveryRequest {
+        DeliveryRequest {
+            destination_chain_id: self.event.destination_chain.chain_id,
+            destination_contract_address: self.destination_contract_address,
+            event: self.event.clone(),
+            proof: Proof {
+                data: self.proof.clone(),
+                metadata: self.proof_metadata.clone(),
+            },
+        }
+    }
+}
+
+/// Which delivery attempts to return from the history; unset fields match anything
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct DeliveryQuery {
+    pub pair: Option<String>,
+    pub source_chain_id: Option<ChainId>,
+    pub dest_chain_id: Option<ChainId>,
+    pub nonce: Option<u64>,
+    pub tx_hash: Option<H256>,
+    pub event_id: Option<EventId>,
+}
+
+impl DeliveryQuery {
+    pub fn matches(&self, outcome: &DeliveryOutcome) -> bool {
+        let pair_matches = match (&self.pair, &outcome.pair) {
+            (None, _) => true,
+            (Some(wanted), Some(pair)) => wanted.eq_ignore_ascii_case(pair),
+            (Some(_), None) => false,
+        };
+        pair_matches
+            && self
+                .source_chain_id
+                .is_none_or(|id| id == outcome.source_chain_id)
+            && self
+                .dest_chain_id
+                .is_none_or(|id| id == outcome.dest_chain_id)
+            && self.nonce.is_none_or(|nonce| nonce == outcome.nonce)
+            && self
+                .tx_hash
+                .is_none_or(|hash| Some(hash) == outcome.tx_hash)
+            && self.event_id.is_none_or(|id| id == outcome.event_id)
+    }
+}
+
+/// Which dead letters to return or replay; unset fields match anything
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DeadLetterQuery {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source_chain_id: Option<ChainId>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dest_chain_id: Option<ChainId>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stage: Option<FailureStage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub event_id: Option<EventId>,
+}
+
+impl DeadLetterQuery {
+    pub fn matches(&self, failed: &FailedDelivery) -> bool {
+        self.source_chain_id
+            .is_none_or(|id| id == failed.event.source_chain.chain_id)
+            && self
+                .dest_chain_id
+                .is_none_or(|id| id == failed.event.destination_chain.chain_id)
+            && self.stage.is_none_or(|stage| stage == failed.stage)
+            && self.event_id.is_none_or(|id| id == failed.event.id())
+    }
+}
+
+/// Entry of the delivery ledger, which records each application nonce of a
+/// relay pair that is being or has been delivered
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LedgerEntry {
+    pub pair: String,
+    pub nonce: u64,
+    /// Instance that claimed the delivery
+    pub owner: String,
+    /// Unix time in seconds the claim was taken
+    pub claimed_at: u64,
+    pub delivered: bool,
+    /// Transaction that carried the delivery, if one did
+    pub tx_hash: Option<H256>,
+}
+
+/// Answer to a claim on delivering an application nonce
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeliveryClaim {
+    /// The caller holds the claim and may deliver
+    Claimed,
+    /// Delivered already, so it must not be delivered again
+    Delivered(Option<H256>),
+    /// Another instance claimed it recently and may be delivering it now
+    Held,
+}
+
+impl LedgerEntry {
+    pub(crate) fn key(pair: &str, nonce: u64) -> String {
+        format!("{}#{}", pair, nonce)
+    }
+
+    pub(crate) fn claimed(pair: &str, nonce: u64, owner: &str) -> Self {
+        Self {
+            pair: pair.to_string(),
+            nonce,
+            owner: owner.to_string(),
+            claimed_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_secs())
+                .unwrap_or_default(),
+            delivered: false,
+            tx_hash: None,
+        }
+    }
+
+    /// Why `owner` may not claim this entry, if it may not; claims left by
+    /// other owners for `stale_after_secs` are presumed abandoned
+    pub(crate) fn refuses(&self, owner: &str, stale_after_secs: u64) -> Option<DeliveryClaim> {
+        if self.delivered {
+            return Some(DeliveryClaim::Delivered(self.tx_hash));
+        }
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or_default();
+        let live = now < self.claimed_at.saturating_add(stale_after_secs);
+        (self.owner != owner && live).then_some(DeliveryClaim::Held)
+    }
+}
+
+/// Durable storage for relayer state that must survive a restart
+pub trait StateStore: Send + Sync {
+    /// Look up the proof job (and proof, once generated) for a source log
+    fn proof(&self, key: &ProofKey) -> Result<Option<ProofRecord>>;
+
+    /// Record the job ID returned by the proof API before polling it
+    fn save_proof_job(&self, key: &ProofKey, job_id: i64) -> Result<()>;
+
+    /// Record a generated proof against its job
+    fn save_proof(&self, key: &ProofKey, proof: &Proof) -> Result<()>;
+
+    /// Drop a proof record once it is no longer needed
+    fn remove_proof(&self, key: &ProofKey) -> Result<()>;
+
+    /// Remember an event that has entered the proof stage but not been delivered
+    fn save_pending_event(&self, key: &ProofKey, event: &RelayEvent) -> Result<()>;
+
+    /// Events left undelivered by a previous run
+    fn pending_events(&self) -> Result<Vec<RelayEvent>>;
+
+    /// Forget an event once it has been delivered
+    fn remove_pending_event(&self, key: &ProofKey) -> Result<()>;
+
+    /// Append the gas cost of a relayer transaction to the spend history
+    fn save_gas_cost(&self, record: &GasCostRecord) -> Result<()>;
+
+    /// Every recorded gas cost
+    fn gas_costs(&self) -> Result<Vec<GasCostRecord>>;
+
+    /// The latest `limit` gas costs of `kind` recorded for `pair`, newest first
+    fn recent_gas_costs(
+        &self,
+        pair: &str,
+        kind: GasCostKind,
+        limit: usize,
+    ) -> Result<Vec<GasCostRecord>> {
+        let mut records: Vec<_> = self
+            .gas_costs()?
+            .into_iter()
+            .filter(|record| record.kind == kind && record.pair == pair)
+            .collect();
+        records.sort_by_key(|record| std::cmp::Reverse(record.recorded_at));
+        records.truncate(limit);
+        Ok(records)
+    }
+
+    /// Append a sent fee claim to the claim history
+    fn save_fee_claim(&self, record: &FeeClaimRecord) -> Result<()>;
+
+    /// Every recorded fee claim
+    fn fee_claims(&self) -> Result<Vec<FeeClaimRecord>>;
+
+    /// Queue a failed delivery for another attempt, replacing any earlier entry
+    fn save_retry(&self, key: &ProofKey, delivery: &FailedDelivery) -> Result<()>;
+
+    /// Look up the queued retry of a delivery
+    fn retry(&self, key: &ProofKey) -> Result<Option<FailedDelivery>>;
+
+    /// Deliveries waiting to be retried
+    fn retries(&self) -> Result<Vec<FailedDelivery>>;
+
+    /// Take a delivery off the retry queue
+    fn remove_retry(&self, key: &ProofKey) -> Result<()>;
+
+    /// Park a delivery that will not be retried again
+    fn save_dead_letter(&self, key: &ProofKey, delivery: &FailedDelivery) -> Result<()>;
+
+    /// Look up a parked delivery
+    fn dead_letter(&self, key: &ProofKey) -> Result<Option<FailedDelivery>>;
+
+    /// Deliveries given up on
+    fn dead_letters(&self) -> Result<Vec<FailedDelivery>>;
+
+    /// Take a delivery out of the dead letter queue
+    fn remove_dead_letter(&self, key: &ProofKey) -> Result<()>;
+
+    /// Park a proven event while the delivery queue is full
+    fn spill_delivery(&self, key: &ProofKey, request: &DeliveryRequest) -> Result<()>;
+
+    /// Proven events waiting for room in the delivery queue
+    fn spilled_deliveries(&self) -> Result<Vec<DeliveryRequest>>;
+
+    /// Take a proven event off the spill once it is queued for delivery
+    fn remove_spilled_delivery(&self, key: &ProofKey) -> Result<()>;
+
+    /// Append a delivery attempt to the delivery history
+    fn save_delivery_attempt(&self, outcome: &DeliveryOutcome) -> Result<()>;
+
+    /// Delivery attempts matching `query`, oldest first
+    fn delivery_history(&self, query: &DeliveryQuery) -> Result<Vec<DeliveryOutcome>>;
+
+    /// Claim the delivery of `nonce` of `pair` for `owner`, unless it was
+    /// delivered already or another owner's claim is younger than
+    /// `stale_after_secs`
+    ///
+    /// Claiming is atomic, also between instances sharing the store.
+    fn claim_delivery(
+        &self,
+        pair: &str,
+        nonce: u64,
+        owner: &str,
+        stale_after_secs: u64,
+    ) -> Result<DeliveryClaim>;
+
+    /// Record a claimed delivery as done, for good
+    fn confirm_delivery(&self, pair: &str, nonce: u64, tx_hash: Option<H256>) -> Result<()>;
+
+    /// Give up `owner`'s claim after a failed delivery, so it can be claimed again
+    fn release_delivery(&self, pair: &str, nonce: u64, owner: &str) -> Result<()>;
+
+    /// Look up the ledger entry for `nonce` of `pair`
+    fn ledger_entry(&self, pair: &str, nonce: u64) -> Result<Option<LedgerEntry>>;
+
+    /// Highest nonce the ledger records as delivered, by relay pair
+    fn last_delivered_nonces(&self) -> Result<HashMap<String, u64>>;
+
+    /// Move an event to the status `record` gives, failing with
+    /// `RelayerError::InvalidTransition` if its stored status cannot become
+    /// that
+    ///
+    /// Checking and writing is atomic, also between instances sharing the store.
+    fn transition_event(&self, record: &EventRecord) -> Result<()>;
+
+    /// Look up the stored status of an event
+    fn event_record(&self, id: &EventId) -> Result<Option<EventRecord>>;
+
+    /// The stored status of every event
+    fn event_records(&self) -> Result<Vec<EventRecord>>;
+
+    /// The page of stored statuses `query` asks for, least recently changed
+    /// first
+    fn query_events(&self, query: &EventQuery) -> Result<Vec<EventRecord>>;
+
+    /// The stored statuses of events with `nonce` of `pair`, more than one
+    /// only if the pair emitted the nonce more than once
+    fn events_at_nonce(&self, pair: &str, nonce: u64) -> Result<Vec<EventRecord>> {
+        self.query_events(&EventQuery {
+            pair: Some(pair.to_string()),
+            from_nonce: Some(nonce),
+            to_nonce: Some(nonce),
+            ..Default::default()
+        })
+    }
+
+    /// Make sure everything written so far survives the process exiting
+    fn flush(&self) -> Result<()> {
+        Ok(())
+    }
+}
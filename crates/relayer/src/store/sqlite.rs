@@ -0,0 +1,549 @@
+use anyhow::{anyhow, Context, Result};
+use ethers::core::types::H256;
+use rusqlite::{params, Connection, OptionalExtension, TransactionBehavior};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    collections::HashMap,
+    fs,
+    path::PathBuf,
+    sync::{Mutex, MutexGuard},
+};
+use tracing::info;
+
+use super::{
+    DeliveryClaim, DeliveryQuery, FailedDelivery, LedgerEntry, ProofKey, ProofRecord, StateStore,
+};
+use crate::accounting::{GasCostKind, GasCostRecord};
+use crate::fee_claims::FeeClaimRecord;
+use crate::status::{EventQuery, EventRecord};
+use crate::types::{
+    ChainId, DeliveryOutcome, DeliveryRequest, EventId, Proof, RelayEvent, RelayerError,
+};
+
+const DATABASE_FILE: &str = "relayer.db";
+
+// Tables holding one JSON document per key
+const PROOFS: &str = "proofs";
+const PENDING_EVENTS: &str = "pending_events";
+const GAS_COSTS: &str = "gas_costs";
+const FEE_CLAIMS: &str = "fee_claims";
+const RETRIES: &str = "retries";
+const DEAD_LETTERS: &str = "dead_letters";
+const SPILLED_DELIVERIES: &str = "spilled_deliveries";
+const DELIVERY_LEDGER: &str = "delivery_ledger";
+const EVENT_STATUSES: &str = "event_statuses";
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS proofs (key TEXT PRIMARY KEY, value TEXT NOT NULL);
+    CREATE TABLE IF NOT EXISTS pending_events (key TEXT PRIMARY KEY, value TEXT NOT NULL);
+    CREATE TABLE IF NOT EXISTS gas_costs (key TEXT PRIMARY KEY, value TEXT NOT NULL);
+    CREATE TABLE IF NOT EXISTS fee_claims (key TEXT PRIMARY KEY, value TEXT NOT NULL);
+    CREATE TABLE IF NOT EXISTS retries (key TEXT PRIMARY KEY, value TEXT NOT NULL);
+    CREATE TABLE IF NOT EXISTS dead_letters (key TEXT PRIMARY KEY, value TEXT NOT NULL);
+    CREATE TABLE IF NOT EXISTS spilled_deliveries (key TEXT PRIMARY KEY, value TEXT NOT NULL);
+    CREATE TABLE IF NOT EXISTS delivery_ledger (key TEXT PRIMARY KEY, value TEXT NOT NULL);
+    CREATE TABLE IF NOT EXISTS event_statuses (key TEXT PRIMARY KEY, value TEXT NOT NULL);
+    CREATE TABLE IF NOT EXISTS deliveries (
+        key TEXT PRIMARY KEY,
+        pair TEXT,
+        source_chain_id INTEGER NOT NULL,
+        dest_chain_id INTEGER NOT NULL,
+        nonce INTEGER NOT NULL,
+        tx_hash TEXT,
+        event_id TEXT,
+        attempted_at INTEGER NOT NULL,
+        attempt INTEGER NOT NULL,
+        value TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS deliveries_by_nonce ON deliveries (dest_chain_id, nonce);
+    CREATE INDEX IF NOT EXISTS deliveries_by_tx_hash ON deliveries (tx_hash);
+    CREATE INDEX IF NOT EXISTS event_statuses_by_nonce ON event_statuses (
+        json_extract(value, '$.pair'),
+        json_extract(value, '$.nonce')
+    );
+    CREATE INDEX IF NOT EXISTS gas_costs_by_pair ON gas_costs (
+        json_extract(value, '$.pair'),
+        json_extract(value, '$.kind'),
+        json_extract(value, '$.recorded_at')
+    );
+";
+
+// Databases created before event ids have no event_id column; attempts
+// recorded there keep a NULL id
+fn add_event_ids(conn: &Connection) -> Result<()> {
+    let has_column = conn
+        .prepare("SELECT 1 FROM pragma_table_info('deliveries') WHERE name = 'event_id'")?
+        .exists([])?;
+    if !has_column {
+        conn.execute_batch("ALTER TABLE deliveries ADD COLUMN event_id TEXT")?;
+    }
+    conn.execute_batch(
+        "CREATE INDEX IF NOT EXISTS deliveries_by_event_id ON deliveries (event_id)",
+    )?;
+    Ok(())
+}
+
+/// State store backed by a SQLite database in a local directory
+///
+/// Every write is its own transaction, so a crash loses at most the write in
+/// progress.
+pub struct SqliteStateStore {
+    conn: Mutex<Connection>,
+}
+
+impl SqliteStateStore {
+    pub fn open(dir: impl Into<PathBuf>) -> Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir).context(format!(
+            "Failed to create state directory {}",
+            dir.display()
+        ))?;
+
+        let path = dir.join(DATABASE_FILE);
+        let conn = Connection::open(&path)
+            .context(format!("Failed to open state database {}", path.display()))?;
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        conn.execute_batch(SCHEMA)
+            .context("Failed to create state database schema")?;
+        add_event_ids(&conn).context("Failed to add event ids to delivery history")?;
+
+        let store = Self {
+            conn: Mutex::new(conn),
+        };
+        info!(
+            state_db = %path.display(),
+            proofs = store.count(PROOFS)?,
+            pending_events = store.count(PENDING_EVENTS)?,
+            retries = store.count(RETRIES)?,
+            dead_letters = store.count(DEAD_LETTERS)?,
+            spilled = store.count(SPILLED_DELIVERIES)?,
+            "Opened state store"
+        );
+
+        Ok(store)
+    }
+
+    fn conn(&self) -> Result<MutexGuard<'_, Connection>> {
+        self.conn
+            .lock()
+            .map_err(|_| anyhow!("State store lock poisoned"))
+    }
+
+    fn count(&self, table: &str) -> Result<u64> {
+        let count: i64 =
+            self.conn()?
+                .query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| {
+                    row.get(0)
+                })?;
+        Ok(count as u64)
+    }
+
+    fn get<T: DeserializeOwned>(&self, table: &str, key: &str) -> Result<Option<T>> {
+        let value: Option<String> = self
+            .conn()?
+            .query_row(
+                &format!("SELECT value FROM {} WHERE key = ?1", table),
+                [key],
+                |row| row.get(0),
+            )
+            .optional()?;
+        value
+            .map(|value| serde_json::from_str(&value))
+            .transpose()
+            .context(format!("Failed to parse {} entry {}", table, key))
+    }
+
+    fn values<T: DeserializeOwned>(&self, table: &str) -> Result<Vec<T>> {
+        let conn = self.conn()?;
+        let mut statement = conn.prepare(&format!("SELECT value FROM {} ORDER BY key", table))?;
+        let values = statement
+            .query_map([], |row| row.get::<_, String>(0))?
+            .map(|value| Ok(serde_json::from_str(&value?)?))
+            .collect::<Result<Vec<T>>>()
+            .context(format!("Failed to read {}", table))?;
+        Ok(values)
+    }
+
+    fn put<T: Serialize>(&self, table: &str, key: &str, value: &T) -> Result<()> {
+        self.conn()?.execute(
+            &format!(
+                "INSERT INTO {} (key, value) VALUES (?1, ?2)
+                 ON CONFLICT (key) DO UPDATE SET value = excluded.value",
+                table
+            ),
+            params![key, serde_json::to_string(value)?],
+        )?;
+        Ok(())
+    }
+
+    fn delete(&self, table: &str, key: &str) -> Result<()> {
+        self.conn()?
+            .execute(&format!("DELETE FROM {} WHERE key = ?1", table), [key])?;
+        Ok(())
+    }
+
+    // Read, change and write back an entry in one transaction that takes the
+    // write lock up front, so instances sharing the database can't
+    // interleave. The entry is deleted if `change` leaves `None`.
+    fn update<T: Serialize + DeserializeOwned, R>(
+        &self,
+        table: &str,
+        key: &str,
+        change: impl FnOnce(&mut Option<T>) -> R,
+    ) -> Result<R> {
+        let mut conn = self.conn()?;
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        let value: Option<String> = tx
+            .query_row(
+                &format!("SELECT value FROM {} WHERE key = ?1", table),
+                [key],
+                |row| row.get(0),
+            )
+            .optional()?;
+        let mut entry = value
+            .map(|value| serde_json::from_str(&value))
+            .transpose()
+            .context(format!("Failed to parse {} entry {}", table, key))?;
+        let result = change(&mut entry);
+        match &entry {
+            Some(entry) => tx.execute(
+                &format!(
+                    "INSERT INTO {} (key, value) VALUES (?1, ?2)
+                     ON CONFLICT (key) DO UPDATE SET value = excluded.value",
+                    table
+                ),
+                params![key, serde_json::to_string(entry)?],
+            )?,
+            None => tx.execute(&format!("DELETE FROM {} WHERE key = ?1", table), [key])?,
+        };
+        tx.commit()?;
+        Ok(result)
+    }
+
+    fn update_ledger<R>(
+        &self,
+        key: &str,
+        change: impl FnOnce(&mut Option<LedgerEntry>) -> R,
+    ) -> Result<R> {
+        self.update(DELIVERY_LEDGER, key, change)
+    }
+}
+
+impl StateStore for SqliteStateStore {
+    fn proof(&self, key: &ProofKey) -> Result<Option<ProofRecord>> {
+        self.get(PROOFS, &key.to_string())
+    }
+
+    fn save_proof_job(&self, key: &ProofKey, job_id: i64) -> Result<()> {
+        let record = ProofRecord {
+            job_id,
+            proof: None,
+            metadata: Default::default(),
+        };
+        self.put(PROOFS, &key.to_string(), &record)
+    }
+
+    fn save_proof(&self, key: &ProofKey, proof: &Proof) -> Result<()> {
+        let Some(mut record) = self.proof(key)? else {
+            return Ok(());
+        };
+        record.proof = Some(proof.data.clone());
+        record.metadata = proof.metadata.clone();
+        self.put(PROOFS, &key.to_string(), &record)
+    }
+
+    fn remove_proof(&self, key: &ProofKey) -> Result<()> {
+        self.delete(PROOFS, &key.to_string())
+    }
+
+    fn save_pending_event(&self, key: &ProofKey, event: &RelayEvent) -> Result<()> {
+        self.put(PENDING_EVENTS, &key.to_string(), event)
+    }
+
+    fn pending_events(&self) -> Result<Vec<RelayEvent>> {
+        self.values(PENDING_EVENTS)
+    }
+
+    fn remove_pending_event(&self, key: &ProofKey) -> Result<()> {
+        self.delete(PENDING_EVENTS, &key.to_string())
+    }
+
+    fn save_gas_cost(&self, record: &GasCostRecord) -> Result<()> {
+        self.put(GAS_COSTS, &format!("{:?}", record.tx_hash), record)
+    }
+
+    fn gas_costs(&self) -> Result<Vec<GasCostRecord>> {
+        self.values(GAS_COSTS)
+    }
+
+    fn recent_gas_costs(
+        &self,
+        pair: &str,
+        kind: GasCostKind,
+        limit: usize,
+    ) -> Result<Vec<GasCostRecord>> {
+        let conn = self.conn()?;
+        let mut statement = conn.prepare(
+            "SELECT value FROM gas_costs
+             WHERE json_extract(value, '$.pair') = ?1
+               AND json_extract(value, '$.kind') = ?2
+             ORDER BY json_extract(value, '$.recorded_at') DESC
+             LIMIT ?3",
+        )?;
+        let records = statement
+            .query_map(
+                params![
+                    pair,
+                    kind.as_str(),
+                    i64::try_from(limit).unwrap_or(i64::MAX)
+                ],
+                |row| row.get::<_, String>(0),
+            )?
+            .map(|value| Ok(serde_json::from_str(&value?)?))
+            .collect::<Result<Vec<GasCostRecord>>>()
+            .context("Failed to read gas costs")?;
+        Ok(records)
+    }
+
+    fn save_fee_claim(&self, record: &FeeClaimRecord) -> Result<()> {
+        self.put(FEE_CLAIMS, &format!("{:?}", record.tx_hash), record)
+    }
+
+    fn fee_claims(&self) -> Result<Vec<FeeClaimRecord>> {
+        self.values(FEE_CLAIMS)
+    }
+
+    fn save_retry(&self, key: &ProofKey, delivery: &FailedDelivery) -> Result<()> {
+        self.put(RETRIES, &key.to_string(), delivery)
+    }
+
+    fn retry(&self, key: &ProofKey) -> Result<Option<FailedDelivery>> {
+        self.get(RETRIES, &key.to_string())
+    }
+
+    fn retries(&self) -> Result<Vec<FailedDelivery>> {
+        self.values(RETRIES)
+    }
+
+    fn remove_retry(&self, key: &ProofKey) -> Result<()> {
+        self.delete(RETRIES, &key.to_string())
+    }
+
+    fn save_dead_letter(&self, key: &ProofKey, delivery: &FailedDelivery) -> Result<()> {
+        self.put(DEAD_LETTERS, &key.to_string(), delivery)
+    }
+
+    fn dead_letter(&self, key: &ProofKey) -> Result<Option<FailedDelivery>> {
+        self.get(DEAD_LETTERS, &key.to_string())
+    }
+
+    fn dead_letters(&self) -> Result<Vec<FailedDelivery>> {
+        self.values(DEAD_LETTERS)
+    }
+
+    fn remove_dead_letter(&self, key: &ProofKey) -> Result<()> {
+        self.delete(DEAD_LETTERS, &key.to_string())
+    }
+
+    fn spill_delivery(&self, key: &ProofKey, request: &DeliveryRequest) -> Result<()> {
+        self.put(SPILLED_DELIVERIES, &key.to_string(), request)
+    }
+
+    fn spilled_deliveries(&self) -> Result<Vec<DeliveryRequest>> {
+        self.values(SPILLED_DELIVERIES)
+    }
+
+    fn remove_spilled_delivery(&self, key: &ProofKey) -> Result<()> {
+        self.delete(SPILLED_DELIVERIES, &key.to_string())
+    }
+
+    fn save_delivery_attempt(&self, outcome: &DeliveryOutcome) -> Result<()> {
+        let key = format!(
+            "{}#{}@{}",
+            outcome.proof_key, outcome.attempt, outcome.attempted_at
+        );
+        self.conn()?.execute(
+            "INSERT INTO deliveries (
+                 key, pair, source_chain_id, dest_chain_id, nonce, tx_hash,
+                 event_id, attempted_at, attempt, value
+             ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
+             ON CONFLICT (key) DO UPDATE SET value = excluded.value",
+            params![
+                key,
+                outcome.pair,
+                outcome.source_chain_id.as_i64()?,
+                outcome.dest_chain_id.as_i64()?,
+                outcome.nonce as i64,
+                outcome.tx_hash.map(|hash| format!("{:?}", hash)),
+                outcome.event_id.to_string(),
+                outcome.attempted_at as i64,
+                outcome.attempt,
+                serde_json::to_string(outcome)?,
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn delivery_history(&self, query: &DeliveryQuery) -> Result<Vec<DeliveryOutcome>> {
+        let conn = self.conn()?;
+        let mut statement = conn.prepare(
+            "SELECT value FROM deliveries
+             WHERE (?1 IS NULL OR pair = ?1 COLLATE NOCASE)
+               AND (?2 IS NULL OR source_chain_id = ?2)
+               AND (?3 IS NULL OR dest_chain_id = ?3)
+               AND (?4 IS NULL OR nonce = ?4)
+               AND (?5 IS NULL OR tx_hash = ?5)
+               AND (?6 IS NULL OR event_id = ?6)
+             ORDER BY attempted_at, attempt",
+        )?;
+        let history = statement
+            .query_map(
+                params![
+                    query.pair,
+                    query.source_chain_id.map(ChainId::as_i64).transpose()?,
+                    query.dest_chain_id.map(ChainId::as_i64).transpose()?,
+                    query.nonce.map(|nonce| nonce as i64),
+                    query.tx_hash.map(|hash| format!("{:?}", hash)),
+                    query.event_id.map(|id| id.to_string()),
+                ],
+                |row| row.get::<_, String>(0),
+            )?
+            .map(|value| Ok(serde_json::from_str(&value?)?))
+            .collect::<Result<Vec<DeliveryOutcome>>>()
+            .context("Failed to read delivery history")?;
+        Ok(history)
+    }
+
+    fn claim_delivery(
+        &self,
+        pair: &str,
+        nonce: u64,
+        owner: &str,
+        stale_after_secs: u64,
+    ) -> Result<DeliveryClaim> {
+        self.update_ledger(&LedgerEntry::key(pair, nonce), |entry| {
+            if let Some(refusal) = entry
+                .as_ref()
+                .and_then(|entry| entry.refuses(owner, stale_after_secs))
+            {
+                return refusal;
+            }
+            *entry = Some(LedgerEntry::claimed(pair, nonce, owner));
+            DeliveryClaim::Claimed
+        })
+    }
+
+    fn confirm_delivery(&self, pair: &str, nonce: u64, tx_hash: Option<H256>) -> Result<()> {
+        self.update_ledger(&LedgerEntry::key(pair, nonce), |entry| {
+            let entry = entry.get_or_insert_with(|| LedgerEntry::claimed(pair, nonce, ""));
+            entry.delivered = true;
+            // Finding it executed later says nothing of the transaction
+            entry.tx_hash = tx_hash.or(entry.tx_hash);
+        })
+    }
+
+    fn release_delivery(&self, pair: &str, nonce: u64, owner: &str) -> Result<()> {
+        self.update_ledger(&LedgerEntry::key(pair, nonce), |entry| {
+            if entry
+                .as_ref()
+                .is_some_and(|entry| !entry.delivered && entry.owner == owner)
+            {
+                *entry = None;
+            }
+        })
+    }
+
+    fn ledger_entry(&self, pair: &str, nonce: u64) -> Result<Option<LedgerEntry>> {
+        self.get(DELIVERY_LEDGER, &LedgerEntry::key(pair, nonce))
+    }
+
+    fn last_delivered_nonces(&self) -> Result<HashMap<String, u64>> {
+        let conn = self.conn()?;
+        let mut statement = conn.prepare(
+            "SELECT json_extract(value, '$.pair'), MAX(json_extract(value, '$.nonce'))
+             FROM delivery_ledger
+             WHERE json_extract(value, '$.delivered')
+             GROUP BY 1",
+        )?;
+        let nonces = statement
+            .query_map([], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)? as u64))
+            })?
+            .collect::<rusqlite::Result<HashMap<_, _>>>()
+            .context("Failed to read the delivery ledger")?;
+        Ok(nonces)
+    }
+
+    fn transition_event(&self, record: &EventRecord) -> Result<()> {
+        let key = record.event_id.to_string();
+        self.update(EVENT_STATUSES, &key, |current: &mut Option<EventRecord>| {
+            record.follows(current.as_ref())?;
+            *current = Some(record.clone());
+            Ok::<_, RelayerError>(())
+        })??;
+        Ok(())
+    }
+
+    fn event_record(&self, id: &EventId) -> Result<Option<EventRecord>> {
+        self.get(EVENT_STATUSES, &id.to_string())
+    }
+
+    fn event_records(&self) -> Result<Vec<EventRecord>> {
+        self.values(EVENT_STATUSES)
+    }
+
+    fn query_events(&self, query: &EventQuery) -> Result<Vec<EventRecord>> {
+        let conn = self.conn()?;
+        let mut statement = conn.prepare(
+            "SELECT value FROM event_statuses
+             WHERE (?1 IS NULL OR json_extract(value, '$.pair') = ?1 COLLATE NOCASE)
+               AND (?2 IS NULL OR json_extract(value, '$.status.state') = lower(?2))
+               AND (?3 IS NULL OR json_extract(value, '$.nonce') >= ?3)
+               AND (?4 IS NULL OR json_extract(value, '$.nonce') <= ?4)
+               AND (?5 IS NULL OR json_extract(value, '$.updated_at') >= ?5)
+               AND (?6 IS NULL OR json_extract(value, '$.updated_at') <= ?6)
+             ORDER BY json_extract(value, '$.updated_at'), key
+             LIMIT ?7 OFFSET ?8",
+        )?;
+        let records = statement
+            .query_map(
+                params![
+                    query.pair,
+                    query.status,
+                    query.from_nonce.map(|nonce| nonce as i64),
+                    query.to_nonce.map(|nonce| nonce as i64),
+                    query.since.map(|since| since as i64),
+                    query.until.map(|until| until as i64),
+                    query.page_size() as i64,
+                    i64::try_from(query.offset.unwrap_or_default()).unwrap_or(i64::MAX),
+                ],
+                |row| row.get::<_, String>(0),
+            )?
+            .map(|value| Ok(serde_json::from_str(&value?)?))
+            .collect::<Result<Vec<EventRecord>>>()
+            .context("Failed to query event statuses")?;
+        Ok(records)
+    }
+
+    fn events_at_nonce(&self, pair: &str, nonce: u64) -> Result<Vec<EventRecord>> {
+        let conn = self.conn()?;
+        let mut statement = conn.prepare(
+            "SELECT value FROM event_statuses
+             WHERE json_extract(value, '$.pair') = ?1
+               AND json_extract(value, '$.nonce') = ?2",
+        )?;
+        let records = statement
+            .query_map(params![pair, nonce as i64], |row| row.get::<_, String>(0))?
+            .map(|value| Ok(serde_json::from_str(&value?)?))
+            .collect::<Result<Vec<EventRecord>>>()
+            .context("Failed to look up event statuses")?;
+        Ok(records)
+    }
+
+    fn flush(&self) -> Result<()> {
+        // Fold the write-ahead log back into the database file
+        self.conn()?
+            .query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))?;
+        Ok(())
+    }
+}
@@ -0,0 +1,220 @@
+use anyhow::{bail, Context, Result};
+use async_trait::async_trait;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+};
+
+use super::{Message, Publisher};
+
+/// API key of Kafka's Produce request
+pub(crate) const PRODUCE: i16 = 0;
+// Oldest Produce version Kafka 4 still accepts, taking v2 record batches
+const PRODUCE_VERSION: i16 = 3;
+const CLIENT_ID: &str = "relayer";
+// Wait for every in-sync replica before answering
+const ACKS_ALL: i16 = -1;
+const ACK_TIMEOUT_MS: i32 = 5_000;
+
+/// Producer writing batches to one partition of a topic, over Kafka's wire
+/// protocol
+///
+/// It only talks to the configured broker, which must lead the partition;
+/// a broker that does not answers the batch with an error.
+pub(super) struct KafkaProducer {
+    connection: TcpStream,
+    topic: String,
+    partition: i32,
+    correlation_id: i32,
+}
+
+impl KafkaProducer {
+    pub(super) async fn connect(broker: &str, topic: &str, partition: i32) -> Result<Self> {
+        let connection = TcpStream::connect(broker)
+            .await
+            .with_context(|| format!("Failed to connect to Kafka broker {}", broker))?;
+        Ok(Self {
+            connection,
+            topic: topic.to_string(),
+            partition,
+            correlation_id: 0,
+        })
+    }
+
+    fn produce_request(&mut self, messages: &[Message]) -> Vec<u8> {
+        self.correlation_id = self.correlation_id.wrapping_add(1);
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_millis() as i64)
+            .unwrap_or_default();
+        let batch = record_batch(messages, timestamp);
+
+        let mut request = Vec::new();
+        request.extend(PRODUCE.to_be_bytes());
+        request.extend(PRODUCE_VERSION.to_be_bytes());
+        request.extend(self.correlation_id.to_be_bytes());
+        put_string(&mut request, CLIENT_ID);
+        // No transactional id
+        request.extend((-1i16).to_be_bytes());
+        request.extend(ACKS_ALL.to_be_bytes());
+        request.extend(ACK_TIMEOUT_MS.to_be_bytes());
+        request.extend(1i32.to_be_bytes());
+        put_string(&mut request, &self.topic);
+        request.extend(1i32.to_be_bytes());
+        request.extend(self.partition.to_be_bytes());
+        request.extend((batch.len() as i32).to_be_bytes());
+        request.extend(batch);
+
+        let mut frame = (request.len() as i32).to_be_bytes().to_vec();
+        frame.extend(request);
+        frame
+    }
+
+    // Error code the broker answered the batch with
+    fn produce_error(&self, response: &[u8]) -> Result<i16> {
+        let mut reader = Reader(response);
+        if reader.i32()? != self.correlation_id {
+            bail!("Kafka answered another request");
+        }
+        for _ in 0..reader.i32()? {
+            let topic_len = reader.i16()?;
+            reader.take(topic_len.max(0) as usize)?;
+            for _ in 0..reader.i32()? {
+                let partition = reader.i32()?;
+                let error_code = reader.i16()?;
+                // Base offset and log append time
+                reader.take(16)?;
+                if partition == self.partition {
+                    return Ok(error_code);
+                }
+            }
+        }
+        bail!("Kafka did not answer for partition {}", self.partition)
+    }
+}
+
+#[async_trait]
+impl Publisher for KafkaProducer {
+    async fn publish(&mut self, messages: &[Message]) -> Result<()> {
+        let request = self.produce_request(messages);
+        self.connection.write_all(&request).await?;
+        let size = self.connection.read_i32().await?;
+        let mut response = vec![0; size.max(0) as usize];
+        self.connection.read_exact(&mut response).await?;
+        match self.produce_error(&response)? {
+            0 => Ok(()),
+            code => bail!(
+                "Kafka refused the batch for {}/{} with error code {}",
+                self.topic,
+                self.partition,
+                code
+            ),
+        }
+    }
+}
+
+// Record batch of format v2 holding one record per message, uncompressed
+fn record_batch(messages: &[Message], timestamp: i64) -> Vec<u8> {
+    let mut records = Vec::new();
+    for (offset_delta, message) in messages.iter().enumerate() {
+        let mut record = vec![0]; // attributes
+        put_varint(&mut record, 0); // timestamp delta
+        put_varint(&mut record, offset_delta as i64);
+        put_varint(&mut record, message.key.len() as i64);
+        record.extend(message.key.as_bytes());
+        put_varint(&mut record, message.payload.len() as i64);
+        record.extend(&message.payload);
+        put_varint(&mut record, 0); // headers
+        put_varint(&mut records, record.len() as i64);
+        records.extend(record);
+    }
+
+    // What the CRC covers: everything after it
+    let mut body = Vec::new();
+    body.extend(0i16.to_be_bytes()); // attributes
+    body.extend((messages.len() as i32 - 1).to_be_bytes()); // last offset delta
+    body.extend(timestamp.to_be_bytes()); // first timestamp
+    body.extend(timestamp.to_be_bytes()); // max timestamp
+    body.extend((-1i64).to_be_bytes()); // producer id
+    body.extend((-1i16).to_be_bytes()); // producer epoch
+    body.extend((-1i32).to_be_bytes()); // base sequence
+    body.extend((messages.len() as i32).to_be_bytes());
+    body.extend(records);
+
+    let mut batch = Vec::new();
+    // Base offset, then the batch length counted from the leader epoch on
+    batch.extend(0i64.to_be_bytes());
+    batch.extend(((4 + 1 + 4 + body.len()) as i32).to_be_bytes());
+    batch.extend((-1i32).to_be_bytes()); // partition leader epoch
+    batch.push(2); // magic
+    batch.extend(crc32c(&body).to_be_bytes());
+    batch.extend(body);
+    batch
+}
+
+fn put_string(out: &mut Vec<u8>, value: &str) {
+    out.extend((value.len() as i16).to_be_bytes());
+    out.extend(value.as_bytes());
+}
+
+// Zigzag varint, as record fields are encoded
+fn put_varint(out: &mut Vec<u8>, value: i64) {
+    let mut zigzag = ((value << 1) ^ (value >> 63)) as u64;
+    while zigzag >= 0x80 {
+        out.push(zigzag as u8 | 0x80);
+        zigzag >>= 7;
+    }
+    out.push(zigzag as u8);
+}
+
+/// CRC-32C (Castagnoli), the checksum of v2 record batches
+pub(crate) fn crc32c(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in bytes {
+        crc = CRC32C_TABLE[((crc ^ u32::from(*byte)) & 0xff) as usize] ^ (crc >> 8);
+    }
+    !crc
+}
+
+const CRC32C_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut index = 0;
+    while index < 256 {
+        let mut crc = index as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0x82f6_3b78
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[index] = crc;
+        index += 1;
+    }
+    table
+};
+
+// Big-endian fields read off the front of a response
+struct Reader<'a>(&'a [u8]);
+
+impl Reader<'_> {
+    fn take(&mut self, len: usize) -> Result<&[u8]> {
+        if self.0.len() < len {
+            bail!("Kafka response ended early");
+        }
+        let (taken, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(taken)
+    }
+
+    fn i16(&mut self) -> Result<i16> {
+        Ok(i16::from_be_bytes(self.take(2)?.try_into()?))
+    }
+
+    fn i32(&mut self) -> Result<i32> {
+        Ok(i32::from_be_bytes(self.take(4)?.try_into()?))
+    }
+}
@@ -0,0 +1,192 @@
+mod kafka;
+mod nats;
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use std::{
+    collections::VecDeque,
+    future::Future,
+    sync::{Arc, Mutex, RwLock},
+    time::Duration,
+};
+use tokio::sync::Notify;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, warn};
+
+use crate::config::{EventStreamConfig, StreamBroker};
+use crate::metrics::STREAM_MESSAGES;
+use crate::status::EventRecord;
+
+#[cfg(feature = "testkit")]
+pub(crate) use kafka::{crc32c, PRODUCE};
+
+// Stream of the running relayer, unset while none is configured
+static STREAM: RwLock<Option<Arc<EventStream>>> = RwLock::new(None);
+
+/// One status change as the broker gets it
+pub(crate) struct Message {
+    /// Event id, so a partitioned broker keeps each event's changes in order
+    pub(crate) key: String,
+    /// The [`EventRecord`] as JSON
+    pub(crate) payload: Vec<u8>,
+}
+
+/// Connection to the configured broker
+#[async_trait]
+trait Publisher: Send {
+    /// Publish `messages` in order, returning once the broker has them all
+    async fn publish(&mut self, messages: &[Message]) -> Result<()>;
+}
+
+struct EventStream {
+    config: EventStreamConfig,
+    queue: Mutex<Queue>,
+    queued: Notify,
+}
+
+// Status changes not yet published, numbered so a batch can be removed once
+// sent even if the oldest were dropped meanwhile
+#[derive(Default)]
+struct Queue {
+    records: VecDeque<(u64, EventRecord)>,
+    next: u64,
+}
+
+/// Start publishing status changes to the configured broker, replacing any
+/// earlier configuration
+pub(crate) fn init(config: Option<&EventStreamConfig>) {
+    let stream = config.map(|config| {
+        Arc::new(EventStream {
+            config: config.clone(),
+            queue: Mutex::new(Queue::default()),
+            queued: Notify::new(),
+        })
+    });
+    *STREAM.write().expect("event stream lock poisoned") = stream;
+}
+
+fn current() -> Option<Arc<EventStream>> {
+    STREAM.read().expect("event stream lock poisoned").clone()
+}
+
+/// Queue a status change for the broker, dropping the oldest queued one if
+/// the buffer is full
+pub(crate) fn publish(record: &EventRecord) {
+    let Some(stream) = current() else {
+        return;
+    };
+    {
+        let mut queue = stream.lock();
+        if queue.records.len() >= stream.config.buffer {
+            queue.records.pop_front();
+            STREAM_MESSAGES.with_label_values(&["dropped"]).inc();
+        }
+        let seq = queue.next;
+        queue.next += 1;
+        queue.records.push_back((seq, record.clone()));
+    }
+    stream.queued.notify_one();
+}
+
+/// Publish queued status changes until `stop` is cancelled, then make one
+/// last attempt at those still queued
+pub(crate) async fn run(stop: CancellationToken) {
+    let Some(stream) = current() else {
+        return;
+    };
+    let backoff = Duration::from_millis(stream.config.reconnect_backoff_ms);
+    let mut publisher = None;
+    loop {
+        let stopping = stop.is_cancelled();
+        if stream.lock().records.is_empty() {
+            if stopping {
+                return;
+            }
+            tokio::select! {
+                _ = stream.queued.notified() => {}
+                _ = stop.cancelled() => {}
+            }
+            continue;
+        }
+        if let Err(e) = stream.send_batch(&mut publisher).await {
+            warn!(error = %e, "Failed to publish to the event stream");
+            publisher = None;
+            if stopping {
+                let abandoned = stream.lock().records.len();
+                STREAM_MESSAGES
+                    .with_label_values(&["dropped"])
+                    .inc_by(abandoned as u64);
+                return;
+            }
+            tokio::select! {
+                _ = tokio::time::sleep(backoff) => {}
+                _ = stop.cancelled() => {}
+            }
+        }
+    }
+}
+
+impl EventStream {
+    // Publish the oldest queued changes, connecting first if need be
+    async fn send_batch(&self, publisher: &mut Option<Box<dyn Publisher>>) -> Result<()> {
+        let batch: Vec<(u64, EventRecord)> = self
+            .lock()
+            .records
+            .iter()
+            .take(self.config.max_batch)
+            .cloned()
+            .collect();
+        let Some((last, _)) = batch.last() else {
+            return Ok(());
+        };
+        let last = *last;
+        let messages = batch
+            .iter()
+            .map(|(_, record)| {
+                Ok(Message {
+                    key: record.event_id.to_string(),
+                    payload: serde_json::to_vec(record)?,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        if publisher.is_none() {
+            *publisher = Some(self.within_timeout(connect(&self.config.broker)).await?);
+        }
+        let publisher = publisher.as_mut().expect("publisher connected above");
+        self.within_timeout(publisher.publish(&messages)).await?;
+
+        self.lock().records.retain(|(seq, _)| *seq > last);
+        STREAM_MESSAGES
+            .with_label_values(&["published"])
+            .inc_by(messages.len() as u64);
+        debug!(count = messages.len(), "Published status changes");
+        Ok(())
+    }
+
+    async fn within_timeout<T>(&self, call: impl Future<Output = Result<T>>) -> Result<T> {
+        let timeout = Duration::from_millis(self.config.timeout_ms);
+        tokio::time::timeout(timeout, call)
+            .await
+            .map_err(|_| anyhow!("Event stream broker did not answer within {:?}", timeout))?
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Queue> {
+        self.queue.lock().expect("event stream queue lock poisoned")
+    }
+}
+
+async fn connect(broker: &StreamBroker) -> Result<Box<dyn Publisher>> {
+    Ok(match broker {
+        StreamBroker::Nats {
+            url,
+            subject,
+            token,
+        } => Box::new(nats::NatsPublisher::connect(url, subject, token.as_deref()).await?),
+        StreamBroker::Kafka {
+            broker,
+            topic,
+            partition,
+        } => Box::new(kafka::KafkaProducer::connect(broker, topic, *partition).await?),
+    })
+}
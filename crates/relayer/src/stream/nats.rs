@@ -0,0 +1,95 @@
+use anyhow::{bail, Context, Result};
+use async_trait::async_trait;
+use serde_json::json;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::TcpStream,
+};
+
+use super::{Message, Publisher};
+
+/// Client of the core NATS protocol that publishes on one subject
+///
+/// Each batch is followed by a `PING`, so the server's `PONG` confirms it
+/// processed every `PUB` before it.
+pub(super) struct NatsPublisher {
+    connection: BufReader<TcpStream>,
+    subject: String,
+}
+
+impl NatsPublisher {
+    pub(super) async fn connect(url: &str, subject: &str, token: Option<&str>) -> Result<Self> {
+        let address = url.strip_prefix("nats://").unwrap_or(url);
+        let tcp = TcpStream::connect(address)
+            .await
+            .with_context(|| format!("Failed to connect to NATS server {}", address))?;
+        let mut publisher = Self {
+            connection: BufReader::new(tcp),
+            subject: subject.to_string(),
+        };
+        let info = publisher.read_line().await?;
+        if !info.starts_with("INFO ") {
+            bail!("NATS server greeted with {:?} instead of INFO", info);
+        }
+
+        let mut options = json!({
+            "verbose": false,
+            "pedantic": false,
+            "name": "relayer",
+            "lang": "rust",
+            "version": env!("CARGO_PKG_VERSION"),
+        });
+        if let Some(token) = token {
+            options["auth_token"] = json!(token);
+        }
+        let connect = format!("CONNECT {}\r\nPING\r\n", options);
+        publisher.write(connect.as_bytes()).await?;
+        publisher.await_pong().await?;
+        Ok(publisher)
+    }
+
+    async fn write(&mut self, bytes: &[u8]) -> Result<()> {
+        let stream = self.connection.get_mut();
+        stream.write_all(bytes).await?;
+        stream.flush().await?;
+        Ok(())
+    }
+
+    async fn read_line(&mut self) -> Result<String> {
+        let mut line = String::new();
+        if self.connection.read_line(&mut line).await? == 0 {
+            bail!("NATS server closed the connection");
+        }
+        Ok(line.trim_end().to_string())
+    }
+
+    // Wait for the answer to our `PING`, answering the server's own
+    async fn await_pong(&mut self) -> Result<()> {
+        loop {
+            let line = self.read_line().await?;
+            match line.as_str() {
+                "PONG" => return Ok(()),
+                "PING" => self.write(b"PONG\r\n").await?,
+                _ if line.starts_with("-ERR") => bail!("NATS server refused: {}", line),
+                // `+OK` and updated `INFO`
+                _ => {}
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Publisher for NatsPublisher {
+    async fn publish(&mut self, messages: &[Message]) -> Result<()> {
+        let mut batch = Vec::new();
+        for message in messages {
+            let header = format!("PUB {} {}\r\n", self.subject, message.payload.len());
+            batch.extend_from_slice(header.as_bytes());
+            batch.extend_from_slice(&message.payload);
+            batch.extend_from_slice(b"\r\n");
+        }
+        batch.extend_from_slice(b"PING\r\n");
+        self.write(&batch).await?;
+        self.await_pong().await
+    }
+}
@@ -0,0 +1,108 @@
+use anyhow::{Context as _, Result};
+use opentelemetry::{
+    global,
+    trace::{TraceContextExt, TracerProvider as _},
+    Context,
+};
+use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+use opentelemetry_sdk::{propagation::TraceContextPropagator, trace::SdkTracerProvider, Resource};
+use std::collections::HashMap;
+use tracing::{Span, Subscriber};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::{
+    fmt::{self, MakeWriter},
+    layer::SubscriberExt,
+    registry::LookupSpan,
+    util::SubscriberInitExt,
+    EnvFilter, Layer,
+};
+
+use crate::config::{LogFormat, TelemetryConfig};
+
+/// Trace context an event carries between pipeline stages, as W3C
+/// `traceparent`/`tracestate` headers
+pub type TraceContext = HashMap<String, String>;
+
+/// Install the global tracing subscriber, exporting spans over OTLP/HTTP when
+/// an endpoint is configured
+///
+/// Shut the returned provider down before exiting so buffered spans are sent.
+pub fn init_tracing(config: &TelemetryConfig) -> Result<Option<SdkTracerProvider>> {
+    let filter = EnvFilter::new(std::env::var("RUST_LOG").unwrap_or_else(|_| "info".into()));
+    let json = config.log_format == LogFormat::Json;
+    let registry = tracing_subscriber::registry()
+        .with(filter)
+        .with((!json).then(fmt::layer))
+        .with(json.then(|| json_layer(std::io::stdout)));
+
+    let Some(endpoint) = &config.otlp_endpoint else {
+        registry.init();
+        return Ok(None);
+    };
+
+    let exporter = SpanExporter::builder()
+        .with_http()
+        .with_endpoint(endpoint)
+        .build()
+        .context("Failed to build OTLP span exporter")?;
+    let provider = SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(
+            Resource::builder()
+                .with_service_name(config.service_name.clone())
+                .build(),
+        )
+        .build();
+    global::set_text_map_propagator(TraceContextPropagator::new());
+
+    registry
+        .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("relayer")))
+        .init();
+    Ok(Some(provider))
+}
+
+/// Log lines in the JSON format, written to `writer`
+///
+/// Event fields are at the top level, those of the innermost span under
+/// "span" and those of every span it is in under "spans", so a line logged
+/// deep inside an event's work still carries its `event_id`.
+pub(crate) fn json_layer<S, W>(writer: W) -> impl Layer<S>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    W: for<'w> MakeWriter<'w> + Send + Sync + 'static,
+{
+    fmt::layer()
+        .json()
+        .flatten_event(true)
+        .with_current_span(true)
+        .with_span_list(true)
+        .with_writer(writer)
+}
+
+/// Capture `span` so a later stage can continue its trace
+pub(crate) fn inject(span: &Span) -> TraceContext {
+    let mut carrier = TraceContext::new();
+    global::get_text_map_propagator(|propagator| {
+        propagator.inject_context(&span.context(), &mut carrier)
+    });
+    carrier
+}
+
+/// Make `span` part of the trace captured in `carrier`
+pub(crate) fn attach(span: &Span, carrier: &TraceContext) {
+    if !carrier.is_empty() {
+        let _ = span.set_parent(extract(carrier));
+    }
+}
+
+/// Link `span` to the trace captured in `carrier` without joining it, for
+/// work shared by several events
+pub(crate) fn link(span: &Span, carrier: &TraceContext) {
+    if !carrier.is_empty() {
+        span.add_link(extract(carrier).span().span_context().clone());
+    }
+}
+
+fn extract(carrier: &TraceContext) -> Context {
+    global::get_text_map_propagator(|propagator| propagator.extract(carrier))
+}
@@ -0,0 +1,69 @@
+use async_trait::async_trait;
+use std::time::{Duration, Instant, SystemTime};
+use tokio::sync::watch;
+
+use crate::Clock;
+
+/// Clock that only moves when a test advances it
+///
+/// Sleeps on it finish once [`ManualClock::advance`] has moved it past their
+/// deadline, so retries, deadlines and cooldowns can be stepped through
+/// without waiting for real time to pass. Give it to the relayer with
+/// [`RelayerBuilder::clock`].
+///
+/// [`RelayerBuilder::clock`]: crate::RelayerBuilder::clock
+pub struct ManualClock {
+    start: Instant,
+    epoch: SystemTime,
+    elapsed: watch::Sender<Duration>,
+}
+
+impl ManualClock {
+    /// Clock reading the current time until it is advanced
+    pub fn new() -> Self {
+        Self::starting_at(SystemTime::now())
+    }
+
+    /// Clock whose wall-clock time starts at `epoch`
+    pub fn starting_at(epoch: SystemTime) -> Self {
+        Self {
+            start: Instant::now(),
+            epoch,
+            elapsed: watch::channel(Duration::ZERO).0,
+        }
+    }
+
+    /// Move the clock forward, waking every sleep that is now over
+    pub fn advance(&self, duration: Duration) {
+        self.elapsed.send_modify(|elapsed| *elapsed += duration);
+    }
+
+    /// How far the clock has been advanced
+    pub fn elapsed(&self) -> Duration {
+        *self.elapsed.borrow()
+    }
+}
+
+impl Default for ManualClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl Clock for ManualClock {
+    fn now(&self) -> Instant {
+        self.start + self.elapsed()
+    }
+
+    fn system_time(&self) -> SystemTime {
+        self.epoch + self.elapsed()
+    }
+
+    async fn sleep(&self, duration: Duration) {
+        let until = self.elapsed() + duration;
+        let mut elapsed = self.elapsed.subscribe();
+        // The sender lives as long as the clock, which outlives the borrow
+        let _ = elapsed.wait_for(|elapsed| *elapsed >= until).await;
+    }
+}
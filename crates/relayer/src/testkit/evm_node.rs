@@ -0,0 +1,266 @@
+use anyhow::{Context, Result};
+use axum::{
+    extract::State,
+    response::{IntoResponse, Response},
+    routing::post,
+    Json, Router,
+};
+use ethers::{
+    core::types::{
+        transaction::eip2718::TypedTransaction, Address, Block, Bytes, NameOrAddress,
+        TransactionReceipt, H256, U256, U64,
+    },
+    utils::{id, keccak256, rlp::Rlp},
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+};
+use tokio_util::sync::DropGuard;
+
+use super::serve;
+
+// Height every transaction is mined at, and what the mock charges for gas:
+// a base fee and tip adding up to its gas price
+const BLOCK_NUMBER: u64 = 100;
+const GAS_PRICE: u64 = 1_000_000_000;
+const BASE_FEE: u64 = 900_000_000;
+const PRIORITY_FEE: u64 = GAS_PRICE - BASE_FEE;
+const GAS_USED: u64 = 50_000;
+
+/// Transaction the mock accepted, its signature recovered
+#[derive(Debug, Clone)]
+pub struct SentEvmTransaction {
+    pub hash: H256,
+    pub from: Address,
+    pub to: Option<Address>,
+    pub nonce: U256,
+    pub data: Bytes,
+}
+
+#[derive(Default)]
+struct Script {
+    chain_id: u64,
+    sent: Vec<SentEvmTransaction>,
+    receipts: HashMap<H256, TransactionReceipt>,
+    /// What `eth_call` returns, by contract and selector
+    returns: HashMap<(Address, [u8; 4]), Bytes>,
+    balances: HashMap<Address, U256>,
+    calls: Vec<String>,
+    /// Selector of every `eth_call`, in order
+    eth_calls: Vec<[u8; 4]>,
+}
+
+#[derive(Deserialize)]
+struct Call {
+    method: String,
+    #[serde(default)]
+    params: Vec<Value>,
+    #[serde(default)]
+    id: Value,
+}
+
+/// Local stand-in for an EVM node with a fee market, answering what a
+/// relayer needs to read contracts and send transactions
+///
+/// Calls to contracts return what was scripted with [`on_call`] and revert
+/// otherwise, and accounts hold nothing until given a [`set_balance`]. Every
+/// raw transaction sent is decoded and mined at once with a successful
+/// receipt. The server stops when the handle is dropped.
+///
+/// [`on_call`]: MockEvmNode::on_call
+/// [`set_balance`]: MockEvmNode::set_balance
+pub struct MockEvmNode {
+    addr: SocketAddr,
+    script: Arc<Mutex<Script>>,
+    _stop: DropGuard,
+}
+
+impl MockEvmNode {
+    /// Serve chain `chain_id` on a free port on the loopback interface
+    pub async fn start(chain_id: u64) -> Result<Self> {
+        let script = Arc::new(Mutex::new(Script {
+            chain_id,
+            ..Default::default()
+        }));
+        let app = Router::new()
+            .route("/", post(handle))
+            .with_state(script.clone());
+        let (addr, stop) = serve(app).await.context("Failed to start mock EVM node")?;
+        Ok(Self {
+            addr,
+            script,
+            _stop: stop,
+        })
+    }
+
+    /// URL to configure as the chain's RPC endpoint
+    pub fn url(&self) -> String {
+        format!("http://{}/", self.addr)
+    }
+
+    /// Answer `eth_call`s of `signature`, e.g. `balanceOf(address)`, on `to`
+    /// with `returned`, whatever the arguments
+    pub fn on_call(&self, to: Address, signature: &str, returned: impl Into<Bytes>) -> &Self {
+        self.lock()
+            .returns
+            .insert((to, id(signature)), returned.into());
+        self
+    }
+
+    /// Answer `eth_getBalance` for `address` with `wei`, whatever it spends
+    pub fn set_balance(&self, address: Address, wei: impl Into<U256>) -> &Self {
+        self.lock().balances.insert(address, wei.into());
+        self
+    }
+
+    /// How many `eth_call`s of `signature` were received so far
+    pub fn eth_call_count(&self, signature: &str) -> usize {
+        let selector = id(signature);
+        self.lock()
+            .eth_calls
+            .iter()
+            .filter(|called| **called == selector)
+            .count()
+    }
+
+    /// Transactions accepted so far, oldest first
+    pub fn sent(&self) -> Vec<SentEvmTransaction> {
+        self.lock().sent.clone()
+    }
+
+    /// How many calls of `method` were received so far
+    pub fn call_count(&self, method: &str) -> usize {
+        self.lock()
+            .calls
+            .iter()
+            .filter(|call| *call == method)
+            .count()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Script> {
+        self.script.lock().expect("mock EVM node lock poisoned")
+    }
+}
+
+async fn handle(State(script): State<Arc<Mutex<Script>>>, Json(call): Json<Call>) -> Response {
+    let mut script = script.lock().expect("mock EVM node lock poisoned");
+    script.calls.push(call.method.clone());
+    let result = match call.method.as_str() {
+        "eth_chainId" => Ok(json!(U64::from(script.chain_id))),
+        "eth_blockNumber" => Ok(json!(U64::from(BLOCK_NUMBER))),
+        "eth_gasPrice" => Ok(json!(U256::from(GAS_PRICE))),
+        "eth_maxPriorityFeePerGas" => Ok(json!(U256::from(PRIORITY_FEE))),
+        "eth_estimateGas" => Ok(json!(U256::from(GAS_USED))),
+        "eth_getBlockByNumber" => Ok(json!(Block::<H256> {
+            number: Some(BLOCK_NUMBER.into()),
+            hash: Some(H256::repeat_byte(1)),
+            base_fee_per_gas: Some(BASE_FEE.into()),
+            ..Default::default()
+        })),
+        "eth_feeHistory" => Ok(json!({
+            "oldestBlock": U64::from(BLOCK_NUMBER - 9),
+            "baseFeePerGas": vec![U256::from(BASE_FEE); 11],
+            "gasUsedRatio": vec![0.5; 10],
+            "reward": vec![vec![U256::from(PRIORITY_FEE)]; 10],
+        })),
+        "eth_call" => call_contract(&mut script, &call.params),
+        "eth_getBalance" => {
+            let balance = call
+                .params
+                .first()
+                .and_then(|address| serde_json::from_value::<Address>(address.clone()).ok())
+                .and_then(|address| script.balances.get(&address).copied())
+                .unwrap_or_default();
+            Ok(json!(balance))
+        }
+        "eth_getTransactionCount" => {
+            let from = call
+                .params
+                .first()
+                .and_then(|from| serde_json::from_value::<Address>(from.clone()).ok());
+            let count = script
+                .sent
+                .iter()
+                .filter(|tx| Some(tx.from) == from)
+                .count();
+            Ok(json!(U256::from(count)))
+        }
+        "eth_sendRawTransaction" => send(&mut script, &call.params),
+        "eth_getTransactionReceipt" => {
+            let receipt = call
+                .params
+                .first()
+                .and_then(|hash| serde_json::from_value::<H256>(hash.clone()).ok())
+                .and_then(|hash| script.receipts.get(&hash));
+            Ok(json!(receipt))
+        }
+        _ => Err(json!({ "code": -32601, "message": "Method not found" })),
+    };
+    let body = match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": call.id, "result": result }),
+        Err(error) => json!({ "jsonrpc": "2.0", "id": call.id, "error": error }),
+    };
+    Json(body).into_response()
+}
+
+fn call_contract(script: &mut Script, params: &[Value]) -> Result<Value, Value> {
+    let call = params.first().cloned().unwrap_or_default();
+    let to = serde_json::from_value::<Address>(call["to"].clone()).ok();
+    let data = ["input", "data"]
+        .iter()
+        .find_map(|field| serde_json::from_value::<Bytes>(call[*field].clone()).ok())
+        .unwrap_or_default();
+    let selector: Option<[u8; 4]> = data.get(..4).and_then(|selector| selector.try_into().ok());
+    if let Some(selector) = selector {
+        script.eth_calls.push(selector);
+    }
+    to.zip(selector)
+        .and_then(|key| script.returns.get(&key))
+        .map(|returned| json!(returned))
+        .ok_or_else(|| json!({ "code": 3, "message": "execution reverted" }))
+}
+
+fn send(script: &mut Script, params: &[Value]) -> Result<Value, Value> {
+    let invalid = |message: String| json!({ "code": -32602, "message": message });
+    let raw: Bytes = params
+        .first()
+        .and_then(|raw| serde_json::from_value(raw.clone()).ok())
+        .ok_or_else(|| invalid("missing transaction".to_string()))?;
+    let (tx, signature) = TypedTransaction::decode_signed(&Rlp::new(&raw))
+        .map_err(|e| invalid(format!("invalid transaction: {}", e)))?;
+    let from = signature
+        .recover(tx.sighash())
+        .map_err(|e| invalid(format!("invalid signature: {}", e)))?;
+
+    let hash = H256(keccak256(&raw));
+    let to = match tx.to() {
+        Some(NameOrAddress::Address(to)) => Some(*to),
+        _ => None,
+    };
+    script.sent.push(SentEvmTransaction {
+        hash,
+        from,
+        to,
+        nonce: tx.nonce().copied().unwrap_or_default(),
+        data: tx.data().cloned().unwrap_or_default(),
+    });
+    script.receipts.insert(
+        hash,
+        TransactionReceipt {
+            transaction_hash: hash,
+            block_hash: Some(H256::repeat_byte(1)),
+            block_number: Some(BLOCK_NUMBER.into()),
+            from,
+            to,
+            gas_used: Some(GAS_USED.into()),
+            effective_gas_price: Some(GAS_PRICE.into()),
+            status: Some(1.into()),
+            ..Default::default()
+        },
+    );
+    Ok(json!(hash))
+}
@@ -0,0 +1,205 @@
+use ethers::core::types::{Address, Bytes, H256};
+use std::{
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use super::polymer_api::polymer_proof;
+use crate::config::{
+    ChainConfig, DeliveryConfig, MockProofConfig, ProofBackendConfig, RelayPair, RelayerConfig,
+    RelayerConfigBuilder, RetryConfig,
+};
+use crate::types::{ChainId, DeliveryRequest, EventMeta, Proof, ProofMetadata, RelayEvent};
+
+/// Source chain of [`relay_pair`] and [`event`]
+pub const SOURCE_CHAIN_ID: u64 = 11155420;
+/// Destination chain of [`relay_pair`] and [`event`]
+pub const DEST_CHAIN_ID: u64 = 84532;
+
+// `execute(uint256)` selector, so payloads look like the calls dapps receive
+const EXECUTE_SELECTOR: [u8; 4] = [0xfe, 0x0d, 0x94, 0xc1];
+
+/// Chain `chain_id` named `name`, pointing at a local node, with every other
+/// setting at its default
+pub fn chain(chain_id: u64, name: &str) -> ChainConfig {
+    ChainConfig {
+        name: name.to_string(),
+        chain_id: ChainId::new(chain_id),
+        rpc_url: "http://127.0.0.1:8545".to_string(),
+        ..Default::default()
+    }
+}
+
+// Chain named after its ID, as the config and event fixtures make them
+fn named_chain(chain_id: u64) -> ChainConfig {
+    chain(chain_id, &format!("chain-{}", chain_id))
+}
+
+/// Pair between the two test chains, with fixed non-zero resolver and dapp
+/// addresses
+pub fn relay_pair() -> RelayPair {
+    RelayPair {
+        source_chain_id: ChainId::new(SOURCE_CHAIN_ID),
+        source_resolver_address: Address::from_low_u64_be(0x5e50),
+        dest_chain_id: ChainId::new(DEST_CHAIN_ID),
+        dest_dapp_address: Address::from_low_u64_be(0xda99),
+        ..Default::default()
+    }
+}
+
+/// Configuration relaying [`relay_pair`] with mock proofs that come back at
+/// once, retries a second apart and state kept under `state_dir`
+///
+/// Nothing is served over HTTP or a control socket. Further settings can be
+/// changed on the returned builder.
+pub fn config(state_dir: impl AsRef<Path>) -> RelayerConfigBuilder {
+    RelayerConfig::builder()
+        .chain(named_chain(SOURCE_CHAIN_ID))
+        .chain(named_chain(DEST_CHAIN_ID))
+        .relay_pair(relay_pair())
+        .proof_backend(ProofBackendConfig::Mock(MockProofConfig {
+            latency_ms: 0,
+            failure_rate: 0.0,
+        }))
+        .delivery(DeliveryConfig {
+            retry: RetryConfig {
+                max_attempts: 3,
+                initial_backoff_ms: 1_000,
+                max_backoff_ms: 1_000,
+            },
+            ..Default::default()
+        })
+        .state_dir(state_dir.as_ref())
+}
+
+/// Event for [`relay_pair`] with nonce `nonce` and every other field at its
+/// [`EventBuilder`] default
+pub fn event(nonce: u64) -> RelayEvent {
+    EventBuilder::new(&relay_pair()).nonce(nonce).build()
+}
+
+/// Request delivering `event` with a Polymer proof of its source log
+pub fn delivery_request(event: RelayEvent) -> DeliveryRequest {
+    let meta = &event.meta;
+    let proof = polymer_proof(
+        meta.chain_id.into(),
+        meta.block_number,
+        meta.tx_index as u16,
+        meta.log_index as u8,
+    );
+    DeliveryRequest {
+        destination_chain_id: event.destination_chain.chain_id,
+        destination_contract_address: event.dest_dapp_address,
+        proof: Proof {
+            data: proof.into(),
+            metadata: ProofMetadata::default(),
+        },
+        event,
+    }
+}
+
+/// Builds a [`RelayEvent`] for a pair
+///
+/// Unless set, the nonce is 1, the source log is log 0 of transaction 0 in
+/// block 100, the transaction hash is derived from the nonce and the payload
+/// calls `execute(nonce)`, so events built with different nonces have
+/// different IDs.
+#[derive(Debug, Clone)]
+pub struct EventBuilder {
+    source_chain: ChainConfig,
+    destination_chain: ChainConfig,
+    source_resolver_address: Address,
+    dest_dapp_address: Address,
+    nonce: u64,
+    block_number: u64,
+    tx_index: u32,
+    log_index: u32,
+    tx_hash: Option<H256>,
+    payload: Option<Bytes>,
+    priority: u8,
+}
+
+impl EventBuilder {
+    pub fn new(pair: &RelayPair) -> Self {
+        Self {
+            source_chain: named_chain(pair.source_chain_id.into()),
+            destination_chain: named_chain(pair.dest_chain_id.into()),
+            source_resolver_address: pair.source_resolver_address,
+            dest_dapp_address: pair.dest_dapp_address,
+            nonce: 1,
+            block_number: 100,
+            tx_index: 0,
+            log_index: 0,
+            tx_hash: None,
+            payload: None,
+            priority: pair.priority,
+        }
+    }
+
+    /// Use these chain configs instead of ones made up from the pair's IDs
+    pub fn chains(mut self, source: ChainConfig, destination: ChainConfig) -> Self {
+        self.source_chain = source;
+        self.destination_chain = destination;
+        self
+    }
+
+    pub fn nonce(mut self, nonce: u64) -> Self {
+        self.nonce = nonce;
+        self
+    }
+
+    /// Block, transaction index and log index of the source log
+    pub fn log(mut self, block_number: u64, tx_index: u32, log_index: u32) -> Self {
+        self.block_number = block_number;
+        self.tx_index = tx_index;
+        self.log_index = log_index;
+        self
+    }
+
+    pub fn tx_hash(mut self, tx_hash: H256) -> Self {
+        self.tx_hash = Some(tx_hash);
+        self
+    }
+
+    pub fn payload(mut self, payload: impl Into<Bytes>) -> Self {
+        self.payload = Some(payload.into());
+        self
+    }
+
+    pub fn priority(mut self, priority: u8) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    pub fn build(self) -> RelayEvent {
+        let payload = self.payload.unwrap_or_else(|| {
+            let mut payload = EXECUTE_SELECTOR.to_vec();
+            payload.extend_from_slice(H256::from_low_u64_be(self.nonce).as_bytes());
+            payload.into()
+        });
+        RelayEvent {
+            meta: EventMeta {
+                chain_id: self.source_chain.chain_id,
+                tx_hash: Some(
+                    self.tx_hash
+                        .unwrap_or_else(|| H256::from_low_u64_be(self.nonce + 1)),
+                ),
+                block_number: self.block_number,
+                tx_index: self.tx_index,
+                log_index: self.log_index,
+            },
+            source_chain: self.source_chain,
+            source_resolver_address: self.source_resolver_address,
+            destination_chain: self.destination_chain,
+            dest_dapp_address: self.dest_dapp_address,
+            exec_payload: payload,
+            nonce: self.nonce,
+            detected_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_secs())
+                .unwrap_or_default(),
+            trace_context: Default::default(),
+            priority: self.priority,
+        }
+    }
+}
@@ -0,0 +1,232 @@
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::{
+    sync::watch,
+    time::{self, Instant},
+};
+
+use super::fixtures::EventBuilder;
+use super::pipeline::TestPipeline;
+use crate::config::RelayerConfig;
+use crate::hooks::{HookDecision, PipelineHook};
+use crate::types::{DeliveryOutcome, DeliveryRequest, DeliveryStatus, EventId, RelayEvent};
+
+/// Synthetic events to push through a [`TestPipeline`] with [`run_load`]
+#[derive(Debug, Clone)]
+pub struct LoadProfile {
+    pub events: usize,
+    /// Events emitted per second; as fast as the pipeline takes them if unset
+    pub rate_per_sec: Option<u32>,
+    /// How long every event gets to be delivered before the run fails
+    pub timeout: Duration,
+}
+
+impl Default for LoadProfile {
+    fn default() -> Self {
+        Self {
+            events: 500,
+            rate_per_sec: None,
+            timeout: Duration::from_secs(60),
+        }
+    }
+}
+
+/// Latency percentiles of one stage across a load run
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LatencySummary {
+    pub p50: Duration,
+    pub p90: Duration,
+    pub p99: Duration,
+    pub max: Duration,
+}
+
+impl LatencySummary {
+    fn of(mut samples: Vec<Duration>) -> Self {
+        samples.sort();
+        let at = |percentile: usize| {
+            let index = (samples.len() * percentile / 100).min(samples.len().saturating_sub(1));
+            samples.get(index).copied().unwrap_or_default()
+        };
+        Self {
+            p50: at(50),
+            p90: at(90),
+            p99: at(99),
+            max: samples.last().copied().unwrap_or_default(),
+        }
+    }
+}
+
+impl fmt::Display for LatencySummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "p50 {:?}, p90 {:?}, p99 {:?}, max {:?}",
+            self.p50, self.p90, self.p99, self.max
+        )
+    }
+}
+
+/// What a load run measured
+#[derive(Debug, Clone)]
+pub struct LoadReport {
+    pub events: usize,
+    /// From the first event emitted to the last one delivered
+    pub elapsed: Duration,
+    /// Emitted until taken in by the proof fetcher
+    pub intake: LatencySummary,
+    /// Taken in until proven
+    pub proving: LatencySummary,
+    /// Proven until delivered, including the wait in the delivery queue
+    pub delivery: LatencySummary,
+    /// Emitted until delivered
+    pub end_to_end: LatencySummary,
+}
+
+impl LoadReport {
+    pub fn events_per_sec(&self) -> f64 {
+        self.events as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+    }
+}
+
+impl fmt::Display for LoadReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} events in {:?} ({:.0} events/s)",
+            self.events,
+            self.elapsed,
+            self.events_per_sec()
+        )?;
+        writeln!(f, "  intake      {}", self.intake)?;
+        writeln!(f, "  proving     {}", self.proving)?;
+        writeln!(f, "  delivery    {}", self.delivery)?;
+        write!(f, "  end to end  {}", self.end_to_end)
+    }
+}
+
+/// Push `profile`'s events through a [`TestPipeline`] started for `config`
+/// and time each stage
+///
+/// Events carry nonces from 1 up, so `config` needs a state directory no
+/// earlier run delivered them from.
+pub async fn run_load(config: RelayerConfig, profile: &LoadProfile) -> Result<LoadReport> {
+    let timer = Arc::new(StageTimer::default());
+    let hook = timer.clone();
+    let pipeline = TestPipeline::start_with(config, |builder| builder.hook(hook)).await?;
+    let pair = super::relay_pair();
+    let mut pacing = profile
+        .rate_per_sec
+        .map(|rate| time::interval(Duration::from_secs(1) / rate.max(1)));
+
+    let started = Instant::now();
+    for nonce in 1..=profile.events as u64 {
+        if let Some(pacing) = &mut pacing {
+            pacing.tick().await;
+        }
+        let event = EventBuilder::new(&pair).nonce(nonce).build();
+        timer.record(Stage::Emitted, event.id());
+        pipeline.emit(event).await?;
+    }
+    let mut delivered = timer.delivered.subscribe();
+    time::timeout(
+        profile.timeout,
+        delivered.wait_for(|delivered| *delivered >= profile.events),
+    )
+    .await
+    .map_err(|_| {
+        anyhow!(
+            "{} of {} events delivered within {:?}",
+            *timer.delivered.borrow(),
+            profile.events,
+            profile.timeout
+        )
+    })??;
+    let elapsed = started.elapsed();
+    pipeline.shutdown().await?;
+
+    let between = |from: Stage, to: Stage| timer.between(from, to);
+    Ok(LoadReport {
+        events: profile.events,
+        elapsed,
+        intake: between(Stage::Emitted, Stage::Detected),
+        proving: between(Stage::Detected, Stage::Proven),
+        delivery: between(Stage::Proven, Stage::Delivered),
+        end_to_end: between(Stage::Emitted, Stage::Delivered),
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Stage {
+    Emitted,
+    Detected,
+    Proven,
+    Delivered,
+}
+
+// Notes when each event reaches each stage
+struct StageTimer {
+    reached: Mutex<HashMap<(EventId, Stage), Instant>>,
+    delivered: watch::Sender<usize>,
+}
+
+impl Default for StageTimer {
+    fn default() -> Self {
+        Self {
+            reached: Mutex::new(HashMap::new()),
+            delivered: watch::channel(0).0,
+        }
+    }
+}
+
+impl StageTimer {
+    fn record(&self, stage: Stage, event_id: EventId) {
+        self.reached
+            .lock()
+            .expect("stage timer lock poisoned")
+            .entry((event_id, stage))
+            .or_insert_with(Instant::now);
+    }
+
+    fn between(&self, from: Stage, to: Stage) -> LatencySummary {
+        let reached = self.reached.lock().expect("stage timer lock poisoned");
+        let samples = reached
+            .iter()
+            .filter(|((_, stage), _)| *stage == to)
+            .filter_map(|((event_id, _), at)| {
+                let start = reached.get(&(*event_id, from))?;
+                Some(at.saturating_duration_since(*start))
+            })
+            .collect();
+        LatencySummary::of(samples)
+    }
+}
+
+#[async_trait]
+impl PipelineHook for StageTimer {
+    fn name(&self) -> &str {
+        "stage-timer"
+    }
+
+    async fn on_event_detected(&self, event: &RelayEvent) -> Result<HookDecision> {
+        self.record(Stage::Detected, event.id());
+        Ok(HookDecision::Continue)
+    }
+
+    async fn on_proof_fetched(&self, request: &DeliveryRequest) -> Result<HookDecision> {
+        self.record(Stage::Proven, request.event.id());
+        Ok(HookDecision::Continue)
+    }
+
+    async fn after_delivery(&self, outcome: &DeliveryOutcome) {
+        if outcome.status == DeliveryStatus::Delivered {
+            self.record(Stage::Delivered, outcome.event_id);
+            self.delivered.send_modify(|delivered| *delivered += 1);
+        }
+    }
+}
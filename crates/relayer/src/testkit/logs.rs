@@ -0,0 +1,59 @@
+use anyhow::{anyhow, Result};
+use serde_json::Value;
+use std::{
+    io,
+    sync::{Arc, Mutex},
+};
+use tracing::level_filters::LevelFilter;
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+
+use crate::telemetry;
+
+/// Every line the process logs, captured in the format `--log-format json`
+/// writes
+///
+/// Logging is set up once per process, so only the first capture of a test
+/// binary can be installed.
+pub struct LogCapture {
+    buffer: Buffer,
+}
+
+impl LogCapture {
+    /// Capture lines logged at `info` and above from now on
+    pub fn install() -> Result<Self> {
+        let buffer = Buffer::default();
+        let writer = buffer.clone();
+        tracing_subscriber::registry()
+            .with(LevelFilter::INFO)
+            .with(telemetry::json_layer(move || writer.clone()))
+            .try_init()
+            .map_err(|e| anyhow!("Failed to install log capture: {}", e))?;
+        Ok(Self { buffer })
+    }
+
+    /// The lines logged so far, oldest first
+    pub fn lines(&self) -> Vec<Value> {
+        let bytes = self.buffer.0.lock().expect("log capture lock poisoned");
+        String::from_utf8_lossy(&bytes)
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect()
+    }
+}
+
+#[derive(Clone, Default)]
+struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+impl io::Write for Buffer {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+        self.0
+            .lock()
+            .expect("log capture lock poisoned")
+            .extend_from_slice(bytes);
+        Ok(bytes.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,58 @@
+// Helpers for testing the relayer and integrations built on it, compiled in
+// with the `testkit` feature
+
+mod clock;
+mod evm_node;
+mod fixtures;
+mod load;
+mod logs;
+mod pipeline;
+mod polymer_api;
+mod rpc_cassette;
+mod solana_rpc;
+mod stream;
+mod webhook;
+
+pub use clock::ManualClock;
+pub use evm_node::{MockEvmNode, SentEvmTransaction};
+pub use fixtures::{
+    chain, config, delivery_request, event, relay_pair, EventBuilder, DEST_CHAIN_ID,
+    SOURCE_CHAIN_ID,
+};
+pub use load::{run_load, LatencySummary, LoadProfile, LoadReport};
+pub use logs::LogCapture;
+pub use pipeline::{ChannelSource, RecordingSink, TestPipeline};
+pub use polymer_api::{polymer_proof, MockPolymerApi, MockReply, RecordedCall};
+pub use rpc_cassette::{RpcCassette, RpcInteraction, RpcRecorder, RpcReplayer};
+pub use solana_rpc::{MockSolanaRpc, SentSolanaTransaction, SolanaCall, SolanaReply};
+pub use stream::{MockKafka, MockNats, StreamedMessage};
+pub use webhook::{MockWebhook, ReceivedPost};
+
+use anyhow::Result;
+use std::net::{SocketAddr, TcpListener};
+use tokio_util::sync::{CancellationToken, DropGuard};
+
+// Serve `app` on a free loopback port until the returned guard is dropped
+async fn serve(app: axum::Router) -> Result<(SocketAddr, DropGuard)> {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let addr = listener.local_addr()?;
+    let stop = CancellationToken::new();
+    let stopped = stop.clone();
+    tokio::spawn(async move {
+        let _ = axum::serve(listener, app)
+            .with_graceful_shutdown(async move { stopped.cancelled().await })
+            .await;
+    });
+    Ok((addr, stop.drop_guard()))
+}
+
+/// Pick a free loopback address for a relayer's `http_addr`
+///
+/// The port is only probed, not held, so another process could take it
+/// before the relayer binds; tests that start servers themselves should
+/// bind port 0 and keep the listener instead.
+pub fn free_addr() -> SocketAddr {
+    TcpListener::bind("127.0.0.1:0")
+        .and_then(|listener| listener.local_addr())
+        .expect("no free loopback port")
+}
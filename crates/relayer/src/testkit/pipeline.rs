@@ -0,0 +1,330 @@
+use anyhow::{anyhow, bail, Result};
+use async_trait::async_trait;
+use ethers::core::types::H256;
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::{
+    sync::{broadcast, mpsc, watch},
+    time::{self, Instant},
+};
+use tokio_util::sync::CancellationToken;
+
+use crate::app::RelayerHandle;
+use crate::builder::RelayerBuilder;
+use crate::config::{RelayerConfig, StoreBackend};
+use crate::event_delivery::DeliverySink;
+use crate::event_source::{EventEmitter, EventSource};
+use crate::status::{EventRecord, EventStatus};
+use crate::store::{FileStateStore, SqliteStateStore, StateStore};
+use crate::types::{DeliveryOutcome, DeliveryRequest, DeliveryStatus, RelayEvent, RelayerError};
+
+// How often store-backed expectations look again
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Event source fed by hand through the sender [`ChannelSource::new`]
+/// returns
+pub struct ChannelSource {
+    events: tokio::sync::Mutex<mpsc::Receiver<RelayEvent>>,
+}
+
+impl ChannelSource {
+    pub fn new() -> (Self, mpsc::Sender<RelayEvent>) {
+        let (tx, rx) = mpsc::channel(1_024);
+        let source = Self {
+            events: tokio::sync::Mutex::new(rx),
+        };
+        (source, tx)
+    }
+}
+
+#[async_trait]
+impl EventSource for ChannelSource {
+    fn name(&self) -> &str {
+        "channel"
+    }
+
+    async fn run(&self, emitter: EventEmitter, shutdown: CancellationToken) -> Result<()> {
+        let mut events = self.events.lock().await;
+        loop {
+            let event = tokio::select! {
+                event = events.recv() => event,
+                _ = shutdown.cancelled() => return Ok(()),
+            };
+            match event {
+                Some(event) => emitter.emit(event).await?,
+                None => return Ok(()),
+            }
+        }
+    }
+}
+
+/// Delivery sink that keeps every request it is handed instead of sending it
+/// anywhere
+///
+/// Deliveries succeed unless failures were scripted with
+/// [`RecordingSink::fail_next`] or [`RecordingSink::reject_next`].
+pub struct RecordingSink {
+    state: Mutex<SinkState>,
+    delivered: watch::Sender<usize>,
+}
+
+#[derive(Default)]
+struct SinkState {
+    requests: Vec<DeliveryRequest>,
+    failures: usize,
+    rejections: usize,
+}
+
+impl RecordingSink {
+    pub fn new() -> Self {
+        Self {
+            state: Mutex::new(SinkState::default()),
+            delivered: watch::channel(0).0,
+        }
+    }
+
+    /// Fail the next `count` deliveries with a retryable error
+    pub fn fail_next(&self, count: usize) -> &Self {
+        self.lock().failures += count;
+        self
+    }
+
+    /// Fail the next `count` deliveries with an error that dead-letters them
+    pub fn reject_next(&self, count: usize) -> &Self {
+        self.lock().rejections += count;
+        self
+    }
+
+    /// Requests delivered so far, oldest first
+    pub fn requests(&self) -> Vec<DeliveryRequest> {
+        self.lock().requests.clone()
+    }
+
+    /// Nonces of the events delivered so far, in delivery order
+    pub fn nonces(&self) -> Vec<u64> {
+        self.lock()
+            .requests
+            .iter()
+            .map(|request| request.event.nonce)
+            .collect()
+    }
+
+    /// Wait until `count` deliveries have succeeded, returning all of them
+    pub async fn wait_for(&self, count: usize, timeout: Duration) -> Result<Vec<DeliveryRequest>> {
+        let mut delivered = self.delivered.subscribe();
+        time::timeout(timeout, delivered.wait_for(|delivered| *delivered >= count))
+            .await
+            .map_err(|_| {
+                anyhow!(
+                    "{} of {} deliveries arrived within {:?}",
+                    self.lock().requests.len(),
+                    count,
+                    timeout
+                )
+            })?
+            .map_err(|_| anyhow!("Recording sink dropped"))?;
+        Ok(self.requests())
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, SinkState> {
+        self.state.lock().expect("recording sink lock poisoned")
+    }
+}
+
+impl Default for RecordingSink {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl DeliverySink for RecordingSink {
+    async fn deliver(&self, request: &DeliveryRequest) -> Result<Option<H256>> {
+        let mut state = self.lock();
+        if state.rejections > 0 {
+            state.rejections -= 1;
+            return Err(RelayerError::TransactionFailed {
+                chain_id: request.destination_chain_id,
+                source: anyhow!("Delivery rejected by recording sink"),
+            }
+            .into());
+        }
+        if state.failures > 0 {
+            state.failures -= 1;
+            bail!("Delivery failed by recording sink");
+        }
+        state.requests.push(request.clone());
+        let delivered = state.requests.len();
+        drop(state);
+        self.delivered.send_replace(delivered);
+        Ok(None)
+    }
+}
+
+/// A relayer running every stage but the chain ends: events go in through a
+/// [`ChannelSource`] and come out of a [`RecordingSink`]
+///
+/// Proofs come from whatever backend the configuration names, so a
+/// [`MockPolymerApi`] or the mock proof backend keeps the run local.
+///
+/// [`MockPolymerApi`]: super::MockPolymerApi
+pub struct TestPipeline {
+    events: mpsc::Sender<RelayEvent>,
+    sink: Arc<RecordingSink>,
+    store: Arc<dyn StateStore>,
+    outcomes: broadcast::Receiver<DeliveryOutcome>,
+    // Outcomes received while waiting for another event's
+    seen: Vec<DeliveryOutcome>,
+    handle: RelayerHandle,
+}
+
+impl TestPipeline {
+    /// Start a relayer for `config`
+    pub async fn start(config: RelayerConfig) -> Result<Self> {
+        Self::start_with(config, |builder| builder).await
+    }
+
+    /// Start a relayer for `config`, letting `customize` add hooks, proof
+    /// providers or more event sources to its builder first
+    pub async fn start_with(
+        config: RelayerConfig,
+        customize: impl FnOnce(RelayerBuilder) -> RelayerBuilder,
+    ) -> Result<Self> {
+        let store: Arc<dyn StateStore> = match config.store_backend {
+            StoreBackend::File => Arc::new(FileStateStore::open(&config.state_dir)?),
+            StoreBackend::Sqlite => Arc::new(SqliteStateStore::open(&config.state_dir)?),
+        };
+        let (source, events) = ChannelSource::new();
+        let sink = Arc::new(RecordingSink::new());
+        let builder = RelayerBuilder::new(config)
+            .without_chain_events()
+            .event_source(Arc::new(source))
+            .delivery_sink(sink.clone())
+            .store(store.clone());
+        let app = customize(builder).build()?;
+        let outcomes = app.subscribe_outcomes();
+        Ok(Self {
+            events,
+            sink,
+            store,
+            outcomes,
+            seen: Vec::new(),
+            handle: app.run(),
+        })
+    }
+
+    /// Hand `event` to the pipeline as if a source had detected it
+    pub async fn emit(&self, event: RelayEvent) -> Result<()> {
+        self.events
+            .send(event)
+            .await
+            .map_err(|_| anyhow!("Pipeline stopped, event was not emitted"))
+    }
+
+    pub fn sink(&self) -> &RecordingSink {
+        &self.sink
+    }
+
+    pub fn store(&self) -> &Arc<dyn StateStore> {
+        &self.store
+    }
+
+    pub fn handle(&self) -> &RelayerHandle {
+        &self.handle
+    }
+
+    /// Wait for `event` to be delivered, failing if it is dead-lettered or
+    /// expires instead
+    pub async fn expect_delivered(
+        &mut self,
+        event: &RelayEvent,
+        timeout: Duration,
+    ) -> Result<DeliveryOutcome> {
+        let outcome = self.settled(event, timeout).await?;
+        match outcome.status {
+            DeliveryStatus::Delivered | DeliveryStatus::AlreadyExecuted => Ok(outcome),
+            status => bail!(
+                "Event {} settled as {:?} instead of delivered: {}",
+                outcome.event_id,
+                status,
+                outcome.error.as_deref().unwrap_or("no error")
+            ),
+        }
+    }
+
+    /// Wait for `event` to be given up on and moved to the dead letter queue
+    pub async fn expect_dead_lettered(
+        &mut self,
+        event: &RelayEvent,
+        timeout: Duration,
+    ) -> Result<DeliveryOutcome> {
+        let outcome = self.settled(event, timeout).await?;
+        match outcome.status {
+            DeliveryStatus::DeadLettered => Ok(outcome),
+            status => bail!(
+                "Event {} settled as {:?} instead of dead-lettered",
+                outcome.event_id,
+                status
+            ),
+        }
+    }
+
+    /// Wait for `event`'s stored status to be `status`
+    pub async fn expect_status(
+        &self,
+        event: &RelayEvent,
+        status: EventStatus,
+        timeout: Duration,
+    ) -> Result<EventRecord> {
+        let id = event.id();
+        let deadline = Instant::now() + timeout;
+        loop {
+            let record = self.store.event_record(&id)?;
+            match record {
+                Some(record) if record.status == status => return Ok(record),
+                _ if Instant::now() >= deadline => bail!(
+                    "Event {} is {} after {:?}, expected {}",
+                    id,
+                    record.map_or("unknown", |record| record.status.name()),
+                    timeout,
+                    status
+                ),
+                _ => time::sleep(POLL_INTERVAL).await,
+            }
+        }
+    }
+
+    /// Stop the relayer and wait for it to finish
+    pub async fn shutdown(self) -> Result<()> {
+        self.handle.shutdown();
+        self.handle.await_terminated().await
+    }
+
+    // First outcome for `event` that is not a retry
+    async fn settled(&mut self, event: &RelayEvent, timeout: Duration) -> Result<DeliveryOutcome> {
+        let id = event.id();
+        let is_settled = |outcome: &DeliveryOutcome| {
+            outcome.event_id == id && !matches!(outcome.status, DeliveryStatus::Retrying { .. })
+        };
+        if let Some(index) = self.seen.iter().position(is_settled) {
+            return Ok(self.seen.remove(index));
+        }
+        let deadline = Instant::now() + timeout;
+        loop {
+            let outcome = match time::timeout_at(deadline, self.outcomes.recv()).await {
+                Ok(Ok(outcome)) => outcome,
+                Ok(Err(broadcast::error::RecvError::Lagged(_))) => continue,
+                Ok(Err(broadcast::error::RecvError::Closed)) => {
+                    bail!("Relayer stopped before event {} settled", id)
+                }
+                Err(_) => bail!("Event {} did not settle within {:?}", id, timeout),
+            };
+            if is_settled(&outcome) {
+                return Ok(outcome);
+            }
+            self.seen.push(outcome);
+        }
+    }
+}
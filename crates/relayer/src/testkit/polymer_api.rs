@@ -0,0 +1,328 @@
+use anyhow::{Context, Result};
+use axum::{
+    extract::State,
+    http::{header::AUTHORIZATION, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::post,
+    Json, Router,
+};
+use ethers::utils::hex;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::{
+    collections::{HashMap, VecDeque},
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio_util::sync::DropGuard;
+
+use super::serve;
+
+// Polymer v2 proof header offsets, as PolymerProofProvider reads them
+const CHAIN_ID_OFFSET: usize = 97;
+const BLOCK_NUMBER_OFFSET: usize = 109;
+const RECEIPT_INDEX_OFFSET: usize = 117;
+const LOG_INDEX_OFFSET: usize = 119;
+const HEADER_LEN: usize = 120;
+
+/// What the mock API answers one call with
+#[derive(Debug, Clone)]
+pub enum MockReply {
+    /// Result of `log_requestProof`
+    JobId(i64),
+    /// Result of `log_queryProof`
+    Proof {
+        status: String,
+        proof: Option<String>,
+    },
+    /// JSON-RPC error object
+    RpcError { code: i64, message: String },
+    /// Bare HTTP status with an empty body
+    Http(StatusCode),
+    /// Another reply, sent once `delay` has passed
+    Delayed(Duration, Box<MockReply>),
+}
+
+impl MockReply {
+    /// Proof job still being generated
+    pub fn pending() -> Self {
+        Self::Proof {
+            status: "pending".to_string(),
+            proof: None,
+        }
+    }
+
+    /// Finished proof carrying `proof`, hex encoded
+    pub fn ready(proof: impl AsRef<[u8]>) -> Self {
+        Self::Proof {
+            status: "ready".to_string(),
+            proof: Some(format!("0x{}", hex::encode(proof))),
+        }
+    }
+
+    pub fn rpc_error(code: i64, message: impl Into<String>) -> Self {
+        Self::RpcError {
+            code,
+            message: message.into(),
+        }
+    }
+
+    pub fn http(status: StatusCode) -> Self {
+        Self::Http(status)
+    }
+
+    /// This reply, held back for `delay`
+    pub fn after(self, delay: Duration) -> Self {
+        Self::Delayed(delay, Box::new(self))
+    }
+}
+
+/// Call the mock API received
+#[derive(Debug, Clone)]
+pub struct RecordedCall {
+    pub method: String,
+    pub params: Vec<Value>,
+    /// Bearer token sent with the call, if any
+    pub token: Option<String>,
+    /// Size of the JSON-RPC batch the call came in, `None` if sent alone
+    pub batch: Option<usize>,
+}
+
+#[derive(Default)]
+struct Script {
+    requests: VecDeque<MockReply>,
+    queries: VecDeque<MockReply>,
+    calls: Vec<RecordedCall>,
+    posts: usize,
+    /// Source log each job was requested for, to build its default proof
+    jobs: HashMap<i64, [u64; 4]>,
+    next_job_id: i64,
+    token: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Call {
+    method: String,
+    #[serde(default)]
+    params: Vec<Value>,
+    #[serde(default)]
+    id: Value,
+}
+
+/// Local stand-in for the Polymer proof API, answering `log_requestProof`
+/// and `log_queryProof` from a script
+///
+/// Scripted replies are used in the order they were added, per method. Once
+/// a method's script runs out, requests get the next job ID and queries a
+/// ready proof whose header names the requested log, so
+/// [`PolymerProofProvider`] proofs pass validation. Every call is recorded,
+/// each call of a JSON-RPC batch on its own; a reply of [`MockReply::Http`]
+/// to any of them answers the whole batch.
+///
+/// The server stops when the handle is dropped.
+///
+/// [`PolymerProofProvider`]: crate::PolymerProofProvider
+pub struct MockPolymerApi {
+    addr: SocketAddr,
+    script: Arc<Mutex<Script>>,
+    _stop: DropGuard,
+}
+
+impl MockPolymerApi {
+    /// Serve on a free port on the loopback interface
+    pub async fn start() -> Result<Self> {
+        let script = Arc::new(Mutex::new(Script {
+            next_job_id: 1,
+            ..Default::default()
+        }));
+        let app = Router::new()
+            .route("/", post(handle))
+            .with_state(script.clone());
+        let (addr, stop) = serve(app)
+            .await
+            .context("Failed to start mock Polymer API")?;
+        Ok(Self {
+            addr,
+            script,
+            _stop: stop,
+        })
+    }
+
+    /// URL to configure as the proof API endpoint
+    pub fn endpoint(&self) -> String {
+        format!("http://{}/", self.addr)
+    }
+
+    /// Answer the next `log_requestProof` calls with `replies`, in order
+    pub fn script_requests(&self, replies: impl IntoIterator<Item = MockReply>) -> &Self {
+        self.lock().requests.extend(replies);
+        self
+    }
+
+    /// Answer the next `log_queryProof` calls with `replies`, in order
+    pub fn script_queries(&self, replies: impl IntoIterator<Item = MockReply>) -> &Self {
+        self.lock().queries.extend(replies);
+        self
+    }
+
+    /// Answer `log_requestProof` with 401 unless it carries `token`
+    pub fn require_token(&self, token: impl Into<String>) -> &Self {
+        self.lock().token = Some(token.into());
+        self
+    }
+
+    /// Every call received so far, oldest first
+    pub fn calls(&self) -> Vec<RecordedCall> {
+        self.lock().calls.clone()
+    }
+
+    /// How many HTTP requests were received so far, a batch counting once
+    pub fn post_count(&self) -> usize {
+        self.lock().posts
+    }
+
+    /// How many calls of `method` were received so far
+    pub fn call_count(&self, method: &str) -> usize {
+        self.lock()
+            .calls
+            .iter()
+            .filter(|call| call.method == method)
+            .count()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Script> {
+        self.script.lock().expect("mock Polymer API lock poisoned")
+    }
+}
+
+/// Proof of the Polymer v2 header layout naming a source log, with no body
+pub fn polymer_proof(
+    chain_id: u64,
+    block_number: u64,
+    receipt_index: u16,
+    log_index: u8,
+) -> Vec<u8> {
+    let mut proof = vec![0u8; HEADER_LEN];
+    proof[CHAIN_ID_OFFSET..CHAIN_ID_OFFSET + 4].copy_from_slice(&(chain_id as u32).to_be_bytes());
+    proof[BLOCK_NUMBER_OFFSET..BLOCK_NUMBER_OFFSET + 8]
+        .copy_from_slice(&block_number.to_be_bytes());
+    proof[RECEIPT_INDEX_OFFSET..RECEIPT_INDEX_OFFSET + 2]
+        .copy_from_slice(&receipt_index.to_be_bytes());
+    proof[LOG_INDEX_OFFSET] = log_index;
+    proof
+}
+
+async fn handle(
+    State(script): State<Arc<Mutex<Script>>>,
+    headers: HeaderMap,
+    Json(body): Json<Value>,
+) -> Response {
+    let token = headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::to_string);
+    script.lock().expect("mock Polymer API lock poisoned").posts += 1;
+
+    let (calls, batch) = match body {
+        Value::Array(calls) => {
+            let size = calls.len();
+            (calls, Some(size))
+        }
+        call => (vec![call], None),
+    };
+    let mut bodies = Vec::new();
+    for call in calls {
+        let Ok(call) = serde_json::from_value::<Call>(call) else {
+            return StatusCode::BAD_REQUEST.into_response();
+        };
+        match answer(&script, call, token.clone(), batch).await {
+            Ok(body) => bodies.push(body),
+            Err(status) => return status.into_response(),
+        }
+    }
+    match batch {
+        Some(_) => Json(Value::Array(bodies)).into_response(),
+        None => Json(bodies.remove(0)).into_response(),
+    }
+}
+
+// Response object for one call, or the HTTP status to answer its request with
+async fn answer(
+    script: &Mutex<Script>,
+    call: Call,
+    token: Option<String>,
+    batch: Option<usize>,
+) -> Result<Value, StatusCode> {
+    let mut reply = {
+        let mut script = script.lock().expect("mock Polymer API lock poisoned");
+        script.calls.push(RecordedCall {
+            method: call.method.clone(),
+            params: call.params.clone(),
+            token: token.clone(),
+            batch,
+        });
+        match call.method.as_str() {
+            "log_requestProof" => {
+                if script.token.is_some() && script.token != token {
+                    MockReply::Http(StatusCode::UNAUTHORIZED)
+                } else {
+                    script.requests.pop_front().unwrap_or_else(|| {
+                        let job_id = script.next_job_id;
+                        script.next_job_id += 1;
+                        MockReply::JobId(job_id)
+                    })
+                }
+            }
+            "log_queryProof" => script.queries.pop_front().unwrap_or_else(|| {
+                let job_id = call
+                    .params
+                    .first()
+                    .and_then(Value::as_i64)
+                    .unwrap_or_default();
+                match script.jobs.get(&job_id) {
+                    Some([chain_id, block, receipt, log]) => MockReply::ready(polymer_proof(
+                        *chain_id,
+                        *block,
+                        *receipt as u16,
+                        *log as u8,
+                    )),
+                    None => MockReply::rpc_error(-32602, format!("unknown job {}", job_id)),
+                }
+            }),
+            _ => MockReply::rpc_error(-32601, "method not found"),
+        }
+    };
+
+    while let MockReply::Delayed(delay, inner) = reply {
+        tokio::time::sleep(delay).await;
+        reply = *inner;
+    }
+    if let MockReply::JobId(job_id) = &reply {
+        let log: Vec<u64> = call.params.iter().filter_map(Value::as_u64).collect();
+        if let [chain_id, block, receipt, log_index] = log[..] {
+            script
+                .lock()
+                .expect("mock Polymer API lock poisoned")
+                .jobs
+                .insert(*job_id, [chain_id, block, receipt, log_index]);
+        }
+    }
+
+    Ok(match reply {
+        MockReply::JobId(job_id) => json!({ "jsonrpc": "2.0", "id": call.id, "result": job_id }),
+        MockReply::Proof { status, proof } => json!({
+            "jsonrpc": "2.0",
+            "id": call.id,
+            "result": { "status": status, "proof": proof.unwrap_or_default() },
+        }),
+        MockReply::RpcError { code, message } => json!({
+            "jsonrpc": "2.0",
+            "id": call.id,
+            "error": { "code": code, "message": message },
+        }),
+        MockReply::Http(status) => return Err(status),
+        MockReply::Delayed(..) => unreachable!("delays are unwrapped above"),
+    })
+}
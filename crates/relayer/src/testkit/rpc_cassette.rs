@@ -0,0 +1,306 @@
+use anyhow::{Context, Result};
+use axum::{extract::State, routing::post, Json, Router};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::{
+    collections::HashSet,
+    fs,
+    net::SocketAddr,
+    path::Path,
+    sync::{Arc, Mutex},
+};
+use tokio_util::sync::DropGuard;
+
+use super::serve;
+
+/// One JSON-RPC call and the response the node gave it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RpcInteraction {
+    pub method: String,
+    /// Call params, null when there are none
+    #[serde(default)]
+    pub params: Value,
+    /// The node's response object, without its `jsonrpc` and `id` members
+    pub response: Value,
+}
+
+/// JSON-RPC interactions recorded against a node, in the order they happened
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RpcCassette {
+    pub interactions: Vec<RpcInteraction>,
+}
+
+impl RpcCassette {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read RPC cassette {}", path.display()))?;
+        serde_json::from_str(&contents)
+            .with_context(|| format!("Failed to parse RPC cassette {}", path.display()))
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let contents = serde_json::to_string_pretty(self)?;
+        fs::write(path, contents)
+            .with_context(|| format!("Failed to write RPC cassette {}", path.display()))
+    }
+
+    /// Add a call answered with `result`
+    pub fn with_result(mut self, method: &str, params: Value, result: Value) -> Self {
+        self.interactions.push(RpcInteraction {
+            method: method.to_string(),
+            params,
+            response: json!({ "result": result }),
+        });
+        self
+    }
+
+    /// Add a call answered with a JSON-RPC error
+    pub fn with_error(mut self, method: &str, params: Value, code: i64, message: &str) -> Self {
+        self.interactions.push(RpcInteraction {
+            method: method.to_string(),
+            params,
+            response: json!({ "error": { "code": code, "message": message } }),
+        });
+        self
+    }
+}
+
+/// Proxy in front of a live node that records every JSON-RPC call passing
+/// through it
+///
+/// Point a chain's `rpc_url` at [`RpcRecorder::endpoint`] for a live run,
+/// then save the [`RpcCassette`] for an [`RpcReplayer`] to serve in tests.
+/// The proxy stops when the handle is dropped.
+pub struct RpcRecorder {
+    addr: SocketAddr,
+    recorder: Arc<Recorder>,
+    _stop: DropGuard,
+}
+
+struct Recorder {
+    upstream: String,
+    http: reqwest::Client,
+    cassette: Mutex<RpcCassette>,
+}
+
+impl RpcRecorder {
+    /// Forward calls to the node at `upstream`
+    pub async fn start(upstream: impl Into<String>) -> Result<Self> {
+        let recorder = Arc::new(Recorder {
+            upstream: upstream.into(),
+            http: reqwest::Client::new(),
+            cassette: Mutex::new(RpcCassette::default()),
+        });
+        let app = Router::new()
+            .route("/", post(record))
+            .with_state(recorder.clone());
+        let (addr, stop) = serve(app).await.context("Failed to start RPC recorder")?;
+        Ok(Self {
+            addr,
+            recorder,
+            _stop: stop,
+        })
+    }
+
+    /// URL to configure as the chain's RPC endpoint
+    pub fn endpoint(&self) -> String {
+        format!("http://{}/", self.addr)
+    }
+
+    /// Everything recorded so far
+    pub fn cassette(&self) -> RpcCassette {
+        self.recorder
+            .cassette
+            .lock()
+            .expect("RPC recorder lock poisoned")
+            .clone()
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        self.cassette().save(path)
+    }
+}
+
+/// Local node answering JSON-RPC calls from an [`RpcCassette`]
+///
+/// A call is answered with the first unused interaction of the same method
+/// and params. Once those are used up the last of them is repeated, so calls
+/// the relayer polls keep their final answer. Calls the cassette has no
+/// interaction for get a JSON-RPC error and are kept as misses.
+///
+/// The server stops when the handle is dropped.
+pub struct RpcReplayer {
+    addr: SocketAddr,
+    replay: Arc<Mutex<Replay>>,
+    _stop: DropGuard,
+}
+
+struct Replay {
+    cassette: RpcCassette,
+    used: HashSet<usize>,
+    misses: Vec<RpcInteraction>,
+}
+
+impl RpcReplayer {
+    pub async fn start(cassette: RpcCassette) -> Result<Self> {
+        let replay = Arc::new(Mutex::new(Replay {
+            cassette,
+            used: HashSet::new(),
+            misses: Vec::new(),
+        }));
+        let app = Router::new()
+            .route("/", post(replay_call))
+            .with_state(replay.clone());
+        let (addr, stop) = serve(app).await.context("Failed to start RPC replayer")?;
+        Ok(Self {
+            addr,
+            replay,
+            _stop: stop,
+        })
+    }
+
+    /// Serve the cassette saved at `path`
+    pub async fn load(path: impl AsRef<Path>) -> Result<Self> {
+        Self::start(RpcCassette::load(path)?).await
+    }
+
+    /// URL to configure as the chain's RPC endpoint
+    pub fn endpoint(&self) -> String {
+        format!("http://{}/", self.addr)
+    }
+
+    /// Calls the cassette had no interaction for, with the error they got
+    pub fn misses(&self) -> Vec<RpcInteraction> {
+        self.lock().misses.clone()
+    }
+
+    /// Recorded interactions no call has used yet
+    pub fn unused(&self) -> Vec<RpcInteraction> {
+        let replay = self.lock();
+        replay
+            .cassette
+            .interactions
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| !replay.used.contains(index))
+            .map(|(_, interaction)| interaction.clone())
+            .collect()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Replay> {
+        self.replay.lock().expect("RPC replayer lock poisoned")
+    }
+}
+
+impl Replay {
+    fn answer(&mut self, method: &str, params: &Value) -> Value {
+        let matching: Vec<usize> = (0..self.cassette.interactions.len())
+            .filter(|&index| {
+                let interaction = &self.cassette.interactions[index];
+                interaction.method == method && interaction.params == *params
+            })
+            .collect();
+        let unused = matching.iter().find(|index| !self.used.contains(index));
+        let Some(&index) = unused.or(matching.last()) else {
+            let message = format!("{} is not in the cassette", method);
+            let response = json!({ "error": { "code": -32601, "message": message } });
+            self.misses.push(RpcInteraction {
+                method: method.to_string(),
+                params: params.clone(),
+                response: response.clone(),
+            });
+            return response;
+        };
+        self.used.insert(index);
+        self.cassette.interactions[index].response.clone()
+    }
+}
+
+// Forwards the body as it is and records each call of a batch on its own
+async fn record(State(recorder): State<Arc<Recorder>>, Json(body): Json<Value>) -> Json<Value> {
+    let response = match recorder
+        .http
+        .post(&recorder.upstream)
+        .json(&body)
+        .send()
+        .await
+    {
+        Ok(response) => response.json::<Value>().await.ok(),
+        Err(_) => None,
+    };
+    let Some(response) = response else {
+        return Json(json!({
+            "jsonrpc": "2.0",
+            "id": body.get("id").cloned().unwrap_or(Value::Null),
+            "error": { "code": -32603, "message": "upstream node unreachable" },
+        }));
+    };
+
+    let calls = as_calls(&body);
+    let responses = as_calls(&response);
+    let mut cassette = recorder
+        .cassette
+        .lock()
+        .expect("RPC recorder lock poisoned");
+    for call in calls {
+        let id = call.get("id");
+        let Some(response) = responses.iter().find(|response| response.get("id") == id) else {
+            continue;
+        };
+        let mut response = (*response).clone();
+        if let Some(response) = response.as_object_mut() {
+            response.remove("jsonrpc");
+            response.remove("id");
+        }
+        cassette.interactions.push(RpcInteraction {
+            method: call["method"].as_str().unwrap_or_default().to_string(),
+            params: params(call),
+            response,
+        });
+    }
+    Json(response)
+}
+
+async fn replay_call(
+    State(replay): State<Arc<Mutex<Replay>>>,
+    Json(body): Json<Value>,
+) -> Json<Value> {
+    let mut replay = replay.lock().expect("RPC replayer lock poisoned");
+    let mut responses: Vec<Value> = as_calls(&body)
+        .into_iter()
+        .map(|call| {
+            let method = call["method"].as_str().unwrap_or_default();
+            let mut response = replay.answer(method, &params(call));
+            if let Some(response) = response.as_object_mut() {
+                response.insert("jsonrpc".to_string(), json!("2.0"));
+                response.insert(
+                    "id".to_string(),
+                    call.get("id").cloned().unwrap_or(Value::Null),
+                );
+            }
+            response
+        })
+        .collect();
+    match body {
+        Value::Array(_) => Json(Value::Array(responses)),
+        _ => Json(responses.pop().unwrap_or(Value::Null)),
+    }
+}
+
+fn as_calls(body: &Value) -> Vec<&Value> {
+    match body {
+        Value::Array(calls) => calls.iter().collect(),
+        call => vec![call],
+    }
+}
+
+// Missing and empty params are the same call
+fn params(call: &Value) -> Value {
+    match call.get("params") {
+        Some(Value::Array(params)) if params.is_empty() => Value::Null,
+        Some(params) => params.clone(),
+        None => Value::Null,
+    }
+}
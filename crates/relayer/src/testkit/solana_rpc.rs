@@ -0,0 +1,328 @@
+use anyhow::{Context, Result};
+use axum::{
+    extract::State,
+    response::{IntoResponse, Response},
+    routing::post,
+    Json, Router,
+};
+use base64::{engine::general_purpose, Engine};
+use ed25519_dalek::{Signature, VerifyingKey};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::{
+    collections::{HashMap, VecDeque},
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+};
+use tokio_util::sync::DropGuard;
+
+use super::serve;
+use crate::config::SolanaAccountConfig;
+use crate::event_delivery::{
+    decode, DecodedTransaction, COMPUTE_BUDGET_PROGRAM, SET_COMPUTE_UNIT_LIMIT,
+    SET_COMPUTE_UNIT_PRICE,
+};
+use crate::types::SolanaPubkey;
+
+// Height at which the mock's blockhash stops being valid, and the chain's
+// height before and after it expires
+const LAST_VALID_BLOCK_HEIGHT: u64 = 1_000;
+const BLOCK_HEIGHT: u64 = 900;
+const EXPIRED_BLOCK_HEIGHT: u64 = 1_001;
+
+/// How the mock treats the next transaction it is sent
+#[derive(Debug, Clone)]
+pub enum SolanaReply {
+    /// Fail the preflight simulation with transaction error `err` and
+    /// program `logs`
+    Reject { err: Value, logs: Vec<String> },
+    /// Accept it, then report it failed on chain with `err`
+    FailOnChain(Value),
+    /// Accept it, then never land it, letting its blockhash expire
+    Drop,
+}
+
+impl SolanaReply {
+    /// Preflight failure of the delivery instruction, explained by `logs`
+    pub fn program_error(logs: &[&str]) -> Self {
+        Self::Reject {
+            err: json!({ "InstructionError": [2, { "Custom": 6000 }] }),
+            logs: logs.iter().map(|log| log.to_string()).collect(),
+        }
+    }
+
+    /// Preflight failure with no instruction at fault, such as
+    /// `"InsufficientFundsForFee"` or `"BlockhashNotFound"`
+    pub fn transaction_error(err: &str) -> Self {
+        Self::Reject {
+            err: json!(err),
+            logs: Vec::new(),
+        }
+    }
+}
+
+/// Call to a program other than the compute budget program
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SolanaCall {
+    pub program_id: SolanaPubkey,
+    /// Accounts after the fee payer, which is always passed first
+    pub accounts: Vec<SolanaAccountConfig>,
+    pub data: Vec<u8>,
+}
+
+/// Transaction the mock accepted, its signature checked
+#[derive(Debug, Clone)]
+pub struct SentSolanaTransaction {
+    pub signature: String,
+    pub fee_payer: SolanaPubkey,
+    pub recent_blockhash: String,
+    pub compute_unit_limit: Option<u32>,
+    /// Priority fee, in micro-lamports per compute unit
+    pub compute_unit_price: Option<u64>,
+    pub calls: Vec<SolanaCall>,
+    /// Size on the wire
+    pub size: usize,
+}
+
+#[derive(Default)]
+struct Script {
+    replies: VecDeque<SolanaReply>,
+    priority_fees: Vec<u64>,
+    sent: Vec<SentSolanaTransaction>,
+    /// On-chain error of each landed transaction, `Value::Null` if it succeeded
+    landed: HashMap<String, Value>,
+    expired: bool,
+    calls: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct Call {
+    method: String,
+    #[serde(default)]
+    params: Vec<Value>,
+    #[serde(default)]
+    id: Value,
+}
+
+/// Local stand-in for a Solana RPC node, answering the calls [`SolanaSink`]
+/// makes
+///
+/// Every transaction sent is decoded and its fee payer's signature checked;
+/// unless a [`SolanaReply`] was scripted for it, it lands at once and reports
+/// finalized. The server stops when the handle is dropped.
+///
+/// [`SolanaSink`]: crate::SolanaSink
+pub struct MockSolanaRpc {
+    addr: SocketAddr,
+    script: Arc<Mutex<Script>>,
+    blockhash: String,
+    _stop: DropGuard,
+}
+
+impl MockSolanaRpc {
+    /// Serve on a free port on the loopback interface
+    pub async fn start() -> Result<Self> {
+        let blockhash = bs58::encode([7u8; 32]).into_string();
+        let script = Arc::new(Mutex::new(Script::default()));
+        let app = Router::new()
+            .route("/", post(handle))
+            .with_state((script.clone(), blockhash.clone()));
+        let (addr, stop) = serve(app)
+            .await
+            .context("Failed to start mock Solana RPC")?;
+        Ok(Self {
+            addr,
+            script,
+            blockhash,
+            _stop: stop,
+        })
+    }
+
+    /// URL to configure as the sink's RPC endpoint
+    pub fn url(&self) -> String {
+        format!("http://{}/", self.addr)
+    }
+
+    /// Blockhash every transaction is expected to carry
+    pub fn blockhash(&self) -> &str {
+        &self.blockhash
+    }
+
+    /// Treat the next transactions sent as `replies` say, in order
+    pub fn script(&self, replies: impl IntoIterator<Item = SolanaReply>) -> &Self {
+        self.lock().replies.extend(replies);
+        self
+    }
+
+    /// Answer `getRecentPrioritizationFees` with these fees, one per slot
+    pub fn priority_fees(&self, fees: impl IntoIterator<Item = u64>) -> &Self {
+        self.lock().priority_fees = fees.into_iter().collect();
+        self
+    }
+
+    /// Transactions accepted so far, oldest first
+    pub fn sent(&self) -> Vec<SentSolanaTransaction> {
+        self.lock().sent.clone()
+    }
+
+    /// How many calls of `method` were received so far
+    pub fn call_count(&self, method: &str) -> usize {
+        self.lock()
+            .calls
+            .iter()
+            .filter(|call| *call == method)
+            .count()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Script> {
+        self.script.lock().expect("mock Solana RPC lock poisoned")
+    }
+}
+
+async fn handle(
+    State((script, blockhash)): State<(Arc<Mutex<Script>>, String)>,
+    Json(call): Json<Call>,
+) -> Response {
+    let mut script = script.lock().expect("mock Solana RPC lock poisoned");
+    script.calls.push(call.method.clone());
+    let result = match call.method.as_str() {
+        "getLatestBlockhash" => Ok(json!({
+            "context": { "slot": BLOCK_HEIGHT },
+            "value": { "blockhash": blockhash, "lastValidBlockHeight": LAST_VALID_BLOCK_HEIGHT },
+        })),
+        "getBlockHeight" => Ok(json!(if script.expired {
+            EXPIRED_BLOCK_HEIGHT
+        } else {
+            BLOCK_HEIGHT
+        })),
+        "getRecentPrioritizationFees" => Ok(Value::Array(
+            script
+                .priority_fees
+                .iter()
+                .enumerate()
+                .map(|(slot, fee)| json!({ "slot": slot, "prioritizationFee": fee }))
+                .collect(),
+        )),
+        "sendTransaction" => send(&mut script, &call.params),
+        "getSignatureStatuses" => {
+            let statuses: Vec<Value> = call
+                .params
+                .first()
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+                .map(|signature| {
+                    let landed = signature.as_str().and_then(|sig| script.landed.get(sig));
+                    match landed {
+                        Some(err) => json!({
+                            "slot": BLOCK_HEIGHT,
+                            "confirmations": null,
+                            "err": err,
+                            "confirmationStatus": "finalized",
+                        }),
+                        None => Value::Null,
+                    }
+                })
+                .collect();
+            Ok(json!({ "context": { "slot": BLOCK_HEIGHT }, "value": statuses }))
+        }
+        _ => Err(json!({ "code": -32601, "message": "Method not found" })),
+    };
+    let body = match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": call.id, "result": result }),
+        Err(error) => json!({ "jsonrpc": "2.0", "id": call.id, "error": error }),
+    };
+    Json(body).into_response()
+}
+
+fn send(script: &mut Script, params: &[Value]) -> Result<Value, Value> {
+    let invalid = |message: String| json!({ "code": -32602, "message": message });
+    let encoded = params
+        .first()
+        .and_then(Value::as_str)
+        .ok_or_else(|| invalid("missing transaction".to_string()))?;
+    let bytes = general_purpose::STANDARD
+        .decode(encoded)
+        .map_err(|e| invalid(format!("invalid base64: {}", e)))?;
+    let transaction = decode(&bytes).map_err(|e| invalid(format!("invalid transaction: {}", e)))?;
+    if !signed_by_fee_payer(&transaction) {
+        return Err(json!({
+            "code": -32003,
+            "message": "Transaction signature verification failure",
+        }));
+    }
+
+    let signature = bs58::encode(transaction.signatures[0]).into_string();
+    match script.replies.pop_front() {
+        Some(SolanaReply::Reject { err, logs }) => {
+            return Err(json!({
+                "code": -32002,
+                "message": format!("Transaction simulation failed: {}", err),
+                "data": { "err": err, "logs": logs },
+            }));
+        }
+        Some(SolanaReply::FailOnChain(err)) => {
+            script.landed.insert(signature.clone(), err);
+        }
+        Some(SolanaReply::Drop) => script.expired = true,
+        None => {
+            script.landed.insert(signature.clone(), Value::Null);
+        }
+    }
+    script
+        .sent
+        .push(sent(&transaction, signature.clone(), bytes.len()));
+    Ok(json!(signature))
+}
+
+fn signed_by_fee_payer(transaction: &DecodedTransaction) -> bool {
+    let (Some(signature), Some(fee_payer)) = (
+        transaction.signatures.first(),
+        transaction.account_keys.first(),
+    ) else {
+        return false;
+    };
+    VerifyingKey::from_bytes(&fee_payer.to_bytes())
+        .and_then(|key| key.verify_strict(&transaction.message, &Signature::from_bytes(signature)))
+        .is_ok()
+}
+
+fn sent(transaction: &DecodedTransaction, signature: String, size: usize) -> SentSolanaTransaction {
+    let mut sent = SentSolanaTransaction {
+        signature,
+        fee_payer: transaction.account_keys[0],
+        recent_blockhash: bs58::encode(transaction.recent_blockhash).into_string(),
+        compute_unit_limit: None,
+        compute_unit_price: None,
+        calls: Vec::new(),
+        size,
+    };
+    for instruction in &transaction.instructions {
+        if instruction.program_id != COMPUTE_BUDGET_PROGRAM {
+            sent.calls.push(SolanaCall {
+                program_id: instruction.program_id,
+                accounts: instruction
+                    .accounts
+                    .iter()
+                    .filter(|meta| meta.pubkey != sent.fee_payer)
+                    .map(|meta| SolanaAccountConfig {
+                        pubkey: meta.pubkey,
+                        writable: meta.writable,
+                    })
+                    .collect(),
+                data: instruction.data.clone(),
+            });
+            continue;
+        }
+        match instruction.data.split_first() {
+            Some((&SET_COMPUTE_UNIT_LIMIT, units)) => {
+                sent.compute_unit_limit = units.try_into().ok().map(u32::from_le_bytes);
+            }
+            Some((&SET_COMPUTE_UNIT_PRICE, price)) => {
+                sent.compute_unit_price = price.try_into().ok().map(u64::from_le_bytes);
+            }
+            _ => {}
+        }
+    }
+    sent
+}
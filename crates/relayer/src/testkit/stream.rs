@@ -0,0 +1,333 @@
+use anyhow::{bail, Context, Result};
+use std::{
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+    net::{TcpListener, TcpStream},
+};
+use tokio_util::sync::{CancellationToken, DropGuard};
+
+use crate::status::EventRecord;
+use crate::stream::{crc32c, PRODUCE};
+
+// Kafka's error code for a record batch failing its CRC
+const CORRUPT_MESSAGE: i16 = 2;
+
+/// Status change a mock broker received
+#[derive(Debug, Clone)]
+pub struct StreamedMessage {
+    /// NATS subject or Kafka topic it was published on
+    pub topic: String,
+    /// Kafka partition it was written to; NATS has none
+    pub partition: Option<i32>,
+    /// Kafka record key; NATS messages have none
+    pub key: Option<String>,
+    pub payload: Vec<u8>,
+}
+
+impl StreamedMessage {
+    /// The status change the message carries
+    pub fn record(&self) -> Result<EventRecord> {
+        Ok(serde_json::from_slice(&self.payload)?)
+    }
+}
+
+type Received = Arc<Mutex<Vec<StreamedMessage>>>;
+
+/// Local NATS server speaking just enough of the protocol for the event
+/// stream, recording every `PUB`
+///
+/// The server stops when the handle is dropped.
+pub struct MockNats {
+    addr: SocketAddr,
+    received: Received,
+    _stop: DropGuard,
+}
+
+impl MockNats {
+    /// Serve on a free port on the loopback interface
+    pub async fn start() -> Result<Self> {
+        let received = Received::default();
+        let (addr, stop) = listen(received.clone(), serve_nats)
+            .await
+            .context("Failed to start mock NATS server")?;
+        Ok(Self {
+            addr,
+            received,
+            _stop: stop,
+        })
+    }
+
+    /// URL to configure as the stream's NATS server
+    pub fn url(&self) -> String {
+        format!("nats://{}", self.addr)
+    }
+
+    /// Every message published so far, oldest first
+    pub fn messages(&self) -> Vec<StreamedMessage> {
+        self.received
+            .lock()
+            .expect("mock NATS lock poisoned")
+            .clone()
+    }
+}
+
+/// Local Kafka broker taking Produce v3 requests, recording every record
+/// of the batches whose CRC checks out
+///
+/// Batches failing the check are answered with `CORRUPT_MESSAGE`. The server
+/// stops when the handle is dropped.
+pub struct MockKafka {
+    addr: SocketAddr,
+    received: Received,
+    _stop: DropGuard,
+}
+
+impl MockKafka {
+    /// Serve on a free port on the loopback interface
+    pub async fn start() -> Result<Self> {
+        let received = Received::default();
+        let (addr, stop) = listen(received.clone(), serve_kafka)
+            .await
+            .context("Failed to start mock Kafka broker")?;
+        Ok(Self {
+            addr,
+            received,
+            _stop: stop,
+        })
+    }
+
+    /// Address to configure as the stream's Kafka broker
+    pub fn broker(&self) -> String {
+        self.addr.to_string()
+    }
+
+    /// Every record written so far, oldest first
+    pub fn messages(&self) -> Vec<StreamedMessage> {
+        self.received
+            .lock()
+            .expect("mock Kafka lock poisoned")
+            .clone()
+    }
+}
+
+// Accept connections on a free loopback port, serving each with `serve`,
+// until the returned guard is dropped
+async fn listen<F, Fut>(received: Received, serve: F) -> Result<(SocketAddr, DropGuard)>
+where
+    F: Fn(TcpStream, Received) -> Fut + Send + 'static,
+    Fut: std::future::Future<Output = Result<()>> + Send + 'static,
+{
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let addr = listener.local_addr()?;
+    let stop = CancellationToken::new();
+    let stopped = stop.clone();
+    tokio::spawn(async move {
+        loop {
+            let connection = tokio::select! {
+                accepted = listener.accept() => accepted,
+                _ = stopped.cancelled() => return,
+            };
+            let Ok((connection, _)) = connection else {
+                continue;
+            };
+            let connection = serve(connection, received.clone());
+            let stopped = stopped.clone();
+            tokio::spawn(async move {
+                tokio::select! {
+                    _ = connection => {}
+                    _ = stopped.cancelled() => {}
+                }
+            });
+        }
+    });
+    Ok((addr, stop.drop_guard()))
+}
+
+async fn serve_nats(connection: TcpStream, received: Received) -> Result<()> {
+    let (read, mut write) = connection.into_split();
+    let mut reader = BufReader::new(read);
+    write
+        .write_all(b"INFO {\"server_id\":\"mock\",\"max_payload\":1048576}\r\n")
+        .await?;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).await? == 0 {
+            return Ok(());
+        }
+        let line = line.trim_end();
+        if line == "PING" {
+            write.write_all(b"PONG\r\n").await?;
+        } else if let Some(args) = line.strip_prefix("PUB ") {
+            // `PUB <subject> [reply-to] <size>`
+            let args: Vec<&str> = args.split_whitespace().collect();
+            let (Some(subject), Some(size)) = (args.first(), args.last()) else {
+                bail!("Malformed PUB {:?}", line);
+            };
+            let size: usize = size.parse()?;
+            let mut payload = vec![0; size + 2];
+            reader.read_exact(&mut payload).await?;
+            payload.truncate(size);
+            received
+                .lock()
+                .expect("mock NATS lock poisoned")
+                .push(StreamedMessage {
+                    topic: subject.to_string(),
+                    partition: None,
+                    key: None,
+                    payload,
+                });
+        }
+        // `CONNECT` and anything else are taken silently
+    }
+}
+
+async fn serve_kafka(mut connection: TcpStream, received: Received) -> Result<()> {
+    loop {
+        let Ok(size) = connection.read_i32().await else {
+            return Ok(());
+        };
+        let mut request = vec![0; size.max(0) as usize];
+        connection.read_exact(&mut request).await?;
+        let response = produce(&request, &received)?;
+        connection
+            .write_all(&(response.len() as i32).to_be_bytes())
+            .await?;
+        connection.write_all(&response).await?;
+    }
+}
+
+// Answer a Produce request, recording the records it carries
+fn produce(request: &[u8], received: &Mutex<Vec<StreamedMessage>>) -> Result<Vec<u8>> {
+    let mut request = Cursor(request);
+    let api_key = request.i16()?;
+    let _version = request.i16()?;
+    let correlation_id = request.i32()?;
+    request.string()?; // client id
+    if api_key != PRODUCE {
+        bail!(
+            "Mock Kafka only takes Produce requests, got API key {}",
+            api_key
+        );
+    }
+    request.string()?; // transactional id
+    let _acks = request.i16()?;
+    let _timeout = request.i32()?;
+
+    let mut response = correlation_id.to_be_bytes().to_vec();
+    let topics = request.i32()?;
+    response.extend(topics.to_be_bytes());
+    for _ in 0..topics {
+        let topic = request.string()?.unwrap_or_default();
+        response.extend((topic.len() as i16).to_be_bytes());
+        response.extend(topic.as_bytes());
+        let partitions = request.i32()?;
+        response.extend(partitions.to_be_bytes());
+        for _ in 0..partitions {
+            let partition = request.i32()?;
+            let size = request.i32()?;
+            let batch = request.take(size.max(0) as usize)?;
+            let error_code = match records(batch) {
+                Some(records) => {
+                    let mut received = received.lock().expect("mock Kafka lock poisoned");
+                    for (key, payload) in records {
+                        received.push(StreamedMessage {
+                            topic: topic.clone(),
+                            partition: Some(partition),
+                            key,
+                            payload,
+                        });
+                    }
+                    0
+                }
+                None => CORRUPT_MESSAGE,
+            };
+            response.extend(partition.to_be_bytes());
+            response.extend(error_code.to_be_bytes());
+            response.extend(0i64.to_be_bytes()); // base offset
+            response.extend((-1i64).to_be_bytes()); // log append time
+        }
+    }
+    response.extend(0i32.to_be_bytes()); // throttle time
+    Ok(response)
+}
+
+// Keys and values of the records in a v2 batch, if it is well formed
+fn records(batch: &[u8]) -> Option<Vec<(Option<String>, Vec<u8>)>> {
+    let mut batch = Cursor(batch);
+    // Base offset, batch length and partition leader epoch
+    batch.take(16).ok()?;
+    if batch.take(1).ok()? != [2] {
+        return None;
+    }
+    let crc = batch.i32().ok()? as u32;
+    if crc32c(batch.0) != crc {
+        return None;
+    }
+    // Attributes, offset delta, timestamps, producer id, epoch and sequence
+    batch.take(36).ok()?;
+    let count = batch.i32().ok()?;
+    let mut records = Vec::new();
+    for _ in 0..count {
+        batch.varint()?; // length
+        batch.take(1).ok()?; // attributes
+        batch.varint()?; // timestamp delta
+        batch.varint()?; // offset delta
+        let key = match batch.varint()? {
+            -1 => None,
+            len => Some(String::from_utf8(batch.take(len as usize).ok()?.to_vec()).ok()?),
+        };
+        let len = batch.varint()?;
+        let value = batch.take(len.max(0) as usize).ok()?.to_vec();
+        if batch.varint()? != 0 {
+            return None;
+        }
+        records.push((key, value));
+    }
+    Some(records)
+}
+
+// Big-endian fields read off the front of a request
+struct Cursor<'a>(&'a [u8]);
+
+impl<'a> Cursor<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        if self.0.len() < len {
+            bail!("Kafka request ended early");
+        }
+        let (taken, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(taken)
+    }
+
+    fn i16(&mut self) -> Result<i16> {
+        Ok(i16::from_be_bytes(self.take(2)?.try_into()?))
+    }
+
+    fn i32(&mut self) -> Result<i32> {
+        Ok(i32::from_be_bytes(self.take(4)?.try_into()?))
+    }
+
+    fn string(&mut self) -> Result<Option<String>> {
+        let len = self.i16()?;
+        if len < 0 {
+            return Ok(None);
+        }
+        Ok(Some(String::from_utf8(self.take(len as usize)?.to_vec())?))
+    }
+
+    // Zigzag varint
+    fn varint(&mut self) -> Option<i64> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.take(1).ok()?[0];
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Some((value >> 1) as i64 ^ -((value & 1) as i64));
+            }
+        }
+        None
+    }
+}
@@ -0,0 +1,109 @@
+use anyhow::{Context, Result};
+use axum::{
+    body::Bytes,
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::post,
+    Json, Router,
+};
+use ethers::core::types::H256;
+use serde_json::json;
+use std::{
+    collections::VecDeque,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+};
+use tokio_util::sync::DropGuard;
+
+use super::serve;
+
+/// POST the mock endpoint received
+#[derive(Debug, Clone)]
+pub struct ReceivedPost {
+    pub headers: HeaderMap,
+    pub body: Bytes,
+}
+
+impl ReceivedPost {
+    /// Value of header `name`, if it was sent as text
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).and_then(|value| value.to_str().ok())
+    }
+}
+
+#[derive(Default)]
+struct Script {
+    replies: VecDeque<StatusCode>,
+    tx_hash: Option<H256>,
+    posts: Vec<ReceivedPost>,
+}
+
+/// Local endpoint for a [`WebhookSink`], answering each POST with the next
+/// scripted status
+///
+/// Once the script runs out every POST gets 200, with the configured
+/// transaction hash in the body if there is one. Every POST is recorded.
+/// The server stops when the handle is dropped.
+///
+/// [`WebhookSink`]: crate::WebhookSink
+pub struct MockWebhook {
+    addr: SocketAddr,
+    script: Arc<Mutex<Script>>,
+    _stop: DropGuard,
+}
+
+impl MockWebhook {
+    /// Serve on a free port on the loopback interface
+    pub async fn start() -> Result<Self> {
+        let script = Arc::new(Mutex::new(Script::default()));
+        let app = Router::new()
+            .route("/", post(handle))
+            .with_state(script.clone());
+        let (addr, stop) = serve(app).await.context("Failed to start mock webhook")?;
+        Ok(Self {
+            addr,
+            script,
+            _stop: stop,
+        })
+    }
+
+    /// URL to configure as the sink's endpoint
+    pub fn url(&self) -> String {
+        format!("http://{}/", self.addr)
+    }
+
+    /// Answer the next POSTs with `replies`, in order
+    pub fn script(&self, replies: impl IntoIterator<Item = StatusCode>) -> &Self {
+        self.lock().replies.extend(replies);
+        self
+    }
+
+    /// Name `tx_hash` as the delivering transaction in successful answers
+    pub fn tx_hash(&self, tx_hash: H256) -> &Self {
+        self.lock().tx_hash = Some(tx_hash);
+        self
+    }
+
+    /// Every POST received so far, oldest first
+    pub fn posts(&self) -> Vec<ReceivedPost> {
+        self.lock().posts.clone()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Script> {
+        self.script.lock().expect("mock webhook lock poisoned")
+    }
+}
+
+async fn handle(
+    State(script): State<Arc<Mutex<Script>>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    let mut script = script.lock().expect("mock webhook lock poisoned");
+    script.posts.push(ReceivedPost { headers, body });
+    match script.replies.pop_front() {
+        Some(status) if !status.is_success() => (status, "scripted failure").into_response(),
+        _ => Json(json!({ "tx_hash": script.tx_hash })).into_response(),
+    }
+}
@@ -0,0 +1,308 @@
+use anyhow::Result;
+use ethers::utils::keccak256;
+use serde::Serialize;
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
+use tokio::sync::watch;
+use tracing::{info, warn};
+
+use crate::config::{ChainConfig, RelayPair, ShardingConfig};
+use crate::metrics::PAUSED;
+use crate::types::{ChainId, RelayEvent, RelayerError};
+
+/// Chains and relay pairs the relayer serves, editable while it runs
+///
+/// Changes take effect on the event generator's next poll. They are kept in
+/// memory only, so a fresh process starts again from its configuration.
+///
+/// Pausing holds new event detection and delivery submission, globally or
+/// for single pairs, while leaving everything already detected in place.
+///
+/// When relay pairs are sharded, every instance knows every pair but only
+/// picks up events for those in its own shard. Shard assignments made at
+/// runtime have to be made on every instance alike.
+#[derive(Clone)]
+pub struct Topology {
+    inner: Arc<RwLock<Inner>>,
+    /// Signalled whenever relaying is paused or resumed
+    pause_changes: Arc<watch::Sender<()>>,
+}
+
+struct Inner {
+    chains: HashMap<ChainId, ChainConfig>,
+    pairs: Vec<ManagedPair>,
+    sharding: ShardingConfig,
+    /// Every pair is held, whatever its own pause state
+    paused: bool,
+}
+
+/// A relay pair and whether new events are being picked up for it
+#[derive(Debug, Clone, Serialize)]
+pub struct ManagedPair {
+    pub id: String,
+    pub enabled: bool,
+    /// Detection and delivery are held for the pair until it is resumed
+    pub paused: bool,
+    /// Shard whose instance picks up the pair's events
+    pub shard: u32,
+    /// Whether the shard was assigned rather than derived from the pair ID
+    pub pinned: bool,
+    pub pair: RelayPair,
+}
+
+impl Topology {
+    pub fn new(
+        chains: HashMap<ChainId, ChainConfig>,
+        pairs: Vec<RelayPair>,
+        sharding: ShardingConfig,
+    ) -> Self {
+        let count = sharding.shard_count.max(1);
+        for (id, shard) in &sharding.assignments {
+            if *shard >= count {
+                warn!(pair = %id, shard, count, "Ignoring assignment to a shard out of range");
+            }
+        }
+        let mut inner = Inner {
+            chains,
+            pairs: Vec::new(),
+            sharding,
+            paused: false,
+        };
+        inner.pairs = pairs.into_iter().map(|pair| inner.manage(pair)).collect();
+        Self {
+            inner: Arc::new(RwLock::new(inner)),
+            pause_changes: Arc::new(watch::channel(()).0),
+        }
+    }
+
+    /// This instance's shard and the number of shards
+    pub fn shard(&self) -> (u32, u32) {
+        let inner = self.read();
+        (
+            inner.sharding.shard_index,
+            inner.sharding.shard_count.max(1),
+        )
+    }
+
+    /// Every configured chain, ordered by chain ID
+    pub fn chains(&self) -> Vec<ChainConfig> {
+        let mut chains: Vec<ChainConfig> = self.read().chains.values().cloned().collect();
+        chains.sort_by_key(|chain| chain.chain_id);
+        chains
+    }
+
+    pub fn chain(&self, chain_id: ChainId) -> Option<ChainConfig> {
+        self.read().chains.get(&chain_id).cloned()
+    }
+
+    /// Every relay pair, enabled or not
+    pub fn pairs(&self) -> Vec<ManagedPair> {
+        self.read().pairs.clone()
+    }
+
+    /// Relay pairs in this instance's shard that new events are picked up for,
+    /// leaving out paused ones
+    pub fn enabled_pairs(&self) -> Vec<RelayPair> {
+        let inner = self.read();
+        if inner.paused {
+            return Vec::new();
+        }
+        inner
+            .pairs
+            .iter()
+            .filter(|managed| managed.enabled && !managed.paused)
+            .filter(|managed| managed.shard == inner.sharding.shard_index)
+            .map(|managed| managed.pair.clone())
+            .collect()
+    }
+
+    /// Whether relaying is paused for every pair
+    pub fn paused(&self) -> bool {
+        self.read().paused
+    }
+
+    /// Hold or resume detection and delivery for one pair, or for every pair
+    /// if `id` is unset
+    ///
+    /// Pausing every pair leaves each pair's own pause state as it was.
+    pub fn set_paused(&self, id: Option<&str>, paused: bool) -> Result<()> {
+        {
+            let mut inner = self.write();
+            match id {
+                Some(id) => {
+                    let managed = inner
+                        .pairs
+                        .iter_mut()
+                        .find(|managed| managed.id == id)
+                        .ok_or_else(|| RelayerError::UnknownRelayPair(id.to_string()))?;
+                    managed.paused = paused;
+                }
+                None => inner.paused = paused,
+            }
+        }
+        let pair = id.unwrap_or("*");
+        PAUSED.with_label_values(&[pair]).set(paused as i64);
+        info!(pair, paused, "Relaying pause changed");
+        self.pause_changes.send_replace(());
+        Ok(())
+    }
+
+    /// Whether an event is held by a global pause or its pair's
+    pub(crate) fn is_paused(&self, event: &RelayEvent) -> bool {
+        let inner = self.read();
+        inner.paused
+            || inner
+                .pairs
+                .iter()
+                .any(|managed| managed.paused && managed.pair.matches(event))
+    }
+
+    /// Wait until none of `events` is held by a pause any more
+    pub(crate) async fn wait_while_paused(&self, events: &[&RelayEvent]) {
+        let mut changes = self.pause_changes.subscribe();
+        while events.iter().any(|event| self.is_paused(event)) {
+            // The sender lives as long as the topology
+            let _ = changes.changed().await;
+        }
+    }
+
+    /// The pair an event was relayed for, including a disabled one so events
+    /// already in flight still deliver the way their pair asks
+    pub fn pair_for(&self, event: &RelayEvent) -> Option<RelayPair> {
+        self.read()
+            .pairs
+            .iter()
+            .find(|managed| managed.pair.matches(event))
+            .map(|managed| managed.pair.clone())
+    }
+
+    pub fn add_chain(&self, chain: ChainConfig) -> Result<()> {
+        let mut inner = self.write();
+        if inner.chains.contains_key(&chain.chain_id) {
+            return Err(RelayerError::ChainExists(chain.chain_id).into());
+        }
+        inner.chains.insert(chain.chain_id, chain);
+        Ok(())
+    }
+
+    /// Remove a chain no relay pair uses any more
+    pub fn remove_chain(&self, chain_id: ChainId) -> Result<ChainConfig> {
+        let mut inner = self.write();
+        if let Some(managed) = inner.pairs.iter().find(|managed| {
+            managed.pair.source_chain_id == chain_id || managed.pair.dest_chain_id == chain_id
+        }) {
+            return Err(RelayerError::ChainInUse {
+                chain_id,
+                pair: managed.id.clone(),
+            }
+            .into());
+        }
+        inner
+            .chains
+            .remove(&chain_id)
+            .ok_or_else(|| RelayerError::UnknownChain(chain_id).into())
+    }
+
+    /// Add an enabled pair between two configured chains
+    pub fn add_pair(&self, pair: RelayPair) -> Result<ManagedPair> {
+        pair.validate()?;
+        let mut inner = self.write();
+        for chain_id in [pair.source_chain_id, pair.dest_chain_id] {
+            if !inner.chains.contains_key(&chain_id) {
+                return Err(RelayerError::UnknownChain(chain_id).into());
+            }
+        }
+        pair.validate_chains(
+            &inner.chains[&pair.source_chain_id],
+            &inner.chains[&pair.dest_chain_id],
+        )?;
+        let id = pair.id();
+        if inner.pairs.iter().any(|managed| managed.id == id) {
+            return Err(RelayerError::RelayPairExists(id).into());
+        }
+        let managed = inner.manage(pair);
+        inner.pairs.push(managed.clone());
+        Ok(managed)
+    }
+
+    /// Pin a pair to a shard, or return it to the shard its ID hashes to
+    pub fn assign_shard(&self, id: &str, shard: Option<u32>) -> Result<ManagedPair> {
+        let mut inner = self.write();
+        let count = inner.sharding.shard_count.max(1);
+        if let Some(shard) = shard.filter(|shard| *shard >= count) {
+            return Err(RelayerError::InvalidShard { shard, count }.into());
+        }
+        let managed = inner
+            .pairs
+            .iter_mut()
+            .find(|managed| managed.id == id)
+            .ok_or_else(|| RelayerError::UnknownRelayPair(id.to_string()))?;
+        managed.shard = shard.unwrap_or_else(|| hashed_shard(&managed.id, count));
+        managed.pinned = shard.is_some();
+        Ok(managed.clone())
+    }
+
+    /// Start or stop picking up new events for a pair
+    pub fn set_enabled(&self, id: &str, enabled: bool) -> Result<ManagedPair> {
+        let mut inner = self.write();
+        let managed = inner
+            .pairs
+            .iter_mut()
+            .find(|managed| managed.id == id)
+            .ok_or_else(|| RelayerError::UnknownRelayPair(id.to_string()))?;
+        managed.enabled = enabled;
+        Ok(managed.clone())
+    }
+
+    /// Forget a pair; disable it first and let its deliveries finish, since
+    /// events still in flight fall back to default delivery settings
+    pub fn remove_pair(&self, id: &str) -> Result<ManagedPair> {
+        let mut inner = self.write();
+        let index = inner
+            .pairs
+            .iter()
+            .position(|managed| managed.id == id)
+            .ok_or_else(|| RelayerError::UnknownRelayPair(id.to_string()))?;
+        Ok(inner.pairs.remove(index))
+    }
+
+    fn read(&self) -> std::sync::RwLockReadGuard<'_, Inner> {
+        self.inner.read().expect("topology lock poisoned")
+    }
+
+    fn write(&self) -> std::sync::RwLockWriteGuard<'_, Inner> {
+        self.inner.write().expect("topology lock poisoned")
+    }
+}
+
+impl Inner {
+    // An enabled pair in the shard configured for it, or else the one its ID
+    // hashes to
+    fn manage(&self, pair: RelayPair) -> ManagedPair {
+        let id = pair.id();
+        let count = self.sharding.shard_count.max(1);
+        let pinned = self
+            .sharding
+            .assignments
+            .get(&id)
+            .copied()
+            .filter(|shard| *shard < count);
+        ManagedPair {
+            shard: pinned.unwrap_or_else(|| hashed_shard(&id, count)),
+            pinned: pinned.is_some(),
+            id,
+            enabled: true,
+            paused: false,
+            pair,
+        }
+    }
+}
+
+// Shard a pair ID falls in, the same on every instance and every run
+fn hashed_shard(id: &str, count: u32) -> u32 {
+    let hash = keccak256(id.as_bytes());
+    let prefix = u64::from_be_bytes(hash[..8].try_into().expect("hash has 32 bytes"));
+    (prefix % count as u64) as u32
+}
@@ -1,44 +1,327 @@
-use ethers::core::types::{Bytes, H256};
+use ethers::{
+    abi::{self, Token},
+    core::types::{Address, Bytes, H256, U256},
+    utils::keccak256,
+};
+use serde::{Deserialize, Serialize};
 
 // Re-export the config types
 pub use crate::config::ChainConfig;
+use crate::config::pair_id;
+use crate::schema::{DeliveryRequestV1, EventMetaV1, RelayEventV1};
+use crate::store::ProofKey;
+use crate::telemetry::TraceContext;
+
+/// EVM chain id
+///
+/// Chain ids are 64-bit, but contracts and the SQLite store take narrower
+/// integers; converting to those goes through the checked `as_*` methods
+/// rather than a truncating cast.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize,
+)]
+#[serde(transparent)]
+pub struct ChainId(u64);
+
+impl ChainId {
+    pub const fn new(id: u64) -> Self {
+        Self(id)
+    }
+
+    pub const fn as_u64(self) -> u64 {
+        self.0
+    }
+
+    /// The id as a `uint32`, as the resolver's `crossChainChecker` takes it
+    pub fn as_u32(self) -> Result<u32, RelayerError> {
+        u32::try_from(self.0).map_err(|_| RelayerError::ChainIdOutOfRange {
+            chain_id: self,
+            target: "u32",
+        })
+    }
+
+    /// The id as a signed 64-bit integer, as SQLite stores it
+    pub fn as_i64(self) -> Result<i64, RelayerError> {
+        i64::try_from(self.0).map_err(|_| RelayerError::ChainIdOutOfRange {
+            chain_id: self,
+            target: "i64",
+        })
+    }
+}
+
+impl std::fmt::Display for ChainId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl std::str::FromStr for ChainId {
+    type Err = std::num::ParseIntError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse().map(Self)
+    }
+}
+
+impl From<u64> for ChainId {
+    fn from(id: u64) -> Self {
+        Self(id)
+    }
+}
+
+impl From<u32> for ChainId {
+    fn from(id: u32) -> Self {
+        Self(id.into())
+    }
+}
+
+impl From<ChainId> for u64 {
+    fn from(id: ChainId) -> Self {
+        id.0
+    }
+}
+
+impl From<ChainId> for U256 {
+    fn from(id: ChainId) -> Self {
+        id.0.into()
+    }
+}
+
+/// Solana account or program address, written in base58
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(into = "String", try_from = "String")]
+pub struct SolanaPubkey([u8; 32]);
+
+impl SolanaPubkey {
+    pub const fn new(bytes: [u8; 32]) -> Self {
+        Self(bytes)
+    }
+
+    pub const fn to_bytes(self) -> [u8; 32] {
+        self.0
+    }
+}
+
+impl std::fmt::Display for SolanaPubkey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&bs58::encode(self.0).into_string())
+    }
+}
+
+impl std::fmt::Debug for SolanaPubkey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Display::fmt(self, f)
+    }
+}
+
+impl std::str::FromStr for SolanaPubkey {
+    type Err = RelayerError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut bytes = [0u8; 32];
+        match bs58::decode(s).onto(&mut bytes) {
+            Ok(32) => Ok(Self(bytes)),
+            _ => Err(RelayerError::InvalidConfig(format!(
+                "{} is not a base58 Solana public key",
+                s
+            ))),
+        }
+    }
+}
+
+impl TryFrom<String> for SolanaPubkey {
+    type Error = RelayerError;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<SolanaPubkey> for String {
+    fn from(pubkey: SolanaPubkey) -> Self {
+        pubkey.to_string()
+    }
+}
 
 // Event detected by the event generator
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(into = "RelayEventV1", try_from = "RelayEventV1")]
 pub struct RelayEvent {
     pub source_chain: ChainConfig,
-    pub source_resolver_address: String,
+    pub source_resolver_address: Address,
     pub destination_chain: ChainConfig,
-    pub dest_dapp_address: String,
+    pub dest_dapp_address: Address,
     pub exec_payload: Bytes,
     pub nonce: u64,
     pub meta: EventMeta,
+    /// Unix time in seconds the event was detected, 0 if unknown
+    pub detected_at: u64,
+    /// Trace of the event's lifecycle, continued by every stage that handles it
+    pub trace_context: TraceContext,
+    /// Priority of the pair the event was detected for, higher goes first
+    pub priority: u8,
 }
 
-#[derive(Debug, Clone)]
+impl RelayEvent {
+    /// Identifier of the event, the same in every log line, stored record and
+    /// admin API response about it
+    ///
+    /// Hashes the source chain, transaction, log index and nonce, so every
+    /// instance derives the same ID for the same event.
+    pub fn id(&self) -> EventId {
+        let encoded = abi::encode(&[
+            Token::Uint(self.meta.chain_id.into()),
+            Token::FixedBytes(self.meta.tx_hash.unwrap_or_default().as_bytes().to_vec()),
+            Token::Uint(self.meta.log_index.into()),
+            Token::Uint(self.nonce.into()),
+        ]);
+        EventId(H256(keccak256(encoded)))
+    }
+
+    /// Identifier of the relay pair the event was detected for
+    pub fn pair_id(&self) -> String {
+        pair_id(
+            self.source_chain.chain_id,
+            &self.source_resolver_address,
+            self.destination_chain.chain_id,
+            &self.dest_dapp_address,
+        )
+    }
+}
+
+/// Stable identifier of a relay event, from [`RelayEvent::id`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct EventId(H256);
+
+impl std::fmt::Display for EventId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Full hex rather than H256's abbreviated form
+        write!(f, "{:?}", self.0)
+    }
+}
+
+impl std::str::FromStr for EventId {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        s.parse()
+            .map(Self)
+            .map_err(|_| anyhow::anyhow!("Invalid event id {:?}, expected 32 bytes of hex", s))
+    }
+}
+
+// Location of the source log an event was emitted in
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(into = "EventMetaV1", try_from = "EventMetaV1")]
 pub struct EventMeta {
+    pub chain_id: ChainId,
     pub tx_hash: Option<H256>,
     pub block_number: u64,
     pub tx_index: u32,
     pub log_index: u32,
 }
 
+// Proof produced for a source log
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Proof {
+    pub data: Bytes,
+    pub metadata: ProofMetadata,
+}
+
+// What the proving backend reported about a proof, kept for debugging
+// failed on-chain verification
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ProofMetadata {
+    /// Height of the proving chain the proof was generated against
+    pub proven_height: Option<u64>,
+    pub receipt_root: Option<H256>,
+    pub format_version: Option<String>,
+}
+
 // Proof request sent to the proof fetcher
 #[derive(Debug, Clone)]
 pub struct ProofRequest {
     pub event: RelayEvent,
     pub tx_hash: H256,
-    pub destination_chain_id: u64,
-    pub dest_contract_address: String,
+    pub destination_chain_id: ChainId,
+    pub dest_contract_address: Address,
 }
 
 // Delivery request sent to the event deliverer
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(into = "DeliveryRequestV1", try_from = "DeliveryRequestV1")]
 pub struct DeliveryRequest {
-    pub destination_chain_id: u64,
-    pub destination_contract_address: String,
+    pub destination_chain_id: ChainId,
+    pub destination_contract_address: Address,
     pub event: RelayEvent,
-    pub proof: Bytes,
+    pub proof: Proof,
+}
+
+/// Result of one delivery attempt, published to outcome subscribers and kept
+/// in the delivery history
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeliveryOutcome {
+    pub proof_key: ProofKey,
+    /// Zero for attempts recorded before events had IDs
+    #[serde(default)]
+    pub event_id: EventId,
+    /// Relay pair the event belongs to, if it still matches a configured one
+    pub pair: Option<String>,
+    pub source_chain_id: ChainId,
+    pub dest_chain_id: ChainId,
+    pub nonce: u64,
+    /// 1 for the first attempt at delivering the event, counting up with retries
+    pub attempt: u32,
+    /// Unix time in seconds the attempt finished
+    pub attempted_at: u64,
+    /// Delivery transaction, when one was sent
+    pub tx_hash: Option<H256>,
+    /// Block the delivery transaction was mined in
+    pub block_number: Option<u64>,
+    /// Gas used by the transaction; shared by every event in a batch
+    pub gas_used: Option<U256>,
+    pub status: DeliveryStatus,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum DeliveryStatus {
+    Delivered,
+    /// Executed by someone else first; nothing left to do
+    AlreadyExecuted,
+    /// Abandoned after the pair's delivery deadline
+    Expired,
+    /// Failed `attempts` times so far and queued for another attempt
+    Retrying {
+        attempts: u32,
+    },
+    /// Given up on and moved to the dead letter queue
+    DeadLettered,
+}
+
+/// Why a delivery reverted on the destination chain
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RevertKind {
+    /// The message was already executed, by this relayer or another
+    AlreadyExecuted,
+    /// The destination prover did not accept the proof; retries resend the
+    /// same proof, so this is not retried
+    InvalidProof,
+    /// The destination dapp rejected the call itself
+    DappRejected,
+}
+
+impl std::fmt::Display for RevertKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let kind = match self {
+            RevertKind::AlreadyExecuted => "already executed",
+            RevertKind::InvalidProof => "invalid proof",
+            RevertKind::DappRejected => "dapp rejected",
+        };
+        f.write_str(kind)
+    }
 }
 
 // Define error types
@@ -46,16 +329,19 @@ pub struct DeliveryRequest {
 pub enum RelayerError {
     #[error("Failed to connect to RPC endpoint for chain {chain_id}: {source}")]
     RpcConnection {
-        chain_id: u64,
+        chain_id: ChainId,
         source: anyhow::Error,
     },
 
     #[error("Transaction failed on chain {chain_id}: {source}")]
     TransactionFailed {
-        chain_id: u64,
+        chain_id: ChainId,
         source: anyhow::Error,
     },
 
+    #[error("Failed to sign with the relayer wallet: {source}")]
+    Signing { source: anyhow::Error },
+
     #[error("Proof verification failed: {0}")]
     ProofVerification(String),
 
@@ -64,4 +350,189 @@ pub enum RelayerError {
 
     #[error("Resolver error: {0}")]
     ResolverError(String),
+
+    #[error("Invalid exec payload: {0}")]
+    InvalidPayload(String),
+
+    #[error("Proof API rejected {method} (code {code}): {message}")]
+    ProofRequestRejected {
+        method: String,
+        code: i64,
+        message: String,
+    },
+
+    #[error("Proof job {job_id} not ready after {attempts} polls")]
+    ProofTimeout { job_id: i64, attempts: u32 },
+
+    #[error("Proof API call {method} timed out or could not connect: {source}")]
+    ProofApiTimeout {
+        method: String,
+        source: anyhow::Error,
+    },
+
+    #[error("Proof API unavailable for {method} (code {code}): {message}")]
+    ProofApiUnavailable {
+        method: String,
+        code: i64,
+        message: String,
+    },
+
+    #[error("Delivery {tx_hash:?} still unmined after {attempts} broadcasts at the fee ceiling")]
+    DeliveryStuck { tx_hash: H256, attempts: u32 },
+
+    #[error("Delivery {tx_hash:?} reverted on chain {chain_id} ({kind}): {reason}")]
+    DeliveryReverted {
+        chain_id: ChainId,
+        tx_hash: H256,
+        kind: RevertKind,
+        reason: String,
+    },
+
+    #[error("Nonce {nonce} already executed on chain {chain_id}")]
+    AlreadyExecuted { chain_id: ChainId, nonce: u64 },
+
+    #[error("Delivery {tx_hash:?} was dropped from the chain by a reorg")]
+    DeliveryReorged { tx_hash: H256 },
+
+    #[error("Chain {chain_id} stalled at block {head} before {tx_hash:?} was confirmed")]
+    ConfirmationsStalled {
+        chain_id: ChainId,
+        tx_hash: H256,
+        head: u64,
+    },
+
+    #[error("Delivery of nonce {nonce} to chain {chain_id} passed its deadline")]
+    DeliveryExpired { chain_id: ChainId, nonce: u64 },
+
+    #[error("Nonce {nonce} of {pair} is being delivered by another instance")]
+    DeliveryClaimed { pair: String, nonce: u64 },
+
+    #[error("Signer balance on chain {chain_id} is below its threshold, deliveries halted")]
+    InsufficientBalance { chain_id: ChainId },
+
+    #[error("Delivery was cancelled by an operator in {tx_hash:?}")]
+    DeliveryCancelled { tx_hash: H256 },
+
+    #[error("Delivery simulation on chain {chain_id} reverted ({kind}): {reason}")]
+    DeliverySimulationFailed {
+        chain_id: ChainId,
+        kind: RevertKind,
+        reason: String,
+    },
+
+    #[error("Chain id {chain_id} does not fit in {target}")]
+    ChainIdOutOfRange {
+        chain_id: ChainId,
+        target: &'static str,
+    },
+
+    #[error("Invalid configuration: {0}")]
+    InvalidConfig(String),
+
+    #[error("Chain {0} is not configured")]
+    UnknownChain(ChainId),
+
+    #[error("Chain {0} is already configured")]
+    ChainExists(ChainId),
+
+    #[error("Chain {chain_id} is still used by relay pair {pair}")]
+    ChainInUse { chain_id: ChainId, pair: String },
+
+    #[error("Relay pair {0} is not configured")]
+    UnknownRelayPair(String),
+
+    #[error("Relay pair {0} is already configured")]
+    RelayPairExists(String),
+
+    #[error("No dead letter for {0}")]
+    UnknownDeadLetter(String),
+
+    #[error("Event {event_id} cannot move from {from} to {to}")]
+    InvalidTransition {
+        event_id: EventId,
+        from: &'static str,
+        to: &'static str,
+    },
+
+    #[error("No status recorded for event {0}")]
+    UnknownEvent(EventId),
+
+    #[error("Shard {shard} is out of range for {count} shards")]
+    InvalidShard { shard: u32, count: u32 },
+
+    #[error("No {kind} plugin is registered as {name}")]
+    UnknownPlugin { kind: &'static str, name: String },
+
+    #[error("Unsupported schema {schema}, expected {expected}")]
+    UnsupportedSchema {
+        schema: String,
+        expected: &'static str,
+    },
+
+    #[error("Vetoed by hook {hook} in {stage}: {reason}")]
+    Vetoed {
+        hook: String,
+        stage: &'static str,
+        reason: String,
+    },
+
+    #[error("Invalid CCIP-Read lookup: {0}")]
+    InvalidLookup(String),
+
+    #[error("No proof to serve {sender:?} for nonce {nonce} from chain {source_chain_id}")]
+    ProofUnavailable {
+        sender: Address,
+        source_chain_id: ChainId,
+        nonce: u64,
+    },
+
+    #[error("Webhook rejected the delivery ({status}): {message}")]
+    WebhookRejected { status: u16, message: String },
+
+    #[error("Webhook failed the delivery {attempts} times: {source}")]
+    WebhookUnavailable {
+        attempts: u32,
+        source: anyhow::Error,
+    },
+
+    #[error("Invalid event query: {0}")]
+    InvalidQuery(String),
+
+    #[error("Audit log chain broken at line {line}: {reason}")]
+    AuditChainBroken { line: u64, reason: String },
+}
+
+impl RelayerError {
+    /// Whether retrying the failed operation may succeed
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            RelayerError::RpcConnection { .. }
+                | RelayerError::ProofApiUnavailable { .. }
+                | RelayerError::ProofApiTimeout { .. }
+                | RelayerError::ProofTimeout { .. }
+                | RelayerError::DeliveryStuck { .. }
+                | RelayerError::DeliveryReorged { .. }
+                | RelayerError::ConfirmationsStalled { .. }
+                | RelayerError::DeliveryClaimed { .. }
+                | RelayerError::InsufficientBalance { .. }
+                | RelayerError::WebhookUnavailable { .. }
+        )
+    }
+
+    /// Whether the failure shows the event no longer needs delivering
+    pub fn is_already_delivered(&self) -> bool {
+        matches!(
+            self,
+            RelayerError::AlreadyExecuted { .. }
+                | RelayerError::DeliveryReverted {
+                    kind: RevertKind::AlreadyExecuted,
+                    ..
+                }
+                | RelayerError::DeliverySimulationFailed {
+                    kind: RevertKind::AlreadyExecuted,
+                    ..
+                }
+        )
+    }
 }
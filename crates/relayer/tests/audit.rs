@@ -0,0 +1,123 @@
+use relayer::testkit::{self, TestPipeline};
+use relayer::{
+    verify_audit_log, AuditAction, AuditConfig, AuditEntry, DeliveryStatus, RelayerError,
+};
+use std::{fs, path::Path, time::Duration};
+use tokio::sync::Mutex;
+
+// A relayer audits to the log of the last one created in the process, so
+// the pipelines here take turns
+static PIPELINES: Mutex<()> = Mutex::const_new(());
+
+// Relay the events with `nonces` through a pipeline auditing to `audit.log`
+// in `state_dir`
+async fn relay(state_dir: &Path, nonces: &[u64]) {
+    let config = testkit::config(state_dir)
+        .audit(AuditConfig::default())
+        .build()
+        .unwrap();
+    let mut pipeline = TestPipeline::start(config).await.unwrap();
+    for nonce in nonces {
+        let event = testkit::event(*nonce);
+        pipeline.emit(event.clone()).await.unwrap();
+        pipeline
+            .expect_delivered(&event, Duration::from_secs(10))
+            .await
+            .unwrap();
+    }
+    pipeline.shutdown().await.unwrap();
+}
+
+fn entries(path: &Path) -> Vec<AuditEntry> {
+    fs::read_to_string(path)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect()
+}
+
+fn broken_at(path: &Path) -> u64 {
+    let error = verify_audit_log(path).unwrap_err();
+    match error.downcast_ref::<RelayerError>() {
+        Some(RelayerError::AuditChainBroken { line, .. }) => *line,
+        _ => panic!("unexpected error {:#}", error),
+    }
+}
+
+#[tokio::test]
+async fn audits_every_step_in_one_chain_across_restarts() {
+    let _turn = PIPELINES.lock().await;
+    let state = tempfile::tempdir().unwrap();
+    relay(state.path(), &[1, 2]).await;
+    relay(state.path(), &[3]).await;
+
+    let path = state.path().join("audit.log");
+    let entries = entries(&path);
+    let head = verify_audit_log(&path).unwrap();
+    assert_eq!(head.entries, entries.len() as u64);
+    assert_eq!(head.hash, entries.last().unwrap().hash);
+
+    for nonce in 1..=3 {
+        let event = testkit::event(nonce);
+        let id = event.id();
+        let steps: Vec<&AuditAction> = entries
+            .iter()
+            .map(|entry| &entry.action)
+            .filter(|action| match action {
+                AuditAction::Triggered { event_id, .. }
+                | AuditAction::ProofRequested { event_id, .. }
+                | AuditAction::Proved { event_id, .. }
+                | AuditAction::ProofFailed { event_id, .. }
+                | AuditAction::Delivery { event_id, .. } => *event_id == id,
+            })
+            .collect();
+        assert!(
+            matches!(
+                steps.as_slice(),
+                [
+                    AuditAction::Triggered { tx_hash, nonce: triggered, .. },
+                    AuditAction::ProofRequested { .. },
+                    AuditAction::Proved { .. },
+                    AuditAction::Delivery {
+                        status: DeliveryStatus::Delivered,
+                        attempt: 1,
+                        ..
+                    },
+                ] if *tx_hash == event.meta.tx_hash && *triggered == nonce
+            ),
+            "unexpected audit trail for nonce {}: {:?}",
+            nonce,
+            steps
+        );
+    }
+}
+
+#[tokio::test]
+async fn edited_or_removed_entries_break_the_chain() {
+    let _turn = PIPELINES.lock().await;
+    let state = tempfile::tempdir().unwrap();
+    relay(state.path(), &[1]).await;
+    let path = state.path().join("audit.log");
+    let lines: Vec<String> = fs::read_to_string(&path)
+        .unwrap()
+        .lines()
+        .map(String::from)
+        .collect();
+    assert!(lines.len() >= 4, "too few entries: {:?}", lines);
+
+    let tampered = state.path().join("tampered.log");
+    let mut edited = lines.clone();
+    edited[1] = edited[1].replacen("\"recorded_at\":", "\"recorded_at\":1", 1);
+    fs::write(&tampered, edited.join("\n") + "\n").unwrap();
+    assert_eq!(broken_at(&tampered), 2);
+
+    let mut removed = lines.clone();
+    removed.remove(2);
+    fs::write(&tampered, removed.join("\n") + "\n").unwrap();
+    assert_eq!(broken_at(&tampered), 3);
+
+    let mut reordered = lines;
+    reordered.swap(0, 1);
+    fs::write(&tampered, reordered.join("\n") + "\n").unwrap();
+    assert_eq!(broken_at(&tampered), 1);
+}
@@ -0,0 +1,156 @@
+use ethers::core::types::{Address, Bytes};
+use relayer::testkit::{self, EventBuilder, TestPipeline};
+use relayer::{
+    CcipGateway, ChainId, DeliveryMode, EventStatus, ForwarderConfig, ProofKey, RelayPair,
+    RelayerConfig, RelayerError,
+};
+use reqwest::StatusCode;
+use serde_json::{json, Value};
+use std::{
+    net::SocketAddr,
+    path::Path,
+    time::{Duration, Instant},
+};
+
+fn pull_pair() -> RelayPair {
+    RelayPair {
+        delivery_mode: DeliveryMode::CcipRead,
+        ..testkit::relay_pair()
+    }
+}
+
+// A pair pushing deliveries as usual, to a dapp of its own
+fn push_pair() -> RelayPair {
+    RelayPair {
+        dest_dapp_address: Address::from_low_u64_be(0xd0),
+        ..testkit::relay_pair()
+    }
+}
+
+fn config(state_dir: &Path, addr: SocketAddr) -> RelayerConfig {
+    let mut config = testkit::config(state_dir)
+        .relay_pair(push_pair())
+        .http_addr(addr)
+        .build()
+        .unwrap();
+    config.relay_pairs[0] = pull_pair();
+    config
+}
+
+fn lookup_url(addr: SocketAddr, sender: Address, nonce: u64) -> String {
+    let data = CcipGateway::call_data(ChainId::new(testkit::SOURCE_CHAIN_ID), nonce);
+    format!("http://{}/ccip/{:?}/{}.json", addr, sender, data)
+}
+
+// Ask the gateway until it answers `expected`, as the server and the
+// proof's publication may both trail the event's status
+async fn get(url: &str, expected: StatusCode) -> Value {
+    let deadline = Instant::now() + Duration::from_secs(10);
+    loop {
+        if let Ok(response) = reqwest::get(url).await {
+            if response.status() == expected {
+                let origin = &response.headers()["access-control-allow-origin"];
+                assert_eq!(origin, "*");
+                return response.json().await.unwrap();
+            }
+        }
+        assert!(
+            Instant::now() < deadline,
+            "{} never answered {}",
+            url,
+            expected
+        );
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+}
+
+#[tokio::test]
+async fn serves_proofs_of_pull_pairs_instead_of_delivering_them() {
+    let state = tempfile::tempdir().unwrap();
+    let addr = testkit::free_addr();
+    let pipeline = TestPipeline::start(config(state.path(), addr))
+        .await
+        .unwrap();
+    let pair = pull_pair();
+    let event = EventBuilder::new(&pair).nonce(5).build();
+
+    pipeline.emit(event.clone()).await.unwrap();
+    pipeline
+        .expect_status(&event, EventStatus::Proved, Duration::from_secs(10))
+        .await
+        .unwrap();
+    let answer = get(&lookup_url(addr, pair.dest_dapp_address, 5), StatusCode::OK).await;
+
+    let stored = pipeline
+        .store()
+        .proof(&ProofKey::from_meta(&event.meta))
+        .unwrap()
+        .and_then(|record| record.proof)
+        .unwrap();
+    assert_eq!(answer, json!({ "data": stored }));
+    let posted: Value = reqwest::Client::new()
+        .post(format!("http://{}/ccip", addr))
+        .json(&json!({
+            "sender": pair.dest_dapp_address,
+            "data": CcipGateway::call_data(pair.source_chain_id, 5),
+        }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(posted, answer);
+
+    assert!(pipeline.sink().requests().is_empty());
+    assert!(pipeline.store().pending_events().unwrap().is_empty());
+    pipeline.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn refuses_lookups_it_has_no_proof_for() {
+    let state = tempfile::tempdir().unwrap();
+    let addr = testkit::free_addr();
+    let mut pipeline = TestPipeline::start(config(state.path(), addr))
+        .await
+        .unwrap();
+    let pushed = EventBuilder::new(&push_pair()).nonce(1).build();
+    pipeline.emit(pushed.clone()).await.unwrap();
+    pipeline
+        .expect_delivered(&pushed, Duration::from_secs(10))
+        .await
+        .unwrap();
+
+    let dapp = pull_pair().dest_dapp_address;
+    let unknown = get(&lookup_url(addr, dapp, 9), StatusCode::NOT_FOUND).await;
+    assert!(unknown["message"].as_str().unwrap().contains("nonce 9"));
+    // Pushed events are the deliverer's, not the gateway's
+    get(
+        &lookup_url(addr, push_pair().dest_dapp_address, 1),
+        StatusCode::NOT_FOUND,
+    )
+    .await;
+    let malformed = format!("http://{}/ccip/{:?}/0x1234.json", addr, dapp);
+    get(&malformed, StatusCode::BAD_REQUEST).await;
+    pipeline.shutdown().await.unwrap();
+}
+
+#[test]
+fn pull_pairs_cannot_use_a_forwarder() {
+    let pair = RelayPair {
+        forwarder: Some(ForwarderConfig::new(Address::from_low_u64_be(0xf0))),
+        ..pull_pair()
+    };
+    let state = tempfile::tempdir().unwrap();
+
+    let error = testkit::config(state.path())
+        .relay_pair(pair)
+        .build()
+        .unwrap_err();
+
+    assert!(matches!(error, RelayerError::InvalidConfig(_)));
+    assert_eq!(
+        CcipGateway::call_data(ChainId::new(10), 7),
+        Bytes::from([[0; 31].as_slice(), &[10], &[0; 31], &[7]].concat())
+    );
+}
@@ -0,0 +1,73 @@
+use relayer::testkit::{self, ManualClock, TestPipeline};
+use relayer::{Clock, EventStatus, FailedDelivery, StateStore};
+use std::{sync::Arc, time::Duration};
+
+const TIMEOUT: Duration = Duration::from_secs(20);
+// Long enough for a retry that was wrongly let through to be seen
+const SETTLE: Duration = Duration::from_millis(300);
+
+async fn queued_retry(store: &Arc<dyn StateStore>) -> FailedDelivery {
+    tokio::time::timeout(TIMEOUT, async {
+        loop {
+            if let Some(failed) = store.retries().unwrap().pop() {
+                return failed;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    })
+    .await
+    .expect("no delivery was queued for retry")
+}
+
+#[tokio::test]
+async fn sleeps_until_advanced_past_their_deadline() {
+    let clock = Arc::new(ManualClock::new());
+    let started = clock.now();
+    let sleep = tokio::spawn({
+        let clock = clock.clone();
+        async move { clock.sleep(Duration::from_secs(60)).await }
+    });
+    // Let the sleep start before moving the clock
+    tokio::time::sleep(SETTLE).await;
+
+    clock.advance(Duration::from_secs(59));
+    tokio::time::sleep(SETTLE).await;
+    assert!(!sleep.is_finished());
+
+    clock.advance(Duration::from_secs(1));
+    tokio::time::timeout(TIMEOUT, sleep).await.unwrap().unwrap();
+    assert_eq!(clock.now() - started, Duration::from_secs(60));
+    assert_eq!(clock.elapsed(), Duration::from_secs(60));
+}
+
+#[tokio::test]
+async fn retries_a_failed_delivery_once_the_clock_moves() {
+    let state = tempfile::tempdir().unwrap();
+    let clock = Arc::new(ManualClock::new());
+    let config = testkit::config(state.path()).build().unwrap();
+    let mut pipeline = TestPipeline::start_with(config, |builder| builder.clock(clock.clone()))
+        .await
+        .unwrap();
+    pipeline.sink().fail_next(1);
+    let event = testkit::event(11);
+
+    pipeline.emit(event.clone()).await.unwrap();
+
+    let failed = queued_retry(pipeline.store()).await;
+    assert_eq!(failed.attempts, 1);
+    assert_eq!(failed.next_attempt_at, clock.unix_time() + 1);
+    // Due by now, but the retry queue is only checked every five seconds
+    clock.advance(Duration::from_secs(4));
+    tokio::time::sleep(SETTLE).await;
+    assert!(pipeline.sink().requests().is_empty());
+
+    clock.advance(Duration::from_secs(1));
+    let outcome = pipeline.expect_delivered(&event, TIMEOUT).await.unwrap();
+    assert_eq!(outcome.attempt, 2);
+    assert_eq!(outcome.attempted_at, clock.unix_time());
+    pipeline
+        .expect_status(&event, EventStatus::Delivered, TIMEOUT)
+        .await
+        .unwrap();
+    pipeline.shutdown().await.unwrap();
+}
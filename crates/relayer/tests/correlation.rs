@@ -0,0 +1,54 @@
+use relayer::testkit::{self, LogCapture, TestPipeline};
+use serde_json::Value;
+use std::time::Duration;
+
+fn message(line: &Value) -> &str {
+    line["message"].as_str().unwrap_or_default()
+}
+
+#[tokio::test]
+async fn every_line_about_an_event_carries_its_id() {
+    let logs = LogCapture::install().unwrap();
+    let state = tempfile::tempdir().unwrap();
+    let config = testkit::config(state.path()).build().unwrap();
+    let mut pipeline = TestPipeline::start(config).await.unwrap();
+    let events = [testkit::event(1), testkit::event(2)];
+    for event in &events {
+        pipeline.emit(event.clone()).await.unwrap();
+    }
+    for event in &events {
+        pipeline
+            .expect_delivered(event, Duration::from_secs(10))
+            .await
+            .unwrap();
+    }
+    pipeline.shutdown().await.unwrap();
+
+    let lines = logs.lines();
+    let ids = events.map(|event| event.id().to_string());
+    for id in &ids {
+        // What `grep <event-id>` on the log turns up
+        let journey: Vec<&Value> = lines
+            .iter()
+            .filter(|line| line.to_string().contains(id.as_str()))
+            .collect();
+        let messages: Vec<&str> = journey.iter().map(|line| message(line)).collect();
+        // Logged by the proof provider, in a span of its own below the event's
+        assert!(
+            messages.contains(&"Generated mock proof"),
+            "{} has no proof in {:?}",
+            id,
+            messages
+        );
+        assert!(
+            messages.contains(&"Event delivered to sink"),
+            "{} has no delivery in {:?}",
+            id,
+            messages
+        );
+    }
+    assert!(lines.iter().all(|line| {
+        let line = line.to_string();
+        !ids.iter().all(|id| line.contains(id.as_str()))
+    }));
+}
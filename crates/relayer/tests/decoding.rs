@@ -0,0 +1,152 @@
+use base64::{engine::general_purpose, Engine};
+use ethers::core::types::{Address, Bytes, Log, TransactionReceipt, H256, U256, U64};
+use ethers::utils::{hex, keccak256};
+use proptest::prelude::*;
+use relayer::{
+    decode_proof, exec_request_meta, find_exec_request, function_selector, ChainId,
+    MockProofConfig, MockProofProvider, PolymerApiConfig, PolymerProofProvider, Proof,
+    ProofEncoding, ProofMetadata, ProofProvider, StateStore,
+};
+use std::sync::Arc;
+
+const RESOLVER: u64 = 0x5e50;
+
+fn exec_requested_topic() -> H256 {
+    H256::from(keccak256(relayer::CROSS_CHAIN_EXEC_REQUESTED))
+}
+
+fn proof(data: Vec<u8>) -> Proof {
+    Proof {
+        data: Bytes::from(data),
+        metadata: ProofMetadata::default(),
+    }
+}
+
+// Logs from the resolver or another contract, carrying the request topic,
+// some other topic or none at all, at any log index
+fn log() -> impl Strategy<Value = Log> {
+    let address = prop_oneof![Just(RESOLVER), Just(0xbeef_u64)];
+    let topics = prop_oneof![
+        Just(vec![exec_requested_topic()]),
+        any::<[u8; 32]>().prop_map(|topic| vec![H256::from(topic)]),
+        Just(Vec::new()),
+    ];
+    let log_index = prop_oneof![
+        Just(None),
+        any::<u32>().prop_map(|index| Some(U256::from(index))),
+        any::<[u8; 32]>().prop_map(|index| Some(U256::from_big_endian(&index))),
+    ];
+    (address, topics, log_index).prop_map(|(address, topics, log_index)| Log {
+        address: Address::from_low_u64_be(address),
+        topics,
+        log_index,
+        ..Default::default()
+    })
+}
+
+fn receipt() -> impl Strategy<Value = TransactionReceipt> {
+    let block_number = proptest::option::of(any::<u64>());
+    (
+        prop::collection::vec(log(), 0..6),
+        any::<u64>(),
+        block_number,
+    )
+        .prop_map(
+            |(logs, transaction_index, block_number)| TransactionReceipt {
+                logs,
+                transaction_index: U64::from(transaction_index),
+                block_number: block_number.map(U64::from),
+                ..Default::default()
+            },
+        )
+}
+
+proptest! {
+    #[test]
+    fn selects_the_first_four_payload_bytes(payload in prop::collection::vec(any::<u8>(), 0..64)) {
+        match function_selector(&payload) {
+            Ok(selector) => prop_assert_eq!(&selector[..], &payload[..4]),
+            Err(e) => {
+                prop_assert!(payload.len() < 4);
+                prop_assert!(!e.is_retryable());
+            }
+        }
+    }
+
+    #[test]
+    fn finds_only_the_resolvers_request(logs in prop::collection::vec(log(), 0..8)) {
+        let resolver = Address::from_low_u64_be(RESOLVER);
+        let expected = logs.iter().position(|log| {
+            log.address == resolver && log.topics.first() == Some(&exec_requested_topic())
+        });
+
+        let found = find_exec_request(&logs, resolver);
+
+        prop_assert_eq!(found, expected.map(|index| &logs[index]));
+    }
+
+    #[test]
+    fn locates_requests_in_any_receipt(receipt in receipt()) {
+        let resolver = Address::from_low_u64_be(RESOLVER);
+        let chain_id = ChainId::new(11155420);
+
+        let Ok(meta) = exec_request_meta(chain_id, &receipt, resolver) else {
+            return Ok(());
+        };
+
+        let log = find_exec_request(&receipt.logs, resolver).unwrap();
+        prop_assert_eq!(meta.chain_id, chain_id);
+        prop_assert_eq!(Some(U64::from(meta.block_number)), receipt.block_number);
+        prop_assert_eq!(U64::from(meta.tx_index), receipt.transaction_index);
+        prop_assert_eq!(Some(U256::from(meta.log_index)), log.log_index);
+    }
+
+    #[test]
+    fn decodes_encoded_proofs(data in prop::collection::vec(any::<u8>(), 0..256)) {
+        let hex = hex::encode(&data);
+        let prefixed = format!("0x{}", hex);
+        let base64 = general_purpose::STANDARD.encode(&data);
+
+        prop_assert_eq!(&decode_proof(&prefixed, ProofEncoding::Auto).unwrap()[..], &data[..]);
+        prop_assert_eq!(&decode_proof(&prefixed, ProofEncoding::Hex).unwrap()[..], &data[..]);
+        prop_assert_eq!(&decode_proof(&hex, ProofEncoding::Hex).unwrap()[..], &data[..]);
+        prop_assert_eq!(&decode_proof(&base64, ProofEncoding::Auto).unwrap()[..], &data[..]);
+        prop_assert_eq!(&decode_proof(&base64, ProofEncoding::Base64).unwrap()[..], &data[..]);
+    }
+
+    #[test]
+    fn survives_malformed_proof_strings(proof in "(0x)?[0-9a-zA-Z+/=_-]{0,64}|\\PC{0,32}") {
+        for encoding in [ProofEncoding::Auto, ProofEncoding::Base64, ProofEncoding::Hex] {
+            let _ = decode_proof(&proof, encoding);
+        }
+    }
+
+    #[test]
+    fn inspects_any_polymer_proof(data in prop::collection::vec(any::<u8>(), 0..200)) {
+        let state = tempfile::tempdir().unwrap();
+        let store: Arc<dyn StateStore> =
+            Arc::new(relayer::FileStateStore::open(state.path()).unwrap());
+        let provider = PolymerProofProvider::new(PolymerApiConfig::default(), store).unwrap();
+
+        let identified = provider.inspect(&proof(data.clone()));
+
+        prop_assert_eq!(identified.is_ok(), data.len() >= 120);
+        if let Ok(Some(log)) = identified {
+            prop_assert_eq!(log.chain_id.as_u64(), u64::from(u32::from_be_bytes(
+                data[97..101].try_into().unwrap()
+            )));
+            prop_assert_eq!(log.log_index, u32::from(data[119]));
+        }
+    }
+
+    #[test]
+    fn inspects_any_mock_proof(data in prop::collection::vec(any::<u8>(), 0..160)) {
+        let provider = MockProofProvider::new(MockProofConfig::default());
+
+        let identified = provider.inspect(&proof(data.clone()));
+
+        if data.len() < 128 {
+            prop_assert!(identified.is_err());
+        }
+    }
+}
@@ -0,0 +1,81 @@
+use ethers::{
+    abi::{self, ParamType, Token},
+    contract::Contract,
+    core::types::{Bytes, U256},
+    providers::{Http, Middleware, Provider},
+    utils::keccak256,
+};
+use relayer::{Devnet, DevnetChain, DevnetConfig};
+use std::sync::Arc;
+
+fn devnet_config(state: &tempfile::TempDir) -> DevnetConfig {
+    DevnetConfig {
+        chains: vec![
+            DevnetChain {
+                name: "devnet-a".to_string(),
+                chain_id: 31337,
+                port: 18545,
+            },
+            DevnetChain {
+                name: "devnet-b".to_string(),
+                chain_id: 31338,
+                port: 18546,
+            },
+        ],
+        state_dir: state.path().to_path_buf(),
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn reports_a_missing_anvil() {
+    let state = tempfile::tempdir().unwrap();
+    let config = DevnetConfig {
+        anvil: state.path().join("anvil"),
+        ..devnet_config(&state)
+    };
+
+    let error = Devnet::up(config).await.err().unwrap();
+
+    assert!(format!("{:#}", error).contains("is Foundry installed?"));
+}
+
+#[tokio::test]
+#[ignore = "needs anvil on PATH"]
+async fn sample_resolver_requests_executions() {
+    let state = tempfile::tempdir().unwrap();
+    let devnet = Devnet::up(devnet_config(&state)).await.unwrap();
+    let config = devnet.relayer_config().unwrap();
+    assert_eq!(config.relay_pairs.len(), 2);
+    assert!(devnet
+        .write_config(&config)
+        .unwrap()
+        .ends_with("devnet.json"));
+
+    let pair = &devnet.relay_pairs()[0];
+    let source = &devnet.chains()[0];
+    let provider = Arc::new(Provider::<Http>::try_from(&source.rpc_url).unwrap());
+    let resolver = Contract::new(
+        pair.source_resolver_address,
+        abi::parse_abi(&["function crossChainChecker(uint32) view returns (bool, bytes, uint256)"])
+            .unwrap(),
+        provider.clone(),
+    );
+    let (can_exec, payload, nonce): (bool, Bytes, U256) = resolver
+        .method("crossChainChecker", 31338u32)
+        .unwrap()
+        .call()
+        .await
+        .unwrap();
+    assert!(can_exec);
+    assert_eq!(nonce, U256::one());
+    assert_eq!(&payload[..4], &keccak256("execute(uint256)")[..4]);
+    assert_eq!(
+        abi::decode(&[ParamType::Uint(256)], &payload[4..]).unwrap(),
+        [Token::Uint(nonce)]
+    );
+
+    let dest = Provider::<Http>::try_from(&devnet.chains()[1].rpc_url).unwrap();
+    let code = dest.get_code(pair.dest_dapp_address, None).await.unwrap();
+    assert!(!code.is_empty());
+}
@@ -0,0 +1,149 @@
+use relayer::testkit::{self, TestPipeline};
+use relayer::{
+    AdminClient, EventQuery, EventRecord, EventStatus, FileStateStore, SqliteStateStore, StateStore,
+};
+use reqwest::StatusCode;
+use std::time::Duration;
+
+// Nonces 1 to 8 of two pairs, odd ones failed, each entering its status a
+// second after the one before
+fn fill(store: &dyn StateStore) -> String {
+    let pair = testkit::event(1).pair_id();
+    for nonce in 1..=8 {
+        let status = if nonce % 2 == 1 {
+            EventStatus::Failed {
+                reason: "reverted".to_string(),
+            }
+        } else {
+            EventStatus::Delivered
+        };
+        let mut record = EventRecord::new(&testkit::event(nonce), status);
+        record.updated_at = 1_000 + nonce;
+        if nonce > 6 {
+            record.pair = "other".to_string();
+        }
+        store.transition_event(&record).unwrap();
+    }
+    pair
+}
+
+fn nonces(store: &dyn StateStore, query: EventQuery) -> Vec<u64> {
+    store
+        .query_events(&query)
+        .unwrap()
+        .iter()
+        .map(|record| record.nonce)
+        .collect()
+}
+
+#[test]
+fn both_stores_answer_queries_alike() {
+    let file_dir = tempfile::tempdir().unwrap();
+    let sqlite_dir = tempfile::tempdir().unwrap();
+    let stores: [Box<dyn StateStore>; 2] = [
+        Box::new(FileStateStore::open(file_dir.path()).unwrap()),
+        Box::new(SqliteStateStore::open(sqlite_dir.path()).unwrap()),
+    ];
+
+    for store in &stores {
+        let pair = fill(store.as_ref());
+        let query = |query: EventQuery| nonces(store.as_ref(), query);
+
+        assert_eq!(query(EventQuery::default()), [1, 2, 3, 4, 5, 6, 7, 8]);
+        assert_eq!(
+            query(EventQuery {
+                pair: Some(pair.to_uppercase()),
+                status: Some("FAILED".to_string()),
+                ..Default::default()
+            }),
+            [1, 3, 5]
+        );
+        assert_eq!(
+            query(EventQuery {
+                from_nonce: Some(3),
+                to_nonce: Some(5),
+                ..Default::default()
+            }),
+            [3, 4, 5]
+        );
+        assert_eq!(
+            query(EventQuery {
+                since: Some(1_006),
+                until: Some(1_007),
+                ..Default::default()
+            }),
+            [6, 7]
+        );
+        assert_eq!(
+            query(EventQuery {
+                status: Some("delivered".to_string()),
+                limit: Some(2),
+                offset: Some(1),
+                ..Default::default()
+            }),
+            [4, 6]
+        );
+    }
+}
+
+#[test]
+fn rejects_queries_that_cannot_match() {
+    for query in [
+        EventQuery {
+            status: Some("done".to_string()),
+            ..Default::default()
+        },
+        EventQuery {
+            from_nonce: Some(5),
+            to_nonce: Some(4),
+            ..Default::default()
+        },
+        EventQuery {
+            since: Some(2_000),
+            until: Some(1_000),
+            ..Default::default()
+        },
+    ] {
+        assert!(query.validate().is_err(), "{:?} was accepted", query);
+    }
+    assert!(EventQuery::default().validate().is_ok());
+}
+
+#[tokio::test]
+async fn serves_event_queries_over_http() {
+    let state = tempfile::tempdir().unwrap();
+    let addr = testkit::free_addr();
+    let config = testkit::config(state.path())
+        .http_addr(addr)
+        .build()
+        .unwrap();
+    let mut pipeline = TestPipeline::start(config).await.unwrap();
+    for nonce in 1..=3 {
+        let event = testkit::event(nonce);
+        pipeline.emit(event.clone()).await.unwrap();
+        pipeline
+            .expect_delivered(&event, Duration::from_secs(10))
+            .await
+            .unwrap();
+    }
+
+    let client = AdminClient::new(format!("http://{}", addr), None);
+    let records = client
+        .events(&EventQuery {
+            status: Some("delivered".to_string()),
+            from_nonce: Some(2),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    let mut nonces: Vec<u64> = records.iter().map(|record| record.nonce).collect();
+    nonces.sort();
+    assert_eq!(nonces, [2, 3]);
+
+    let response = reqwest::get(format!("http://{}/events?status=done", addr))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    pipeline.shutdown().await.unwrap();
+}
@@ -0,0 +1,140 @@
+use relayer::testkit::{self, MockKafka, MockNats, StreamedMessage, TestPipeline};
+use relayer::{EventStreamConfig, RelayEvent, RelayerError, StreamBroker};
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+// A relayer publishes to the stream of the last one created in the process,
+// so the pipelines here take turns
+static PIPELINES: Mutex<()> = Mutex::const_new(());
+
+fn stream(broker: StreamBroker) -> EventStreamConfig {
+    EventStreamConfig {
+        broker,
+        timeout_ms: 1_000,
+        reconnect_backoff_ms: 50,
+        ..Default::default()
+    }
+}
+
+// Relay `event` through a pipeline publishing to `broker`, returning what
+// the broker received about it once it heard of the delivery
+async fn relay(
+    broker: StreamBroker,
+    event: &RelayEvent,
+    received: impl Fn() -> Vec<StreamedMessage>,
+) -> Vec<StreamedMessage> {
+    let _turn = PIPELINES.lock().await;
+    let state = tempfile::tempdir().unwrap();
+    let config = testkit::config(state.path())
+        .event_stream(stream(broker))
+        .build()
+        .unwrap();
+    let mut pipeline = TestPipeline::start(config).await.unwrap();
+
+    pipeline.emit(event.clone()).await.unwrap();
+    pipeline
+        .expect_delivered(event, Duration::from_secs(10))
+        .await
+        .unwrap();
+    let deadline = Instant::now() + Duration::from_secs(10);
+    let messages = loop {
+        let messages: Vec<StreamedMessage> = received()
+            .into_iter()
+            .filter(|message| message.record().unwrap().event_id == event.id())
+            .collect();
+        let delivered = messages
+            .last()
+            .is_some_and(|message| message.record().unwrap().status.name() == "delivered");
+        if delivered {
+            break messages;
+        }
+        assert!(Instant::now() < deadline, "delivery never streamed");
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    };
+    pipeline.shutdown().await.unwrap();
+    messages
+}
+
+fn statuses(messages: &[StreamedMessage]) -> Vec<&'static str> {
+    messages
+        .iter()
+        .map(|message| message.record().unwrap().status.name())
+        .collect()
+}
+
+#[tokio::test]
+async fn streams_status_changes_to_nats() {
+    let nats = MockNats::start().await.unwrap();
+    let event = testkit::event(1);
+    let broker = StreamBroker::Nats {
+        url: nats.url(),
+        subject: "relayer.events".to_string(),
+        token: Some("stream-token".to_string()),
+    };
+
+    let messages = relay(broker, &event, || nats.messages()).await;
+
+    assert_eq!(
+        statuses(&messages),
+        ["detected", "proving", "proved", "delivering", "delivered"]
+    );
+    assert!(messages
+        .iter()
+        .all(|message| message.topic == "relayer.events"));
+    let record = messages[0].record().unwrap();
+    assert_eq!(record.pair, event.pair_id());
+    assert_eq!(record.nonce, 1);
+}
+
+#[tokio::test]
+async fn streams_status_changes_to_kafka_keyed_by_event() {
+    let kafka = MockKafka::start().await.unwrap();
+    let event = testkit::event(2);
+    let broker = StreamBroker::Kafka {
+        broker: kafka.broker(),
+        topic: "relayer-events".to_string(),
+        partition: 3,
+    };
+
+    let messages = relay(broker, &event, || kafka.messages()).await;
+
+    assert_eq!(
+        statuses(&messages),
+        ["detected", "proving", "proved", "delivering", "delivered"]
+    );
+    let event_id = event.id().to_string();
+    for message in &messages {
+        assert_eq!(message.topic, "relayer-events");
+        assert_eq!(message.partition, Some(3));
+        assert_eq!(message.key.as_deref(), Some(event_id.as_str()));
+    }
+}
+
+#[test]
+fn rejects_unusable_stream_settings() {
+    let state = tempfile::tempdir().unwrap();
+    let build =
+        |stream: EventStreamConfig| testkit::config(state.path()).event_stream(stream).build();
+    let nats = |url: &str, subject: &str| StreamBroker::Nats {
+        url: url.to_string(),
+        subject: subject.to_string(),
+        token: None,
+    };
+
+    assert!(build(EventStreamConfig::default()).is_ok());
+    for stream in [
+        EventStreamConfig {
+            buffer: 0,
+            ..Default::default()
+        },
+        self::stream(nats("tls://nats:4222", "relayer.events")),
+        self::stream(nats("nats://nats:4222", "relayer events")),
+        self::stream(StreamBroker::Kafka {
+            broker: "kafka:9092".to_string(),
+            topic: String::new(),
+            partition: 0,
+        }),
+    ] {
+        assert!(matches!(build(stream), Err(RelayerError::InvalidConfig(_))));
+    }
+}
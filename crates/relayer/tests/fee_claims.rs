@@ -0,0 +1,133 @@
+use ethers::{
+    abi::{self, Token},
+    core::types::{Address, U256},
+    signers::{LocalWallet, Signer},
+    utils::id,
+};
+use relayer::testkit::{self, MockEvmNode, TestPipeline, DEST_CHAIN_ID};
+use relayer::{
+    ChainConfig, ChainId, FeeClaimConfig, FeeContract, GasCostKind, RelayerError, StoreBackend,
+    DEVNET_PRIVATE_KEY,
+};
+use std::time::{Duration, Instant};
+
+fn contract(byte: u8, function: &str) -> FeeContract {
+    FeeContract {
+        chain_id: ChainId::new(DEST_CHAIN_ID),
+        address: Address::repeat_byte(byte),
+        function: function.to_string(),
+    }
+}
+
+async fn claims_fees_worth_claiming(backend: StoreBackend) {
+    let node = MockEvmNode::start(DEST_CHAIN_ID).await.unwrap();
+    let amount = |wei: u64| abi::encode(&[Token::Uint(U256::from(wei))]);
+    // Owes 5e15 wei, returns nothing it could be priced by, owes too little,
+    // and has nothing to claim at all
+    let owing = contract(0x01, "claimFees()");
+    let silent = contract(0x02, "withdraw()");
+    let dust = contract(0x03, "claimFees()");
+    let empty = contract(0x04, "withdraw()");
+    node.on_call(owing.address, "claimFees()", amount(5_000_000_000_000_000))
+        .on_call(silent.address, "withdraw()", Vec::new())
+        .on_call(dust.address, "claimFees()", amount(10));
+
+    let state = tempfile::tempdir().unwrap();
+    let config = testkit::config(state.path())
+        .store_backend(backend)
+        .chain(ChainConfig {
+            rpc_url: node.url(),
+            ..testkit::chain(DEST_CHAIN_ID, &format!("chain-{}", DEST_CHAIN_ID))
+        })
+        .fee_claims(FeeClaimConfig {
+            contracts: vec![owing.clone(), silent.clone(), dust.clone(), empty.clone()],
+            interval_ms: 50,
+            min_claim_wei: 1_000,
+        })
+        .build()
+        .unwrap();
+    let pipeline =
+        TestPipeline::start_with(config, |builder| builder.private_key(DEVNET_PRIVATE_KEY))
+            .await
+            .unwrap();
+
+    let deadline = Instant::now() + Duration::from_secs(10);
+    let claims = loop {
+        let claims = pipeline.store().fee_claims().unwrap();
+        let claimed =
+            |contract: &FeeContract| claims.iter().any(|c| c.contract == contract.address);
+        if claimed(&owing) && claimed(&silent) {
+            break claims;
+        }
+        assert!(
+            Instant::now() < deadline,
+            "fees were not claimed: {:?}",
+            claims
+        );
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    };
+    let claim_of = |contract: &FeeContract| {
+        claims
+            .iter()
+            .find(|claim| claim.contract == contract.address)
+            .unwrap()
+    };
+    assert_eq!(
+        claim_of(&owing).amount_wei,
+        Some(U256::from(5_000_000_000_000_000u64))
+    );
+    assert_eq!(claim_of(&silent).amount_wei, None);
+    assert!(claims.iter().all(|claim| claim.succeeded));
+
+    let wallet: LocalWallet = DEVNET_PRIVATE_KEY.parse().unwrap();
+    let sent = node.sent();
+    assert!(sent.iter().all(|tx| tx.from == wallet.address()));
+    assert!(sent
+        .iter()
+        .any(|tx| tx.to == Some(owing.address) && tx.data[..] == id("claimFees()")));
+    assert!(!sent
+        .iter()
+        .any(|tx| tx.to == Some(dust.address) || tx.to == Some(empty.address)));
+    assert!(pipeline
+        .store()
+        .gas_costs()
+        .unwrap()
+        .iter()
+        .any(|cost| cost.kind == GasCostKind::FeeClaim && cost.pair == "fee_claim"));
+    pipeline.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn claims_fees_worth_claiming_into_file_store() {
+    claims_fees_worth_claiming(StoreBackend::File).await;
+}
+
+#[tokio::test]
+async fn claims_fees_worth_claiming_into_sqlite_store() {
+    claims_fees_worth_claiming(StoreBackend::Sqlite).await;
+}
+
+#[test]
+fn rejects_claims_on_unknown_chains_or_taking_arguments() {
+    let state = tempfile::tempdir().unwrap();
+    let config = testkit::config(state.path())
+        .fee_claims(FeeClaimConfig {
+            contracts: vec![contract(0x01, "withdraw(uint256)")],
+            ..Default::default()
+        })
+        .build();
+    assert!(matches!(config, Err(RelayerError::InvalidConfig(_))));
+
+    let unknown = testkit::config(state.path())
+        .fee_claims(FeeClaimConfig {
+            contracts: vec![FeeContract {
+                chain_id: ChainId::new(999),
+                ..contract(0x01, "claimFees()")
+            }],
+            ..Default::default()
+        })
+        .build();
+    assert!(
+        matches!(unknown, Err(RelayerError::UnknownChain(chain)) if chain == ChainId::new(999))
+    );
+}
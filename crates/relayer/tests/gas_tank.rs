@@ -0,0 +1,177 @@
+use ethers::{
+    core::types::{Address, H256, U256},
+    signers::{LocalWallet, Signer},
+};
+use relayer::testkit::{self, MockEvmNode, MockWebhook, DEST_CHAIN_ID, SOURCE_CHAIN_ID};
+use relayer::{
+    AlertConfig, ChainConfig, ChainId, ControlClient, DeliveryConfig, FileStateStore, GasCostKind,
+    GasCostRecord, GasTankConfig, RelayerBuilder, RelayerError, StateStore, WebhookConfig,
+    WebhookKind, DEVNET_PRIVATE_KEY,
+};
+use std::{
+    sync::Arc,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+const ETH: u64 = 1_000_000_000_000_000_000;
+
+fn spent(byte: u8, from: Address, cost_wei: u64, secs_ago: u64) -> GasCostRecord {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    GasCostRecord {
+        tx_hash: H256::repeat_byte(byte),
+        chain_id: ChainId::new(DEST_CHAIN_ID),
+        pair: testkit::relay_pair().id(),
+        kind: GasCostKind::Delivery,
+        from,
+        gas_used: U256::from(21_000),
+        effective_gas_price: U256::from(cost_wei / 21_000),
+        l1_fee_wei: U256::zero(),
+        cost_wei: U256::from(cost_wei),
+        recorded_at: now - secs_ago,
+    }
+}
+
+#[tokio::test]
+async fn projects_each_wallets_runway_from_its_recent_spend() {
+    let wallet = DEVNET_PRIVATE_KEY.parse::<LocalWallet>().unwrap().address();
+    let source = MockEvmNode::start(SOURCE_CHAIN_ID).await.unwrap();
+    let dest = MockEvmNode::start(DEST_CHAIN_ID).await.unwrap();
+    source.set_balance(wallet, ETH);
+    dest.set_balance(wallet, ETH);
+
+    // A quarter ETH spent in the last day, a larger spend from before the
+    // window, and half an ETH from another wallet on the same chain
+    let state = tempfile::tempdir().unwrap();
+    let store = Arc::new(FileStateStore::open(state.path()).unwrap());
+    store
+        .save_gas_cost(&spent(1, wallet, ETH / 4, 3_600))
+        .unwrap();
+    store
+        .save_gas_cost(&spent(2, wallet, 10 * ETH, 2 * 86_400))
+        .unwrap();
+    store
+        .save_gas_cost(&spent(3, Address::repeat_byte(0x77), ETH / 2, 60))
+        .unwrap();
+
+    let alerts = MockWebhook::start().await.unwrap();
+    let addr = testkit::free_addr();
+    let socket = state.path().join("relayer.sock");
+    let chain = |chain_id, node: &MockEvmNode| ChainConfig {
+        rpc_url: node.url(),
+        ..testkit::chain(chain_id, &format!("chain-{}", chain_id))
+    };
+    let config = testkit::config(state.path())
+        .chain(chain(SOURCE_CHAIN_ID, &source))
+        .chain(chain(DEST_CHAIN_ID, &dest))
+        .delivery(DeliveryConfig {
+            balance_check_interval_ms: 50,
+            gas_tank: GasTankConfig {
+                min_runway_days: Some(7.0),
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .alerts(AlertConfig {
+            webhooks: vec![WebhookConfig {
+                url: alerts.url(),
+                kind: WebhookKind::Generic,
+            }],
+            ..Default::default()
+        })
+        .http_addr(addr)
+        .control_socket(&socket)
+        .shutdown_grace_period_ms(1_000)
+        .build()
+        .unwrap();
+    let relayer = RelayerBuilder::new(config)
+        .private_key(DEVNET_PRIVATE_KEY)
+        .without_chain_events()
+        .store(store)
+        .build()
+        .unwrap()
+        .run();
+
+    let client = ControlClient::new(&socket);
+    let deadline = Instant::now() + Duration::from_secs(10);
+    let tanks = loop {
+        if let Ok(status) = client.status().await {
+            if status.gas_tanks.len() == 2 {
+                break status.gas_tanks;
+            }
+        }
+        assert!(Instant::now() < deadline, "gas tanks were not checked");
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    };
+    let tank = |chain_id| {
+        tanks
+            .iter()
+            .find(|tank| tank.chain_id == ChainId::new(chain_id))
+            .unwrap()
+    };
+    let dest_tank = tank(DEST_CHAIN_ID);
+    assert_eq!(dest_tank.wallet, wallet);
+    assert_eq!(dest_tank.balance_wei, U256::from(ETH));
+    assert_eq!(dest_tank.spend_per_day_wei, U256::from(ETH / 4));
+    assert_eq!(dest_tank.chain_spend_per_day_wei, U256::from(3 * ETH / 4));
+    assert!(dest_tank
+        .runway_days
+        .is_some_and(|days| (days - 4.0).abs() < 1e-9));
+    // Nothing spent on the source chain, so its wallet never runs dry
+    assert_eq!(tank(SOURCE_CHAIN_ID).runway_days, None);
+
+    let metrics = reqwest::get(format!("http://{}/metrics", addr))
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    let dest_name = format!("chain-{}", DEST_CHAIN_ID);
+    assert!(metrics.contains(&format!(
+        "relayer_spend_rate_eth_per_day{{chain=\"{}\"}} 0.75\n",
+        dest_name
+    )));
+    assert!(metrics.contains(&format!(
+        "relayer_signer_runway_days{{chain=\"{}\",signer=\"{:?}\"}} 4\n",
+        dest_name, wallet
+    )));
+    let served: Vec<serde_json::Value> = reqwest::get(format!("http://{}/gas-tanks", addr))
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(served.len(), 2);
+
+    let deadline = Instant::now() + Duration::from_secs(10);
+    loop {
+        let short = alerts.posts().into_iter().any(|post| {
+            serde_json::from_slice::<serde_json::Value>(&post.body)
+                .is_ok_and(|alert| alert["kind"] == "low_runway")
+        });
+        if short {
+            break;
+        }
+        assert!(Instant::now() < deadline, "no low runway alert");
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    relayer.shutdown();
+    relayer.await_terminated().await.unwrap();
+}
+
+#[test]
+fn rejects_spend_windows_under_a_second() {
+    let state = tempfile::tempdir().unwrap();
+    let config = testkit::config(state.path())
+        .delivery(DeliveryConfig {
+            gas_tank: GasTankConfig {
+                spend_window_ms: 500,
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .build();
+    assert!(matches!(config, Err(RelayerError::InvalidConfig(_))));
+}
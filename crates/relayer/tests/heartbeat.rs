@@ -0,0 +1,134 @@
+use ethers::{
+    abi::{self, ParamType, Token},
+    core::types::{Address, U256},
+    signers::{LocalWallet, Signer},
+    utils::id,
+};
+use relayer::testkit::{self, MockEvmNode, TestPipeline, DEST_CHAIN_ID, SOURCE_CHAIN_ID};
+use relayer::{
+    ChainConfig, ChainId, GasCostKind, HeartbeatConfig, HeartbeatRegistry, RelayerError,
+    DEVNET_PRIVATE_KEY,
+};
+use std::time::{Duration, Instant};
+
+// Last nonce each pair in `reportHeartbeat` calldata reports, with the
+// pair's source chain, resolver and dapp
+fn reported(data: &[u8]) -> Vec<(U256, Address, Address, U256)> {
+    assert_eq!(
+        data[..4],
+        id("reportHeartbeat((uint256,address,address,uint256)[])")
+    );
+    let report = ParamType::Tuple(vec![
+        ParamType::Uint(256),
+        ParamType::Address,
+        ParamType::Address,
+        ParamType::Uint(256),
+    ]);
+    let decoded = abi::decode(&[ParamType::Array(Box::new(report))], &data[4..]).unwrap();
+    let Some(Token::Array(reports)) = decoded.into_iter().next() else {
+        panic!("no reports in {:?}", data);
+    };
+    reports
+        .into_iter()
+        .map(|report| {
+            let [source_chain_id, resolver, dapp, last_nonce]: [Token; 4] =
+                report.into_tuple().unwrap().try_into().unwrap();
+            (
+                source_chain_id.into_uint().unwrap(),
+                resolver.into_address().unwrap(),
+                dapp.into_address().unwrap(),
+                last_nonce.into_uint().unwrap(),
+            )
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn reports_the_last_delivered_nonce_of_each_pair() {
+    let node = MockEvmNode::start(DEST_CHAIN_ID).await.unwrap();
+    let registry = Address::repeat_byte(0x42);
+    let state = tempfile::tempdir().unwrap();
+    let config = testkit::config(state.path())
+        .chain(ChainConfig {
+            rpc_url: node.url(),
+            ..testkit::chain(DEST_CHAIN_ID, &format!("chain-{}", DEST_CHAIN_ID))
+        })
+        .heartbeat(HeartbeatConfig {
+            registries: vec![HeartbeatRegistry {
+                chain_id: ChainId::new(DEST_CHAIN_ID),
+                address: registry,
+            }],
+            interval_ms: 50,
+            wallet_key: Some(DEVNET_PRIVATE_KEY.to_string()),
+        })
+        .build()
+        .unwrap();
+    let mut pipeline = TestPipeline::start(config).await.unwrap();
+    for nonce in 1..=3 {
+        let event = testkit::event(nonce);
+        pipeline.emit(event.clone()).await.unwrap();
+        pipeline
+            .expect_delivered(&event, Duration::from_secs(10))
+            .await
+            .unwrap();
+    }
+
+    let pair = testkit::relay_pair();
+    let expected = vec![(
+        U256::from(SOURCE_CHAIN_ID),
+        pair.source_resolver_address,
+        pair.dest_dapp_address,
+        U256::from(3),
+    )];
+    let deadline = Instant::now() + Duration::from_secs(10);
+    let heartbeat = loop {
+        let sent = node.sent();
+        if let Some(tx) = sent.iter().find(|tx| reported(&tx.data) == expected) {
+            break tx.clone();
+        }
+        assert!(
+            Instant::now() < deadline,
+            "no heartbeat reported nonce 3: {:?}",
+            sent
+        );
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    };
+    let wallet: LocalWallet = DEVNET_PRIVATE_KEY.parse().unwrap();
+    assert_eq!(heartbeat.from, wallet.address());
+    assert_eq!(heartbeat.to, Some(registry));
+    assert!(pipeline
+        .store()
+        .gas_costs()
+        .unwrap()
+        .iter()
+        .any(|cost| cost.kind == GasCostKind::Heartbeat && cost.pair == "heartbeat"));
+    pipeline.shutdown().await.unwrap();
+}
+
+#[test]
+fn rejects_registries_on_unknown_chains_or_at_zero() {
+    let state = tempfile::tempdir().unwrap();
+    let unknown = testkit::config(state.path())
+        .heartbeat(HeartbeatConfig {
+            registries: vec![HeartbeatRegistry {
+                chain_id: ChainId::new(999),
+                address: Address::repeat_byte(0x42),
+            }],
+            ..Default::default()
+        })
+        .build();
+    assert!(
+        matches!(unknown, Err(RelayerError::UnknownChain(chain)) if chain == ChainId::new(999))
+    );
+
+    let zero = testkit::config(state.path())
+        .heartbeat(HeartbeatConfig {
+            registries: vec![HeartbeatRegistry {
+                chain_id: ChainId::new(DEST_CHAIN_ID),
+                address: Address::zero(),
+            }],
+            ..Default::default()
+        })
+        .build();
+    assert!(matches!(zero, Err(RelayerError::InvalidConfig(_))));
+}
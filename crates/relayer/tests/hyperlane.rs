@@ -0,0 +1,85 @@
+use ethers::core::types::{Address, Bytes, H256};
+use relayer::testkit::{self, EventBuilder};
+use relayer::{
+    hyperlane_message, DeliveryMode, HyperlaneConfig, RelayMode, RelayPair, RelayerConfig,
+    RelayerError,
+};
+
+fn mailbox() -> Address {
+    Address::from_low_u64_be(0x3a11b0c)
+}
+
+fn pair() -> RelayPair {
+    RelayPair {
+        hyperlane: Some(HyperlaneConfig::new(mailbox())),
+        ..testkit::relay_pair()
+    }
+}
+
+#[test]
+fn packs_events_into_hyperlane_messages() {
+    let pair = pair();
+    let event = EventBuilder::new(&pair)
+        .nonce(9)
+        .payload(vec![0xca, 0xfe])
+        .build();
+
+    let message = hyperlane_message(pair.hyperlane.as_ref().unwrap(), &event).unwrap();
+
+    let expected = [
+        &[3][..],
+        &9u32.to_be_bytes(),
+        &(testkit::SOURCE_CHAIN_ID as u32).to_be_bytes(),
+        H256::from(pair.source_resolver_address).as_bytes(),
+        &(testkit::DEST_CHAIN_ID as u32).to_be_bytes(),
+        H256::from(pair.dest_dapp_address).as_bytes(),
+        &[0xca, 0xfe],
+    ]
+    .concat();
+    assert_eq!(message, Bytes::from(expected));
+
+    // Configured domains replace the chain ids
+    let domains = HyperlaneConfig {
+        origin_domain: Some(1_000),
+        destination_domain: Some(2_000),
+        ..HyperlaneConfig::new(mailbox())
+    };
+    let message = hyperlane_message(&domains, &event).unwrap();
+    assert_eq!(message[5..9], 1_000u32.to_be_bytes());
+    assert_eq!(message[41..45], 2_000u32.to_be_bytes());
+
+    let wide_nonce = EventBuilder::new(&pair).nonce(1 << 32).build();
+    let error = hyperlane_message(&domains, &wide_nonce).unwrap_err();
+    assert!(matches!(error, RelayerError::InvalidPayload(_)));
+}
+
+#[test]
+fn hyperlane_pairs_need_a_mailbox_and_polymer_pushes() {
+    let state = tempfile::tempdir().unwrap();
+    let build = |pair: RelayPair| {
+        RelayerConfig::builder()
+            .chain(testkit::chain(testkit::SOURCE_CHAIN_ID, "source"))
+            .chain(testkit::chain(testkit::DEST_CHAIN_ID, "destination"))
+            .relay_pair(pair)
+            .state_dir(state.path())
+            .build()
+    };
+
+    assert!(build(pair()).is_ok());
+    for pair in [
+        RelayPair {
+            hyperlane: Some(HyperlaneConfig::new(Address::zero())),
+            ..pair()
+        },
+        RelayPair {
+            relay_mode: RelayMode::Erc7683,
+            ..pair()
+        },
+        RelayPair {
+            delivery_mode: DeliveryMode::CcipRead,
+            ..pair()
+        },
+    ] {
+        assert!(matches!(build(pair), Err(RelayerError::InvalidConfig(_))));
+    }
+}
@@ -0,0 +1,198 @@
+use ethers::abi::{self, Token};
+use ethers::core::types::{Address, Bytes, Log, H256, U256, U64};
+use ethers::utils::keccak256;
+use relayer::testkit::{self, RpcCassette, RpcReplayer, TestPipeline};
+use relayer::{
+    decode_open, ChainId, FileStateStore, IntentSource, IntentSourceConfig, RelayMode, RelayPair,
+    RelayerConfig, Topology, OPEN,
+};
+use serde_json::json;
+use std::{
+    path::Path,
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+const HEAD: u64 = 100;
+
+fn pair() -> RelayPair {
+    RelayPair {
+        relay_mode: RelayMode::Erc7683,
+        ..testkit::relay_pair()
+    }
+}
+
+fn now() -> u32 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as u32
+}
+
+fn order_id(id: u8) -> H256 {
+    H256::from([id; 32])
+}
+
+// `Open` log for an order with a single leg to `settler` on `destination`
+fn open(id: u8, destination: u64, settler: Address, fill_deadline: u32, log_index: u64) -> Log {
+    let output = Token::Tuple(vec![
+        Token::FixedBytes(vec![1; 32]),
+        Token::Uint(1_000.into()),
+        Token::FixedBytes(vec![2; 32]),
+        Token::Uint(destination.into()),
+    ]);
+    let order = Token::Tuple(vec![
+        Token::Address(Address::from_low_u64_be(0xa11ce)),
+        Token::Uint(testkit::SOURCE_CHAIN_ID.into()),
+        Token::Uint(fill_deadline.into()),
+        Token::Uint(fill_deadline.into()),
+        Token::FixedBytes(order_id(id).as_bytes().to_vec()),
+        Token::Array(vec![output.clone()]),
+        Token::Array(vec![output]),
+        Token::Array(vec![Token::Tuple(vec![
+            Token::Uint(destination.into()),
+            Token::FixedBytes(H256::from(settler).as_bytes().to_vec()),
+            Token::Bytes(vec![id; 3]),
+        ])]),
+    ]);
+    Log {
+        address: pair().source_resolver_address,
+        topics: vec![H256::from(keccak256(OPEN)), order_id(id)],
+        data: Bytes::from(abi::encode(&[order])),
+        block_number: Some(U64::from(90)),
+        transaction_hash: Some(H256::from_low_u64_be(id.into())),
+        transaction_index: Some(U64::from(1)),
+        log_index: Some(U256::from(log_index)),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn decodes_opened_orders() {
+    let settler = pair().dest_dapp_address;
+    let log = open(7, testkit::DEST_CHAIN_ID, settler, 1_234, 0);
+
+    let order = decode_open(&log).unwrap();
+
+    assert_eq!(order.order_id, order_id(7));
+    assert_eq!(order.user, Address::from_low_u64_be(0xa11ce));
+    assert_eq!(order.origin_chain_id, testkit::SOURCE_CHAIN_ID.into());
+    assert_eq!(order.fill_deadline, 1_234);
+    assert_eq!(order.fill_instructions.len(), 1);
+    let instruction = &order.fill_instructions[0];
+    assert_eq!(instruction.destination_chain_id, testkit::DEST_CHAIN_ID);
+    assert_eq!(instruction.destination_settler, H256::from(settler));
+    assert_eq!(instruction.origin_data, Bytes::from(vec![7; 3]));
+
+    let truncated = Log {
+        data: Bytes::from(log.data[..100].to_vec()),
+        ..log.clone()
+    };
+    assert!(decode_open(&truncated).is_err());
+    let other_event = Log {
+        topics: vec![H256::zero(), order_id(7)],
+        ..log
+    };
+    assert!(decode_open(&other_event).is_err());
+}
+
+#[tokio::test]
+async fn relays_orders_with_a_leg_to_the_pair_settler() {
+    let pair = pair();
+    let settler = pair.dest_dapp_address;
+    let deadline = now() + 3_600;
+    let logs = vec![
+        open(1, testkit::DEST_CHAIN_ID, settler, deadline, 0),
+        open(
+            2,
+            testkit::DEST_CHAIN_ID,
+            Address::from_low_u64_be(0xbad),
+            deadline,
+            1,
+        ),
+        open(3, 10, settler, deadline, 2),
+        open(4, testkit::DEST_CHAIN_ID, settler, now() - 60, 3),
+    ];
+    let node = RpcReplayer::start(
+        RpcCassette::default()
+            .with_result(
+                "eth_blockNumber",
+                json!(null),
+                json!(format!("{:#x}", HEAD)),
+            )
+            .with_result(
+                "eth_getLogs",
+                json!([{
+                    "address": pair.source_resolver_address,
+                    "fromBlock": "0x0",
+                    "toBlock": format!("{:#x}", HEAD),
+                    "topics": [H256::from(keccak256(OPEN))],
+                }]),
+                serde_json::to_value(&logs).unwrap(),
+            ),
+    )
+    .await
+    .unwrap();
+    let state = tempfile::tempdir().unwrap();
+    let mut config = testkit::config(state.path()).build().unwrap();
+    config.relay_pairs = vec![pair.clone()];
+    for chain in config.chains.values_mut() {
+        chain.rpc_url = node.endpoint();
+    }
+    let source_state = tempfile::tempdir().unwrap();
+    let source = intent_source(&config, source_state.path());
+    let mut pipeline = TestPipeline::start_with(config, |builder| builder.event_source(source))
+        .await
+        .unwrap();
+
+    let requests = pipeline
+        .sink()
+        .wait_for(1, Duration::from_secs(10))
+        .await
+        .unwrap();
+    pipeline
+        .expect_delivered(&requests[0].event, Duration::from_secs(10))
+        .await
+        .unwrap();
+    // Later polls find no new blocks to scan
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    let requests = pipeline.sink().requests();
+    assert_eq!(requests.len(), 1);
+    let event = &requests[0].event;
+    assert_eq!(event.dest_dapp_address, settler);
+    assert_eq!(
+        event.destination_chain.chain_id,
+        ChainId::new(testkit::DEST_CHAIN_ID)
+    );
+    assert_eq!(event.nonce, u64::from_be_bytes([1; 8]));
+    assert_eq!(event.meta.tx_hash, Some(H256::from_low_u64_be(1)));
+    assert_eq!((event.meta.block_number, event.meta.log_index), (90, 0));
+    assert_eq!(
+        event.exec_payload,
+        Bytes::from(abi::encode(&[
+            Token::FixedBytes(order_id(1).as_bytes().to_vec()),
+            Token::Bytes(vec![1; 3]),
+        ]))
+    );
+    assert!(node.unused().is_empty());
+    pipeline.shutdown().await.unwrap();
+}
+
+// Source scanning every block up to the head, with a store of its own
+fn intent_source(config: &RelayerConfig, state_dir: &Path) -> Arc<IntentSource> {
+    let topology = Topology::new(
+        config.chains.clone(),
+        config.relay_pairs.clone(),
+        Default::default(),
+    );
+    Arc::new(IntentSource::new(
+        topology,
+        Arc::new(FileStateStore::open(state_dir).unwrap()),
+        IntentSourceConfig {
+            poll_interval_ms: 50,
+            lookback_blocks: HEAD,
+            ..Default::default()
+        },
+    ))
+}
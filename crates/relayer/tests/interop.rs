@@ -0,0 +1,204 @@
+use ethers::abi::{self, ParamType, Token};
+use ethers::core::types::{Address, Block, Bytes, Log, TransactionReceipt, H256, U256, U64};
+use ethers::utils::keccak256;
+use relayer::testkit::{self, EventBuilder, RpcCassette, RpcReplayer, TestPipeline};
+use relayer::{
+    sent_message_meta, ChainConfig, ChainId, ChainKind, ForwarderConfig, MockProofConfig,
+    ProofBackendConfig, RelayMode, RelayPair, RelayerConfig, RelayerError,
+    L2_TO_L2_CROSS_DOMAIN_MESSENGER, SENT_MESSAGE,
+};
+use serde_json::json;
+use std::time::Duration;
+
+const BLOCK_NUMBER: u64 = 1_234;
+const TIMESTAMP: u64 = 1_700_000_000;
+const LOG_INDEX: u32 = 3;
+
+fn pair() -> RelayPair {
+    RelayPair {
+        relay_mode: RelayMode::OpInterop,
+        ..testkit::relay_pair()
+    }
+}
+
+fn op_chain(chain_id: u64, rpc_url: &str) -> ChainConfig {
+    ChainConfig {
+        rpc_url: rpc_url.to_string(),
+        kind: ChainKind::OpStack,
+        ..testkit::chain(chain_id, &format!("op-{}", chain_id))
+    }
+}
+
+fn tx_hash() -> H256 {
+    H256::from_low_u64_be(0xabc)
+}
+
+// `SentMessage` log the messenger emits when `sender` sends `message` to
+// `target` on `destination`
+fn sent_message(sender: Address, destination: u64, target: Address, log_index: u32) -> Log {
+    Log {
+        address: L2_TO_L2_CROSS_DOMAIN_MESSENGER,
+        topics: vec![
+            H256::from(keccak256(SENT_MESSAGE)),
+            H256::from_low_u64_be(destination),
+            H256::from(target),
+            H256::from_low_u64_be(7),
+        ],
+        data: Bytes::from(abi::encode(&[
+            Token::Address(sender),
+            Token::Bytes(vec![0xfe, 0x0d, 0x94, 0xc1]),
+        ])),
+        log_index: Some(U256::from(log_index)),
+        ..Default::default()
+    }
+}
+
+fn receipt(logs: Vec<Log>) -> TransactionReceipt {
+    TransactionReceipt {
+        transaction_hash: tx_hash(),
+        transaction_index: U64::from(2),
+        block_number: Some(U64::from(BLOCK_NUMBER)),
+        logs,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn locates_the_message_sent_for_the_pair() {
+    let pair = pair();
+    let resolver = pair.source_resolver_address;
+    let dapp = pair.dest_dapp_address;
+    let stranger = Address::from_low_u64_be(0xbad);
+    let receipt = receipt(vec![
+        sent_message(stranger, testkit::DEST_CHAIN_ID, dapp, 0),
+        sent_message(resolver, 10, dapp, 1),
+        sent_message(resolver, testkit::DEST_CHAIN_ID, stranger, 2),
+        sent_message(resolver, testkit::DEST_CHAIN_ID, dapp, LOG_INDEX),
+    ]);
+    let source = pair.source_chain_id;
+
+    let meta = sent_message_meta(source, &receipt, resolver, pair.dest_chain_id, dapp).unwrap();
+
+    assert_eq!(meta.chain_id, source);
+    assert_eq!(meta.tx_hash, Some(tx_hash()));
+    assert_eq!(meta.block_number, BLOCK_NUMBER);
+    assert_eq!(meta.tx_index, 2);
+    assert_eq!(meta.log_index, LOG_INDEX);
+    let other = ChainId::new(10);
+    assert!(sent_message_meta(source, &receipt, stranger, other, dapp).is_err());
+}
+
+#[tokio::test]
+async fn relays_interop_pairs_with_the_message_identifier() {
+    let pair = pair();
+    let log = sent_message(
+        pair.source_resolver_address,
+        testkit::DEST_CHAIN_ID,
+        pair.dest_dapp_address,
+        LOG_INDEX,
+    );
+    let block = Block::<H256> {
+        number: Some(U64::from(BLOCK_NUMBER)),
+        timestamp: U256::from(TIMESTAMP),
+        ..Default::default()
+    };
+    let node = RpcReplayer::start(
+        RpcCassette::default()
+            .with_result(
+                "eth_getTransactionReceipt",
+                json!([tx_hash()]),
+                serde_json::to_value(receipt(vec![log.clone()])).unwrap(),
+            )
+            .with_result(
+                "eth_getBlockByNumber",
+                json!([format!("{:#x}", BLOCK_NUMBER), false]),
+                serde_json::to_value(block).unwrap(),
+            ),
+    )
+    .await
+    .unwrap();
+    let source = op_chain(testkit::SOURCE_CHAIN_ID, &node.endpoint());
+    let destination = op_chain(testkit::DEST_CHAIN_ID, &node.endpoint());
+    let state = tempfile::tempdir().unwrap();
+    let config = RelayerConfig::builder()
+        .chain(source.clone())
+        .chain(destination.clone())
+        .relay_pair(pair.clone())
+        .proof_backend(ProofBackendConfig::Mock(MockProofConfig {
+            latency_ms: 0,
+            failure_rate: 0.0,
+        }))
+        .state_dir(state.path())
+        .build()
+        .unwrap();
+    let mut pipeline = TestPipeline::start(config).await.unwrap();
+    let event = EventBuilder::new(&pair)
+        .chains(source, destination)
+        .log(BLOCK_NUMBER, 2, LOG_INDEX)
+        .tx_hash(tx_hash())
+        .build();
+
+    pipeline.emit(event.clone()).await.unwrap();
+    pipeline
+        .expect_delivered(&event, Duration::from_secs(10))
+        .await
+        .unwrap();
+
+    let identifier = ParamType::Tuple(vec![
+        ParamType::Address,
+        ParamType::Uint(256),
+        ParamType::Uint(256),
+        ParamType::Uint(256),
+        ParamType::Uint(256),
+    ]);
+    let arguments = abi::decode(
+        &[identifier, ParamType::Bytes],
+        &pipeline.sink().requests()[0].proof.data,
+    )
+    .unwrap();
+    assert_eq!(
+        arguments[0],
+        Token::Tuple(vec![
+            Token::Address(L2_TO_L2_CROSS_DOMAIN_MESSENGER),
+            Token::Uint(BLOCK_NUMBER.into()),
+            Token::Uint(LOG_INDEX.into()),
+            Token::Uint(TIMESTAMP.into()),
+            Token::Uint(testkit::SOURCE_CHAIN_ID.into()),
+        ])
+    );
+    let mut message: Vec<u8> = log.topics.iter().flat_map(|t| t.0).collect();
+    message.extend_from_slice(&log.data);
+    assert_eq!(arguments[1], Token::Bytes(message));
+    assert!(node.unused().is_empty());
+    pipeline.shutdown().await.unwrap();
+}
+
+#[test]
+fn interop_pairs_need_op_stack_chains_and_no_forwarder() {
+    let state = tempfile::tempdir().unwrap();
+    let config = |kind: ChainKind, pair: RelayPair| {
+        let chain = |chain_id| ChainConfig {
+            kind,
+            ..testkit::chain(chain_id, "chain")
+        };
+        RelayerConfig::builder()
+            .chain(chain(testkit::SOURCE_CHAIN_ID))
+            .chain(chain(testkit::DEST_CHAIN_ID))
+            .relay_pair(pair)
+            .state_dir(state.path())
+            .build()
+    };
+    let forwarded = RelayPair {
+        forwarder: Some(ForwarderConfig::new(Address::from_low_u64_be(0xf0))),
+        ..pair()
+    };
+
+    assert!(config(ChainKind::OpStack, pair()).is_ok());
+    for error in [
+        config(ChainKind::Standard, pair()),
+        config(ChainKind::OpStack, forwarded),
+    ] {
+        assert!(matches!(error, Err(RelayerError::InvalidConfig(_))));
+    }
+    assert!(config(ChainKind::Standard, testkit::relay_pair()).is_ok());
+}
@@ -0,0 +1,107 @@
+use relayer::testkit::{self, MockWebhook, TestPipeline};
+use relayer::{
+    AlertConfig, MockProofConfig, ProofBackendConfig, RelayerError, SloConfig, WebhookConfig,
+    WebhookKind,
+};
+use std::time::{Duration, Instant};
+
+#[tokio::test]
+async fn slow_deliveries_are_measured_and_breach_the_slo() {
+    let alerts = MockWebhook::start().await.unwrap();
+    let state = tempfile::tempdir().unwrap();
+    let addr = testkit::free_addr();
+    let config = testkit::config(state.path())
+        .http_addr(addr)
+        .proof_backend(ProofBackendConfig::Mock(MockProofConfig {
+            latency_ms: 50,
+            failure_rate: 0.0,
+        }))
+        .slo(SloConfig {
+            target_ms: 10,
+            min_deliveries: 2,
+            ..Default::default()
+        })
+        .alerts(AlertConfig {
+            webhooks: vec![WebhookConfig {
+                url: alerts.url(),
+                kind: WebhookKind::Generic,
+            }],
+            ..Default::default()
+        })
+        .build()
+        .unwrap();
+    let mut pipeline = TestPipeline::start(config).await.unwrap();
+    for nonce in 1..=2 {
+        let event = testkit::event(nonce);
+        pipeline.emit(event.clone()).await.unwrap();
+        pipeline
+            .expect_delivered(&event, Duration::from_secs(10))
+            .await
+            .unwrap();
+    }
+
+    let metrics = reqwest::get(format!("http://{}/metrics", addr))
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    for stage in [
+        "detection_to_proof",
+        "proof_to_broadcast",
+        "broadcast_to_confirmation",
+    ] {
+        let series = metrics.lines().find(|line| {
+            line.starts_with("relayer_stage_latency_seconds_count")
+                && line.contains(&format!("stage=\"{}\"", stage))
+        });
+        assert!(
+            series.is_some_and(|line| line.ends_with(" 2")),
+            "{} not measured: {:?}",
+            stage,
+            series
+        );
+    }
+    assert!(metrics.lines().any(|line| line
+        .starts_with("relayer_end_to_end_latency_seconds_count")
+        && line.ends_with(" 2")));
+    assert!(metrics.contains("relayer_slo_compliance_ratio 0\n"));
+    assert!(metrics.contains("relayer_slo_deliveries_total{result=\"missed\"} 2\n"));
+
+    let deadline = Instant::now() + Duration::from_secs(10);
+    let breach = loop {
+        let breach = alerts.posts().into_iter().find_map(|post| {
+            let alert: serde_json::Value = serde_json::from_slice(&post.body).ok()?;
+            (alert["kind"] == "slo_breach").then_some(alert)
+        });
+        if let Some(breach) = breach {
+            break breach;
+        }
+        assert!(Instant::now() < deadline, "no SLO breach alert");
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    };
+    assert!(
+        breach["message"].as_str().unwrap().contains("0.0% of 2"),
+        "{}",
+        breach
+    );
+    pipeline.shutdown().await.unwrap();
+}
+
+#[test]
+fn rejects_objectives_outside_zero_to_one() {
+    for objective in [0.0, 1.5, f64::NAN] {
+        let state = tempfile::tempdir().unwrap();
+        let result = testkit::config(state.path())
+            .slo(SloConfig {
+                objective,
+                ..Default::default()
+            })
+            .build();
+        assert!(
+            matches!(result, Err(RelayerError::InvalidConfig(_))),
+            "objective {} was accepted",
+            objective
+        );
+    }
+}
@@ -0,0 +1,77 @@
+use relayer::testkit::{self, TestPipeline};
+use relayer::{MetricLabels, MetricsConfig};
+use std::time::Duration;
+
+// Relay one event with metrics labelled as `labels` says, returning the
+// label sets of the `relayer_events_total` series counting deliveries
+//
+// Labels are set for the whole process by the last relayer created, so
+// only one pipeline may run at a time.
+async fn delivered_series(labels: MetricLabels, nonce: u64) -> Vec<String> {
+    let state = tempfile::tempdir().unwrap();
+    let addr = testkit::free_addr();
+    let config = testkit::config(state.path())
+        .http_addr(addr)
+        .metrics(MetricsConfig { labels })
+        .build()
+        .unwrap();
+    let mut pipeline = TestPipeline::start(config).await.unwrap();
+    let event = testkit::event(nonce);
+    pipeline.emit(event.clone()).await.unwrap();
+    pipeline
+        .expect_delivered(&event, Duration::from_secs(10))
+        .await
+        .unwrap();
+
+    let metrics = reqwest::get(format!("http://{}/metrics", addr))
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    pipeline.shutdown().await.unwrap();
+    metrics
+        .lines()
+        .filter_map(|line| line.strip_prefix("relayer_events_total{"))
+        .filter_map(|line| line.split_once('}'))
+        .map(|(labels, _)| labels.to_string())
+        .filter(|labels| labels.contains("status=\"delivered\""))
+        .collect()
+}
+
+#[tokio::test]
+async fn labels_series_by_pair_unless_collapsed() {
+    let event = testkit::event(1);
+    let source = format!("source_chain=\"{}\"", testkit::SOURCE_CHAIN_ID);
+    let dest = format!("dest_chain=\"{}\"", testkit::DEST_CHAIN_ID);
+    let pair = format!("pair_id=\"{}\"", event.pair_id());
+
+    let series = delivered_series(MetricLabels::Pair, 1).await;
+    assert!(
+        series.iter().any(|labels| labels.contains(&source)
+            && labels.contains(&dest)
+            && labels.contains(&pair)),
+        "no per-pair series in {:?}",
+        series
+    );
+
+    let series = delivered_series(MetricLabels::Route, 2).await;
+    assert!(
+        series.iter().any(|labels| labels.contains(&source)
+            && labels.contains(&dest)
+            && labels.contains("pair_id=\"*\"")),
+        "no per-route series in {:?}",
+        series
+    );
+
+    let series = delivered_series(MetricLabels::Aggregate, 3).await;
+    assert!(
+        series
+            .iter()
+            .any(|labels| labels.contains("source_chain=\"*\"")
+                && labels.contains("dest_chain=\"*\"")
+                && labels.contains("pair_id=\"*\"")),
+        "no aggregate series in {:?}",
+        series
+    );
+}
@@ -0,0 +1,254 @@
+use axum::http::StatusCode;
+use relayer::testkit::{MockPolymerApi, MockReply};
+use relayer::{
+    ChainId, EventMeta, FileStateStore, PolymerApiConfig, PolymerProofProvider, ProofBatchConfig,
+    ProofProvider, RelayerError,
+};
+use std::{sync::Arc, time::Duration};
+use tokio::task::JoinSet;
+
+const REQUEST_PROOF: &str = "log_requestProof";
+const QUERY_PROOF: &str = "log_queryProof";
+
+fn meta() -> EventMeta {
+    EventMeta {
+        chain_id: ChainId::new(11155420),
+        tx_hash: None,
+        block_number: 1_234,
+        tx_index: 5,
+        log_index: 2,
+    }
+}
+
+fn config(api: &MockPolymerApi) -> PolymerApiConfig {
+    PolymerApiConfig {
+        endpoint: api.endpoint(),
+        token: "test-token".to_string(),
+        request_timeout_ms: 500,
+        ..Default::default()
+    }
+}
+
+fn provider(api: &MockPolymerApi, state: &tempfile::TempDir) -> PolymerProofProvider {
+    let store = Arc::new(FileStateStore::open(state.path()).unwrap());
+    PolymerProofProvider::new(config(api), store).unwrap()
+}
+
+fn batching_provider(
+    api: &MockPolymerApi,
+    state: &tempfile::TempDir,
+    batch: ProofBatchConfig,
+) -> Arc<PolymerProofProvider> {
+    let config = PolymerApiConfig {
+        batch: Some(batch),
+        ..config(api)
+    };
+    let store = Arc::new(FileStateStore::open(state.path()).unwrap());
+    Arc::new(PolymerProofProvider::new(config, store).unwrap())
+}
+
+fn window(window_ms: u64) -> ProofBatchConfig {
+    ProofBatchConfig {
+        window_ms,
+        ..Default::default()
+    }
+}
+
+// Prove logs 0 to `count - 1` of the same transaction at once, returning the
+// log index each proof names, or the error proving it
+async fn prove_logs(
+    provider: &Arc<PolymerProofProvider>,
+    count: u32,
+) -> Vec<Result<u32, anyhow::Error>> {
+    let mut proofs = JoinSet::new();
+    for log_index in 0..count {
+        let provider = provider.clone();
+        proofs.spawn(async move {
+            let meta = EventMeta {
+                log_index,
+                ..meta()
+            };
+            let proof = provider.prove(&meta).await?;
+            Ok(provider.inspect(&proof)?.unwrap().log_index)
+        });
+    }
+    proofs.join_all().await
+}
+
+#[tokio::test]
+async fn polls_until_the_proof_is_ready() {
+    let api = MockPolymerApi::start().await.unwrap();
+    api.script_queries([MockReply::pending(), MockReply::pending()]);
+    let state = tempfile::tempdir().unwrap();
+    let provider = provider(&api, &state);
+
+    let proof = provider.prove(&meta()).await.unwrap();
+
+    let log = provider.inspect(&proof).unwrap().unwrap();
+    assert_eq!(log.chain_id, meta().chain_id);
+    assert_eq!(log.block_number, 1_234);
+    assert_eq!(log.receipt_index, 5);
+    assert_eq!(log.log_index, 2);
+    assert_eq!(api.call_count(REQUEST_PROOF), 1);
+    assert_eq!(api.call_count(QUERY_PROOF), 3);
+}
+
+#[tokio::test]
+async fn sends_the_log_location_and_token() {
+    let api = MockPolymerApi::start().await.unwrap();
+    api.require_token("test-token");
+    let state = tempfile::tempdir().unwrap();
+
+    provider(&api, &state).prove(&meta()).await.unwrap();
+
+    let request = &api.calls()[0];
+    assert_eq!(request.method, REQUEST_PROOF);
+    assert_eq!(request.params, [11155420, 1_234, 5, 2]);
+    assert_eq!(request.token.as_deref(), Some("test-token"));
+}
+
+#[tokio::test]
+async fn rejected_requests_are_not_retried() {
+    let api = MockPolymerApi::start().await.unwrap();
+    api.script_requests([MockReply::rpc_error(-32602, "invalid params")]);
+    let state = tempfile::tempdir().unwrap();
+
+    let error = provider(&api, &state).prove(&meta()).await.unwrap_err();
+
+    assert!(matches!(
+        error.downcast_ref::<RelayerError>(),
+        Some(RelayerError::ProofRequestRejected { code: -32602, .. })
+    ));
+    assert_eq!(api.call_count(REQUEST_PROOF), 1);
+}
+
+#[tokio::test]
+async fn transient_errors_are_retried() {
+    let api = MockPolymerApi::start().await.unwrap();
+    api.script_requests([MockReply::rpc_error(-32603, "internal error")]);
+    let state = tempfile::tempdir().unwrap();
+
+    provider(&api, &state).prove(&meta()).await.unwrap();
+
+    assert_eq!(api.call_count(REQUEST_PROOF), 2);
+}
+
+#[tokio::test]
+async fn slow_responses_time_out_and_are_retried() {
+    let api = MockPolymerApi::start().await.unwrap();
+    api.script_requests([MockReply::JobId(7).after(Duration::from_secs(2))]);
+    let state = tempfile::tempdir().unwrap();
+
+    provider(&api, &state).prove(&meta()).await.unwrap();
+
+    assert_eq!(api.call_count(REQUEST_PROOF), 2);
+}
+
+#[tokio::test]
+async fn wrong_token_is_refused() {
+    let api = MockPolymerApi::start().await.unwrap();
+    api.require_token("another-token");
+    let state = tempfile::tempdir().unwrap();
+
+    let error = provider(&api, &state).prove(&meta()).await.unwrap_err();
+
+    assert!(error.to_string().contains("rejected the configured token"));
+    assert_eq!(api.call_count(QUERY_PROOF), 0);
+}
+
+#[tokio::test]
+async fn stored_proofs_are_reused() {
+    let api = MockPolymerApi::start().await.unwrap();
+    let state = tempfile::tempdir().unwrap();
+    let provider = provider(&api, &state);
+
+    let first = provider.prove(&meta()).await.unwrap();
+    let second = provider.prove(&meta()).await.unwrap();
+
+    assert_eq!(first.data, second.data);
+    assert_eq!(api.calls().len(), 2);
+}
+
+#[tokio::test]
+async fn concurrent_proofs_share_batch_calls() {
+    let api = MockPolymerApi::start().await.unwrap();
+    api.require_token("test-token");
+    let state = tempfile::tempdir().unwrap();
+    let provider = batching_provider(&api, &state, window(1_000));
+
+    let mut proven: Vec<u32> = prove_logs(&provider, 10)
+        .await
+        .into_iter()
+        .map(Result::unwrap)
+        .collect();
+
+    proven.sort();
+    assert_eq!(proven, (0..10).collect::<Vec<_>>());
+    assert_eq!(api.post_count(), 2);
+    assert_eq!(api.call_count(REQUEST_PROOF), 10);
+    assert_eq!(api.call_count(QUERY_PROOF), 10);
+    assert!(api.calls().iter().all(|call| call.batch == Some(10)));
+    assert!(api
+        .calls()
+        .iter()
+        .filter(|call| call.method == REQUEST_PROOF)
+        .all(|call| call.token.as_deref() == Some("test-token")));
+}
+
+#[tokio::test]
+async fn full_batches_do_not_wait_out_the_window() {
+    let api = MockPolymerApi::start().await.unwrap();
+    let state = tempfile::tempdir().unwrap();
+    let batch = ProofBatchConfig {
+        window_ms: 60_000,
+        max_size: 2,
+    };
+    let provider = batching_provider(&api, &state, batch);
+
+    let proven = tokio::time::timeout(Duration::from_secs(5), prove_logs(&provider, 2))
+        .await
+        .unwrap();
+
+    assert!(proven.iter().all(Result::is_ok));
+    assert_eq!(api.post_count(), 2);
+}
+
+#[tokio::test]
+async fn a_rejected_call_fails_only_its_own_proof() {
+    let api = MockPolymerApi::start().await.unwrap();
+    api.script_requests([MockReply::rpc_error(-32602, "invalid params")]);
+    let state = tempfile::tempdir().unwrap();
+    let provider = batching_provider(&api, &state, window(1_000));
+
+    let proven = prove_logs(&provider, 3).await;
+
+    let rejected: Vec<_> = proven
+        .iter()
+        .filter_map(|proof| proof.as_ref().err())
+        .collect();
+    assert_eq!(rejected.len(), 1);
+    assert!(matches!(
+        rejected[0].downcast_ref::<RelayerError>(),
+        Some(RelayerError::ProofRequestRejected { code: -32602, .. })
+    ));
+    assert_eq!(api.call_count(REQUEST_PROOF), 3);
+}
+
+#[tokio::test]
+async fn failed_batch_calls_fall_back_to_single_calls() {
+    let api = MockPolymerApi::start().await.unwrap();
+    api.script_requests([MockReply::http(StatusCode::BAD_REQUEST)]);
+    let state = tempfile::tempdir().unwrap();
+    let provider = batching_provider(&api, &state, window(1_000));
+
+    let proven = prove_logs(&provider, 3).await;
+
+    assert!(proven.iter().all(Result::is_ok));
+    let requests: Vec<_> = api
+        .calls()
+        .into_iter()
+        .filter(|call| call.method == REQUEST_PROOF)
+        .map(|call| call.batch)
+        .collect();
+    assert_eq!(requests, [Some(3), None, None, None]);
+}
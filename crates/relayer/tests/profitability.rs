@@ -0,0 +1,204 @@
+use ethers::{
+    abi::{self, Token},
+    core::types::U256,
+    utils::id,
+};
+use relayer::testkit::{self, MockEvmNode, RecordingSink, DEST_CHAIN_ID, SOURCE_CHAIN_ID};
+use relayer::{
+    ChainConfig, MockProofConfig, ProfitabilityConfig, ProofBackendConfig, RelayMode, RelayPair,
+    RelayerBuilder, RelayerConfig, RelayerError, RelayerHandle, UnprofitableRelays,
+    DEVNET_PRIVATE_KEY,
+};
+use std::{
+    path::Path,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+const REWARD: &str = "executionReward(uint32)";
+const CHECKER: &str = "crossChainChecker(uint32)";
+const TRIGGER: &str = "requestRemoteExecution(uint32)";
+
+// Relaying costs about 3.5e14 wei on the mock nodes: 50k trigger gas and the
+// default 300k delivery gas at 1 gwei
+const UNPROFITABLE: u64 = 1_000;
+const PROFITABLE: u64 = 1_000_000_000_000_000;
+
+struct Chains {
+    source: MockEvmNode,
+    dest: MockEvmNode,
+}
+
+impl Chains {
+    // Nodes whose resolver has a request pending that pays `reward`
+    async fn start(reward: u64) -> Self {
+        let chains = Self {
+            source: MockEvmNode::start(SOURCE_CHAIN_ID).await.unwrap(),
+            dest: MockEvmNode::start(DEST_CHAIN_ID).await.unwrap(),
+        };
+        let resolver = testkit::relay_pair().source_resolver_address;
+        let pending = abi::encode(&[
+            Token::Bool(true),
+            Token::Bytes(vec![0xab; 4]),
+            Token::Uint(U256::from(7)),
+        ]);
+        chains.source.on_call(resolver, CHECKER, pending);
+        chains.reward(reward);
+        chains
+    }
+
+    fn reward(&self, reward: u64) {
+        let resolver = testkit::relay_pair().source_resolver_address;
+        let reward = abi::encode(&[Token::Uint(U256::from(reward))]);
+        self.source.on_call(resolver, REWARD, reward);
+    }
+
+    fn triggers(&self) -> usize {
+        self.source
+            .sent()
+            .iter()
+            .filter(|tx| tx.data.starts_with(&id(TRIGGER)))
+            .count()
+    }
+
+    // Run a relayer polling these chains for `pair`'s requests
+    fn relay(
+        &self,
+        state_dir: &Path,
+        pair: RelayPair,
+        profitability: ProfitabilityConfig,
+    ) -> RelayerHandle {
+        let chain = |chain_id, node: &MockEvmNode| ChainConfig {
+            rpc_url: node.url(),
+            ..testkit::chain(chain_id, &format!("chain-{}", chain_id))
+        };
+        let config = RelayerConfig::builder()
+            .chain(chain(SOURCE_CHAIN_ID, &self.source))
+            .chain(chain(DEST_CHAIN_ID, &self.dest))
+            .relay_pair(pair)
+            .proof_backend(ProofBackendConfig::Mock(MockProofConfig {
+                latency_ms: 0,
+                failure_rate: 0.0,
+            }))
+            .polling_interval_ms(50)
+            .shutdown_grace_period_ms(1_000)
+            .profitability(profitability)
+            .state_dir(state_dir)
+            .build()
+            .unwrap();
+        RelayerBuilder::new(config)
+            .private_key(DEVNET_PRIVATE_KEY)
+            .delivery_sink(Arc::new(RecordingSink::new()))
+            .build()
+            .unwrap()
+            .run()
+    }
+}
+
+fn rewarding_pair() -> RelayPair {
+    RelayPair {
+        reward_function: Some(REWARD.to_string()),
+        ..testkit::relay_pair()
+    }
+}
+
+async fn wait_until(what: &str, done: impl Fn() -> bool) {
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while !done() {
+        assert!(
+            Instant::now() < deadline,
+            "timed out waiting until {}",
+            what
+        );
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+}
+
+#[tokio::test]
+async fn queues_requests_until_the_reward_covers_the_cost() {
+    let chains = Chains::start(UNPROFITABLE).await;
+    let state = tempfile::tempdir().unwrap();
+    let relayer = chains.relay(state.path(), rewarding_pair(), Default::default());
+
+    wait_until("the request was priced three times", || {
+        chains.source.eth_call_count(REWARD) >= 3
+    })
+    .await;
+    assert_eq!(chains.triggers(), 0);
+
+    chains.reward(PROFITABLE);
+    wait_until("the request was triggered", || chains.triggers() > 0).await;
+    relayer.shutdown();
+    relayer.await_terminated().await.unwrap();
+}
+
+#[tokio::test]
+async fn skipped_requests_are_not_priced_again() {
+    let chains = Chains::start(UNPROFITABLE).await;
+    let state = tempfile::tempdir().unwrap();
+    let profitability = ProfitabilityConfig {
+        unprofitable: UnprofitableRelays::Skip,
+        ..Default::default()
+    };
+    let relayer = chains.relay(state.path(), rewarding_pair(), profitability);
+
+    wait_until("the resolver was polled three times", || {
+        chains.source.eth_call_count(CHECKER) >= 3
+    })
+    .await;
+    chains.reward(PROFITABLE);
+    wait_until("the resolver was polled three more times", || {
+        chains.source.eth_call_count(CHECKER) >= 6
+    })
+    .await;
+    assert_eq!(chains.source.eth_call_count(REWARD), 1);
+    assert_eq!(chains.triggers(), 0);
+    relayer.shutdown();
+    relayer.await_terminated().await.unwrap();
+}
+
+#[tokio::test]
+async fn always_relay_pairs_are_relayed_whatever_they_pay() {
+    let chains = Chains::start(UNPROFITABLE).await;
+    let state = tempfile::tempdir().unwrap();
+    let pair = RelayPair {
+        always_relay: true,
+        ..rewarding_pair()
+    };
+    let relayer = chains.relay(state.path(), pair, Default::default());
+
+    wait_until("the request was triggered", || chains.triggers() > 0).await;
+    assert_eq!(chains.source.eth_call_count(REWARD), 0);
+    relayer.shutdown();
+    relayer.await_terminated().await.unwrap();
+}
+
+#[test]
+fn rejects_reward_functions_on_intent_pairs() {
+    let state = tempfile::tempdir().unwrap();
+    let config = RelayerConfig::builder()
+        .chain(testkit::chain(SOURCE_CHAIN_ID, "source"))
+        .chain(testkit::chain(DEST_CHAIN_ID, "dest"))
+        .relay_pair(RelayPair {
+            relay_mode: RelayMode::Erc7683,
+            ..rewarding_pair()
+        })
+        .state_dir(state.path())
+        .build();
+    assert!(matches!(config, Err(RelayerError::InvalidConfig(_))));
+}
+
+#[test]
+fn rejects_rewarded_pairs_between_different_gas_tokens() {
+    let state = tempfile::tempdir().unwrap();
+    let config = RelayerConfig::builder()
+        .chain(testkit::chain(SOURCE_CHAIN_ID, "source"))
+        .chain(ChainConfig {
+            native_token: Some("POL".to_string()),
+            ..testkit::chain(DEST_CHAIN_ID, "dest")
+        })
+        .relay_pair(rewarding_pair())
+        .state_dir(state.path())
+        .build();
+    assert!(matches!(config, Err(RelayerError::InvalidConfig(_))));
+}
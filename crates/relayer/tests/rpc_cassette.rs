@@ -0,0 +1,147 @@
+use ethers::providers::{Http, Middleware, Provider};
+use ethers::types::{H256, U64};
+use relayer::testkit::{self, RpcCassette, RpcRecorder, RpcReplayer};
+use relayer::{ChainId, Health, HealthConfig, ShardingConfig, Topology};
+use serde_json::{json, Value};
+use std::{collections::HashMap, time::Duration};
+
+const OP_STACK_DEPOSIT: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/tests/fixtures/op_stack_deposit.json"
+);
+
+fn provider(endpoint: &str) -> Provider<Http> {
+    Provider::<Http>::try_from(endpoint).unwrap()
+}
+
+#[tokio::test]
+async fn decodes_a_recorded_op_stack_deposit_receipt() {
+    let node = RpcReplayer::load(OP_STACK_DEPOSIT).await.unwrap();
+    let hash: H256 = "0x6d1f1bb2c9d6c7dd1e3bb4b12a1c4cbd6a7a8e3f1c7bdfd1a8b0c1e4c2f9e0a1"
+        .parse()
+        .unwrap();
+
+    let receipt = provider(&node.endpoint())
+        .get_transaction_receipt(hash)
+        .await
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(receipt.transaction_type, Some(U64::from(0x7e)));
+    assert_eq!(receipt.status, Some(U64::one()));
+    assert_eq!(receipt.gas_used, Some(0xb1f1.into()));
+    assert!(receipt.other.contains_key("l1Fee"));
+    assert!(node.misses().is_empty());
+}
+
+#[tokio::test]
+async fn replays_what_was_recorded() {
+    let upstream = RpcReplayer::start(
+        RpcCassette::default()
+            .with_result("eth_chainId", Value::Null, json!("0x14a34"))
+            .with_result("eth_gasPrice", Value::Null, json!("0x0")),
+    )
+    .await
+    .unwrap();
+    let recorder = RpcRecorder::start(upstream.endpoint()).await.unwrap();
+
+    let recording = provider(&recorder.endpoint());
+    let chain_id = recording.get_chainid().await.unwrap();
+    let gas_price = recording.get_gas_price().await.unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("cassette.json");
+    recorder.save(&path).unwrap();
+
+    let cassette = RpcCassette::load(&path).unwrap();
+    assert_eq!(cassette, recorder.cassette());
+    assert_eq!(cassette.interactions.len(), 2);
+    let replay = RpcReplayer::start(cassette).await.unwrap();
+    let replaying = provider(&replay.endpoint());
+    assert_eq!(replaying.get_chainid().await.unwrap(), chain_id);
+    assert_eq!(replaying.get_gas_price().await.unwrap(), gas_price);
+    assert!(replay.unused().is_empty());
+}
+
+#[tokio::test]
+async fn repeats_the_last_answer_once_used_up() {
+    let node = RpcReplayer::start(
+        RpcCassette::default()
+            .with_result("eth_blockNumber", Value::Null, json!("0x1"))
+            .with_result("eth_blockNumber", Value::Null, json!("0x2")),
+    )
+    .await
+    .unwrap();
+    let provider = provider(&node.endpoint());
+
+    let mut blocks = Vec::new();
+    for _ in 0..3 {
+        blocks.push(provider.get_block_number().await.unwrap().as_u64());
+    }
+
+    assert_eq!(blocks, [1, 2, 2]);
+}
+
+#[tokio::test]
+async fn answers_unrecorded_calls_with_an_error() {
+    let node = RpcReplayer::start(RpcCassette::default().with_error(
+        "eth_estimateGas",
+        json!([{ "to": "0x0000000000000000000000000000000000000001" }]),
+        -32000,
+        "execution reverted",
+    ))
+    .await
+    .unwrap();
+
+    let error = provider(&node.endpoint()).get_chainid().await.unwrap_err();
+
+    assert!(error
+        .to_string()
+        .contains("eth_chainId is not in the cassette"));
+    assert_eq!(node.misses().len(), 1);
+    assert_eq!(node.unused().len(), 1);
+}
+
+#[tokio::test]
+async fn health_checks_flag_a_node_serving_another_chain() {
+    let node = RpcReplayer::start(
+        RpcCassette::default()
+            .with_result("eth_chainId", Value::Null, json!("0x1"))
+            .with_result("eth_blockNumber", Value::Null, json!("0x10")),
+    )
+    .await
+    .unwrap();
+    let mut chain = testkit::chain(testkit::SOURCE_CHAIN_ID, "source");
+    chain.rpc_url = node.endpoint();
+    let topology = Topology::new(
+        HashMap::from([(chain.chain_id, chain)]),
+        Vec::new(),
+        ShardingConfig::default(),
+    );
+    let health = Health::new(HealthConfig::default());
+
+    let checks = tokio::spawn({
+        let health = health.clone();
+        async move { health.run_chain_checks(topology).await }
+    });
+    let report = tokio::time::timeout(Duration::from_secs(5), async {
+        loop {
+            let report = health.report();
+            if !report.chains.is_empty() {
+                return report;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    })
+    .await
+    .unwrap();
+    checks.abort();
+
+    let chain = &report.chains[0];
+    assert_eq!(chain.chain_id, ChainId::new(testkit::SOURCE_CHAIN_ID));
+    assert!(!chain.reachable);
+    assert_eq!(
+        chain.error.as_deref(),
+        Some("RPC serves chain 1, expected 11155420")
+    );
+    assert!(!report.started);
+}
@@ -0,0 +1,349 @@
+use relayer::testkit::{self, MockSolanaRpc, SolanaReply, TestPipeline};
+use relayer::{
+    DeliverySink, FailureClass, PluginConfig, PluginSpec, RelayerBuilder, RelayerError, RevertKind,
+    SolanaAccountConfig, SolanaFeeConfig, SolanaKeypair, SolanaProofBufferConfig, SolanaPubkey,
+    SolanaSink, SolanaSinkConfig,
+};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::{sync::Arc, time::Duration};
+
+const PROGRAM: SolanaPubkey = SolanaPubkey::new([9; 32]);
+const STATE: SolanaPubkey = SolanaPubkey::new([10; 32]);
+const PROVER: SolanaPubkey = SolanaPubkey::new([11; 32]);
+const BUFFER: SolanaPubkey = SolanaPubkey::new([12; 32]);
+
+fn payer() -> SolanaKeypair {
+    SolanaKeypair::from_seed([42; 32])
+}
+
+fn config(rpc: &MockSolanaRpc) -> SolanaSinkConfig {
+    SolanaSinkConfig {
+        rpc_url: rpc.url(),
+        program_id: PROGRAM,
+        accounts: vec![
+            SolanaAccountConfig {
+                pubkey: STATE,
+                writable: true,
+            },
+            SolanaAccountConfig {
+                pubkey: PROVER,
+                writable: false,
+            },
+        ],
+        confirm_timeout_ms: 5_000,
+        request_timeout_ms: 1_000,
+        ..Default::default()
+    }
+}
+
+fn sink(config: SolanaSinkConfig) -> SolanaSink {
+    SolanaSink::with_keypair(config, payer()).unwrap()
+}
+
+fn rejection(error: &anyhow::Error) -> &RelayerError {
+    error
+        .chain()
+        .find_map(|cause| cause.downcast_ref::<RelayerError>())
+        .unwrap_or_else(|| panic!("no relayer error in {:#}", error))
+}
+
+#[tokio::test]
+async fn delivers_a_signed_call_to_the_program() {
+    let rpc = MockSolanaRpc::start().await.unwrap();
+    let request = testkit::delivery_request(testkit::event(7));
+
+    let tx_hash = sink(config(&rpc)).deliver(&request).await.unwrap();
+
+    assert_eq!(tx_hash, None);
+    let sent = rpc.sent();
+    assert_eq!(sent.len(), 1);
+    let sent = &sent[0];
+    assert_eq!(sent.fee_payer, payer().pubkey());
+    assert_eq!(sent.recent_blockhash, rpc.blockhash());
+    assert_eq!(sent.compute_unit_limit, Some(400_000));
+    assert_eq!(sent.calls.len(), 1);
+    let call = &sent.calls[0];
+    assert_eq!(call.program_id, PROGRAM);
+    assert_eq!(call.accounts, config(&rpc).accounts);
+
+    // Anchor discriminator, then borsh `(u64, u64, Vec<u8>, Vec<u8>)`
+    let event = &request.event;
+    let mut data = Sha256::digest("global:execute")[..8].to_vec();
+    data.extend_from_slice(&event.source_chain.chain_id.as_u64().to_le_bytes());
+    data.extend_from_slice(&7u64.to_le_bytes());
+    data.extend_from_slice(&(event.exec_payload.len() as u32).to_le_bytes());
+    data.extend_from_slice(&event.exec_payload);
+    data.extend_from_slice(&(request.proof.data.len() as u32).to_le_bytes());
+    data.extend_from_slice(&request.proof.data);
+    assert_eq!(call.data, data);
+}
+
+#[tokio::test]
+async fn bids_a_percentile_of_recent_priority_fees() {
+    let rpc = MockSolanaRpc::start().await.unwrap();
+    rpc.priority_fees([400, 100, 300, 200, 500]);
+    let request = testkit::delivery_request(testkit::event(1));
+
+    sink(config(&rpc)).deliver(&request).await.unwrap();
+
+    assert_eq!(rpc.sent()[0].compute_unit_price, Some(400));
+}
+
+#[tokio::test]
+async fn keeps_priority_fee_bids_within_bounds() {
+    let rpc = MockSolanaRpc::start().await.unwrap();
+    let bounded = SolanaSinkConfig {
+        fees: SolanaFeeConfig {
+            min_compute_unit_price: 1_000,
+            max_compute_unit_price: 5_000,
+            ..Default::default()
+        },
+        ..config(&rpc)
+    };
+    let sink = sink(bounded);
+
+    sink.deliver(&testkit::delivery_request(testkit::event(1)))
+        .await
+        .unwrap();
+    rpc.priority_fees([50_000]);
+    sink.deliver(&testkit::delivery_request(testkit::event(2)))
+        .await
+        .unwrap();
+
+    let prices: Vec<_> = rpc
+        .sent()
+        .iter()
+        .map(|sent| sent.compute_unit_price)
+        .collect();
+    assert_eq!(prices, [Some(1_000), Some(5_000)]);
+}
+
+#[tokio::test]
+async fn classifies_failed_simulations_by_program_logs() {
+    let rpc = MockSolanaRpc::start().await.unwrap();
+    rpc.script([
+        SolanaReply::program_error(&["Program log: Error: nonce already executed"]),
+        SolanaReply::program_error(&["Program log: Error: proof verification failed"]),
+        SolanaReply::program_error(&["Program log: Error: unknown recipient"]),
+    ]);
+    let sink = sink(config(&rpc));
+    let request = testkit::delivery_request(testkit::event(3));
+
+    let already = sink.deliver(&request).await.unwrap_err();
+    let invalid_proof = sink.deliver(&request).await.unwrap_err();
+    let rejected = sink.deliver(&request).await.unwrap_err();
+
+    assert!(rejection(&already).is_already_delivered());
+    let RelayerError::DeliverySimulationFailed { kind, .. } = rejection(&invalid_proof) else {
+        panic!("expected a failed simulation, got {:#}", invalid_proof);
+    };
+    assert_eq!(*kind, RevertKind::InvalidProof);
+    assert_eq!(FailureClass::of(&invalid_proof), FailureClass::Fatal);
+    let RelayerError::DeliverySimulationFailed { reason, .. } = rejection(&rejected) else {
+        panic!("expected a failed simulation, got {:#}", rejected);
+    };
+    assert!(reason.contains("unknown recipient"), "{}", reason);
+    assert_eq!(FailureClass::of(&rejected), FailureClass::Fatal);
+    assert!(rpc.sent().is_empty());
+}
+
+#[tokio::test]
+async fn waits_out_a_payer_too_poor_for_the_fee() {
+    let rpc = MockSolanaRpc::start().await.unwrap();
+    rpc.script([SolanaReply::transaction_error("InsufficientFundsForFee")]);
+
+    let error = sink(config(&rpc))
+        .deliver(&testkit::delivery_request(testkit::event(1)))
+        .await
+        .unwrap_err();
+
+    assert!(matches!(
+        rejection(&error),
+        RelayerError::InsufficientBalance { .. }
+    ));
+    assert_eq!(FailureClass::of(&error), FailureClass::Retryable);
+}
+
+#[tokio::test]
+async fn gives_up_on_deliveries_failing_on_chain() {
+    let rpc = MockSolanaRpc::start().await.unwrap();
+    rpc.script([SolanaReply::FailOnChain(
+        json!({ "InstructionError": [2, "InvalidAccountData"] }),
+    )]);
+
+    let error = sink(config(&rpc))
+        .deliver(&testkit::delivery_request(testkit::event(1)))
+        .await
+        .unwrap_err();
+
+    assert!(matches!(
+        rejection(&error),
+        RelayerError::TransactionFailed { .. }
+    ));
+    assert_eq!(FailureClass::of(&error), FailureClass::Fatal);
+}
+
+#[tokio::test]
+async fn retries_deliveries_whose_blockhash_expired() {
+    let rpc = MockSolanaRpc::start().await.unwrap();
+    rpc.script([SolanaReply::Drop]);
+    let sink = sink(config(&rpc));
+    let request = testkit::delivery_request(testkit::event(1));
+
+    let error = sink.deliver(&request).await.unwrap_err();
+
+    assert!(error.to_string().contains("expired"), "{:#}", error);
+    assert_ne!(FailureClass::of(&error), FailureClass::Fatal);
+    assert_eq!(rpc.sent().len(), 1);
+}
+
+#[tokio::test]
+async fn refuses_transactions_over_the_size_limit() {
+    let rpc = MockSolanaRpc::start().await.unwrap();
+    let event = testkit::EventBuilder::new(&testkit::relay_pair())
+        .payload(vec![0xab; 1_024])
+        .build();
+
+    let error = sink(config(&rpc))
+        .deliver(&testkit::delivery_request(event))
+        .await
+        .unwrap_err();
+
+    assert!(matches!(rejection(&error), RelayerError::InvalidPayload(_)));
+    assert_eq!(rpc.call_count("sendTransaction"), 0);
+}
+
+#[tokio::test]
+async fn stages_proofs_too_large_to_send_inline_in_the_buffer() {
+    let rpc = MockSolanaRpc::start().await.unwrap();
+    let buffered = SolanaSinkConfig {
+        proof_buffer: Some(SolanaProofBufferConfig {
+            account: BUFFER,
+            ..Default::default()
+        }),
+        ..config(&rpc)
+    };
+    let mut request = testkit::delivery_request(testkit::event(4));
+    request.proof.data = (0..3_000).map(|i| i as u8).collect::<Vec<u8>>().into();
+
+    sink(buffered).deliver(&request).await.unwrap();
+
+    let sent = rpc.sent();
+    assert!(sent.len() > 3, "{} transactions sent", sent.len());
+    assert!(sent.iter().all(|sent| sent.size <= 1232));
+    let write = Sha256::digest("global:write_proof")[..8].to_vec();
+    let buffer_account = SolanaAccountConfig {
+        pubkey: BUFFER,
+        writable: true,
+    };
+    let (delivery, writes) = sent.split_last().unwrap();
+    let mut staged = Vec::new();
+    for sent in writes {
+        let call = &sent.calls[0];
+        assert_eq!(call.accounts, [buffer_account]);
+        let (discriminator, args) = call.data.split_at(8);
+        assert_eq!(discriminator, write);
+        assert_eq!(args[..4], 3_000u32.to_le_bytes());
+        assert_eq!(args[4..8], (staged.len() as u32).to_le_bytes());
+        staged.extend_from_slice(&args[12..]);
+    }
+    assert_eq!(staged, request.proof.data.to_vec());
+
+    // The delivery itself reads the proof from the buffer, passed last
+    let call = &delivery.calls[0];
+    assert_eq!(call.accounts.last(), Some(&buffer_account));
+    assert_eq!(call.data[call.data.len() - 4..], 0u32.to_le_bytes());
+}
+
+#[tokio::test]
+async fn delivers_pipeline_events_to_solana() {
+    let rpc = MockSolanaRpc::start().await.unwrap();
+    let state = tempfile::tempdir().unwrap();
+    let relayer_config = testkit::config(state.path()).build().unwrap();
+    let sink = Arc::new(sink(config(&rpc)));
+    let mut pipeline =
+        TestPipeline::start_with(relayer_config, |builder| builder.delivery_sink(sink))
+            .await
+            .unwrap();
+    let event = testkit::event(1);
+
+    pipeline.emit(event.clone()).await.unwrap();
+    pipeline
+        .expect_delivered(&event, Duration::from_secs(10))
+        .await
+        .unwrap();
+
+    assert_eq!(rpc.sent().len(), 1);
+    assert!(pipeline.sink().requests().is_empty());
+    pipeline.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn builds_the_solana_plugin_from_its_options() {
+    let rpc = MockSolanaRpc::start().await.unwrap();
+    let state = tempfile::tempdir().unwrap();
+    let keypair_path = state.path().join("payer.json");
+    std::fs::write(
+        &keypair_path,
+        json!(payer().to_bytes().to_vec()).to_string(),
+    )
+    .unwrap();
+    let plugins = |options: serde_json::Value| PluginConfig {
+        delivery_sink: Some(PluginSpec {
+            name: "solana".to_string(),
+            options,
+        }),
+        ..Default::default()
+    };
+    let build = |options| {
+        let config = testkit::config(state.path())
+            .plugins(plugins(options))
+            .build()
+            .unwrap();
+        RelayerBuilder::new(config).without_chain_events().build()
+    };
+
+    let options = json!({
+        "rpc_url": rpc.url(),
+        "program_id": PROGRAM.to_string(),
+        "keypair_path": keypair_path,
+        "accounts": [{ "pubkey": STATE.to_string(), "writable": true }],
+    });
+    build(options).unwrap();
+
+    let error = build(json!({ "keypair_path": keypair_path }))
+        .err()
+        .unwrap();
+    assert!(matches!(rejection(&error), RelayerError::InvalidConfig(_)));
+    assert!(build(json!({ "program_id": "not base58!" })).is_err());
+}
+
+#[test]
+fn reads_keypair_files_and_base58_keys() {
+    let state = tempfile::tempdir().unwrap();
+    let path = state.path().join("payer.json");
+    std::fs::write(&path, json!(payer().to_bytes().to_vec()).to_string()).unwrap();
+
+    assert_eq!(
+        SolanaKeypair::read(&path).unwrap().pubkey(),
+        payer().pubkey()
+    );
+
+    let mut mismatched = payer().to_bytes();
+    mismatched[63] ^= 1;
+    assert!(SolanaKeypair::from_bytes(&mismatched).is_err());
+
+    let program: SolanaPubkey = "ComputeBudget111111111111111111111111111111"
+        .parse()
+        .unwrap();
+    assert_eq!(
+        program.to_string(),
+        "ComputeBudget111111111111111111111111111111"
+    );
+    assert_eq!(
+        serde_json::to_value(program).unwrap(),
+        json!("ComputeBudget111111111111111111111111111111")
+    );
+    assert!("11111".parse::<SolanaPubkey>().is_err());
+}
@@ -0,0 +1,140 @@
+use relayer::testkit::{self, EventBuilder, MockPolymerApi, TestPipeline};
+use relayer::{
+    DeliveryStatus, EventStatus, PolymerApiConfig, PolymerProofProvider, ProofBackendConfig,
+    ProofProvider, RelayerConfig,
+};
+use std::{sync::Arc, time::Duration};
+
+const TIMEOUT: Duration = Duration::from_secs(20);
+
+fn config(state: &tempfile::TempDir) -> RelayerConfig {
+    testkit::config(state.path()).build().unwrap()
+}
+
+#[tokio::test]
+async fn delivers_emitted_events() {
+    let state = tempfile::tempdir().unwrap();
+    let mut pipeline = TestPipeline::start(config(&state)).await.unwrap();
+    let first = testkit::event(1);
+    let second = testkit::event(2);
+
+    pipeline.emit(first.clone()).await.unwrap();
+    pipeline.emit(second.clone()).await.unwrap();
+
+    let outcome = pipeline.expect_delivered(&second, TIMEOUT).await.unwrap();
+    assert_eq!(outcome.nonce, 2);
+    assert_eq!(outcome.attempt, 1);
+    pipeline.expect_delivered(&first, TIMEOUT).await.unwrap();
+    let mut nonces = pipeline.sink().nonces();
+    nonces.sort();
+    assert_eq!(nonces, [1, 2]);
+    pipeline
+        .expect_status(&first, EventStatus::Delivered, TIMEOUT)
+        .await
+        .unwrap();
+    pipeline.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn retries_a_failed_delivery() {
+    let state = tempfile::tempdir().unwrap();
+    let mut pipeline = TestPipeline::start(config(&state)).await.unwrap();
+    pipeline.sink().fail_next(1);
+    let event = testkit::event(7);
+
+    pipeline.emit(event.clone()).await.unwrap();
+
+    let outcome = pipeline.expect_delivered(&event, TIMEOUT).await.unwrap();
+    assert_eq!(outcome.attempt, 2);
+    assert_eq!(pipeline.sink().wait_for(1, TIMEOUT).await.unwrap().len(), 1);
+    pipeline.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn dead_letters_a_rejected_delivery() {
+    let state = tempfile::tempdir().unwrap();
+    let mut pipeline = TestPipeline::start(config(&state)).await.unwrap();
+    pipeline.sink().reject_next(1);
+    let event = testkit::event(3);
+
+    pipeline.emit(event.clone()).await.unwrap();
+
+    let outcome = pipeline
+        .expect_dead_lettered(&event, TIMEOUT)
+        .await
+        .unwrap();
+    assert_eq!(outcome.status, DeliveryStatus::DeadLettered);
+    assert!(pipeline
+        .expect_delivered(&event, Duration::ZERO)
+        .await
+        .is_err());
+    let record = pipeline.store().event_record(&event.id()).unwrap().unwrap();
+    assert_eq!(record.status.name(), "failed");
+    assert!(pipeline.sink().requests().is_empty());
+    pipeline.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn proves_through_the_mock_polymer_api() {
+    let api = MockPolymerApi::start().await.unwrap();
+    let state = tempfile::tempdir().unwrap();
+    let config = testkit::config(state.path())
+        .proof_backend(ProofBackendConfig::Polymer(PolymerApiConfig {
+            endpoint: api.endpoint(),
+            token: "test-token".to_string(),
+            ..Default::default()
+        }))
+        .build()
+        .unwrap();
+    let mut pipeline = TestPipeline::start(config).await.unwrap();
+    let event = EventBuilder::new(&testkit::relay_pair())
+        .nonce(4)
+        .log(2_000, 3, 1)
+        .build();
+
+    pipeline.emit(event.clone()).await.unwrap();
+
+    pipeline.expect_delivered(&event, TIMEOUT).await.unwrap();
+    assert_eq!(api.call_count("log_requestProof"), 1);
+    pipeline.shutdown().await.unwrap();
+}
+
+#[test]
+fn built_events_have_distinct_ids() {
+    let pair = testkit::relay_pair();
+    let event = EventBuilder::new(&pair).nonce(1).build();
+
+    assert!(pair.matches(&event));
+    assert_ne!(event.id(), testkit::event(2).id());
+    assert_ne!(
+        event.id(),
+        EventBuilder::new(&pair)
+            .nonce(1)
+            .log(100, 0, 1)
+            .build()
+            .id()
+    );
+    assert_eq!(event.id(), testkit::event(1).id());
+}
+
+#[tokio::test]
+async fn delivery_requests_carry_a_valid_proof() {
+    let state = tempfile::tempdir().unwrap();
+    let store = Arc::new(relayer::FileStateStore::open(state.path()).unwrap());
+    let provider = PolymerProofProvider::new(PolymerApiConfig::default(), store).unwrap();
+    let event = EventBuilder::new(&testkit::relay_pair())
+        .log(42, 6, 2)
+        .build();
+
+    let request = testkit::delivery_request(event.clone());
+
+    let log = provider.inspect(&request.proof).unwrap().unwrap();
+    assert_eq!(log.chain_id, event.meta.chain_id);
+    assert_eq!(log.block_number, 42);
+    assert_eq!(log.receipt_index, 6);
+    assert_eq!(log.log_index, 2);
+    assert_eq!(
+        request.destination_chain_id,
+        event.destination_chain.chain_id
+    );
+}
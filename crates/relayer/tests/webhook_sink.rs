@@ -0,0 +1,158 @@
+use axum::http::StatusCode;
+use ethers::core::types::H256;
+use relayer::testkit::{self, MockWebhook, TestPipeline};
+use relayer::{
+    DeliveryRequest, DeliverySink, FailureClass, PluginConfig, PluginSpec, RelayerBuilder,
+    RelayerError, WebhookSink, WebhookSinkConfig,
+};
+use serde_json::json;
+use std::{sync::Arc, time::Duration};
+
+const SECRET: &str = "webhook-secret";
+
+fn config(webhook: &MockWebhook) -> WebhookSinkConfig {
+    WebhookSinkConfig {
+        url: webhook.url(),
+        secret: SECRET.to_string(),
+        initial_backoff_ms: 10,
+        request_timeout_ms: 1_000,
+        allow_http: true,
+        ..Default::default()
+    }
+}
+
+fn sink(webhook: &MockWebhook) -> WebhookSink {
+    WebhookSink::new(config(webhook)).unwrap()
+}
+
+fn rejection(error: &anyhow::Error) -> &RelayerError {
+    error
+        .chain()
+        .find_map(|cause| cause.downcast_ref::<RelayerError>())
+        .unwrap_or_else(|| panic!("no relayer error in {:#}", error))
+}
+
+#[tokio::test]
+async fn posts_signed_delivery_requests() {
+    let webhook = MockWebhook::start().await.unwrap();
+    webhook.tx_hash(H256::from_low_u64_be(0x7e));
+    let request = testkit::delivery_request(testkit::event(7));
+
+    let tx_hash = sink(&webhook).deliver(&request).await.unwrap();
+
+    assert_eq!(tx_hash, Some(H256::from_low_u64_be(0x7e)));
+    let posts = webhook.posts();
+    assert_eq!(posts.len(), 1);
+    let post = &posts[0];
+    let timestamp: u64 = post.header("x-relayer-timestamp").unwrap().parse().unwrap();
+    assert_eq!(
+        post.header("x-relayer-signature").unwrap(),
+        WebhookSink::signature(SECRET, timestamp, &post.body)
+    );
+    assert_ne!(
+        post.header("x-relayer-signature").unwrap(),
+        WebhookSink::signature("another secret", timestamp, &post.body)
+    );
+    assert_eq!(
+        post.header("x-relayer-event-id").unwrap(),
+        request.event.id().to_string()
+    );
+    let posted: DeliveryRequest = serde_json::from_slice(&post.body).unwrap();
+    assert_eq!(posted.event.id(), request.event.id());
+    assert_eq!(posted.proof.data, request.proof.data);
+}
+
+#[tokio::test]
+async fn retries_transient_failures_until_out_of_attempts() {
+    let webhook = MockWebhook::start().await.unwrap();
+    let sink = sink(&webhook);
+    let request = testkit::delivery_request(testkit::event(1));
+
+    webhook.script([
+        StatusCode::SERVICE_UNAVAILABLE,
+        StatusCode::TOO_MANY_REQUESTS,
+    ]);
+    assert_eq!(sink.deliver(&request).await.unwrap(), None);
+    assert_eq!(webhook.posts().len(), 3);
+
+    webhook.script([StatusCode::BAD_GATEWAY; 3]);
+    let error = sink.deliver(&request).await.unwrap_err();
+    assert!(matches!(
+        rejection(&error),
+        RelayerError::WebhookUnavailable { attempts: 3, .. }
+    ));
+    assert_eq!(FailureClass::of(&error), FailureClass::Retryable);
+    assert_eq!(webhook.posts().len(), 6);
+}
+
+#[tokio::test]
+async fn stops_at_answers_retrying_cannot_change() {
+    let webhook = MockWebhook::start().await.unwrap();
+    let sink = sink(&webhook);
+    let request = testkit::delivery_request(testkit::event(1));
+
+    webhook.script([StatusCode::UNPROCESSABLE_ENTITY, StatusCode::CONFLICT]);
+    let rejected = sink.deliver(&request).await.unwrap_err();
+    let delivered = sink.deliver(&request).await.unwrap_err();
+
+    assert!(matches!(
+        rejection(&rejected),
+        RelayerError::WebhookRejected { status: 422, .. }
+    ));
+    assert_eq!(FailureClass::of(&rejected), FailureClass::Fatal);
+    assert!(rejection(&delivered).is_already_delivered());
+    assert_eq!(webhook.posts().len(), 2);
+}
+
+#[tokio::test]
+async fn delivers_pipeline_events_to_the_webhook() {
+    let webhook = MockWebhook::start().await.unwrap();
+    let state = tempfile::tempdir().unwrap();
+    let relayer_config = testkit::config(state.path()).build().unwrap();
+    let sink = Arc::new(sink(&webhook));
+    let mut pipeline =
+        TestPipeline::start_with(relayer_config, |builder| builder.delivery_sink(sink))
+            .await
+            .unwrap();
+    let event = testkit::event(1);
+
+    pipeline.emit(event.clone()).await.unwrap();
+    pipeline
+        .expect_delivered(&event, Duration::from_secs(10))
+        .await
+        .unwrap();
+
+    assert_eq!(webhook.posts().len(), 1);
+    assert!(pipeline.sink().requests().is_empty());
+    pipeline.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn builds_the_webhook_plugin_from_its_options() {
+    let webhook = MockWebhook::start().await.unwrap();
+    let state = tempfile::tempdir().unwrap();
+    let build = |options| {
+        let plugins = PluginConfig {
+            delivery_sink: Some(PluginSpec {
+                name: "webhook".to_string(),
+                options,
+            }),
+            ..Default::default()
+        };
+        let config = testkit::config(state.path())
+            .plugins(plugins)
+            .build()
+            .unwrap();
+        RelayerBuilder::new(config).without_chain_events().build()
+    };
+
+    build(json!({ "url": webhook.url(), "secret": SECRET, "allow_http": true })).unwrap();
+
+    for options in [
+        json!({ "url": webhook.url(), "secret": SECRET }),
+        json!({ "url": "https://dapp.example/relay" }),
+    ] {
+        let error = build(options).err().unwrap();
+        assert!(matches!(rejection(&error), RelayerError::InvalidConfig(_)));
+    }
+}